@@ -0,0 +1,13 @@
+//! Decode arbitrary frames received from an external model.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use rvfs_sim_core::element::external::{decode_hello, decode_outputs};
+
+fuzz_target!(|data: &[u8]| {
+    let _ = decode_hello(data);
+    if let Some((outputs, payload)) = data.split_first() {
+        let _ = decode_outputs(payload, usize::from(*outputs));
+    }
+});
@@ -0,0 +1,12 @@
+//! Parse arbitrary text as an I2C transaction.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use rvfs_sim_core::element::i2c::I2cTransaction;
+
+fuzz_target!(|text: &str| {
+    if let Ok(transaction) = text.parse::<I2cTransaction>() {
+        assert!(transaction.address < 0x80);
+    }
+});
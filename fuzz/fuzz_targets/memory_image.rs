@@ -0,0 +1,19 @@
+//! Parse arbitrary data as a memory image, in each format and with word widths including invalid ones.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use rvfs_sim_core::element::memory::{parse_image, ImageFormat};
+
+fuzz_target!(|input: (u8, u8, u16, &[u8])| {
+    let (format, width, depth, data) = input;
+    let format = [
+        ImageFormat::Binary,
+        ImageFormat::Hex,
+        ImageFormat::IntelHex,
+        ImageFormat::Elf,
+    ][usize::from(format % 4)];
+    if let Ok(contents) = parse_image(data, format, usize::from(width % 66), usize::from(depth)) {
+        assert_eq!(usize::from(depth), contents.len());
+    }
+});
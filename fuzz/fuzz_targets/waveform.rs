@@ -0,0 +1,17 @@
+//! Load an arbitrary WaveJSON description into a waveform generator, and step it.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use rvfs_sim_core::element::waveforms::WaveformGenerator;
+use rvfs_sim_core::element::Element;
+
+fuzz_target!(|input: (u8, &str)| {
+    let (tick, description) = input;
+    if let Ok(mut generator) = WaveformGenerator::new("FUZZ", description, u64::from(tick)) {
+        let mut outputs = generator.output_pins();
+        for _ in 0..64 {
+            generator.step(&[], &mut outputs, 7).unwrap();
+        }
+    }
+});
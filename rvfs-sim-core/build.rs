@@ -0,0 +1,50 @@
+//! Build script generating the gRPC service stubs when the `grpc` feature is enabled.
+//!
+//! The service is described here in Rust rather than compiled from `proto/rvfs_sim.proto`, so that building does not
+//! need `protoc`.  The two descriptions must be kept in step.
+
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+
+    #[cfg(feature = "grpc")]
+    grpc();
+}
+
+/// Generate the stubs of the `rvfs_sim.Simulator` service.
+#[cfg(feature = "grpc")]
+fn grpc() {
+    use tonic_build::manual::{Builder, Method, Service};
+
+    // Methods as (Rust name, route name, request type, reply type, whether the reply is streamed).
+    let methods = [
+        ("load", "Load", "LoadRequest", "LoadReply", false),
+        ("step", "Step", "StepRequest", "StepReply", false),
+        ("read", "Read", "ReadRequest", "ReadReply", false),
+        ("force", "Force", "ForceRequest", "ForceReply", false),
+        (
+            "subscribe",
+            "Subscribe",
+            "SubscribeRequest",
+            "ChangeEvent",
+            true,
+        ),
+    ];
+    let service = methods.into_iter().fold(
+        Service::builder().name("Simulator").package("rvfs_sim"),
+        |service, (name, route, input, output, streaming)| {
+            let method = Method::builder()
+                .name(name)
+                .route_name(route)
+                .input_type(format!("super::{input}"))
+                .output_type(format!("super::{output}"))
+                .codec_path("tonic::codec::ProstCodec");
+            service.method(if streaming {
+                method.server_streaming().build()
+            } else {
+                method.build()
+            })
+        },
+    );
+
+    Builder::new().compile(&[service.build()]);
+}
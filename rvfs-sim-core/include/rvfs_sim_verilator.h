@@ -0,0 +1,78 @@
+// Exports a Verilated model through the C interface loaded by the rvfs-sim `verilator` element.
+//
+// Compile the Verilated model and a source file like the following into a shared library:
+//
+//     #include "Vcounter.h"
+//     #include "rvfs_sim_verilator.h"
+//
+//     RVFS_SIM_EXPORT_MODEL(Vcounter,
+//         RVFS_SIM_INPUT(Vcounter, clk, 1),
+//         RVFS_SIM_INPUT(Vcounter, rst, 1),
+//         RVFS_SIM_OUTPUT(Vcounter, count, 8))
+//
+// Each top-level port of up to 64 bits which is to be connected is listed with its width.  Ports are evaluated in
+// lockstep with the simulation, whose time is passed to the model's context before each evaluation.
+
+#ifndef RVFS_SIM_VERILATOR_H
+#define RVFS_SIM_VERILATOR_H
+
+#include <cstddef>
+#include <cstdint>
+
+// Version of the C interface, checked by the simulator on loading.
+#define RVFS_SIM_MODEL_ABI_VERSION 1
+
+namespace rvfs_sim {
+
+// A top-level port of a model M.
+template <class M>
+struct Port {
+    // Name of the port.
+    const char *name;
+    // Whether the port is an output of the model, rather than an input.
+    int output;
+    // Width of the port in bits, from 1 to 64.
+    unsigned width;
+    // Sets an input port.
+    void (*set)(M *, uint64_t);
+    // Gets an output port.
+    uint64_t (*get)(M *);
+};
+
+}  // namespace rvfs_sim
+
+// Describe an input port of model M.
+#define RVFS_SIM_INPUT(M, port, width) \
+    rvfs_sim::Port<M> { #port, 0, width, [](M *model, uint64_t value) { model->port = value; }, nullptr }
+
+// Describe an output port of model M.
+#define RVFS_SIM_OUTPUT(M, port, width) \
+    rvfs_sim::Port<M> { #port, 1, width, nullptr, [](M *model) -> uint64_t { return model->port; } }
+
+// Export model M, with the ports described by RVFS_SIM_INPUT and RVFS_SIM_OUTPUT.
+#define RVFS_SIM_EXPORT_MODEL(M, ...)                                                              \
+    static const rvfs_sim::Port<M> rvfs_sim_ports[] = {__VA_ARGS__};                               \
+    extern "C" {                                                                                   \
+    uint32_t rvfs_sim_model_abi_version(void) { return RVFS_SIM_MODEL_ABI_VERSION; }               \
+    void *rvfs_sim_model_new(void) { return new M; }                                               \
+    void rvfs_sim_model_delete(void *model) {                                                      \
+        static_cast<M *>(model)->final();                                                          \
+        delete static_cast<M *>(model);                                                            \
+    }                                                                                              \
+    size_t rvfs_sim_model_port_count(void) { return sizeof(rvfs_sim_ports) / sizeof(*rvfs_sim_ports); } \
+    const char *rvfs_sim_model_port_name(size_t port) { return rvfs_sim_ports[port].name; }        \
+    int rvfs_sim_model_port_output(size_t port) { return rvfs_sim_ports[port].output; }            \
+    unsigned rvfs_sim_model_port_width(size_t port) { return rvfs_sim_ports[port].width; }         \
+    void rvfs_sim_model_set_input(void *model, size_t port, uint64_t value) {                      \
+        rvfs_sim_ports[port].set(static_cast<M *>(model), value);                                  \
+    }                                                                                              \
+    uint64_t rvfs_sim_model_get_output(void *model, size_t port) {                                 \
+        return rvfs_sim_ports[port].get(static_cast<M *>(model));                                  \
+    }                                                                                              \
+    void rvfs_sim_model_eval(void *model, uint64_t time) {                                         \
+        static_cast<M *>(model)->contextp()->time(time);                                           \
+        static_cast<M *>(model)->eval();                                                           \
+    }                                                                                              \
+    }
+
+#endif  // RVFS_SIM_VERILATOR_H
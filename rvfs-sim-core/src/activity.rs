@@ -0,0 +1,340 @@
+//! Reports ranking Wires by their switching activity, to locate hot or glitch-prone nets.
+
+use crate::json;
+use crate::sim::Simulation;
+use crate::trace::{Change, Logic, Tracer, DEFAULT_HIGH_THRESHOLD, DEFAULT_LOW_THRESHOLD};
+use crate::wirevalue::WireValue;
+use crate::WireId;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+/// Activity of a single net.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NetActivity {
+    /// Name of the Wire.
+    pub name: String,
+    /// Number of logic transitions made by the Wire.
+    pub transitions: u64,
+    /// Simulation time the Wire spent between the logic thresholds.
+    pub mid_threshold_time: u64,
+}
+
+/// Activity of every net over a Simulation run.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ActivityReport {
+    /// Simulation time covered by the report.
+    pub duration: u64,
+    /// Activity of each net, in Id order.
+    pub nets: Vec<NetActivity>,
+}
+
+impl ActivityReport {
+    /// Obtain the nets ranked by transition count, busiest first.
+    pub fn by_transitions(&self) -> Vec<&NetActivity> {
+        let mut nets: Vec<&NetActivity> = self.nets.iter().collect();
+        nets.sort_by(|a, b| {
+            b.transitions
+                .cmp(&a.transitions)
+                .then_with(|| a.name.cmp(&b.name))
+        });
+        nets
+    }
+
+    /// Obtain the nets ranked by time spent between the logic thresholds, longest first.
+    pub fn by_mid_threshold_time(&self) -> Vec<&NetActivity> {
+        let mut nets: Vec<&NetActivity> = self.nets.iter().collect();
+        nets.sort_by(|a, b| {
+            b.mid_threshold_time
+                .cmp(&a.mid_threshold_time)
+                .then_with(|| a.name.cmp(&b.name))
+        });
+        nets
+    }
+
+    /// Render the report as a JSON object, with both rankings.
+    pub fn to_json(&self) -> String {
+        let entries = |nets: Vec<&NetActivity>| -> String {
+            nets.iter()
+                .map(|net| {
+                    format!(
+                        "{{\"name\":{},\"transitions\":{},\"mid_threshold_time\":{}}}",
+                        json::string(&net.name),
+                        net.transitions,
+                        net.mid_threshold_time
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join(",")
+        };
+        format!(
+            "{{\"duration\":{},\"by_transitions\":[{}],\"by_mid_threshold_time\":[{}]}}",
+            self.duration,
+            entries(self.by_transitions()),
+            entries(self.by_mid_threshold_time())
+        )
+    }
+}
+
+impl fmt::Display for ActivityReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Activity over {} time units", self.duration)?;
+        writeln!(f, "By transitions:")?;
+        for net in self.by_transitions() {
+            writeln!(f, "  {:<32} {:>10}", net.name, net.transitions)?;
+        }
+        writeln!(f, "By time mid-threshold:")?;
+        for net in self.by_mid_threshold_time() {
+            writeln!(f, "  {:<32} {:>10}", net.name, net.mid_threshold_time)?;
+        }
+        Ok(())
+    }
+}
+
+/// Activity accumulated for a single Wire.
+#[derive(Debug, Clone)]
+struct Activity {
+    /// Name of the Wire.
+    name: String,
+    /// Present logic level of the Wire.
+    logic: Logic,
+    /// Last definite logic level of the Wire, if it has had one.
+    definite: Option<Logic>,
+    /// Number of logic transitions made by the Wire.
+    transitions: u64,
+    /// Simulation time the Wire has spent between the logic thresholds.
+    mid_threshold_time: u64,
+}
+
+/// State shared between all clones of an ActivityMonitor.
+#[derive(Debug, Default)]
+struct State {
+    /// Activity of each Wire, keyed by Wire Id.
+    activity: BTreeMap<WireId, Activity>,
+    /// Simulation time at which monitoring started.
+    start_time: u64,
+    /// Most recent simulation time seen.
+    time: u64,
+}
+
+/// A Tracer which counts the logic transitions of every Wire, and the time each spends between the logic thresholds.
+///
+/// A transition is counted when a Wire reaches a definite logic level different from its last one, so a Wire which
+/// wanders into the indeterminate band and back is not counted as transitioning, but is charged for the time it spent
+/// there.
+///
+/// Clones of an ActivityMonitor share their state, so a clone can be kept to obtain the report after the original has
+/// been attached to a Simulation.
+///
+/// # Example
+///
+/// ```
+/// # use rvfs_sim_core::activity::ActivityMonitor;
+/// # use rvfs_sim_core::sim::Simulation;
+/// # use rvfs_sim_core::time::SimDuration;
+/// # use rvfs_sim_core::wire::{Wire, WirePull};
+/// let mut sim = Simulation::new(SimDuration::from_ticks(10));
+/// sim.add_wire(Wire::new("/RESET", WirePull::Up)).unwrap();
+/// let activity = ActivityMonitor::new();
+/// sim.add_tracer(Box::new(activity.clone()));
+///
+/// sim.step().unwrap();
+/// println!("{}", activity.report());
+/// ```
+#[derive(Debug, Clone)]
+pub struct ActivityMonitor {
+    /// Shared monitoring state.
+    state: Arc<Mutex<State>>,
+    /// Wire level at or below which a Wire is considered logic low.
+    low_threshold: f32,
+    /// Wire level at or above which a Wire is considered logic high.
+    high_threshold: f32,
+}
+
+impl ActivityMonitor {
+    /// Create a new ActivityMonitor.
+    pub fn new() -> Self {
+        Self {
+            state: Arc::new(Mutex::new(State::default())),
+            low_threshold: DEFAULT_LOW_THRESHOLD,
+            high_threshold: DEFAULT_HIGH_THRESHOLD,
+        }
+    }
+
+    /// Change the thresholds used to derive logic levels from Wire levels.
+    ///
+    /// # Parameters
+    ///
+    /// - `low`: Level at or below which a Wire is considered logic low.
+    /// - `high`: Level at or above which a Wire is considered logic high.
+    pub fn with_thresholds(mut self, low: f32, high: f32) -> Self {
+        self.low_threshold = low;
+        self.high_threshold = high;
+        self
+    }
+
+    /// Produce a report of the activity so far.
+    pub fn report(&self) -> ActivityReport {
+        let state = self.lock();
+        ActivityReport {
+            duration: state.time - state.start_time,
+            nets: state
+                .activity
+                .values()
+                .map(|activity| NetActivity {
+                    name: activity.name.clone(),
+                    transitions: activity.transitions,
+                    mid_threshold_time: activity.mid_threshold_time,
+                })
+                .collect(),
+        }
+    }
+
+    /// Lock the shared state.
+    fn lock(&self) -> std::sync::MutexGuard<'_, State> {
+        self.state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Convert a Wire level into a logic level.
+    fn logic(&self, value: WireValue) -> Logic {
+        Logic::from_level(value, self.low_threshold, self.high_threshold)
+    }
+}
+
+impl Default for ActivityMonitor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Tracer for ActivityMonitor {
+    fn start(&mut self, sim: &Simulation) -> Result<(), String> {
+        let mut activity = BTreeMap::new();
+        for id in sim.wires() {
+            let wire = sim.wire(id)?;
+            let logic = self.logic(wire.measure());
+            activity.insert(
+                id,
+                Activity {
+                    name: wire.name().clone(),
+                    logic,
+                    definite: (logic != Logic::Unknown).then_some(logic),
+                    transitions: 0,
+                    mid_threshold_time: 0,
+                },
+            );
+        }
+
+        let mut state = self.lock();
+        state.activity = activity;
+        state.start_time = sim.time();
+        state.time = sim.time();
+        Ok(())
+    }
+
+    fn record(&mut self, sim: &Simulation, changes: &[Change]) -> Result<(), String> {
+        let mut state = self.lock();
+        let elapsed = sim.time() - state.time;
+        state.time = sim.time();
+
+        // Each Wire is taken to have held its level from the previous step until this one.
+        for activity in state.activity.values_mut() {
+            if activity.logic == Logic::Unknown {
+                activity.mid_threshold_time += elapsed;
+            }
+        }
+
+        for change in changes {
+            let logic = self.logic(change.value);
+            if let Some(activity) = state.activity.get_mut(&change.id) {
+                activity.logic = logic;
+                if logic != Logic::Unknown {
+                    if activity.definite.is_some_and(|previous| previous != logic) {
+                        activity.transitions += 1;
+                    }
+                    activity.definite = Some(logic);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self, _sim: &Simulation) -> Result<(), String> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::time::SimDuration;
+    use crate::wire::{Wire, WirePull};
+
+    /// Build a change to a Wire's level.
+    fn change(id: WireId, previous: f32, value: f32) -> Change {
+        Change {
+            id,
+            previous: WireValue::new(previous),
+            value: WireValue::new(value),
+        }
+    }
+
+    #[test]
+    fn activity_report() {
+        // GIVEN a started ActivityMonitor for a clock and a glitchy data line
+        let mut sim = Simulation::new(SimDuration::from_ticks(10));
+        let clk = sim.add_wire(Wire::new("CLK", WirePull::Down)).unwrap();
+        let data = sim.add_wire(Wire::new("DATA", WirePull::Down)).unwrap();
+        let mut activity = ActivityMonitor::new();
+        activity.start(&sim).unwrap();
+        // WHEN the clock toggles twice, and the data line spends two steps mid-threshold without transitioning
+        sim.step().unwrap();
+        activity
+            .record(&sim, &[change(clk, 0.0, 1.0), change(data, 0.0, 0.5)])
+            .unwrap();
+        sim.step().unwrap();
+        activity.record(&sim, &[change(clk, 1.0, 0.0)]).unwrap();
+        sim.step().unwrap();
+        activity.record(&sim, &[change(data, 0.5, 0.0)]).unwrap();
+        // THEN the rankings reflect each kind of activity
+        let report = activity.report();
+        assert_eq!(30, report.duration);
+        assert_eq!("CLK", report.by_transitions()[0].name);
+        assert_eq!(2, report.by_transitions()[0].transitions);
+        assert_eq!("DATA", report.by_mid_threshold_time()[0].name);
+        assert_eq!(0, report.by_mid_threshold_time()[0].transitions);
+        assert_eq!(20, report.by_mid_threshold_time()[0].mid_threshold_time);
+    }
+    #[test]
+    fn activity_to_json() {
+        // GIVEN a report of two nets
+        let report = ActivityReport {
+            duration: 100,
+            nets: vec![
+                NetActivity {
+                    name: "A".to_string(),
+                    transitions: 1,
+                    mid_threshold_time: 20,
+                },
+                NetActivity {
+                    name: "B".to_string(),
+                    transitions: 5,
+                    mid_threshold_time: 0,
+                },
+            ],
+        };
+        // WHEN it is rendered as JSON
+        // THEN both rankings are included in order
+        assert_eq!(
+            "{\"duration\":100,\"by_transitions\":[\
+             {\"name\":\"B\",\"transitions\":5,\"mid_threshold_time\":0},\
+             {\"name\":\"A\",\"transitions\":1,\"mid_threshold_time\":20}],\
+             \"by_mid_threshold_time\":[\
+             {\"name\":\"A\",\"transitions\":1,\"mid_threshold_time\":20},\
+             {\"name\":\"B\",\"transitions\":5,\"mid_threshold_time\":0}]}",
+            report.to_json()
+        );
+    }
+}
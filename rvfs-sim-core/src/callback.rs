@@ -0,0 +1,439 @@
+//! Callbacks registered against points in the step loop of a Simulation, following the semantics of the Verilog
+//! Procedural Interface (VPI), so that tools written against simulators of hardware description languages find
+//! familiar hook points.
+//!
+//! Each step of a [Callbacks] runs its callbacks in the order of the VPI scheduling regions:
+//!
+//! 1. [Reason::NextSimTime] callbacks, and [Reason::AtTime] and [Reason::AfterDelay] callbacks which have fallen due,
+//!    before the Simulation is stepped;
+//! 2. [Reason::ValueChange] callbacks, for each Wire which changed value during the step, in order of Wire Id;
+//! 3. [Reason::ReadWriteSync] callbacks, which may force Wires, taking effect in the next step;
+//! 4. [Reason::ReadOnlySync] callbacks, which only observe the settled state of the step.
+//!
+//! Callbacks of the same reason run in the order they were registered.  Time callbacks run once and are then removed,
+//! while every other callback runs until it is [removed](Callbacks::remove).
+
+use crate::prelude::*;
+use crate::sim::{SimResult, Simulation};
+use crate::trace::Change;
+use crate::WireId;
+
+/// The reason for which a callback is run, corresponding to a VPI callback reason.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Reason {
+    /// The Wire with the given Id changed value during a step, as for `cbValueChange`.
+    ValueChange(WireId),
+    /// A step is about to start at or after the given simulation time, as for `cbAtStartOfSimTime`.
+    AtTime(u64),
+    /// A step is about to start at or after the given simulation time has elapsed from registration, as for
+    /// `cbAfterDelay`.
+    AfterDelay(u64),
+    /// A step is about to start, as for `cbNextSimTime`.
+    NextSimTime,
+    /// A step has completed, and Wires may be forced for the next step, as for `cbReadWriteSynch`.
+    ReadWriteSync,
+    /// A step has completed, and its settled state may be observed, as for `cbReadOnlySynch`.
+    ReadOnlySync,
+}
+
+/// The data passed to a callback when it is run.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct CallbackData {
+    /// The reason the callback was registered with.
+    pub reason: Reason,
+    /// Present simulation time.
+    pub time: u64,
+    /// The change in value, for a [Reason::ValueChange] callback.
+    pub change: Option<Change>,
+}
+
+/// Handle identifying a registered callback, with which it can be removed.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct CallbackHandle(usize);
+
+/// A callback routine which may modify the Simulation.
+type ReadWriteRoutine = Box<dyn FnMut(&mut Simulation, &CallbackData) -> Result<(), String>>;
+
+/// A callback routine which may only observe the Simulation.
+type ReadOnlyRoutine = Box<dyn FnMut(&Simulation, &CallbackData) -> Result<(), String>>;
+
+/// The routine of a callback, and the access it has to the Simulation.
+enum Routine {
+    /// A routine which may modify the Simulation.
+    ReadWrite(ReadWriteRoutine),
+    /// A routine which may only observe the Simulation.
+    ReadOnly(ReadOnlyRoutine),
+}
+
+/// A registered callback.
+struct Entry {
+    /// The reason the callback was registered with.
+    reason: Reason,
+    /// Simulation time at which a time callback falls due.
+    due: u64,
+    /// The routine to run.
+    routine: Routine,
+}
+
+/// A Simulation stepped with registered callbacks.
+///
+/// # Example
+///
+/// ```
+/// # use rvfs_sim_core::callback::{Callbacks, Reason};
+/// # use rvfs_sim_core::sim::Simulation;
+/// # use rvfs_sim_core::time::SimDuration;
+/// # use rvfs_sim_core::wire::{Wire, WirePull};
+/// # use std::cell::RefCell;
+/// # use std::rc::Rc;
+/// let mut sim = Simulation::new(SimDuration::from_ticks(10));
+/// let reset = sim.add_wire(Wire::new("/RESET", WirePull::Up)).unwrap();
+///
+/// let mut callbacks = Callbacks::new(sim);
+/// callbacks
+///     .register(Reason::AtTime(30), move |sim, _| {
+///         Ok(sim.force_wire(reset, Some(WirePull::Down))?)
+///     })
+///     .unwrap();
+/// let changes = Rc::new(RefCell::new(Vec::new()));
+/// let recorded = changes.clone();
+/// callbacks
+///     .register(Reason::ValueChange(reset), move |_, data| {
+///         recorded.borrow_mut().push(data.time);
+///         Ok(())
+///     })
+///     .unwrap();
+/// for _ in 0..5 {
+///     callbacks.step().unwrap();
+/// }
+///
+/// assert_eq!(vec![40], *changes.borrow());
+/// ```
+pub struct Callbacks {
+    /// The Simulation.
+    sim: Simulation,
+    /// Registered callbacks, indexed by handle, or `None` once removed.
+    entries: Vec<Option<Entry>>,
+}
+
+impl Callbacks {
+    /// Create a new set of Callbacks, with none registered.
+    ///
+    /// # Parameters
+    ///
+    /// - `sim`: The Simulation to step.
+    pub fn new(sim: Simulation) -> Self {
+        Self {
+            sim,
+            entries: Vec::new(),
+        }
+    }
+
+    /// Obtain the Simulation.
+    pub fn simulation(&self) -> &Simulation {
+        &self.sim
+    }
+
+    /// Obtain the Simulation to modify it, such as to force Wires between steps.
+    pub fn simulation_mut(&mut self) -> &mut Simulation {
+        &mut self.sim
+    }
+
+    /// Take back the Simulation.
+    pub fn into_simulation(self) -> Simulation {
+        self.sim
+    }
+
+    /// Register a callback which may modify the Simulation.
+    ///
+    /// [Reason::ReadOnlySync] callbacks must be registered with [Callbacks::register_read_only] instead.
+    ///
+    /// # Parameters
+    ///
+    /// - `reason`: The reason for which to run the callback.
+    /// - `routine`: Closure run with the Simulation and the callback data, which fails the step if it fails.
+    pub fn register(
+        &mut self,
+        reason: Reason,
+        routine: impl FnMut(&mut Simulation, &CallbackData) -> Result<(), String> + 'static,
+    ) -> Result<CallbackHandle, String> {
+        if reason == Reason::ReadOnlySync {
+            return Err(
+                "Read-only synchronisation callbacks must be registered as read-only".to_string(),
+            );
+        }
+        self.add(reason, Routine::ReadWrite(Box::new(routine)))
+    }
+
+    /// Register a callback which only observes the Simulation.
+    ///
+    /// # Parameters
+    ///
+    /// - `reason`: The reason for which to run the callback.
+    /// - `routine`: Closure run with the Simulation and the callback data, which fails the step if it fails.
+    pub fn register_read_only(
+        &mut self,
+        reason: Reason,
+        routine: impl FnMut(&Simulation, &CallbackData) -> Result<(), String> + 'static,
+    ) -> Result<CallbackHandle, String> {
+        self.add(reason, Routine::ReadOnly(Box::new(routine)))
+    }
+
+    /// Remove a callback, returning whether it was still registered.
+    ///
+    /// # Parameters
+    ///
+    /// - `handle`: Handle of the callback.
+    pub fn remove(&mut self, handle: CallbackHandle) -> bool {
+        self.entries
+            .get_mut(handle.0)
+            .and_then(Option::take)
+            .is_some()
+    }
+
+    /// Advance the Simulation by one time step, running the callbacks due around it.
+    pub fn step(&mut self) -> Result<SimResult, String> {
+        let time = self.sim.time();
+        self.run(
+            |entry| match entry.reason {
+                Reason::NextSimTime => true,
+                Reason::AtTime(_) | Reason::AfterDelay(_) => entry.due <= time,
+                _ => false,
+            },
+            None,
+        )?;
+        for entry in &mut self.entries {
+            if entry.as_ref().is_some_and(|entry| {
+                matches!(entry.reason, Reason::AtTime(_) | Reason::AfterDelay(_))
+                    && entry.due <= time
+            }) {
+                *entry = None;
+            }
+        }
+
+        let result = self.sim.step().map_err(|error| error.to_string())?;
+
+        for change in self.sim.changes().to_vec() {
+            self.run(
+                |entry| entry.reason == Reason::ValueChange(change.id),
+                Some(change),
+            )?;
+        }
+        self.run(|entry| entry.reason == Reason::ReadWriteSync, None)?;
+        self.run(|entry| entry.reason == Reason::ReadOnlySync, None)?;
+
+        Ok(result)
+    }
+
+    /// Add a callback after checking its reason.
+    ///
+    /// # Parameters
+    ///
+    /// - `reason`: The reason for which to run the callback.
+    /// - `routine`: The routine to run.
+    fn add(&mut self, reason: Reason, routine: Routine) -> Result<CallbackHandle, String> {
+        let due = match reason {
+            Reason::ValueChange(id) => {
+                self.sim.wire(id)?;
+                0
+            }
+            Reason::AtTime(time) if time < self.sim.time() => {
+                return Err(format!(
+                    "Cannot register a callback at time {time}, before the present time {}",
+                    self.sim.time()
+                ))
+            }
+            Reason::AtTime(time) => time,
+            Reason::AfterDelay(delay) => self.sim.time().saturating_add(delay),
+            _ => 0,
+        };
+
+        self.entries.push(Some(Entry {
+            reason,
+            due,
+            routine,
+        }));
+        Ok(CallbackHandle(self.entries.len() - 1))
+    }
+
+    /// Run every registered callback selected by a filter, in order of registration.
+    ///
+    /// # Parameters
+    ///
+    /// - `filter`: Selects the callbacks to run.
+    /// - `change`: The change in value to pass to the callbacks, if any.
+    fn run(
+        &mut self,
+        filter: impl Fn(&Entry) -> bool,
+        change: Option<Change>,
+    ) -> Result<(), String> {
+        for (handle, entry) in self.entries.iter_mut().enumerate() {
+            let Some(entry) = entry.as_mut().filter(|entry| filter(entry)) else {
+                continue;
+            };
+            let data = CallbackData {
+                reason: entry.reason,
+                time: self.sim.time(),
+                change,
+            };
+            match &mut entry.routine {
+                Routine::ReadWrite(routine) => routine(&mut self.sim, &data),
+                Routine::ReadOnly(routine) => routine(&self.sim, &data),
+            }
+            .map_err(|message| {
+                format!(
+                    "Callback {handle} for {:?} failed at time {}: {message}",
+                    data.reason, data.time
+                )
+            })?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::time::SimDuration;
+    use crate::wire::{Wire, WirePull};
+    use alloc::rc::Rc;
+    use core::cell::RefCell;
+
+    /// Create Callbacks for a Simulation with a single Wire pulled up, returning them and the Wire.
+    fn pulled_up() -> (Callbacks, WireId) {
+        let mut sim = Simulation::new(SimDuration::from_ticks(10));
+        let wire = sim.add_wire(Wire::new("/RESET", WirePull::Up)).unwrap();
+        (Callbacks::new(sim), wire)
+    }
+
+    /// Register a callback which records its reason and time in a log.
+    fn log(
+        callbacks: &mut Callbacks,
+        reason: Reason,
+        log: &Rc<RefCell<Vec<(Reason, u64)>>>,
+    ) -> CallbackHandle {
+        let log = log.clone();
+        callbacks
+            .register_read_only(reason, move |_, data| {
+                log.borrow_mut().push((data.reason, data.time));
+                Ok(())
+            })
+            .unwrap()
+    }
+
+    #[test]
+    fn callbacks_run_in_region_order() {
+        // GIVEN callbacks of every reason, registered out of order, and a Wire forced low before the first step
+        let (mut callbacks, wire) = pulled_up();
+        callbacks
+            .simulation_mut()
+            .force_wire(wire, Some(WirePull::Down))
+            .unwrap();
+        let entries = Rc::new(RefCell::new(Vec::new()));
+        log(&mut callbacks, Reason::ReadOnlySync, &entries);
+        log(&mut callbacks, Reason::ReadWriteSync, &entries);
+        log(&mut callbacks, Reason::ValueChange(wire), &entries);
+        log(&mut callbacks, Reason::AfterDelay(10), &entries);
+        log(&mut callbacks, Reason::AtTime(0), &entries);
+        log(&mut callbacks, Reason::NextSimTime, &entries);
+        // WHEN the Simulation is stepped twice
+        callbacks.step().unwrap();
+        callbacks.step().unwrap();
+        // THEN each step runs them region by region, time callbacks run once, and value changes only when they happen
+        assert_eq!(
+            vec![
+                (Reason::AtTime(0), 0),
+                (Reason::NextSimTime, 0),
+                (Reason::ValueChange(wire), 10),
+                (Reason::ReadWriteSync, 10),
+                (Reason::ReadOnlySync, 10),
+                (Reason::AfterDelay(10), 10),
+                (Reason::NextSimTime, 10),
+                (Reason::ReadWriteSync, 20),
+                (Reason::ReadOnlySync, 20),
+            ],
+            *entries.borrow()
+        );
+    }
+
+    #[test]
+    fn callbacks_read_write_sync() {
+        // GIVEN a read-write callback which pulses a Wire low, and a value change callback recording the change
+        let (mut callbacks, wire) = pulled_up();
+        callbacks
+            .register(Reason::ReadWriteSync, move |sim, data| {
+                let pull = (data.time == 20).then_some(WirePull::Down);
+                Ok(sim.force_wire(wire, pull)?)
+            })
+            .unwrap();
+        let changes = Rc::new(RefCell::new(Vec::<f32>::new()));
+        let recorded = changes.clone();
+        callbacks
+            .register(Reason::ValueChange(wire), move |_, data| {
+                recorded
+                    .borrow_mut()
+                    .push(data.change.unwrap().value.into());
+                Ok(())
+            })
+            .unwrap();
+        // WHEN the Simulation is stepped
+        for _ in 0..4 {
+            callbacks.step().unwrap();
+        }
+        // THEN the forced level shows in the following step
+        assert_eq!(vec![0.0f32, 1.0], *changes.borrow());
+    }
+
+    #[test]
+    fn callbacks_remove() {
+        // GIVEN a callback on every step
+        let (mut callbacks, _) = pulled_up();
+        let entries = Rc::new(RefCell::new(Vec::new()));
+        let handle = log(&mut callbacks, Reason::NextSimTime, &entries);
+        // WHEN it is removed after a step, and then removed again
+        callbacks.step().unwrap();
+        let removed = callbacks.remove(handle);
+        let removed_again = callbacks.remove(handle);
+        callbacks.step().unwrap();
+        // THEN it only ran before its removal
+        assert!(removed);
+        assert!(!removed_again);
+        assert_eq!(vec![(Reason::NextSimTime, 0)], *entries.borrow());
+    }
+
+    #[test]
+    fn callbacks_errors() {
+        // GIVEN Callbacks for a stepped Simulation
+        let (mut callbacks, _) = pulled_up();
+        callbacks.step().unwrap();
+        // WHEN invalid callbacks are registered, and a callback fails
+        let wire_error = callbacks
+            .register(Reason::ValueChange(WireId::from(7)), |_, _| Ok(()))
+            .unwrap_err();
+        let time_error = callbacks
+            .register(Reason::AtTime(0), |_, _| Ok(()))
+            .unwrap_err();
+        let read_only_error = callbacks
+            .register(Reason::ReadOnlySync, |_, _| Ok(()))
+            .unwrap_err();
+        callbacks
+            .register_read_only(Reason::ReadOnlySync, |_, _| Err("bad state".to_string()))
+            .unwrap();
+        let step_error = callbacks.step().unwrap_err();
+        // THEN each is reported
+        assert_eq!("No wire found for the given ID", wire_error);
+        assert_eq!(
+            "Cannot register a callback at time 0, before the present time 10",
+            time_error
+        );
+        assert_eq!(
+            "Read-only synchronisation callbacks must be registered as read-only",
+            read_only_error
+        );
+        assert_eq!(
+            "Callback 0 for ReadOnlySync failed at time 20: bad state",
+            step_error
+        );
+    }
+}
@@ -0,0 +1,401 @@
+//! Checking of signals which cross between clock domains.
+//!
+//! Sequential Elements are [tagged](Simulation::set_element_domain) with the clock domain they are clocked in, and
+//! Wires whose domain cannot be inferred, such as inputs from outside the Simulation, are
+//! [tagged](Simulation::set_wire_domain) too.  A signal launched in one domain and captured by an Element in another
+//! may change just as it is sampled, leaving the capturing Element metastable, unless it is captured by a
+//! [synchroniser](crate::element::Element::is_synchroniser).
+//!
+//! [crossings] follows every signal from where it is launched, through untagged Elements, which are taken to be
+//! combinational, to the tagged Elements which capture it, and reports each unsynchronised crossing with its path.
+//! [check] turns the crossings into an error, to reject a netlist before it is simulated, and a [CdcMonitor] reports
+//! each change of a crossing signal while it is simulated.
+
+use crate::sim::Simulation;
+use crate::trace::{Change, Logic, Tracer, DEFAULT_HIGH_THRESHOLD, DEFAULT_LOW_THRESHOLD};
+use crate::{ElementId, WireId};
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+/// A path by which a signal crosses from one clock domain to another without a synchroniser.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Crossing {
+    /// Clock domain in which the signal is launched.
+    pub from: String,
+    /// Clock domain of the Element which captures the signal.
+    pub to: String,
+    /// The path of the signal, from the launching pin or tagged Wire, through Wires and untagged Elements, to the
+    /// capturing pin.  Pins are named as `element.pin`.
+    pub path: Vec<String>,
+    /// Id of the Wire from which the signal is captured.
+    pub wire: WireId,
+}
+
+impl fmt::Display for Crossing {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} -> {}: {}",
+            self.from,
+            self.to,
+            self.path.join(" -> ")
+        )
+    }
+}
+
+/// Find every signal in a Simulation which crosses between clock domains without a synchroniser.
+///
+/// Signals are launched by the OutputPins of tagged Elements, and by tagged Wires, whose tags take precedence over the
+/// domains of the Elements driving them.  A signal passes on through untagged Elements, and stops at a tagged Element
+/// or a synchroniser, which is reported as a crossing if it is tagged with another domain and is not a synchroniser.
+/// Each crossing is reported once, by its shortest path, in the order found.
+///
+/// # Parameters
+///
+/// - `sim`: The Simulation to check.
+///
+/// # Example
+///
+/// ```
+/// # use rvfs_sim_core::cdc;
+/// # use rvfs_sim_core::element::flipflops::DFlipFlop;
+/// # use rvfs_sim_core::sim::Simulation;
+/// # use rvfs_sim_core::time::SimDuration;
+/// # use rvfs_sim_core::wire::{Wire, WirePull};
+/// let mut sim = Simulation::new(SimDuration::from_ticks(10));
+/// let q = sim.add_wire(Wire::new("Q1", WirePull::None)).unwrap();
+/// let u1 = sim.add_element(Box::new(DFlipFlop::new("U1", 0))).unwrap();
+/// let u2 = sim.add_element(Box::new(DFlipFlop::new("U2", 0))).unwrap();
+/// sim.connect_output(sim.output_pin(u1, "Q").unwrap(), q).unwrap();
+/// sim.connect_input(q, sim.input_pin(u2, "D").unwrap()).unwrap();
+/// sim.set_element_domain(u1, Some("cpu")).unwrap();
+/// sim.set_element_domain(u2, Some("usb")).unwrap();
+///
+/// let crossings = cdc::crossings(&sim).unwrap();
+/// assert_eq!("cpu -> usb: U1.Q -> Q1 -> U2.D", crossings[0].to_string());
+/// ```
+pub fn crossings(sim: &Simulation) -> Result<Vec<Crossing>, String> {
+    // The Elements reading each Wire, with the index of the reading InputPin, and the Wires each Element drives.
+    let mut readers: BTreeMap<WireId, Vec<(ElementId, usize)>> = BTreeMap::new();
+    let mut driven = BTreeMap::new();
+    for element in sim.elements() {
+        for (index, wire) in sim.input_wires(element)?.into_iter().enumerate() {
+            if let Some(wire) = wire {
+                readers.entry(wire).or_default().push((element, index));
+            }
+        }
+        driven.insert(element, sim.output_wires(element)?);
+    }
+
+    let wire_name = |wire: WireId| sim.wire(wire).map(|wire| wire.name().clone());
+    let mut queue = VecDeque::new();
+    for wire in sim.wires() {
+        if let Some(domain) = sim.wire_domain(wire)? {
+            queue.push_back((wire, domain.to_string(), vec![wire_name(wire)?]));
+        }
+    }
+    for element in sim.elements() {
+        let Some(domain) = sim.element_domain(element)? else {
+            continue;
+        };
+        let launcher = sim.element(element)?;
+        for (pin, wire) in launcher.output_pins().iter().zip(&driven[&element]) {
+            if let Some(wire) = *wire {
+                if sim.wire_domain(wire)?.is_none() {
+                    let path = vec![
+                        format!("{}.{}", launcher.name(), pin.name()),
+                        wire_name(wire)?,
+                    ];
+                    queue.push_back((wire, domain.to_string(), path));
+                }
+            }
+        }
+    }
+
+    let mut visited = BTreeSet::new();
+    let mut captured = BTreeSet::new();
+    let mut crossings = Vec::new();
+    while let Some((wire, domain, path)) = queue.pop_front() {
+        if !visited.insert((wire, domain.clone())) {
+            continue;
+        }
+        for &(element, index) in readers.get(&wire).into_iter().flatten() {
+            let reader = sim.element(element)?;
+            match sim.element_domain(element)? {
+                Some(to) => {
+                    if to != domain
+                        && !reader.is_synchroniser()
+                        && captured.insert((domain.clone(), element, index))
+                    {
+                        let mut path = path.clone();
+                        path.push(format!(
+                            "{}.{}",
+                            reader.name(),
+                            reader.input_pins()[index].name()
+                        ));
+                        crossings.push(Crossing {
+                            from: domain.clone(),
+                            to: to.to_string(),
+                            path,
+                            wire,
+                        });
+                    }
+                }
+                None if reader.is_synchroniser() => (),
+                None => {
+                    for output in driven[&element].iter().flatten().copied() {
+                        if sim.wire_domain(output)?.is_none() {
+                            let mut path = path.clone();
+                            path.push(reader.name().to_string());
+                            path.push(wire_name(output)?);
+                            queue.push_back((output, domain.clone(), path));
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(crossings)
+}
+
+/// Check that no signal in a Simulation crosses between clock domains without a synchroniser.
+///
+/// The error lists the path of every [crossing](crossings), one per line.
+///
+/// # Parameters
+///
+/// - `sim`: The Simulation to check.
+pub fn check(sim: &Simulation) -> Result<(), String> {
+    let crossings = crossings(sim)?;
+    if crossings.is_empty() {
+        return Ok(());
+    }
+
+    let mut message = format!(
+        "{} clock-domain crossings without a synchroniser:",
+        crossings.len()
+    );
+    for crossing in &crossings {
+        message.push_str(&format!("\n  {crossing}"));
+    }
+    Err(message)
+}
+
+/// A change of a signal crossing between clock domains, observed during a Simulation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CrossingEvent {
+    /// Simulation time at the end of the step in which the signal changed.
+    pub time: u64,
+    /// The crossing the signal took.
+    pub crossing: Crossing,
+}
+
+impl fmt::Display for CrossingEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.time, self.crossing)
+    }
+}
+
+/// State shared between all clones of a CdcMonitor.
+#[derive(Debug, Default)]
+struct State {
+    /// The crossings found when monitoring started.
+    crossings: Vec<Crossing>,
+    /// Changes of crossing signals, oldest first.
+    events: Vec<CrossingEvent>,
+}
+
+/// A Tracer which reports each change in the logic level of a signal crossing between clock domains.
+///
+/// The crossings are found when the monitor is started, and each change of the Wire a crossing is captured from,
+/// including into or out of the indeterminate band, is recorded as an event, since it is at these moments that the
+/// capturing Element may sample the signal mid-transition.
+///
+/// Clones of a CdcMonitor share their state, so a clone can be kept to obtain the events after the original has been
+/// attached to a Simulation.
+#[derive(Debug, Clone)]
+pub struct CdcMonitor {
+    /// Shared monitoring state.
+    state: Arc<Mutex<State>>,
+}
+
+impl CdcMonitor {
+    /// Create a new CdcMonitor.
+    pub fn new() -> Self {
+        Self {
+            state: Arc::new(Mutex::new(State::default())),
+        }
+    }
+
+    /// Obtain the crossings found when monitoring started.
+    pub fn crossings(&self) -> Vec<Crossing> {
+        self.lock().crossings.clone()
+    }
+
+    /// Obtain the changes of crossing signals so far, oldest first.
+    pub fn events(&self) -> Vec<CrossingEvent> {
+        self.lock().events.clone()
+    }
+
+    /// Lock the shared state.
+    fn lock(&self) -> std::sync::MutexGuard<'_, State> {
+        self.state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl Default for CdcMonitor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Tracer for CdcMonitor {
+    fn start(&mut self, sim: &Simulation) -> Result<(), String> {
+        let crossings = crossings(sim)?;
+        let mut state = self.lock();
+        state.crossings = crossings;
+        state.events.clear();
+        Ok(())
+    }
+
+    fn record(&mut self, sim: &Simulation, changes: &[Change]) -> Result<(), String> {
+        let logic = |value| Logic::from_level(value, DEFAULT_LOW_THRESHOLD, DEFAULT_HIGH_THRESHOLD);
+        let mut state = self.lock();
+        let State { crossings, events } = &mut *state;
+        for change in changes {
+            if logic(change.previous) == logic(change.value) {
+                continue;
+            }
+            for crossing in crossings
+                .iter()
+                .filter(|crossing| crossing.wire == change.id)
+            {
+                events.push(CrossingEvent {
+                    time: sim.time(),
+                    crossing: crossing.clone(),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self, _sim: &Simulation) -> Result<(), String> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::circuit;
+    use crate::error::{ComponentKind, SimError};
+
+    #[test]
+    fn cdc_crossings() {
+        // GIVEN a circuit in two clock domains, with an asynchronous input, a crossing through a gate, a
+        // synchronised crossing and a signal staying in its domain
+        let mut circuit = circuit! {
+            interval 10;
+            wire ext: pull_down;
+            wire q1: none;
+            wire x: none;
+            element u1: dff { Q: q1 };
+            element u2: not { I0: q1, Y: x };
+            element u3: dff { D: x };
+            element u4: synchroniser { D: q1 };
+            element u5: dff { D: ext };
+            element u6: dff { D: q1 };
+        }
+        .unwrap();
+        for (element, domain) in [
+            ("u1", "a"),
+            ("u3", "b"),
+            ("u4", "b"),
+            ("u5", "a"),
+            ("u6", "a"),
+        ] {
+            let id = circuit.element(element).unwrap();
+            circuit
+                .simulation_mut()
+                .set_element_domain(id, Some(domain))
+                .unwrap();
+        }
+        let ext = circuit.wire("ext").unwrap();
+        circuit
+            .simulation_mut()
+            .set_wire_domain(ext, Some("async"))
+            .unwrap();
+        // WHEN the crossings are found
+        let crossings = crossings(circuit.simulation()).unwrap();
+        // THEN only the unsynchronised crossings are reported, with their paths
+        let paths: Vec<String> = crossings.iter().map(Crossing::to_string).collect();
+        assert_eq!(
+            vec![
+                "async -> a: ext -> u5.D",
+                "a -> b: u1.Q -> q1 -> u2 -> x -> u3.D"
+            ],
+            paths
+        );
+        assert_eq!(circuit.wire("x").unwrap(), crossings[1].wire);
+        // AND THEN checking the circuit fails, listing them
+        assert_eq!(
+            Err(
+                "2 clock-domain crossings without a synchroniser:\n  async -> a: ext -> u5.D\n  \
+                 a -> b: u1.Q -> q1 -> u2 -> x -> u3.D"
+                    .to_string()
+            ),
+            check(circuit.simulation())
+        );
+        // AND THEN removing the tags leaves no crossings
+        circuit.simulation_mut().set_wire_domain(ext, None).unwrap();
+        let u3 = circuit.element("u3").unwrap();
+        circuit
+            .simulation_mut()
+            .set_element_domain(u3, None)
+            .unwrap();
+        assert_eq!(Ok(()), check(circuit.simulation()));
+        assert_eq!(
+            Err(SimError::UnknownId(ComponentKind::Wire)),
+            circuit
+                .simulation_mut()
+                .set_wire_domain(WireId::from(99), Some("a"))
+        );
+    }
+    #[test]
+    fn cdc_monitor() {
+        // GIVEN a clock in one domain sampled by a flip-flop in another, and a monitor
+        let mut circuit = circuit! {
+            interval 10;
+            wire clk_a: pull_down;
+            wire clk_b: pull_down;
+            element x1: clock(period = 40) { CLK: clk_a };
+            element x2: clock(period = 30) { CLK: clk_b };
+            element u1: dff { CLK: clk_b, D: clk_a };
+        }
+        .unwrap();
+        let (clk_a, clk_b) = (
+            circuit.wire("clk_a").unwrap(),
+            circuit.wire("clk_b").unwrap(),
+        );
+        let u1 = circuit.element("u1").unwrap();
+        let sim = circuit.simulation_mut();
+        sim.set_wire_domain(clk_a, Some("a")).unwrap();
+        sim.set_wire_domain(clk_b, Some("b")).unwrap();
+        sim.set_element_domain(u1, Some("b")).unwrap();
+        let monitor = CdcMonitor::new();
+        sim.add_tracer(Box::new(monitor.clone()));
+        // WHEN the simulation is stepped
+        for _ in 0..8 {
+            sim.step().unwrap();
+        }
+        // THEN the crossing is found, and each change of the crossing signal is reported
+        assert_eq!(1, monitor.crossings().len());
+        let times: Vec<u64> = monitor.events().iter().map(|event| event.time).collect();
+        assert_eq!(vec![10, 30, 50, 70], times);
+        assert_eq!("10: a -> b: clk_a -> u1.D", monitor.events()[0].to_string());
+    }
+}
@@ -0,0 +1,358 @@
+//! A declarative way to build a Simulation in Rust, for testbenches which would otherwise be mostly wiring code.
+//!
+//! The [circuit!](crate::circuit!) macro describes the Wires of a circuit and the Elements connected to them, and
+//! builds a [Circuit]: the Simulation, along with the Ids of its Wires and Elements looked up by the names given in the
+//! description.
+//!
+//! ```
+//! # use rvfs_sim_core::circuit;
+//! let mut circuit = circuit! {
+//!     interval 10;
+//!     wire clk: pull_down tau 2.0;
+//!     wire "/CLK": none;
+//!     element x1: clock(period = 40) { CLK: clk };
+//!     element u1: not(delay = 10) { I0: clk, Y: "/CLK" };
+//! }
+//! .unwrap();
+//!
+//! let clk_bar = circuit.wire("/CLK").unwrap();
+//! for _ in 0..4 {
+//!     circuit.simulation_mut().step().unwrap();
+//! }
+//! assert_eq!(1.0, f32::from(circuit.simulation().wire(clk_bar).unwrap().measure()));
+//! ```
+//!
+//! The description starts with the simulation interval, in ticks, followed by Wires and Elements in any order, each
+//! ending in a semicolon.  A name is either an identifier or a string literal, for names such as `/CLK` which are not
+//! identifiers.
+//!
+//! - `wire <name>: <pull> [tau <time constant>];` adds a Wire, where the pull is `pull_up`, `pull_down` or `none`.
+//! - `element <name>: <kind>[(<parameter> = <value>, ...)] { <pin>: <wire>, ... };` instantiates an Element of a kind
+//!   in the [standard Registry](crate::element::Registry::standard), with parameters of any type which can be
+//!   converted to a string.
+//! - `element <name> = <expression> => { <pin>: <wire>, ... };` adds an Element constructed in Rust.  The expression
+//!   may use `?` on a `Result<_, String>`.
+//!
+//! Each pin, input or output, is connected to the Wire named after it.  The macro evaluates to a
+//! `Result<Circuit, String>`, failing if an Element cannot be instantiated, or a name or connection is invalid.
+
+use crate::element::{Element, Registry};
+use crate::monitor::{Monitors, Rule};
+use crate::sim::Simulation;
+use crate::subcircuit::Subcircuit;
+use crate::time::SimDuration;
+use crate::wire::Wire;
+use crate::{ElementId, WireId};
+use std::collections::BTreeMap;
+
+/// A Simulation built by the [circuit!](crate::circuit!) macro, with its Wires and Elements named.
+#[derive(Debug)]
+pub struct Circuit {
+    /// The Simulation.
+    sim: Simulation,
+    /// Ids of the Wires, keyed by name.
+    wires: BTreeMap<String, WireId>,
+    /// Ids of the Elements, keyed by name.
+    elements: BTreeMap<String, ElementId>,
+    /// Monitors checked as a Tracer of the Simulation, once the first has been added.
+    monitors: Option<Monitors>,
+}
+
+impl Circuit {
+    /// Create a new, empty Circuit.
+    ///
+    /// # Parameters
+    ///
+    /// - `interval`: Simulation time between steps.
+    pub fn new(interval: SimDuration) -> Self {
+        Self {
+            sim: Simulation::new(interval),
+            wires: BTreeMap::new(),
+            elements: BTreeMap::new(),
+            monitors: None,
+        }
+    }
+
+    /// Add a Wire, named as the Wire itself is.
+    ///
+    /// # Parameters
+    ///
+    /// - `wire`: The Wire.
+    pub fn add_wire(&mut self, wire: Wire) -> Result<WireId, String> {
+        let name = wire.name().clone();
+        if self.wires.contains_key(&name) {
+            return Err(format!("Duplicate wire \"{name}\""));
+        }
+        let id = self.sim.add_wire(wire)?;
+        self.wires.insert(name, id);
+        Ok(id)
+    }
+
+    /// Add an Element, and connect its pins to Wires already added.
+    ///
+    /// # Parameters
+    ///
+    /// - `name`: Name to look the Element up by.
+    /// - `element`: The Element.
+    /// - `connections`: Pairs of the name of a pin, input or output, and the name of the Wire to connect it to.
+    pub fn add_element(
+        &mut self,
+        name: &str,
+        element: Box<dyn Element>,
+        connections: &[(&str, &str)],
+    ) -> Result<ElementId, String> {
+        if self.elements.contains_key(name) {
+            return Err(format!("Duplicate element \"{name}\""));
+        }
+        let id = self.sim.add_element(element)?;
+        self.elements.insert(name.to_string(), id);
+        for (pin, wire) in connections {
+            let wire = self.wire(wire)?;
+            if let Ok(input) = self.sim.input_pin(id, pin) {
+                self.sim.connect_input(wire, input)?;
+            } else {
+                let output = self
+                    .sim
+                    .output_pin(id, pin)
+                    .map_err(|_| format!("Element \"{name}\" has no pin \"{pin}\""))?;
+                self.sim.connect_output(output, wire)?;
+            }
+        }
+        Ok(id)
+    }
+
+    /// Add an instance of a [Subcircuit], with its Wires and Elements named beneath the instance.
+    ///
+    /// # Parameters
+    ///
+    /// - `registry`: The Registry of Element kinds, from which the Subcircuit's Elements are instantiated.
+    /// - `subcircuit`: The Subcircuit.
+    /// - `name`: Name of the instance.
+    /// - `ports`: Pairs of the name of each port of the Subcircuit and the name of the Wire already added to map it to.
+    pub fn instantiate(
+        &mut self,
+        registry: &Registry,
+        subcircuit: &Subcircuit,
+        name: &str,
+        ports: &[(&str, &str)],
+    ) -> Result<(), String> {
+        subcircuit.instantiate(self, registry, name, ports)
+    }
+
+    /// Add a monitor, checked after every step of the Simulation.
+    ///
+    /// The first monitor added attaches the Circuit's [Monitors] to the Simulation as a Tracer.
+    ///
+    /// # Parameters
+    ///
+    /// - `name`: A human-readable name for the monitor, used when reporting.
+    /// - `rule`: The rule to check.
+    pub fn add_monitor(&mut self, name: &str, rule: Rule) {
+        let sim = &mut self.sim;
+        self.monitors
+            .get_or_insert_with(|| {
+                let monitors = Monitors::new();
+                sim.add_tracer(Box::new(monitors.clone()));
+                monitors
+            })
+            .add(name, rule);
+    }
+
+    /// Obtain the monitors added to the Circuit, if any have been.
+    ///
+    /// They share their state with those attached to the Simulation, so a clone can be kept to examine the results
+    /// after the Circuit has been consumed by [into_simulation](Self::into_simulation).
+    pub fn monitors(&self) -> Option<&Monitors> {
+        self.monitors.as_ref()
+    }
+
+    /// Look up the Id of a Wire.
+    ///
+    /// # Parameters
+    ///
+    /// - `name`: Name of the Wire.
+    pub fn wire(&self, name: &str) -> Result<WireId, String> {
+        self.wires
+            .get(name)
+            .copied()
+            .ok_or_else(|| format!("No wire named \"{name}\""))
+    }
+
+    /// Look up the Id of an Element.
+    ///
+    /// # Parameters
+    ///
+    /// - `name`: Name the Element was added with.
+    pub fn element(&self, name: &str) -> Result<ElementId, String> {
+        self.elements
+            .get(name)
+            .copied()
+            .ok_or_else(|| format!("No element named \"{name}\""))
+    }
+
+    /// Obtain the Simulation.
+    pub fn simulation(&self) -> &Simulation {
+        &self.sim
+    }
+
+    /// Obtain the Simulation mutably, to step it.
+    pub fn simulation_mut(&mut self) -> &mut Simulation {
+        &mut self.sim
+    }
+
+    /// Consume the Circuit, returning its Simulation.
+    pub fn into_simulation(self) -> Simulation {
+        self.sim
+    }
+}
+
+/// Build a [Circuit](crate::circuit::Circuit) from a description of its Wires and Elements, as described in the
+/// [module](crate::circuit) documentation.
+#[macro_export]
+macro_rules! circuit {
+    (interval $interval:expr; $($body:tt)*) => {
+        (|| -> ::std::result::Result<$crate::circuit::Circuit, ::std::string::String> {
+            #[allow(unused_variables)]
+            let registry = $crate::element::Registry::standard();
+            let mut circuit =
+                $crate::circuit::Circuit::new($crate::time::SimDuration::from_ticks($interval));
+            $crate::circuit!(@items circuit registry $($body)*);
+            ::std::result::Result::Ok(circuit)
+        })()
+    };
+    (@items $circuit:ident $registry:ident) => {};
+    (@items $circuit:ident $registry:ident
+        wire $name:tt : $pull:ident $(tau $tau:expr)? ; $($rest:tt)*
+    ) => {
+        #[allow(unused_mut)]
+        let mut wire = $crate::wire::Wire::new($crate::circuit!(@name $name), $crate::circuit!(@pull $pull));
+        $(wire.set_time_constant($tau);)?
+        $circuit.add_wire(wire)?;
+        $crate::circuit!(@items $circuit $registry $($rest)*);
+    };
+    (@items $circuit:ident $registry:ident
+        element $name:tt : $kind:ident $(($($parameter:ident = $value:expr),* $(,)?))?
+            { $($pin:tt : $wire:tt),* $(,)? } ; $($rest:tt)*
+    ) => {
+        let parameters = $crate::element::Parameters::new()
+            $($(.with(stringify!($parameter), &$value.to_string())) *)?;
+        let element = $registry.create(stringify!($kind), $crate::circuit!(@name $name), &parameters)?;
+        $circuit.add_element(
+            $crate::circuit!(@name $name),
+            element,
+            &[$(($crate::circuit!(@name $pin), $crate::circuit!(@name $wire))),*],
+        )?;
+        $crate::circuit!(@items $circuit $registry $($rest)*);
+    };
+    (@items $circuit:ident $registry:ident
+        element $name:tt = $element:expr => { $($pin:tt : $wire:tt),* $(,)? } ; $($rest:tt)*
+    ) => {
+        $circuit.add_element(
+            $crate::circuit!(@name $name),
+            ::std::boxed::Box::new($element),
+            &[$(($crate::circuit!(@name $pin), $crate::circuit!(@name $wire))),*],
+        )?;
+        $crate::circuit!(@items $circuit $registry $($rest)*);
+    };
+    (@name $name:ident) => {
+        stringify!($name)
+    };
+    (@name $name:literal) => {
+        $name
+    };
+    (@pull pull_up) => {
+        $crate::wire::WirePull::Up
+    };
+    (@pull pull_down) => {
+        $crate::wire::WirePull::Down
+    };
+    (@pull none) => {
+        $crate::wire::WirePull::None
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::element::gates::{Gate, GateKind};
+    use crate::wire::WirePull;
+
+    #[test]
+    fn circuit_macro() {
+        // GIVEN a description of an SR latch built from NAND gates, one of them constructed in Rust
+        // WHEN the circuit is built
+        let mut circuit = circuit! {
+            interval 10;
+            wire set: pull_up;
+            wire reset: pull_down tau 0.0;
+            wire q: none;
+            wire "/Q": none;
+            element u1: nand(inputs = 2, delay = 10,) { I0: set, I1: "/Q", Y: q, };
+            element u2 = Gate::new("U2", GateKind::Nand, 2, 10)? => { I0: reset, I1: q, "Y": "/Q" };
+        }
+        .unwrap();
+        // THEN the Wires and Elements are named, and the latch is reset
+        let q = circuit.wire("q").unwrap();
+        assert_eq!(
+            WirePull::Up,
+            circuit
+                .simulation()
+                .wire(circuit.wire("set").unwrap())
+                .unwrap()
+                .pull()
+        );
+        assert_eq!(
+            "u1",
+            circuit
+                .simulation()
+                .element(circuit.element("u1").unwrap())
+                .unwrap()
+                .name()
+        );
+        assert_eq!(
+            "U2",
+            circuit
+                .simulation()
+                .element(circuit.element("u2").unwrap())
+                .unwrap()
+                .name()
+        );
+        for _ in 0..5 {
+            circuit.simulation_mut().step().unwrap();
+        }
+        let sim = circuit.into_simulation();
+        assert_eq!(0.0, f32::from(sim.wire(q).unwrap().measure()));
+    }
+    #[test]
+    fn circuit_macro_errors() {
+        // GIVEN descriptions with an unknown kind, an unknown Wire, an unknown pin and a duplicate Wire
+        // WHEN they are built
+        let kind = circuit! {
+            interval 10;
+            element u1: flux_capacitor {};
+        };
+        let wire = circuit! {
+            interval 10;
+            element u1: not { I0: a };
+        };
+        let pin = circuit! {
+            interval 10;
+            wire a: none;
+            element u1: not { D: a };
+        };
+        let duplicate = circuit! {
+            interval 10;
+            wire a: none;
+            wire a: pull_up;
+        };
+        // THEN each fails
+        assert_eq!("Unknown element kind \"flux_capacitor\"", kind.unwrap_err());
+        assert_eq!("No wire named \"a\"", wire.unwrap_err());
+        assert_eq!("Element \"u1\" has no pin \"D\"", pin.unwrap_err());
+        assert_eq!("Duplicate wire \"a\"", duplicate.unwrap_err());
+        assert_eq!(
+            Err("No element named \"u9\"".to_string()),
+            Circuit::new(SimDuration::from_ticks(10)).element("u9")
+        );
+    }
+}
@@ -0,0 +1,502 @@
+//! Control of a running Simulation from other threads.
+//!
+//! [Running](crate::sim::Simulation::run) a Simulation consumes it and occupies the thread until the run finishes, so
+//! other parts of a program, such as a daemon serving remote clients or a GUI, control it through a [SimController]
+//! obtained [beforehand](crate::sim::Simulation::controller).  A controller sends commands into the run loop, which
+//! serves them between steps, and can be cloned and shared freely.  It can query Wire values, force Wires, set
+//! breakpoints, and pause, resume or stop the run.
+//!
+//! While paused, the run loop waits for commands rather than stepping, so the Simulation can be inspected at leisure,
+//! and its circuit [modified](SimController::modify) without losing the time already simulated.
+//! A paused run resumes by itself once every controller has been dropped, so that it cannot be stranded.
+
+use crate::monitor::Condition;
+use crate::sim::Simulation;
+use crate::wire::WirePull;
+use crate::wirevalue::WireValue;
+use crate::WireId;
+use std::fmt;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender, TryRecvError};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Time a paused run waits for a command before checking whether any controller remains.
+const PAUSED_POLL: Duration = Duration::from_millis(50);
+
+/// Handle identifying a breakpoint, with which it can be removed.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct BreakpointId(u64);
+
+/// Status of a controlled run.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Status {
+    /// Present simulation time.
+    pub time: u64,
+    /// Whether the run is paused.
+    pub paused: bool,
+    /// The breakpoint which paused the run, if it was paused by one.
+    pub breakpoint: Option<BreakpointId>,
+}
+
+/// A command sent from a controller into the run loop, with a channel for any reply.
+#[derive(Debug)]
+enum Command {
+    /// Obtain the status of the run.
+    Status(Sender<Status>),
+    /// Obtain the status of the run once it is paused.
+    WaitPaused(Sender<Status>),
+    /// Measure a Wire.
+    Value(WireId, Sender<Result<WireValue, String>>),
+    /// Force a Wire, or release it.
+    Force(WireId, Option<WirePull>, Sender<Result<(), String>>),
+    /// Add a breakpoint.
+    AddBreakpoint(Condition, Sender<Result<BreakpointId, String>>),
+    /// Remove a breakpoint.
+    RemoveBreakpoint(BreakpointId, Sender<Result<(), String>>),
+    /// Modify the Simulation, if the run is paused.
+    Modify(Modification),
+    /// Pause the run.
+    Pause,
+    /// Resume the run.
+    Resume,
+    /// Stop the run.
+    Stop,
+}
+
+/// A function modifying the Simulation, which is told whether the run is paused and sends its own reply.
+type ModifyFn = Box<dyn FnOnce(&mut Simulation, bool) + Send>;
+
+/// A [ModifyFn] sent as a command.
+struct Modification(ModifyFn);
+
+impl fmt::Debug for Modification {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Modification").finish_non_exhaustive()
+    }
+}
+
+/// A cloneable handle with which other threads control a running Simulation.
+///
+/// Every method fails if the run has finished, or the Simulation has been dropped.
+///
+/// # Example
+///
+/// ```
+/// # use rvfs_sim_core::element::clocks::ClockGenerator;
+/// # use rvfs_sim_core::monitor::Condition;
+/// # use rvfs_sim_core::sim::Simulation;
+/// # use rvfs_sim_core::time::SimDuration;
+/// # use rvfs_sim_core::wire::{Wire, WirePull};
+/// let mut sim = Simulation::new(SimDuration::from_ticks(10));
+/// let clk = sim.add_wire(Wire::new("CLK", WirePull::None)).unwrap();
+/// let x1 = sim
+///     .add_element(Box::new(ClockGenerator::new("X1", 100.0, 0.5, 0.0, 0, 0.0).unwrap()))
+///     .unwrap();
+/// sim.connect_output(sim.output_pin(x1, "CLK").unwrap(), clk).unwrap();
+/// let controller = sim.controller();
+/// let run = std::thread::spawn(move || sim.run());
+///
+/// controller.add_breakpoint(Condition::High(clk)).unwrap();
+/// let status = controller.wait_paused().unwrap();
+/// assert_eq!(1.0, f32::from(controller.value(clk).unwrap()));
+/// controller.stop().unwrap();
+/// run.join().unwrap().unwrap();
+/// # assert!(status.paused);
+/// ```
+#[derive(Debug, Clone)]
+pub struct SimController {
+    /// Sender of commands into the run loop.
+    sender: Sender<Command>,
+    /// Token shared by every controller, so the run loop can tell when none remain.
+    _token: Arc<()>,
+}
+
+impl SimController {
+    /// Obtain the status of the run.
+    pub fn status(&self) -> Result<Status, String> {
+        self.request(Command::Status)
+    }
+
+    /// Wait until the run is paused, by a breakpoint or a [pause](Self::pause), and obtain its status.
+    pub fn wait_paused(&self) -> Result<Status, String> {
+        self.request(Command::WaitPaused)
+    }
+
+    /// Measure the present value of a Wire.
+    ///
+    /// # Parameters
+    ///
+    /// - `wire`: The Id of the Wire.
+    pub fn value(&self, wire: WireId) -> Result<WireValue, String> {
+        self.request(|reply| Command::Value(wire, reply))?
+    }
+
+    /// Force a Wire to feel a pull, or release it, from the next step.
+    ///
+    /// # Parameters
+    ///
+    /// - `wire`: The Id of the Wire.
+    /// - `pull`: The pull to force, or `None` to release the Wire.
+    pub fn force(&self, wire: WireId, pull: Option<WirePull>) -> Result<(), String> {
+        self.request(|reply| Command::Force(wire, pull, reply))?
+    }
+
+    /// Add a breakpoint, which pauses the run after any step at the end of which its condition has come to hold.
+    ///
+    /// Like every request awaiting a reply, this blocks until the run loop serves it, so it must not be called from
+    /// the thread which is to run the Simulation before the run has started.
+    ///
+    /// # Parameters
+    ///
+    /// - `condition`: The condition, which must not already hold for the run to pause.
+    pub fn add_breakpoint(&self, condition: Condition) -> Result<BreakpointId, String> {
+        self.request(|reply| Command::AddBreakpoint(condition, reply))?
+    }
+
+    /// Remove a breakpoint.
+    ///
+    /// # Parameters
+    ///
+    /// - `breakpoint`: The breakpoint, as returned when it was added.
+    pub fn remove_breakpoint(&self, breakpoint: BreakpointId) -> Result<(), String> {
+        self.request(|reply| Command::RemoveBreakpoint(breakpoint, reply))?
+    }
+
+    /// Modify the Simulation while the run is paused, such as to patch its circuit, and obtain the result.
+    ///
+    /// The function is called by the run loop between steps, with the Simulation as it was left by the last step, and
+    /// can add, remove and reconnect [Wires](Simulation::remove_wire) and [Elements](Simulation::remove_element).
+    /// The state of everything it leaves untouched is preserved, and the run carries on from the same time when
+    /// resumed.
+    ///
+    /// # Parameters
+    ///
+    /// - `f`: The function, which is not called unless the run is paused.
+    pub fn modify<T, F>(&self, f: F) -> Result<T, String>
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut Simulation) -> Result<T, String> + Send + 'static,
+    {
+        self.request(|reply| {
+            Command::Modify(Modification(Box::new(move |sim, paused| {
+                let _ = reply.send(if paused {
+                    f(sim)
+                } else {
+                    Err("Simulation must be paused to modify it".to_string())
+                });
+            })))
+        })?
+    }
+
+    /// Request the run to pause before its next step.
+    pub fn pause(&self) -> Result<(), String> {
+        self.send(Command::Pause)
+    }
+
+    /// Request a paused run to resume.
+    pub fn resume(&self) -> Result<(), String> {
+        self.send(Command::Resume)
+    }
+
+    /// Request the run to stop before its next step, finishing as if its Elements had finished it.
+    pub fn stop(&self) -> Result<(), String> {
+        self.send(Command::Stop)
+    }
+
+    /// Send a command into the run loop.
+    ///
+    /// # Parameters
+    ///
+    /// - `command`: The command.
+    fn send(&self, command: Command) -> Result<(), String> {
+        self.sender
+            .send(command)
+            .map_err(|_| "Simulation is no longer running".to_string())
+    }
+
+    /// Send a command into the run loop, and wait for its reply.
+    ///
+    /// # Parameters
+    ///
+    /// - `command`: Function creating the command from the channel for its reply.
+    fn request<T>(&self, command: impl FnOnce(Sender<T>) -> Command) -> Result<T, String> {
+        let (reply, receiver) = mpsc::channel();
+        self.send(command(reply))?;
+        receiver
+            .recv()
+            .map_err(|_| "Simulation is no longer running".to_string())
+    }
+}
+
+/// A breakpoint, with whether its condition held at the end of the last step.
+#[derive(Debug)]
+struct Breakpoint {
+    /// Handle identifying the breakpoint.
+    id: BreakpointId,
+    /// The condition at which to pause.
+    condition: Condition,
+    /// Whether the condition held at the end of the last step.
+    held: bool,
+}
+
+/// The run loop's end of the controllers' channel, along with the state of the run they control.
+#[derive(Debug)]
+pub(crate) struct Control {
+    /// Sender from which new controllers are created.
+    sender: Sender<Command>,
+    /// Receiver of commands from every controller.
+    receiver: Receiver<Command>,
+    /// Token shared by every controller.
+    token: Arc<()>,
+    /// The breakpoints, in the order they were added.
+    breakpoints: Vec<Breakpoint>,
+    /// Number of breakpoints ever added, from which their Ids are taken.
+    added: u64,
+    /// Whether the run is paused, and the breakpoint which paused it, if any.
+    paused: Option<Option<BreakpointId>>,
+    /// Replies awaiting a pause.
+    waiting: Vec<Sender<Status>>,
+}
+
+impl Control {
+    /// Create a new Control, with no controllers yet.
+    pub(crate) fn new() -> Self {
+        let (sender, receiver) = mpsc::channel();
+        Self {
+            sender,
+            receiver,
+            token: Arc::new(()),
+            breakpoints: Vec::new(),
+            added: 0,
+            paused: None,
+            waiting: Vec::new(),
+        }
+    }
+
+    /// Create a new controller.
+    pub(crate) fn controller(&self) -> SimController {
+        SimController {
+            sender: self.sender.clone(),
+            _token: Arc::clone(&self.token),
+        }
+    }
+
+    /// Serve the commands sent since the last step, and pause if a breakpoint has been hit, waiting for commands
+    /// until the run is resumed.
+    ///
+    /// Returns false if the run is to stop.
+    ///
+    /// # Parameters
+    ///
+    /// - `sim`: The Simulation being run.
+    pub(crate) fn serve(&mut self, sim: &mut Simulation) -> bool {
+        for breakpoint in &mut self.breakpoints {
+            let held = breakpoint.condition.evaluate(sim).unwrap_or(false);
+            if held && !breakpoint.held && self.paused.is_none() {
+                self.paused = Some(Some(breakpoint.id));
+            }
+            breakpoint.held = held;
+        }
+
+        loop {
+            if self.paused.is_some() {
+                let status = self.status(sim);
+                for reply in self.waiting.drain(..) {
+                    let _ = reply.send(status);
+                }
+            }
+            let command = if self.paused.is_some() {
+                match self.receiver.recv_timeout(PAUSED_POLL) {
+                    Ok(command) => command,
+                    Err(RecvTimeoutError::Timeout) if Arc::strong_count(&self.token) > 1 => {
+                        continue
+                    }
+                    Err(_) => {
+                        self.paused = None;
+                        return true;
+                    }
+                }
+            } else {
+                match self.receiver.try_recv() {
+                    Ok(command) => command,
+                    Err(TryRecvError::Empty | TryRecvError::Disconnected) => return true,
+                }
+            };
+
+            match command {
+                Command::Status(reply) => {
+                    let _ = reply.send(self.status(sim));
+                }
+                Command::WaitPaused(reply) => self.waiting.push(reply),
+                Command::Value(wire, reply) => {
+                    let _ = reply.send(
+                        sim.wire(wire)
+                            .map(|wire| wire.measure())
+                            .map_err(String::from),
+                    );
+                }
+                Command::Force(wire, pull, reply) => {
+                    let _ = reply.send(sim.force_wire(wire, pull).map_err(String::from));
+                }
+                Command::AddBreakpoint(condition, reply) => {
+                    let _ = reply.send(condition.evaluate(sim).map(|held| {
+                        self.added += 1;
+                        let id = BreakpointId(self.added);
+                        self.breakpoints.push(Breakpoint {
+                            id,
+                            condition,
+                            held,
+                        });
+                        id
+                    }));
+                }
+                Command::RemoveBreakpoint(id, reply) => {
+                    let count = self.breakpoints.len();
+                    self.breakpoints.retain(|breakpoint| breakpoint.id != id);
+                    let _ = reply.send(if self.breakpoints.len() < count {
+                        Ok(())
+                    } else {
+                        Err("No breakpoint found for the given ID".to_string())
+                    });
+                }
+                Command::Modify(Modification(modify)) => modify(sim, self.paused.is_some()),
+                Command::Pause => self.paused = self.paused.or(Some(None)),
+                Command::Resume => {
+                    // Take at least one step, so a pause requested straight after is not served first.
+                    if self.paused.take().is_some() {
+                        return true;
+                    }
+                }
+                Command::Stop => return false,
+            }
+        }
+    }
+
+    /// Obtain the status of the run.
+    ///
+    /// # Parameters
+    ///
+    /// - `sim`: The Simulation being run.
+    fn status(&self, sim: &Simulation) -> Status {
+        Status {
+            time: sim.time(),
+            paused: self.paused.is_some(),
+            breakpoint: self.paused.flatten(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::element::clocks::ClockGenerator;
+    use crate::element::gates::{Gate, GateKind};
+    use crate::time::SimDuration;
+    use crate::wire::Wire;
+    use std::thread;
+
+    /// Build a Simulation of a free-running clock, returning it and the Id of the clock Wire.
+    fn clock() -> (Simulation, WireId) {
+        let mut sim = Simulation::new(SimDuration::from_ticks(10));
+        let clk = sim.add_wire(Wire::new("CLK", WirePull::None)).unwrap();
+        let x1 = sim
+            .add_element(Box::new(
+                ClockGenerator::new("X1", 100.0, 0.5, 0.0, 0, 0.0).unwrap(),
+            ))
+            .unwrap();
+        sim.connect_output(sim.output_pin(x1, "CLK").unwrap(), clk)
+            .unwrap();
+        (sim, clk)
+    }
+
+    #[test]
+    fn controller_breakpoints() {
+        // GIVEN a running clock with a breakpoint on its rising edge
+        let (mut sim, clk) = clock();
+        let controller = sim.controller();
+        let run = thread::spawn(move || sim.run());
+        let breakpoint = controller.add_breakpoint(Condition::High(clk)).unwrap();
+        // WHEN the run pauses at the breakpoint
+        let first = controller.wait_paused().unwrap();
+        // THEN the clock is high, and the breakpoint is reported
+        assert_eq!(Some(breakpoint), first.breakpoint);
+        assert_eq!(1.0, f32::from(controller.value(clk).unwrap()));
+        assert_eq!(first, controller.status().unwrap());
+        // WHEN the run is resumed
+        controller.resume().unwrap();
+        let second = controller.wait_paused().unwrap();
+        // THEN it pauses again one clock period later
+        assert_eq!(first.time + 100, second.time);
+        // WHEN the breakpoint is removed and the run is resumed and stopped
+        controller.remove_breakpoint(breakpoint).unwrap();
+        assert!(controller.remove_breakpoint(breakpoint).is_err());
+        controller.resume().unwrap();
+        controller.stop().unwrap();
+        // THEN the run finishes, and the controller fails
+        assert_eq!(Ok(crate::sim::SimResult::Finished), run.join().unwrap());
+        assert_eq!(
+            Err("Simulation is no longer running".to_string()),
+            controller.status()
+        );
+    }
+    #[test]
+    fn controller_pause_and_force() {
+        // GIVEN a running clock, with a controller in another thread
+        let (mut sim, clk) = clock();
+        let controller = sim.controller();
+        let remote = controller.clone();
+        let run = thread::spawn(move || sim.run());
+        // WHEN it is paused, and its clock Wire forced low
+        remote.pause().unwrap();
+        let paused = controller.wait_paused().unwrap();
+        controller.force(clk, Some(WirePull::Down)).unwrap();
+        // THEN it stays paused without a breakpoint, and the force takes effect once resumed
+        assert_eq!(None, paused.breakpoint);
+        assert_eq!(paused.time, controller.status().unwrap().time);
+        assert!(controller.value(WireId::from(99)).is_err());
+        controller.resume().unwrap();
+        controller.pause().unwrap();
+        let resumed = controller.wait_paused().unwrap();
+        assert!(resumed.time > paused.time);
+        assert_eq!(0.0, f32::from(controller.value(clk).unwrap()));
+        controller.stop().unwrap();
+        run.join().unwrap().unwrap();
+    }
+    #[test]
+    fn controller_modify() {
+        // GIVEN a running clock
+        let (mut sim, clk) = clock();
+        let controller = sim.controller();
+        let run = thread::spawn(move || sim.run());
+        // WHEN it is modified before being paused
+        let early = controller.modify(|sim| Ok(sim.add_wire(Wire::new("/CLK", WirePull::None))?));
+        // THEN the modification fails
+        assert_eq!(
+            Err("Simulation must be paused to modify it".to_string()),
+            early
+        );
+        // WHEN it is paused, and an inverter is added to the clock
+        controller.pause().unwrap();
+        let paused = controller.wait_paused().unwrap();
+        let clk_bar = controller
+            .modify(move |sim| {
+                let clk_bar = sim.add_wire(Wire::new("/CLK", WirePull::None))?;
+                let u1 = sim.add_element(Box::new(Gate::new("U1", GateKind::Not, 1, 0)?))?;
+                sim.connect_input(clk, sim.input_pin(u1, "I0")?)?;
+                sim.connect_output(sim.output_pin(u1, "Y")?, clk_bar)?;
+                Ok(clk_bar)
+            })
+            .unwrap();
+        // THEN simulated time is kept, and once resumed the new Wire follows the clock inverted
+        assert_eq!(paused, controller.status().unwrap());
+        assert_eq!(0.5, f32::from(controller.value(clk_bar).unwrap()));
+        let inverted = Condition::All(vec![Condition::High(clk), Condition::Low(clk_bar)]);
+        controller.add_breakpoint(inverted).unwrap();
+        controller.resume().unwrap();
+        let high = controller.wait_paused().unwrap();
+        assert!(high.time > paused.time);
+        assert_eq!(0.0, f32::from(controller.value(clk_bar).unwrap()));
+        controller.stop().unwrap();
+        run.join().unwrap().unwrap();
+    }
+}
@@ -0,0 +1,243 @@
+//! Comparison of the states of two Simulations, such as two variants of a design, or a run and its replay.
+//!
+//! A [StateDiff] is obtained with [Simulation::diff](crate::sim::Simulation::diff).  Wires and Elements are matched by
+//! name, and pins by the names of their Elements and themselves as `element.pin`, so that Simulations built in a
+//! different order, or with components added or removed, can still be compared.  A component present in only one of
+//! the Simulations is reported as a difference, with the other side missing.
+
+use crate::ipin::InputPinState;
+use crate::opin::OutputPinState;
+use crate::prelude::*;
+use crate::wirevalue::WireValue;
+use alloc::collections::BTreeMap;
+use core::fmt;
+
+/// A difference in one named component between two Simulations.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Difference<T> {
+    /// Name of the component.
+    pub name: String,
+    /// State of the component in the first Simulation, or `None` if it is missing.
+    pub left: Option<T>,
+    /// State of the component in the second Simulation, or `None` if it is missing.
+    pub right: Option<T>,
+}
+
+/// The differences between the states of two Simulations, each list ordered by name.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StateDiff {
+    /// Simulation times of the first and second Simulations.
+    pub times: (u64, u64),
+    /// Wires with differing values.
+    pub wires: Vec<Difference<WireValue>>,
+    /// InputPins with differing states.
+    pub inputs: Vec<Difference<InputPinState>>,
+    /// OutputPins with differing states.
+    pub outputs: Vec<Difference<OutputPinState>>,
+    /// Elements with differing [internal states](crate::element::Element::state).
+    pub elements: Vec<Difference<String>>,
+}
+
+impl StateDiff {
+    /// Compare the states of two Simulations.
+    ///
+    /// # Parameters
+    ///
+    /// - `left`: State of the first Simulation.
+    /// - `right`: State of the second Simulation.
+    pub(crate) fn between(left: State, right: State) -> Self {
+        Self {
+            times: (left.time, right.time),
+            wires: compare(left.wires, right.wires),
+            inputs: compare(left.inputs, right.inputs),
+            outputs: compare(left.outputs, right.outputs),
+            elements: compare(left.elements, right.elements),
+        }
+    }
+
+    /// Query whether the Simulations are at the same time and in the same state.
+    pub fn is_empty(&self) -> bool {
+        self.times.0 == self.times.1
+            && self.wires.is_empty()
+            && self.inputs.is_empty()
+            && self.outputs.is_empty()
+            && self.elements.is_empty()
+    }
+}
+
+impl fmt::Display for StateDiff {
+    /// Describe each difference on a line of its own, or that there are none.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_empty() {
+            return write!(f, "no differences");
+        }
+
+        let mut lines = Vec::new();
+        if self.times.0 != self.times.1 {
+            lines.push(format!("time: {} != {}", self.times.0, self.times.1));
+        }
+        for wire in &self.wires {
+            lines.push(describe("wire", wire, |value| {
+                f32::from(*value).to_string()
+            }));
+        }
+        for input in &self.inputs {
+            lines.push(describe("input", input, |state| format!("{state:?}")));
+        }
+        for output in &self.outputs {
+            lines.push(describe("output", output, |state| format!("{state:?}")));
+        }
+        for element in &self.elements {
+            lines.push(if element.left.is_some() && element.right.is_some() {
+                format!("element \"{}\": state differs", element.name)
+            } else {
+                describe("element", element, |_| "present".to_string())
+            });
+        }
+        write!(f, "{}", lines.join("\n"))
+    }
+}
+
+/// The state of a Simulation, keyed by the names of its components.
+#[derive(Debug, Default)]
+pub(crate) struct State {
+    /// Simulation time.
+    pub(crate) time: u64,
+    /// Wire values.
+    pub(crate) wires: BTreeMap<String, WireValue>,
+    /// InputPin states, keyed as `element.pin`.
+    pub(crate) inputs: BTreeMap<String, InputPinState>,
+    /// OutputPin states, keyed as `element.pin`.
+    pub(crate) outputs: BTreeMap<String, OutputPinState>,
+    /// Element internal states.
+    pub(crate) elements: BTreeMap<String, String>,
+}
+
+/// Find the components whose states differ between two Simulations, or which are present in only one of them.
+///
+/// # Parameters
+///
+/// - `left`: States of the components of the first Simulation.
+/// - `right`: States of the components of the second Simulation.
+fn compare<T: PartialEq>(
+    mut left: BTreeMap<String, T>,
+    mut right: BTreeMap<String, T>,
+) -> Vec<Difference<T>> {
+    let mut names: Vec<String> = left.keys().chain(right.keys()).cloned().collect();
+    names.sort();
+    names.dedup();
+
+    names
+        .into_iter()
+        .filter_map(|name| {
+            let (left, right) = (left.remove(&name), right.remove(&name));
+            (left != right).then_some(Difference { name, left, right })
+        })
+        .collect()
+}
+
+/// Describe a difference as a line of a report.
+///
+/// # Parameters
+///
+/// - `kind`: Kind of component.
+/// - `difference`: The difference.
+/// - `show`: Function describing a state.
+fn describe<T>(kind: &str, difference: &Difference<T>, show: impl Fn(&T) -> String) -> String {
+    let side = |state: &Option<T>| state.as_ref().map_or("missing".to_string(), &show);
+    format!(
+        "{kind} \"{}\": {} != {}",
+        difference.name,
+        side(&difference.left),
+        side(&difference.right)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::element::clocks::ClockGenerator;
+    use crate::element::gates::{Gate, GateKind};
+    use crate::ipin::InputPinState;
+    use crate::prelude::*;
+    use crate::sim::Simulation;
+    use crate::time::SimDuration;
+    use crate::wire::{Wire, WirePull};
+
+    /// Build a Simulation of a clock with the given period driving an inverter, optionally with a spare Wire.
+    fn build(period: f64, spare: bool) -> Simulation {
+        let mut sim = Simulation::new(SimDuration::from_ticks(10));
+        let clk = sim.add_wire(Wire::new("CLK", WirePull::None)).unwrap();
+        let clk_bar = sim.add_wire(Wire::new("/CLK", WirePull::None)).unwrap();
+        if spare {
+            sim.add_wire(Wire::new("SPARE", WirePull::Up)).unwrap();
+        }
+        let x1 = sim
+            .add_element(Box::new(
+                ClockGenerator::new("X1", period, 0.5, 0.0, 0, 0.0).unwrap(),
+            ))
+            .unwrap();
+        let u1 = sim
+            .add_element(Box::new(Gate::new("U1", GateKind::Not, 1, 0).unwrap()))
+            .unwrap();
+        sim.connect_output(sim.output_pin(x1, "CLK").unwrap(), clk)
+            .unwrap();
+        sim.connect_input(clk, sim.input_pin(u1, "I0").unwrap())
+            .unwrap();
+        sim.connect_output(sim.output_pin(u1, "Y").unwrap(), clk_bar)
+            .unwrap();
+        sim
+    }
+
+    #[test]
+    fn diff_identical() {
+        // GIVEN a Simulation and its replay
+        let mut run = build(100.0, false);
+        let mut replay = build(100.0, false);
+        // WHEN both are stepped to the same time and compared
+        for _ in 0..7 {
+            run.step().unwrap();
+            replay.step().unwrap();
+        }
+        let diff = run.diff(&replay);
+        // THEN there are no differences
+        assert!(diff.is_empty());
+        assert_eq!("no differences", diff.to_string());
+    }
+    #[test]
+    fn diff_variants() {
+        // GIVEN two variants of a design, with clocks of different periods, one with a spare Wire
+        let mut a = build(100.0, false);
+        let mut b = build(60.0, true);
+        // WHEN both are stepped until their clocks disagree, and compared
+        for _ in 0..5 {
+            a.step().unwrap();
+            b.step().unwrap();
+        }
+        let diff = a.diff(&b);
+        // THEN the differing Wires, pins and Elements are reported by name
+        assert_eq!((50, 50), diff.times);
+        let wires: Vec<&str> = diff.wires.iter().map(|d| d.name.as_str()).collect();
+        assert_eq!(vec!["/CLK", "CLK", "SPARE"], wires);
+        assert_eq!(None, diff.wires[2].left);
+        assert_eq!("U1.I0", diff.inputs[0].name);
+        assert_eq!(Some(InputPinState::High), diff.inputs[0].left);
+        assert_eq!(Some(InputPinState::Low), diff.inputs[0].right);
+        assert_eq!(vec!["U1.Y", "X1.CLK"], {
+            diff.outputs
+                .iter()
+                .map(|d| d.name.as_str())
+                .collect::<Vec<_>>()
+        });
+        assert_eq!("X1", diff.elements[0].name);
+        assert_eq!(
+            "wire \"/CLK\": 0 != 1\n\
+             wire \"CLK\": 1 != 0\n\
+             wire \"SPARE\": missing != 1\n\
+             input \"U1.I0\": High != Low\n\
+             output \"U1.Y\": Low != High\n\
+             output \"X1.CLK\": High != Low\n\
+             element \"X1\": state differs",
+            diff.to_string()
+        );
+    }
+}
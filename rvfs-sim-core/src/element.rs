@@ -0,0 +1,586 @@
+//! Elements are the logic components of a Simulation, calculating output drive states from input logic states.
+
+pub mod buffers;
+pub mod buses;
+pub mod clocks;
+#[cfg(feature = "std")]
+pub mod cosim;
+pub mod counters;
+pub mod custom;
+pub mod decoders;
+#[cfg(feature = "std")]
+pub mod emulator;
+#[cfg(feature = "std")]
+pub mod external;
+pub mod flipflops;
+pub mod gates;
+#[cfg(feature = "std")]
+pub mod gpio;
+#[cfg(feature = "std")]
+pub mod i2c;
+#[cfg(feature = "std")]
+pub mod indicators;
+pub mod latches;
+#[cfg(feature = "std")]
+pub mod memory;
+pub mod mux;
+#[cfg(feature = "plugins")]
+pub mod plugins;
+pub mod processors;
+pub mod pwm;
+pub mod registers;
+#[cfg(any(feature = "lua", feature = "rhai"))]
+pub mod scripts;
+#[cfg(feature = "std")]
+pub mod switches;
+pub mod timers;
+#[cfg(feature = "std")]
+pub mod uart;
+#[cfg(feature = "verilator")]
+pub mod verilator;
+#[cfg(feature = "std")]
+pub mod waveforms;
+
+use crate::ipin::{InputPin, InputPinState};
+use crate::opin::{OutputPin, OutputPinState};
+use crate::prelude::*;
+use crate::rng::SimRng;
+use crate::sim::SimResult;
+use crate::time::TICKS_PER_SECOND;
+use alloc::collections::BTreeMap;
+use core::fmt;
+use core::str::FromStr;
+
+/// Number of simulation time units in a second, when converting between periods and frequencies.
+pub(crate) const UNITS_PER_SECOND: f64 = TICKS_PER_SECOND as f64;
+
+/// A logic component of a Simulation.
+///
+/// An Element declares its pins when it is [added](crate::sim::Simulation::add_element) to a Simulation, which then
+/// owns them and connects them to Wires.  On each step the Element is handed its InputPins, freshly sampled from their
+/// Wires, and sets the next states of its OutputPins.
+pub trait Element: Send + fmt::Debug {
+    /// Obtain the name of the Element.
+    fn name(&self) -> &str;
+
+    /// Create the InputPins of the Element, in the order in which they will be passed to [step](Self::step).
+    fn input_pins(&self) -> Vec<InputPin>;
+
+    /// Create the OutputPins of the Element, in the order in which they will be passed to [step](Self::step).
+    fn output_pins(&self) -> Vec<OutputPin>;
+
+    /// Recalculate the outputs of the Element.
+    ///
+    /// Outputs are usually set with [OutputPin::drive], so that an unchanged output does not restart its propagation
+    /// delay.
+    ///
+    /// # Parameters
+    ///
+    /// - `inputs`: The InputPins of the Element.
+    /// - `outputs`: The OutputPins of the Element.
+    /// - `delta_t`: The simulation time elapsed since the last step.
+    fn step(
+        &mut self,
+        inputs: &[InputPin],
+        outputs: &mut [OutputPin],
+        delta_t: u64,
+    ) -> Result<SimResult, String>;
+
+    /// Complete the Element at the end of a [run](crate::sim::Simulation::run), such as by writing out its state.
+    ///
+    /// The default implementation does nothing.
+    fn finish(&mut self) -> Result<(), String> {
+        Ok(())
+    }
+
+    /// Hand the Element the random number generator of its Simulation, from which any pseudo-random behaviour must be
+    /// derived so that runs can be reproduced from the Simulation's seed.
+    ///
+    /// This is called when the Element is added to a Simulation, and again whenever the Simulation's seed is changed.
+    /// The default implementation ignores the generator.
+    ///
+    /// # Parameters
+    ///
+    /// - `rng`: The generator, from which the Element should [fork](SimRng::fork) a stream of its own.
+    fn set_rng(&mut self, _rng: &SimRng) {}
+
+    /// Query whether the Element is combinational, its outputs depending only on the present states of its inputs, so
+    /// that the [event-driven](crate::sim::Scheduler::EventDriven) scheduler need only step it when an input changes.
+    ///
+    /// The default implementation returns false, so that the Element is stepped on every step.
+    fn is_combinational(&self) -> bool {
+        false
+    }
+
+    /// Query whether the Element can be replayed when a Simulation [steps back](crate::sim::Simulation::step_back).
+    ///
+    /// An Element which shares state with its copies, such as a host handle or an external process, must return false,
+    /// since replaying its steps would repeat their effects on that state.  The default implementation returns true.
+    fn is_replayable(&self) -> bool {
+        true
+    }
+
+    /// Query whether the Element is a synchroniser, which may safely sample signals from another clock domain.
+    ///
+    /// The default implementation returns false.
+    fn is_synchroniser(&self) -> bool {
+        false
+    }
+
+    /// Describe the Element's internal state, so that it can be [compared](crate::sim::Simulation::diff) between
+    /// Simulations.
+    ///
+    /// The default implementation describes the Element by its Debug representation.
+    fn state(&self) -> String {
+        format!("{self:?}")
+    }
+
+    /// Save the Element's internal state into a [snapshot](crate::snapshot), from which it can be
+    /// [restored](Self::restore_state) into a copy of the Element created with the same parameters.
+    ///
+    /// The default implementation saves nothing for a combinational Element, which has no state, and refuses to save
+    /// any other Element.
+    #[cfg(feature = "serde")]
+    fn save_state(&self) -> Result<serde_json::Value, String> {
+        if self.is_combinational() {
+            Ok(serde_json::Value::Null)
+        } else {
+            Err(format!(
+                "Element \"{}\" cannot be saved in a snapshot",
+                self.name()
+            ))
+        }
+    }
+
+    /// Restore the Element's internal state from a [snapshot](crate::snapshot), as [saved](Self::save_state) by a copy
+    /// of the Element created with the same parameters.
+    ///
+    /// The default implementation accepts the empty state saved for a combinational Element.
+    ///
+    /// # Parameters
+    ///
+    /// - `state`: The saved state.
+    #[cfg(feature = "serde")]
+    fn restore_state(&mut self, state: serde_json::Value) -> Result<(), String> {
+        if self.is_combinational() && state.is_null() {
+            Ok(())
+        } else {
+            Err(format!(
+                "Element \"{}\" cannot be restored from a snapshot",
+                self.name()
+            ))
+        }
+    }
+
+    /// Create a boxed copy of the Element, so Simulations holding it can be checkpointed.
+    fn box_clone(&self) -> Box<dyn Element>;
+}
+
+impl Clone for Box<dyn Element> {
+    fn clone(&self) -> Self {
+        self.box_clone()
+    }
+}
+
+/// Named parameters used to instantiate an Element, as given in a configuration file.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Parameters {
+    /// Parameter values, keyed by name.
+    values: BTreeMap<String, String>,
+}
+
+impl Parameters {
+    /// Create a new, empty set of Parameters.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a parameter value, replacing any previous value.
+    ///
+    /// # Parameters
+    ///
+    /// - `name`: Name of the parameter.
+    /// - `value`: Textual value of the parameter.
+    pub fn with(mut self, name: &str, value: &str) -> Self {
+        self.values.insert(name.to_string(), value.to_string());
+        self
+    }
+
+    /// Obtain a parameter value, or a default if it is absent.
+    ///
+    /// # Parameters
+    ///
+    /// - `name`: Name of the parameter.
+    /// - `default`: Value to use if the parameter is absent.
+    pub fn get_or<T: FromStr>(&self, name: &str, default: T) -> Result<T, String> {
+        match self.values.get(name) {
+            Some(value) => value
+                .parse()
+                .map_err(|_| format!("Invalid value \"{value}\" for parameter \"{name}\"")),
+            None => Ok(default),
+        }
+    }
+}
+
+/// A function which instantiates an Element from its name and parameters.
+pub type Constructor = fn(name: &str, parameters: &Parameters) -> Result<Box<dyn Element>, String>;
+
+/// A registry of Element kinds, so that Elements can be instantiated by kind name from configuration files.
+///
+/// # Example
+///
+/// ```
+/// # use rvfs_sim_core::element::{Parameters, Registry};
+/// let registry = Registry::standard();
+/// let parameters = Parameters::new().with("inputs", "3").with("delay", "5");
+/// let gate = registry.create("nand", "U1", &parameters).unwrap();
+///
+/// assert_eq!("U1", gate.name());
+/// assert_eq!(3, gate.input_pins().len());
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Registry {
+    /// Constructors, keyed by kind name.
+    constructors: BTreeMap<String, Constructor>,
+}
+
+impl Registry {
+    /// Create a new, empty Registry.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a Registry holding the standard Element library.
+    pub fn standard() -> Self {
+        let mut registry = Self::new();
+        gates::register(&mut registry);
+        #[cfg(feature = "std")]
+        gpio::register(&mut registry);
+        buffers::register(&mut registry);
+        buses::register(&mut registry);
+        clocks::register(&mut registry);
+        counters::register(&mut registry);
+        decoders::register(&mut registry);
+        #[cfg(feature = "std")]
+        emulator::register(&mut registry);
+        #[cfg(feature = "std")]
+        external::register(&mut registry);
+        flipflops::register(&mut registry);
+        #[cfg(feature = "std")]
+        i2c::register(&mut registry);
+        #[cfg(feature = "std")]
+        indicators::register(&mut registry);
+        latches::register(&mut registry);
+        #[cfg(feature = "std")]
+        memory::register(&mut registry);
+        mux::register(&mut registry);
+        processors::register(&mut registry);
+        pwm::register(&mut registry);
+        registers::register(&mut registry);
+        #[cfg(any(feature = "lua", feature = "rhai"))]
+        scripts::register(&mut registry);
+        #[cfg(feature = "std")]
+        switches::register(&mut registry);
+        timers::register(&mut registry);
+        #[cfg(feature = "std")]
+        uart::register(&mut registry);
+        #[cfg(feature = "verilator")]
+        verilator::register(&mut registry);
+        #[cfg(feature = "std")]
+        waveforms::register(&mut registry);
+        registry
+    }
+
+    /// Register a kind of Element, replacing any existing kind of the same name.
+    ///
+    /// # Parameters
+    ///
+    /// - `kind`: Name of the kind of Element.
+    /// - `constructor`: Function which instantiates the kind of Element.
+    pub fn register(&mut self, kind: &str, constructor: Constructor) {
+        self.constructors.insert(kind.to_string(), constructor);
+    }
+
+    /// Obtain the names of all registered kinds, in alphabetical order.
+    pub fn kinds(&self) -> impl Iterator<Item = &str> {
+        self.constructors.keys().map(String::as_str)
+    }
+
+    /// Instantiate an Element of a registered kind.
+    ///
+    /// # Parameters
+    ///
+    /// - `kind`: Name of the kind of Element.
+    /// - `name`: Name to give the Element.
+    /// - `parameters`: Parameters of the Element.
+    pub fn create(
+        &self,
+        kind: &str,
+        name: &str,
+        parameters: &Parameters,
+    ) -> Result<Box<dyn Element>, String> {
+        let constructor = self
+            .constructors
+            .get(kind)
+            .ok_or(format!("Unknown element kind \"{kind}\""))?;
+        constructor(name, parameters)
+    }
+}
+
+/// Save the state of an Element for a snapshot, as [Element::save_state] does.
+///
+/// # Parameters
+///
+/// - `state`: The fields holding the state.
+#[cfg(feature = "serde")]
+pub(crate) fn save_state<T: serde::Serialize>(state: T) -> Result<serde_json::Value, String> {
+    serde_json::to_value(state).map_err(|err| err.to_string())
+}
+
+/// Restore the state of an Element from a snapshot, as [Element::restore_state] does.
+///
+/// # Parameters
+///
+/// - `name`: Name of the Element, for reporting.
+/// - `state`: The saved state.
+#[cfg(feature = "serde")]
+pub(crate) fn restore_state<T: serde::de::DeserializeOwned>(
+    name: &str,
+    state: serde_json::Value,
+) -> Result<T, String> {
+    serde_json::from_value(state)
+        .map_err(|err| format!("Element \"{name}\": invalid saved state: {err}"))
+}
+
+/// Convert a bit to the state which drives it onto a Wire.
+///
+/// An unknown bit drives the output unknown, so that the Wire it drives reads as X rather than show a level the Element
+/// cannot vouch for, and Elements reading that Wire pass the X on.
+///
+/// # Parameters
+///
+/// - `bit`: The bit, if known.
+pub(crate) fn level(bit: Option<bool>) -> OutputPinState {
+    match bit {
+        Some(true) => OutputPinState::High,
+        Some(false) => OutputPinState::Low,
+        None => OutputPinState::Unknown,
+    }
+}
+
+/// Convert a bit to the state which drives it onto a tri-state output, such as one on a shared bus.
+///
+/// Unlike [level], having no bit to drive releases the output, so that another Element can drive the Wire.
+///
+/// # Parameters
+///
+/// - `bit`: The bit to drive, if any.
+pub(crate) fn tristate(bit: Option<bool>) -> OutputPinState {
+    bit.map_or(OutputPinState::HighImpedance, |bit| level(Some(bit)))
+}
+
+/// Drive a stored bit onto a pair of complementary outputs.
+///
+/// An unknown bit drives both outputs unknown, as for [level].
+///
+/// # Parameters
+///
+/// - `state`: The stored bit, if known.
+/// - `q`: The true output.
+/// - `q_bar`: The complementary output.
+pub(crate) fn drive_outputs(state: Option<bool>, q: &mut OutputPin, q_bar: &mut OutputPin) {
+    q.drive(level(state));
+    q_bar.drive(level(state.map(|state| !state)));
+}
+
+/// Obtain the number of address bits needed to select one of a number of items.
+///
+/// # Parameters
+///
+/// - `count`: Number of items, which must be at least one.
+pub(crate) fn address_bits(count: usize) -> usize {
+    (usize::BITS - (count - 1).leading_zeros()) as usize
+}
+
+/// Convert the logic state of an InputPin to a bit, if it is definite.
+///
+/// # Parameters
+///
+/// - `pin`: The InputPin.
+pub(crate) fn bit(pin: &InputPin) -> Option<bool> {
+    match pin.state() {
+        InputPinState::High => Some(true),
+        InputPinState::Low => Some(false),
+        InputPinState::Indeterminate => None,
+    }
+}
+
+/// Convert the logic states of a set of InputPins to a word, least significant bit first, if every state is definite.
+///
+/// # Parameters
+///
+/// - `pins`: The InputPins, of which there must be no more than 64.
+pub(crate) fn word(pins: &[InputPin]) -> Option<u64> {
+    pins.iter()
+        .enumerate()
+        .try_fold(0, |word, (i, pin)| Some(word | (u64::from(bit(pin)?) << i)))
+}
+
+/// Helpers for testing Elements.
+#[cfg(test)]
+pub(crate) mod testing {
+    use crate::element::Element;
+    use crate::ipin::InputPin;
+    use crate::opin::{OutputPin, OutputPinState};
+    use crate::prelude::*;
+    use crate::time::SimDuration;
+    use crate::wirevalue::WireValue;
+
+    /// Build a set of InputPins which have sampled the given levels, after first sampling the given previous levels.
+    pub(crate) fn inputs<const N: usize>(previous: [f32; N], levels: [f32; N]) -> Vec<InputPin> {
+        previous
+            .into_iter()
+            .zip(levels)
+            .map(|(previous, level)| {
+                let mut pin = InputPin::new("I");
+                pin.step(WireValue::new(previous));
+                pin.step(WireValue::new(level));
+                pin
+            })
+            .collect()
+    }
+
+    /// Build a set of InputPins which have steadily sampled the given levels.
+    pub(crate) fn steady<const N: usize>(levels: [f32; N]) -> Vec<InputPin> {
+        inputs(levels, levels)
+    }
+
+    /// Step an Element once by 10 ticks and let its outputs propagate for as long, returning the output states.
+    pub(crate) fn step(
+        element: &mut dyn Element,
+        outputs: &mut [OutputPin],
+        inputs: &[InputPin],
+    ) -> Vec<OutputPinState> {
+        element.step(inputs, outputs, 10).unwrap();
+        outputs
+            .iter_mut()
+            .map(|output| {
+                output.step(SimDuration::from_ticks(10));
+                output.state()
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parameters_get_or() {
+        // GIVEN parameters with a valid and an invalid value
+        let parameters = Parameters::new().with("inputs", "3").with("delay", "soon");
+        // WHEN values are obtained
+        // THEN present values are parsed, absent values take the default and unparseable values are errors
+        assert_eq!(Ok(3), parameters.get_or("inputs", 2usize));
+        assert_eq!(Ok(7), parameters.get_or("width", 7u32));
+        assert!(parameters.get_or("delay", 0u64).is_err());
+    }
+    #[test]
+    fn registry_unknown_kind() {
+        // GIVEN the standard registry
+        let registry = Registry::standard();
+        // WHEN an unknown kind is created
+        let result = registry.create("flux_capacitor", "U1", &Parameters::new());
+        // THEN the result is an error
+        assert!(result.is_err());
+    }
+    #[test]
+    fn registry_standard_kinds() {
+        // GIVEN the standard registry
+        let registry = Registry::standard();
+        // WHEN its kinds are listed
+        let kinds: Vec<&str> = registry.kinds().collect();
+        // THEN every standard kind of Element is present, along with those of enabled features
+        let mut expected = vec![
+            "and",
+            "buffer",
+            "bus_fabric",
+            "clock",
+            "clock_divider",
+            "counter",
+            "decoder",
+            "demux",
+            "dff",
+            "dlatch",
+            "jkff",
+            "mos6502",
+            "mux",
+            "nand",
+            "nor",
+            "not",
+            "or",
+            "priority_encoder",
+            "pwm",
+            "rv32i",
+            "shift_register",
+            "srlatch",
+            "synchroniser",
+            "tff",
+            "timer",
+            "transceiver",
+            "xnor",
+            "xor",
+        ];
+        if cfg!(feature = "std") {
+            expected.extend([
+                "button",
+                "emulator",
+                "external",
+                "gpio",
+                "i2c_master",
+                "i2c_registers",
+                "led",
+                "rom",
+                "sram",
+                "switch",
+                "uart",
+                "wavedrom",
+            ]);
+        }
+        if cfg!(feature = "lua") {
+            expected.push("lua");
+        }
+        if cfg!(feature = "rhai") {
+            expected.push("rhai");
+        }
+        if cfg!(feature = "verilator") {
+            expected.push("verilator");
+        }
+        expected.sort_unstable();
+        assert_eq!(expected, kinds);
+    }
+    #[test]
+    fn address_bit_counts() {
+        // GIVEN item counts which are and are not powers of two
+        // WHEN the number of address bits is calculated
+        // THEN it is enough to address every item
+        assert_eq!(1, address_bits(2));
+        assert_eq!(2, address_bits(3));
+        assert_eq!(2, address_bits(4));
+        assert_eq!(3, address_bits(5));
+        assert_eq!(4, address_bits(16));
+    }
+    #[test]
+    fn word_from_pins() {
+        // GIVEN sets of pins with definite and indeterminate levels
+        // WHEN they are converted to words
+        // THEN the first pin is the least significant bit, and any indeterminate pin makes the word unknown
+        assert_eq!(
+            Some(0b110),
+            word(&testing::inputs([0.0; 3], [0.0, 1.0, 1.0]))
+        );
+        assert_eq!(None, word(&testing::inputs([0.0; 3], [0.0, 0.5, 1.0])));
+        assert_eq!(Some(0), word(&[]));
+    }
+}
@@ -0,0 +1,443 @@
+//! Tri-state buffers and bus transceivers.
+//!
+//! Their outputs are released to the HighImpedance state while they are disabled, so that several of them can be
+//! [connected](crate::sim::Simulation::connect_output) to the same Wire to form a shared bus, provided only one is
+//! enabled at a time.
+
+use crate::element::{bit, level, Element, Parameters, Registry};
+use crate::ipin::{InputPin, InputPinState};
+use crate::opin::{OutputPin, OutputPinState};
+use crate::prelude::*;
+use crate::sim::SimResult;
+use crate::time::SimDuration;
+
+/// Default width of an element instantiated from a configuration file.
+const DEFAULT_WIDTH: usize = 8;
+
+/// A tri-state buffer with an active-low output enable, modelled on one half of a 74244.
+///
+/// The inputs are named `/OE` and `A0` to `An`, and the outputs are named `Y0` to `Yn`.  While `/OE` is low the
+/// outputs follow `A0` to `An`, and otherwise they are released.  An indeterminate input makes its output unknown (X).
+///
+/// # Example
+///
+/// ```
+/// # use rvfs_sim_core::element::buffers::Buffer;
+/// # use rvfs_sim_core::element::Element;
+/// let buffer = Buffer::new("U1", 4, 0).unwrap();
+///
+/// assert_eq!("/OE", buffer.input_pins()[0].name());
+/// assert_eq!(4, buffer.output_pins().len());
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Buffer {
+    /// Name of the buffer.
+    name: String,
+    /// Number of bits.
+    width: usize,
+    /// Propagation delay from an input to the outputs.
+    delay: u64,
+}
+
+impl Buffer {
+    /// Create a new Buffer.
+    ///
+    /// # Parameters
+    ///
+    /// - `name`: Name of the buffer.
+    /// - `width`: Number of bits, which must be at least one.
+    /// - `delay`: Propagation delay from an input to the outputs.
+    pub fn new(name: &str, width: usize, delay: u64) -> Result<Self, String> {
+        if width == 0 {
+            return Err(format!("Buffer \"{name}\": width must be at least one bit"));
+        }
+
+        Ok(Self {
+            name: name.to_string(),
+            width,
+            delay,
+        })
+    }
+}
+
+impl Element for Buffer {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn input_pins(&self) -> Vec<InputPin> {
+        ["/OE".to_string()]
+            .into_iter()
+            .chain((0..self.width).map(|i| format!("A{i}")))
+            .map(|name| InputPin::new(&name))
+            .collect()
+    }
+
+    fn output_pins(&self) -> Vec<OutputPin> {
+        (0..self.width)
+            .map(|i| {
+                OutputPin::new(
+                    &format!("Y{i}"),
+                    SimDuration::from_ticks(self.delay),
+                    OutputPinState::HighImpedance,
+                )
+            })
+            .collect()
+    }
+
+    fn step(
+        &mut self,
+        inputs: &[InputPin],
+        outputs: &mut [OutputPin],
+        _delta_t: u64,
+    ) -> Result<SimResult, String> {
+        let [enable, data @ ..] = inputs else {
+            return Err(format!("Buffer \"{}\": unexpected pins", self.name));
+        };
+        if data.len() != self.width || outputs.len() != self.width {
+            return Err(format!("Buffer \"{}\": unexpected pins", self.name));
+        }
+
+        let enabled = enable.state() == InputPinState::Low;
+        for (input, output) in data.iter().zip(outputs) {
+            output.drive(if enabled {
+                level(bit(input))
+            } else {
+                OutputPinState::HighImpedance
+            });
+        }
+
+        Ok(SimResult::Continuing)
+    }
+
+    fn is_combinational(&self) -> bool {
+        true
+    }
+
+    fn box_clone(&self) -> Box<dyn Element> {
+        Box::new(self.clone())
+    }
+}
+
+/// A bidirectional bus transceiver with direction and active-low output enable pins, modelled on the 74245.
+///
+/// The inputs are named `DIR`, `/OE`, `A0` to `An` and `B0` to `Bn`, and the outputs are also named `A0` to `An` and
+/// `B0` to `Bn`.  Each bit of each port has both an InputPin and an OutputPin, which should both be connected to the
+/// same Wire.  While `/OE` is low, the B port follows the A port if `DIR` is high, and the A port follows the B port
+/// if `DIR` is low.  The port being read, and both ports while `/OE` is not low, are released.
+///
+/// An indeterminate input of the port being read makes its output unknown, and an indeterminate `DIR` makes every
+/// output unknown (X).
+#[derive(Debug, Clone, PartialEq)]
+pub struct Transceiver {
+    /// Name of the transceiver.
+    name: String,
+    /// Number of bits in each port.
+    width: usize,
+    /// Propagation delay from an input to the outputs.
+    delay: u64,
+}
+
+impl Transceiver {
+    /// Create a new Transceiver.
+    ///
+    /// # Parameters
+    ///
+    /// - `name`: Name of the transceiver.
+    /// - `width`: Number of bits in each port, which must be at least one.
+    /// - `delay`: Propagation delay from an input to the outputs.
+    pub fn new(name: &str, width: usize, delay: u64) -> Result<Self, String> {
+        if width == 0 {
+            return Err(format!(
+                "Transceiver \"{name}\": width must be at least one bit"
+            ));
+        }
+
+        Ok(Self {
+            name: name.to_string(),
+            width,
+            delay,
+        })
+    }
+
+    /// Obtain the names of the pins of both ports, A port first.
+    fn port_names(&self) -> impl Iterator<Item = String> + '_ {
+        ["A", "B"]
+            .into_iter()
+            .flat_map(|port| (0..self.width).map(move |i| format!("{port}{i}")))
+    }
+}
+
+impl Element for Transceiver {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn input_pins(&self) -> Vec<InputPin> {
+        ["DIR".to_string(), "/OE".to_string()]
+            .into_iter()
+            .chain(self.port_names())
+            .map(|name| InputPin::new(&name))
+            .collect()
+    }
+
+    fn output_pins(&self) -> Vec<OutputPin> {
+        self.port_names()
+            .map(|name| {
+                OutputPin::new(
+                    &name,
+                    SimDuration::from_ticks(self.delay),
+                    OutputPinState::HighImpedance,
+                )
+            })
+            .collect()
+    }
+
+    fn step(
+        &mut self,
+        inputs: &[InputPin],
+        outputs: &mut [OutputPin],
+        _delta_t: u64,
+    ) -> Result<SimResult, String> {
+        let [direction, enable, ports @ ..] = inputs else {
+            return Err(format!("Transceiver \"{}\": unexpected pins", self.name));
+        };
+        if ports.len() != 2 * self.width || outputs.len() != 2 * self.width {
+            return Err(format!("Transceiver \"{}\": unexpected pins", self.name));
+        }
+        let (a_in, b_in) = ports.split_at(self.width);
+        let (a_out, b_out) = outputs.split_at_mut(self.width);
+
+        let (a_source, b_source) = match (enable.state(), direction.state()) {
+            (InputPinState::Low, InputPinState::High) => (None, Some(a_in)),
+            (InputPinState::Low, InputPinState::Low) => (Some(b_in), None),
+            _ => (None, None),
+        };
+        drive_port(a_out, a_source);
+        drive_port(b_out, b_source);
+
+        Ok(SimResult::Continuing)
+    }
+
+    fn is_combinational(&self) -> bool {
+        true
+    }
+
+    fn box_clone(&self) -> Box<dyn Element> {
+        Box::new(self.clone())
+    }
+}
+
+/// Drive one port of a transceiver from the other, or release it.
+///
+/// # Parameters
+///
+/// - `outputs`: The OutputPins of the port to drive.
+/// - `source`: The InputPins of the opposite port, or `None` to release the port.
+fn drive_port(outputs: &mut [OutputPin], source: Option<&[InputPin]>) {
+    for (i, output) in outputs.iter_mut().enumerate() {
+        output.drive(match source {
+            Some(source) => level(bit(&source[i])),
+            None => OutputPinState::HighImpedance,
+        });
+    }
+}
+
+/// Register the buffer and transceiver, taking the `width` (default 8) and `delay` (default 0) parameters.
+///
+/// # Parameters
+///
+/// - `registry`: The Registry to add the elements to.
+pub fn register(registry: &mut Registry) {
+    registry.register("buffer", |name, parameters: &Parameters| {
+        Ok(Box::new(Buffer::new(
+            name,
+            parameters.get_or("width", DEFAULT_WIDTH)?,
+            parameters.get_or("delay", 0)?,
+        )?))
+    });
+    registry.register("transceiver", |name, parameters: &Parameters| {
+        Ok(Box::new(Transceiver::new(
+            name,
+            parameters.get_or("width", DEFAULT_WIDTH)?,
+            parameters.get_or("delay", 0)?,
+        )?))
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::element::testing::{steady, step};
+    use crate::sim::Simulation;
+    use crate::wire::{Wire, WirePull};
+    use crate::wirevalue::LogicValue;
+    use crate::WireId;
+
+    const L: OutputPinState = OutputPinState::Low;
+    const H: OutputPinState = OutputPinState::High;
+    const Z: OutputPinState = OutputPinState::HighImpedance;
+    const X: OutputPinState = OutputPinState::Unknown;
+
+    #[test]
+    fn buffer_output_enable() {
+        // GIVEN a two bit buffer
+        let mut buffer = Buffer::new("U1", 2, 0).unwrap();
+        let mut outputs = buffer.output_pins();
+        // WHEN its outputs are disabled
+        // THEN they are released
+        assert_eq!(
+            vec![Z, Z],
+            step(&mut buffer, &mut outputs, &steady([1.0, 1.0, 0.0]))
+        );
+        // WHEN its outputs are enabled
+        // THEN they follow the inputs, and an indeterminate input drives its output unknown
+        assert_eq!(
+            vec![H, L],
+            step(&mut buffer, &mut outputs, &steady([0.0, 1.0, 0.0]))
+        );
+        assert_eq!(
+            vec![X, L],
+            step(&mut buffer, &mut outputs, &steady([0.0, 0.5, 0.0]))
+        );
+    }
+    #[test]
+    fn transceiver_direction() {
+        // GIVEN a two bit transceiver
+        let mut transceiver = Transceiver::new("U1", 2, 0).unwrap();
+        let mut outputs = transceiver.output_pins();
+        // WHEN DIR is high
+        // THEN the B port follows the A port
+        assert_eq!(
+            vec![Z, Z, H, L],
+            step(
+                &mut transceiver,
+                &mut outputs,
+                &steady([1.0, 0.0, 1.0, 0.0, 0.0, 1.0])
+            )
+        );
+        // WHEN DIR is low
+        // THEN the A port follows the B port
+        assert_eq!(
+            vec![L, H, Z, Z],
+            step(
+                &mut transceiver,
+                &mut outputs,
+                &steady([0.0, 0.0, 1.0, 0.0, 0.0, 1.0])
+            )
+        );
+        // WHEN /OE is high or DIR is indeterminate
+        // THEN both ports are released
+        assert_eq!(
+            vec![Z, Z, Z, Z],
+            step(
+                &mut transceiver,
+                &mut outputs,
+                &steady([0.0, 1.0, 1.0, 0.0, 0.0, 1.0])
+            )
+        );
+        assert_eq!(
+            vec![Z, Z, Z, Z],
+            step(
+                &mut transceiver,
+                &mut outputs,
+                &steady([0.5, 0.0, 1.0, 0.0, 0.0, 1.0])
+            )
+        );
+    }
+    #[test]
+    fn buffer_width_validation() {
+        // GIVEN element definitions with no bits
+        // WHEN they are created
+        // THEN creation fails
+        assert!(Buffer::new("U1", 0, 0).is_err());
+        assert!(Transceiver::new("U1", 0, 0).is_err());
+    }
+    #[test]
+    fn buffer_registered() {
+        // GIVEN the standard registry
+        let registry = Registry::standard();
+        // WHEN a buffer and transceiver are created with and without a width
+        let buffer = registry.create("buffer", "U1", &Parameters::new()).unwrap();
+        let transceiver = registry
+            .create("transceiver", "U2", &Parameters::new().with("width", "4"))
+            .unwrap();
+        // THEN they have the expected pins
+        assert_eq!(9, buffer.input_pins().len());
+        assert_eq!(10, transceiver.input_pins().len());
+        assert_eq!("B3", transceiver.output_pins()[7].name());
+    }
+
+    /// Build a simulation of two single bit buffers sharing a bus, with their inputs and output enables pulled as
+    /// given, returning the simulation and the Id of the bus wire.
+    fn shared_bus(sources: [WirePull; 2], enables: [WirePull; 2]) -> (Simulation, WireId) {
+        let mut sim = Simulation::new(SimDuration::from_ticks(10));
+        let bus = sim.add_wire(Wire::new("BUS", WirePull::None)).unwrap();
+        for (i, (source, enable)) in sources.into_iter().zip(enables).enumerate() {
+            let a = sim.add_wire(Wire::new(&format!("A{i}"), source)).unwrap();
+            let oe = sim.add_wire(Wire::new(&format!("/OE{i}"), enable)).unwrap();
+            let buffer = sim
+                .add_element(Box::new(Buffer::new(&format!("U{i}"), 1, 0).unwrap()))
+                .unwrap();
+            sim.connect_input(oe, sim.input_pin(buffer, "/OE").unwrap())
+                .unwrap();
+            sim.connect_input(a, sim.input_pin(buffer, "A0").unwrap())
+                .unwrap();
+            sim.connect_output(sim.output_pin(buffer, "Y0").unwrap(), bus)
+                .unwrap();
+        }
+        (sim, bus)
+    }
+
+    #[test]
+    fn buffer_shared_bus() {
+        // GIVEN two buffers sharing a bus, driving it high and low respectively, with only the second enabled
+        let (mut sim, bus) = shared_bus(
+            [WirePull::Up, WirePull::Down],
+            [WirePull::Up, WirePull::Down],
+        );
+        // WHEN the simulation is stepped
+        sim.step().unwrap();
+        // THEN the bus follows the enabled buffer
+        assert_eq!(0.0, f32::from(sim.wire(bus).unwrap().measure()));
+    }
+    #[test]
+    fn buffer_bus_contention() {
+        // GIVEN two buffers sharing a bus, driving it high and low respectively, with both enabled
+        let (mut sim, _) = shared_bus(
+            [WirePull::Up, WirePull::Down],
+            [WirePull::Down, WirePull::Down],
+        );
+        // WHEN the simulation is stepped
+        let err = sim.step().unwrap_err();
+        // THEN the step fails, identifying the contended bus
+        assert_eq!(Some("BUS".to_string()), err.component);
+        assert!(err.to_string().contains("driven both high and low"));
+    }
+    #[test]
+    fn buffer_bus_logic() {
+        // GIVEN shared buses with neither, one and both of their buffers enabled
+        let (up, down) = (WirePull::Up, WirePull::Down);
+        let mut buses =
+            [[up, up], [up, down], [down, down]].map(|enables| shared_bus([up, down], enables));
+        // WHEN each is stepped
+        let results: Vec<bool> = buses
+            .iter_mut()
+            .map(|(sim, _)| sim.step().is_ok())
+            .collect();
+        // THEN the bus reads as released, as driven by the enabled buffer, and as unknown while they fight
+        assert_eq!(vec![true, true, false], results);
+        let logic: Vec<LogicValue> = buses
+            .iter()
+            .map(|(sim, bus)| sim.logic(*bus).unwrap())
+            .collect();
+        assert_eq!(
+            vec![
+                LogicValue::HighImpedance,
+                LogicValue::Low,
+                LogicValue::Unknown
+            ],
+            logic
+        );
+    }
+}
@@ -0,0 +1,542 @@
+//! Memory-mapped bus fabrics, which decode an address bus against a declared address map.
+//!
+//! A [BusFabric] takes the place of the address decoders and strobe gating which would otherwise be wired up gate by
+//! gate: each peripheral on a processor's bus is given a chip select from the fabric, and its reads and writes are
+//! qualified by the fabric's strobes.  Slow peripherals can be given wait states, which the fabric inserts by pulling
+//! the processor's `RDY` low.
+
+use crate::element::{bit, level, word, Element, Parameters, Registry};
+use crate::ipin::{InputPin, InputPinState};
+use crate::opin::{OutputPin, OutputPinState};
+use crate::prelude::*;
+use crate::sim::SimResult;
+use crate::time::SimDuration;
+use core::str::FromStr;
+
+/// Default address width of a fabric instantiated from a configuration file.
+const DEFAULT_WIDTH: usize = 16;
+
+/// A region of an address map, selecting one peripheral.
+///
+/// A region can be parsed from text holding its name, its first and last addresses in hexadecimal separated by `-`,
+/// and optionally its number of wait states in decimal, such as `io 4000-40ff 2`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Region {
+    /// Name of the region, which names its chip select output.
+    pub name: String,
+    /// The first address of the region.
+    pub start: u64,
+    /// The last address of the region.
+    pub end: u64,
+    /// Number of wait states inserted into each access to the region.
+    pub wait_states: u32,
+}
+
+impl FromStr for Region {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("Invalid address map region \"{}\"", s.trim());
+        let mut fields = s.split_whitespace();
+        let (Some(name), Some(range)) = (fields.next(), fields.next()) else {
+            return Err(invalid());
+        };
+        let (start, end) = range.split_once('-').ok_or_else(invalid)?;
+        let region = Self {
+            name: name.to_string(),
+            start: u64::from_str_radix(start, 16).map_err(|_| invalid())?,
+            end: u64::from_str_radix(end, 16).map_err(|_| invalid())?,
+            wait_states: match fields.next() {
+                Some(wait_states) => wait_states.parse().map_err(|_| invalid())?,
+                None => 0,
+            },
+        };
+        if fields.next().is_some() {
+            return Err(invalid());
+        }
+
+        Ok(region)
+    }
+}
+
+/// A bus fabric, decoding an address bus into chip selects for the regions of an address map, with read and write
+/// strobes and wait state insertion.
+///
+/// The inputs are named `A0` to `An`, `R/W` and `PHI2`, following the 6502 bus, and the outputs are named `/CS_name`
+/// for each region in order, then `/RD`, `/WR` and `RDY`.  The chip select of the region holding the address on the
+/// bus is low and the rest are high.  `/RD` is low while `PHI2` is high during a read, and `/WR` is low while `PHI2` is
+/// high during a write, so they can drive the output and write enables of memories and peripherals directly.  An
+/// indeterminate address or `R/W` makes the outputs depending on it unknown (X).
+///
+/// When a bus cycle accessing a region with wait states begins, `RDY` is pulled low, open-drain, as `PHI2` rises,
+/// and released after that many falling edges of `PHI2`, so a processor stalled by `RDY` repeats the cycle once for
+/// each wait state.  `RDY` should be connected to a Wire pulled up.  As with the 6502, a processor may ignore `RDY`
+/// during writes.
+///
+/// # Example
+///
+/// ```
+/// # use rvfs_sim_core::element::buses::BusFabric;
+/// # use rvfs_sim_core::element::Element;
+/// let map = ["ram 0000-3fff", "io 4000-40ff 2", "rom 8000-ffff"];
+/// let fabric = BusFabric::new("U1", 16, map.map(|region| region.parse().unwrap()).to_vec(), 0).unwrap();
+///
+/// assert_eq!("/CS_io", fabric.output_pins()[1].name());
+/// assert_eq!("RDY", fabric.output_pins()[5].name());
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct BusFabric {
+    /// Name of the fabric.
+    name: String,
+    /// Number of address bits.
+    width: usize,
+    /// The regions of the address map.
+    regions: Vec<Region>,
+    /// Propagation delay from an input to the outputs.
+    delay: u64,
+    /// Number of wait states remaining in the bus cycle in progress.
+    waiting: u32,
+    /// Whether the next rising edge of `PHI2` belongs to a new bus cycle, rather than one being repeated.
+    fresh: bool,
+}
+
+impl BusFabric {
+    /// Create a new BusFabric.
+    ///
+    /// # Parameters
+    ///
+    /// - `name`: Name of the fabric.
+    /// - `width`: Number of address bits, from 1 to 64.
+    /// - `regions`: The regions of the address map, which must lie within the address space and not overlap.
+    /// - `delay`: Propagation delay from an input to the outputs.
+    pub fn new(name: &str, width: usize, regions: Vec<Region>, delay: u64) -> Result<Self, String> {
+        if !(1..=64).contains(&width) {
+            return Err(format!(
+                "Bus fabric \"{name}\": width must be from 1 to 64 bits"
+            ));
+        }
+        let last = u64::MAX >> (64 - width);
+        for (i, region) in regions.iter().enumerate() {
+            if region.start > region.end || region.end > last {
+                return Err(format!(
+                    "Bus fabric \"{name}\": region \"{}\" does not fit the address space",
+                    region.name
+                ));
+            }
+            if let Some(other) = regions[..i]
+                .iter()
+                .find(|other| other.start <= region.end && region.start <= other.end)
+            {
+                return Err(format!(
+                    "Bus fabric \"{name}\": regions \"{}\" and \"{}\" overlap",
+                    other.name, region.name
+                ));
+            }
+        }
+
+        Ok(Self {
+            name: name.to_string(),
+            width,
+            regions,
+            delay,
+            waiting: 0,
+            fresh: true,
+        })
+    }
+
+    /// Find the region holding an address, if any.
+    ///
+    /// # Parameters
+    ///
+    /// - `address`: The address.
+    pub fn decode(&self, address: u64) -> Option<&Region> {
+        self.regions
+            .iter()
+            .find(|region| (region.start..=region.end).contains(&address))
+    }
+}
+
+impl Element for BusFabric {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn input_pins(&self) -> Vec<InputPin> {
+        (0..self.width)
+            .map(|i| format!("A{i}"))
+            .chain(["R/W".to_string(), "PHI2".to_string()])
+            .map(|name| InputPin::new(&name))
+            .collect()
+    }
+
+    fn output_pins(&self) -> Vec<OutputPin> {
+        self.regions
+            .iter()
+            .map(|region| format!("/CS_{}", region.name))
+            .chain(["/RD".to_string(), "/WR".to_string()])
+            .map(|name| {
+                OutputPin::new(
+                    &name,
+                    SimDuration::from_ticks(self.delay),
+                    OutputPinState::High,
+                )
+            })
+            .chain([OutputPin::new(
+                "RDY",
+                SimDuration::from_ticks(self.delay),
+                OutputPinState::HighImpedance,
+            )])
+            .collect()
+    }
+
+    fn step(
+        &mut self,
+        inputs: &[InputPin],
+        outputs: &mut [OutputPin],
+        _delta_t: u64,
+    ) -> Result<SimResult, String> {
+        if inputs.len() != self.width + 2 || outputs.len() != self.regions.len() + 3 {
+            return Err(format!("Bus fabric \"{}\": unexpected pins", self.name));
+        }
+        let (address, control) = inputs.split_at(self.width);
+        let [rw, strobe] = control else {
+            return Err(format!("Bus fabric \"{}\": unexpected pins", self.name));
+        };
+        let (selects, strobes) = outputs.split_at_mut(self.regions.len());
+        let [read_out, write_out, ready_out] = strobes else {
+            return Err(format!("Bus fabric \"{}\": unexpected pins", self.name));
+        };
+
+        let address = word(address);
+        let region = address.map(|address| self.decode(address).cloned());
+        if strobe.rising() && self.fresh {
+            self.fresh = false;
+            self.waiting = region
+                .as_ref()
+                .and_then(Option::as_ref)
+                .map_or(0, |region| region.wait_states);
+        } else if strobe.falling() {
+            if self.waiting > 0 {
+                self.waiting -= 1;
+            } else {
+                self.fresh = true;
+            }
+        }
+
+        for (output, candidate) in selects.iter_mut().zip(&self.regions) {
+            output.drive(level(
+                region
+                    .as_ref()
+                    .map(|region| region.as_ref() != Some(candidate)),
+            ));
+        }
+        let active = strobe.state() == InputPinState::High;
+        read_out.drive(level(bit(rw).map(|read| !(active && read))));
+        write_out.drive(level(bit(rw).map(|read| !(active && !read))));
+        ready_out.drive(if self.waiting > 0 {
+            OutputPinState::Low
+        } else {
+            OutputPinState::HighImpedance
+        });
+
+        Ok(SimResult::Continuing)
+    }
+
+    #[cfg(feature = "serde")]
+    fn save_state(&self) -> Result<serde_json::Value, String> {
+        super::save_state((self.waiting, self.fresh))
+    }
+
+    #[cfg(feature = "serde")]
+    fn restore_state(&mut self, state: serde_json::Value) -> Result<(), String> {
+        (self.waiting, self.fresh) = super::restore_state(&self.name, state)?;
+        Ok(())
+    }
+
+    fn box_clone(&self) -> Box<dyn Element> {
+        Box::new(self.clone())
+    }
+}
+
+/// Register every kind of bus element.
+///
+/// The `bus_fabric` kind takes the `width` (default 16) and `delay` parameters, and the `map` parameter holding its
+/// [regions](Region) separated by `,`.
+///
+/// # Parameters
+///
+/// - `registry`: The Registry to add the bus elements to.
+pub fn register(registry: &mut Registry) {
+    registry.register("bus_fabric", |name, parameters: &Parameters| {
+        let map: String = parameters.get_or("map", String::new())?;
+        let regions = map
+            .split(',')
+            .filter(|region| !region.trim().is_empty())
+            .map(str::parse)
+            .collect::<Result<_, _>>()
+            .map_err(|message| format!("Bus fabric \"{name}\": {message}"))?;
+        Ok(Box::new(BusFabric::new(
+            name,
+            parameters.get_or("width", DEFAULT_WIDTH)?,
+            regions,
+            parameters.get_or("delay", 0)?,
+        )?))
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(feature = "std")]
+    use crate::element::clocks::ClockGenerator;
+    #[cfg(feature = "std")]
+    use crate::element::indicators::Led;
+    #[cfg(feature = "std")]
+    use crate::element::memory::Rom;
+    #[cfg(feature = "std")]
+    use crate::element::processors::Mos6502;
+    use crate::element::testing::inputs;
+    #[cfg(feature = "std")]
+    use crate::sim::Simulation;
+    #[cfg(feature = "std")]
+    use crate::wire::{Wire, WirePull};
+    #[cfg(feature = "std")]
+    use crate::WireId;
+
+    /// Pins of an element connected to wires, by name.
+    #[cfg(feature = "std")]
+    type Connections<'a> = Vec<(&'a str, WireId)>;
+
+    /// Build a fabric with an 8 bit address bus and a small address map.
+    fn fabric() -> BusFabric {
+        let map = ["ram 00-3f", "io 40-43 1", "rom 80-ff"];
+        BusFabric::new(
+            "U1",
+            8,
+            map.map(|region| region.parse().unwrap()).to_vec(),
+            0,
+        )
+        .unwrap()
+    }
+
+    /// Step a fabric from an address, `R/W` and `PHI2`, returning its outputs as a string of `0`, `1` and `-`.
+    fn step(fabric: &mut BusFabric, previous: [f32; 10], levels: [f32; 10]) -> String {
+        let mut outputs = fabric.output_pins();
+        fabric
+            .step(&inputs(previous, levels), &mut outputs, 1)
+            .unwrap();
+        outputs
+            .iter_mut()
+            .map(|output| {
+                output.step(SimDuration::from_ticks(1));
+                match output.state() {
+                    OutputPinState::Low => '0',
+                    OutputPinState::High => '1',
+                    OutputPinState::HighImpedance => '-',
+                    OutputPinState::Unknown => 'x',
+                }
+            })
+            .collect()
+    }
+
+    /// Build the input levels of a fabric with an 8 bit address bus.
+    fn levels(address: u8, read: bool, strobe: bool) -> [f32; 10] {
+        let mut levels = [0.0; 10];
+        for (i, level) in levels.iter_mut().take(8).enumerate() {
+            *level = f32::from((address >> i) & 1);
+        }
+        levels[8] = f32::from(u8::from(read));
+        levels[9] = f32::from(u8::from(strobe));
+        levels
+    }
+
+    #[test]
+    fn region_parse() {
+        // GIVEN regions as text
+        // WHEN they are parsed
+        // THEN the name, range and wait states are found, and invalid text is rejected
+        assert_eq!(
+            Ok(Region {
+                name: "io".to_string(),
+                start: 0x4000,
+                end: 0x40ff,
+                wait_states: 2
+            }),
+            "io 4000-40ff 2".parse()
+        );
+        assert_eq!(
+            Ok(0),
+            "rom 8000-ffff".parse().map(|r: Region| r.wait_states)
+        );
+        for text in ["rom", "rom 8000", "rom 8000-ffff x", "rom 8000-ffff 1 2"] {
+            assert!(text.parse::<Region>().is_err());
+        }
+    }
+    #[test]
+    fn fabric_invalid_map() {
+        // GIVEN address maps which overlap or do not fit
+        let parse = |map: &[&str]| map.iter().map(|region| region.parse().unwrap()).collect();
+        // WHEN fabrics are created with them
+        let overlap = BusFabric::new("U1", 16, parse(&["a 0000-7fff", "b 7000-8fff"]), 0);
+        let beyond = BusFabric::new("U2", 8, parse(&["a 00-1ff"]), 0);
+        let backwards = BusFabric::new("U3", 8, parse(&["a 20-10"]), 0);
+        // THEN they are rejected
+        assert_eq!(
+            Err("Bus fabric \"U1\": regions \"a\" and \"b\" overlap".to_string()),
+            overlap
+        );
+        assert!(beyond.is_err());
+        assert!(backwards.is_err());
+    }
+    #[test]
+    fn fabric_decode_and_strobes() {
+        // GIVEN a fabric
+        let mut fabric = fabric();
+        // WHEN addresses are read and written
+        let low = levels(0x10, true, false);
+        let read = step(&mut fabric, low, levels(0x10, true, true));
+        let write = step(
+            &mut fabric,
+            levels(0x90, false, false),
+            levels(0x90, false, true),
+        );
+        let unmapped = step(
+            &mut fabric,
+            levels(0x7f, true, false),
+            levels(0x7f, true, false),
+        );
+        let mut unknown = levels(0x10, true, false);
+        unknown[0] = 0.5;
+        let unknown = step(&mut fabric, unknown, unknown);
+        // THEN the region is selected and qualified by the strobes
+        assert_eq!("01101-", read);
+        assert_eq!("11010-", write);
+        assert_eq!("11111-", unmapped);
+        assert_eq!("xxx11-", unknown);
+    }
+    #[test]
+    fn fabric_wait_states() {
+        // GIVEN a fabric
+        let mut fabric = fabric();
+        // WHEN two cycles access the region with a wait state, with PHI2 cycling twice for each
+        let mut ready = String::new();
+        for _ in 0..2 {
+            for strobe in [true, false, true, false] {
+                let previous = levels(0x41, true, !strobe);
+                let outputs = step(&mut fabric, previous, levels(0x41, true, strobe));
+                ready.push(outputs.chars().last().unwrap());
+            }
+        }
+        // THEN RDY is pulled low until the first falling edge of each
+        assert_eq!("0---0---", ready);
+    }
+    #[cfg(feature = "std")]
+    #[test]
+    fn fabric_with_processor() {
+        // GIVEN a 6502 running from ROM through a fabric, repeatedly reading a slow I/O region watched by an LED
+        let mut sim = Simulation::new(SimDuration::from_ticks(10));
+        let wire =
+            |sim: &mut Simulation, name: &str, pull| sim.add_wire(Wire::new(name, pull)).unwrap();
+        let clock = wire(&mut sim, "CLK", WirePull::Down);
+        let phi2 = wire(&mut sim, "PHI2", WirePull::Down);
+        let rw = wire(&mut sim, "R/W", WirePull::Up);
+        let ready = wire(&mut sim, "RDY", WirePull::Up);
+        let address: Vec<WireId> = (0..16)
+            .map(|i| wire(&mut sim, &format!("A{i}"), WirePull::Down))
+            .collect();
+        let data: Vec<WireId> = (0..8)
+            .map(|i| wire(&mut sim, &format!("D{i}"), WirePull::Up))
+            .collect();
+        let [cs_rom, cs_io, read] =
+            ["/CS_rom", "/CS_io", "/RD"].map(|name| wire(&mut sim, name, WirePull::Up));
+
+        let mut program = vec![0; 256];
+        program[..6].copy_from_slice(&[0xad, 0x00, 0x40, 0x4c, 0x00, 0xff]);
+        program[0xfc..].copy_from_slice(&[0x00, 0xff, 0x00, 0xff]);
+        let map = ["io 4000-40ff 2", "rom ff00-ffff"].map(|region| region.parse().unwrap());
+        let led = Led::new("D1", true);
+        let record = led.record();
+        let parts: Vec<(Box<dyn Element>, Connections, Connections)> = vec![
+            (
+                Box::new(ClockGenerator::new("X1", 200.0, 0.5, 0.0, 0, 0.0).unwrap()),
+                vec![],
+                vec![("CLK", clock)],
+            ),
+            (
+                Box::new(Mos6502::new("U1", 0)),
+                vec![("PHI0", clock), ("RDY", ready)],
+                vec![("PHI2", phi2), ("R/W", rw)],
+            ),
+            (
+                Box::new(BusFabric::new("U2", 16, map.to_vec(), 0).unwrap()),
+                vec![("PHI2", phi2), ("R/W", rw)],
+                vec![
+                    ("/CS_rom", cs_rom),
+                    ("/CS_io", cs_io),
+                    ("/RD", read),
+                    ("RDY", ready),
+                ],
+            ),
+            (
+                Box::new(Rom::new("U3", 8, 256, 0, program).unwrap()),
+                vec![("/CS", cs_rom), ("/OE", read)],
+                vec![],
+            ),
+            (Box::new(led), vec![("A", cs_io)], vec![]),
+        ];
+        let names: Vec<String> = (0..16)
+            .map(|i| format!("A{i}"))
+            .chain((0..8).map(|i| format!("D{i}")))
+            .collect();
+        for (element, element_inputs, element_outputs) in parts {
+            let id = sim.add_element(element).unwrap();
+            for (name, wire) in element_inputs {
+                sim.connect_input(wire, sim.input_pin(id, name).unwrap())
+                    .unwrap();
+            }
+            for (name, wire) in element_outputs {
+                sim.connect_output(sim.output_pin(id, name).unwrap(), wire)
+                    .unwrap();
+            }
+            for (name, wire) in names.iter().zip(address.iter().chain(&data)) {
+                if let Ok(pin) = sim.input_pin(id, name) {
+                    sim.connect_input(*wire, pin).unwrap();
+                }
+                if let Ok(pin) = sim.output_pin(id, name) {
+                    sim.connect_output(pin, *wire).unwrap();
+                }
+            }
+        }
+        // WHEN it runs
+        for _ in 0..(7 + 9 * 20) * 20 {
+            sim.step().unwrap();
+        }
+        // THEN each read of the I/O region lasts three cycles, and the loop nine
+        let intervals = record.intervals();
+        assert!(intervals.len() >= 19);
+        assert!(intervals
+            .iter()
+            .filter_map(|(start, end)| Some(end.as_ref()? - start))
+            .all(|duration| duration == 600));
+        assert!(record.check_blinking(1e9 / 1800.0, 0.01).is_ok());
+    }
+    #[test]
+    fn fabric_registered() {
+        // GIVEN the standard registry
+        let registry = Registry::standard();
+        // WHEN fabrics are created with good and bad maps
+        let good = registry.create(
+            "bus_fabric",
+            "U1",
+            &Parameters::new().with("map", "ram 0000-7fff, rom 8000-ffff 1"),
+        );
+        let bad = registry.create(
+            "bus_fabric",
+            "U2",
+            &Parameters::new().with("map", "ram 0000-7fff, rom"),
+        );
+        // THEN only the good map is accepted
+        assert_eq!("/CS_rom", good.unwrap().output_pins()[1].name());
+        assert!(bad.is_err());
+    }
+}
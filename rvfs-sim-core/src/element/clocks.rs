@@ -0,0 +1,525 @@
+//! Clock generators, and dividers for deriving clocks from a clock within the design.
+//!
+//! Generators are specified in simulation time units.  A frequency may be given instead of a period when registering
+//! one, in which case a time unit is taken to be one nanosecond, as it is when [tracing](crate::trace::vcd) to VCD.
+
+use crate::element::{Element, Parameters, Registry, UNITS_PER_SECOND};
+use crate::ipin::{InputPin, InputPinState};
+use crate::math;
+use crate::opin::{OutputPin, OutputPinState};
+use crate::prelude::*;
+use crate::sim::SimResult;
+use crate::time::SimDuration;
+
+/// Default divisor of a divider instantiated from a configuration file.
+const DEFAULT_DIVISOR: u32 = 2;
+
+/// A divide-by-N clock divider with asynchronous clear, such as a prescaler.
+///
+/// The inputs are named `CLK` and `/CLR`, and the output is named `Q`.  Without duty correction, `Q` goes high on every
+/// Nth rising edge of `CLK` and low on the next, so it pulses high for one input period in every N.  With duty
+/// correction, `Q` toggles on every Nth edge of `CLK`, rising or falling, so it has a 50% duty cycle for any N,
+/// provided the input clock does.  Holding `/CLR` low restarts the count and holds `Q` low, and an unconnected `/CLR`
+/// is treated as inactive.
+///
+/// Unlike most registers, the divider starts with its count at zero and `Q` low, as if it had just been cleared, so
+/// that derived clocks need no reset circuitry.
+///
+/// # Example
+///
+/// ```
+/// # use rvfs_sim_core::element::clocks::ClockDivider;
+/// # use rvfs_sim_core::element::Element;
+/// let divider = ClockDivider::new("U1", 10, true, 0).unwrap();
+///
+/// assert_eq!("/CLR", divider.input_pins()[1].name());
+/// assert_eq!(10, divider.divisor());
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClockDivider {
+    /// Name of the divider.
+    name: String,
+    /// Number of input clock periods in each output clock period.
+    divisor: u32,
+    /// Whether the output has a 50% duty cycle, rather than pulsing.
+    duty_correction: bool,
+    /// Propagation delay from a clock edge or input to the output.
+    delay: u64,
+    /// Number of edges counted since the output last changed.
+    count: u32,
+    /// State of the output.
+    q: bool,
+}
+
+impl ClockDivider {
+    /// Create a new ClockDivider.
+    ///
+    /// # Parameters
+    ///
+    /// - `name`: Name of the divider.
+    /// - `divisor`: Number of input clock periods in each output clock period, which must be at least 2.
+    /// - `duty_correction`: Whether the output has a 50% duty cycle, rather than pulsing.
+    /// - `delay`: Propagation delay from a clock edge or input to the output.
+    pub fn new(
+        name: &str,
+        divisor: u32,
+        duty_correction: bool,
+        delay: u64,
+    ) -> Result<Self, String> {
+        if divisor < 2 {
+            return Err(format!(
+                "Clock divider \"{name}\": divisor must be at least 2"
+            ));
+        }
+
+        Ok(Self {
+            name: name.to_string(),
+            divisor,
+            duty_correction,
+            delay,
+            count: 0,
+            q: false,
+        })
+    }
+
+    /// Obtain the number of input clock periods in each output clock period.
+    pub fn divisor(&self) -> u32 {
+        self.divisor
+    }
+}
+
+impl Element for ClockDivider {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn input_pins(&self) -> Vec<InputPin> {
+        ["CLK", "/CLR"].into_iter().map(InputPin::new).collect()
+    }
+
+    fn output_pins(&self) -> Vec<OutputPin> {
+        vec![OutputPin::new(
+            "Q",
+            SimDuration::from_ticks(self.delay),
+            OutputPinState::Low,
+        )]
+    }
+
+    fn step(
+        &mut self,
+        inputs: &[InputPin],
+        outputs: &mut [OutputPin],
+        _delta_t: u64,
+    ) -> Result<SimResult, String> {
+        let [clk, clear] = inputs else {
+            return Err(format!(
+                "Clock divider \"{}\": expected 2 inputs",
+                self.name
+            ));
+        };
+        let [q] = outputs else {
+            return Err(format!(
+                "Clock divider \"{}\": expected 1 output",
+                self.name
+            ));
+        };
+
+        if clear.state() == InputPinState::Low {
+            self.count = 0;
+            self.q = false;
+        } else if self.duty_correction {
+            if clk.rising() || clk.falling() {
+                self.count += 1;
+                if self.count == self.divisor {
+                    self.count = 0;
+                    self.q = !self.q;
+                }
+            }
+        } else if clk.rising() {
+            self.count += 1;
+            self.q = self.count == self.divisor;
+            if self.q {
+                self.count = 0;
+            }
+        }
+        q.drive(if self.q {
+            OutputPinState::High
+        } else {
+            OutputPinState::Low
+        });
+
+        Ok(SimResult::Continuing)
+    }
+
+    #[cfg(feature = "serde")]
+    fn save_state(&self) -> Result<serde_json::Value, String> {
+        super::save_state((self.count, self.q))
+    }
+
+    #[cfg(feature = "serde")]
+    fn restore_state(&mut self, state: serde_json::Value) -> Result<(), String> {
+        (self.count, self.q) = super::restore_state(&self.name, state)?;
+        Ok(())
+    }
+
+    fn box_clone(&self) -> Box<dyn Element> {
+        Box::new(self.clone())
+    }
+}
+
+/// A free-running clock generator with a configurable period, duty cycle, phase offset, start delay and drift.
+///
+/// The generator has no inputs, and its output is named `CLK`.  The output is low until the start delay has elapsed,
+/// and then runs with its phase offset, so that a generator with no offset rises at the end of the start delay.  A
+/// drift, in parts per million, lengthens the period, or shortens it if negative, so that generators with nominally
+/// equal periods drift apart as real oscillators do.
+///
+/// The output is computed from the total simulation time on each step rather than accumulated, so it does not gather
+/// rounding errors over long runs.  Its edges are resolved to the step interval of the Simulation.
+///
+/// # Example
+///
+/// ```
+/// # use rvfs_sim_core::element::clocks::ClockGenerator;
+/// let clock = ClockGenerator::new("X1", 100.0, 0.5, 90.0, 0, 0.0).unwrap();
+///
+/// assert!(clock.level_at(0));
+/// assert!(!clock.level_at(30));
+/// assert!(clock.level_at(80));
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClockGenerator {
+    /// Name of the generator.
+    name: String,
+    /// Period of the output, including drift.
+    period: f64,
+    /// Fraction of each period for which the output is high.
+    duty: f64,
+    /// Fraction of a period by which the output leads.
+    phase: f64,
+    /// Time before which the output is held low.
+    start: u64,
+    /// Simulation time of the present step.
+    time: u64,
+}
+
+impl ClockGenerator {
+    /// Create a new ClockGenerator.
+    ///
+    /// # Parameters
+    ///
+    /// - `name`: Name of the generator.
+    /// - `period`: Nominal period of the output, which must be positive.
+    /// - `duty`: Fraction of each period for which the output is high, between 0 and 1 exclusive.
+    /// - `phase`: Phase offset in degrees, from 0 to 360 exclusive, by which the output leads.
+    /// - `start`: Time before which the output is held low.
+    /// - `drift`: Drift of the period in parts per million, which must leave it positive.
+    pub fn new(
+        name: &str,
+        period: f64,
+        duty: f64,
+        phase: f64,
+        start: u64,
+        drift: f64,
+    ) -> Result<Self, String> {
+        let period = period * (1.0 + drift / 1e6);
+        if !(period.is_finite() && period > 0.0) {
+            return Err(format!(
+                "Clock generator \"{name}\": period must be positive"
+            ));
+        }
+        if !(duty > 0.0 && duty < 1.0) {
+            return Err(format!(
+                "Clock generator \"{name}\": duty cycle must be between 0 and 1"
+            ));
+        }
+        if !(0.0..360.0).contains(&phase) {
+            return Err(format!(
+                "Clock generator \"{name}\": phase must be from 0 to 360 degrees"
+            ));
+        }
+
+        Ok(Self {
+            name: name.to_string(),
+            period,
+            duty,
+            phase: phase / 360.0,
+            start,
+            time: 0,
+        })
+    }
+
+    /// Obtain the period of the output, including drift.
+    pub fn period(&self) -> f64 {
+        self.period
+    }
+
+    /// Calculate the level of the output at a simulation time.
+    ///
+    /// # Parameters
+    ///
+    /// - `time`: The simulation time.
+    pub fn level_at(&self, time: u64) -> bool {
+        if time < self.start {
+            return false;
+        }
+        let position = math::fract((time - self.start) as f64 / self.period + self.phase);
+        position < self.duty
+    }
+}
+
+impl Element for ClockGenerator {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn input_pins(&self) -> Vec<InputPin> {
+        Vec::new()
+    }
+
+    fn output_pins(&self) -> Vec<OutputPin> {
+        vec![OutputPin::new(
+            "CLK",
+            SimDuration::ZERO,
+            OutputPinState::Low,
+        )]
+    }
+
+    fn step(
+        &mut self,
+        _inputs: &[InputPin],
+        outputs: &mut [OutputPin],
+        delta_t: u64,
+    ) -> Result<SimResult, String> {
+        let [clk] = outputs else {
+            return Err(format!(
+                "Clock generator \"{}\": expected 1 output",
+                self.name
+            ));
+        };
+
+        clk.drive(if self.level_at(self.time) {
+            OutputPinState::High
+        } else {
+            OutputPinState::Low
+        });
+        self.time += delta_t;
+
+        Ok(SimResult::Continuing)
+    }
+
+    #[cfg(feature = "serde")]
+    fn save_state(&self) -> Result<serde_json::Value, String> {
+        super::save_state(self.time)
+    }
+
+    #[cfg(feature = "serde")]
+    fn restore_state(&mut self, state: serde_json::Value) -> Result<(), String> {
+        self.time = super::restore_state(&self.name, state)?;
+        Ok(())
+    }
+
+    fn box_clone(&self) -> Box<dyn Element> {
+        Box::new(self.clone())
+    }
+}
+
+/// Register every kind of clock element.
+///
+/// The generator takes either the `period` or the `frequency` parameter, with a frequency in hertz taking a time unit
+/// to be one nanosecond.  It also takes the `duty` (default 0.5), `phase` (degrees, default 0), `start` (default 0) and
+/// `drift` (parts per million, default 0) parameters.  The divider takes the `divisor` (default 2), `duty_correction`
+/// (`true` or `false`, default `false`) and `delay` (default 0) parameters.
+///
+/// # Parameters
+///
+/// - `registry`: The Registry to add the clock elements to.
+pub fn register(registry: &mut Registry) {
+    registry.register("clock", |name, parameters: &Parameters| {
+        let period = match (
+            parameters.get_or("period", 0.0)?,
+            parameters.get_or("frequency", 0.0)?,
+        ) {
+            (period, 0.0) => period,
+            (0.0, frequency) => UNITS_PER_SECOND / frequency,
+            _ => {
+                return Err(format!(
+                    "Clock generator \"{name}\": give either a period or a frequency"
+                ))
+            }
+        };
+        Ok(Box::new(ClockGenerator::new(
+            name,
+            period,
+            parameters.get_or("duty", 0.5)?,
+            parameters.get_or("phase", 0.0)?,
+            parameters.get_or("start", 0)?,
+            parameters.get_or("drift", 0.0)?,
+        )?))
+    });
+    registry.register("clock_divider", |name, parameters: &Parameters| {
+        Ok(Box::new(ClockDivider::new(
+            name,
+            parameters.get_or("divisor", DEFAULT_DIVISOR)?,
+            parameters.get_or("duty_correction", false)?,
+            parameters.get_or("delay", 0)?,
+        )?))
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::element::testing::inputs;
+
+    /// Drive a divider's clock through a number of full periods, starting low, returning the state of the output after
+    /// each edge.
+    fn run(divider: &mut ClockDivider, periods: usize) -> Vec<OutputPinState> {
+        let mut outputs = divider.output_pins();
+        let mut states = Vec::new();
+        for _ in 0..periods {
+            for (previous, level) in [(0.0, 1.0), (1.0, 0.0)] {
+                divider
+                    .step(&inputs([previous, 1.0], [level, 1.0]), &mut outputs, 10)
+                    .unwrap();
+                outputs[0].step(SimDuration::from_ticks(10));
+                states.push(outputs[0].state());
+            }
+        }
+        states
+    }
+
+    const L: OutputPinState = OutputPinState::Low;
+    const H: OutputPinState = OutputPinState::High;
+
+    #[test]
+    fn divider_pulses() {
+        // GIVEN a divide-by-3 divider without duty correction
+        let mut divider = ClockDivider::new("U1", 3, false, 0).unwrap();
+        // WHEN it is clocked for six periods
+        // THEN the output is high for one period in every three
+        assert_eq!(
+            vec![L, L, L, L, H, H, L, L, L, L, H, H],
+            run(&mut divider, 6)
+        );
+    }
+    #[test]
+    fn divider_duty_correction() {
+        // GIVEN a divide-by-3 divider with duty correction
+        let mut divider = ClockDivider::new("U1", 3, true, 0).unwrap();
+        // WHEN it is clocked for six periods
+        // THEN the output toggles every three edges, for a 50% duty cycle
+        assert_eq!(
+            vec![L, L, H, H, H, L, L, L, H, H, H, L],
+            run(&mut divider, 6)
+        );
+    }
+    #[test]
+    fn divider_clear() {
+        // GIVEN a divide-by-2 divider whose output is high
+        let mut divider = ClockDivider::new("U1", 2, true, 0).unwrap();
+        assert_eq!(H, run(&mut divider, 1)[1]);
+        // WHEN it is cleared
+        let mut outputs = divider.output_pins();
+        divider
+            .step(&inputs([0.0, 0.0], [1.0, 0.0]), &mut outputs, 10)
+            .unwrap();
+        outputs[0].step(SimDuration::from_ticks(10));
+        // THEN the output is low and the count restarts
+        assert_eq!(L, outputs[0].state());
+        assert_eq!(vec![L, H], run(&mut divider, 1));
+    }
+    #[test]
+    fn divider_registered() {
+        // GIVEN the standard registry
+        let registry = Registry::standard();
+        // WHEN dividers are created with good and bad parameters
+        let parameters = Parameters::new()
+            .with("divisor", "4")
+            .with("duty_correction", "true")
+            .with("delay", "5");
+        let divider = registry.create("clock_divider", "U1", &parameters);
+        // THEN only the good parameters are accepted
+        assert_eq!(5, divider.unwrap().output_pins()[0].delay().ticks());
+        assert!(registry
+            .create("clock_divider", "U2", &parameters.with("divisor", "1"))
+            .is_err());
+        assert!(registry
+            .create(
+                "clock_divider",
+                "U3",
+                &Parameters::new().with("duty_correction", "maybe")
+            )
+            .is_err());
+    }
+    #[test]
+    fn generator_waveform() {
+        // GIVEN a generator with a period of 40, a 25% duty cycle and a start delay of 20
+        let clock = ClockGenerator::new("X1", 40.0, 0.25, 0.0, 20, 0.0).unwrap();
+        // WHEN its level is calculated every 10 time units
+        let levels: Vec<bool> = (0..8).map(|i| clock.level_at(i * 10)).collect();
+        // THEN it is low until the start, then high for one quarter of each period
+        assert_eq!(
+            vec![false, false, true, false, false, false, true, false],
+            levels
+        );
+    }
+    #[test]
+    fn generator_phase_and_drift() {
+        // GIVEN generators of the same nominal period, one leading by 180 degrees and one drifting by 1%
+        let reference = ClockGenerator::new("X1", 100.0, 0.5, 0.0, 0, 0.0).unwrap();
+        let leading = ClockGenerator::new("X2", 100.0, 0.5, 180.0, 0, 0.0).unwrap();
+        let drifting = ClockGenerator::new("X3", 100.0, 0.5, 0.0, 0, 10_000.0).unwrap();
+        // WHEN their levels are compared
+        // THEN the leading clock is inverted, and the drifting clock slips behind over many periods
+        assert!(!leading.level_at(0) && !leading.level_at(1000));
+        assert!(leading.level_at(50));
+        assert_eq!(101.0, drifting.period());
+        assert!(reference.level_at(5000) && !drifting.level_at(5000));
+    }
+    #[test]
+    fn generator_steps() {
+        // GIVEN a generator with a period of two steps
+        let mut clock = ClockGenerator::new("X1", 20.0, 0.5, 0.0, 0, 0.0).unwrap();
+        let mut outputs = clock.output_pins();
+        // WHEN it is stepped
+        // THEN the output alternates, starting high
+        let mut states = Vec::new();
+        for _ in 0..4 {
+            clock.step(&[], &mut outputs, 10).unwrap();
+            outputs[0].step(SimDuration::from_ticks(10));
+            states.push(outputs[0].state());
+        }
+        assert_eq!(vec![H, L, H, L], states);
+    }
+    #[test]
+    fn generator_registered() {
+        // GIVEN the standard registry
+        let registry = Registry::standard();
+        // WHEN generators are created by frequency, by period, and with bad parameters
+        let by_frequency = registry
+            .create("clock", "X1", &Parameters::new().with("frequency", "1e6"))
+            .unwrap();
+        let both = Parameters::new()
+            .with("frequency", "1e6")
+            .with("period", "10");
+        // THEN only the good parameters are accepted
+        assert_eq!(1, by_frequency.output_pins().len());
+        assert!(registry.create("clock", "X2", &both).is_err());
+        assert!(registry.create("clock", "X3", &Parameters::new()).is_err());
+        assert!(registry
+            .create(
+                "clock",
+                "X4",
+                &Parameters::new().with("period", "10").with("duty", "1")
+            )
+            .is_err());
+        assert!(registry
+            .create(
+                "clock",
+                "X5",
+                &Parameters::new().with("period", "10").with("phase", "-90")
+            )
+            .is_err());
+    }
+}
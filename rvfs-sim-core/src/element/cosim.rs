@@ -0,0 +1,236 @@
+//! Synchronisation of Elements which bridge to models running in other processes with clocks of their own, such as
+//! [ExternalModel](crate::element::external::ExternalModel) and
+//! [EmulatorBridge](crate::element::emulator::EmulatorBridge).
+//!
+//! Exchanging with a model on every step keeps the two clocks in lockstep, but costs a round trip per step.  A
+//! [QuantumKeeper] instead lets the Simulation run ahead of the model by up to a quantum of simulation time, after
+//! which the two synchronise: the model is advanced to the simulation time in one exchange, and its outputs are
+//! updated.  Neither side ever races further ahead than that.  Since a model's outputs may depend on its inputs, any
+//! change to the inputs ends the epoch early, so the model sees every change at the simulation time it happened; what
+//! is lost is only the timing of output changes the model makes by itself, which appear up to a quantum late.
+//!
+//! The wall-clock time spent waiting on the model at each synchronisation is recorded in [SyncStats], and a wait
+//! longer than a threshold is reported as a stall, showing where a slow model holds the Simulation back.
+
+use crate::element::Parameters;
+use crate::Instant;
+use std::fmt;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Duration;
+
+/// Default wait on a model at a synchronisation beyond which it is counted as a stall.
+pub const DEFAULT_STALL_THRESHOLD: Duration = Duration::from_millis(10);
+
+/// Statistics of the synchronisations of a model with a Simulation.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SyncStats {
+    /// Number of synchronisations.
+    pub syncs: u64,
+    /// Number of synchronisations brought forward by a change to the model's inputs.
+    pub early: u64,
+    /// Number of steps run ahead of the model without synchronising.
+    pub skipped: u64,
+    /// Number of synchronisations which waited on the model for longer than the stall threshold.
+    pub stalls: u64,
+    /// Total wall-clock time spent waiting on the model.
+    pub waiting: Duration,
+    /// Longest wall-clock time spent waiting on the model at one synchronisation.
+    pub longest: Duration,
+}
+
+impl fmt::Display for SyncStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} syncs ({} early), {} steps ahead, {} stalls, waited {:?} (longest {:?})",
+            self.syncs, self.early, self.skipped, self.stalls, self.waiting, self.longest
+        )
+    }
+}
+
+/// Decides when a model must be synchronised with the Simulation, and records statistics of its synchronisations.
+///
+/// The statistics are shared by every copy of the keeper, as the model is shared by every copy of its Element.
+///
+/// # Example
+///
+/// ```
+/// # use rvfs_sim_core::element::cosim::QuantumKeeper;
+/// let mut keeper = QuantumKeeper::new(100);
+///
+/// assert!(!keeper.due(50, false));
+/// assert!(keeper.due(50, true));
+/// assert!(keeper.due(100, false));
+/// assert_eq!(Ok(100), keeper.synchronise(100, |advance| Ok(advance)));
+/// assert!(!keeper.due(150, false));
+/// ```
+#[derive(Debug, Clone)]
+pub struct QuantumKeeper {
+    /// Longest simulation time the Simulation may run ahead of the model, or 0 to synchronise on every step.
+    quantum: u64,
+    /// Wait on the model beyond which a synchronisation is counted as a stall.
+    stall_threshold: Duration,
+    /// Simulation time to which the model was last synchronised.
+    synced: u64,
+    /// Statistics shared by every copy of the keeper.
+    stats: Arc<Mutex<SyncStats>>,
+}
+
+impl QuantumKeeper {
+    /// Create a new QuantumKeeper.
+    ///
+    /// # Parameters
+    ///
+    /// - `quantum`: Longest simulation time the Simulation may run ahead of the model, or 0 to synchronise on every
+    ///   step.
+    pub fn new(quantum: u64) -> Self {
+        Self {
+            quantum,
+            stall_threshold: DEFAULT_STALL_THRESHOLD,
+            synced: 0,
+            stats: Arc::default(),
+        }
+    }
+
+    /// Set the wait on the model beyond which a synchronisation is counted as a stall.
+    ///
+    /// # Parameters
+    ///
+    /// - `threshold`: The wall-clock time.
+    pub fn with_stall_threshold(mut self, threshold: Duration) -> Self {
+        self.stall_threshold = threshold;
+        self
+    }
+
+    /// Obtain the longest simulation time the Simulation may run ahead of the model.
+    pub fn quantum(&self) -> u64 {
+        self.quantum
+    }
+
+    /// Query whether the model must be synchronised at a simulation time, recording a step run ahead if not.
+    ///
+    /// # Parameters
+    ///
+    /// - `time`: The simulation time.
+    /// - `inputs_changed`: Whether the model's inputs have changed since it was last synchronised.
+    pub fn due(&mut self, time: u64, inputs_changed: bool) -> bool {
+        let due = inputs_changed || time >= self.synced.saturating_add(self.quantum);
+        if !due {
+            self.lock().skipped += 1;
+        }
+        due
+    }
+
+    /// Synchronise the model to a simulation time, timing the exchange with it.
+    ///
+    /// # Parameters
+    ///
+    /// - `time`: The simulation time to synchronise to.
+    /// - `exchange`: Exchanges with the model, given the simulation time to advance it by since the last
+    ///   synchronisation.
+    pub fn synchronise<T>(
+        &mut self,
+        time: u64,
+        exchange: impl FnOnce(u64) -> Result<T, String>,
+    ) -> Result<T, String> {
+        let advance = time.saturating_sub(self.synced);
+        let early = advance < self.quantum;
+        let start = Instant::now();
+        let result = exchange(advance);
+        let waited = start.elapsed();
+
+        self.synced = time;
+        let mut stats = self.lock();
+        stats.syncs += 1;
+        stats.early += u64::from(early);
+        stats.stalls += u64::from(waited > self.stall_threshold);
+        stats.waiting += waited;
+        stats.longest = stats.longest.max(waited);
+        result
+    }
+
+    /// Obtain the statistics of the synchronisations so far.
+    pub fn stats(&self) -> SyncStats {
+        self.lock().clone()
+    }
+
+    /// Lock the shared statistics.
+    fn lock(&self) -> std::sync::MutexGuard<'_, SyncStats> {
+        self.stats.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl Default for QuantumKeeper {
+    fn default() -> Self {
+        Self::new(0)
+    }
+}
+
+/// Create a QuantumKeeper from the `quantum` parameter, in simulation time (default 0), and the `stall` parameter, in
+/// milliseconds, of an Element.
+///
+/// # Parameters
+///
+/// - `parameters`: The parameters of the Element.
+pub(crate) fn quantum_keeper(parameters: &Parameters) -> Result<QuantumKeeper, String> {
+    let stall = parameters.get_or("stall", DEFAULT_STALL_THRESHOLD.as_millis() as u64)?;
+    Ok(QuantumKeeper::new(parameters.get_or("quantum", 0)?)
+        .with_stall_threshold(Duration::from_millis(stall)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeper_lockstep() {
+        // GIVEN a keeper without a quantum
+        let mut keeper = QuantumKeeper::default();
+        // WHEN steps are taken
+        // THEN every step synchronises, advancing the model by the step
+        for time in [10, 20, 30] {
+            assert!(keeper.due(time, false));
+            assert_eq!(Ok(10), keeper.synchronise(time, Ok));
+        }
+        let stats = keeper.stats();
+        assert_eq!((3, 0, 0), (stats.syncs, stats.early, stats.skipped));
+    }
+    #[test]
+    fn keeper_quantum() {
+        // GIVEN a keeper with a quantum of five steps, and a copy of it
+        let mut keeper = QuantumKeeper::new(50);
+        let copy = keeper.clone();
+        // WHEN steps are taken, with an input change part way through the second epoch
+        let mut advances = Vec::new();
+        for time in (10..=120).step_by(10) {
+            if keeper.due(time, time == 70) {
+                advances.push(keeper.synchronise(time, Ok).unwrap());
+            }
+        }
+        // THEN the model is advanced at the end of each epoch, and early for the change
+        assert_eq!(vec![50, 20, 50], advances);
+        // AND THEN the statistics are shared with the copy
+        let stats = copy.stats();
+        assert_eq!((3, 1, 9), (stats.syncs, stats.early, stats.skipped));
+    }
+    #[test]
+    fn keeper_stalls() {
+        // GIVEN a keeper with a short stall threshold
+        let mut keeper = QuantumKeeper::new(0).with_stall_threshold(Duration::from_millis(5));
+        // WHEN one exchange is quick, and one is slow and fails
+        keeper.synchronise(10, |_| Ok(())).unwrap();
+        let result: Result<(), String> = keeper.synchronise(20, |_| {
+            std::thread::sleep(Duration::from_millis(20));
+            Err("model failed".to_string())
+        });
+        // THEN the failure is passed on, and only the slow exchange is a stall
+        assert_eq!(Err("model failed".to_string()), result);
+        let stats = keeper.stats();
+        assert_eq!((2, 1), (stats.syncs, stats.stalls));
+        assert!(stats.longest >= Duration::from_millis(20));
+        assert!(stats.waiting >= stats.longest);
+        assert!(stats
+            .to_string()
+            .starts_with("2 syncs (0 early), 0 steps ahead, 1 stalls, waited "));
+    }
+}
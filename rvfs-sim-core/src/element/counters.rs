@@ -0,0 +1,382 @@
+//! Synchronous counters.
+
+use crate::element::{bit, level, Element, Parameters, Registry};
+use crate::ipin::{InputPin, InputPinState};
+use crate::opin::{OutputPin, OutputPinState};
+use crate::prelude::*;
+use crate::sim::SimResult;
+use crate::time::SimDuration;
+use core::str::FromStr;
+
+/// Default width of a binary counter instantiated from a configuration file.
+const DEFAULT_WIDTH: usize = 4;
+/// Maximum width of a counter.
+const MAX_WIDTH: usize = 32;
+
+/// The sequence a Counter counts through.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum CounterMode {
+    /// Every value the outputs can represent, as the 74161 and 74191 do.
+    Binary,
+    /// A single decimal digit, 0 to 9, as the 74160 and 74190 do.
+    Bcd,
+}
+
+impl FromStr for CounterMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "binary" => Ok(CounterMode::Binary),
+            "bcd" => Ok(CounterMode::Bcd),
+            _ => Err(format!("Invalid counter mode \"{s}\"")),
+        }
+    }
+}
+
+/// A positive edge-triggered up/down counter with enable, synchronous load, asynchronous clear and terminal count.
+///
+/// The inputs are named `CLK`, `EN`, `UP`, `/LOAD`, `/CLR` and `P0` to `Pn`, and the outputs are named `Q0` to `Qn`
+/// and `TC`.  On each rising edge of `CLK` the counter loads `P0` to `Pn` if `/LOAD` is low, and otherwise counts up
+/// if `UP` is high or down if it is low, provided `EN` is high.  Counting wraps from the last value of the sequence to
+/// zero and back, and a loaded value beyond the end of a BCD sequence counts up to zero or down by one, much as on the
+/// 74190.  Holding `/CLR` low clears the count regardless of the clock, and an unconnected `/CLR` is treated as
+/// inactive.
+///
+/// `TC` is high while `EN` is high and the count is at the end of the sequence in the counting direction: the last
+/// value when counting up, or zero when counting down.  It can enable the next counter of a cascade.
+///
+/// The count is unknown until the counter is first cleared or loaded.  An indeterminate input which the count depends
+/// on at a clock edge makes the count unknown, and while it is unknown the outputs are driven unknown (X).
+///
+/// # Example
+///
+/// ```
+/// # use rvfs_sim_core::element::counters::{Counter, CounterMode};
+/// # use rvfs_sim_core::element::Element;
+/// let counter = Counter::new("U1", 4, CounterMode::Bcd, 0).unwrap();
+///
+/// assert_eq!(9, counter.input_pins().len());
+/// assert_eq!("TC", counter.output_pins()[4].name());
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Counter {
+    /// Name of the counter.
+    name: String,
+    /// Number of bits in the count.
+    width: usize,
+    /// Sequence counted through.
+    mode: CounterMode,
+    /// Propagation delay from a clock edge or input to the outputs.
+    delay: u64,
+    /// The count, if known.
+    count: Option<u32>,
+}
+
+impl Counter {
+    /// Create a new Counter.
+    ///
+    /// # Parameters
+    ///
+    /// - `name`: Name of the counter.
+    /// - `width`: Number of bits in the count, from 1 to 32.  A BCD counter is exactly 4 bits wide.
+    /// - `mode`: Sequence to count through.
+    /// - `delay`: Propagation delay from a clock edge or input to the outputs.
+    pub fn new(name: &str, width: usize, mode: CounterMode, delay: u64) -> Result<Self, String> {
+        if !(1..=MAX_WIDTH).contains(&width) {
+            return Err(format!(
+                "Counter \"{name}\": width must be from 1 to {MAX_WIDTH} bits"
+            ));
+        }
+        if mode == CounterMode::Bcd && width != 4 {
+            return Err(format!("Counter \"{name}\": a BCD counter is 4 bits wide"));
+        }
+
+        Ok(Self {
+            name: name.to_string(),
+            width,
+            mode,
+            delay,
+            count: None,
+        })
+    }
+
+    /// Obtain the count, if known.
+    pub fn count(&self) -> Option<u32> {
+        self.count
+    }
+
+    /// Obtain the last value of the counting sequence.
+    fn last(&self) -> u32 {
+        match self.mode {
+            CounterMode::Binary => u32::MAX >> (32 - self.width),
+            CounterMode::Bcd => 9,
+        }
+    }
+}
+
+impl Element for Counter {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn input_pins(&self) -> Vec<InputPin> {
+        let control = ["CLK", "EN", "UP", "/LOAD", "/CLR"]
+            .into_iter()
+            .map(InputPin::new);
+        let parallel = (0..self.width).map(|i| InputPin::new(&format!("P{i}")));
+        control.chain(parallel).collect()
+    }
+
+    fn output_pins(&self) -> Vec<OutputPin> {
+        (0..self.width)
+            .map(|i| format!("Q{i}"))
+            .chain(["TC".to_string()])
+            .map(|name| {
+                OutputPin::new(
+                    &name,
+                    SimDuration::from_ticks(self.delay),
+                    OutputPinState::HighImpedance,
+                )
+            })
+            .collect()
+    }
+
+    fn step(
+        &mut self,
+        inputs: &[InputPin],
+        outputs: &mut [OutputPin],
+        _delta_t: u64,
+    ) -> Result<SimResult, String> {
+        let [clk, en, up, load, clear, parallel @ ..] = inputs else {
+            return Err(format!(
+                "Counter \"{}\": expected {} inputs",
+                self.name,
+                self.width + 5
+            ));
+        };
+        let [q @ .., tc] = outputs else {
+            return Err(format!(
+                "Counter \"{}\": expected {} outputs",
+                self.name,
+                self.width + 1
+            ));
+        };
+        if parallel.len() != self.width || q.len() != self.width {
+            return Err(format!(
+                "Counter \"{}\": expected {} parallel inputs and count outputs",
+                self.name, self.width
+            ));
+        }
+
+        let last = self.last();
+        if clear.state() == InputPinState::Low {
+            self.count = Some(0);
+        } else if clk.rising() {
+            self.count = match load.state() {
+                InputPinState::Low => parallel.iter().enumerate().try_fold(0, |count, (i, pin)| {
+                    Some(count | (u32::from(bit(pin)?) << i))
+                }),
+                InputPinState::High => match (bit(en), bit(up)) {
+                    (Some(false), _) => self.count,
+                    (Some(true), Some(true)) => {
+                        self.count
+                            .map(|count| if count >= last { 0 } else { count + 1 })
+                    }
+                    (Some(true), Some(false)) => {
+                        self.count
+                            .map(|count| if count == 0 { last } else { count - 1 })
+                    }
+                    _ => None,
+                },
+                InputPinState::Indeterminate => None,
+            };
+        }
+
+        for (i, output) in q.iter_mut().enumerate() {
+            output.drive(level(self.count.map(|count| (count >> i) & 1 == 1)));
+        }
+        let terminal = match (bit(en), bit(up), self.count) {
+            (Some(false), _, _) => Some(false),
+            (Some(true), Some(true), Some(count)) => Some(count == last),
+            (Some(true), Some(false), Some(count)) => Some(count == 0),
+            _ => None,
+        };
+        tc.drive(level(terminal));
+
+        Ok(SimResult::Continuing)
+    }
+
+    #[cfg(feature = "serde")]
+    fn save_state(&self) -> Result<serde_json::Value, String> {
+        super::save_state(self.count)
+    }
+
+    #[cfg(feature = "serde")]
+    fn restore_state(&mut self, state: serde_json::Value) -> Result<(), String> {
+        self.count = super::restore_state(&self.name, state)?;
+        Ok(())
+    }
+
+    fn box_clone(&self) -> Box<dyn Element> {
+        Box::new(self.clone())
+    }
+}
+
+/// Register every kind of counter.
+///
+/// The counter takes the `width` (default 4), `mode` (`binary` or `bcd`, default `binary`) and `delay` (default 0)
+/// parameters.
+///
+/// # Parameters
+///
+/// - `registry`: The Registry to add the counters to.
+pub fn register(registry: &mut Registry) {
+    registry.register("counter", |name, parameters: &Parameters| {
+        Ok(Box::new(Counter::new(
+            name,
+            parameters.get_or("width", DEFAULT_WIDTH)?,
+            parameters.get_or("mode", CounterMode::Binary)?,
+            parameters.get_or("delay", 0)?,
+        )?))
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::element::testing::inputs;
+
+    /// Clock a four bit counter once with the given enable, direction, load and parallel inputs, returning the state
+    /// of the terminal count output.
+    fn clock(
+        counter: &mut Counter,
+        en: f32,
+        up: f32,
+        load: f32,
+        parallel: [f32; 4],
+    ) -> OutputPinState {
+        let [p0, p1, p2, p3] = parallel;
+        let mut outputs = counter.output_pins();
+        counter
+            .step(
+                &inputs(
+                    [0.0, en, up, load, 1.0, p0, p1, p2, p3],
+                    [1.0, en, up, load, 1.0, p0, p1, p2, p3],
+                ),
+                &mut outputs,
+                10,
+            )
+            .unwrap();
+        outputs[4].step(SimDuration::from_ticks(10));
+        outputs[4].state()
+    }
+
+    #[test]
+    fn counter_binary_up_wraps() {
+        // GIVEN a binary counter loaded with 14
+        let mut counter = Counter::new("U1", 4, CounterMode::Binary, 0).unwrap();
+        clock(&mut counter, 1.0, 1.0, 0.0, [0.0, 1.0, 1.0, 1.0]);
+        assert_eq!(Some(14), counter.count());
+        // WHEN it counts up twice
+        // THEN the terminal count is reached at 15 and the count wraps to zero
+        assert_eq!(
+            OutputPinState::High,
+            clock(&mut counter, 1.0, 1.0, 1.0, [0.0; 4])
+        );
+        assert_eq!(Some(15), counter.count());
+        assert_eq!(
+            OutputPinState::Low,
+            clock(&mut counter, 1.0, 1.0, 1.0, [0.0; 4])
+        );
+        assert_eq!(Some(0), counter.count());
+    }
+    #[test]
+    fn counter_bcd_down_wraps() {
+        // GIVEN a BCD counter loaded with 1
+        let mut counter = Counter::new("U1", 4, CounterMode::Bcd, 0).unwrap();
+        clock(&mut counter, 1.0, 0.0, 0.0, [1.0, 0.0, 0.0, 0.0]);
+        // WHEN it counts down twice
+        // THEN the terminal count is reached at zero and the count wraps to 9
+        assert_eq!(
+            OutputPinState::High,
+            clock(&mut counter, 1.0, 0.0, 1.0, [0.0; 4])
+        );
+        assert_eq!(Some(0), counter.count());
+        clock(&mut counter, 1.0, 0.0, 1.0, [0.0; 4]);
+        assert_eq!(Some(9), counter.count());
+    }
+    #[test]
+    fn counter_enable() {
+        // GIVEN a counter loaded with 5
+        let mut counter = Counter::new("U1", 4, CounterMode::Binary, 0).unwrap();
+        clock(&mut counter, 0.0, 1.0, 0.0, [1.0, 0.0, 1.0, 0.0]);
+        // WHEN it is clocked while disabled, and then with an indeterminate enable
+        // THEN it holds, and then the count becomes unknown
+        assert_eq!(
+            OutputPinState::Low,
+            clock(&mut counter, 0.0, 1.0, 1.0, [0.0; 4])
+        );
+        assert_eq!(Some(5), counter.count());
+        assert_eq!(
+            OutputPinState::Unknown,
+            clock(&mut counter, 0.5, 1.0, 1.0, [0.0; 4])
+        );
+        assert_eq!(None, counter.count());
+    }
+    #[test]
+    fn counter_clear_and_outputs() {
+        // GIVEN a new counter
+        let mut counter = Counter::new("U1", 2, CounterMode::Binary, 0).unwrap();
+        let mut outputs = counter.output_pins();
+        // WHEN it is cleared and then counts up once
+        counter
+            .step(
+                &inputs([0.0; 7], [0.0, 1.0, 1.0, 1.0, 0.0, 0.0, 0.0]),
+                &mut outputs,
+                10,
+            )
+            .unwrap();
+        counter
+            .step(
+                &inputs([0.0; 7], [1.0, 1.0, 1.0, 1.0, 1.0, 0.0, 0.0]),
+                &mut outputs,
+                10,
+            )
+            .unwrap();
+        outputs
+            .iter_mut()
+            .for_each(|output| output.step(SimDuration::from_ticks(10)));
+        // THEN the outputs show a count of 1
+        let states: Vec<OutputPinState> = outputs.iter().map(OutputPin::state).collect();
+        assert_eq!(
+            vec![
+                OutputPinState::High,
+                OutputPinState::Low,
+                OutputPinState::Low
+            ],
+            states
+        );
+    }
+    #[test]
+    fn counter_registered_parameters() {
+        // GIVEN the standard registry
+        let registry = Registry::standard();
+        // WHEN counters are created with valid and invalid parameters
+        let counter = registry
+            .create("counter", "U1", &Parameters::new().with("width", "8"))
+            .unwrap();
+        // THEN the valid parameters are used and the invalid ones are rejected
+        assert_eq!(9, counter.output_pins().len());
+        assert!(registry
+            .create("counter", "U2", &Parameters::new().with("width", "33"))
+            .is_err());
+        assert!(registry
+            .create(
+                "counter",
+                "U3",
+                &Parameters::new().with("width", "8").with("mode", "bcd")
+            )
+            .is_err());
+    }
+}
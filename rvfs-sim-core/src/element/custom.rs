@@ -0,0 +1,187 @@
+//! Custom elements, whose behaviour is given by a closure rather than by implementing [Element].
+//!
+//! These suit one-off behaviours in tests, which would not repay writing out a full Element and registering it.
+
+use crate::element::Element;
+use crate::ipin::InputPin;
+use crate::opin::{OutputPin, OutputPinState};
+use crate::prelude::*;
+use crate::sim::SimResult;
+use crate::time::SimDuration;
+use core::fmt;
+
+/// An Element whose outputs are calculated by a closure.
+///
+/// On each step the closure is given the InputPins, in the order their names were given, and the simulation time
+/// elapsed since the last step, and returns the next state of each output in the order their names were given.  The
+/// outputs start low, and have no propagation delay.  Any state the behaviour needs can be captured by the closure,
+/// which is cloned along with the Element when a Simulation is checkpointed.
+///
+/// # Example
+///
+/// ```
+/// # use rvfs_sim_core::element::custom::FnElement;
+/// # use rvfs_sim_core::element::Element;
+/// # use rvfs_sim_core::opin::OutputPinState;
+/// let mut edges = 0;
+/// let counter = FnElement::new("U1", &["CLK"], &["ODD"], move |inputs, _delta_t| {
+///     edges += usize::from(inputs[0].rising());
+///     vec![if edges % 2 == 1 {
+///         OutputPinState::High
+///     } else {
+///         OutputPinState::Low
+///     }]
+/// });
+///
+/// assert_eq!("CLK", counter.input_pins()[0].name());
+/// assert_eq!("ODD", counter.output_pins()[0].name());
+/// ```
+#[derive(Clone)]
+pub struct FnElement<F> {
+    /// Name of the Element.
+    name: String,
+    /// Names of the inputs.
+    inputs: Vec<String>,
+    /// Names of the outputs.
+    outputs: Vec<String>,
+    /// The closure calculating the outputs.
+    behaviour: F,
+}
+
+impl<F> FnElement<F>
+where
+    F: FnMut(&[InputPin], u64) -> Vec<OutputPinState> + Clone + Send + 'static,
+{
+    /// Create a new FnElement.
+    ///
+    /// # Parameters
+    ///
+    /// - `name`: Name of the Element.
+    /// - `inputs`: Names of the inputs.
+    /// - `outputs`: Names of the outputs.
+    /// - `behaviour`: Closure calculating the next output states from the inputs and elapsed time.
+    pub fn new(name: &str, inputs: &[&str], outputs: &[&str], behaviour: F) -> Self {
+        Self {
+            name: name.to_string(),
+            inputs: inputs.iter().map(ToString::to_string).collect(),
+            outputs: outputs.iter().map(ToString::to_string).collect(),
+            behaviour,
+        }
+    }
+}
+
+impl<F> fmt::Debug for FnElement<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FnElement")
+            .field("name", &self.name)
+            .field("inputs", &self.inputs)
+            .field("outputs", &self.outputs)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<F> Element for FnElement<F>
+where
+    F: FnMut(&[InputPin], u64) -> Vec<OutputPinState> + Clone + Send + 'static,
+{
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn input_pins(&self) -> Vec<InputPin> {
+        self.inputs.iter().map(|name| InputPin::new(name)).collect()
+    }
+
+    fn output_pins(&self) -> Vec<OutputPin> {
+        self.outputs
+            .iter()
+            .map(|name| OutputPin::new(name, SimDuration::ZERO, OutputPinState::Low))
+            .collect()
+    }
+
+    fn step(
+        &mut self,
+        inputs: &[InputPin],
+        outputs: &mut [OutputPin],
+        delta_t: u64,
+    ) -> Result<SimResult, String> {
+        let states = (self.behaviour)(inputs, delta_t);
+        if states.len() != outputs.len() {
+            return Err(format!(
+                "Element \"{}\": behaviour gave {} output states, expected {}",
+                self.name,
+                states.len(),
+                outputs.len()
+            ));
+        }
+        for (output, state) in outputs.iter_mut().zip(states) {
+            output.drive(state);
+        }
+
+        Ok(SimResult::Continuing)
+    }
+
+    fn box_clone(&self) -> Box<dyn Element> {
+        Box::new(self.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::element::testing::inputs;
+    use crate::ipin::InputPinState;
+    use crate::sim::Simulation;
+    use crate::wire::{Wire, WirePull};
+
+    #[test]
+    fn fn_element_step() {
+        // GIVEN an element inverting its input, and one giving the wrong number of outputs
+        let mut inverter = FnElement::new("U1", &["A"], &["Y"], |inputs, _| {
+            vec![match inputs[0].state() {
+                InputPinState::Low => OutputPinState::High,
+                InputPinState::High => OutputPinState::Low,
+                InputPinState::Indeterminate => OutputPinState::HighImpedance,
+            }]
+        });
+        let mut broken = FnElement::new("U2", &["A"], &["Y"], |_, _| Vec::new());
+        // WHEN they are stepped
+        let mut outputs = inverter.output_pins();
+        inverter
+            .step(&inputs([0.0], [1.0]), &mut outputs, 10)
+            .unwrap();
+        outputs[0].step(SimDuration::from_ticks(10));
+        // THEN the closure sets the outputs, and a mismatch is an error
+        assert_eq!(OutputPinState::Low, outputs[0].state());
+        assert_eq!(
+            Err("Element \"U2\": behaviour gave 0 output states, expected 1".to_string()),
+            broken.step(&inputs([0.0], [1.0]), &mut outputs, 10)
+        );
+    }
+    #[test]
+    fn fn_element_in_simulation() {
+        // GIVEN an element in a Simulation, toggling its output every 30 time units and counting its steps
+        let mut sim = Simulation::new(SimDuration::from_ticks(10));
+        let mut elapsed = 0;
+        let toggler = FnElement::new("U1", &[], &["Q"], move |_, delta_t| {
+            elapsed += delta_t;
+            vec![if (elapsed / 30) % 2 == 1 {
+                OutputPinState::High
+            } else {
+                OutputPinState::Low
+            }]
+        });
+        let id = sim.add_element(Box::new(toggler)).unwrap();
+        let wire = sim.add_wire(Wire::new("Q", WirePull::None)).unwrap();
+        sim.connect_output(sim.output_pin(id, "Q").unwrap(), wire)
+            .unwrap();
+        // WHEN it is stepped
+        let mut levels = Vec::new();
+        for _ in 0..6 {
+            sim.step().unwrap();
+            levels.push(f32::from(sim.wire(wire).unwrap().measure()) > 0.5);
+        }
+        // THEN the captured state drives the output over time
+        assert_eq!(vec![false, false, true, true, true, false], levels);
+    }
+}
@@ -0,0 +1,455 @@
+//! Binary decoders and priority encoders.
+
+use crate::element::{address_bits, bit, level, Element, Parameters, Registry};
+use crate::ipin::{InputPin, InputPinState};
+use crate::opin::{OutputPin, OutputPinState};
+use crate::prelude::*;
+use crate::sim::SimResult;
+use crate::time::SimDuration;
+
+/// Default number of address bits of a decoder instantiated from a configuration file.
+const DEFAULT_DECODER_INPUTS: usize = 3;
+/// Default number of inputs of a priority encoder instantiated from a configuration file.
+const DEFAULT_ENCODER_INPUTS: usize = 8;
+/// Maximum number of address bits of a decoder.
+const MAX_DECODER_INPUTS: usize = 16;
+
+/// A binary decoder with active-low outputs and three enables, modelled on the 74138.
+///
+/// The inputs are named `A0` to `An`, `G1`, `/G2A` and `/G2B`, and the outputs are named `/Y0` to `/Ym`, with one
+/// output for each address.  While `G1` is high and `/G2A` and `/G2B` are low, the output selected by the address is
+/// low and every other output is high.  Otherwise every output is high.  The enables allow decoders to be cascaded to
+/// decode wider addresses.
+///
+/// An indeterminate address makes every output unknown (X) while the decoder is enabled, and an indeterminate enable
+/// makes every output unknown unless another enable disables the decoder.
+///
+/// # Example
+///
+/// ```
+/// # use rvfs_sim_core::element::decoders::Decoder;
+/// # use rvfs_sim_core::element::Element;
+/// let decoder = Decoder::new("U1", 3, 0).unwrap();
+///
+/// assert_eq!(6, decoder.input_pins().len());
+/// assert_eq!("/Y7", decoder.output_pins()[7].name());
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Decoder {
+    /// Name of the decoder.
+    name: String,
+    /// Number of address bits.
+    inputs: usize,
+    /// Propagation delay from an input to the outputs.
+    delay: u64,
+}
+
+impl Decoder {
+    /// Create a new Decoder.
+    ///
+    /// # Parameters
+    ///
+    /// - `name`: Name of the decoder.
+    /// - `inputs`: Number of address bits, from 1 to 16.
+    /// - `delay`: Propagation delay from an input to the outputs.
+    pub fn new(name: &str, inputs: usize, delay: u64) -> Result<Self, String> {
+        if !(1..=MAX_DECODER_INPUTS).contains(&inputs) {
+            return Err(format!(
+                "Decoder \"{name}\": there must be from 1 to {MAX_DECODER_INPUTS} address bits"
+            ));
+        }
+
+        Ok(Self {
+            name: name.to_string(),
+            inputs,
+            delay,
+        })
+    }
+}
+
+impl Element for Decoder {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn input_pins(&self) -> Vec<InputPin> {
+        (0..self.inputs)
+            .map(|i| format!("A{i}"))
+            .chain(["G1", "/G2A", "/G2B"].map(String::from))
+            .map(|name| InputPin::new(&name))
+            .collect()
+    }
+
+    fn output_pins(&self) -> Vec<OutputPin> {
+        (0..1 << self.inputs)
+            .map(|i| {
+                OutputPin::new(
+                    &format!("/Y{i}"),
+                    SimDuration::from_ticks(self.delay),
+                    OutputPinState::HighImpedance,
+                )
+            })
+            .collect()
+    }
+
+    fn step(
+        &mut self,
+        inputs: &[InputPin],
+        outputs: &mut [OutputPin],
+        _delta_t: u64,
+    ) -> Result<SimResult, String> {
+        if inputs.len() != self.inputs + 3 || outputs.len() != 1 << self.inputs {
+            return Err(format!("Decoder \"{}\": unexpected pins", self.name));
+        }
+        let (address, enables) = inputs.split_at(self.inputs);
+
+        // Each enable is active when it reads as this level.
+        let active = [InputPinState::High, InputPinState::Low, InputPinState::Low];
+        let enabled = if enables.iter().zip(active).any(|(pin, active)| {
+            pin.state() != active && pin.state() != InputPinState::Indeterminate
+        }) {
+            Some(false)
+        } else if enables
+            .iter()
+            .any(|pin| pin.state() == InputPinState::Indeterminate)
+        {
+            None
+        } else {
+            Some(true)
+        };
+        let address = address.iter().enumerate().try_fold(0, |address, (i, pin)| {
+            Some(address | (usize::from(bit(pin)?) << i))
+        });
+
+        for (i, output) in outputs.iter_mut().enumerate() {
+            let selected = match enabled {
+                Some(false) => Some(false),
+                Some(true) => address.map(|address| address == i),
+                None => None,
+            };
+            output.drive(level(selected.map(|selected| !selected)));
+        }
+
+        Ok(SimResult::Continuing)
+    }
+
+    fn is_combinational(&self) -> bool {
+        true
+    }
+
+    fn box_clone(&self) -> Box<dyn Element> {
+        Box::new(self.clone())
+    }
+}
+
+/// A priority encoder with active-low inputs and outputs, modelled on the 74148.
+///
+/// The inputs are named `/I0` to `/In` and `/EI`, and the outputs are named `/A0` to `/Am`, `/GS` and `/EO`.  While
+/// `/EI` is low, the number of the highest numbered input which is low appears inverted on `/A0` to `/Am`, and `/GS`
+/// is low to show that an input is active.  If no input is low, `/A0` to `/Am` and `/GS` are high and `/EO` is low,
+/// so that `/EO` can enable a lower priority encoder of a cascade.  While `/EI` is high every output is high.
+///
+/// An indeterminate input makes the outputs unknown unless a higher numbered input is low, and an indeterminate `/EI`
+/// makes every output unknown (X).
+#[derive(Debug, Clone, PartialEq)]
+pub struct PriorityEncoder {
+    /// Name of the encoder.
+    name: String,
+    /// Number of inputs.
+    inputs: usize,
+    /// Propagation delay from an input to the outputs.
+    delay: u64,
+}
+
+impl PriorityEncoder {
+    /// Create a new PriorityEncoder.
+    ///
+    /// # Parameters
+    ///
+    /// - `name`: Name of the encoder.
+    /// - `inputs`: Number of inputs, which must be at least two.
+    /// - `delay`: Propagation delay from an input to the outputs.
+    pub fn new(name: &str, inputs: usize, delay: u64) -> Result<Self, String> {
+        if inputs < 2 {
+            return Err(format!(
+                "Priority encoder \"{name}\": there must be at least two inputs"
+            ));
+        }
+
+        Ok(Self {
+            name: name.to_string(),
+            inputs,
+            delay,
+        })
+    }
+}
+
+impl Element for PriorityEncoder {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn input_pins(&self) -> Vec<InputPin> {
+        (0..self.inputs)
+            .map(|i| format!("/I{i}"))
+            .chain(["/EI".to_string()])
+            .map(|name| InputPin::new(&name))
+            .collect()
+    }
+
+    fn output_pins(&self) -> Vec<OutputPin> {
+        (0..address_bits(self.inputs))
+            .map(|i| format!("/A{i}"))
+            .chain(["/GS", "/EO"].map(String::from))
+            .map(|name| {
+                OutputPin::new(
+                    &name,
+                    SimDuration::from_ticks(self.delay),
+                    OutputPinState::HighImpedance,
+                )
+            })
+            .collect()
+    }
+
+    fn step(
+        &mut self,
+        inputs: &[InputPin],
+        outputs: &mut [OutputPin],
+        _delta_t: u64,
+    ) -> Result<SimResult, String> {
+        let bits = address_bits(self.inputs);
+        let [requests @ .., enable] = inputs else {
+            return Err(format!(
+                "Priority encoder \"{}\": unexpected pins",
+                self.name
+            ));
+        };
+        if requests.len() != self.inputs || outputs.len() != bits + 2 {
+            return Err(format!(
+                "Priority encoder \"{}\": unexpected pins",
+                self.name
+            ));
+        }
+
+        // The highest numbered active input, if any, or None if an indeterminate input may be the highest.
+        let mut highest = Some(None);
+        for (i, pin) in requests.iter().enumerate().rev() {
+            match pin.state() {
+                InputPinState::Low => {
+                    highest = Some(Some(i));
+                    break;
+                }
+                InputPinState::Indeterminate => {
+                    highest = None;
+                    break;
+                }
+                InputPinState::High => (),
+            }
+        }
+
+        // Levels of the address bits, /GS and /EO, or None if unknown.
+        let levels: Option<(usize, bool, bool)> = match (enable.state(), highest) {
+            (InputPinState::High, _) => Some((usize::MAX, true, true)),
+            (InputPinState::Low, Some(Some(i))) => Some((!i, false, true)),
+            (InputPinState::Low, Some(None)) => Some((usize::MAX, true, false)),
+            _ => None,
+        };
+        let (address, gs_eo) = outputs.split_at_mut(bits);
+        for (i, output) in address.iter_mut().enumerate() {
+            output.drive(level(levels.map(|(address, _, _)| (address >> i) & 1 == 1)));
+        }
+        gs_eo[0].drive(level(levels.map(|(_, gs, _)| gs)));
+        gs_eo[1].drive(level(levels.map(|(_, _, eo)| eo)));
+
+        Ok(SimResult::Continuing)
+    }
+
+    fn is_combinational(&self) -> bool {
+        true
+    }
+
+    fn box_clone(&self) -> Box<dyn Element> {
+        Box::new(self.clone())
+    }
+}
+
+/// Register the decoder and priority encoder, taking the `inputs` (default 3 address bits for the decoder, or 8 inputs
+/// for the encoder) and `delay` (default 0) parameters.
+///
+/// # Parameters
+///
+/// - `registry`: The Registry to add the elements to.
+pub fn register(registry: &mut Registry) {
+    registry.register("decoder", |name, parameters: &Parameters| {
+        Ok(Box::new(Decoder::new(
+            name,
+            parameters.get_or("inputs", DEFAULT_DECODER_INPUTS)?,
+            parameters.get_or("delay", 0)?,
+        )?))
+    });
+    registry.register("priority_encoder", |name, parameters: &Parameters| {
+        Ok(Box::new(PriorityEncoder::new(
+            name,
+            parameters.get_or("inputs", DEFAULT_ENCODER_INPUTS)?,
+            parameters.get_or("delay", 0)?,
+        )?))
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::element::testing::{steady, step};
+
+    const L: OutputPinState = OutputPinState::Low;
+    const H: OutputPinState = OutputPinState::High;
+    const X: OutputPinState = OutputPinState::Unknown;
+
+    #[test]
+    fn decoder_selects_output() {
+        // GIVEN an enabled 2-to-4 decoder
+        let mut decoder = Decoder::new("U1", 2, 0).unwrap();
+        let mut outputs = decoder.output_pins();
+        // WHEN address 2 is decoded
+        // THEN only its output is low
+        assert_eq!(
+            vec![H, H, L, H],
+            step(
+                &mut decoder,
+                &mut outputs,
+                &steady([0.0, 1.0, 1.0, 0.0, 0.0])
+            )
+        );
+        // AND THEN an indeterminate address makes every output unknown
+        assert_eq!(
+            vec![X; 4],
+            step(
+                &mut decoder,
+                &mut outputs,
+                &steady([0.5, 1.0, 1.0, 0.0, 0.0])
+            )
+        );
+    }
+    #[test]
+    fn decoder_enables() {
+        // GIVEN a 2-to-4 decoder addressing output 0
+        let mut decoder = Decoder::new("U1", 2, 0).unwrap();
+        let mut outputs = decoder.output_pins();
+        // WHEN any enable is inactive
+        // THEN every output is high, even if another enable is indeterminate
+        assert_eq!(
+            vec![H; 4],
+            step(
+                &mut decoder,
+                &mut outputs,
+                &steady([0.0, 0.0, 0.0, 0.0, 0.0])
+            )
+        );
+        assert_eq!(
+            vec![H; 4],
+            step(
+                &mut decoder,
+                &mut outputs,
+                &steady([0.0, 0.0, 1.0, 1.0, 0.5])
+            )
+        );
+        // AND THEN an indeterminate enable with the others active makes every output unknown
+        assert_eq!(
+            vec![X; 4],
+            step(
+                &mut decoder,
+                &mut outputs,
+                &steady([0.0, 0.0, 0.5, 0.0, 0.0])
+            )
+        );
+    }
+    #[test]
+    fn encoder_priority() {
+        // GIVEN an enabled 8-to-3 priority encoder
+        let mut encoder = PriorityEncoder::new("U1", 8, 0).unwrap();
+        let mut outputs = encoder.output_pins();
+        // WHEN inputs 2 and 5 are active
+        // THEN the inverted code of 5 is output, with /GS low and /EO high
+        assert_eq!(
+            vec![L, H, L, L, H],
+            step(
+                &mut encoder,
+                &mut outputs,
+                &steady([1.0, 1.0, 0.0, 1.0, 1.0, 0.0, 1.0, 1.0, 0.0])
+            )
+        );
+        // AND THEN an indeterminate input below the highest active input is ignored
+        assert_eq!(
+            vec![L, H, L, L, H],
+            step(
+                &mut encoder,
+                &mut outputs,
+                &steady([1.0, 1.0, 0.5, 1.0, 1.0, 0.0, 1.0, 1.0, 0.0])
+            )
+        );
+        // AND THEN an indeterminate input above it makes the outputs unknown
+        assert_eq!(
+            vec![X; 5],
+            step(
+                &mut encoder,
+                &mut outputs,
+                &steady([1.0, 1.0, 1.0, 1.0, 1.0, 0.0, 0.5, 1.0, 0.0])
+            )
+        );
+    }
+    #[test]
+    fn encoder_idle_and_disabled() {
+        // GIVEN a 4-to-2 priority encoder with no active inputs
+        let mut encoder = PriorityEncoder::new("U1", 4, 0).unwrap();
+        let mut outputs = encoder.output_pins();
+        // WHEN it is enabled
+        // THEN only /EO is low
+        assert_eq!(
+            vec![H, H, H, L],
+            step(
+                &mut encoder,
+                &mut outputs,
+                &steady([1.0, 1.0, 1.0, 1.0, 0.0])
+            )
+        );
+        // WHEN it is disabled
+        // THEN every output is high
+        assert_eq!(
+            vec![H; 4],
+            step(
+                &mut encoder,
+                &mut outputs,
+                &steady([0.0, 1.0, 1.0, 1.0, 1.0])
+            )
+        );
+    }
+    #[test]
+    fn decoder_registered_parameters() {
+        // GIVEN the standard registry
+        let registry = Registry::standard();
+        // WHEN elements are created with valid and invalid parameters
+        let decoder = registry
+            .create("decoder", "U1", &Parameters::new())
+            .unwrap();
+        let encoder = registry
+            .create(
+                "priority_encoder",
+                "U2",
+                &Parameters::new().with("inputs", "10"),
+            )
+            .unwrap();
+        // THEN the defaults or given parameters are used and the invalid ones are rejected
+        assert_eq!(8, decoder.output_pins().len());
+        assert_eq!(4 + 2, encoder.output_pins().len());
+        assert!(registry
+            .create("decoder", "U3", &Parameters::new().with("inputs", "0"))
+            .is_err());
+        assert!(registry
+            .create(
+                "priority_encoder",
+                "U4",
+                &Parameters::new().with("inputs", "1")
+            )
+            .is_err());
+    }
+}
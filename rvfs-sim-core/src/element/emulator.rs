@@ -0,0 +1,531 @@
+//! A bridge to a machine emulator, so that firmware running on an emulated processor can interact with discrete logic
+//! simulated here.
+//!
+//! The bridge speaks the subset of the [QEMU qtest protocol](https://www.qemu.org/docs/master/devel/testing/qtest.html)
+//! which drives the GPIO lines of an emulated device and advances the emulator's virtual clock.  QEMU serves it when
+//! started with, for example, `-qtest tcp:localhost:4321,server=on`.  Other emulators, such as Renode, can be bridged
+//! by an adapter which speaks the same commands over their own socket interfaces.
+//!
+//! The protocol is line-based text.  Each command is answered by a line beginning `OK`, or `FAIL` with a reason, and
+//! the emulator may send `IRQ raise <n>` and `IRQ lower <n>` lines at any time to report a change on an intercepted
+//! output line of the device.  The bridge sends:
+//!
+//! - `irq_intercept_out <device>` on connection, so that the device's output lines are reported;
+//! - `set_irq_in <device> <gpio> <n> <level>` whenever input line `n` of the device changes;
+//! - `clock_step <delta_t>` on every step, advancing the virtual clock by the time elapsed in the Simulation, in
+//!   nanoseconds, and answered with `OK <clock>` giving the new virtual clock.  With a
+//!   [quantum](crate::element::cosim), the clock is only stepped once the quantum has passed or an input line has
+//!   changed, by all the time elapsed since it was last stepped.
+//!
+//! The emulator's clock keeps its offset from simulation time at the first step, so the emulator may have run before
+//! the Simulation began.  A step which leaves the clocks out of step fails, since the two would no longer be
+//! synchronised.
+
+use crate::element::cosim::{quantum_keeper, QuantumKeeper, SyncStats};
+use crate::element::external::{open_socket, Socket};
+use crate::element::{bit, level, Element, Parameters, Registry};
+use crate::ipin::InputPin;
+use crate::opin::{OutputPin, OutputPinState};
+use crate::sim::{SimResult, DEFAULT_STEP_PHASE_TIMEOUT};
+use crate::time::SimDuration;
+use std::fmt;
+use std::io::{BufRead, BufReader, ErrorKind, Write};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::time::{Duration, Instant};
+
+/// Name of the input lines of a device which were not given a name.
+const UNNAMED_GPIO_IN: &str = "unnamed-gpio-in";
+
+/// A session with an emulator, shared by every copy of its Element.
+struct Session {
+    /// The socket, buffered for reading lines, or `None` once it has failed and been abandoned.
+    socket: Option<BufReader<Box<dyn Socket>>>,
+    /// The level last set on each input line of the device, if any.
+    inputs: Vec<Option<bool>>,
+    /// The level last reported on each output line of the device.
+    outputs: Vec<bool>,
+    /// The emulator's clock less the simulation time, once known.
+    offset: Option<i128>,
+}
+
+impl Session {
+    /// Send a command and wait for its answer, recording any output line changes reported first.
+    ///
+    /// Returns the text following `OK` in the answer.
+    ///
+    /// # Parameters
+    ///
+    /// - `command`: The command, without its line ending.
+    /// - `timeout`: Maximum time to wait for the answer.
+    fn command(&mut self, command: &str, timeout: Duration) -> Result<String, String> {
+        let socket = self
+            .socket
+            .as_mut()
+            .ok_or("connection was abandoned after an earlier failure")?;
+        let io_error = |error: std::io::Error| match error.kind() {
+            ErrorKind::WouldBlock | ErrorKind::TimedOut => {
+                format!("Timed out waiting for answer to \"{command}\"")
+            }
+            _ => format!("Command \"{command}\" failed: {error}"),
+        };
+        let deadline = Instant::now() + timeout;
+
+        let stream = socket.get_mut();
+        stream.set_write_timeout(Some(timeout)).map_err(io_error)?;
+        stream
+            .write_all(format!("{command}\n").as_bytes())
+            .and_then(|_| stream.flush())
+            .map_err(io_error)?;
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                return Err(format!("Timed out waiting for answer to \"{command}\""));
+            }
+            socket
+                .get_ref()
+                .set_read_timeout(Some(remaining))
+                .map_err(io_error)?;
+            let mut line = String::new();
+            if socket.read_line(&mut line).map_err(io_error)? == 0 {
+                return Err("Connection closed".to_string());
+            }
+
+            let mut words = line.split_whitespace();
+            match words.next() {
+                Some("OK") => return Ok(words.collect::<Vec<_>>().join(" ")),
+                Some("FAIL") => {
+                    return Err(format!(
+                        "Command \"{command}\" failed: {}",
+                        words.collect::<Vec<_>>().join(" ")
+                    ))
+                }
+                Some("IRQ") => {
+                    let high = match words.next() {
+                        Some("raise") => true,
+                        Some("lower") => false,
+                        _ => return Err(format!("Invalid IRQ report \"{}\"", line.trim_end())),
+                    };
+                    let output = words
+                        .next()
+                        .and_then(|n| n.parse::<usize>().ok())
+                        .ok_or_else(|| format!("Invalid IRQ report \"{}\"", line.trim_end()))?;
+                    // Lines the Element has no pin for are not connected to the Simulation.
+                    if let Some(level) = self.outputs.get_mut(output) {
+                        *level = high;
+                    }
+                }
+                _ => return Err(format!("Unexpected answer \"{}\"", line.trim_end())),
+            }
+        }
+    }
+}
+
+/// An Element bridging the GPIO lines of a device in a machine emulator, advancing the emulator's clock in lockstep
+/// with the Simulation.
+///
+/// The inputs are named `IN0` to `INn`, driving the device's input lines of those numbers, and the outputs are named
+/// `OUT0` to `OUTn`, following its intercepted output lines.  An input is set when its level changes, with an
+/// indeterminate level set as low, and every output starts low.
+///
+/// The emulator session is shared by every copy of the Element, so a Simulation holding the Element refuses to
+/// [step back](crate::sim::Simulation::step_back).  Any failure abandons the session, as for an
+/// [ExternalModel](crate::element::external::ExternalModel).
+#[derive(Clone)]
+pub struct EmulatorBridge {
+    /// Name of the Element.
+    name: String,
+    /// Path of the device in the emulator, such as its QOM path for QEMU.
+    device: String,
+    /// Name of the device's input lines.
+    gpio: String,
+    /// Propagation delay from an output line change to the pins.
+    delay: u64,
+    /// Maximum time to wait for each answer.
+    timeout: Duration,
+    /// The session with the emulator.
+    session: Arc<Mutex<Session>>,
+    /// Decides when to step the emulator's clock.
+    keeper: QuantumKeeper,
+    /// Simulation time elapsed since the start of the Simulation.
+    time: u64,
+}
+
+impl EmulatorBridge {
+    /// Create a new EmulatorBridge by connecting to an emulator, and intercept the output lines of the device.
+    ///
+    /// # Parameters
+    ///
+    /// - `name`: Name of the Element.
+    /// - `address`: Address of the emulator: `host:port` for TCP, or `unix:path` for a Unix domain socket.
+    /// - `device`: Path of the device in the emulator, such as its QOM path for QEMU.
+    /// - `gpio`: Name of the device's input lines, or `None` if they are unnamed.
+    /// - `lines`: Number of input lines and number of output lines to bridge.
+    /// - `timeout`: Maximum time to wait for each answer.
+    /// - `delay`: Propagation delay from an output line change to the pins.
+    pub fn connect(
+        name: &str,
+        address: &str,
+        device: &str,
+        gpio: Option<&str>,
+        lines: (usize, usize),
+        timeout: Duration,
+        delay: u64,
+    ) -> Result<Self, String> {
+        let error = |error: String| format!("Emulator bridge \"{name}\": {error}");
+        let socket = open_socket(address).map_err(|io_error| {
+            error(format!(
+                "Cannot connect to emulator at \"{address}\": {io_error}"
+            ))
+        })?;
+        let mut session = Session {
+            socket: Some(BufReader::new(socket)),
+            inputs: vec![None; lines.0],
+            outputs: vec![false; lines.1],
+            offset: None,
+        };
+        if lines.1 > 0 {
+            session
+                .command(&format!("irq_intercept_out {device}"), timeout)
+                .map_err(error)?;
+        }
+
+        Ok(Self {
+            name: name.to_string(),
+            device: device.to_string(),
+            gpio: gpio.unwrap_or(UNNAMED_GPIO_IN).to_string(),
+            delay,
+            timeout,
+            session: Arc::new(Mutex::new(session)),
+            keeper: QuantumKeeper::default(),
+            time: 0,
+        })
+    }
+
+    /// Let the Simulation run ahead of the emulator, stepping its clock only as a QuantumKeeper decides, rather than
+    /// on every step.
+    ///
+    /// # Parameters
+    ///
+    /// - `keeper`: Decides when to step the emulator's clock.
+    pub fn with_quantum_keeper(mut self, keeper: QuantumKeeper) -> Self {
+        self.keeper = keeper;
+        self
+    }
+
+    /// Obtain the statistics of the synchronisations with the emulator so far.
+    pub fn sync_stats(&self) -> SyncStats {
+        self.keeper.stats()
+    }
+
+    /// Lock the session with the emulator.
+    fn lock(&self) -> MutexGuard<'_, Session> {
+        self.session.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Set the changed input lines, advance the emulator's clock, and check it against the simulation time.
+    ///
+    /// # Parameters
+    ///
+    /// - `session`: The session with the emulator.
+    /// - `inputs`: The InputPins of the Element.
+    /// - `advance`: The simulation time elapsed since the emulator's clock was last stepped.
+    fn exchange(
+        &self,
+        session: &mut Session,
+        inputs: &[InputPin],
+        advance: u64,
+    ) -> Result<(), String> {
+        for (line, input) in inputs.iter().enumerate() {
+            let high = bit(input).unwrap_or(false);
+            if session.inputs[line] != Some(high) {
+                session.command(
+                    &format!(
+                        "set_irq_in {} {} {line} {}",
+                        self.device,
+                        self.gpio,
+                        u8::from(high)
+                    ),
+                    self.timeout,
+                )?;
+                session.inputs[line] = Some(high);
+            }
+        }
+
+        let answer = session.command(&format!("clock_step {advance}"), self.timeout)?;
+        let clock: u64 = answer
+            .parse()
+            .map_err(|_| format!("Invalid clock \"{answer}\""))?;
+        let offset = i128::from(clock) - i128::from(self.time);
+        if *session.offset.get_or_insert(offset) != offset {
+            return Err(format!(
+                "emulator clock {clock} is out of step with simulation time {}",
+                self.time
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for EmulatorBridge {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EmulatorBridge")
+            .field("name", &self.name)
+            .field("device", &self.device)
+            .field("gpio", &self.gpio)
+            .field("delay", &self.delay)
+            .field("timeout", &self.timeout)
+            .field("time", &self.time)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Element for EmulatorBridge {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn input_pins(&self) -> Vec<InputPin> {
+        (0..self.lock().inputs.len())
+            .map(|i| InputPin::new(&format!("IN{i}")))
+            .collect()
+    }
+
+    fn output_pins(&self) -> Vec<OutputPin> {
+        (0..self.lock().outputs.len())
+            .map(|i| {
+                OutputPin::new(
+                    &format!("OUT{i}"),
+                    SimDuration::from_ticks(self.delay),
+                    OutputPinState::Low,
+                )
+            })
+            .collect()
+    }
+
+    fn step(
+        &mut self,
+        inputs: &[InputPin],
+        outputs: &mut [OutputPin],
+        delta_t: u64,
+    ) -> Result<SimResult, String> {
+        self.time += delta_t;
+        let mut session = self.session.lock().unwrap_or_else(PoisonError::into_inner);
+        let changed = inputs
+            .iter()
+            .zip(&session.inputs)
+            .any(|(input, sent)| *sent != Some(bit(input).unwrap_or(false)));
+        if self.keeper.due(self.time, changed) {
+            let mut keeper = std::mem::take(&mut self.keeper);
+            let result = keeper.synchronise(self.time, |advance| {
+                self.exchange(&mut session, inputs, advance)
+            });
+            self.keeper = keeper;
+            if let Err(error) = result {
+                session.socket = None;
+                return Err(format!("Emulator bridge \"{}\": {error}", self.name));
+            }
+        }
+
+        for (output, high) in outputs.iter_mut().zip(&session.outputs) {
+            output.drive(level(Some(*high)));
+        }
+        Ok(SimResult::Continuing)
+    }
+
+    fn is_replayable(&self) -> bool {
+        false
+    }
+
+    fn box_clone(&self) -> Box<dyn Element> {
+        Box::new(self.clone())
+    }
+}
+
+/// Register every kind of emulator element.
+///
+/// The `emulator` kind takes the `address` parameter, giving the address of the emulator as for
+/// [EmulatorBridge::connect], the `device` parameter giving the path of the device, the optional `gpio` parameter
+/// naming its input lines, the `inputs` and `outputs` parameters giving the number of lines to bridge (default 0), the
+/// `timeout` parameter in milliseconds (default the Simulation's default phase timeout), the `delay` parameter
+/// (default 0), and the `quantum` and `stall` parameters of its [QuantumKeeper], as for the `external` kind.
+///
+/// # Parameters
+///
+/// - `registry`: The Registry to add the emulator elements to.
+pub fn register(registry: &mut Registry) {
+    registry.register("emulator", |name, parameters: &Parameters| {
+        let address: String = parameters.get_or("address", String::new())?;
+        let device: String = parameters.get_or("device", String::new())?;
+        let gpio: String = parameters.get_or("gpio", String::new())?;
+        let timeout =
+            parameters.get_or("timeout", DEFAULT_STEP_PHASE_TIMEOUT.as_millis() as u64)?;
+        let keeper = quantum_keeper(parameters)?;
+        Ok(Box::new(
+            EmulatorBridge::connect(
+                name,
+                &address,
+                &device,
+                Some(gpio.as_str()).filter(|gpio| !gpio.is_empty()),
+                (
+                    parameters.get_or("inputs", 0)?,
+                    parameters.get_or("outputs", 0)?,
+                ),
+                Duration::from_millis(timeout),
+                parameters.get_or("delay", 0)?,
+            )?
+            .with_quantum_keeper(keeper),
+        ))
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::element::clocks::ClockGenerator;
+    use crate::sim::Simulation;
+    use crate::wire::{Wire, WirePull};
+    use std::io::{BufRead, BufReader};
+    use std::net::{TcpListener, TcpStream};
+    use std::thread::{self, JoinHandle};
+
+    /// Serve an emulated device on a local TCP port, returning its address and a handle yielding the commands it
+    /// received.
+    ///
+    /// The device's output line 0 follows its input line 0 when the clock is next stepped, and its clock starts at
+    /// 5000 and advances by `drift` more than asked at each step.  A command to set a second input line fails.
+    fn serve(drift: u64) -> (String, JoinHandle<Vec<String>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        let handle = thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut writer: TcpStream = stream.try_clone().unwrap();
+            let mut commands = Vec::new();
+            let mut clock = 5000;
+            let mut input = false;
+            let mut output = false;
+            for line in BufReader::new(stream).lines() {
+                let line = line.unwrap();
+                let words: Vec<&str> = line.split_whitespace().collect();
+                let answer = match words[0] {
+                    "set_irq_in" if words[3] == "0" => {
+                        input = words[4] == "1";
+                        "OK".to_string()
+                    }
+                    "clock_step" => {
+                        if input != output {
+                            output = input;
+                            let change = if output { "raise" } else { "lower" };
+                            writeln!(writer, "IRQ {change} 0").unwrap();
+                        }
+                        clock += words[1].parse::<u64>().unwrap() + drift;
+                        format!("OK {clock}")
+                    }
+                    "irq_intercept_out" => "OK".to_string(),
+                    _ => "FAIL unknown command".to_string(),
+                };
+                writeln!(writer, "{answer}").unwrap();
+                commands.push(line);
+            }
+            commands
+        });
+        (address, handle)
+    }
+
+    #[test]
+    fn emulator_bridge_in_simulation() {
+        // GIVEN an emulated device bridged through the registry, with its input driven by a clock
+        let (address, emulator) = serve(0);
+        let mut sim = Simulation::new(SimDuration::from_ticks(10));
+        let parameters = Parameters::new()
+            .with("address", &address)
+            .with("device", "/machine/soc/gpio")
+            .with("inputs", "1")
+            .with("outputs", "1");
+        let bridge = sim
+            .add_element(
+                Registry::standard()
+                    .create("emulator", "U1", &parameters)
+                    .unwrap(),
+            )
+            .unwrap();
+        let clock = sim
+            .add_element(Box::new(
+                ClockGenerator::new("X1", 40.0, 0.5, 0.0, 0, 0.0).unwrap(),
+            ))
+            .unwrap();
+        let clk = sim.add_wire(Wire::new("CLK", WirePull::Down)).unwrap();
+        let out = sim.add_wire(Wire::new("OUT", WirePull::None)).unwrap();
+        sim.connect_output(sim.output_pin(clock, "CLK").unwrap(), clk)
+            .unwrap();
+        sim.connect_input(clk, sim.input_pin(bridge, "IN0").unwrap())
+            .unwrap();
+        sim.connect_output(sim.output_pin(bridge, "OUT0").unwrap(), out)
+            .unwrap();
+        // WHEN it is stepped, and then dropped to close the connection
+        let levels: Vec<f32> = (0..6)
+            .map(|_| {
+                sim.step().unwrap();
+                sim.wire(out).unwrap().measure().into()
+            })
+            .collect();
+        drop(sim);
+        // THEN the output lines are intercepted, the input line is set on each change, and the clock stepped each step
+        assert_eq!(
+            vec![
+                "irq_intercept_out /machine/soc/gpio",
+                "set_irq_in /machine/soc/gpio unnamed-gpio-in 0 0",
+                "clock_step 10",
+                "set_irq_in /machine/soc/gpio unnamed-gpio-in 0 1",
+                "clock_step 10",
+                "clock_step 10",
+                "set_irq_in /machine/soc/gpio unnamed-gpio-in 0 0",
+                "clock_step 10",
+                "clock_step 10",
+                "set_irq_in /machine/soc/gpio unnamed-gpio-in 0 1",
+                "clock_step 10",
+            ],
+            emulator.join().unwrap()
+        );
+        assert_eq!(vec![0.0, 1.0, 1.0, 0.0, 0.0, 1.0], levels);
+    }
+    #[test]
+    fn emulator_bridge_errors() {
+        // GIVEN an emulator whose clock drifts, an emulator which rejects a command, and no emulator at all
+        let (drifting, _drifting) = serve(1);
+        let (rejecting, _rejecting) = serve(0);
+        let unused = TcpListener::bind("127.0.0.1:0").unwrap();
+        let absent = unused.local_addr().unwrap().to_string();
+        drop(unused);
+        let timeout = Duration::from_secs(1);
+        let connect = |name, address: &str, inputs| {
+            EmulatorBridge::connect(name, address, "gpio", Some("in"), (inputs, 1), timeout, 0)
+        };
+        // WHEN they are connected and stepped
+        let mut bridge = connect("U1", &drifting, 1).unwrap();
+        let mut outputs = bridge.output_pins();
+        let inputs = bridge.input_pins();
+        bridge.step(&inputs, &mut outputs, 10).unwrap();
+        let drift_error = bridge.step(&inputs, &mut outputs, 10).unwrap_err();
+        let abandoned_error = bridge.step(&inputs, &mut outputs, 10).unwrap_err();
+        let mut bridge = connect("U2", &rejecting, 2).unwrap();
+        let inputs = bridge.input_pins();
+        let reject_error = bridge.step(&inputs, &mut outputs, 10).unwrap_err();
+        let absent_error = connect("U3", &absent, 1).unwrap_err();
+        // THEN each failure is reported, and the drifting emulator's session is abandoned
+        assert_eq!(
+            "Emulator bridge \"U1\": emulator clock 5022 is out of step with simulation time 20",
+            drift_error
+        );
+        assert_eq!(
+            "Emulator bridge \"U1\": connection was abandoned after an earlier failure",
+            abandoned_error
+        );
+        assert_eq!(
+            "Emulator bridge \"U2\": Command \"set_irq_in gpio in 1 0\" failed: unknown command",
+            reject_error
+        );
+        assert!(absent_error.starts_with(&format!(
+            "Emulator bridge \"U3\": Cannot connect to emulator at \"{absent}\": "
+        )));
+    }
+}
@@ -0,0 +1,793 @@
+//! Elements implemented by external processes over a socket, so that models can be written in any language.
+//!
+//! # Protocol
+//!
+//! The simulator connects to the model process, which listens on a TCP port or, on Unix, a Unix domain socket.  Each
+//! message is a frame: a 32-bit little-endian length, followed by that many bytes of payload.  Integers in payloads
+//! are also little-endian, and names are a 32-bit length followed by that many bytes of UTF-8.
+//!
+//! 1. On connection, the model sends a **hello** frame describing itself: the protocol version ([PROTOCOL_VERSION])
+//!    as 32 bits, the number of input pins as 32 bits followed by their names, and the number of output pins as 32
+//!    bits followed by their names.
+//! 2. On every step, the simulator sends a **step** frame: the simulation time as 64 bits, counted from the start of
+//!    the Simulation, followed by a byte for each input pin: 0 for low, 1 for high, or 2 for indeterminate.  If the
+//!    Element has a [quantum](crate::element::cosim), a step frame is only sent when the quantum has passed since the
+//!    last, or the inputs have changed, so the time may advance by several steps at once.
+//! 3. The model replies with an **outputs** frame: a byte which is 1 if the model has finished the Simulation or
+//!    otherwise 0, followed by a byte for each output pin: 0 to drive it low, 1 to drive it high, or 2 to release it.
+//!
+//! The simulator closes the connection when it is done with the model.
+//!
+//! A model which does not reply within the Simulation's [phase timeout](crate::sim::Simulation::set_phase_timeout)
+//! fails the step, with the error attributed to its Element.  The Element's own timeout, which defaults to the same
+//! value, then abandons the connection, so the model cannot be used again.
+//!
+//! On Linux, the frames can instead be carried by the shared-memory transport of the `shm` module, when the `shm`
+//! feature is enabled.
+
+#[cfg(all(feature = "shm", target_os = "linux"))]
+pub mod shm;
+
+use crate::element::cosim::{quantum_keeper, QuantumKeeper, SyncStats};
+use crate::element::{bit, Element, Parameters, Registry};
+use crate::ipin::InputPin;
+use crate::opin::{OutputPin, OutputPinState};
+use crate::sim::{SimResult, DEFAULT_STEP_PHASE_TIMEOUT};
+use crate::time::SimDuration;
+use std::fmt;
+use std::io::{self, ErrorKind, Read, Write};
+use std::net::TcpStream;
+#[cfg(unix)]
+use std::os::unix::net::UnixStream;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+/// Version of the protocol, sent by the model in its hello frame.
+pub const PROTOCOL_VERSION: u32 = 1;
+/// Largest frame payload accepted, to bound the memory used by a misbehaving model.
+const MAX_FRAME_LENGTH: usize = 1 << 20;
+
+/// A connection carrying the frames of the protocol between the simulator and a model.
+pub trait Transport: Send {
+    /// Send a frame.
+    ///
+    /// # Parameters
+    ///
+    /// - `payload`: The payload of the frame.
+    /// - `timeout`: Maximum time to wait for the frame to be sent.
+    fn send(&mut self, payload: &[u8], timeout: Duration) -> Result<(), String>;
+
+    /// Receive a frame, returning its payload.
+    ///
+    /// # Parameters
+    ///
+    /// - `timeout`: Maximum time to wait for the whole frame.
+    fn receive(&mut self, timeout: Duration) -> Result<Vec<u8>, String>;
+}
+
+/// A byte stream socket with timeouts.
+pub(crate) trait Socket: Read + Write + Send {
+    /// Set the timeout of each read.
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()>;
+
+    /// Set the timeout of each write.
+    fn set_write_timeout(&self, timeout: Option<Duration>) -> io::Result<()>;
+}
+
+impl Socket for TcpStream {
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        TcpStream::set_read_timeout(self, timeout)
+    }
+
+    fn set_write_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        TcpStream::set_write_timeout(self, timeout)
+    }
+}
+
+#[cfg(unix)]
+impl Socket for UnixStream {
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        UnixStream::set_read_timeout(self, timeout)
+    }
+
+    fn set_write_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        UnixStream::set_write_timeout(self, timeout)
+    }
+}
+
+/// Send a frame on a socket.
+///
+/// # Parameters
+///
+/// - `socket`: The socket.
+/// - `payload`: The payload of the frame.
+/// - `timeout`: Maximum time to wait for each write.
+fn send_frame<S: Socket + ?Sized>(
+    socket: &mut S,
+    payload: &[u8],
+    timeout: Duration,
+) -> Result<(), String> {
+    let length = u32::try_from(payload.len()).map_err(|_| "Frame is too long".to_string())?;
+    socket
+        .set_write_timeout(Some(timeout))
+        .and_then(|_| socket.write_all(&length.to_le_bytes()))
+        .and_then(|_| socket.write_all(payload))
+        .and_then(|_| socket.flush())
+        .map_err(|error| format!("Cannot send frame: {error}"))
+}
+
+/// Fill a buffer from a socket before a deadline.
+///
+/// # Parameters
+///
+/// - `socket`: The socket.
+/// - `buffer`: The buffer to fill.
+/// - `deadline`: Time by which the buffer must be filled.
+fn read_before<S: Socket + ?Sized>(
+    socket: &mut S,
+    buffer: &mut [u8],
+    deadline: Instant,
+) -> Result<(), String> {
+    let mut filled = 0;
+    while filled < buffer.len() {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return Err("Timed out waiting for frame".to_string());
+        }
+        socket
+            .set_read_timeout(Some(remaining))
+            .map_err(|error| format!("Cannot receive frame: {error}"))?;
+        match socket.read(&mut buffer[filled..]) {
+            Ok(0) => return Err("Connection closed".to_string()),
+            Ok(count) => filled += count,
+            Err(error) if error.kind() == ErrorKind::Interrupted => {}
+            Err(error) if matches!(error.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {
+                return Err("Timed out waiting for frame".to_string())
+            }
+            Err(error) => return Err(format!("Cannot receive frame: {error}")),
+        }
+    }
+    Ok(())
+}
+
+/// Receive a frame from a socket.
+///
+/// # Parameters
+///
+/// - `socket`: The socket.
+/// - `timeout`: Maximum time to wait for the whole frame.
+fn receive_frame<S: Socket + ?Sized>(socket: &mut S, timeout: Duration) -> Result<Vec<u8>, String> {
+    let deadline = Instant::now() + timeout;
+    let mut length = [0; 4];
+    read_before(socket, &mut length, deadline)?;
+    let length = u32::from_le_bytes(length) as usize;
+    if length > MAX_FRAME_LENGTH {
+        return Err(format!(
+            "Frame of {length} bytes exceeds the limit of {MAX_FRAME_LENGTH}"
+        ));
+    }
+    let mut payload = vec![0; length];
+    read_before(socket, &mut payload, deadline)?;
+    Ok(payload)
+}
+
+impl Transport for TcpStream {
+    fn send(&mut self, payload: &[u8], timeout: Duration) -> Result<(), String> {
+        send_frame(self, payload, timeout)
+    }
+
+    fn receive(&mut self, timeout: Duration) -> Result<Vec<u8>, String> {
+        receive_frame(self, timeout)
+    }
+}
+
+#[cfg(unix)]
+impl Transport for UnixStream {
+    fn send(&mut self, payload: &[u8], timeout: Duration) -> Result<(), String> {
+        send_frame(self, payload, timeout)
+    }
+
+    fn receive(&mut self, timeout: Duration) -> Result<Vec<u8>, String> {
+        receive_frame(self, timeout)
+    }
+}
+
+impl Transport for Box<dyn Socket> {
+    fn send(&mut self, payload: &[u8], timeout: Duration) -> Result<(), String> {
+        send_frame(self.as_mut(), payload, timeout)
+    }
+
+    fn receive(&mut self, timeout: Duration) -> Result<Vec<u8>, String> {
+        receive_frame(self.as_mut(), timeout)
+    }
+}
+
+/// Open a socket to a process.
+///
+/// # Parameters
+///
+/// - `address`: Address of the process: `host:port` for TCP, or `unix:path` for a Unix domain socket.
+pub(crate) fn open_socket(address: &str) -> io::Result<Box<dyn Socket>> {
+    if let Some(path) = address.strip_prefix("unix:") {
+        #[cfg(unix)]
+        return Ok(Box::new(UnixStream::connect(path)?));
+        #[cfg(not(unix))]
+        return Err(io::Error::new(
+            ErrorKind::Unsupported,
+            format!("Unix domain sockets are not supported, for \"{path}\""),
+        ));
+    }
+    let stream = TcpStream::connect(address)?;
+    // Every message is sent as soon as it is complete, so there is nothing to gain from Nagle's algorithm.
+    stream.set_nodelay(true)?;
+    Ok(Box::new(stream))
+}
+
+/// Connect to a model process.
+///
+/// # Parameters
+///
+/// - `address`: Address of the model: `host:port` for TCP, `unix:path` for a Unix domain socket, or `shm:path` for a
+///   shared file, if the shared-memory transport is available.
+pub fn connect(address: &str) -> Result<Box<dyn Transport>, String> {
+    let error = |error: io::Error| format!("Cannot connect to model at \"{address}\": {error}");
+    #[cfg(all(feature = "shm", target_os = "linux"))]
+    if let Some(path) = address.strip_prefix("shm:") {
+        return Ok(Box::new(shm::SharedMemoryTransport::open(
+            std::path::Path::new(path),
+        )?));
+    }
+    Ok(Box::new(open_socket(address).map_err(error)?))
+}
+
+/// A reader of the fields of a frame payload.
+struct Fields<'a>(&'a [u8]);
+
+impl<'a> Fields<'a> {
+    /// Take a number of bytes.
+    ///
+    /// # Parameters
+    ///
+    /// - `count`: Number of bytes to take.
+    fn take(&mut self, count: usize) -> Result<&'a [u8], String> {
+        if self.0.len() < count {
+            return Err("Frame is truncated".to_string());
+        }
+        let (taken, rest) = self.0.split_at(count);
+        self.0 = rest;
+        Ok(taken)
+    }
+
+    /// Take a 32-bit integer.
+    fn u32(&mut self) -> Result<u32, String> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    /// Take a list of names, preceded by their number.
+    fn names(&mut self) -> Result<Vec<String>, String> {
+        (0..self.u32()?)
+            .map(|_| {
+                let length = self.u32()? as usize;
+                String::from_utf8(self.take(length)?.to_vec())
+                    .map_err(|_| "Name is not valid UTF-8".to_string())
+            })
+            .collect()
+    }
+
+    /// Check that every byte has been taken.
+    fn end(&self) -> Result<(), String> {
+        if self.0.is_empty() {
+            Ok(())
+        } else {
+            Err(format!(
+                "Frame has {} unexpected trailing bytes",
+                self.0.len()
+            ))
+        }
+    }
+}
+
+/// Decode the payload of a hello frame, returning the names of the model's input pins and output pins.
+///
+/// # Parameters
+///
+/// - `payload`: The payload of the frame.
+///
+/// # Example
+///
+/// ```
+/// # use rvfs_sim_core::element::external::decode_hello;
+/// let hello = [1, 0, 0, 0, 1, 0, 0, 0, 1, 0, 0, 0, b'A', 0, 0, 0, 0];
+///
+/// assert_eq!(Ok((vec!["A".to_string()], Vec::new())), decode_hello(&hello));
+/// assert!(decode_hello(&hello[..8]).is_err());
+/// ```
+pub fn decode_hello(payload: &[u8]) -> Result<(Vec<String>, Vec<String>), String> {
+    let mut fields = Fields(payload);
+    let version = fields.u32()?;
+    if version != PROTOCOL_VERSION {
+        return Err(format!(
+            "speaks protocol version {version}, expected {PROTOCOL_VERSION}"
+        ));
+    }
+    let inputs = fields.names()?;
+    let outputs = fields.names()?;
+    fields.end()?;
+    Ok((inputs, outputs))
+}
+
+/// Decode the payload of an outputs frame, returning the finished flag and the states of the output pins.
+///
+/// # Parameters
+///
+/// - `payload`: The payload of the frame.
+/// - `outputs`: Number of output pins of the model.
+pub fn decode_outputs(
+    payload: &[u8],
+    outputs: usize,
+) -> Result<(bool, Vec<OutputPinState>), String> {
+    let mut fields = Fields(payload);
+    let finished = match fields.take(1)?[0] {
+        0 => false,
+        1 => true,
+        flag => return Err(format!("Invalid finished flag {flag}")),
+    };
+    let states = fields
+        .take(outputs)?
+        .iter()
+        .map(|state| match state {
+            0 => Ok(OutputPinState::Low),
+            1 => Ok(OutputPinState::High),
+            2 => Ok(OutputPinState::HighImpedance),
+            state => Err(format!("Invalid output state {state}")),
+        })
+        .collect::<Result<_, _>>()?;
+    fields.end()?;
+    Ok((finished, states))
+}
+
+/// The connection to a model, shared by every copy of its Element.
+struct Connection {
+    /// The transport, or `None` once it has failed and been abandoned.
+    transport: Option<Box<dyn Transport>>,
+}
+
+/// An Element implemented by an external model process, exchanging pin states with it on every step.
+///
+/// The model is shared by every copy of the Element, so a Simulation holding the Element refuses to
+/// [step back](crate::sim::Simulation::step_back).  Any failure to exchange a frame, including a timeout, abandons the
+/// connection, since the frames which follow could no longer be matched to their steps.
+#[derive(Clone)]
+pub struct ExternalModel {
+    /// Name of the Element.
+    name: String,
+    /// Names of the input pins.
+    inputs: Vec<String>,
+    /// Names of the output pins.
+    outputs: Vec<String>,
+    /// Propagation delay from an input to the outputs.
+    delay: u64,
+    /// Maximum time to wait for each frame.
+    timeout: Duration,
+    /// The connection to the model.
+    connection: Arc<Mutex<Connection>>,
+    /// Decides when to exchange with the model.
+    keeper: QuantumKeeper,
+    /// The input pin states sent in the last step frame.
+    sent: Vec<u8>,
+    /// Simulation time elapsed since the start of the Simulation.
+    time: u64,
+}
+
+impl ExternalModel {
+    /// Create a new ExternalModel over a connected transport, receiving the model's hello frame.
+    ///
+    /// # Parameters
+    ///
+    /// - `name`: Name of the Element.
+    /// - `transport`: The transport connected to the model.
+    /// - `timeout`: Maximum time to wait for each frame.
+    /// - `delay`: Propagation delay from an input to the outputs.
+    pub fn new(
+        name: &str,
+        mut transport: Box<dyn Transport>,
+        timeout: Duration,
+        delay: u64,
+    ) -> Result<Self, String> {
+        let error = |error: String| format!("External model \"{name}\": {error}");
+        let hello = transport.receive(timeout).map_err(error)?;
+        let (inputs, outputs) = decode_hello(&hello).map_err(error)?;
+
+        Ok(Self {
+            name: name.to_string(),
+            inputs,
+            outputs,
+            delay,
+            timeout,
+            connection: Arc::new(Mutex::new(Connection {
+                transport: Some(transport),
+            })),
+            keeper: QuantumKeeper::default(),
+            sent: Vec::new(),
+            time: 0,
+        })
+    }
+
+    /// Let the Simulation run ahead of the model, exchanging with it only as a QuantumKeeper decides, rather than on
+    /// every step.
+    ///
+    /// # Parameters
+    ///
+    /// - `keeper`: Decides when to exchange with the model.
+    pub fn with_quantum_keeper(mut self, keeper: QuantumKeeper) -> Self {
+        self.keeper = keeper;
+        self
+    }
+
+    /// Obtain the statistics of the exchanges with the model so far.
+    pub fn sync_stats(&self) -> SyncStats {
+        self.keeper.stats()
+    }
+
+    /// Create a new ExternalModel by connecting to a model process.
+    ///
+    /// # Parameters
+    ///
+    /// - `name`: Name of the Element.
+    /// - `address`: Address of the model, as for [connect].
+    /// - `timeout`: Maximum time to wait for each frame.
+    /// - `delay`: Propagation delay from an input to the outputs.
+    pub fn connect(
+        name: &str,
+        address: &str,
+        timeout: Duration,
+        delay: u64,
+    ) -> Result<Self, String> {
+        Self::new(
+            name,
+            connect(address).map_err(|error| format!("External model \"{name}\": {error}"))?,
+            timeout,
+            delay,
+        )
+    }
+
+    /// Exchange a step frame for an outputs frame, returning the finished flag and the output states.
+    ///
+    /// # Parameters
+    ///
+    /// - `transport`: The transport connected to the model.
+    /// - `step`: The payload of the step frame.
+    fn exchange(
+        &self,
+        transport: &mut dyn Transport,
+        step: &[u8],
+    ) -> Result<(bool, Vec<OutputPinState>), String> {
+        transport.send(step, self.timeout)?;
+        let reply = transport.receive(self.timeout)?;
+        decode_outputs(&reply, self.outputs.len())
+    }
+}
+
+impl fmt::Debug for ExternalModel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ExternalModel")
+            .field("name", &self.name)
+            .field("inputs", &self.inputs)
+            .field("outputs", &self.outputs)
+            .field("delay", &self.delay)
+            .field("timeout", &self.timeout)
+            .field("time", &self.time)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Element for ExternalModel {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn input_pins(&self) -> Vec<InputPin> {
+        self.inputs.iter().map(|name| InputPin::new(name)).collect()
+    }
+
+    fn output_pins(&self) -> Vec<OutputPin> {
+        self.outputs
+            .iter()
+            .map(|name| {
+                OutputPin::new(
+                    name,
+                    SimDuration::from_ticks(self.delay),
+                    OutputPinState::HighImpedance,
+                )
+            })
+            .collect()
+    }
+
+    fn step(
+        &mut self,
+        inputs: &[InputPin],
+        outputs: &mut [OutputPin],
+        delta_t: u64,
+    ) -> Result<SimResult, String> {
+        self.time += delta_t;
+        let states: Vec<u8> = inputs
+            .iter()
+            .map(|pin| match bit(pin) {
+                Some(false) => 0,
+                Some(true) => 1,
+                None => 2,
+            })
+            .collect();
+        if !self.keeper.due(self.time, states != self.sent) {
+            return Ok(SimResult::Continuing);
+        }
+        let mut step = self.time.to_le_bytes().to_vec();
+        step.extend(&states);
+        self.sent = states;
+
+        let error = |error: String| format!("External model \"{}\": {error}", self.name);
+        let mut connection = self
+            .connection
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        let transport = connection.transport.as_deref_mut().ok_or_else(|| {
+            error("connection was abandoned after an earlier failure".to_string())
+        })?;
+        let mut keeper = std::mem::take(&mut self.keeper);
+        let reply = keeper.synchronise(self.time, |_| self.exchange(transport, &step));
+        self.keeper = keeper;
+        let (finished, states) = match reply {
+            Ok(reply) => reply,
+            Err(message) => {
+                connection.transport = None;
+                return Err(error(message));
+            }
+        };
+        drop(connection);
+
+        for (output, state) in outputs.iter_mut().zip(states) {
+            output.drive(state);
+        }
+        Ok(if finished {
+            SimResult::Finished
+        } else {
+            SimResult::Continuing
+        })
+    }
+
+    fn is_replayable(&self) -> bool {
+        false
+    }
+
+    fn box_clone(&self) -> Box<dyn Element> {
+        Box::new(self.clone())
+    }
+}
+
+/// Register every kind of external element.
+///
+/// The `external` kind takes the `address` parameter, giving the address of the model process as for [connect], the
+/// `timeout` parameter in milliseconds (default the Simulation's default phase timeout), the `delay` parameter
+/// (default 0), and the `quantum` and `stall` parameters of its [QuantumKeeper]: the quantum in simulation time
+/// (default 0, exchanging on every step) and the stall threshold in milliseconds.
+///
+/// # Parameters
+///
+/// - `registry`: The Registry to add the external elements to.
+pub fn register(registry: &mut Registry) {
+    registry.register("external", |name, parameters: &Parameters| {
+        let address: String = parameters.get_or("address", String::new())?;
+        let timeout =
+            parameters.get_or("timeout", DEFAULT_STEP_PHASE_TIMEOUT.as_millis() as u64)?;
+        Ok(Box::new(
+            ExternalModel::connect(
+                name,
+                &address,
+                Duration::from_millis(timeout),
+                parameters.get_or("delay", 0)?,
+            )?
+            .with_quantum_keeper(quantum_keeper(parameters)?),
+        ))
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::element::clocks::ClockGenerator;
+    use crate::sim::Simulation;
+    use crate::wire::{Wire, WirePull};
+    use std::net::TcpListener;
+    use std::thread::{self, JoinHandle};
+
+    /// Encode a hello frame.
+    fn hello(version: u32, inputs: &[&str], outputs: &[&str]) -> Vec<u8> {
+        let mut payload = version.to_le_bytes().to_vec();
+        for names in [inputs, outputs] {
+            payload.extend((names.len() as u32).to_le_bytes());
+            for name in names {
+                payload.extend((name.len() as u32).to_le_bytes());
+                payload.extend(name.as_bytes());
+            }
+        }
+        payload
+    }
+
+    /// Serve a model on a local TCP port, returning its address and a handle yielding the step frames it received.
+    ///
+    /// The model sends the hello frame, then replies to each step frame with the reply function's outputs frame, until
+    /// the connection is closed.
+    fn serve(
+        hello: Vec<u8>,
+        reply: fn(&[u8]) -> Option<Vec<u8>>,
+    ) -> (String, JoinHandle<Vec<Vec<u8>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        let handle = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let timeout = Duration::from_secs(5);
+            stream.send(&hello, timeout).unwrap();
+            let mut steps = Vec::new();
+            while let Ok(step) = stream.receive(timeout) {
+                let Some(outputs) = reply(&step) else {
+                    thread::sleep(Duration::from_millis(200));
+                    continue;
+                };
+                steps.push(step);
+                stream.send(&outputs, timeout).unwrap();
+            }
+            steps
+        });
+        (address, handle)
+    }
+
+    #[test]
+    fn external_model_in_simulation() {
+        // GIVEN an inverter served by an external process, driven by a clock through the registry
+        let (address, model) = serve(hello(PROTOCOL_VERSION, &["A"], &["Y"]), |step| {
+            Some(vec![0, 1 - step[8].min(1)])
+        });
+        let mut sim = Simulation::new(SimDuration::from_ticks(10));
+        let parameters = Parameters::new().with("address", &address);
+        let inverter = sim
+            .add_element(
+                Registry::standard()
+                    .create("external", "U1", &parameters)
+                    .unwrap(),
+            )
+            .unwrap();
+        let clock = sim
+            .add_element(Box::new(
+                ClockGenerator::new("X1", 40.0, 0.5, 0.0, 0, 0.0).unwrap(),
+            ))
+            .unwrap();
+        let clk = sim.add_wire(Wire::new("CLK", WirePull::None)).unwrap();
+        let clk_bar = sim.add_wire(Wire::new("/CLK", WirePull::None)).unwrap();
+        sim.connect_output(sim.output_pin(clock, "CLK").unwrap(), clk)
+            .unwrap();
+        sim.connect_input(clk, sim.input_pin(inverter, "A").unwrap())
+            .unwrap();
+        sim.connect_output(sim.output_pin(inverter, "Y").unwrap(), clk_bar)
+            .unwrap();
+        // WHEN it is stepped, and then dropped to close the connection
+        let levels: Vec<f32> = (0..6)
+            .map(|_| {
+                sim.step().unwrap();
+                sim.wire(clk_bar).unwrap().measure().into()
+            })
+            .collect();
+        drop(sim);
+        // THEN the model receives the time and input states of each step, and drives the inverted clock
+        let steps = model.join().unwrap();
+        assert_eq!(
+            vec![(10, 2), (20, 1), (30, 1), (40, 0), (50, 0), (60, 1)],
+            steps
+                .iter()
+                .map(|step| (u64::from_le_bytes(step[..8].try_into().unwrap()), step[8]))
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(vec![0.0, 0.0, 0.0, 1.0, 1.0, 0.0], levels);
+    }
+    #[test]
+    fn external_model_quantum() {
+        // GIVEN an inverter served by an external process with a quantum of three steps, driven by a slow clock
+        let (address, model) = serve(hello(PROTOCOL_VERSION, &["A"], &["Y"]), |step| {
+            Some(vec![0, 1 - step[8].min(1)])
+        });
+        let mut sim = Simulation::new(SimDuration::from_ticks(10));
+        let inverter = ExternalModel::connect("U1", &address, Duration::from_secs(1), 0)
+            .unwrap()
+            .with_quantum_keeper(QuantumKeeper::new(30));
+        let keeper = inverter.keeper.clone();
+        let inverter = sim.add_element(Box::new(inverter)).unwrap();
+        let clock = sim
+            .add_element(Box::new(
+                ClockGenerator::new("X1", 120.0, 0.5, 0.0, 0, 0.0).unwrap(),
+            ))
+            .unwrap();
+        let clk = sim.add_wire(Wire::new("CLK", WirePull::None)).unwrap();
+        let clk_bar = sim.add_wire(Wire::new("/CLK", WirePull::None)).unwrap();
+        sim.connect_output(sim.output_pin(clock, "CLK").unwrap(), clk)
+            .unwrap();
+        sim.connect_input(clk, sim.input_pin(inverter, "A").unwrap())
+            .unwrap();
+        sim.connect_output(sim.output_pin(inverter, "Y").unwrap(), clk_bar)
+            .unwrap();
+        // WHEN it is stepped, and then dropped to close the connection
+        let levels: Vec<f32> = (0..12)
+            .map(|_| {
+                sim.step().unwrap();
+                sim.wire(clk_bar).unwrap().measure().into()
+            })
+            .collect();
+        drop(sim);
+        // THEN the model is stepped once per quantum, and early whenever its input changes
+        let steps = model.join().unwrap();
+        assert_eq!(
+            vec![(10, 2), (20, 1), (50, 1), (80, 0), (110, 0)],
+            steps
+                .iter()
+                .map(|step| (u64::from_le_bytes(step[..8].try_into().unwrap()), step[8]))
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(
+            vec![0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 1.0, 1.0, 1.0, 1.0, 1.0],
+            levels
+        );
+        let stats = keeper.stats();
+        assert_eq!((5, 2, 7), (stats.syncs, stats.early, stats.skipped));
+    }
+    #[test]
+    fn external_model_finish() {
+        // GIVEN a model which finishes the Simulation at its second step
+        let (address, _model) = serve(hello(PROTOCOL_VERSION, &[], &["Y"]), |step| {
+            let time = u64::from_le_bytes(step[..8].try_into().unwrap());
+            Some(vec![u8::from(time >= 20), 2])
+        });
+        let mut sim = Simulation::new(SimDuration::from_ticks(10));
+        sim.add_element(Box::new(
+            ExternalModel::connect("U1", &address, Duration::from_secs(1), 0).unwrap(),
+        ))
+        .unwrap();
+        // WHEN it is stepped
+        // THEN the second step finishes the Simulation
+        assert_eq!(SimResult::Continuing, sim.step().unwrap());
+        assert_eq!(SimResult::Finished, sim.step().unwrap());
+    }
+    #[test]
+    fn external_model_errors() {
+        // GIVEN a model speaking another protocol version, a model which is too slow to reply, and no model at all
+        let (old, _old_model) = serve(hello(PROTOCOL_VERSION + 1, &[], &[]), |_| None);
+        let (slow, _slow_model) = serve(hello(PROTOCOL_VERSION, &[], &["Y"]), |_| None);
+        let unused = TcpListener::bind("127.0.0.1:0").unwrap();
+        let absent = unused.local_addr().unwrap().to_string();
+        drop(unused);
+        // WHEN they are connected, and the slow model is stepped twice
+        let timeout = Duration::from_millis(50);
+        let old_error = ExternalModel::connect("U1", &old, timeout, 0).unwrap_err();
+        let absent_error = ExternalModel::connect("U2", &absent, timeout, 0).unwrap_err();
+        let mut model = ExternalModel::connect("U3", &slow, timeout, 0).unwrap();
+        let mut outputs = model.output_pins();
+        let timeout_error = model.step(&[], &mut outputs, 10).unwrap_err();
+        let abandoned_error = model.step(&[], &mut outputs, 10).unwrap_err();
+        // THEN each failure is reported, and the slow model's connection is abandoned
+        assert_eq!(
+            format!(
+                "External model \"U1\": speaks protocol version {}, expected {PROTOCOL_VERSION}",
+                PROTOCOL_VERSION + 1
+            ),
+            old_error
+        );
+        assert!(absent_error.starts_with(&format!(
+            "External model \"U2\": Cannot connect to model at \"{absent}\": "
+        )));
+        assert_eq!(
+            "External model \"U3\": Timed out waiting for frame",
+            timeout_error
+        );
+        assert_eq!(
+            "External model \"U3\": connection was abandoned after an earlier failure",
+            abandoned_error
+        );
+    }
+}
@@ -0,0 +1,510 @@
+//! A shared-memory transport for external models, for step rates at which the system calls of a socket dominate.
+//!
+//! The model creates a file, usually under `/dev/shm`, which both processes map.  It holds a ring buffer in each
+//! direction, carrying the same frames as the socket protocol.  A frame is published by advancing the head of its
+//! ring, and consumed by advancing the tail, so an exchange needs no system calls while both processes are running.
+//! A process waiting on an empty or full ring spins briefly, then sleeps on a futex, which the other process wakes
+//! after advancing the ring.
+//!
+//! The file is laid out as follows, with every integer in native byte order:
+//!
+//! | Offset               | Size       | Content                                                               |
+//! |----------------------|------------|-----------------------------------------------------------------------|
+//! | 0                    | 4          | Magic number `RVFS`, written last by the model once initialised       |
+//! | 4                    | 4          | Layout version ([LAYOUT_VERSION])                                     |
+//! | 8                    | 4          | Capacity of each ring in bytes, a power of two                        |
+//! | 12                   | 4          | Closed flag, set by either process when it is done                    |
+//! | 64                   | 8          | Head and tail of the ring from the simulator to the model             |
+//! | 128                  | 8          | Head and tail of the ring from the model to the simulator             |
+//! | 192                  | capacity   | Data of the ring from the simulator to the model                      |
+//! | 192 + capacity       | capacity   | Data of the ring from the model to the simulator                      |
+//!
+//! Heads and tails count bytes, wrapping at 2<sup>32</sup>, and each is the futex waited on for its ring to change.
+
+use crate::element::external::Transport;
+use std::fs::{File, OpenOptions};
+use std::hint;
+use std::os::fd::AsRawFd;
+use std::path::Path;
+use std::ptr;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::{Duration, Instant};
+
+/// Version of the layout of the shared file.
+pub const LAYOUT_VERSION: u32 = 1;
+/// Default capacity of each ring in bytes.
+pub const DEFAULT_CAPACITY: u32 = 1 << 16;
+/// Magic number identifying an initialised shared file.
+const MAGIC: u32 = u32::from_le_bytes(*b"RVFS");
+/// Offset of the control words of each ring.
+const CONTROL_OFFSETS: [usize; 2] = [64, 128];
+/// Offset of the data of the first ring.
+const DATA_OFFSET: usize = 192;
+/// Number of times to poll a ring before sleeping on its futex.
+const SPIN_LIMIT: u32 = 1000;
+
+/// The side of the shared file used by a process.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Side {
+    /// The simulator, sending on the first ring and receiving on the second.
+    Simulator,
+    /// The model, sending on the second ring and receiving on the first.
+    Model,
+}
+
+/// A Transport over a shared file mapped by the simulator and the model.
+pub struct SharedMemoryTransport {
+    /// Start of the mapping.
+    base: *mut u8,
+    /// Length of the mapping.
+    length: usize,
+    /// Capacity of each ring in bytes.
+    capacity: u32,
+    /// Which side of the file this process uses.
+    side: Side,
+}
+
+// The mapping is only accessed through atomics and byte copies bounded by its length, so it can be used from any
+// thread, and a SharedMemoryTransport is used from one thread at a time, since Transport methods take it by reference.
+unsafe impl Send for SharedMemoryTransport {}
+
+impl SharedMemoryTransport {
+    /// Create a shared file for a model, replacing any existing file, and map it.
+    ///
+    /// # Parameters
+    ///
+    /// - `path`: Path of the shared file.
+    /// - `capacity`: Capacity of each ring in bytes, which must be a power of two, of at least 16 bytes.
+    pub fn create(path: &Path, capacity: u32) -> Result<Self, String> {
+        if !capacity.is_power_of_two() || capacity < 16 {
+            return Err(format!(
+                "Ring capacity {capacity} is not a power of two of at least 16 bytes"
+            ));
+        }
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(path)
+            .and_then(|file| {
+                file.set_len((DATA_OFFSET + 2 * capacity as usize) as u64)?;
+                Ok(file)
+            })
+            .map_err(|error| format!("Cannot create \"{}\": {error}", path.display()))?;
+        let mut transport = Self::map(path, &file, Side::Model)?;
+        transport.capacity = capacity;
+
+        transport.word(4).store(LAYOUT_VERSION, Ordering::Relaxed);
+        transport.word(8).store(capacity, Ordering::Relaxed);
+        transport.word(0).store(MAGIC, Ordering::Release);
+        Ok(transport)
+    }
+
+    /// Open the shared file created by a model, and map it.
+    ///
+    /// # Parameters
+    ///
+    /// - `path`: Path of the shared file.
+    pub fn open(path: &Path) -> Result<Self, String> {
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(path)
+            .map_err(|error| format!("Cannot open \"{}\": {error}", path.display()))?;
+        let mut transport = Self::map(path, &file, Side::Simulator)?;
+
+        if transport.word(0).load(Ordering::Acquire) != MAGIC {
+            return Err(format!(
+                "\"{}\" is not an initialised shared file",
+                path.display()
+            ));
+        }
+        let version = transport.word(4).load(Ordering::Relaxed);
+        if version != LAYOUT_VERSION {
+            return Err(format!(
+                "\"{}\" has layout version {version}, expected {LAYOUT_VERSION}",
+                path.display()
+            ));
+        }
+        let capacity = transport.word(8).load(Ordering::Relaxed);
+        if !capacity.is_power_of_two() || DATA_OFFSET + 2 * capacity as usize > transport.length {
+            return Err(format!(
+                "\"{}\" has an invalid ring capacity {capacity}",
+                path.display()
+            ));
+        }
+        transport.capacity = capacity;
+        Ok(transport)
+    }
+
+    /// Map a shared file, before its capacity is known.
+    ///
+    /// # Parameters
+    ///
+    /// - `path`: Path of the shared file.
+    /// - `file`: The open shared file.
+    /// - `side`: Which side of the file this process uses.
+    fn map(path: &Path, file: &File, side: Side) -> Result<Self, String> {
+        let error = |error: std::io::Error| format!("Cannot map \"{}\": {error}", path.display());
+        let length = file.metadata().map_err(error)?.len() as usize;
+        if length < DATA_OFFSET {
+            return Err(format!("\"{}\" is too short", path.display()));
+        }
+        let base = unsafe {
+            libc::mmap(
+                ptr::null_mut(),
+                length,
+                libc::PROT_READ | libc::PROT_WRITE,
+                libc::MAP_SHARED,
+                file.as_raw_fd(),
+                0,
+            )
+        };
+        if base == libc::MAP_FAILED {
+            return Err(error(std::io::Error::last_os_error()));
+        }
+
+        Ok(Self {
+            base: base.cast(),
+            length,
+            capacity: 0,
+            side,
+        })
+    }
+
+    /// Obtain a 32-bit word of the mapping.
+    ///
+    /// # Parameters
+    ///
+    /// - `offset`: Offset of the word, which must be aligned and within the control area.
+    fn word(&self, offset: usize) -> &AtomicU32 {
+        debug_assert!(offset.is_multiple_of(4) && offset + 4 <= DATA_OFFSET);
+        unsafe { &*self.base.add(offset).cast::<AtomicU32>() }
+    }
+
+    /// Obtain the head, tail and data offset of a ring.
+    ///
+    /// # Parameters
+    ///
+    /// - `ring`: Index of the ring: 0 from the simulator to the model, or 1 from the model to the simulator.
+    fn ring(&self, ring: usize) -> (&AtomicU32, &AtomicU32, usize) {
+        (
+            self.word(CONTROL_OFFSETS[ring]),
+            self.word(CONTROL_OFFSETS[ring] + 4),
+            DATA_OFFSET + ring * self.capacity as usize,
+        )
+    }
+
+    /// Query whether either process has closed the shared file.
+    fn closed(&self) -> bool {
+        self.word(12).load(Ordering::Acquire) != 0
+    }
+
+    /// Wait for a word to change from a value, or for the shared file to be closed.
+    ///
+    /// # Parameters
+    ///
+    /// - `word`: The word.
+    /// - `value`: The value it held when last read.
+    /// - `deadline`: Time by which the word must change.
+    fn wait(&self, word: &AtomicU32, value: u32, deadline: Instant) -> Result<(), String> {
+        for _ in 0..SPIN_LIMIT {
+            if word.load(Ordering::Acquire) != value {
+                return Ok(());
+            }
+            hint::spin_loop();
+        }
+        while word.load(Ordering::Acquire) == value {
+            if self.closed() {
+                return Err("Connection closed".to_string());
+            }
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                return Err("Timed out waiting for frame".to_string());
+            }
+            // The futex is shared between processes, so it must not be private.  Spurious, interrupted and timed out
+            // waits are all resolved by checking the word and deadline again.
+            let timeout = libc::timespec {
+                tv_sec: remaining.as_secs() as libc::time_t,
+                tv_nsec: remaining.subsec_nanos() as libc::c_long,
+            };
+            unsafe {
+                libc::syscall(
+                    libc::SYS_futex,
+                    word.as_ptr(),
+                    libc::FUTEX_WAIT,
+                    value,
+                    &timeout,
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Wake every process waiting on a word.
+    ///
+    /// # Parameters
+    ///
+    /// - `word`: The word.
+    fn wake(word: &AtomicU32) {
+        unsafe {
+            libc::syscall(libc::SYS_futex, word.as_ptr(), libc::FUTEX_WAKE, i32::MAX);
+        }
+    }
+
+    /// Copy bytes into a ring, wrapping at its end.
+    ///
+    /// # Parameters
+    ///
+    /// - `data`: Offset of the data of the ring.
+    /// - `position`: Position in the ring to copy to, which is reduced modulo its capacity.
+    /// - `bytes`: The bytes, no more than the capacity of the ring.
+    fn write_ring(&self, data: usize, position: u32, bytes: &[u8]) {
+        let start = (position & (self.capacity - 1)) as usize;
+        let first = bytes.len().min(self.capacity as usize - start);
+        unsafe {
+            ptr::copy_nonoverlapping(bytes.as_ptr(), self.base.add(data + start), first);
+            ptr::copy_nonoverlapping(
+                bytes[first..].as_ptr(),
+                self.base.add(data),
+                bytes.len() - first,
+            );
+        }
+    }
+
+    /// Copy bytes out of a ring, wrapping at its end.
+    ///
+    /// # Parameters
+    ///
+    /// - `data`: Offset of the data of the ring.
+    /// - `position`: Position in the ring to copy from, which is reduced modulo its capacity.
+    /// - `bytes`: Buffer for the bytes, no longer than the capacity of the ring.
+    fn read_ring(&self, data: usize, position: u32, bytes: &mut [u8]) {
+        let start = (position & (self.capacity - 1)) as usize;
+        let first = bytes.len().min(self.capacity as usize - start);
+        unsafe {
+            ptr::copy_nonoverlapping(self.base.add(data + start), bytes.as_mut_ptr(), first);
+            ptr::copy_nonoverlapping(
+                self.base.add(data),
+                bytes[first..].as_mut_ptr(),
+                bytes.len() - first,
+            );
+        }
+    }
+}
+
+impl Drop for SharedMemoryTransport {
+    fn drop(&mut self) {
+        // A file which failed validation on opening is left alone, since its model may still be initialising it.
+        if self.capacity != 0 {
+            self.word(12).store(1, Ordering::Release);
+            for ring in 0..2 {
+                let (head, tail, _) = self.ring(ring);
+                Self::wake(head);
+                Self::wake(tail);
+            }
+        }
+        unsafe {
+            libc::munmap(self.base.cast(), self.length);
+        }
+    }
+}
+
+impl Transport for SharedMemoryTransport {
+    fn send(&mut self, payload: &[u8], timeout: Duration) -> Result<(), String> {
+        let deadline = Instant::now() + timeout;
+        let (head, tail, data) = self.ring(match self.side {
+            Side::Simulator => 0,
+            Side::Model => 1,
+        });
+        let needed = 4 + payload.len();
+        if needed > self.capacity as usize {
+            return Err(format!(
+                "Frame of {} bytes exceeds the ring capacity of {}",
+                payload.len(),
+                self.capacity
+            ));
+        }
+
+        // Only this process advances the head, and only the other advances the tail.
+        let position = head.load(Ordering::Relaxed);
+        loop {
+            if self.closed() {
+                return Err("Connection closed".to_string());
+            }
+            let consumed = tail.load(Ordering::Acquire);
+            if (self.capacity - position.wrapping_sub(consumed)) as usize >= needed {
+                break;
+            }
+            self.wait(tail, consumed, deadline)?;
+        }
+        self.write_ring(data, position, &(payload.len() as u32).to_ne_bytes());
+        self.write_ring(data, position.wrapping_add(4), payload);
+        head.store(position.wrapping_add(needed as u32), Ordering::Release);
+        Self::wake(head);
+        Ok(())
+    }
+
+    fn receive(&mut self, timeout: Duration) -> Result<Vec<u8>, String> {
+        let deadline = Instant::now() + timeout;
+        let (head, tail, data) = self.ring(match self.side {
+            Side::Simulator => 1,
+            Side::Model => 0,
+        });
+
+        // Only this process advances the tail, and frames are published whole by advancing the head.
+        let position = tail.load(Ordering::Relaxed);
+        let published = loop {
+            let published = head.load(Ordering::Acquire);
+            if published != position {
+                break published;
+            }
+            self.wait(head, published, deadline)?;
+        };
+        let available = published.wrapping_sub(position) as usize;
+        let mut length = [0; 4];
+        self.read_ring(data, position, &mut length);
+        let length = u32::from_ne_bytes(length) as usize;
+        if available < 4 || available - 4 < length {
+            return Err(format!(
+                "Frame of {length} bytes overruns the {available} bytes published"
+            ));
+        }
+        let mut payload = vec![0; length];
+        self.read_ring(data, position.wrapping_add(4), &mut payload);
+        tail.store(position.wrapping_add(4 + length as u32), Ordering::Release);
+        Self::wake(tail);
+        Ok(payload)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::element::external::PROTOCOL_VERSION;
+    use crate::element::testing;
+    use crate::element::{Parameters, Registry};
+    use crate::opin::OutputPinState;
+    use crate::sim::SimResult;
+    use crate::time::SimDuration;
+    use std::path::PathBuf;
+    use std::{env, fs, process, thread};
+
+    /// Obtain a path for a shared file, unique to a test.
+    fn shared_path(test: &str) -> PathBuf {
+        env::temp_dir().join(format!("rvfs-sim-shm-{}-{test}", process::id()))
+    }
+
+    #[test]
+    fn shm_frames() {
+        // GIVEN a small shared file, created by a model and opened by the simulator
+        let path = shared_path("frames");
+        let mut model = SharedMemoryTransport::create(&path, 16).unwrap();
+        let mut simulator = SharedMemoryTransport::open(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        let timeout = Duration::from_secs(1);
+        // WHEN frames are sent each way, wrapping around the rings, and a frame too large for a ring is sent
+        let received: Vec<Vec<u8>> = (0..5u8)
+            .flat_map(|i| {
+                simulator.send(&[i; 7], timeout).unwrap();
+                model.send(&[i + 1; 3], timeout).unwrap();
+                [
+                    model.receive(timeout).unwrap(),
+                    simulator.receive(timeout).unwrap(),
+                ]
+            })
+            .collect();
+        let too_large = simulator.send(&[0; 13], timeout).unwrap_err();
+        // THEN every frame arrives intact, and the large frame is rejected
+        for (i, frames) in received.chunks(2).enumerate() {
+            assert_eq!(vec![i as u8; 7], frames[0]);
+            assert_eq!(vec![i as u8 + 1; 3], frames[1]);
+        }
+        assert_eq!(
+            "Frame of 13 bytes exceeds the ring capacity of 16",
+            too_large
+        );
+    }
+    #[test]
+    fn shm_external_model() {
+        // GIVEN a buffer model served over a shared file by another thread
+        let path = shared_path("model");
+        let mut transport = SharedMemoryTransport::create(&path, DEFAULT_CAPACITY).unwrap();
+        let model = thread::spawn(move || {
+            let timeout = Duration::from_secs(5);
+            let mut hello = PROTOCOL_VERSION.to_le_bytes().to_vec();
+            for name in ["A", "Y"] {
+                hello.extend(1u32.to_le_bytes());
+                hello.extend(1u32.to_le_bytes());
+                hello.extend(name.as_bytes());
+            }
+            transport.send(&hello, timeout).unwrap();
+            let mut steps = 0;
+            while let Ok(step) = transport.receive(timeout) {
+                transport.send(&[0, step[8]], timeout).unwrap();
+                steps += 1;
+            }
+            steps
+        });
+        // WHEN it is created through the registry and stepped with each input state, then dropped
+        let parameters = Parameters::new().with("address", &format!("shm:{}", path.display()));
+        let mut buffer = Registry::standard()
+            .create("external", "U1", &parameters)
+            .unwrap();
+        fs::remove_file(&path).unwrap();
+        let mut outputs = buffer.output_pins();
+        let states: Vec<_> = [[0.0, 0.0], [0.0, 1.0], [1.0, 0.5]]
+            .into_iter()
+            .map(|levels| {
+                let inputs = testing::inputs([levels[0]], [levels[1]]);
+                assert_eq!(
+                    SimResult::Continuing,
+                    buffer.step(&inputs, &mut outputs, 10).unwrap()
+                );
+                outputs[0].step(SimDuration::from_ticks(0));
+                outputs[0].state()
+            })
+            .collect();
+        drop(buffer);
+        // THEN the model follows the input, releasing its output when the input is indeterminate, and sees the close
+        assert_eq!(
+            vec![
+                OutputPinState::Low,
+                OutputPinState::High,
+                OutputPinState::HighImpedance
+            ],
+            states
+        );
+        assert_eq!(3, model.join().unwrap());
+    }
+    #[test]
+    fn shm_errors() {
+        // GIVEN a missing file, an uninitialised file, and a shared file whose model never replies
+        let missing = shared_path("missing");
+        let uninitialised = shared_path("uninitialised");
+        fs::write(&uninitialised, [0; 256]).unwrap();
+        let silent = shared_path("silent");
+        let mut model = SharedMemoryTransport::create(&silent, 64).unwrap();
+        // WHEN they are opened, and the silent one waited on
+        let missing_error = SharedMemoryTransport::open(&missing).err().unwrap();
+        let uninitialised_error = SharedMemoryTransport::open(&uninitialised).err().unwrap();
+        let mut simulator = SharedMemoryTransport::open(&silent).unwrap();
+        let timeout_error = simulator.receive(Duration::from_millis(20)).unwrap_err();
+        drop(simulator);
+        let closed_error = model.receive(Duration::from_secs(1)).unwrap_err();
+        fs::remove_file(&uninitialised).unwrap();
+        fs::remove_file(&silent).unwrap();
+        // THEN each failure is reported, and the model sees the simulator close the file
+        assert!(missing_error.starts_with(&format!("Cannot open \"{}\": ", missing.display())));
+        assert_eq!(
+            format!(
+                "\"{}\" is not an initialised shared file",
+                uninitialised.display()
+            ),
+            uninitialised_error
+        );
+        assert_eq!("Timed out waiting for frame", timeout_error);
+        assert_eq!("Connection closed", closed_error);
+    }
+}
@@ -0,0 +1,767 @@
+//! Edge-triggered flip-flops.
+//!
+//! Timing violations are modelled at the resolution of a step.  An input which is indeterminate when it is sampled at a
+//! clock edge, such as a Wire still slewing between levels because its setup time was not met, leaves the stored bit
+//! unknown, as a real flip-flop may go metastable and settle either way.  An unknown bit drives the outputs unknown (X)
+//! and persists until the flip-flop is set, reset or clocked with definite inputs.
+
+use crate::element::{bit, drive_outputs, level, Element, Parameters, Registry};
+use crate::ipin::{InputPin, InputPinState};
+use crate::opin::{OutputPin, OutputPinState};
+use crate::prelude::*;
+use crate::sim::SimResult;
+use crate::time::SimDuration;
+
+/// Step a positive edge-triggered flip-flop with active-low asynchronous set and reset, and drive its outputs.
+///
+/// The asynchronous inputs override the clock while they are low, and an unconnected one is treated as inactive.
+/// Holding both low drives both outputs high, as the 7474 does, and loses the stored bit.
+///
+/// # Parameters
+///
+/// - `state`: The stored bit, if known, which is updated.
+/// - `clk`: The clock input.
+/// - `set`: The active-low asynchronous set input.
+/// - `reset`: The active-low asynchronous reset input.
+/// - `next`: Function calculating the bit to store at a rising clock edge from the present stored bit.
+/// - `q`: The true output.
+/// - `q_bar`: The complementary output.
+fn clock<F>(
+    state: &mut Option<bool>,
+    clk: &InputPin,
+    set: &InputPin,
+    reset: &InputPin,
+    next: F,
+    q: &mut OutputPin,
+    q_bar: &mut OutputPin,
+) where
+    F: FnOnce(Option<bool>) -> Option<bool>,
+{
+    let set = set.state() == InputPinState::Low;
+    let reset = reset.state() == InputPinState::Low;
+    match (set, reset) {
+        (true, true) => {
+            *state = None;
+            q.drive(OutputPinState::High);
+            q_bar.drive(OutputPinState::High);
+            return;
+        }
+        (true, false) => *state = Some(true),
+        (false, true) => *state = Some(false),
+        (false, false) => {
+            if clk.rising() {
+                *state = next(*state);
+            }
+        }
+    }
+    drive_outputs(*state, q, q_bar);
+}
+
+/// A positive edge-triggered D flip-flop with asynchronous set and reset, modelled on one half of a 7474.
+///
+/// The inputs are named `CLK`, `D`, `/SET` and `/RESET`, and the outputs are named `Q` and `/Q`.  On each rising edge
+/// of `CLK` the level of `D` is stored and appears on the outputs after the clock-to-Q delay.  The active-low `/SET`
+/// and `/RESET` inputs override the clock while they are low; holding both low drives both outputs high, as the 7474
+/// does.  An unconnected `/SET` or `/RESET` is treated as inactive.
+///
+/// The stored bit is unknown until the flip-flop is first clocked, set or reset, and becomes unknown again if `D` is
+/// indeterminate at a clock edge.  While it is unknown the outputs are driven unknown (X).
+///
+/// # Example
+///
+/// ```
+/// # use rvfs_sim_core::element::flipflops::DFlipFlop;
+/// # use rvfs_sim_core::sim::Simulation;
+/// # use rvfs_sim_core::time::SimDuration;
+/// # use rvfs_sim_core::wire::{Wire, WirePull};
+/// let mut sim = Simulation::new(SimDuration::from_ticks(10));
+/// let d = sim.add_wire(Wire::new("D", WirePull::Up)).unwrap();
+/// let reset = sim.add_wire(Wire::new("/RESET", WirePull::Down)).unwrap();
+/// let q = sim.add_wire(Wire::new("Q", WirePull::None)).unwrap();
+/// let ff = sim.add_element(Box::new(DFlipFlop::new("U1", 0))).unwrap();
+/// sim.connect_input(d, sim.input_pin(ff, "D").unwrap()).unwrap();
+/// sim.connect_input(reset, sim.input_pin(ff, "/RESET").unwrap()).unwrap();
+/// sim.connect_output(sim.output_pin(ff, "Q").unwrap(), q).unwrap();
+///
+/// sim.step().unwrap();
+///
+/// assert_eq!(0.0, f32::from(sim.wire(q).unwrap().measure()));
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct DFlipFlop {
+    /// Name of the flip-flop.
+    name: String,
+    /// Propagation delay from a clock edge or asynchronous input to the outputs.
+    delay: u64,
+    /// The stored bit, if known.
+    state: Option<bool>,
+}
+
+impl DFlipFlop {
+    /// Create a new DFlipFlop.
+    ///
+    /// # Parameters
+    ///
+    /// - `name`: Name of the flip-flop.
+    /// - `delay`: Propagation delay from a clock edge or asynchronous input to the outputs.
+    pub fn new(name: &str, delay: u64) -> Self {
+        Self {
+            name: name.to_string(),
+            delay,
+            state: None,
+        }
+    }
+
+    /// Obtain the stored bit, if known.
+    pub fn state(&self) -> Option<bool> {
+        self.state
+    }
+}
+
+impl Element for DFlipFlop {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn input_pins(&self) -> Vec<InputPin> {
+        ["CLK", "D", "/SET", "/RESET"]
+            .into_iter()
+            .map(InputPin::new)
+            .collect()
+    }
+
+    fn output_pins(&self) -> Vec<OutputPin> {
+        ["Q", "/Q"]
+            .into_iter()
+            .map(|name| {
+                OutputPin::new(
+                    name,
+                    SimDuration::from_ticks(self.delay),
+                    OutputPinState::HighImpedance,
+                )
+            })
+            .collect()
+    }
+
+    fn step(
+        &mut self,
+        inputs: &[InputPin],
+        outputs: &mut [OutputPin],
+        _delta_t: u64,
+    ) -> Result<SimResult, String> {
+        let [clk, d, set, reset] = inputs else {
+            return Err(format!("Flip-flop \"{}\": expected 4 inputs", self.name));
+        };
+        let [q, q_bar] = outputs else {
+            return Err(format!("Flip-flop \"{}\": expected 2 outputs", self.name));
+        };
+
+        clock(&mut self.state, clk, set, reset, |_| bit(d), q, q_bar);
+
+        Ok(SimResult::Continuing)
+    }
+
+    #[cfg(feature = "serde")]
+    fn save_state(&self) -> Result<serde_json::Value, String> {
+        super::save_state(self.state)
+    }
+
+    #[cfg(feature = "serde")]
+    fn restore_state(&mut self, state: serde_json::Value) -> Result<(), String> {
+        self.state = super::restore_state(&self.name, state)?;
+        Ok(())
+    }
+
+    fn box_clone(&self) -> Box<dyn Element> {
+        Box::new(self.clone())
+    }
+}
+
+/// A positive edge-triggered JK flip-flop with asynchronous set and reset.
+///
+/// The inputs are named `CLK`, `J`, `K`, `/SET` and `/RESET`, and the outputs are named `Q` and `/Q`.  On each rising
+/// edge of `CLK` the flip-flop holds with `J` and `K` low, sets with only `J` high, resets with only `K` high, and
+/// toggles with both high.  The asynchronous inputs behave as for the [DFlipFlop].
+///
+/// An indeterminate `J` or `K` at a clock edge leaves the stored bit unknown, as does toggling an unknown bit.
+#[derive(Debug, Clone, PartialEq)]
+pub struct JkFlipFlop {
+    /// Name of the flip-flop.
+    name: String,
+    /// Propagation delay from a clock edge or asynchronous input to the outputs.
+    delay: u64,
+    /// The stored bit, if known.
+    state: Option<bool>,
+}
+
+impl JkFlipFlop {
+    /// Create a new JkFlipFlop.
+    ///
+    /// # Parameters
+    ///
+    /// - `name`: Name of the flip-flop.
+    /// - `delay`: Propagation delay from a clock edge or asynchronous input to the outputs.
+    pub fn new(name: &str, delay: u64) -> Self {
+        Self {
+            name: name.to_string(),
+            delay,
+            state: None,
+        }
+    }
+
+    /// Obtain the stored bit, if known.
+    pub fn state(&self) -> Option<bool> {
+        self.state
+    }
+}
+
+impl Element for JkFlipFlop {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn input_pins(&self) -> Vec<InputPin> {
+        ["CLK", "J", "K", "/SET", "/RESET"]
+            .into_iter()
+            .map(InputPin::new)
+            .collect()
+    }
+
+    fn output_pins(&self) -> Vec<OutputPin> {
+        ["Q", "/Q"]
+            .into_iter()
+            .map(|name| {
+                OutputPin::new(
+                    name,
+                    SimDuration::from_ticks(self.delay),
+                    OutputPinState::HighImpedance,
+                )
+            })
+            .collect()
+    }
+
+    fn step(
+        &mut self,
+        inputs: &[InputPin],
+        outputs: &mut [OutputPin],
+        _delta_t: u64,
+    ) -> Result<SimResult, String> {
+        let [clk, j, k, set, reset] = inputs else {
+            return Err(format!("Flip-flop \"{}\": expected 5 inputs", self.name));
+        };
+        let [q, q_bar] = outputs else {
+            return Err(format!("Flip-flop \"{}\": expected 2 outputs", self.name));
+        };
+
+        let next = |state: Option<bool>| match (bit(j)?, bit(k)?) {
+            (false, false) => state,
+            (true, false) => Some(true),
+            (false, true) => Some(false),
+            (true, true) => state.map(|state| !state),
+        };
+        clock(&mut self.state, clk, set, reset, next, q, q_bar);
+
+        Ok(SimResult::Continuing)
+    }
+
+    #[cfg(feature = "serde")]
+    fn save_state(&self) -> Result<serde_json::Value, String> {
+        super::save_state(self.state)
+    }
+
+    #[cfg(feature = "serde")]
+    fn restore_state(&mut self, state: serde_json::Value) -> Result<(), String> {
+        self.state = super::restore_state(&self.name, state)?;
+        Ok(())
+    }
+
+    fn box_clone(&self) -> Box<dyn Element> {
+        Box::new(self.clone())
+    }
+}
+
+/// A positive edge-triggered T (toggle) flip-flop with asynchronous set and reset.
+///
+/// The inputs are named `CLK`, `T`, `/SET` and `/RESET`, and the outputs are named `Q` and `/Q`.  On each rising edge
+/// of `CLK` the flip-flop toggles if `T` is high and holds if it is low.  The asynchronous inputs behave as for the
+/// [DFlipFlop].
+///
+/// An indeterminate `T` at a clock edge leaves the stored bit unknown, as does toggling an unknown bit, so a T
+/// flip-flop must be set or reset before it can count.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TFlipFlop {
+    /// Name of the flip-flop.
+    name: String,
+    /// Propagation delay from a clock edge or asynchronous input to the outputs.
+    delay: u64,
+    /// The stored bit, if known.
+    state: Option<bool>,
+}
+
+impl TFlipFlop {
+    /// Create a new TFlipFlop.
+    ///
+    /// # Parameters
+    ///
+    /// - `name`: Name of the flip-flop.
+    /// - `delay`: Propagation delay from a clock edge or asynchronous input to the outputs.
+    pub fn new(name: &str, delay: u64) -> Self {
+        Self {
+            name: name.to_string(),
+            delay,
+            state: None,
+        }
+    }
+
+    /// Obtain the stored bit, if known.
+    pub fn state(&self) -> Option<bool> {
+        self.state
+    }
+}
+
+impl Element for TFlipFlop {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn input_pins(&self) -> Vec<InputPin> {
+        ["CLK", "T", "/SET", "/RESET"]
+            .into_iter()
+            .map(InputPin::new)
+            .collect()
+    }
+
+    fn output_pins(&self) -> Vec<OutputPin> {
+        ["Q", "/Q"]
+            .into_iter()
+            .map(|name| {
+                OutputPin::new(
+                    name,
+                    SimDuration::from_ticks(self.delay),
+                    OutputPinState::HighImpedance,
+                )
+            })
+            .collect()
+    }
+
+    fn step(
+        &mut self,
+        inputs: &[InputPin],
+        outputs: &mut [OutputPin],
+        _delta_t: u64,
+    ) -> Result<SimResult, String> {
+        let [clk, t, set, reset] = inputs else {
+            return Err(format!("Flip-flop \"{}\": expected 4 inputs", self.name));
+        };
+        let [q, q_bar] = outputs else {
+            return Err(format!("Flip-flop \"{}\": expected 2 outputs", self.name));
+        };
+
+        let next = |state: Option<bool>| match bit(t)? {
+            false => state,
+            true => state.map(|state| !state),
+        };
+        clock(&mut self.state, clk, set, reset, next, q, q_bar);
+
+        Ok(SimResult::Continuing)
+    }
+
+    #[cfg(feature = "serde")]
+    fn save_state(&self) -> Result<serde_json::Value, String> {
+        super::save_state(self.state)
+    }
+
+    #[cfg(feature = "serde")]
+    fn restore_state(&mut self, state: serde_json::Value) -> Result<(), String> {
+        self.state = super::restore_state(&self.name, state)?;
+        Ok(())
+    }
+
+    fn box_clone(&self) -> Box<dyn Element> {
+        Box::new(self.clone())
+    }
+}
+
+/// A multi-stage synchroniser, which brings a signal from another clock domain into the domain of its clock.
+///
+/// The inputs are named `CLK`, `D` and `/RESET`, and the output is named `Q`.  On each rising edge of `CLK` the level
+/// of `D` enters a chain of flip-flops, and appears on `Q` once it has passed through every stage.  The active-low
+/// `/RESET` clears every stage while it is low, and an unconnected `/RESET` is treated as inactive.
+///
+/// A `D` which is indeterminate at a clock edge, as an asynchronous signal caught mid-transition may be, leaves the
+/// first stage holding its previous bit, as a metastable flip-flop must settle one way or the other.  The extra stages
+/// give it time to settle, so unlike a [DFlipFlop] the unknown level never reaches the output.  The stages are unknown
+/// until the synchroniser is first reset or has been clocked with a definite `D` enough times, and while the last
+/// stage is unknown the output is driven unknown (X).
+///
+/// The [clock-domain crossing checker](crate::cdc) accepts a synchroniser sampling a signal from another domain.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Synchroniser {
+    /// Name of the synchroniser.
+    name: String,
+    /// Propagation delay from a clock edge or reset to the output.
+    delay: u64,
+    /// The bit held by each stage, if known, with the stage sampling `D` first.
+    stages: Vec<Option<bool>>,
+}
+
+impl Synchroniser {
+    /// Create a new Synchroniser.
+    ///
+    /// # Parameters
+    ///
+    /// - `name`: Name of the synchroniser.
+    /// - `stages`: Number of flip-flops in the chain, at least 1 and usually 2 or 3.
+    /// - `delay`: Propagation delay from a clock edge or reset to the output.
+    pub fn new(name: &str, stages: usize, delay: u64) -> Result<Self, String> {
+        if stages == 0 {
+            return Err(format!(
+                "Synchroniser \"{name}\": must have at least one stage"
+            ));
+        }
+        Ok(Self {
+            name: name.to_string(),
+            delay,
+            stages: vec![None; stages],
+        })
+    }
+
+    /// Obtain the bit held by each stage, if known, with the stage sampling `D` first.
+    pub fn stages(&self) -> &[Option<bool>] {
+        &self.stages
+    }
+}
+
+impl Element for Synchroniser {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn input_pins(&self) -> Vec<InputPin> {
+        ["CLK", "D", "/RESET"]
+            .into_iter()
+            .map(InputPin::new)
+            .collect()
+    }
+
+    fn output_pins(&self) -> Vec<OutputPin> {
+        vec![OutputPin::new(
+            "Q",
+            SimDuration::from_ticks(self.delay),
+            OutputPinState::HighImpedance,
+        )]
+    }
+
+    fn step(
+        &mut self,
+        inputs: &[InputPin],
+        outputs: &mut [OutputPin],
+        _delta_t: u64,
+    ) -> Result<SimResult, String> {
+        let [clk, d, reset] = inputs else {
+            return Err(format!("Synchroniser \"{}\": expected 3 inputs", self.name));
+        };
+        let [q] = outputs else {
+            return Err(format!("Synchroniser \"{}\": expected 1 output", self.name));
+        };
+
+        if reset.state() == InputPinState::Low {
+            self.stages.fill(Some(false));
+        } else if clk.rising() {
+            let first = bit(d).or(self.stages[0]);
+            self.stages.rotate_right(1);
+            self.stages[0] = first;
+        }
+        q.drive(level(self.stages.last().copied().flatten()));
+
+        Ok(SimResult::Continuing)
+    }
+
+    fn is_synchroniser(&self) -> bool {
+        true
+    }
+
+    #[cfg(feature = "serde")]
+    fn save_state(&self) -> Result<serde_json::Value, String> {
+        super::save_state(&self.stages)
+    }
+
+    #[cfg(feature = "serde")]
+    fn restore_state(&mut self, state: serde_json::Value) -> Result<(), String> {
+        let stages: Vec<_> = super::restore_state(&self.name, state)?;
+        if stages.len() != self.stages.len() {
+            return Err(format!(
+                "Element \"{}\": saved state has {} stages, expected {}",
+                self.name,
+                stages.len(),
+                self.stages.len()
+            ));
+        }
+        self.stages = stages;
+        Ok(())
+    }
+
+    fn box_clone(&self) -> Box<dyn Element> {
+        Box::new(self.clone())
+    }
+}
+
+/// Register every kind of flip-flop, taking the `delay` (default 0) parameter.
+///
+/// The synchroniser also takes the `stages` (default 2) parameter.
+///
+/// # Parameters
+///
+/// - `registry`: The Registry to add the flip-flops to.
+pub fn register(registry: &mut Registry) {
+    registry.register("dff", |name, parameters: &Parameters| {
+        Ok(Box::new(DFlipFlop::new(
+            name,
+            parameters.get_or("delay", 0)?,
+        )))
+    });
+    registry.register("jkff", |name, parameters: &Parameters| {
+        Ok(Box::new(JkFlipFlop::new(
+            name,
+            parameters.get_or("delay", 0)?,
+        )))
+    });
+    registry.register("synchroniser", |name, parameters: &Parameters| {
+        Ok(Box::new(Synchroniser::new(
+            name,
+            parameters.get_or("stages", 2)?,
+            parameters.get_or("delay", 0)?,
+        )?))
+    });
+    registry.register("tff", |name, parameters: &Parameters| {
+        Ok(Box::new(TFlipFlop::new(
+            name,
+            parameters.get_or("delay", 0)?,
+        )))
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::element::testing::inputs;
+
+    /// Step a flip-flop once and let its outputs propagate, returning the output states.
+    fn step(
+        ff: &mut dyn Element,
+        outputs: &mut [OutputPin],
+        inputs: &[InputPin],
+    ) -> [OutputPinState; 2] {
+        ff.step(inputs, outputs, 10).unwrap();
+        for output in outputs.iter_mut() {
+            output.step(SimDuration::from_ticks(10));
+        }
+        [outputs[0].state(), outputs[1].state()]
+    }
+
+    const L: OutputPinState = OutputPinState::Low;
+    const H: OutputPinState = OutputPinState::High;
+    const Z: OutputPinState = OutputPinState::HighImpedance;
+    const X: OutputPinState = OutputPinState::Unknown;
+
+    #[test]
+    fn dff_captures_on_rising_edge() {
+        // GIVEN a new flip-flop with D high
+        let mut ff = DFlipFlop::new("U1", 0);
+        let mut outputs = ff.output_pins();
+        // WHEN the clock stays low
+        // THEN the outputs are unknown
+        assert_eq!(
+            [X, X],
+            step(
+                &mut ff,
+                &mut outputs,
+                &inputs([0.0, 1.0, 1.0, 1.0], [0.0, 1.0, 1.0, 1.0])
+            )
+        );
+        // WHEN the clock rises
+        // THEN D is captured
+        assert_eq!(
+            [H, L],
+            step(
+                &mut ff,
+                &mut outputs,
+                &inputs([0.0, 1.0, 1.0, 1.0], [1.0, 1.0, 1.0, 1.0])
+            )
+        );
+        // WHEN D falls while the clock stays high, and then the clock falls
+        // THEN the stored bit is held
+        assert_eq!(
+            [H, L],
+            step(
+                &mut ff,
+                &mut outputs,
+                &inputs([1.0, 1.0, 1.0, 1.0], [1.0, 0.0, 1.0, 1.0])
+            )
+        );
+        assert_eq!(
+            [H, L],
+            step(
+                &mut ff,
+                &mut outputs,
+                &inputs([1.0, 0.0, 1.0, 1.0], [0.0, 0.0, 1.0, 1.0])
+            )
+        );
+        assert_eq!(Some(true), ff.state());
+    }
+    #[test]
+    fn dff_indeterminate_d() {
+        // GIVEN a flip-flop holding a bit
+        let mut ff = DFlipFlop::new("U1", 0);
+        let mut outputs = ff.output_pins();
+        step(
+            &mut ff,
+            &mut outputs,
+            &inputs([0.0, 0.0, 1.0, 1.0], [1.0, 0.0, 1.0, 1.0]),
+        );
+        // WHEN it is clocked while D is indeterminate
+        let states = step(
+            &mut ff,
+            &mut outputs,
+            &inputs([0.0, 0.5, 1.0, 1.0], [1.0, 0.5, 1.0, 1.0]),
+        );
+        // THEN the stored bit and the outputs become unknown
+        assert_eq!([X, X], states);
+        assert_eq!(None, ff.state());
+    }
+    #[test]
+    fn dff_asynchronous_set_and_reset() {
+        // GIVEN a flip-flop with its clock held low
+        let mut ff = DFlipFlop::new("U1", 0);
+        let mut outputs = ff.output_pins();
+        // WHEN each asynchronous input is asserted
+        // THEN it overrides the clock, and asserting both drives both outputs high
+        assert_eq!(
+            [H, L],
+            step(
+                &mut ff,
+                &mut outputs,
+                &inputs([0.0; 4], [0.0, 0.0, 0.0, 1.0])
+            )
+        );
+        assert_eq!(
+            [L, H],
+            step(
+                &mut ff,
+                &mut outputs,
+                &inputs([0.0; 4], [0.0, 1.0, 1.0, 0.0])
+            )
+        );
+        assert_eq!(
+            [H, H],
+            step(
+                &mut ff,
+                &mut outputs,
+                &inputs([0.0; 4], [0.0, 1.0, 0.0, 0.0])
+            )
+        );
+        // AND THEN a clock edge while reset is held is ignored
+        assert_eq!(
+            [L, H],
+            step(
+                &mut ff,
+                &mut outputs,
+                &inputs([0.0; 4], [1.0, 1.0, 1.0, 0.0])
+            )
+        );
+    }
+    #[test]
+    fn dff_clock_to_q_delay() {
+        // GIVEN the standard registry
+        let registry = Registry::standard();
+        // WHEN a flip-flop is created with a delay
+        let mut ff = registry
+            .create("dff", "U1", &Parameters::new().with("delay", "15"))
+            .unwrap();
+        let mut outputs = ff.output_pins();
+        ff.step(
+            &inputs([0.0, 1.0, 1.0, 1.0], [1.0, 1.0, 1.0, 1.0]),
+            &mut outputs,
+            10,
+        )
+        .unwrap();
+        // THEN a captured bit only appears once the delay has elapsed
+        outputs[0].step(SimDuration::from_ticks(10));
+        assert_eq!(Z, outputs[0].state());
+        outputs[0].step(SimDuration::from_ticks(10));
+        assert_eq!(H, outputs[0].state());
+    }
+    #[test]
+    fn jkff_truth_table() {
+        // GIVEN a flip-flop which has been reset
+        let mut ff = JkFlipFlop::new("U1", 0);
+        let mut outputs = ff.output_pins();
+        step(
+            &mut ff,
+            &mut outputs,
+            &inputs([0.0; 5], [0.0, 0.0, 0.0, 1.0, 0.0]),
+        );
+        // WHEN it is clocked with each combination of J and K
+        // THEN it holds, sets, toggles and resets
+        let edge = |j: f32, k: f32| inputs([0.0, j, k, 1.0, 1.0], [1.0, j, k, 1.0, 1.0]);
+        assert_eq!([L, H], step(&mut ff, &mut outputs, &edge(0.0, 0.0)));
+        assert_eq!([H, L], step(&mut ff, &mut outputs, &edge(1.0, 0.0)));
+        assert_eq!([L, H], step(&mut ff, &mut outputs, &edge(1.0, 1.0)));
+        assert_eq!([H, L], step(&mut ff, &mut outputs, &edge(1.0, 1.0)));
+        assert_eq!([L, H], step(&mut ff, &mut outputs, &edge(0.0, 1.0)));
+        // AND THEN an indeterminate input at an edge makes the stored bit unknown
+        assert_eq!([X, X], step(&mut ff, &mut outputs, &edge(0.5, 0.0)));
+        assert_eq!(None, ff.state());
+    }
+    #[test]
+    fn tff_toggles() {
+        // GIVEN a new flip-flop with T high
+        let mut ff = TFlipFlop::new("U1", 0);
+        let mut outputs = ff.output_pins();
+        let edge = |t: f32| inputs([0.0, t, 1.0, 1.0], [1.0, t, 1.0, 1.0]);
+        // WHEN it is clocked before it has been set or reset
+        // THEN its stored bit remains unknown
+        assert_eq!([X, X], step(&mut ff, &mut outputs, &edge(1.0)));
+        // WHEN it is set and then clocked
+        // THEN it toggles while T is high and holds while T is low
+        step(
+            &mut ff,
+            &mut outputs,
+            &inputs([0.0; 4], [0.0, 1.0, 0.0, 1.0]),
+        );
+        assert_eq!([L, H], step(&mut ff, &mut outputs, &edge(1.0)));
+        assert_eq!([L, H], step(&mut ff, &mut outputs, &edge(0.0)));
+        assert_eq!([H, L], step(&mut ff, &mut outputs, &edge(1.0)));
+        assert_eq!(Some(true), ff.state());
+    }
+    #[test]
+    fn synchroniser_stages() {
+        // GIVEN a two-stage synchroniser which has been reset
+        let registry = Registry::standard();
+        let mut sync = registry
+            .create("synchroniser", "U1", &Parameters::new())
+            .unwrap();
+        assert!(sync.is_synchroniser());
+        let mut outputs = sync.output_pins();
+        let mut clock = |previous: [f32; 3], now: [f32; 3]| {
+            sync.step(&inputs(previous, now), &mut outputs, 10).unwrap();
+            outputs[0].step(SimDuration::from_ticks(10));
+            outputs[0].state()
+        };
+        assert_eq!(L, clock([0.0; 3], [0.0, 0.0, 0.0]));
+        let edge = |d: f32| ([0.0, d, 1.0], [1.0, d, 1.0]);
+        // WHEN it is clocked with D high
+        // THEN D reaches the output after two edges
+        let (previous, now) = edge(1.0);
+        assert_eq!(L, clock(previous, now));
+        assert_eq!(H, clock(previous, now));
+        // WHEN it is clocked with D indeterminate
+        // THEN the first stage settles to its previous bit, and the output stays driven
+        let (previous, now) = edge(0.5);
+        assert_eq!(H, clock(previous, now));
+        assert_eq!(H, clock(previous, now));
+        // AND THEN a synchroniser without stages cannot be created
+        assert!(Synchroniser::new("U2", 0, 0).is_err());
+        assert!(!DFlipFlop::new("U3", 0).is_synchroniser());
+    }
+}
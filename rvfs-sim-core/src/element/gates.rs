@@ -0,0 +1,369 @@
+//! Basic combinational logic gates.
+
+use crate::element::{Element, Parameters, Registry};
+use crate::ipin::{InputPin, InputPinState};
+use crate::opin::{OutputPin, OutputPinState};
+use crate::prelude::*;
+use crate::sim::SimResult;
+use crate::time::SimDuration;
+
+/// Default number of inputs of a gate instantiated from a configuration file.
+const DEFAULT_INPUTS: usize = 2;
+
+/// The logic function of a Gate.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum GateKind {
+    /// High if every input is high.
+    And,
+    /// High if any input is high.
+    Or,
+    /// High if its single input is low.
+    Not,
+    /// Low if every input is high.
+    Nand,
+    /// Low if any input is high.
+    Nor,
+    /// High if an odd number of inputs are high.
+    Xor,
+    /// High if an even number of inputs are high.
+    Xnor,
+}
+
+impl GateKind {
+    /// All kinds of gate.
+    pub const ALL: [GateKind; 7] = [
+        GateKind::And,
+        GateKind::Or,
+        GateKind::Not,
+        GateKind::Nand,
+        GateKind::Nor,
+        GateKind::Xor,
+        GateKind::Xnor,
+    ];
+
+    /// Obtain the name under which the kind of gate is registered.
+    pub fn name(self) -> &'static str {
+        match self {
+            GateKind::And => "and",
+            GateKind::Or => "or",
+            GateKind::Not => "not",
+            GateKind::Nand => "nand",
+            GateKind::Nor => "nor",
+            GateKind::Xor => "xor",
+            GateKind::Xnor => "xnor",
+        }
+    }
+
+    /// Evaluate the logic function.
+    ///
+    /// An indeterminate input only makes the result indeterminate if the result depends on it, so an AND gate with
+    /// any low input is low regardless of its other inputs.
+    ///
+    /// # Parameters
+    ///
+    /// - `inputs`: States of the inputs.
+    pub fn evaluate(self, inputs: &[InputPinState]) -> Option<bool> {
+        let any = |state| inputs.contains(&state);
+        let result = match self {
+            GateKind::And | GateKind::Nand => {
+                if any(InputPinState::Low) {
+                    Some(false)
+                } else if any(InputPinState::Indeterminate) {
+                    None
+                } else {
+                    Some(true)
+                }
+            }
+            GateKind::Or | GateKind::Nor | GateKind::Not => {
+                if any(InputPinState::High) {
+                    Some(true)
+                } else if any(InputPinState::Indeterminate) {
+                    None
+                } else {
+                    Some(false)
+                }
+            }
+            GateKind::Xor | GateKind::Xnor => {
+                if any(InputPinState::Indeterminate) {
+                    None
+                } else {
+                    let highs = inputs.iter().filter(|s| **s == InputPinState::High).count();
+                    Some(highs % 2 == 1)
+                }
+            }
+        };
+        match self {
+            GateKind::Not | GateKind::Nand | GateKind::Nor | GateKind::Xnor => result.map(|r| !r),
+            _ => result,
+        }
+    }
+}
+
+/// A combinational logic gate with a configurable number of inputs and propagation delay.
+///
+/// The inputs are named `I0`, `I1`, and so on, and the output is named `Y`.  The output is not driven until the
+/// inputs first determine it, and holds its last state while inputs passing between the thresholds leave it
+/// indeterminate.  An input from an undriven or unknown Wire which leaves it indeterminate drives it unknown instead.
+///
+/// # Example
+///
+/// ```
+/// # use rvfs_sim_core::element::gates::{Gate, GateKind};
+/// # use rvfs_sim_core::sim::Simulation;
+/// # use rvfs_sim_core::time::SimDuration;
+/// # use rvfs_sim_core::wire::{Wire, WirePull};
+/// let mut sim = Simulation::new(SimDuration::from_ticks(10));
+/// let a = sim.add_wire(Wire::new("A", WirePull::Up)).unwrap();
+/// let y = sim.add_wire(Wire::new("Y", WirePull::None)).unwrap();
+/// let gate = sim
+///     .add_element(Box::new(Gate::new("U1", GateKind::Not, 1, 0).unwrap()))
+///     .unwrap();
+/// sim.connect_input(a, sim.input_pin(gate, "I0").unwrap()).unwrap();
+/// sim.connect_output(sim.output_pin(gate, "Y").unwrap(), y).unwrap();
+///
+/// sim.step().unwrap();
+///
+/// assert_eq!(0.0, f32::from(sim.wire(y).unwrap().measure()));
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Gate {
+    /// Name of the gate.
+    name: String,
+    /// Logic function of the gate.
+    kind: GateKind,
+    /// Number of inputs.
+    inputs: usize,
+    /// Propagation delay from the inputs to the output.
+    delay: u64,
+}
+
+impl Gate {
+    /// Create a new Gate.
+    ///
+    /// # Parameters
+    ///
+    /// - `name`: Name of the gate.
+    /// - `kind`: Logic function of the gate.
+    /// - `inputs`: Number of inputs.  A NOT gate has exactly one input, and every other gate has at least two.
+    /// - `delay`: Propagation delay from the inputs to the output.
+    pub fn new(name: &str, kind: GateKind, inputs: usize, delay: u64) -> Result<Self, String> {
+        if kind == GateKind::Not && inputs != 1 {
+            return Err(format!("Gate \"{name}\": a NOT gate has exactly one input"));
+        }
+        if kind != GateKind::Not && inputs < 2 {
+            return Err(format!(
+                "Gate \"{name}\": an {} gate has at least two inputs",
+                kind.name().to_uppercase()
+            ));
+        }
+
+        Ok(Self {
+            name: name.to_string(),
+            kind,
+            inputs,
+            delay,
+        })
+    }
+
+    /// Obtain the logic function of the gate.
+    pub fn kind(&self) -> GateKind {
+        self.kind
+    }
+}
+
+impl Element for Gate {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn input_pins(&self) -> Vec<InputPin> {
+        (0..self.inputs)
+            .map(|i| InputPin::new(&format!("I{i}")))
+            .collect()
+    }
+
+    fn output_pins(&self) -> Vec<OutputPin> {
+        vec![OutputPin::new(
+            "Y",
+            SimDuration::from_ticks(self.delay),
+            OutputPinState::HighImpedance,
+        )]
+    }
+
+    fn step(
+        &mut self,
+        inputs: &[InputPin],
+        outputs: &mut [OutputPin],
+        _delta_t: u64,
+    ) -> Result<SimResult, String> {
+        let states: Vec<InputPinState> = inputs.iter().map(InputPin::state).collect();
+        match self.kind.evaluate(&states) {
+            Some(true) => outputs[0].drive(OutputPinState::High),
+            Some(false) => outputs[0].drive(OutputPinState::Low),
+            None if inputs.iter().any(InputPin::unknown) => {
+                outputs[0].drive(OutputPinState::Unknown)
+            }
+            None => (),
+        }
+        Ok(SimResult::Continuing)
+    }
+
+    fn is_combinational(&self) -> bool {
+        true
+    }
+
+    fn box_clone(&self) -> Box<dyn Element> {
+        Box::new(self.clone())
+    }
+}
+
+/// Register every kind of gate, taking the `inputs` (default 2, or 1 for NOT) and `delay` (default 0) parameters.
+///
+/// # Parameters
+///
+/// - `registry`: The Registry to add the gates to.
+pub fn register(registry: &mut Registry) {
+    fn create(
+        kind: GateKind,
+        name: &str,
+        parameters: &Parameters,
+    ) -> Result<Box<dyn Element>, String> {
+        let default_inputs = if kind == GateKind::Not {
+            1
+        } else {
+            DEFAULT_INPUTS
+        };
+        let inputs = parameters.get_or("inputs", default_inputs)?;
+        let delay = parameters.get_or("delay", 0)?;
+        Ok(Box::new(Gate::new(name, kind, inputs, delay)?))
+    }
+
+    registry.register("and", |name, parameters| {
+        create(GateKind::And, name, parameters)
+    });
+    registry.register("or", |name, parameters| {
+        create(GateKind::Or, name, parameters)
+    });
+    registry.register("not", |name, parameters| {
+        create(GateKind::Not, name, parameters)
+    });
+    registry.register("nand", |name, parameters| {
+        create(GateKind::Nand, name, parameters)
+    });
+    registry.register("nor", |name, parameters| {
+        create(GateKind::Nor, name, parameters)
+    });
+    registry.register("xor", |name, parameters| {
+        create(GateKind::Xor, name, parameters)
+    });
+    registry.register("xnor", |name, parameters| {
+        create(GateKind::Xnor, name, parameters)
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sim::Simulation;
+    use crate::wire::{Wire, WirePull};
+
+    const L: InputPinState = InputPinState::Low;
+    const H: InputPinState = InputPinState::High;
+    const X: InputPinState = InputPinState::Indeterminate;
+
+    #[test]
+    fn gate_truth_tables() {
+        // GIVEN every kind of two input gate, and NOT
+        // WHEN each is evaluated over its input combinations
+        // THEN the results match the truth tables
+        let table = |kind: GateKind| -> Vec<Option<bool>> {
+            [[L, L], [L, H], [H, L], [H, H]]
+                .iter()
+                .map(|inputs| kind.evaluate(inputs))
+                .collect()
+        };
+        let t = Some(true);
+        let f = Some(false);
+        assert_eq!(vec![f, f, f, t], table(GateKind::And));
+        assert_eq!(vec![f, t, t, t], table(GateKind::Or));
+        assert_eq!(vec![t, t, t, f], table(GateKind::Nand));
+        assert_eq!(vec![t, f, f, f], table(GateKind::Nor));
+        assert_eq!(vec![f, t, t, f], table(GateKind::Xor));
+        assert_eq!(vec![t, f, f, t], table(GateKind::Xnor));
+        assert_eq!(t, GateKind::Not.evaluate(&[L]));
+        assert_eq!(f, GateKind::Not.evaluate(&[H]));
+    }
+    #[test]
+    fn gate_indeterminate_inputs() {
+        // GIVEN gates with an indeterminate input
+        // WHEN they are evaluated
+        // THEN the result is only indeterminate if it depends on that input
+        assert_eq!(Some(false), GateKind::And.evaluate(&[X, L, H]));
+        assert_eq!(None, GateKind::And.evaluate(&[X, H, H]));
+        assert_eq!(Some(false), GateKind::Nor.evaluate(&[X, H]));
+        assert_eq!(None, GateKind::Xor.evaluate(&[X, L]));
+        assert_eq!(None, GateKind::Not.evaluate(&[X]));
+    }
+    #[test]
+    fn gate_wide_inputs() {
+        // GIVEN three input gates
+        // WHEN they are evaluated
+        // THEN every input contributes
+        assert_eq!(Some(true), GateKind::And.evaluate(&[H, H, H]));
+        assert_eq!(Some(false), GateKind::And.evaluate(&[H, H, L]));
+        assert_eq!(Some(true), GateKind::Xor.evaluate(&[H, H, H]));
+    }
+    #[test]
+    fn gate_input_count_validation() {
+        // GIVEN gate definitions with too few or too many inputs
+        // WHEN they are created
+        // THEN creation fails
+        assert!(Gate::new("U1", GateKind::Not, 2, 0).is_err());
+        assert!(Gate::new("U1", GateKind::And, 1, 0).is_err());
+        assert!(Gate::new("U1", GateKind::Xor, 4, 0).is_ok());
+    }
+    #[test]
+    fn gate_registered_parameters() {
+        // GIVEN the standard registry
+        let registry = Registry::standard();
+        // WHEN gates are created with and without parameters
+        let not = registry.create("not", "U1", &Parameters::new()).unwrap();
+        let or = registry
+            .create(
+                "or",
+                "U2",
+                &Parameters::new().with("inputs", "4").with("delay", "3"),
+            )
+            .unwrap();
+        // THEN the defaults or given parameters are used
+        assert_eq!(1, not.input_pins().len());
+        assert_eq!(4, or.input_pins().len());
+        assert_eq!(3, or.output_pins()[0].delay().ticks());
+        assert!(registry
+            .create("and", "U3", &Parameters::new().with("inputs", "1"))
+            .is_err());
+    }
+    #[test]
+    fn gate_propagation_delay() {
+        // GIVEN a simulation of an AND gate with a delay of two steps, whose inputs are both pulled high
+        let mut sim = Simulation::new(SimDuration::from_ticks(10));
+        let a = sim.add_wire(Wire::new("A", WirePull::Up)).unwrap();
+        let b = sim.add_wire(Wire::new("B", WirePull::Up)).unwrap();
+        let y = sim.add_wire(Wire::new("Y", WirePull::Down)).unwrap();
+        let gate = sim
+            .add_element(Box::new(Gate::new("U1", GateKind::And, 2, 20).unwrap()))
+            .unwrap();
+        sim.connect_input(a, sim.input_pin(gate, "I0").unwrap())
+            .unwrap();
+        sim.connect_input(b, sim.input_pin(gate, "I1").unwrap())
+            .unwrap();
+        sim.connect_output(sim.output_pin(gate, "Y").unwrap(), y)
+            .unwrap();
+        // WHEN the simulation is stepped
+        // THEN the output is driven high once the delay has elapsed
+        sim.step().unwrap();
+        assert_eq!(0.0, f32::from(sim.wire(y).unwrap().measure()));
+        sim.step().unwrap();
+        assert_eq!(1.0, f32::from(sim.wire(y).unwrap().measure()));
+    }
+}
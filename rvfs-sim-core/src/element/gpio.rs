@@ -0,0 +1,466 @@
+//! General purpose I/O ports, bridging scripted testbenches and the circuit.
+//!
+//! A [GpioPort] is an Element whose pins connect to Wires like any other, while its other side is a [GpioHost] through
+//! which a testbench sets the direction of each bit, writes the bits driven as outputs, reads the levels on the pins
+//! and collects interrupts raised when they change.
+
+use crate::element::{bit, level, Element, Parameters, Registry};
+use crate::ipin::InputPin;
+use crate::opin::{OutputPin, OutputPinState};
+use crate::sim::SimResult;
+use crate::time::SimDuration;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+
+/// Default width of a port instantiated from a configuration file.
+const DEFAULT_WIDTH: usize = 8;
+
+/// The direction of a bit of a [GpioPort].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Direction {
+    /// The pin is released, so the bit only reads the level of its Wire.
+    Input,
+    /// The pin drives the value written to the bit.
+    Output,
+}
+
+/// The edges of a bit of a [GpioPort] which raise an interrupt.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum Edge {
+    /// No interrupt is raised.
+    #[default]
+    None,
+    /// An interrupt is raised when the pin goes from low to high.
+    Rising,
+    /// An interrupt is raised when the pin goes from high to low.
+    Falling,
+    /// An interrupt is raised when the pin goes from low to high or from high to low.
+    Both,
+}
+
+/// An interrupt raised by a change on a pin of a [GpioPort].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct GpioInterrupt {
+    /// The bit whose pin changed.
+    pub bit: usize,
+    /// Whether the pin went high, rather than low.
+    pub rising: bool,
+    /// The time at which the change was sampled, counted from the start of the Simulation.
+    pub time: u64,
+}
+
+/// The state of a port shared between a [GpioPort] and its [GpioHost].
+#[derive(Debug)]
+struct PortState {
+    /// The direction of each bit.
+    directions: Vec<Direction>,
+    /// The value written to each bit.
+    values: Vec<bool>,
+    /// The edges of each bit which raise an interrupt.
+    edges: Vec<Edge>,
+    /// The level most recently sampled on each pin, if definite.
+    levels: Vec<Option<bool>>,
+    /// Interrupts raised and not yet taken.
+    interrupts: Vec<GpioInterrupt>,
+}
+
+/// The host side of a [GpioPort].
+///
+/// Bits are numbered from zero, and words hold bit zero in their least significant bit.  Changes made through the host
+/// take effect at the next step of the Simulation.  The state is shared by every copy of the port, so a Simulation
+/// holding the port refuses to [step back](crate::sim::Simulation::step_back).
+#[derive(Debug, Clone)]
+pub struct GpioHost {
+    /// The shared state of the port.
+    state: Arc<Mutex<PortState>>,
+}
+
+impl GpioHost {
+    /// Obtain the number of bits in the port.
+    pub fn width(&self) -> usize {
+        self.lock().values.len()
+    }
+
+    /// Set the direction of a bit.
+    ///
+    /// # Parameters
+    ///
+    /// - `bit`: The bit.
+    /// - `direction`: The new direction of the bit.
+    pub fn set_direction(&self, bit: usize, direction: Direction) -> Result<(), String> {
+        *self
+            .lock()
+            .directions
+            .get_mut(bit)
+            .ok_or_else(|| no_bit(bit))? = direction;
+        Ok(())
+    }
+
+    /// Obtain the direction of a bit, if it exists.
+    ///
+    /// # Parameters
+    ///
+    /// - `bit`: The bit.
+    pub fn direction(&self, bit: usize) -> Option<Direction> {
+        self.lock().directions.get(bit).copied()
+    }
+
+    /// Write the value driven by a bit while it is an output.
+    ///
+    /// # Parameters
+    ///
+    /// - `bit`: The bit.
+    /// - `value`: The value to drive.
+    pub fn write(&self, bit: usize, value: bool) -> Result<(), String> {
+        *self.lock().values.get_mut(bit).ok_or_else(|| no_bit(bit))? = value;
+        Ok(())
+    }
+
+    /// Write the values driven by every bit while they are outputs.
+    ///
+    /// # Parameters
+    ///
+    /// - `word`: The values to drive.  Bits beyond the width of the port are ignored.
+    pub fn write_word(&self, word: u64) {
+        for (i, value) in self.lock().values.iter_mut().enumerate() {
+            *value = (word >> i) & 1 != 0;
+        }
+    }
+
+    /// Read the level most recently sampled on the pin of a bit, if it exists and is definite.
+    ///
+    /// The level is read whatever the direction of the bit, so an output reads back the level of its Wire.
+    ///
+    /// # Parameters
+    ///
+    /// - `bit`: The bit.
+    pub fn read(&self, bit: usize) -> Option<bool> {
+        self.lock().levels.get(bit).copied().flatten()
+    }
+
+    /// Read the levels most recently sampled on every pin, if they are all definite.
+    pub fn read_word(&self) -> Option<u64> {
+        self.lock()
+            .levels
+            .iter()
+            .enumerate()
+            .try_fold(0, |word, (i, level)| {
+                Some(word | (u64::from((*level)?) << i))
+            })
+    }
+
+    /// Set the edges of a bit which raise an interrupt.
+    ///
+    /// # Parameters
+    ///
+    /// - `bit`: The bit.
+    /// - `edge`: The edges which raise an interrupt.
+    pub fn set_interrupt(&self, bit: usize, edge: Edge) -> Result<(), String> {
+        *self.lock().edges.get_mut(bit).ok_or_else(|| no_bit(bit))? = edge;
+        Ok(())
+    }
+
+    /// Take the interrupts raised since they were last taken, in the order they were raised.
+    pub fn take_interrupts(&self) -> Vec<GpioInterrupt> {
+        std::mem::take(&mut self.lock().interrupts)
+    }
+
+    /// Lock the shared state.
+    fn lock(&self) -> MutexGuard<'_, PortState> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// Build the error for a bit which does not exist.
+///
+/// # Parameters
+///
+/// - `bit`: The bit.
+fn no_bit(bit: usize) -> String {
+    format!("GPIO port has no bit {bit}")
+}
+
+/// A general purpose I/O port, whose bits are controlled through its [GpioHost].
+///
+/// The inputs and outputs are both named `P0` to `Pn`, and each bit's InputPin and OutputPin should both be
+/// connected to the same Wire.  A bit drives the value written to it while it is an output, and is released while it
+/// is an input.  Every bit starts as an input with the value zero and no interrupt.
+///
+/// # Example
+///
+/// ```
+/// # use rvfs_sim_core::element::gpio::{Direction, GpioPort};
+/// let port = GpioPort::new("U1", 8, 0).unwrap();
+/// let host = port.host();
+///
+/// host.set_direction(0, Direction::Output).unwrap();
+/// host.write_word(0x01);
+/// assert_eq!(Some(Direction::Output), host.direction(0));
+/// assert!(host.write(8, true).is_err());
+/// ```
+#[derive(Debug, Clone)]
+pub struct GpioPort {
+    /// Name of the port.
+    name: String,
+    /// Number of bits.
+    width: usize,
+    /// Propagation delay from a write to the pins.
+    delay: u64,
+    /// Time elapsed since the start of the Simulation.
+    time: u64,
+    /// The host side of the port.
+    host: GpioHost,
+}
+
+impl GpioPort {
+    /// Create a new GpioPort.
+    ///
+    /// # Parameters
+    ///
+    /// - `name`: Name of the port.
+    /// - `width`: Number of bits, which must be at least one.
+    /// - `delay`: Propagation delay from a write to the pins.
+    pub fn new(name: &str, width: usize, delay: u64) -> Result<Self, String> {
+        if width == 0 {
+            return Err(format!(
+                "GPIO port \"{name}\": width must be at least one bit"
+            ));
+        }
+
+        Ok(Self {
+            name: name.to_string(),
+            width,
+            delay,
+            time: 0,
+            host: GpioHost {
+                state: Arc::new(Mutex::new(PortState {
+                    directions: vec![Direction::Input; width],
+                    values: vec![false; width],
+                    edges: vec![Edge::None; width],
+                    levels: vec![None; width],
+                    interrupts: Vec::new(),
+                })),
+            },
+        })
+    }
+
+    /// Obtain the host side of the port.
+    pub fn host(&self) -> GpioHost {
+        self.host.clone()
+    }
+}
+
+impl Element for GpioPort {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn input_pins(&self) -> Vec<InputPin> {
+        (0..self.width)
+            .map(|i| InputPin::new(&format!("P{i}")))
+            .collect()
+    }
+
+    fn output_pins(&self) -> Vec<OutputPin> {
+        (0..self.width)
+            .map(|i| {
+                OutputPin::new(
+                    &format!("P{i}"),
+                    SimDuration::from_ticks(self.delay),
+                    OutputPinState::HighImpedance,
+                )
+            })
+            .collect()
+    }
+
+    fn step(
+        &mut self,
+        inputs: &[InputPin],
+        outputs: &mut [OutputPin],
+        delta_t: u64,
+    ) -> Result<SimResult, String> {
+        if inputs.len() != self.width || outputs.len() != self.width {
+            return Err(format!("GPIO port \"{}\": unexpected pins", self.name));
+        }
+
+        self.time += delta_t;
+        let mut state = self.host.lock();
+        let state = &mut *state;
+        for (i, (input, output)) in inputs.iter().zip(outputs).enumerate() {
+            state.levels[i] = bit(input);
+            let raised = match state.edges[i] {
+                Edge::None => false,
+                Edge::Rising => input.rising(),
+                Edge::Falling => input.falling(),
+                Edge::Both => input.rising() || input.falling(),
+            };
+            if raised {
+                state.interrupts.push(GpioInterrupt {
+                    bit: i,
+                    rising: input.rising(),
+                    time: self.time,
+                });
+            }
+            output.drive(match state.directions[i] {
+                Direction::Input => OutputPinState::HighImpedance,
+                Direction::Output => level(Some(state.values[i])),
+            });
+        }
+
+        Ok(SimResult::Continuing)
+    }
+
+    fn is_replayable(&self) -> bool {
+        false
+    }
+
+    fn box_clone(&self) -> Box<dyn Element> {
+        Box::new(self.clone())
+    }
+}
+
+/// Register every kind of GPIO element.
+///
+/// The `gpio` kind takes the `width` (default 8) and `delay` parameters, along with the `outputs` parameter holding a
+/// mask of the bits which start as outputs and the `value` parameter holding the values they start with, both in
+/// decimal.
+///
+/// # Parameters
+///
+/// - `registry`: The Registry to add the GPIO elements to.
+pub fn register(registry: &mut Registry) {
+    registry.register("gpio", |name, parameters: &Parameters| {
+        let port = GpioPort::new(
+            name,
+            parameters.get_or("width", DEFAULT_WIDTH)?,
+            parameters.get_or("delay", 0)?,
+        )?;
+        let host = port.host();
+        let outputs: u64 = parameters.get_or("outputs", 0)?;
+        for i in (0..host.width()).filter(|i| (outputs >> i) & 1 != 0) {
+            host.set_direction(i, Direction::Output)?;
+        }
+        host.write_word(parameters.get_or("value", 0)?);
+        Ok(Box::new(port))
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sim::Simulation;
+    use crate::wire::{Wire, WirePull};
+
+    /// Build a simulation of two ports of the given width whose pins are connected by Wires with the given pull,
+    /// returning the simulation and the hosts of the ports.
+    fn ports(width: usize, pull: WirePull) -> (Simulation, GpioHost, GpioHost) {
+        let mut sim = Simulation::new(SimDuration::from_ticks(10));
+        let a = GpioPort::new("A", width, 0).unwrap();
+        let b = GpioPort::new("B", width, 0).unwrap();
+        let (host_a, host_b) = (a.host(), b.host());
+        let a = sim.add_element(Box::new(a)).unwrap();
+        let b = sim.add_element(Box::new(b)).unwrap();
+        for i in 0..width {
+            let name = format!("P{i}");
+            let wire = sim.add_wire(Wire::new(&name, pull)).unwrap();
+            for port in [a, b] {
+                sim.connect_input(wire, sim.input_pin(port, &name).unwrap())
+                    .unwrap();
+                sim.connect_output(sim.output_pin(port, &name).unwrap(), wire)
+                    .unwrap();
+            }
+        }
+        (sim, host_a, host_b)
+    }
+
+    /// Step a simulation a number of times.
+    fn run(sim: &mut Simulation, steps: usize) {
+        for _ in 0..steps {
+            sim.step().unwrap();
+        }
+    }
+
+    #[test]
+    fn gpio_write_read() {
+        // GIVEN two ports connected by pulled down Wires
+        let (mut sim, a, b) = ports(4, WirePull::Down);
+        run(&mut sim, 3);
+        assert_eq!(Some(0b0000), b.read_word());
+        // WHEN one port drives some of its bits
+        a.set_direction(1, Direction::Output).unwrap();
+        a.set_direction(2, Direction::Output).unwrap();
+        a.write_word(0b1110);
+        run(&mut sim, 3);
+        // THEN only the output bits are driven, and both ports read them
+        assert_eq!(Some(0b0110), b.read_word());
+        assert_eq!(Some(0b0110), a.read_word());
+        assert_eq!(Some(true), b.read(1));
+        assert_eq!(None, b.read(4));
+    }
+    #[test]
+    fn gpio_interrupts() {
+        // GIVEN two ports connected by pulled up Wires, with interrupts on different edges of the second
+        let (mut sim, a, b) = ports(3, WirePull::Up);
+        b.set_interrupt(0, Edge::Falling).unwrap();
+        b.set_interrupt(1, Edge::Both).unwrap();
+        run(&mut sim, 3);
+        assert_eq!(Vec::<GpioInterrupt>::new(), b.take_interrupts());
+        // WHEN the first port pulls every bit low and then releases them again
+        for i in 0..3 {
+            a.set_direction(i, Direction::Output).unwrap();
+        }
+        run(&mut sim, 3);
+        for i in 0..3 {
+            a.set_direction(i, Direction::Input).unwrap();
+        }
+        run(&mut sim, 3);
+        // THEN interrupts are raised only for the chosen edges, in order
+        let interrupts = b.take_interrupts();
+        assert_eq!(
+            vec![(0, false), (1, false), (1, true)],
+            interrupts
+                .iter()
+                .map(|interrupt| (interrupt.bit, interrupt.rising))
+                .collect::<Vec<_>>()
+        );
+        assert!(interrupts[1].time < interrupts[2].time);
+        assert!(b.take_interrupts().is_empty());
+    }
+    #[test]
+    fn gpio_registered() {
+        // GIVEN the standard registry
+        let registry = Registry::standard();
+        // WHEN a port is created with some bits starting as outputs
+        let mut port = registry
+            .create(
+                "gpio",
+                "U1",
+                &Parameters::new()
+                    .with("width", "4")
+                    .with("outputs", "3")
+                    .with("value", "1"),
+            )
+            .unwrap();
+        let inputs = port.input_pins();
+        let mut outputs = port.output_pins();
+        port.step(&inputs, &mut outputs, 1).unwrap();
+        outputs
+            .iter_mut()
+            .for_each(|output| output.step(SimDuration::from_ticks(1)));
+        // THEN those bits drive their starting values, and the rest are released
+        assert_eq!(
+            vec![
+                OutputPinState::High,
+                OutputPinState::Low,
+                OutputPinState::HighImpedance,
+                OutputPinState::HighImpedance
+            ],
+            outputs
+                .iter()
+                .map(|output| output.state())
+                .collect::<Vec<_>>()
+        );
+        assert!(registry
+            .create("gpio", "U2", &Parameters::new().with("width", "0"))
+            .is_err());
+    }
+}
@@ -0,0 +1,937 @@
+//! I2C bus masters and slaves.
+//!
+//! I2C elements drive the `SCL` and `SDA` Wires open-drain: their outputs either pull a Wire low or release it to the
+//! HighImpedance state, so the Wires must be pulled up and every element on the bus [connects](
+//! crate::sim::Simulation::connect_output) both an InputPin and an OutputPin to each.  A Wire is then low whenever any
+//! element pulls it low, which is what makes clock stretching and multi-master arbitration work.
+//!
+//! A master is given transactions to perform through its [I2cHost], and a slave answers on behalf of a [RegisterMap],
+//! which can be implemented to model a real device.
+
+use crate::element::{bit, Element, Parameters, Registry};
+use crate::ipin::{InputPin, InputPinState};
+use crate::opin::{OutputPin, OutputPinState};
+use crate::sim::SimResult;
+use crate::time::SimDuration;
+use std::collections::VecDeque;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex, PoisonError};
+
+/// Default SCL period of a master instantiated from a configuration file, which is 100 kHz if a time unit is one
+/// nanosecond.
+const DEFAULT_PERIOD: u64 = 10_000;
+/// Default number of registers of a slave instantiated from a configuration file.
+const DEFAULT_SIZE: usize = 256;
+
+/// Convert whether an open-drain output pulls its Wire low to the state of the output.
+///
+/// # Parameters
+///
+/// - `low`: Whether the output pulls its Wire low.
+fn open_drain(low: bool) -> OutputPinState {
+    if low {
+        OutputPinState::Low
+    } else {
+        OutputPinState::HighImpedance
+    }
+}
+
+/// Parse a number given in decimal, or in hexadecimal with a `0x` prefix.
+///
+/// # Parameters
+///
+/// - `text`: The text of the number.
+fn parse_number(text: &str) -> Result<u8, String> {
+    match text.strip_prefix("0x") {
+        Some(digits) => u8::from_str_radix(digits, 16),
+        None => text.parse(),
+    }
+    .map_err(|_| format!("Invalid number \"{text}\""))
+}
+
+/// A transaction performed by an [I2cMaster].
+///
+/// Any bytes to write are written first, and then any bytes to read are read following a repeated start.  A
+/// transaction with nothing to write or read just addresses the slave, to check that it is present.
+///
+/// A transaction can be parsed from text holding the slave address in hexadecimal, followed by `w` and the bytes to
+/// write in hexadecimal and/or `r` and the number of bytes to read in decimal, such as `50 w 00 r 2`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct I2cTransaction {
+    /// The 7 bit address of the slave.
+    pub address: u8,
+    /// The bytes to write.
+    pub write: Vec<u8>,
+    /// The number of bytes to read.
+    pub read: usize,
+}
+
+impl I2cTransaction {
+    /// Build the bus symbols which perform the transaction.
+    fn symbols(&self) -> VecDeque<Symbol> {
+        let mut symbols = VecDeque::new();
+        let byte = |symbols: &mut VecDeque<Symbol>, value: u8| {
+            symbols.extend((0..8).rev().map(|i| Symbol::Send((value >> i) & 1 != 0)));
+            symbols.push_back(Symbol::Ack);
+        };
+        if !self.write.is_empty() || self.read == 0 {
+            symbols.push_back(Symbol::Start);
+            byte(&mut symbols, self.address << 1);
+            for value in &self.write {
+                byte(&mut symbols, *value);
+            }
+        }
+        if self.read > 0 {
+            symbols.push_back(Symbol::Start);
+            byte(&mut symbols, (self.address << 1) | 1);
+            for i in 0..self.read {
+                symbols.extend([Symbol::Receive; 8]);
+                symbols.push_back(Symbol::Send(i + 1 == self.read));
+            }
+        }
+        symbols.push_back(Symbol::Stop);
+
+        symbols
+    }
+}
+
+impl FromStr for I2cTransaction {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("Invalid I2C transaction \"{s}\"");
+        let mut tokens = s.split_whitespace();
+        let address = tokens
+            .next()
+            .and_then(|token| u8::from_str_radix(token, 16).ok())
+            .filter(|address| *address < 0x80)
+            .ok_or_else(invalid)?;
+        let mut transaction = Self {
+            address,
+            write: Vec::new(),
+            read: 0,
+        };
+        let mut reading = false;
+        let mut writing = false;
+        for token in tokens {
+            match token {
+                "w" if !writing && !reading => writing = true,
+                "r" if !reading => reading = true,
+                _ if reading && transaction.read == 0 => {
+                    transaction.read = token.parse().map_err(|_| invalid())?
+                }
+                _ if writing && !reading => transaction
+                    .write
+                    .push(u8::from_str_radix(token, 16).map_err(|_| invalid())?),
+                _ => return Err(invalid()),
+            }
+        }
+        if reading && transaction.read == 0 {
+            return Err(invalid());
+        }
+
+        Ok(transaction)
+    }
+}
+
+/// The outcome of an [I2cTransaction].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum I2cOutcome {
+    /// The transaction completed, with the bytes read.
+    Complete(Vec<u8>),
+    /// The slave did not acknowledge a byte, so the transaction was abandoned with a stop condition.
+    Nack,
+    /// Another master won arbitration for the bus, so the transaction was abandoned.
+    ArbitrationLost,
+}
+
+/// The queues shared between an [I2cMaster] and its [I2cHost].
+#[derive(Debug, Default)]
+struct HostQueues {
+    /// Transactions waiting to be performed.
+    pending: VecDeque<I2cTransaction>,
+    /// Outcomes of the transactions performed, in order.
+    outcomes: Vec<I2cOutcome>,
+}
+
+/// The host side of an [I2cMaster], through which a testbench queues transactions and collects their outcomes.
+///
+/// The queues are shared by every copy of the master, so a Simulation holding the master refuses to
+/// [step back](crate::sim::Simulation::step_back).
+#[derive(Debug, Clone, Default)]
+pub struct I2cHost {
+    /// The shared queues.
+    queues: Arc<Mutex<HostQueues>>,
+}
+
+impl I2cHost {
+    /// Queue a transaction to be performed once the master has finished any before it.
+    ///
+    /// # Parameters
+    ///
+    /// - `transaction`: The transaction to perform.
+    pub fn queue(&self, transaction: I2cTransaction) {
+        self.lock().pending.push_back(transaction);
+    }
+
+    /// Take the outcomes of the transactions performed since they were last taken, in order.
+    pub fn take_outcomes(&self) -> Vec<I2cOutcome> {
+        std::mem::take(&mut self.lock().outcomes)
+    }
+
+    /// Query whether any queued transactions have not been started.
+    pub fn is_pending(&self) -> bool {
+        !self.lock().pending.is_empty()
+    }
+
+    /// Lock the shared queues.
+    fn lock(&self) -> std::sync::MutexGuard<'_, HostQueues> {
+        self.queues.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// One bit period of a master on the bus.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Symbol {
+    /// A start or repeated start condition.
+    Start,
+    /// A bit sent by the master, which is also the master's acknowledgement of a byte read.
+    Send(bool),
+    /// An acknowledgement sampled from the slave.
+    Ack,
+    /// A bit received from the slave.
+    Receive,
+    /// A stop condition.
+    Stop,
+}
+
+/// An I2C bus master, performing the transactions queued through its [I2cHost].
+///
+/// The inputs and outputs are both named `SCL` and `SDA`, and drive open-drain.  Each bit takes one SCL period, divided
+/// into quarters: SDA is set in the first, SCL is released in the second, SDA is sampled at the start of the third,
+/// and SCL is pulled low in the fourth.  The second quarter does not end until SCL is seen high, so a slave may
+/// stretch the clock by holding it low.  A start condition is not begun until SDA is seen high as well.
+///
+/// If SDA is low when the master sends a high bit, another master has won arbitration, so the master releases the bus
+/// and abandons the transaction.  If a slave does not acknowledge a byte, the transaction is abandoned with a stop
+/// condition.
+///
+/// The SCL period should span several simulation steps, so that each quarter lasts at least one step.
+///
+/// # Example
+///
+/// ```
+/// # use rvfs_sim_core::element::i2c::{I2cMaster, I2cTransaction};
+/// let master = I2cMaster::new("U1", 10_000).unwrap();
+/// master.host().queue("50 w 00 r 2".parse::<I2cTransaction>().unwrap());
+///
+/// assert!(master.host().is_pending());
+/// ```
+#[derive(Debug, Clone)]
+pub struct I2cMaster {
+    /// Name of the master.
+    name: String,
+    /// Duration of a quarter of the SCL period.
+    quarter: u64,
+    /// The host side, holding the transactions to perform.
+    host: I2cHost,
+    /// The remaining symbols of the transaction in progress.
+    symbols: VecDeque<Symbol>,
+    /// The present quarter of the symbol in progress.
+    phase: u8,
+    /// Time elapsed in the present quarter.
+    elapsed: u64,
+    /// Bytes read so far in the transaction in progress, the last of which may be incomplete.
+    data: Vec<u8>,
+    /// Number of bits received so far in the transaction in progress.
+    received: usize,
+    /// Whether the slave failed to acknowledge a byte of the transaction in progress.
+    nack: bool,
+    /// Whether SCL is pulled low.
+    scl_low: bool,
+    /// Whether SDA is pulled low.
+    sda_low: bool,
+}
+
+impl I2cMaster {
+    /// Create a new I2cMaster.
+    ///
+    /// # Parameters
+    ///
+    /// - `name`: Name of the master.
+    /// - `period`: The SCL period, which must be at least 4 time units.
+    pub fn new(name: &str, period: u64) -> Result<Self, String> {
+        if period < 4 {
+            return Err(format!("I2C master \"{name}\": period must be at least 4"));
+        }
+
+        Ok(Self {
+            name: name.to_string(),
+            quarter: period / 4,
+            host: I2cHost::default(),
+            symbols: VecDeque::new(),
+            phase: 0,
+            elapsed: 0,
+            data: Vec::new(),
+            received: 0,
+            nack: false,
+            scl_low: false,
+            sda_low: false,
+        })
+    }
+
+    /// Obtain the host side of the master.
+    pub fn host(&self) -> I2cHost {
+        self.host.clone()
+    }
+
+    /// Finish the transaction in progress, reporting its outcome to the host.
+    ///
+    /// # Parameters
+    ///
+    /// - `outcome`: The outcome of the transaction.
+    fn finish(&mut self, outcome: I2cOutcome) {
+        self.symbols.clear();
+        self.scl_low = false;
+        self.sda_low = false;
+        self.host.lock().outcomes.push(outcome);
+    }
+
+    /// Begin the present quarter of the symbol in progress.
+    ///
+    /// # Parameters
+    ///
+    /// - `sda`: The sampled state of SDA.
+    fn enter(&mut self, sda: InputPinState) {
+        let Some(symbol) = self.symbols.front().copied() else {
+            return;
+        };
+        match (self.phase, symbol) {
+            (0, Symbol::Start | Symbol::Ack | Symbol::Receive) => self.sda_low = false,
+            (0, Symbol::Send(value)) => self.sda_low = !value,
+            (0, Symbol::Stop) => self.sda_low = true,
+            (1, _) => self.scl_low = false,
+            (2, Symbol::Start) => self.sda_low = true,
+            (2, Symbol::Stop) => self.sda_low = false,
+            (2, Symbol::Send(true)) if sda != InputPinState::High => {
+                self.finish(I2cOutcome::ArbitrationLost)
+            }
+            (2, Symbol::Ack) if sda != InputPinState::Low => {
+                self.nack = true;
+                self.symbols.truncate(1);
+                self.symbols.push_back(Symbol::Stop);
+            }
+            (2, Symbol::Receive) => {
+                if self.received.is_multiple_of(8) {
+                    self.data.push(0);
+                }
+                if let Some(byte) = self.data.last_mut() {
+                    *byte = (*byte << 1) | u8::from(sda == InputPinState::High);
+                }
+                self.received += 1;
+            }
+            (3, Symbol::Start | Symbol::Send(_) | Symbol::Ack | Symbol::Receive) => {
+                self.scl_low = true
+            }
+            _ => (),
+        }
+    }
+}
+
+impl Element for I2cMaster {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn input_pins(&self) -> Vec<InputPin> {
+        ["SCL", "SDA"].into_iter().map(InputPin::new).collect()
+    }
+
+    fn output_pins(&self) -> Vec<OutputPin> {
+        ["SCL", "SDA"]
+            .into_iter()
+            .map(|name| OutputPin::new(name, SimDuration::ZERO, OutputPinState::HighImpedance))
+            .collect()
+    }
+
+    fn step(
+        &mut self,
+        inputs: &[InputPin],
+        outputs: &mut [OutputPin],
+        delta_t: u64,
+    ) -> Result<SimResult, String> {
+        let [scl, sda] = inputs else {
+            return Err(format!("I2C master \"{}\": expected 2 inputs", self.name));
+        };
+        let [scl_out, sda_out] = outputs else {
+            return Err(format!("I2C master \"{}\": expected 2 outputs", self.name));
+        };
+
+        if self.symbols.is_empty() {
+            let next = self.host.lock().pending.pop_front();
+            if let Some(transaction) = next {
+                self.symbols = transaction.symbols();
+                self.phase = 0;
+                self.elapsed = 0;
+                self.data.clear();
+                self.received = 0;
+                self.nack = false;
+                self.enter(sda.state());
+            }
+        } else {
+            self.elapsed += delta_t;
+            let released = match (self.phase, self.symbols.front()) {
+                (1, Some(Symbol::Start)) => {
+                    scl.state() == InputPinState::High && sda.state() == InputPinState::High
+                }
+                (1, _) => scl.state() == InputPinState::High,
+                _ => true,
+            };
+            if self.elapsed >= self.quarter && released {
+                self.elapsed = 0;
+                self.phase = (self.phase + 1) % 4;
+                if self.phase == 0 {
+                    self.symbols.pop_front();
+                    if self.symbols.is_empty() {
+                        let outcome = if self.nack {
+                            I2cOutcome::Nack
+                        } else {
+                            I2cOutcome::Complete(std::mem::take(&mut self.data))
+                        };
+                        self.finish(outcome);
+                    }
+                }
+                self.enter(sda.state());
+            }
+        }
+        scl_out.drive(open_drain(self.scl_low));
+        sda_out.drive(open_drain(self.sda_low));
+
+        Ok(SimResult::Continuing)
+    }
+
+    fn is_replayable(&self) -> bool {
+        false
+    }
+
+    fn box_clone(&self) -> Box<dyn Element> {
+        Box::new(self.clone())
+    }
+}
+
+/// The registers of a device modelled by an [I2cSlave].
+///
+/// Implement this to model a real device, such as a sensor whose registers change as it measures.
+pub trait RegisterMap: Send + fmt::Debug {
+    /// Read a register, as the master reads the byte at the register pointer.
+    ///
+    /// # Parameters
+    ///
+    /// - `register`: The register number.
+    fn read(&mut self, register: u8) -> u8;
+
+    /// Write a register, as the master writes a byte following the register pointer.
+    ///
+    /// # Parameters
+    ///
+    /// - `register`: The register number.
+    /// - `value`: The value written.
+    fn write(&mut self, register: u8, value: u8);
+
+    /// Create a boxed copy of the registers, so Simulations holding them can be checkpointed.
+    fn box_clone(&self) -> Box<dyn RegisterMap>;
+}
+
+impl Clone for Box<dyn RegisterMap> {
+    fn clone(&self) -> Self {
+        self.box_clone()
+    }
+}
+
+/// A plain file of read/write registers.
+///
+/// Reading a register beyond the end of the file gives 0xff, as an undriven bus would, and writing one has no effect.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RegisterFile {
+    /// The register values.
+    values: Vec<u8>,
+}
+
+impl RegisterFile {
+    /// Create a new RegisterFile with every register zero.
+    ///
+    /// # Parameters
+    ///
+    /// - `size`: Number of registers, up to 256.
+    pub fn new(size: usize) -> Self {
+        Self {
+            values: vec![0; size.min(256)],
+        }
+    }
+
+    /// Obtain the register values.
+    pub fn values(&self) -> &[u8] {
+        &self.values
+    }
+}
+
+impl RegisterMap for RegisterFile {
+    fn read(&mut self, register: u8) -> u8 {
+        self.values
+            .get(usize::from(register))
+            .copied()
+            .unwrap_or(0xff)
+    }
+
+    fn write(&mut self, register: u8, value: u8) {
+        if let Some(slot) = self.values.get_mut(usize::from(register)) {
+            *slot = value;
+        }
+    }
+
+    fn box_clone(&self) -> Box<dyn RegisterMap> {
+        Box::new(self.clone())
+    }
+}
+
+/// The part of a transfer an [I2cSlave] is taking part in.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum SlaveState {
+    /// Not addressed, waiting for a start condition.
+    Idle,
+    /// Receiving the address byte.
+    Address,
+    /// Receiving the register pointer.
+    Pointer,
+    /// Receiving bytes to write to the registers.
+    Write,
+    /// Sending bytes read from the registers.
+    Read,
+}
+
+/// An I2C slave, answering for a [RegisterMap] in the usual register-pointer style.
+///
+/// The inputs and outputs are both named `SCL` and `SDA`, and drive open-drain.  The first byte written after the
+/// slave is addressed sets the register pointer, and each further byte is written to the register at the pointer.
+/// Each byte read comes from the register at the pointer.  The pointer advances after every register written or read,
+/// and is kept across a repeated start, so a register is read by writing its number and then reading.
+///
+/// After acknowledging each byte, the slave can stretch the clock by holding SCL low for a while, as a slow device
+/// does.  A bit sampled while SDA is indeterminate makes the slave ignore the rest of the transfer.
+#[derive(Debug, Clone)]
+pub struct I2cSlave {
+    /// Name of the slave.
+    name: String,
+    /// The 7 bit address of the slave.
+    address: u8,
+    /// Time to hold SCL low after acknowledging a byte.
+    stretch: u64,
+    /// The registers of the device.
+    registers: Box<dyn RegisterMap>,
+    /// The register pointer.
+    pointer: u8,
+    /// The part of the transfer in progress.
+    state: SlaveState,
+    /// The byte being received or sent.
+    byte: u8,
+    /// Number of clock pulses seen for the byte, with 9 during its acknowledgement.
+    bits: u8,
+    /// Whether the master acknowledged the byte most recently sent.
+    acked: bool,
+    /// Remaining time for which SCL is held low.
+    hold: u64,
+    /// Whether SDA is pulled low.
+    sda_low: bool,
+}
+
+impl I2cSlave {
+    /// Create a new I2cSlave.
+    ///
+    /// # Parameters
+    ///
+    /// - `name`: Name of the slave.
+    /// - `address`: The 7 bit address of the slave.
+    /// - `stretch`: Time to hold SCL low after acknowledging a byte.
+    /// - `registers`: The registers of the device.
+    pub fn new(
+        name: &str,
+        address: u8,
+        stretch: u64,
+        registers: Box<dyn RegisterMap>,
+    ) -> Result<Self, String> {
+        if address >= 0x80 {
+            return Err(format!("I2C slave \"{name}\": address must be 7 bits"));
+        }
+
+        Ok(Self {
+            name: name.to_string(),
+            address,
+            stretch,
+            registers,
+            pointer: 0,
+            state: SlaveState::Idle,
+            byte: 0,
+            bits: 0,
+            acked: false,
+            hold: 0,
+            sda_low: false,
+        })
+    }
+
+    /// Obtain the registers of the device.
+    pub fn registers(&self) -> &dyn RegisterMap {
+        self.registers.as_ref()
+    }
+
+    /// Drive the bit of the byte being sent which follows the clock pulses seen so far.
+    fn send_bit(&mut self) {
+        self.sda_low = (self.byte >> (7 - self.bits)) & 1 == 0;
+    }
+
+    /// Handle a rising edge on SCL, when the bit on SDA is valid.
+    ///
+    /// # Parameters
+    ///
+    /// - `sda`: The bit on SDA, if definite.
+    fn rising(&mut self, sda: Option<bool>) {
+        match (self.state, self.bits) {
+            (SlaveState::Idle, _) => (),
+            (SlaveState::Read, 9) => self.acked = sda == Some(false),
+            (SlaveState::Read, _) => self.bits += 1,
+            (_, 9) => (),
+            (_, _) => match sda {
+                Some(value) => {
+                    self.byte = (self.byte << 1) | u8::from(value);
+                    self.bits += 1;
+                }
+                None => self.state = SlaveState::Idle,
+            },
+        }
+    }
+
+    /// Handle a falling edge on SCL, when SDA may change.
+    fn falling(&mut self) {
+        match (self.state, self.bits) {
+            (SlaveState::Idle, _) => self.sda_low = false,
+            (SlaveState::Read, 8) => {
+                self.sda_low = false;
+                self.bits = 9;
+            }
+            (SlaveState::Read, 9) => {
+                self.pointer = self.pointer.wrapping_add(1);
+                if self.acked {
+                    self.byte = self.registers.read(self.pointer);
+                    self.bits = 0;
+                    self.hold = self.stretch;
+                    self.send_bit();
+                } else {
+                    self.state = SlaveState::Idle;
+                    self.sda_low = false;
+                }
+            }
+            (SlaveState::Read, _) => self.send_bit(),
+            (state, 8) => {
+                if state != SlaveState::Address || self.byte >> 1 == self.address {
+                    self.sda_low = true;
+                    self.bits = 9;
+                } else {
+                    self.state = SlaveState::Idle;
+                }
+            }
+            (state, 9) => {
+                self.sda_low = false;
+                self.bits = 0;
+                self.hold = self.stretch;
+                let byte = std::mem::take(&mut self.byte);
+                match state {
+                    SlaveState::Address if byte & 1 == 1 => {
+                        self.state = SlaveState::Read;
+                        self.byte = self.registers.read(self.pointer);
+                        self.send_bit();
+                    }
+                    SlaveState::Address => self.state = SlaveState::Pointer,
+                    SlaveState::Pointer => {
+                        self.pointer = byte;
+                        self.state = SlaveState::Write;
+                    }
+                    _ => {
+                        self.registers.write(self.pointer, byte);
+                        self.pointer = self.pointer.wrapping_add(1);
+                    }
+                }
+            }
+            _ => (),
+        }
+    }
+}
+
+impl Element for I2cSlave {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn input_pins(&self) -> Vec<InputPin> {
+        ["SCL", "SDA"].into_iter().map(InputPin::new).collect()
+    }
+
+    fn output_pins(&self) -> Vec<OutputPin> {
+        ["SCL", "SDA"]
+            .into_iter()
+            .map(|name| OutputPin::new(name, SimDuration::ZERO, OutputPinState::HighImpedance))
+            .collect()
+    }
+
+    fn step(
+        &mut self,
+        inputs: &[InputPin],
+        outputs: &mut [OutputPin],
+        delta_t: u64,
+    ) -> Result<SimResult, String> {
+        let [scl, sda] = inputs else {
+            return Err(format!("I2C slave \"{}\": expected 2 inputs", self.name));
+        };
+        let [scl_out, sda_out] = outputs else {
+            return Err(format!("I2C slave \"{}\": expected 2 outputs", self.name));
+        };
+
+        self.hold = self.hold.saturating_sub(delta_t);
+        let clock_high = scl.state() == InputPinState::High && !scl.changed();
+        if clock_high && sda.falling() {
+            // A start or repeated start condition.
+            self.state = SlaveState::Address;
+            self.byte = 0;
+            self.bits = 0;
+            self.sda_low = false;
+        } else if clock_high && sda.rising() {
+            // A stop condition.
+            self.state = SlaveState::Idle;
+            self.sda_low = false;
+        } else if scl.rising() {
+            self.rising(bit(sda));
+        } else if scl.falling() {
+            self.falling();
+        }
+        scl_out.drive(open_drain(self.hold > 0));
+        sda_out.drive(open_drain(self.sda_low));
+
+        Ok(SimResult::Continuing)
+    }
+
+    fn box_clone(&self) -> Box<dyn Element> {
+        Box::new(self.clone())
+    }
+}
+
+/// Register every kind of I2C element.
+///
+/// The master takes the `period` (default 10000) parameter, and the `transactions` parameter holding
+/// [transactions](I2cTransaction) to queue, separated by `;`.  The slave is a [RegisterFile], taking the `address`
+/// (decimal, or hexadecimal with a `0x` prefix), `size` (default 256) and `stretch` (default 0) parameters.
+///
+/// # Parameters
+///
+/// - `registry`: The Registry to add the I2C elements to.
+pub fn register(registry: &mut Registry) {
+    registry.register("i2c_master", |name, parameters: &Parameters| {
+        let master = I2cMaster::new(name, parameters.get_or("period", DEFAULT_PERIOD)?)?;
+        let transactions: String = parameters.get_or("transactions", String::new())?;
+        for transaction in transactions.split(';').filter(|t| !t.trim().is_empty()) {
+            master.host().queue(transaction.parse()?);
+        }
+        Ok(Box::new(master))
+    });
+    registry.register("i2c_registers", |name, parameters: &Parameters| {
+        let address: String = parameters.get_or("address", String::new())?;
+        let address =
+            parse_number(&address).map_err(|message| format!("I2C slave \"{name}\": {message}"))?;
+        Ok(Box::new(I2cSlave::new(
+            name,
+            address,
+            parameters.get_or("stretch", 0)?,
+            Box::new(RegisterFile::new(parameters.get_or("size", DEFAULT_SIZE)?)),
+        )?))
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sim::Simulation;
+    use crate::wire::{Wire, WirePull};
+    use crate::WireId;
+
+    /// Connect an I2C element to the bus Wires.
+    fn attach(sim: &mut Simulation, element: Box<dyn Element>, scl: WireId, sda: WireId) {
+        let id = sim.add_element(element).unwrap();
+        for (name, wire) in [("SCL", scl), ("SDA", sda)] {
+            sim.connect_input(wire, sim.input_pin(id, name).unwrap())
+                .unwrap();
+            sim.connect_output(sim.output_pin(id, name).unwrap(), wire)
+                .unwrap();
+        }
+    }
+
+    /// Build a simulation of a bus with pulled up Wires, a register slave at address 0x50 stretching the clock by the
+    /// given time, and the given number of masters, returning the simulation and the hosts of the masters.
+    fn bus(stretch: u64, masters: usize) -> (Simulation, Vec<I2cHost>) {
+        let mut sim = Simulation::new(SimDuration::from_ticks(10));
+        let scl = sim.add_wire(Wire::new("SCL", WirePull::Up)).unwrap();
+        let sda = sim.add_wire(Wire::new("SDA", WirePull::Up)).unwrap();
+        let slave = I2cSlave::new("U1", 0x50, stretch, Box::new(RegisterFile::new(4))).unwrap();
+        attach(&mut sim, Box::new(slave), scl, sda);
+        let hosts = (0..masters)
+            .map(|i| {
+                let master = I2cMaster::new(&format!("M{i}"), 80).unwrap();
+                let host = master.host();
+                attach(&mut sim, Box::new(master), scl, sda);
+                host
+            })
+            .collect();
+        (sim, hosts)
+    }
+
+    /// Step a simulation until every host has the given number of outcomes, returning the number of steps taken.
+    fn run(
+        sim: &mut Simulation,
+        hosts: &[I2cHost],
+        outcomes: &mut [Vec<I2cOutcome>],
+        count: usize,
+    ) -> usize {
+        for steps in 0..10_000 {
+            for (host, outcomes) in hosts.iter().zip(outcomes.iter_mut()) {
+                outcomes.extend(host.take_outcomes());
+            }
+            if outcomes.iter().all(|outcomes| outcomes.len() >= count) {
+                return steps;
+            }
+            sim.step().unwrap();
+        }
+        panic!("I2C transactions did not complete");
+    }
+
+    #[test]
+    fn transaction_parse() {
+        // GIVEN transactions as text
+        // WHEN they are parsed
+        // THEN the address, bytes written and count read are found, and invalid text is rejected
+        assert_eq!(
+            Ok(I2cTransaction {
+                address: 0x50,
+                write: vec![0x00, 0xab],
+                read: 2
+            }),
+            "50 w 00 ab r 2".parse()
+        );
+        assert_eq!(
+            Ok(0x68),
+            "68 r 1".parse().map(|t: I2cTransaction| t.address)
+        );
+        assert!("80 w 00".parse::<I2cTransaction>().is_err());
+        assert!("50 w 00 r".parse::<I2cTransaction>().is_err());
+        assert!("50 r 1 w 00".parse::<I2cTransaction>().is_err());
+    }
+    #[test]
+    fn i2c_write_then_read() {
+        // GIVEN a master and a register slave on a bus
+        let (mut sim, hosts) = bus(0, 1);
+        let mut outcomes = vec![Vec::new()];
+        // WHEN registers are written, and then read back from the second register
+        hosts[0].queue("50 w 01 12 34".parse().unwrap());
+        hosts[0].queue("50 w 02 r 2".parse().unwrap());
+        run(&mut sim, &hosts, &mut outcomes, 2);
+        // THEN both transactions complete with the written data
+        assert_eq!(
+            vec![
+                I2cOutcome::Complete(vec![]),
+                I2cOutcome::Complete(vec![0x34, 0x00])
+            ],
+            outcomes[0]
+        );
+    }
+    #[test]
+    fn i2c_nack() {
+        // GIVEN a master and a register slave on a bus
+        let (mut sim, hosts) = bus(0, 1);
+        let mut outcomes = vec![Vec::new()];
+        // WHEN a transaction is addressed to an absent slave
+        hosts[0].queue("51 w 00".parse().unwrap());
+        hosts[0].queue("50".parse().unwrap());
+        run(&mut sim, &hosts, &mut outcomes, 2);
+        // THEN it is not acknowledged, and the bus is left free for the next
+        assert_eq!(
+            vec![I2cOutcome::Nack, I2cOutcome::Complete(vec![])],
+            outcomes[0]
+        );
+    }
+    #[test]
+    fn i2c_clock_stretching() {
+        // GIVEN buses whose slaves do and do not stretch the clock
+        let (mut fast, fast_hosts) = bus(0, 1);
+        let (mut slow, slow_hosts) = bus(200, 1);
+        // WHEN the same transaction is performed on each
+        let transaction: I2cTransaction = "50 w 00 5a r 1".parse().unwrap();
+        fast_hosts[0].queue(transaction.clone());
+        slow_hosts[0].queue(transaction);
+        let mut fast_outcomes = vec![Vec::new()];
+        let mut slow_outcomes = vec![Vec::new()];
+        let fast_steps = run(&mut fast, &fast_hosts, &mut fast_outcomes, 1);
+        let slow_steps = run(&mut slow, &slow_hosts, &mut slow_outcomes, 1);
+        // THEN both complete, but the master waits for the stretching slave
+        assert_eq!(fast_outcomes, slow_outcomes);
+        assert!(slow_steps >= fast_steps + 4 * 10);
+    }
+    #[test]
+    fn i2c_arbitration() {
+        // GIVEN two masters on a bus
+        let (mut sim, hosts) = bus(0, 2);
+        let mut outcomes = vec![Vec::new(), Vec::new()];
+        // WHEN they write different values to the same register at the same time
+        hosts[0].queue("50 w 00 c0".parse().unwrap());
+        hosts[1].queue("50 w 00 a0".parse().unwrap());
+        run(&mut sim, &hosts, &mut outcomes, 1);
+        // THEN the master writing the lower value wins arbitration
+        assert_eq!(vec![I2cOutcome::ArbitrationLost], outcomes[0]);
+        assert_eq!(vec![I2cOutcome::Complete(vec![])], outcomes[1]);
+        // AND THEN the winner's value is written
+        hosts[0].queue("50 w 00 r 1".parse().unwrap());
+        run(&mut sim, &hosts[..1], &mut outcomes[..1], 2);
+        assert_eq!(I2cOutcome::Complete(vec![0xa0]), outcomes[0][1]);
+    }
+    #[test]
+    fn i2c_registered() {
+        // GIVEN the standard registry
+        let registry = Registry::standard();
+        // WHEN I2C elements are created with good and bad parameters
+        let master = registry.create(
+            "i2c_master",
+            "M1",
+            &Parameters::new().with("transactions", "50 w 00; 50 r 1"),
+        );
+        let slave = registry.create(
+            "i2c_registers",
+            "U1",
+            &Parameters::new().with("address", "0x50"),
+        );
+        // THEN only the good parameters are accepted
+        assert_eq!(2, master.unwrap().output_pins().len());
+        assert_eq!("SDA", slave.unwrap().input_pins()[1].name());
+        assert!(registry
+            .create(
+                "i2c_master",
+                "M2",
+                &Parameters::new().with("transactions", "x")
+            )
+            .is_err());
+        assert!(registry
+            .create(
+                "i2c_registers",
+                "U2",
+                &Parameters::new().with("address", "0x80")
+            )
+            .is_err());
+        assert!(registry
+            .create("i2c_registers", "U3", &Parameters::new())
+            .is_err());
+    }
+}
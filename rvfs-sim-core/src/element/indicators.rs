@@ -0,0 +1,318 @@
+//! LEDs and other indicators, which record when they are lit so that human-visible outputs can be tested.
+
+use crate::element::{bit, Element, Parameters, Registry, UNITS_PER_SECOND};
+use crate::ipin::InputPin;
+use crate::opin::OutputPin;
+use crate::sim::SimResult;
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+
+/// Maximum number of on intervals retained for each LED.
+const INTERVAL_LIMIT: usize = 1000;
+/// Number of most recent blink periods over which the blink frequency is estimated.
+const FREQUENCY_WINDOW: usize = 8;
+
+/// The illumination history of an LED.
+#[derive(Debug, Default)]
+struct History {
+    /// Time elapsed since the start of the Simulation.
+    time: u64,
+    /// The time at which the LED last lit, if it is lit.
+    on_since: Option<u64>,
+    /// The most recent intervals for which the LED was lit, as start and end times.
+    intervals: VecDeque<(u64, u64)>,
+    /// Total time for which the LED was lit over the intervals which have ended, including any no longer retained.
+    on_time: u64,
+}
+
+/// The illumination history of an [Led], shared with the Simulation holding it so that it can be examined and asserted
+/// on by a testbench.
+///
+/// The history is shared by every copy of the LED, so a Simulation holding the LED refuses to
+/// [step back](crate::sim::Simulation::step_back).  Frequencies are in Hz, taking one time unit as one nanosecond.
+#[derive(Debug, Clone, Default)]
+pub struct LedRecord {
+    /// The shared history.
+    history: Arc<Mutex<History>>,
+}
+
+impl LedRecord {
+    /// Query whether the LED is lit.
+    pub fn is_on(&self) -> bool {
+        self.lock().on_since.is_some()
+    }
+
+    /// Obtain the most recent intervals for which the LED was lit, as start and end times, with no end time if it is
+    /// still lit.  Up to 1000 intervals are retained.
+    pub fn intervals(&self) -> Vec<(u64, Option<u64>)> {
+        let history = self.lock();
+        history
+            .intervals
+            .iter()
+            .map(|(start, end)| (*start, Some(*end)))
+            .chain(history.on_since.map(|start| (start, None)))
+            .collect()
+    }
+
+    /// Obtain the total time for which the LED has been lit.
+    pub fn on_time(&self) -> u64 {
+        let history = self.lock();
+        history.on_time + history.on_since.map_or(0, |start| history.time - start)
+    }
+
+    /// Obtain the fraction of the time since the start of the Simulation for which the LED has been lit, if any time
+    /// has passed.
+    pub fn duty(&self) -> Option<f64> {
+        let time = self.lock().time;
+        (time > 0).then(|| self.on_time() as f64 / time as f64)
+    }
+
+    /// Estimate the frequency at which the LED is blinking from the times at which it lit over its 8 most recent
+    /// blinks, if it has blinked at least once.
+    pub fn blink_frequency(&self) -> Option<f64> {
+        let history = self.lock();
+        let starts: Vec<u64> = history
+            .intervals
+            .iter()
+            .map(|(start, _)| *start)
+            .chain(history.on_since)
+            .collect();
+        let recent = &starts[starts.len().saturating_sub(FREQUENCY_WINDOW + 1)..];
+        match recent {
+            [first, .., last] if last > first => {
+                Some((recent.len() - 1) as f64 * UNITS_PER_SECOND / (last - first) as f64)
+            }
+            _ => None,
+        }
+    }
+
+    /// Check that the LED is blinking at a frequency, such as 2 Hz.
+    ///
+    /// # Parameters
+    ///
+    /// - `frequency`: The expected frequency, in Hz.
+    /// - `tolerance`: The allowed error, as a fraction of the expected frequency.
+    pub fn check_blinking(&self, frequency: f64, tolerance: f64) -> Result<(), String> {
+        match self.blink_frequency() {
+            Some(actual) if (actual - frequency).abs() <= frequency * tolerance => Ok(()),
+            Some(actual) => Err(format!(
+                "LED blinks at {actual:.3} Hz, expected {frequency:.3} Hz"
+            )),
+            None => Err(format!("LED is not blinking, expected {frequency:.3} Hz")),
+        }
+    }
+
+    /// Lock the shared history.
+    fn lock(&self) -> MutexGuard<'_, History> {
+        self.history.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// A light-emitting diode.
+///
+/// The input is named `A`.  The LED is lit while the input is high, or while it is low for an active-low LED wired
+/// from a supply to its driver, and is dark while the input is indeterminate.  The LED has no outputs, and its
+/// illumination over time is recorded in its [LedRecord].
+///
+/// # Example
+///
+/// ```
+/// # use rvfs_sim_core::element::indicators::Led;
+/// # use rvfs_sim_core::element::Element;
+/// let led = Led::new("D1", false);
+///
+/// assert_eq!("A", led.input_pins()[0].name());
+/// assert!(!led.record().is_on());
+/// assert_eq!(None, led.record().blink_frequency());
+/// ```
+#[derive(Debug, Clone)]
+pub struct Led {
+    /// Name of the LED.
+    name: String,
+    /// Whether the LED is lit by a low input.
+    active_low: bool,
+    /// The illumination history.
+    record: LedRecord,
+}
+
+impl Led {
+    /// Create a new Led.
+    ///
+    /// # Parameters
+    ///
+    /// - `name`: Name of the LED.
+    /// - `active_low`: Whether the LED is lit by a low input.
+    pub fn new(name: &str, active_low: bool) -> Self {
+        Self {
+            name: name.to_string(),
+            active_low,
+            record: LedRecord::default(),
+        }
+    }
+
+    /// Obtain the illumination history of the LED.
+    pub fn record(&self) -> LedRecord {
+        self.record.clone()
+    }
+}
+
+impl Element for Led {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn input_pins(&self) -> Vec<InputPin> {
+        vec![InputPin::new("A")]
+    }
+
+    fn output_pins(&self) -> Vec<OutputPin> {
+        Vec::new()
+    }
+
+    fn step(
+        &mut self,
+        inputs: &[InputPin],
+        _outputs: &mut [OutputPin],
+        delta_t: u64,
+    ) -> Result<SimResult, String> {
+        let [input] = inputs else {
+            return Err(format!("LED \"{}\": expected 1 input", self.name));
+        };
+
+        let lit = bit(input) == Some(!self.active_low);
+        let mut history = self.record.lock();
+        history.time += delta_t;
+        let time = history.time;
+        match (lit, history.on_since) {
+            (true, None) => history.on_since = Some(time),
+            (false, Some(start)) => {
+                history.on_since = None;
+                history.on_time += time - start;
+                if history.intervals.len() == INTERVAL_LIMIT {
+                    history.intervals.pop_front();
+                }
+                history.intervals.push_back((start, time));
+            }
+            _ => (),
+        }
+
+        Ok(SimResult::Continuing)
+    }
+
+    // The history is shared outside the Simulation, so the LED has no state of its own to save.
+    #[cfg(feature = "serde")]
+    fn save_state(&self) -> Result<serde_json::Value, String> {
+        Ok(serde_json::Value::Null)
+    }
+
+    #[cfg(feature = "serde")]
+    fn restore_state(&mut self, _state: serde_json::Value) -> Result<(), String> {
+        Ok(())
+    }
+
+    fn is_replayable(&self) -> bool {
+        false
+    }
+
+    fn box_clone(&self) -> Box<dyn Element> {
+        Box::new(self.clone())
+    }
+}
+
+/// Register every kind of indicator.
+///
+/// The `led` kind takes the `active_low` parameter (default false).
+///
+/// # Parameters
+///
+/// - `registry`: The Registry to add the indicators to.
+pub fn register(registry: &mut Registry) {
+    registry.register("led", |name, parameters: &Parameters| {
+        Ok(Box::new(Led::new(
+            name,
+            parameters.get_or("active_low", false)?,
+        )))
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::element::testing::inputs;
+
+    /// Step an LED through a sequence of input levels, each held for a duration.
+    fn drive(led: &mut Led, levels: &[(f32, u64)]) {
+        let mut previous = 0.0;
+        for &(level, duration) in levels {
+            led.step(&inputs([previous], [level]), &mut [], duration)
+                .unwrap();
+            previous = level;
+        }
+    }
+
+    #[test]
+    fn led_intervals() {
+        // GIVEN an LED and an active-low LED
+        let mut led = Led::new("D1", false);
+        let mut inverted = Led::new("D2", true);
+        // WHEN they are stepped through the same levels
+        let levels = [
+            (0.0, 10),
+            (1.0, 10),
+            (1.0, 20),
+            (0.5, 10),
+            (1.0, 10),
+            (0.0, 10),
+        ];
+        drive(&mut led, &levels);
+        drive(&mut inverted, &levels);
+        // THEN they record opposite intervals, with indeterminate inputs dark
+        assert_eq!(
+            vec![(20, Some(50)), (60, Some(70))],
+            led.record().intervals()
+        );
+        assert_eq!(40, led.record().on_time());
+        assert_eq!(
+            vec![(10, Some(20)), (70, None)],
+            inverted.record().intervals()
+        );
+        assert!(inverted.record().is_on());
+        assert_eq!(Some(10.0 / 70.0), inverted.record().duty());
+    }
+    #[test]
+    fn led_blinking() {
+        // GIVEN an LED
+        let mut led = Led::new("D1", false);
+        // WHEN it blinks at 2 Hz for a while, with a little jitter
+        for i in 0..10 {
+            drive(
+                &mut led,
+                &[(1.0, 250_000_000 + i * 1000), (0.0, 250_000_000)],
+            );
+        }
+        // THEN its blink frequency is estimated, and can be checked
+        let record = led.record();
+        assert!((record.blink_frequency().unwrap() - 2.0).abs() < 0.01);
+        assert!((record.duty().unwrap() - 0.5).abs() < 0.01);
+        assert!(record.check_blinking(2.0, 0.05).is_ok());
+        assert_eq!(
+            Err("LED blinks at 2.000 Hz, expected 3.000 Hz".to_string()),
+            record.check_blinking(3.0, 0.1)
+        );
+        assert!(Led::new("D2", false)
+            .record()
+            .check_blinking(2.0, 0.1)
+            .is_err());
+    }
+    #[test]
+    fn led_registered() {
+        // GIVEN the standard registry
+        let registry = Registry::standard();
+        // WHEN LEDs are created with good and bad parameters
+        let led = registry.create("led", "D1", &Parameters::new().with("active_low", "true"));
+        let bad = registry.create("led", "D2", &Parameters::new().with("active_low", "yes"));
+        // THEN only the good parameters are accepted
+        assert_eq!(1, led.unwrap().input_pins().len());
+        assert!(bad.is_err());
+    }
+}
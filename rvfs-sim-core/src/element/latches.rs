@@ -0,0 +1,343 @@
+//! Level-sensitive latches.
+//!
+//! Timing violations are modelled at the resolution of a step.  A latch which is released from a state it cannot
+//! hold, such as a transparent latch whose data input is indeterminate, or an SR latch whose set and reset inputs are
+//! released together, may go metastable and settle either way, so its stored bit becomes unknown.  An unknown bit
+//! drives the outputs unknown (X) and persists until the latch is next written with definite inputs.
+
+use crate::element::{bit, drive_outputs, Element, Parameters, Registry};
+use crate::ipin::{InputPin, InputPinState};
+use crate::opin::{OutputPin, OutputPinState};
+use crate::prelude::*;
+use crate::sim::SimResult;
+use crate::time::SimDuration;
+
+/// A transparent D latch, modelled on one bit of a 74373 without its output enable.
+///
+/// The inputs are named `D` and `EN`, and the outputs are named `Q` and `/Q`.  While `EN` is high the outputs follow
+/// `D` after the propagation delay, and while `EN` is low they hold the level `D` had when `EN` fell.
+///
+/// The stored bit is unknown until the latch is first enabled with a definite `D`.  It becomes unknown if `D` is
+/// indeterminate while the latch is transparent, or if `EN` is indeterminate while `D` differs from the stored bit.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DLatch {
+    /// Name of the latch.
+    name: String,
+    /// Propagation delay from an input to the outputs.
+    delay: u64,
+    /// The stored bit, if known.
+    state: Option<bool>,
+}
+
+impl DLatch {
+    /// Create a new DLatch.
+    ///
+    /// # Parameters
+    ///
+    /// - `name`: Name of the latch.
+    /// - `delay`: Propagation delay from an input to the outputs.
+    pub fn new(name: &str, delay: u64) -> Self {
+        Self {
+            name: name.to_string(),
+            delay,
+            state: None,
+        }
+    }
+
+    /// Obtain the stored bit, if known.
+    pub fn state(&self) -> Option<bool> {
+        self.state
+    }
+}
+
+impl Element for DLatch {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn input_pins(&self) -> Vec<InputPin> {
+        ["D", "EN"].into_iter().map(InputPin::new).collect()
+    }
+
+    fn output_pins(&self) -> Vec<OutputPin> {
+        ["Q", "/Q"]
+            .into_iter()
+            .map(|name| {
+                OutputPin::new(
+                    name,
+                    SimDuration::from_ticks(self.delay),
+                    OutputPinState::HighImpedance,
+                )
+            })
+            .collect()
+    }
+
+    fn step(
+        &mut self,
+        inputs: &[InputPin],
+        outputs: &mut [OutputPin],
+        _delta_t: u64,
+    ) -> Result<SimResult, String> {
+        let [d, en] = inputs else {
+            return Err(format!("Latch \"{}\": expected 2 inputs", self.name));
+        };
+        let [q, q_bar] = outputs else {
+            return Err(format!("Latch \"{}\": expected 2 outputs", self.name));
+        };
+
+        match en.state() {
+            InputPinState::High => self.state = bit(d),
+            InputPinState::Low => (),
+            InputPinState::Indeterminate => {
+                if bit(d) != self.state {
+                    self.state = None;
+                }
+            }
+        }
+        drive_outputs(self.state, q, q_bar);
+
+        Ok(SimResult::Continuing)
+    }
+
+    #[cfg(feature = "serde")]
+    fn save_state(&self) -> Result<serde_json::Value, String> {
+        super::save_state(self.state)
+    }
+
+    #[cfg(feature = "serde")]
+    fn restore_state(&mut self, state: serde_json::Value) -> Result<(), String> {
+        self.state = super::restore_state(&self.name, state)?;
+        Ok(())
+    }
+
+    fn box_clone(&self) -> Box<dyn Element> {
+        Box::new(self.clone())
+    }
+}
+
+/// An SR latch with active-low inputs, modelled on one latch of a 74279.
+///
+/// The inputs are named `/S` and `/R`, and the outputs are named `Q` and `/Q`.  Pulling `/S` low sets the latch and
+/// pulling `/R` low resets it, and an unconnected input is treated as inactive.  Holding both low drives both outputs
+/// high, as cross-coupled NAND gates do.
+///
+/// The stored bit is unknown until the latch is first set or reset.  It also becomes unknown if `/S` and `/R` are
+/// released on the same step after both being held low, since the latch then races to an unpredictable state.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SrLatch {
+    /// Name of the latch.
+    name: String,
+    /// Propagation delay from an input to the outputs.
+    delay: u64,
+    /// The stored bit, if known.
+    state: Option<bool>,
+    /// Whether both inputs were held low on the previous step.
+    forbidden: bool,
+}
+
+impl SrLatch {
+    /// Create a new SrLatch.
+    ///
+    /// # Parameters
+    ///
+    /// - `name`: Name of the latch.
+    /// - `delay`: Propagation delay from an input to the outputs.
+    pub fn new(name: &str, delay: u64) -> Self {
+        Self {
+            name: name.to_string(),
+            delay,
+            state: None,
+            forbidden: false,
+        }
+    }
+
+    /// Obtain the stored bit, if known.
+    pub fn state(&self) -> Option<bool> {
+        self.state
+    }
+}
+
+impl Element for SrLatch {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn input_pins(&self) -> Vec<InputPin> {
+        ["/S", "/R"].into_iter().map(InputPin::new).collect()
+    }
+
+    fn output_pins(&self) -> Vec<OutputPin> {
+        ["Q", "/Q"]
+            .into_iter()
+            .map(|name| {
+                OutputPin::new(
+                    name,
+                    SimDuration::from_ticks(self.delay),
+                    OutputPinState::HighImpedance,
+                )
+            })
+            .collect()
+    }
+
+    fn step(
+        &mut self,
+        inputs: &[InputPin],
+        outputs: &mut [OutputPin],
+        _delta_t: u64,
+    ) -> Result<SimResult, String> {
+        let [set, reset] = inputs else {
+            return Err(format!("Latch \"{}\": expected 2 inputs", self.name));
+        };
+        let [q, q_bar] = outputs else {
+            return Err(format!("Latch \"{}\": expected 2 outputs", self.name));
+        };
+
+        let set = set.state() == InputPinState::Low;
+        let reset = reset.state() == InputPinState::Low;
+        let forbidden = self.forbidden;
+        self.forbidden = set && reset;
+        match (set, reset) {
+            (true, true) => {
+                self.state = None;
+                q.drive(OutputPinState::High);
+                q_bar.drive(OutputPinState::High);
+                return Ok(SimResult::Continuing);
+            }
+            (true, false) => self.state = Some(true),
+            (false, true) => self.state = Some(false),
+            (false, false) => {
+                if forbidden {
+                    self.state = None;
+                }
+            }
+        }
+        drive_outputs(self.state, q, q_bar);
+
+        Ok(SimResult::Continuing)
+    }
+
+    #[cfg(feature = "serde")]
+    fn save_state(&self) -> Result<serde_json::Value, String> {
+        super::save_state((self.state, self.forbidden))
+    }
+
+    #[cfg(feature = "serde")]
+    fn restore_state(&mut self, state: serde_json::Value) -> Result<(), String> {
+        (self.state, self.forbidden) = super::restore_state(&self.name, state)?;
+        Ok(())
+    }
+
+    fn box_clone(&self) -> Box<dyn Element> {
+        Box::new(self.clone())
+    }
+}
+
+/// Register every kind of latch, taking the `delay` (default 0) parameter.
+///
+/// # Parameters
+///
+/// - `registry`: The Registry to add the latches to.
+pub fn register(registry: &mut Registry) {
+    registry.register("dlatch", |name, parameters: &Parameters| {
+        Ok(Box::new(DLatch::new(name, parameters.get_or("delay", 0)?)))
+    });
+    registry.register("srlatch", |name, parameters: &Parameters| {
+        Ok(Box::new(SrLatch::new(name, parameters.get_or("delay", 0)?)))
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::element::testing;
+
+    /// Build a set of inputs which have steadily sampled the given levels.
+    fn inputs<const N: usize>(levels: [f32; N]) -> Vec<InputPin> {
+        testing::inputs(levels, levels)
+    }
+
+    /// Step a latch once and let its outputs propagate, returning the output states.
+    fn step(
+        latch: &mut dyn Element,
+        outputs: &mut [OutputPin],
+        inputs: &[InputPin],
+    ) -> [OutputPinState; 2] {
+        latch.step(inputs, outputs, 10).unwrap();
+        for output in outputs.iter_mut() {
+            output.step(SimDuration::from_ticks(10));
+        }
+        [outputs[0].state(), outputs[1].state()]
+    }
+
+    const L: OutputPinState = OutputPinState::Low;
+    const H: OutputPinState = OutputPinState::High;
+    const X: OutputPinState = OutputPinState::Unknown;
+
+    #[test]
+    fn dlatch_transparent_and_holding() {
+        // GIVEN a new latch
+        let mut latch = DLatch::new("U1", 0);
+        let mut outputs = latch.output_pins();
+        // WHEN it is disabled
+        // THEN the outputs are unknown
+        assert_eq!([X, X], step(&mut latch, &mut outputs, &inputs([1.0, 0.0])));
+        // WHEN it is enabled
+        // THEN the outputs follow D
+        assert_eq!([H, L], step(&mut latch, &mut outputs, &inputs([1.0, 1.0])));
+        assert_eq!([L, H], step(&mut latch, &mut outputs, &inputs([0.0, 1.0])));
+        // WHEN it is disabled again
+        // THEN the outputs hold while D changes
+        assert_eq!([L, H], step(&mut latch, &mut outputs, &inputs([1.0, 0.0])));
+    }
+    #[test]
+    fn dlatch_indeterminate_inputs() {
+        // GIVEN a latch holding a low bit
+        let mut latch = DLatch::new("U1", 0);
+        let mut outputs = latch.output_pins();
+        step(&mut latch, &mut outputs, &inputs([0.0, 1.0]));
+        // WHEN EN is indeterminate while D agrees with the stored bit
+        // THEN the bit is kept
+        assert_eq!([L, H], step(&mut latch, &mut outputs, &inputs([0.0, 0.5])));
+        // WHEN EN is indeterminate while D differs from the stored bit
+        // THEN the bit becomes unknown
+        assert_eq!([X, X], step(&mut latch, &mut outputs, &inputs([1.0, 0.5])));
+        assert_eq!(None, latch.state());
+    }
+    #[test]
+    fn srlatch_set_reset() {
+        // GIVEN a new latch
+        let mut latch = SrLatch::new("U1", 0);
+        let mut outputs = latch.output_pins();
+        // WHEN it is set, released, reset and released
+        // THEN it holds the last written bit
+        assert_eq!([H, L], step(&mut latch, &mut outputs, &inputs([0.0, 1.0])));
+        assert_eq!([H, L], step(&mut latch, &mut outputs, &inputs([1.0, 1.0])));
+        assert_eq!([L, H], step(&mut latch, &mut outputs, &inputs([1.0, 0.0])));
+        assert_eq!([L, H], step(&mut latch, &mut outputs, &inputs([1.0, 1.0])));
+    }
+    #[test]
+    fn srlatch_simultaneous_release() {
+        // GIVEN a latch with both inputs held low
+        let mut latch = SrLatch::new("U1", 0);
+        let mut outputs = latch.output_pins();
+        assert_eq!([H, H], step(&mut latch, &mut outputs, &inputs([0.0, 0.0])));
+        // WHEN both inputs are released together
+        // THEN the stored bit is unknown
+        assert_eq!([X, X], step(&mut latch, &mut outputs, &inputs([1.0, 1.0])));
+        // AND THEN releasing one input before the other leaves the latch in the state of the one still held
+        step(&mut latch, &mut outputs, &inputs([0.0, 0.0]));
+        assert_eq!([L, H], step(&mut latch, &mut outputs, &inputs([1.0, 0.0])));
+    }
+    #[test]
+    fn latch_registered() {
+        // GIVEN the standard registry
+        let registry = Registry::standard();
+        // WHEN latches are created with a delay
+        let parameters = Parameters::new().with("delay", "7");
+        let dlatch = registry.create("dlatch", "U1", &parameters).unwrap();
+        let srlatch = registry.create("srlatch", "U2", &parameters).unwrap();
+        // THEN they have the expected pins and delay
+        assert_eq!(2, dlatch.input_pins().len());
+        assert_eq!(7, srlatch.output_pins()[1].delay().ticks());
+    }
+}
@@ -0,0 +1,1340 @@
+//! Memories, holding words of up to 64 bits.
+//!
+//! The contents of a memory may be loaded from an image file in one of several [formats](ImageFormat), so that
+//! firmware and microcode built by external tools can be part of a simulated system.
+
+use crate::element::{address_bits, level, word, Element, Parameters, Registry};
+use crate::ipin::{InputPin, InputPinState};
+use crate::opin::{OutputPin, OutputPinState};
+use crate::sim::SimResult;
+use crate::time::SimDuration;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Arc;
+
+/// Default word width of a memory instantiated from a configuration file.
+const DEFAULT_WIDTH: usize = 8;
+/// Default number of words of a memory instantiated from a configuration file.
+const DEFAULT_DEPTH: usize = 256;
+
+/// The format of a memory image file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageFormat {
+    /// Raw binary, with each word stored little-endian in the fewest whole bytes which hold it.
+    Binary,
+    /// Text holding one hexadecimal number per word, separated by whitespace, as read by Verilog's `$readmemh`.  A
+    /// token of the form `@address` moves to a new hexadecimal word address, and `//` or `#` starts a comment.
+    Hex,
+    /// Intel HEX records, addressing bytes which are assembled into words as for [Binary](ImageFormat::Binary).
+    IntelHex,
+    /// An ELF executable, whose loadable segments address bytes which are assembled into words as for
+    /// [Binary](ImageFormat::Binary), as [parse_elf] describes.
+    Elf,
+}
+
+impl FromStr for ImageFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "bin" => Ok(ImageFormat::Binary),
+            "hex" => Ok(ImageFormat::Hex),
+            "ihex" => Ok(ImageFormat::IntelHex),
+            "elf" => Ok(ImageFormat::Elf),
+            _ => Err(format!("Invalid image format \"{s}\"")),
+        }
+    }
+}
+
+/// Load the contents of a memory from an image file.
+///
+/// # Parameters
+///
+/// - `path`: Path of the image file.
+/// - `format`: Format of the image file.
+/// - `width`: Number of bits in each word, from 1 to 64.
+/// - `depth`: Number of words in the memory.
+pub fn load_image(
+    path: &Path,
+    format: ImageFormat,
+    width: usize,
+    depth: usize,
+) -> Result<Vec<u64>, String> {
+    let data = fs::read(path)
+        .map_err(|error| format!("Failed to read image \"{}\": {error}", path.display()))?;
+    parse_image(&data, format, width, depth)
+        .map_err(|message| format!("Invalid image \"{}\": {message}", path.display()))
+}
+
+/// Load the contents of a memory from an ELF executable, as [parse_elf] does.
+///
+/// # Parameters
+///
+/// - `path`: Path of the ELF file.
+/// - `width`: Number of bits in each word, from 1 to 64.
+/// - `depth`: Number of words in the memory.
+/// - `base`: Byte address at which the memory starts.
+pub fn load_elf(path: &Path, width: usize, depth: usize, base: u64) -> Result<Vec<u64>, String> {
+    let data = fs::read(path)
+        .map_err(|error| format!("Failed to read image \"{}\": {error}", path.display()))?;
+    parse_elf(&data, width, depth, base)
+        .map_err(|message| format!("Invalid image \"{}\": {message}", path.display()))
+}
+
+/// Parse the contents of a memory from the data of an image file.
+///
+/// Words which the image does not set are zero.  The image may not set words beyond the depth of the memory, nor
+/// values which do not fit in a word.
+///
+/// # Parameters
+///
+/// - `data`: The data of the image file.
+/// - `format`: Format of the image file.
+/// - `width`: Number of bits in each word, from 1 to 64.
+/// - `depth`: Number of words in the memory.
+///
+/// # Example
+///
+/// ```
+/// # use rvfs_sim_core::element::memory::{parse_image, ImageFormat};
+/// let contents = parse_image(b"12 34 @8 ff", ImageFormat::Hex, 8, 16).unwrap();
+///
+/// assert_eq!(16, contents.len());
+/// assert_eq!([0x12, 0x34, 0x00], contents[0..3]);
+/// assert_eq!(0xff, contents[8]);
+/// ```
+pub fn parse_image(
+    data: &[u8],
+    format: ImageFormat,
+    width: usize,
+    depth: usize,
+) -> Result<Vec<u64>, String> {
+    if !(1..=64).contains(&width) {
+        return Err(format!("word width {width} is not from 1 to 64 bits"));
+    }
+    let mut contents = vec![0; depth];
+    let mut store = |address: usize, value: u64| {
+        let slot = contents.get_mut(address).ok_or(format!(
+            "address {address:#x} is beyond the depth of {depth} words"
+        ))?;
+        if width < 64 && value >> width != 0 {
+            return Err(format!(
+                "value {value:#x} at address {address:#x} does not fit in {width} bits"
+            ));
+        }
+        *slot = value;
+        Ok(())
+    };
+    let word_bytes = width.div_ceil(8);
+
+    match format {
+        ImageFormat::Elf => return parse_elf(data, width, depth, 0),
+        ImageFormat::Binary => {
+            if !data.len().is_multiple_of(word_bytes) {
+                return Err(format!(
+                    "length {} is not a whole number of {word_bytes} byte words",
+                    data.len()
+                ));
+            }
+            for (address, bytes) in data.chunks(word_bytes).enumerate() {
+                store(address, little_endian(bytes))?;
+            }
+        }
+        ImageFormat::Hex => {
+            let text = std::str::from_utf8(data).map_err(|_| "not valid text".to_string())?;
+            let mut address = 0;
+            for line in text.lines() {
+                let line = line.split("//").next().unwrap_or_default();
+                let line = line.split('#').next().unwrap_or_default();
+                for token in line.split_whitespace() {
+                    let (target, digits) = match token.strip_prefix('@') {
+                        Some(digits) => (true, digits),
+                        None => (false, token),
+                    };
+                    let value = u64::from_str_radix(&digits.replace('_', ""), 16)
+                        .map_err(|_| format!("invalid hexadecimal number \"{token}\""))?;
+                    if target {
+                        address = value as usize;
+                    } else {
+                        store(address, value)?;
+                        address += 1;
+                    }
+                }
+            }
+        }
+        ImageFormat::IntelHex => {
+            let text = std::str::from_utf8(data).map_err(|_| "not valid text".to_string())?;
+            let mut records = Vec::new();
+            for (number, record) in text.lines().enumerate() {
+                let record = record.trim();
+                if record.is_empty() {
+                    continue;
+                }
+                let fields = intel_hex_record(record)
+                    .map_err(|message| format!("line {}: {message}", number + 1))?;
+                records.push(fields);
+            }
+            let mut words = std::collections::BTreeMap::new();
+            let mut base = 0;
+            for (kind, offset, payload) in records {
+                match kind {
+                    0x00 => {
+                        for (i, byte) in payload.iter().enumerate() {
+                            let address = base + offset + i;
+                            let value: &mut u64 = words.entry(address / word_bytes).or_default();
+                            *value |= u64::from(*byte) << (8 * (address % word_bytes));
+                        }
+                    }
+                    0x01 => break,
+                    0x02 => base = big_endian(&payload) << 4,
+                    0x04 => base = big_endian(&payload) << 16,
+                    _ => (),
+                }
+            }
+            for (address, value) in words {
+                store(address, value)?;
+            }
+        }
+    }
+
+    Ok(contents)
+}
+
+/// Parse the contents of a memory from the data of an ELF executable, as built for a processor by a compiler.
+///
+/// The bytes of each loadable segment are placed at its physical address, and the part of a segment which is larger in
+/// memory than in the file is zero.  The bytes are assembled into words as for [Binary](ImageFormat::Binary).  Only the
+/// part of each segment within the memory is loaded, so that the same executable can preload each memory of a system,
+/// such as its code into a ROM and its initial data into a RAM.  Both 32 and 64 bit, and little and big-endian, files
+/// are read.
+///
+/// # Parameters
+///
+/// - `data`: The data of the ELF file.
+/// - `width`: Number of bits in each word, from 1 to 64.
+/// - `depth`: Number of words in the memory.
+/// - `base`: Byte address at which the memory starts.
+pub fn parse_elf(data: &[u8], width: usize, depth: usize, base: u64) -> Result<Vec<u64>, String> {
+    /// Program header type of a loadable segment.
+    const PT_LOAD: u64 = 1;
+
+    if !(1..=64).contains(&width) {
+        return Err(format!("word width {width} is not from 1 to 64 bits"));
+    }
+    if !data.starts_with(b"\x7fELF") {
+        return Err("not an ELF file".to_string());
+    }
+    let wide = match data.get(4) {
+        Some(1) => false,
+        Some(2) => true,
+        _ => return Err("invalid ELF class".to_string()),
+    };
+    let big = match data.get(5) {
+        Some(1) => false,
+        Some(2) => true,
+        _ => return Err("invalid ELF data encoding".to_string()),
+    };
+    let field = |offset: usize, size: usize| {
+        let bytes = data
+            .get(offset..offset + size)
+            .ok_or("truncated ELF file".to_string())?;
+        Ok::<_, String>(if big {
+            bytes
+                .iter()
+                .fold(0, |value, byte| (value << 8) | u64::from(*byte))
+        } else {
+            little_endian(bytes)
+        })
+    };
+
+    let (headers, header_size, count) = if wide {
+        (field(0x20, 8)?, field(0x36, 2)?, field(0x38, 2)?)
+    } else {
+        (field(0x1c, 4)?, field(0x2a, 2)?, field(0x2c, 2)?)
+    };
+    let word_bytes = width.div_ceil(8) as u64;
+    let end = base.saturating_add((depth as u64).saturating_mul(word_bytes));
+    let mut words = std::collections::BTreeMap::new();
+    for index in 0..count {
+        let header = headers
+            .checked_add(index * header_size)
+            .and_then(|header| usize::try_from(header).ok())
+            .filter(|header| *header < data.len())
+            .ok_or("truncated ELF file".to_string())?;
+        let (kind, offset, address, file_size, memory_size) = if wide {
+            (
+                field(header, 4)?,
+                field(header + 8, 8)?,
+                field(header + 24, 8)?,
+                field(header + 32, 8)?,
+                field(header + 40, 8)?,
+            )
+        } else {
+            (
+                field(header, 4)?,
+                field(header + 4, 4)?,
+                field(header + 12, 4)?,
+                field(header + 16, 4)?,
+                field(header + 20, 4)?,
+            )
+        };
+        if kind != PT_LOAD {
+            continue;
+        }
+        if file_size > memory_size {
+            return Err(format!(
+                "segment {index} is larger in the file than in memory"
+            ));
+        }
+        let bytes = usize::try_from(offset)
+            .ok()
+            .zip(usize::try_from(file_size).ok())
+            .and_then(|(offset, size)| data.get(offset..offset.checked_add(size)?))
+            .ok_or(format!("segment {index} is truncated"))?;
+        for byte_address in address.max(base)..address.saturating_add(memory_size).min(end) {
+            let byte = bytes
+                .get((byte_address - address) as usize)
+                .copied()
+                .unwrap_or(0);
+            let offset = byte_address - base;
+            let value: &mut u64 = words.entry((offset / word_bytes) as usize).or_default();
+            *value |= u64::from(byte) << (8 * (offset % word_bytes));
+        }
+    }
+    let mut contents = vec![0; depth];
+    for (address, value) in words {
+        if width < 64 && value >> width != 0 {
+            return Err(format!(
+                "value {value:#x} at address {address:#x} does not fit in {width} bits"
+            ));
+        }
+        contents[address] = value;
+    }
+
+    Ok(contents)
+}
+
+/// Assemble a word from bytes stored least significant first.
+///
+/// # Parameters
+///
+/// - `bytes`: The bytes of the word, of which there must be no more than 8.
+fn little_endian(bytes: &[u8]) -> u64 {
+    bytes
+        .iter()
+        .rev()
+        .fold(0, |value, byte| (value << 8) | u64::from(*byte))
+}
+
+/// Assemble an address from bytes stored most significant first, as in Intel HEX records.
+///
+/// # Parameters
+///
+/// - `bytes`: The bytes of the address.
+fn big_endian(bytes: &[u8]) -> usize {
+    bytes
+        .iter()
+        .fold(0, |value, byte| (value << 8) | usize::from(*byte))
+}
+
+/// Decode an Intel HEX record, returning its type, address offset and data after checking its length and checksum.
+///
+/// # Parameters
+///
+/// - `record`: The text of the record, starting with `:`.
+fn intel_hex_record(record: &str) -> Result<(u8, usize, Vec<u8>), String> {
+    let digits = record
+        .strip_prefix(':')
+        .ok_or("record does not start with \":\"".to_string())?;
+    if !digits.is_ascii() {
+        return Err("record holds invalid hexadecimal digits".to_string());
+    }
+    if !digits.len().is_multiple_of(2) || digits.len() < 10 {
+        return Err("record is truncated".to_string());
+    }
+    let bytes = (0..digits.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&digits[i..i + 2], 16))
+        .collect::<Result<Vec<u8>, _>>()
+        .map_err(|_| "record holds invalid hexadecimal digits".to_string())?;
+    let length = usize::from(bytes[0]);
+    if bytes.len() != length + 5 {
+        return Err(format!("record length {length} does not match its data"));
+    }
+    if bytes.iter().fold(0u8, |sum, byte| sum.wrapping_add(*byte)) != 0 {
+        return Err("record checksum is incorrect".to_string());
+    }
+    let kind = bytes[3];
+    if matches!(kind, 0x02 | 0x04) && length != 2 {
+        return Err(format!("record of type {kind:02x} must hold 2 bytes"));
+    }
+
+    Ok((
+        kind,
+        big_endian(&bytes[1..3]),
+        bytes[4..4 + length].to_vec(),
+    ))
+}
+
+/// A read-only memory with chip select and output enable inputs, modelled on a 27-series EPROM.
+///
+/// The inputs are named `A0` to `An`, `/CS` and `/OE`, and the outputs are named `D0` to `Dn`.  While `/CS` and `/OE`
+/// are both low the outputs present the word at the address after the access delay, and otherwise they are released.
+/// An indeterminate address, or an address beyond the depth of the memory, makes the word unknown, and unknown bits are
+/// driven unknown (X).
+///
+/// The contents are shared between copies of the ROM, so checkpointing a Simulation does not copy them.
+///
+/// # Example
+///
+/// ```
+/// # use rvfs_sim_core::element::memory::Rom;
+/// # use rvfs_sim_core::element::Element;
+/// let rom = Rom::new("U1", 8, 1024, 0, vec![0xea; 1024]).unwrap();
+///
+/// assert_eq!("/CS", rom.input_pins()[10].name());
+/// assert_eq!(8, rom.output_pins().len());
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rom {
+    /// Name of the ROM.
+    name: String,
+    /// Number of bits in each word.
+    width: usize,
+    /// Access delay from an input to the outputs.
+    delay: u64,
+    /// The stored words, indexed by address.
+    contents: Arc<[u64]>,
+}
+
+impl Rom {
+    /// Create a new Rom.
+    ///
+    /// # Parameters
+    ///
+    /// - `name`: Name of the ROM.
+    /// - `width`: Number of bits in each word, from 1 to 64.
+    /// - `depth`: Number of words, which must be at least one.
+    /// - `delay`: Access delay from an input to the outputs.
+    /// - `contents`: The stored words, indexed by address.  Words beyond the end of the contents are zero.
+    pub fn new(
+        name: &str,
+        width: usize,
+        depth: usize,
+        delay: u64,
+        mut contents: Vec<u64>,
+    ) -> Result<Self, String> {
+        validate("ROM", name, width, depth)?;
+        if contents.len() > depth {
+            return Err(format!(
+                "ROM \"{name}\": {} words of contents exceed the depth of {depth} words",
+                contents.len()
+            ));
+        }
+        if let Some(value) = contents
+            .iter()
+            .find(|value| width < 64 && *value >> width != 0)
+        {
+            return Err(format!(
+                "ROM \"{name}\": value {value:#x} does not fit in {width} bits"
+            ));
+        }
+        contents.resize(depth, 0);
+
+        Ok(Self {
+            name: name.to_string(),
+            width,
+            delay,
+            contents: contents.into(),
+        })
+    }
+
+    /// Obtain the stored words, indexed by address.
+    pub fn contents(&self) -> &[u64] {
+        &self.contents
+    }
+}
+
+impl Element for Rom {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn input_pins(&self) -> Vec<InputPin> {
+        (0..address_bits(self.contents.len()))
+            .map(|i| format!("A{i}"))
+            .chain(["/CS".to_string(), "/OE".to_string()])
+            .map(|name| InputPin::new(&name))
+            .collect()
+    }
+
+    fn output_pins(&self) -> Vec<OutputPin> {
+        (0..self.width)
+            .map(|i| {
+                OutputPin::new(
+                    &format!("D{i}"),
+                    SimDuration::from_ticks(self.delay),
+                    OutputPinState::HighImpedance,
+                )
+            })
+            .collect()
+    }
+
+    fn step(
+        &mut self,
+        inputs: &[InputPin],
+        outputs: &mut [OutputPin],
+        _delta_t: u64,
+    ) -> Result<SimResult, String> {
+        let [address @ .., cs, oe] = inputs else {
+            return Err(format!("ROM \"{}\": unexpected pins", self.name));
+        };
+        if address.len() != address_bits(self.contents.len()) || outputs.len() != self.width {
+            return Err(format!("ROM \"{}\": unexpected pins", self.name));
+        }
+
+        if cs.state() == InputPinState::Low && oe.state() == InputPinState::Low {
+            let value = word(address).and_then(|address| self.contents.get(address as usize));
+            drive_word(outputs, value.copied());
+        } else {
+            release(outputs);
+        }
+
+        Ok(SimResult::Continuing)
+    }
+
+    // The contents of a ROM never change, so it has no state to save.
+    #[cfg(feature = "serde")]
+    fn save_state(&self) -> Result<serde_json::Value, String> {
+        Ok(serde_json::Value::Null)
+    }
+
+    #[cfg(feature = "serde")]
+    fn restore_state(&mut self, _state: serde_json::Value) -> Result<(), String> {
+        Ok(())
+    }
+
+    fn box_clone(&self) -> Box<dyn Element> {
+        Box::new(self.clone())
+    }
+}
+
+/// Format the contents of a memory as an image file in the [Hex](ImageFormat::Hex) format.
+///
+/// Unknown words are omitted, with an `@address` token moving past each run of them, so that the image can be loaded
+/// again with [parse_image].
+///
+/// # Parameters
+///
+/// - `contents`: The stored words, if known, indexed by address.
+/// - `width`: Number of bits in each word.
+///
+/// # Example
+///
+/// ```
+/// # use rvfs_sim_core::element::memory::format_image;
+/// let image = format_image(&[Some(0x12), None, Some(0xab)], 8);
+///
+/// assert_eq!("12\n@2\nab\n", image);
+/// ```
+pub fn format_image(contents: &[Option<u64>], width: usize) -> String {
+    let digits = width.div_ceil(4);
+    let mut image = String::new();
+    let mut skipped = false;
+    for (address, value) in contents.iter().enumerate() {
+        match value {
+            Some(value) => {
+                if skipped {
+                    image.push_str(&format!("@{address:x}\n"));
+                    skipped = false;
+                }
+                image.push_str(&format!("{value:0digits$x}\n"));
+            }
+            None => skipped = true,
+        }
+    }
+
+    image
+}
+
+/// An asynchronous static RAM with chip select, write enable and output enable inputs, modelled on a 6264.
+///
+/// The inputs are named `A0` to `An`, `D0` to `Dn`, `/CS`, `/WE` and `/OE`, and the outputs are also named `D0` to
+/// `Dn`.  Each data bit has both an InputPin and an OutputPin, which should both be connected to the same Wire.  While
+/// `/CS` and `/OE` are low and `/WE` is high the outputs present the word at the address after the access delay, and
+/// otherwise they are released.
+///
+/// A write lasts while `/CS` and `/WE` are both low, and stores the data present at its end to the address present at
+/// its end.  The write must last for at least the write pulse width, and the address and data must have been stable
+/// for at least the setup time before its end, or the word written becomes unknown.  An indeterminate `/CS` or `/WE`
+/// during a write also makes the word written unknown, and a write to an indeterminate address makes every word
+/// unknown.
+///
+/// Every word is unknown until it is first written or loaded, and unknown bits are driven unknown (X).  If a dump path
+/// is set, the contents are written to it in the [Hex](ImageFormat::Hex) format when the Simulation finishes.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sram {
+    /// Name of the RAM.
+    name: String,
+    /// Number of bits in each word.
+    width: usize,
+    /// Access delay from an input to the outputs.
+    delay: u64,
+    /// Minimum duration of a write.
+    write_pulse: u64,
+    /// Minimum time the address and data must be stable before the end of a write.
+    setup: u64,
+    /// The stored words, if known, indexed by address.
+    contents: Vec<Option<u64>>,
+    /// Path to write the contents to when the Simulation finishes, if any.
+    dump: Option<PathBuf>,
+    /// The address and data most recently sampled.
+    sampled: (Option<u64>, Option<u64>),
+    /// Time for which the sampled address and data had been stable before the present step.
+    stable: u64,
+    /// The duration of the write in progress, if any.
+    writing: Option<u64>,
+    /// Whether the write in progress has seen an indeterminate `/CS` or `/WE`.
+    corrupt: bool,
+}
+
+impl Sram {
+    /// Create a new Sram.
+    ///
+    /// # Parameters
+    ///
+    /// - `name`: Name of the RAM.
+    /// - `width`: Number of bits in each word, from 1 to 64.
+    /// - `depth`: Number of words, which must be at least one.
+    /// - `delay`: Access delay from an input to the outputs.
+    /// - `write_pulse`: Minimum duration of a write.
+    /// - `setup`: Minimum time the address and data must be stable before the end of a write.
+    pub fn new(
+        name: &str,
+        width: usize,
+        depth: usize,
+        delay: u64,
+        write_pulse: u64,
+        setup: u64,
+    ) -> Result<Self, String> {
+        validate("SRAM", name, width, depth)?;
+
+        Ok(Self {
+            name: name.to_string(),
+            width,
+            delay,
+            write_pulse,
+            setup,
+            contents: vec![None; depth],
+            dump: None,
+            sampled: (None, None),
+            stable: 0,
+            writing: None,
+            corrupt: false,
+        })
+    }
+
+    /// Load words into the RAM, starting at address zero.
+    ///
+    /// # Parameters
+    ///
+    /// - `contents`: The words to load, which must fit within the depth and width of the RAM.
+    pub fn load(&mut self, contents: &[u64]) -> Result<(), String> {
+        if contents.len() > self.contents.len() {
+            return Err(format!(
+                "SRAM \"{}\": {} words of contents exceed the depth of {} words",
+                self.name,
+                contents.len(),
+                self.contents.len()
+            ));
+        }
+        if let Some(value) = contents
+            .iter()
+            .find(|value| self.width < 64 && *value >> self.width != 0)
+        {
+            return Err(format!(
+                "SRAM \"{}\": value {value:#x} does not fit in {} bits",
+                self.name, self.width
+            ));
+        }
+        for (slot, value) in self.contents.iter_mut().zip(contents) {
+            *slot = Some(*value);
+        }
+
+        Ok(())
+    }
+
+    /// Set the path to write the contents to when the Simulation finishes.
+    ///
+    /// # Parameters
+    ///
+    /// - `path`: Path of the image file to write.
+    pub fn set_dump(&mut self, path: &Path) {
+        self.dump = Some(path.to_path_buf());
+    }
+
+    /// Obtain the stored words, if known, indexed by address.
+    pub fn contents(&self) -> &[Option<u64>] {
+        &self.contents
+    }
+
+    /// Complete a write, storing the word if the write met its timing requirements.
+    ///
+    /// # Parameters
+    ///
+    /// - `duration`: Duration of the write.
+    fn store(&mut self, duration: u64) {
+        let (address, data) = self.sampled;
+        let valid = !self.corrupt && duration >= self.write_pulse && self.stable >= self.setup;
+        match address {
+            Some(address) => {
+                if let Some(slot) = self.contents.get_mut(address as usize) {
+                    *slot = data.filter(|_| valid);
+                }
+            }
+            None => self.contents.fill(None),
+        }
+    }
+}
+
+impl Element for Sram {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn input_pins(&self) -> Vec<InputPin> {
+        (0..address_bits(self.contents.len()))
+            .map(|i| format!("A{i}"))
+            .chain((0..self.width).map(|i| format!("D{i}")))
+            .chain(["/CS".to_string(), "/WE".to_string(), "/OE".to_string()])
+            .map(|name| InputPin::new(&name))
+            .collect()
+    }
+
+    fn output_pins(&self) -> Vec<OutputPin> {
+        (0..self.width)
+            .map(|i| {
+                OutputPin::new(
+                    &format!("D{i}"),
+                    SimDuration::from_ticks(self.delay),
+                    OutputPinState::HighImpedance,
+                )
+            })
+            .collect()
+    }
+
+    fn step(
+        &mut self,
+        inputs: &[InputPin],
+        outputs: &mut [OutputPin],
+        delta_t: u64,
+    ) -> Result<SimResult, String> {
+        let bits = address_bits(self.contents.len());
+        let [pins @ .., cs, we, oe] = inputs else {
+            return Err(format!("SRAM \"{}\": unexpected pins", self.name));
+        };
+        if pins.len() != bits + self.width || outputs.len() != self.width {
+            return Err(format!("SRAM \"{}\": unexpected pins", self.name));
+        }
+        let (address, data) = pins.split_at(bits);
+
+        let sampled = (word(address), word(data));
+        if sampled != self.sampled {
+            self.sampled = sampled;
+            self.stable = 0;
+        }
+
+        let write = match (cs.state(), we.state()) {
+            (InputPinState::High, _) | (_, InputPinState::High) => Some(false),
+            (InputPinState::Low, InputPinState::Low) => Some(true),
+            _ => None,
+        };
+        match (self.writing, write) {
+            (Some(duration), Some(false)) => {
+                self.store(duration);
+                self.writing = None;
+            }
+            (writing, Some(true) | None) => {
+                if writing.is_none() {
+                    self.corrupt = false;
+                }
+                self.corrupt |= write.is_none();
+                self.writing = Some(writing.unwrap_or(0) + delta_t);
+            }
+            (None, Some(false)) => (),
+        }
+        self.stable += delta_t;
+
+        let read = cs.state() == InputPinState::Low
+            && oe.state() == InputPinState::Low
+            && we.state() == InputPinState::High;
+        if read {
+            let value = sampled
+                .0
+                .and_then(|address| self.contents.get(address as usize))
+                .copied()
+                .flatten();
+            drive_word(outputs, value);
+        } else {
+            release(outputs);
+        }
+
+        Ok(SimResult::Continuing)
+    }
+
+    fn finish(&mut self) -> Result<(), String> {
+        match &self.dump {
+            Some(path) => {
+                fs::write(path, format_image(&self.contents, self.width)).map_err(|error| {
+                    format!(
+                        "SRAM \"{}\": failed to write dump \"{}\": {error}",
+                        self.name,
+                        path.display()
+                    )
+                })
+            }
+            None => Ok(()),
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    fn save_state(&self) -> Result<serde_json::Value, String> {
+        super::save_state((
+            &self.contents,
+            self.sampled,
+            self.stable,
+            self.writing,
+            self.corrupt,
+        ))
+    }
+
+    #[cfg(feature = "serde")]
+    fn restore_state(&mut self, state: serde_json::Value) -> Result<(), String> {
+        let (contents, sampled, stable, writing, corrupt): (Vec<_>, _, _, _, _) =
+            super::restore_state(&self.name, state)?;
+        if contents.len() != self.contents.len() {
+            return Err(format!(
+                "Element \"{}\": saved state has {} words, expected {}",
+                self.name,
+                contents.len(),
+                self.contents.len()
+            ));
+        }
+        (
+            self.contents,
+            self.sampled,
+            self.stable,
+            self.writing,
+            self.corrupt,
+        ) = (contents, sampled, stable, writing, corrupt);
+        Ok(())
+    }
+
+    fn box_clone(&self) -> Box<dyn Element> {
+        Box::new(self.clone())
+    }
+}
+
+/// Check the dimensions of a memory.
+///
+/// # Parameters
+///
+/// - `kind`: Kind of memory, for error messages.
+/// - `name`: Name of the memory.
+/// - `width`: Number of bits in each word.
+/// - `depth`: Number of words.
+fn validate(kind: &str, name: &str, width: usize, depth: usize) -> Result<(), String> {
+    if !(1..=64).contains(&width) {
+        return Err(format!(
+            "{kind} \"{name}\": width must be from 1 to 64 bits"
+        ));
+    }
+    if depth == 0 {
+        return Err(format!(
+            "{kind} \"{name}\": depth must be at least one word"
+        ));
+    }
+
+    Ok(())
+}
+
+/// Drive a word onto a set of outputs, least significant bit first.
+///
+/// # Parameters
+///
+/// - `outputs`: The OutputPins.
+/// - `value`: The word, if known.  An unknown word releases every output.
+fn drive_word(outputs: &mut [OutputPin], value: Option<u64>) {
+    for (i, output) in outputs.iter_mut().enumerate() {
+        output.drive(level(value.map(|value| (value >> i) & 1 != 0)));
+    }
+}
+
+/// Release a set of outputs.
+///
+/// # Parameters
+///
+/// - `outputs`: The OutputPins.
+fn release(outputs: &mut [OutputPin]) {
+    for output in outputs {
+        output.drive(OutputPinState::HighImpedance);
+    }
+}
+
+/// Load the contents of a memory from the image file named by its parameters, if any.
+///
+/// # Parameters
+///
+/// - `kind`: Kind of memory, for error messages.
+/// - `name`: Name of the memory.
+/// - `parameters`: Parameters of the memory.
+/// - `width`: Number of bits in each word.
+/// - `depth`: Number of words.
+fn load_parameters(
+    kind: &str,
+    name: &str,
+    parameters: &Parameters,
+    width: usize,
+    depth: usize,
+) -> Result<Vec<u64>, String> {
+    validate(kind, name, width, depth)?;
+    let image: String = parameters.get_or("image", String::new())?;
+    if image.is_empty() {
+        return Ok(Vec::new());
+    }
+    let format = parameters.get_or("format", ImageFormat::Binary)?;
+    let contents = match format {
+        ImageFormat::Elf => load_elf(
+            Path::new(&image),
+            width,
+            depth,
+            parameters.get_or("base", 0)?,
+        ),
+        _ => load_image(Path::new(&image), format, width, depth),
+    }
+    .map_err(|message| format!("{kind} \"{name}\": {message}"))?;
+
+    Ok(contents)
+}
+
+/// Register every kind of memory, taking the `width` (default 8), `depth` (default 256) and `delay` (default 0)
+/// parameters, and the `image` parameter giving the path of an image file to load the contents from, with the
+/// `format` parameter (default `bin`) naming the [format](ImageFormat) of that file.  An ELF file is loaded as if the
+/// memory starts at the byte address given by the `base` parameter (default 0).  An SRAM also takes the
+/// `write_pulse` (default 0) and `setup` (default 0) parameters, and the `dump` parameter giving the path to write its
+/// contents to when the Simulation finishes.
+///
+/// # Parameters
+///
+/// - `registry`: The Registry to add the memories to.
+pub fn register(registry: &mut Registry) {
+    registry.register("rom", |name, parameters: &Parameters| {
+        let width = parameters.get_or("width", DEFAULT_WIDTH)?;
+        let depth = parameters.get_or("depth", DEFAULT_DEPTH)?;
+        let contents = load_parameters("ROM", name, parameters, width, depth)?;
+        Ok(Box::new(Rom::new(
+            name,
+            width,
+            depth,
+            parameters.get_or("delay", 0)?,
+            contents,
+        )?))
+    });
+    registry.register("sram", |name, parameters: &Parameters| {
+        let width = parameters.get_or("width", DEFAULT_WIDTH)?;
+        let depth = parameters.get_or("depth", DEFAULT_DEPTH)?;
+        let contents = load_parameters("SRAM", name, parameters, width, depth)?;
+        let mut sram = Sram::new(
+            name,
+            width,
+            depth,
+            parameters.get_or("delay", 0)?,
+            parameters.get_or("write_pulse", 0)?,
+            parameters.get_or("setup", 0)?,
+        )?;
+        sram.load(&contents)?;
+        let dump: String = parameters.get_or("dump", String::new())?;
+        if !dump.is_empty() {
+            sram.set_dump(Path::new(&dump));
+        }
+        Ok(Box::new(sram))
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::element::testing::{steady, step};
+    use crate::sim::Simulation;
+
+    /// Build a little-endian ELF file holding loadable segments, each given by its address, the bytes held in the file
+    /// and its size in memory.
+    fn elf(wide: bool, segments: &[(u64, &[u8], u64)]) -> Vec<u8> {
+        let (header, entry) = if wide { (64, 56) } else { (52, 32) };
+        let mut data = vec![0; header + entry * segments.len()];
+        data[0..6].copy_from_slice(&[0x7f, b'E', b'L', b'F', 1 + u8::from(wide), 1]);
+        let put = |data: &mut Vec<u8>, offset: usize, size: usize, value: u64| {
+            data[offset..offset + size].copy_from_slice(&value.to_le_bytes()[..size]);
+        };
+        let count = segments.len() as u64;
+        if wide {
+            put(&mut data, 0x20, 8, 64);
+            put(&mut data, 0x36, 2, 56);
+            put(&mut data, 0x38, 2, count);
+        } else {
+            put(&mut data, 0x1c, 4, 52);
+            put(&mut data, 0x2a, 2, 32);
+            put(&mut data, 0x2c, 2, count);
+        }
+        for (i, (address, bytes, size)) in segments.iter().enumerate() {
+            let at = header + entry * i;
+            let (offset, length) = (data.len() as u64, bytes.len() as u64);
+            data.extend_from_slice(bytes);
+            let fields = if wide {
+                [
+                    (0, 4, 1),
+                    (8, 8, offset),
+                    (24, 8, *address),
+                    (32, 8, length),
+                    (40, 8, *size),
+                ]
+            } else {
+                [
+                    (0, 4, 1),
+                    (4, 4, offset),
+                    (12, 4, *address),
+                    (16, 4, length),
+                    (20, 4, *size),
+                ]
+            };
+            for (field, width, value) in fields {
+                put(&mut data, at + field, width, value);
+            }
+        }
+        data
+    }
+
+    const L: OutputPinState = OutputPinState::Low;
+    const H: OutputPinState = OutputPinState::High;
+    const Z: OutputPinState = OutputPinState::HighImpedance;
+    const X: OutputPinState = OutputPinState::Unknown;
+
+    #[test]
+    fn image_binary() {
+        // GIVEN a binary image of 12 bit words
+        let data = [0x34, 0x02, 0xff, 0x0f];
+        // WHEN it is parsed
+        let contents = parse_image(&data, ImageFormat::Binary, 12, 4).unwrap();
+        // THEN the words are stored little-endian
+        assert_eq!(vec![0x234, 0xfff, 0, 0], contents);
+        // AND THEN partial words, words which do not fit, too many words, and invalid widths are rejected
+        assert!(parse_image(&data[0..3], ImageFormat::Binary, 12, 4).is_err());
+        assert!(parse_image(&[0x00, 0x10], ImageFormat::Binary, 12, 4).is_err());
+        assert!(parse_image(&data, ImageFormat::Binary, 8, 2).is_err());
+        assert_eq!(
+            Err("word width 0 is not from 1 to 64 bits".to_string()),
+            parse_image(&data, ImageFormat::Binary, 0, 4)
+        );
+        assert!(parse_image(&data, ImageFormat::IntelHex, 65, 4).is_err());
+    }
+    #[test]
+    fn image_hex() {
+        // GIVEN a hexadecimal text image with comments and an address
+        let text = "// boot vector\n1a 2B # first two\n@3\n  ff_ff\n";
+        // WHEN it is parsed
+        let contents = parse_image(text.as_bytes(), ImageFormat::Hex, 16, 4).unwrap();
+        // THEN the words are stored at their addresses
+        assert_eq!(vec![0x1a, 0x2b, 0, 0xffff], contents);
+        // AND THEN invalid numbers and addresses beyond the depth are rejected
+        assert!(parse_image(b"xyz", ImageFormat::Hex, 16, 4).is_err());
+        assert!(parse_image(b"@4 00", ImageFormat::Hex, 16, 4).is_err());
+    }
+    #[test]
+    fn image_intel_hex() {
+        // GIVEN an Intel HEX image of 16 bit words, with an extended linear address record
+        let text = ":0400000001020304F2\n:020000040000FA\n:02000600AABB93\n:00000001FF\n";
+        // WHEN it is parsed
+        let contents = parse_image(text.as_bytes(), ImageFormat::IntelHex, 16, 4).unwrap();
+        // THEN the bytes are assembled into words
+        assert_eq!(vec![0x0201, 0x0403, 0, 0xbbaa], contents);
+        // AND THEN records with a bad checksum are rejected
+        let error = parse_image(b":0400000001020304F3", ImageFormat::IntelHex, 16, 4).unwrap_err();
+        assert_eq!("line 1: record checksum is incorrect", error);
+    }
+    #[test]
+    fn image_elf() {
+        // GIVEN 32 and 64 bit ELF files with code at 0x0000, and initial data and zeroed data at 0x1000
+        let segments: [(u64, &[u8], u64); 2] = [
+            (0x0000, &[0x93, 0x00, 0xa0, 0x00, 0x13, 0x01], 6),
+            (0x1000, &[0x34, 0x12], 8),
+        ];
+        let narrow = elf(false, &segments);
+        let wide = elf(true, &segments);
+        // WHEN they are parsed for memories at each address
+        let rom = parse_image(&narrow, ImageFormat::Elf, 32, 4).unwrap();
+        let ram = parse_elf(&narrow, 32, 4, 0x1000).unwrap();
+        // THEN each memory holds the part of the segments within it
+        assert_eq!(vec![0x00a0_0093, 0x0113, 0, 0], rom);
+        assert_eq!(vec![0x1234, 0, 0, 0], ram);
+        assert_eq!(Ok(rom), parse_elf(&wide, 32, 4, 0));
+        // AND THEN files which are not ELF or are truncated, and bytes which do not fit in a word, are rejected
+        assert_eq!(
+            Err("not an ELF file".to_string()),
+            parse_elf(b"MZ", 32, 4, 0)
+        );
+        assert_eq!(
+            Err("truncated ELF file".to_string()),
+            parse_elf(&narrow[..60], 32, 4, 0)
+        );
+        assert_eq!(
+            Err("segment 1 is truncated".to_string()),
+            parse_elf(&narrow[..narrow.len() - 1], 32, 4, 0)
+        );
+        assert!(parse_elf(&narrow, 4, 4, 0).is_err());
+    }
+    #[test]
+    fn rom_read() {
+        // GIVEN a ROM of four 2 bit words
+        let mut rom = Rom::new("U1", 2, 4, 0, vec![0b01, 0b10, 0b11]).unwrap();
+        let mut outputs = rom.output_pins();
+        // WHEN it is selected and enabled
+        // THEN it presents the word at the address, and words beyond the contents are zero
+        assert_eq!(
+            vec![H, L],
+            step(&mut rom, &mut outputs, &steady([0.0, 0.0, 0.0, 0.0]))
+        );
+        assert_eq!(
+            vec![H, H],
+            step(&mut rom, &mut outputs, &steady([0.0, 1.0, 0.0, 0.0]))
+        );
+        assert_eq!(
+            vec![L, L],
+            step(&mut rom, &mut outputs, &steady([1.0, 1.0, 0.0, 0.0]))
+        );
+        // WHEN the address is indeterminate
+        // THEN the outputs are unknown
+        assert_eq!(
+            vec![X, X],
+            step(&mut rom, &mut outputs, &steady([0.5, 1.0, 0.0, 0.0]))
+        );
+    }
+    #[test]
+    fn rom_select_and_enable() {
+        // GIVEN a ROM of two 1 bit words
+        let mut rom = Rom::new("U1", 1, 2, 0, vec![1, 1]).unwrap();
+        let mut outputs = rom.output_pins();
+        // WHEN it is deselected or its outputs are disabled
+        // THEN the outputs are released
+        assert_eq!(
+            vec![Z],
+            step(&mut rom, &mut outputs, &steady([0.0, 1.0, 0.0]))
+        );
+        assert_eq!(
+            vec![Z],
+            step(&mut rom, &mut outputs, &steady([0.0, 0.0, 1.0]))
+        );
+        assert_eq!(
+            vec![H],
+            step(&mut rom, &mut outputs, &steady([0.0, 0.0, 0.0]))
+        );
+    }
+    #[test]
+    fn rom_validation() {
+        // GIVEN ROM definitions with bad dimensions or contents
+        // WHEN they are created
+        // THEN creation fails
+        assert!(Rom::new("U1", 0, 4, 0, Vec::new()).is_err());
+        assert!(Rom::new("U1", 65, 4, 0, Vec::new()).is_err());
+        assert!(Rom::new("U1", 8, 0, 0, Vec::new()).is_err());
+        assert!(Rom::new("U1", 8, 1, 0, vec![0, 0]).is_err());
+        assert!(Rom::new("U1", 8, 1, 0, vec![0x100]).is_err());
+        assert!(Rom::new("U1", 64, 1, 0, vec![u64::MAX]).is_ok());
+    }
+    #[test]
+    fn rom_registered_with_image() {
+        // GIVEN an image file on disk
+        let path = std::env::temp_dir().join(format!("rvfs-sim-rom-{}.hex", std::process::id()));
+        fs::write(&path, "de ad be ef").unwrap();
+        // WHEN ROMs are created from the standard registry, loading the image, then an ELF image replacing it
+        let parameters = Parameters::new()
+            .with("depth", "8")
+            .with("delay", "100")
+            .with("image", path.to_str().unwrap())
+            .with("format", "hex");
+        let rom = Registry::standard().create("rom", "U1", &parameters);
+        fs::write(&path, elf(false, &[(0x100, &[0xab], 1)])).unwrap();
+        let elf_parameters = parameters
+            .clone()
+            .with("format", "elf")
+            .with("base", "256")
+            .with("delay", "0");
+        let elf_rom = Registry::standard().create("rom", "U3", &elf_parameters);
+        fs::remove_file(&path).unwrap();
+        let rom = rom.unwrap();
+        // THEN it has the expected pins and delay
+        assert_eq!(5, rom.input_pins().len());
+        assert_eq!(100, rom.output_pins()[7].delay().ticks());
+        // AND THEN the ELF image is loaded from the ROM's base address
+        let mut elf_rom = elf_rom.unwrap();
+        let mut outputs = elf_rom.output_pins();
+        assert_eq!(
+            vec![H, H, L, H, L, H, L, H],
+            step(elf_rom.as_mut(), &mut outputs, &steady([0.0; 5]))
+        );
+        // AND THEN a missing image is reported
+        let error = Registry::standard()
+            .create(
+                "rom",
+                "U2",
+                &parameters.with("image", "/nonexistent/rom.bin"),
+            )
+            .unwrap_err();
+        assert!(error.starts_with("ROM \"U2\": Failed to read image"));
+    }
+    #[test]
+    fn image_format_round_trip() {
+        // GIVEN contents with unknown words
+        let contents = [Some(0x1), None, None, Some(0xabc), Some(0)];
+        // WHEN they are formatted as an image and parsed again
+        let image = format_image(&contents, 12);
+        let parsed = parse_image(image.as_bytes(), ImageFormat::Hex, 12, 5).unwrap();
+        // THEN the known words are preserved, padded to the width
+        assert_eq!("001\n@3\nabc\n000\n", image);
+        assert_eq!(vec![0x1, 0, 0, 0xabc, 0], parsed);
+    }
+    #[test]
+    fn sram_write_and_read() {
+        // GIVEN a RAM of two 2 bit words
+        let mut sram = Sram::new("U1", 2, 2, 0, 0, 0).unwrap();
+        let mut outputs = sram.output_pins();
+        // WHEN a word is written to each address
+        // THEN the outputs are released during the writes
+        for (address, data) in [(1.0, [1.0, 0.0]), (0.0, [0.0, 1.0])] {
+            let [d0, d1] = data;
+            assert_eq!(
+                vec![Z, Z],
+                step(
+                    &mut sram,
+                    &mut outputs,
+                    &steady([address, d0, d1, 0.0, 0.0, 1.0])
+                )
+            );
+            step(
+                &mut sram,
+                &mut outputs,
+                &steady([address, d0, d1, 0.0, 1.0, 1.0]),
+            );
+        }
+        // THEN the words are stored and can be read back
+        assert_eq!(vec![Some(0b10), Some(0b01)], sram.contents());
+        assert_eq!(
+            vec![L, H],
+            step(
+                &mut sram,
+                &mut outputs,
+                &steady([0.0, 0.5, 0.5, 0.0, 1.0, 0.0])
+            )
+        );
+        assert_eq!(
+            vec![H, L],
+            step(
+                &mut sram,
+                &mut outputs,
+                &steady([1.0, 0.5, 0.5, 0.0, 1.0, 0.0])
+            )
+        );
+        // WHEN it is deselected
+        // THEN the outputs are released
+        assert_eq!(
+            vec![Z, Z],
+            step(
+                &mut sram,
+                &mut outputs,
+                &steady([1.0, 0.5, 0.5, 1.0, 1.0, 0.0])
+            )
+        );
+    }
+    #[test]
+    fn sram_write_timing() {
+        // GIVEN a RAM requiring a write pulse of 20 and a setup time of 20, at 10 per step
+        let mut sram = Sram::new("U1", 1, 2, 0, 20, 20).unwrap();
+        let mut outputs = sram.output_pins();
+        // WHEN a write lasts for one step
+        step(&mut sram, &mut outputs, &steady([0.0, 1.0, 0.0, 0.0, 1.0]));
+        step(&mut sram, &mut outputs, &steady([0.0, 1.0, 0.0, 1.0, 1.0]));
+        // THEN the word written is unknown
+        assert_eq!(None, sram.contents()[0]);
+        // WHEN a write lasts for two steps, but the data changes on the second
+        step(&mut sram, &mut outputs, &steady([0.0, 0.0, 0.0, 0.0, 1.0]));
+        step(&mut sram, &mut outputs, &steady([0.0, 1.0, 0.0, 0.0, 1.0]));
+        step(&mut sram, &mut outputs, &steady([0.0, 1.0, 0.0, 1.0, 1.0]));
+        // THEN the word written is still unknown
+        assert_eq!(None, sram.contents()[0]);
+        // WHEN a write lasts for two steps with stable data
+        step(&mut sram, &mut outputs, &steady([0.0, 1.0, 0.0, 0.0, 1.0]));
+        step(&mut sram, &mut outputs, &steady([0.0, 1.0, 0.0, 0.0, 1.0]));
+        step(&mut sram, &mut outputs, &steady([0.0, 1.0, 0.0, 1.0, 1.0]));
+        // THEN the word is stored
+        assert_eq!(Some(1), sram.contents()[0]);
+    }
+    #[test]
+    fn sram_write_indeterminate() {
+        // GIVEN a loaded RAM of two 1 bit words
+        let mut sram = Sram::new("U1", 1, 2, 0, 0, 0).unwrap();
+        sram.load(&[1, 0]).unwrap();
+        let mut outputs = sram.output_pins();
+        // WHEN a write sees an indeterminate /WE
+        step(&mut sram, &mut outputs, &steady([1.0, 1.0, 0.0, 0.5, 1.0]));
+        step(&mut sram, &mut outputs, &steady([1.0, 1.0, 0.0, 1.0, 1.0]));
+        // THEN the word written is unknown
+        assert_eq!(vec![Some(1), None], sram.contents());
+        // WHEN a write ends at an indeterminate address
+        step(&mut sram, &mut outputs, &steady([0.5, 1.0, 0.0, 0.0, 1.0]));
+        step(&mut sram, &mut outputs, &steady([0.5, 1.0, 0.0, 1.0, 1.0]));
+        // THEN every word is unknown
+        assert_eq!(vec![None, None], sram.contents());
+    }
+    #[test]
+    fn sram_load_validation() {
+        // GIVEN a RAM of two 4 bit words
+        let mut sram = Sram::new("U1", 4, 2, 0, 0, 0).unwrap();
+        // WHEN contents which do not fit are loaded
+        // THEN loading fails
+        assert!(sram.load(&[0, 0, 0]).is_err());
+        assert!(sram.load(&[0x10]).is_err());
+        assert!(sram.load(&[0xf]).is_ok());
+        assert!(Sram::new("U1", 4, 0, 0, 0, 0).is_err());
+    }
+    #[test]
+    fn sram_registered() {
+        // GIVEN the standard registry
+        let registry = Registry::standard();
+        // WHEN an SRAM is created with parameters
+        let parameters = Parameters::new()
+            .with("width", "4")
+            .with("depth", "4")
+            .with("delay", "70");
+        let sram = registry.create("sram", "U1", &parameters).unwrap();
+        // THEN it has the expected pins and delay
+        assert_eq!(9, sram.input_pins().len());
+        assert_eq!("/WE", sram.input_pins()[7].name());
+        assert_eq!(70, sram.output_pins()[3].delay().ticks());
+    }
+    #[test]
+    fn sram_dump() {
+        // GIVEN a loaded SRAM with a dump path, in a simulation
+        let path = std::env::temp_dir().join(format!("rvfs-sim-sram-{}.hex", std::process::id()));
+        let mut sram = Sram::new("U1", 8, 4, 0, 0, 0).unwrap();
+        sram.load(&[0x12, 0x34]).unwrap();
+        sram.set_dump(&path);
+        let mut sim = Simulation::new(SimDuration::from_ticks(10));
+        sim.add_element(Box::new(sram)).unwrap();
+        // WHEN the simulation finishes
+        sim.finish_elements().unwrap();
+        // THEN the known contents are dumped
+        let image = fs::read_to_string(&path);
+        fs::remove_file(&path).unwrap();
+        assert_eq!("12\n34\n", image.unwrap());
+    }
+}
@@ -0,0 +1,543 @@
+//! Multiplexers and demultiplexers.
+//!
+//! Both route data between a number of channels according to a binary select bus, in which `S0` is the least
+//! significant bit.  Each channel is `width` bits wide, and its pins are named with a `_` and the bit number when it is
+//! wider than one bit, so the second bit of channel 3 of a multiplexer is `I3_1`.
+//!
+//! Real parts are usually slower from their select inputs than from their data inputs, so the select bus may be given
+//! a longer delay than the data.  A change of select is held back by the difference between the two delays before it
+//! takes effect, and then propagates through the outputs with the data delay.
+
+use crate::element::{address_bits, bit, level, Element, Parameters, Registry};
+use crate::ipin::{InputPin, InputPinState};
+use crate::opin::{OutputPin, OutputPinState};
+use crate::prelude::*;
+use crate::sim::SimResult;
+use crate::time::SimDuration;
+
+/// Default number of channels of an element instantiated from a configuration file.
+const DEFAULT_CHANNELS: usize = 2;
+/// Default width of each channel of an element instantiated from a configuration file.
+const DEFAULT_WIDTH: usize = 1;
+
+/// Obtain the name of one bit of a channel.
+///
+/// # Parameters
+///
+/// - `prefix`: Prefix of the channel pin names.
+/// - `channel`: Channel number, or `None` for an element with a single channel on that side.
+/// - `bit`: Bit number within the channel.
+/// - `width`: Width of the channel.
+fn pin_name(prefix: &str, channel: Option<usize>, bit: usize, width: usize) -> String {
+    match (channel, width) {
+        (Some(channel), 1) => format!("{prefix}{channel}"),
+        (Some(channel), _) => format!("{prefix}{channel}_{bit}"),
+        (None, 1) => prefix.to_string(),
+        (None, _) => format!("{prefix}{bit}"),
+    }
+}
+
+/// Validate the shape of a multiplexer or demultiplexer.
+///
+/// # Parameters
+///
+/// - `kind`: Kind of the element, for error messages.
+/// - `name`: Name of the element.
+/// - `channels`: Number of channels, which must be at least two.
+/// - `width`: Width of each channel, which must be at least one bit.
+/// - `delay`: Propagation delay from a data input to the outputs.
+/// - `select_delay`: Propagation delay from a select input to the outputs, which must be at least `delay`.
+fn validate(
+    kind: &str,
+    name: &str,
+    channels: usize,
+    width: usize,
+    delay: u64,
+    select_delay: u64,
+) -> Result<(), String> {
+    if channels < 2 {
+        return Err(format!(
+            "{kind} \"{name}\": there must be at least two channels"
+        ));
+    }
+    if width == 0 {
+        return Err(format!("{kind} \"{name}\": width must be at least one bit"));
+    }
+    if select_delay < delay {
+        return Err(format!(
+            "{kind} \"{name}\": select delay must be at least the data delay"
+        ));
+    }
+    Ok(())
+}
+
+/// A select bus whose changes take effect after a delay.
+#[derive(Debug, Clone, PartialEq)]
+struct Select {
+    /// Extra delay before a change of select takes effect.
+    delay: u64,
+    /// The select value in effect, if known.
+    current: Option<usize>,
+    /// The select value about to take effect, if known, and the time remaining until it does.
+    pending: Option<(Option<usize>, u64)>,
+}
+
+impl Select {
+    /// Create a new Select, with no select value in effect.
+    ///
+    /// # Parameters
+    ///
+    /// - `delay`: Extra delay before a change of select takes effect.
+    fn new(delay: u64) -> Self {
+        Self {
+            delay,
+            current: None,
+            pending: None,
+        }
+    }
+
+    /// Sample the select bus and obtain the select value in effect.
+    ///
+    /// # Parameters
+    ///
+    /// - `pins`: The select InputPins, least significant first.
+    /// - `delta_t`: The simulation time elapsed since the last step.
+    fn update(&mut self, pins: &[InputPin], delta_t: u64) -> Option<usize> {
+        let sampled = pins.iter().enumerate().try_fold(0, |select, (i, pin)| {
+            Some(select | (usize::from(bit(pin)?) << i))
+        });
+        let target = self.pending.map_or(self.current, |(select, _)| select);
+        if sampled != target {
+            self.pending = Some((sampled, self.delay));
+        }
+
+        if let Some((select, remaining)) = self.pending {
+            if delta_t >= remaining {
+                self.current = select;
+                self.pending = None;
+            } else {
+                self.pending = Some((select, remaining - delta_t));
+            }
+        }
+        self.current
+    }
+}
+
+/// An N:1 multiplexer with an active-low enable, modelled on the 74151 and 74153.
+///
+/// The inputs are named `S0` to `Sn`, `/EN`, and then the bits of each channel `I0` to `In`, and the outputs are the
+/// bits of `Y`.  While `/EN` is low the outputs follow the selected channel, and otherwise they are low, as is the
+/// case for a select value beyond the last channel.  `/EN` must be held low for the multiplexer to pass data.
+///
+/// An indeterminate select or enable makes every output unknown, and an indeterminate bit of the selected channel
+/// makes its output unknown (X).
+///
+/// # Example
+///
+/// ```
+/// # use rvfs_sim_core::element::mux::Multiplexer;
+/// # use rvfs_sim_core::element::Element;
+/// let mux = Multiplexer::new("U1", 4, 2, 10, 15).unwrap();
+///
+/// assert_eq!(2 + 1 + 4 * 2, mux.input_pins().len());
+/// assert_eq!("I3_1", mux.input_pins()[10].name());
+/// assert_eq!("Y1", mux.output_pins()[1].name());
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Multiplexer {
+    /// Name of the multiplexer.
+    name: String,
+    /// Number of input channels.
+    channels: usize,
+    /// Width of each channel.
+    width: usize,
+    /// Propagation delay from a data input to the outputs.
+    delay: u64,
+    /// The select bus.
+    select: Select,
+}
+
+impl Multiplexer {
+    /// Create a new Multiplexer.
+    ///
+    /// # Parameters
+    ///
+    /// - `name`: Name of the multiplexer.
+    /// - `channels`: Number of input channels, which must be at least two.
+    /// - `width`: Width of each channel, which must be at least one bit.
+    /// - `delay`: Propagation delay from a data input to the outputs.
+    /// - `select_delay`: Propagation delay from a select input to the outputs, which must be at least `delay`.
+    pub fn new(
+        name: &str,
+        channels: usize,
+        width: usize,
+        delay: u64,
+        select_delay: u64,
+    ) -> Result<Self, String> {
+        validate("Multiplexer", name, channels, width, delay, select_delay)?;
+
+        Ok(Self {
+            name: name.to_string(),
+            channels,
+            width,
+            delay,
+            select: Select::new(select_delay - delay),
+        })
+    }
+}
+
+impl Element for Multiplexer {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn input_pins(&self) -> Vec<InputPin> {
+        let select = (0..address_bits(self.channels)).map(|i| format!("S{i}"));
+        let data = (0..self.channels).flat_map(|channel| {
+            (0..self.width).map(move |bit| pin_name("I", Some(channel), bit, self.width))
+        });
+        select
+            .chain(["/EN".to_string()])
+            .chain(data)
+            .map(|name| InputPin::new(&name))
+            .collect()
+    }
+
+    fn output_pins(&self) -> Vec<OutputPin> {
+        (0..self.width)
+            .map(|bit| {
+                OutputPin::new(
+                    &pin_name("Y", None, bit, self.width),
+                    SimDuration::from_ticks(self.delay),
+                    OutputPinState::HighImpedance,
+                )
+            })
+            .collect()
+    }
+
+    fn step(
+        &mut self,
+        inputs: &[InputPin],
+        outputs: &mut [OutputPin],
+        delta_t: u64,
+    ) -> Result<SimResult, String> {
+        let bits = address_bits(self.channels);
+        if inputs.len() != bits + 1 + self.channels * self.width || outputs.len() != self.width {
+            return Err(format!("Multiplexer \"{}\": unexpected pins", self.name));
+        }
+        let (select, rest) = inputs.split_at(bits);
+        let (enable, data) = rest.split_at(1);
+
+        let select = self.select.update(select, delta_t);
+        for (i, output) in outputs.iter_mut().enumerate() {
+            let value = match (enable[0].state(), select) {
+                (InputPinState::High, _) => Some(false),
+                (InputPinState::Low, Some(channel)) if channel < self.channels => {
+                    bit(&data[channel * self.width + i])
+                }
+                (InputPinState::Low, Some(_)) => Some(false),
+                _ => None,
+            };
+            output.drive(level(value));
+        }
+
+        Ok(SimResult::Continuing)
+    }
+
+    #[cfg(feature = "serde")]
+    fn save_state(&self) -> Result<serde_json::Value, String> {
+        super::save_state((self.select.current, self.select.pending))
+    }
+
+    #[cfg(feature = "serde")]
+    fn restore_state(&mut self, state: serde_json::Value) -> Result<(), String> {
+        (self.select.current, self.select.pending) = super::restore_state(&self.name, state)?;
+        Ok(())
+    }
+
+    fn box_clone(&self) -> Box<dyn Element> {
+        Box::new(self.clone())
+    }
+}
+
+/// A 1:N demultiplexer with an active-low enable, modelled on the 74155.
+///
+/// The inputs are named `S0` to `Sn`, `/EN`, and then the bits of `D`, and the outputs are the bits of each channel
+/// `Y0` to `Yn`.  While `/EN` is low the selected channel follows `D`, and every other channel is low.  `/EN` must be
+/// held low for the demultiplexer to pass data.
+///
+/// An indeterminate select or enable makes every output unknown, and an indeterminate bit of `D` makes its output in
+/// the selected channel unknown (X).
+#[derive(Debug, Clone, PartialEq)]
+pub struct Demultiplexer {
+    /// Name of the demultiplexer.
+    name: String,
+    /// Number of output channels.
+    channels: usize,
+    /// Width of each channel.
+    width: usize,
+    /// Propagation delay from a data input to the outputs.
+    delay: u64,
+    /// The select bus.
+    select: Select,
+}
+
+impl Demultiplexer {
+    /// Create a new Demultiplexer.
+    ///
+    /// # Parameters
+    ///
+    /// - `name`: Name of the demultiplexer.
+    /// - `channels`: Number of output channels, which must be at least two.
+    /// - `width`: Width of each channel, which must be at least one bit.
+    /// - `delay`: Propagation delay from a data input to the outputs.
+    /// - `select_delay`: Propagation delay from a select input to the outputs, which must be at least `delay`.
+    pub fn new(
+        name: &str,
+        channels: usize,
+        width: usize,
+        delay: u64,
+        select_delay: u64,
+    ) -> Result<Self, String> {
+        validate("Demultiplexer", name, channels, width, delay, select_delay)?;
+
+        Ok(Self {
+            name: name.to_string(),
+            channels,
+            width,
+            delay,
+            select: Select::new(select_delay - delay),
+        })
+    }
+}
+
+impl Element for Demultiplexer {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn input_pins(&self) -> Vec<InputPin> {
+        let select = (0..address_bits(self.channels)).map(|i| format!("S{i}"));
+        let data = (0..self.width).map(|bit| pin_name("D", None, bit, self.width));
+        select
+            .chain(["/EN".to_string()])
+            .chain(data)
+            .map(|name| InputPin::new(&name))
+            .collect()
+    }
+
+    fn output_pins(&self) -> Vec<OutputPin> {
+        (0..self.channels)
+            .flat_map(|channel| {
+                (0..self.width).map(move |bit| {
+                    OutputPin::new(
+                        &pin_name("Y", Some(channel), bit, self.width),
+                        SimDuration::from_ticks(self.delay),
+                        OutputPinState::HighImpedance,
+                    )
+                })
+            })
+            .collect()
+    }
+
+    fn step(
+        &mut self,
+        inputs: &[InputPin],
+        outputs: &mut [OutputPin],
+        delta_t: u64,
+    ) -> Result<SimResult, String> {
+        let bits = address_bits(self.channels);
+        if inputs.len() != bits + 1 + self.width || outputs.len() != self.channels * self.width {
+            return Err(format!("Demultiplexer \"{}\": unexpected pins", self.name));
+        }
+        let (select, rest) = inputs.split_at(bits);
+        let (enable, data) = rest.split_at(1);
+
+        let select = self.select.update(select, delta_t);
+        for (i, output) in outputs.iter_mut().enumerate() {
+            let (channel, bit_number) = (i / self.width, i % self.width);
+            let value = match (enable[0].state(), select) {
+                (InputPinState::High, _) => Some(false),
+                (InputPinState::Low, Some(selected)) if selected == channel => {
+                    bit(&data[bit_number])
+                }
+                (InputPinState::Low, Some(_)) => Some(false),
+                _ => None,
+            };
+            output.drive(level(value));
+        }
+
+        Ok(SimResult::Continuing)
+    }
+
+    #[cfg(feature = "serde")]
+    fn save_state(&self) -> Result<serde_json::Value, String> {
+        super::save_state((self.select.current, self.select.pending))
+    }
+
+    #[cfg(feature = "serde")]
+    fn restore_state(&mut self, state: serde_json::Value) -> Result<(), String> {
+        (self.select.current, self.select.pending) = super::restore_state(&self.name, state)?;
+        Ok(())
+    }
+
+    fn box_clone(&self) -> Box<dyn Element> {
+        Box::new(self.clone())
+    }
+}
+
+/// Register the multiplexer and demultiplexer.
+///
+/// Both take the `channels` (default 2), `width` (default 1), `delay` (default 0) and `select_delay` (default `delay`)
+/// parameters.
+///
+/// # Parameters
+///
+/// - `registry`: The Registry to add the elements to.
+pub fn register(registry: &mut Registry) {
+    fn shape(parameters: &Parameters) -> Result<(usize, usize, u64, u64), String> {
+        let delay = parameters.get_or("delay", 0)?;
+        Ok((
+            parameters.get_or("channels", DEFAULT_CHANNELS)?,
+            parameters.get_or("width", DEFAULT_WIDTH)?,
+            delay,
+            parameters.get_or("select_delay", delay)?,
+        ))
+    }
+
+    registry.register("mux", |name, parameters| {
+        let (channels, width, delay, select_delay) = shape(parameters)?;
+        Ok(Box::new(Multiplexer::new(
+            name,
+            channels,
+            width,
+            delay,
+            select_delay,
+        )?))
+    });
+    registry.register("demux", |name, parameters| {
+        let (channels, width, delay, select_delay) = shape(parameters)?;
+        Ok(Box::new(Demultiplexer::new(
+            name,
+            channels,
+            width,
+            delay,
+            select_delay,
+        )?))
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::element::testing::{steady, step};
+
+    const L: OutputPinState = OutputPinState::Low;
+    const H: OutputPinState = OutputPinState::High;
+    const X: OutputPinState = OutputPinState::Unknown;
+
+    #[test]
+    fn mux_selects_channel() {
+        // GIVEN an enabled 4:1 multiplexer with channel data 1, 0, 1, 1
+        let mut mux = Multiplexer::new("U1", 4, 1, 0, 0).unwrap();
+        let mut outputs = mux.output_pins();
+        // WHEN each channel is selected
+        // THEN the output follows it
+        assert_eq!(
+            vec![H],
+            step(
+                &mut mux,
+                &mut outputs,
+                &steady([0.0, 0.0, 0.0, 1.0, 0.0, 1.0, 1.0])
+            )
+        );
+        assert_eq!(
+            vec![L],
+            step(
+                &mut mux,
+                &mut outputs,
+                &steady([1.0, 0.0, 0.0, 1.0, 0.0, 1.0, 1.0])
+            )
+        );
+        assert_eq!(
+            vec![H],
+            step(
+                &mut mux,
+                &mut outputs,
+                &steady([1.0, 1.0, 0.0, 1.0, 0.0, 1.0, 1.0])
+            )
+        );
+        // AND THEN disabling the multiplexer drives the output low, and an indeterminate select makes it unknown
+        assert_eq!(
+            vec![L],
+            step(
+                &mut mux,
+                &mut outputs,
+                &steady([1.0, 1.0, 1.0, 1.0, 0.0, 1.0, 1.0])
+            )
+        );
+        assert_eq!(
+            vec![X],
+            step(
+                &mut mux,
+                &mut outputs,
+                &steady([0.5, 1.0, 0.0, 1.0, 0.0, 1.0, 1.0])
+            )
+        );
+    }
+    #[test]
+    fn mux_select_delay() {
+        // GIVEN an enabled 2:1 multiplexer whose select is 20 slower than its data, selecting a high channel 0
+        let mut mux = Multiplexer::new("U1", 2, 1, 0, 20).unwrap();
+        let mut outputs = mux.output_pins();
+        step(&mut mux, &mut outputs, &steady([0.0, 0.0, 1.0, 0.0]));
+        step(&mut mux, &mut outputs, &steady([0.0, 0.0, 1.0, 0.0]));
+        // WHEN the low channel 1 is selected
+        // THEN the output only changes once the extra select delay has elapsed
+        assert_eq!(
+            vec![H],
+            step(&mut mux, &mut outputs, &steady([1.0, 0.0, 1.0, 0.0]))
+        );
+        assert_eq!(
+            vec![L],
+            step(&mut mux, &mut outputs, &steady([1.0, 0.0, 1.0, 0.0]))
+        );
+    }
+    #[test]
+    fn demux_routes_data() {
+        // GIVEN an enabled 1:3 demultiplexer of two bit channels with data 0b01
+        let mut demux = Demultiplexer::new("U1", 3, 2, 0, 0).unwrap();
+        let mut outputs = demux.output_pins();
+        // WHEN channel 1 is selected
+        let states = step(&mut demux, &mut outputs, &steady([1.0, 0.0, 0.0, 1.0, 0.0]));
+        // THEN only that channel carries the data
+        assert_eq!(vec![L, L, H, L, L, L], states);
+        // AND THEN selecting the non-existent channel 3 drives every output low
+        let states = step(&mut demux, &mut outputs, &steady([1.0, 1.0, 0.0, 1.0, 0.0]));
+        assert_eq!(vec![L; 6], states);
+    }
+    #[test]
+    fn mux_registered_parameters() {
+        // GIVEN the standard registry
+        let registry = Registry::standard();
+        // WHEN elements are created with valid and invalid parameters
+        let parameters = Parameters::new().with("channels", "8").with("width", "4");
+        let mux = registry.create("mux", "U1", &parameters).unwrap();
+        let demux = registry.create("demux", "U2", &parameters).unwrap();
+        // THEN the valid parameters are used and the invalid ones are rejected
+        assert_eq!(3 + 1 + 32, mux.input_pins().len());
+        assert_eq!(32, demux.output_pins().len());
+        assert!(registry
+            .create("mux", "U3", &Parameters::new().with("channels", "1"))
+            .is_err());
+        assert!(registry
+            .create(
+                "demux",
+                "U4",
+                &Parameters::new()
+                    .with("delay", "10")
+                    .with("select_delay", "5")
+            )
+            .is_err());
+    }
+}
@@ -0,0 +1,235 @@
+//! Element plugins, loaded from shared libraries at startup so chip models can be distributed separately from the
+//! simulator.
+//!
+//! A plugin is a `cdylib` crate depending on `rvfs-sim-core`, which registers its kinds of Element with a [Registry]
+//! and declares itself with [export_plugin](crate::export_plugin):
+//!
+//! ```ignore
+//! use rvfs_sim_core::element::Registry;
+//!
+//! fn register(registry: &mut Registry) {
+//!     registry.register("my_chip", |name, parameters| Ok(Box::new(MyChip::new(name, parameters)?)));
+//! }
+//!
+//! rvfs_sim_core::export_plugin!(register);
+//! ```
+//!
+//! Elements are passed between the simulator and the plugin as Rust trait objects, which have no stable layout, so a
+//! plugin must be built against the same version of `rvfs-sim-core`, with the same compiler, as the simulator loading
+//! it.  The declaration records the plugin ABI version and the `rvfs-sim-core` version, which are checked on loading.
+
+use crate::element::Registry;
+use libloading::Library;
+use std::mem;
+use std::path::Path;
+
+/// Version of the plugin interface, incremented whenever [PluginDeclaration] or the way it is used changes.
+pub const ABI_VERSION: u32 = 1;
+
+/// Version of `rvfs-sim-core`, which must match between a plugin and the simulator loading it.
+pub const CORE_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Name of the symbol under which a plugin exports its [PluginDeclaration].
+const DECLARATION_SYMBOL: &[u8] = b"RVFS_SIM_PLUGIN\0";
+
+/// The declaration a plugin exports, describing how to register its kinds of Element.
+///
+/// The ABI version comes first and the layout is fixed, so that it can be checked before anything else is trusted.
+#[repr(C)]
+#[derive(Debug)]
+pub struct PluginDeclaration {
+    /// Version of the plugin interface the plugin was built for.
+    pub abi_version: u32,
+    /// Version of `rvfs-sim-core` the plugin was built against.
+    pub core_version: &'static str,
+    /// Function registering the kinds of Element in the plugin.
+    pub register: fn(&mut Registry),
+}
+
+impl PluginDeclaration {
+    /// Check that the plugin was built for the same interface as this simulator.
+    ///
+    /// # Parameters
+    ///
+    /// - `path`: Path of the plugin, for error messages.
+    fn check(&self, path: &Path) -> Result<(), String> {
+        if self.abi_version != ABI_VERSION {
+            return Err(format!(
+                "Plugin \"{}\": built for plugin ABI version {}, expected {ABI_VERSION}",
+                path.display(),
+                self.abi_version
+            ));
+        }
+        if self.core_version != CORE_VERSION {
+            return Err(format!(
+                "Plugin \"{}\": built against rvfs-sim-core {}, expected {CORE_VERSION}",
+                path.display(),
+                self.core_version
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+/// Export the [PluginDeclaration] of a plugin crate.
+///
+/// # Parameters
+///
+/// - `$register`: Function of type `fn(&mut Registry)` registering the kinds of Element in the plugin.
+#[macro_export]
+macro_rules! export_plugin {
+    ($register:expr) => {
+        #[no_mangle]
+        pub static RVFS_SIM_PLUGIN: $crate::element::plugins::PluginDeclaration =
+            $crate::element::plugins::PluginDeclaration {
+                abi_version: $crate::element::plugins::ABI_VERSION,
+                core_version: $crate::element::plugins::CORE_VERSION,
+                register: $register,
+            };
+    };
+}
+
+impl Registry {
+    /// Load a plugin from a shared library and register its kinds of Element, replacing any existing kinds of the same
+    /// names.
+    ///
+    /// Plugins are never unloaded, since the constructors and Elements they provide refer to code in the library.
+    ///
+    /// # Parameters
+    ///
+    /// - `path`: Path of the shared library.
+    ///
+    /// # Safety
+    ///
+    /// Loading a library runs its initialisation code, and the declaration it exports is trusted once its versions
+    /// have been checked, so the library must be a plugin built as described in the [module](self) documentation.
+    pub unsafe fn load_plugin(&mut self, path: &Path) -> Result<(), String> {
+        let library = Library::new(path)
+            .map_err(|error| format!("Plugin \"{}\": cannot load: {error}", path.display()))?;
+        let declaration = *library
+            .get::<*const PluginDeclaration>(DECLARATION_SYMBOL)
+            .map_err(|error| {
+                format!(
+                    "Plugin \"{}\": no plugin declaration: {error}",
+                    path.display()
+                )
+            })?;
+        let declaration = &*declaration;
+        declaration.check(path)?;
+        (declaration.register)(self);
+        mem::forget(library);
+
+        Ok(())
+    }
+
+    /// Load every plugin in a directory, in order of file name.  Files are taken to be plugins if they have the
+    /// platform's shared library extension.
+    ///
+    /// # Parameters
+    ///
+    /// - `directory`: Path of the directory.
+    ///
+    /// # Safety
+    ///
+    /// Every shared library in the directory must be a plugin, as for [load_plugin](Self::load_plugin).
+    pub unsafe fn load_plugins(&mut self, directory: &Path) -> Result<(), String> {
+        let entries = directory.read_dir().map_err(|error| {
+            format!(
+                "Plugin directory \"{}\": cannot read: {error}",
+                directory.display()
+            )
+        })?;
+        let mut paths = Vec::new();
+        for entry in entries {
+            let path = entry
+                .map_err(|error| {
+                    format!(
+                        "Plugin directory \"{}\": cannot read: {error}",
+                        directory.display()
+                    )
+                })?
+                .path();
+            if path.extension().is_some_and(|extension| {
+                extension == std::env::consts::DLL_EXTENSION && path.is_file()
+            }) {
+                paths.push(path);
+            }
+        }
+        paths.sort();
+        for path in paths {
+            self.load_plugin(&path)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    /// Registration function for test declarations.
+    fn register(registry: &mut Registry) {
+        registry.register("plugin_gate", |name, parameters| {
+            Registry::standard().create("nand", name, parameters)
+        });
+    }
+
+    #[test]
+    fn declaration_check() {
+        // GIVEN declarations for this simulator, an older ABI, and another core version
+        let current = PluginDeclaration {
+            abi_version: ABI_VERSION,
+            core_version: CORE_VERSION,
+            register,
+        };
+        let old_abi = PluginDeclaration {
+            abi_version: 0,
+            ..current
+        };
+        let other_core = PluginDeclaration {
+            core_version: "0.0.0-other",
+            ..current
+        };
+        // WHEN they are checked
+        let path = Path::new("libchip.so");
+        // THEN only the matching declaration is accepted
+        assert_eq!(Ok(()), current.check(path));
+        assert_eq!(
+            Err(format!(
+                "Plugin \"libchip.so\": built for plugin ABI version 0, expected {ABI_VERSION}"
+            )),
+            old_abi.check(path)
+        );
+        assert_eq!(
+            Err(format!(
+                "Plugin \"libchip.so\": built against rvfs-sim-core 0.0.0-other, expected {CORE_VERSION}"
+            )),
+            other_core.check(path)
+        );
+        let mut registry = Registry::new();
+        (current.register)(&mut registry);
+        assert_eq!(vec!["plugin_gate"], registry.kinds().collect::<Vec<_>>());
+    }
+    #[test]
+    fn load_plugin_errors() {
+        // GIVEN a missing file, and a directory holding a file which is not a library
+        let directory =
+            std::env::temp_dir().join(format!("rvfs-sim-plugins-{}", std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        let bogus = directory.join(format!("bogus.{}", std::env::consts::DLL_EXTENSION));
+        fs::write(&bogus, "not a library").unwrap();
+        fs::write(directory.join("README.txt"), "ignored").unwrap();
+        // WHEN they are loaded
+        let mut registry = Registry::new();
+        let missing = unsafe { registry.load_plugin(&directory.join("missing.so")) };
+        let loaded = unsafe { registry.load_plugins(&directory) };
+        fs::remove_dir_all(&directory).unwrap();
+        // THEN they are rejected, having registered nothing
+        assert!(missing.unwrap_err().contains("missing.so\": cannot load"));
+        assert!(loaded.unwrap_err().contains("bogus"));
+        assert_eq!(0, registry.kinds().count());
+    }
+}
@@ -0,0 +1,1978 @@
+//! Microprocessors, with bus timing close enough to the real parts to exercise the glue logic around them.
+
+use crate::element::{bit, level, tristate, word, Element, Parameters, Registry};
+use crate::ipin::{InputPin, InputPinState};
+use crate::opin::{OutputPin, OutputPinState};
+use crate::prelude::*;
+use crate::sim::SimResult;
+use crate::time::SimDuration;
+
+/// Carry flag.
+const CARRY: u8 = 0x01;
+/// Zero flag.
+const ZERO: u8 = 0x02;
+/// Interrupt disable flag.
+const INTERRUPT: u8 = 0x04;
+/// Decimal mode flag.
+const DECIMAL: u8 = 0x08;
+/// Break flag, which only exists in copies of the status register pushed to the stack.
+const BREAK: u8 = 0x10;
+/// Unused flag, which always reads as set.
+const UNUSED: u8 = 0x20;
+/// Overflow flag.
+const OVERFLOW: u8 = 0x40;
+/// Negative flag.
+const NEGATIVE: u8 = 0x80;
+
+/// Address of the non-maskable interrupt vector.
+const NMI_VECTOR: u16 = 0xfffa;
+/// Address of the reset vector.
+const RESET_VECTOR: u16 = 0xfffc;
+/// Address of the interrupt request and break vector.
+const IRQ_VECTOR: u16 = 0xfffe;
+
+/// The programmer-visible registers of a [Mos6502].
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Registers {
+    /// The accumulator.
+    pub a: u8,
+    /// The X index register.
+    pub x: u8,
+    /// The Y index register.
+    pub y: u8,
+    /// The stack pointer, within page one.
+    pub s: u8,
+    /// The processor status flags.
+    pub p: u8,
+    /// The program counter.
+    pub pc: u16,
+}
+
+impl Registers {
+    /// Set the zero and negative flags from a value, returning the value.
+    fn nz(&mut self, value: u8) -> u8 {
+        self.flag(ZERO, value == 0);
+        self.flag(NEGATIVE, value & 0x80 != 0);
+        value
+    }
+
+    /// Set or clear a flag.
+    fn flag(&mut self, flag: u8, set: bool) {
+        if set {
+            self.p |= flag;
+        } else {
+            self.p &= !flag;
+        }
+    }
+
+    /// Add a value and the carry to the accumulator, in decimal if the decimal flag is set.
+    fn adc(&mut self, value: u8) {
+        let carry = u16::from(self.p & CARRY);
+        let binary = u16::from(self.a) + u16::from(value) + carry;
+        self.flag(ZERO, binary & 0xff == 0);
+        if self.p & DECIMAL == 0 {
+            self.flag(
+                OVERFLOW,
+                (!(self.a ^ value) & (self.a ^ binary as u8)) & 0x80 != 0,
+            );
+            self.flag(CARRY, binary > 0xff);
+            self.a = binary as u8;
+            self.flag(NEGATIVE, self.a & 0x80 != 0);
+        } else {
+            let mut low = u16::from(self.a & 0x0f) + u16::from(value & 0x0f) + carry;
+            let mut high = u16::from(self.a >> 4) + u16::from(value >> 4);
+            if low > 9 {
+                low += 6;
+            }
+            if low > 0x0f {
+                high += 1;
+            }
+            let partial = ((high << 4) | (low & 0x0f)) as u8;
+            self.flag(NEGATIVE, partial & 0x80 != 0);
+            self.flag(
+                OVERFLOW,
+                (!(self.a ^ value) & (self.a ^ partial)) & 0x80 != 0,
+            );
+            if high > 9 {
+                high += 6;
+            }
+            self.flag(CARRY, high > 0x0f);
+            self.a = ((high << 4) | (low & 0x0f)) as u8;
+        }
+    }
+
+    /// Subtract a value and the borrow from the accumulator, in decimal if the decimal flag is set.
+    fn sbc(&mut self, value: u8) {
+        let borrow = i16::from(self.p & CARRY == 0);
+        let binary = i16::from(self.a) - i16::from(value) - borrow;
+        let result = binary as u8;
+        self.flag(OVERFLOW, ((self.a ^ value) & (self.a ^ result)) & 0x80 != 0);
+        self.flag(CARRY, binary >= 0);
+        self.nz(result);
+        if self.p & DECIMAL == 0 {
+            self.a = result;
+        } else {
+            let mut low = i16::from(self.a & 0x0f) - i16::from(value & 0x0f) - borrow;
+            let mut high = i16::from(self.a >> 4) - i16::from(value >> 4);
+            if low < 0 {
+                low -= 6;
+                high -= 1;
+            }
+            if high < 0 {
+                high -= 6;
+            }
+            self.a = ((high << 4) | (low & 0x0f)) as u8;
+        }
+    }
+
+    /// Compare a register with a value.
+    fn compare(&mut self, register: u8, value: u8) {
+        self.flag(CARRY, register >= value);
+        self.nz(register.wrapping_sub(value));
+    }
+
+    /// Shift a value left, into the carry.
+    fn asl(&mut self, value: u8) -> u8 {
+        self.flag(CARRY, value & 0x80 != 0);
+        self.nz(value << 1)
+    }
+
+    /// Shift a value right, into the carry.
+    fn lsr(&mut self, value: u8) -> u8 {
+        self.flag(CARRY, value & 0x01 != 0);
+        self.nz(value >> 1)
+    }
+
+    /// Rotate a value left, through the carry.
+    fn rol(&mut self, value: u8) -> u8 {
+        let carry = self.p & CARRY;
+        self.flag(CARRY, value & 0x80 != 0);
+        self.nz((value << 1) | carry)
+    }
+
+    /// Rotate a value right, through the carry.
+    fn ror(&mut self, value: u8) -> u8 {
+        let carry = self.p & CARRY;
+        self.flag(CARRY, value & 0x01 != 0);
+        self.nz((value >> 1) | (carry << 7))
+    }
+
+    /// Increment a value.
+    fn inc(&mut self, value: u8) -> u8 {
+        self.nz(value.wrapping_add(1))
+    }
+
+    /// Decrement a value.
+    fn dec(&mut self, value: u8) -> u8 {
+        self.nz(value.wrapping_sub(1))
+    }
+}
+
+/// A bus cycle, reading from or writing to an address.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+struct Cycle {
+    /// The address.
+    address: u16,
+    /// The value written, if the cycle is a write.
+    write: Option<u8>,
+}
+
+/// The part of an operation which could not run because its next bus cycle has not happened yet.
+#[derive(Debug)]
+struct Pending;
+
+/// An addressing mode.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Mode {
+    /// The operand follows the opcode.
+    Immediate,
+    /// An address in page zero.
+    ZeroPage,
+    /// An address in page zero, indexed by X.
+    ZeroPageX,
+    /// An address in page zero, indexed by Y.
+    ZeroPageY,
+    /// A full address.
+    Absolute,
+    /// A full address, indexed by X.
+    AbsoluteX,
+    /// A full address, indexed by Y.
+    AbsoluteY,
+    /// An address read from page zero, at a pointer indexed by X.
+    IndirectX,
+    /// An address read from page zero, then indexed by Y.
+    IndirectY,
+}
+
+impl Mode {
+    /// Obtain the addressing mode of an opcode, from its bit pattern.  Opcodes without an operand give a meaningless
+    /// mode, which is not used.
+    ///
+    /// # Parameters
+    ///
+    /// - `opcode`: The opcode.
+    fn of(opcode: u8) -> Self {
+        match (opcode & 0x03, (opcode >> 2) & 0x07) {
+            (1, 0) => Self::IndirectX,
+            (1, 2) | (_, 0) => Self::Immediate,
+            (1, 4) => Self::IndirectY,
+            (1, 6) => Self::AbsoluteY,
+            (_, 1) => Self::ZeroPage,
+            (_, 3) => Self::Absolute,
+            (_, 5) if matches!(opcode, 0x96 | 0xb6) => Self::ZeroPageY,
+            (_, 5) => Self::ZeroPageX,
+            (_, 7) if opcode == 0xbe => Self::AbsoluteY,
+            _ => Self::AbsoluteX,
+        }
+    }
+}
+
+/// How an instruction accesses its operand, which determines its dummy bus cycles.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Access {
+    /// The operand is read.
+    Read,
+    /// The operand is written.
+    Write,
+    /// The operand is read, modified and written back.
+    Modify,
+}
+
+/// A run of an operation against the bus cycles completed so far.
+///
+/// An operation is run from its start each time a bus cycle completes, with the cycles already completed replayed from
+/// the log, until it asks for a cycle which has not happened yet.  That cycle is then performed on the pins.
+struct Run<'a> {
+    /// The registers, as modified by the operation so far.
+    r: Registers,
+    /// The values read or written by the bus cycles completed so far.
+    log: &'a [u8],
+    /// Number of bus cycles replayed so far.
+    index: usize,
+    /// The next bus cycle to perform.
+    request: Option<Cycle>,
+}
+
+impl Run<'_> {
+    /// Perform a bus cycle.
+    fn cycle(&mut self, address: u16, write: Option<u8>) -> Result<u8, Pending> {
+        match self.log.get(self.index) {
+            Some(value) => {
+                self.index += 1;
+                Ok(*value)
+            }
+            None => {
+                self.request = Some(Cycle { address, write });
+                Err(Pending)
+            }
+        }
+    }
+
+    /// Read from an address.
+    fn read(&mut self, address: u16) -> Result<u8, Pending> {
+        self.cycle(address, None)
+    }
+
+    /// Write to an address.
+    fn write(&mut self, address: u16, value: u8) -> Result<(), Pending> {
+        self.cycle(address, Some(value)).map(|_| ())
+    }
+
+    /// Read the byte at the program counter, and advance it.
+    fn fetch(&mut self) -> Result<u8, Pending> {
+        let value = self.read(self.r.pc)?;
+        self.r.pc = self.r.pc.wrapping_add(1);
+        Ok(value)
+    }
+
+    /// Read the byte at the program counter without using it, as instructions without operands do.
+    fn idle(&mut self) -> Result<(), Pending> {
+        self.read(self.r.pc).map(|_| ())
+    }
+
+    /// Push a byte to the stack.
+    fn push(&mut self, value: u8) -> Result<(), Pending> {
+        self.write(0x0100 | u16::from(self.r.s), value)?;
+        self.r.s = self.r.s.wrapping_sub(1);
+        Ok(())
+    }
+
+    /// Pull a byte from the stack.
+    fn pull(&mut self) -> Result<u8, Pending> {
+        self.r.s = self.r.s.wrapping_add(1);
+        self.read(0x0100 | u16::from(self.r.s))
+    }
+
+    /// Read a little-endian address.
+    fn read_address(&mut self, low: u16, high: u16) -> Result<u16, Pending> {
+        let low = self.read(low)?;
+        let high = self.read(high)?;
+        Ok(u16::from_le_bytes([low, high]))
+    }
+
+    /// Index a base address, with the dummy read of the unfixed address made when a page is crossed, and always for
+    /// writes.
+    fn index(&mut self, base: u16, index: u8, access: Access) -> Result<u16, Pending> {
+        let address = base.wrapping_add(u16::from(index));
+        let unfixed = (base & 0xff00) | (address & 0x00ff);
+        if access != Access::Read || unfixed != address {
+            self.read(unfixed)?;
+        }
+        Ok(address)
+    }
+
+    /// Fetch the operand address of an instruction.
+    fn address(&mut self, mode: Mode, access: Access) -> Result<u16, Pending> {
+        Ok(match mode {
+            Mode::Immediate => {
+                let address = self.r.pc;
+                self.r.pc = self.r.pc.wrapping_add(1);
+                address
+            }
+            Mode::ZeroPage => u16::from(self.fetch()?),
+            Mode::ZeroPageX | Mode::ZeroPageY => {
+                let base = self.fetch()?;
+                self.read(u16::from(base))?;
+                let index = if mode == Mode::ZeroPageX {
+                    self.r.x
+                } else {
+                    self.r.y
+                };
+                u16::from(base.wrapping_add(index))
+            }
+            Mode::Absolute => {
+                let low = self.fetch()?;
+                u16::from_le_bytes([low, self.fetch()?])
+            }
+            Mode::AbsoluteX | Mode::AbsoluteY => {
+                let low = self.fetch()?;
+                let base = u16::from_le_bytes([low, self.fetch()?]);
+                let index = if mode == Mode::AbsoluteX {
+                    self.r.x
+                } else {
+                    self.r.y
+                };
+                self.index(base, index, access)?
+            }
+            Mode::IndirectX => {
+                let pointer = self.fetch()?;
+                self.read(u16::from(pointer))?;
+                let pointer = pointer.wrapping_add(self.r.x);
+                self.read_address(u16::from(pointer), u16::from(pointer.wrapping_add(1)))?
+            }
+            Mode::IndirectY => {
+                let pointer = self.fetch()?;
+                let base =
+                    self.read_address(u16::from(pointer), u16::from(pointer.wrapping_add(1)))?;
+                self.index(base, self.r.y, access)?
+            }
+        })
+    }
+
+    /// Read the operand of an instruction.
+    fn operand(&mut self, mode: Mode) -> Result<u8, Pending> {
+        let address = self.address(mode, Access::Read)?;
+        self.read(address)
+    }
+
+    /// Write the operand of an instruction.
+    fn store(&mut self, mode: Mode, value: u8) -> Result<(), Pending> {
+        let address = self.address(mode, Access::Write)?;
+        self.write(address, value)
+    }
+
+    /// Read, modify and write back the operand of an instruction, writing the unmodified value first as the real part
+    /// does.
+    fn modify(&mut self, mode: Mode, f: fn(&mut Registers, u8) -> u8) -> Result<(), Pending> {
+        let address = self.address(mode, Access::Modify)?;
+        let value = self.read(address)?;
+        self.write(address, value)?;
+        let value = f(&mut self.r, value);
+        self.write(address, value)
+    }
+
+    /// Modify the accumulator.
+    fn modify_a(&mut self, f: fn(&mut Registers, u8) -> u8) -> Result<(), Pending> {
+        self.idle()?;
+        let a = self.r.a;
+        self.r.a = f(&mut self.r, a);
+        Ok(())
+    }
+
+    /// Branch on a condition.
+    fn branch(&mut self, taken: bool) -> Result<(), Pending> {
+        let offset = self.fetch()? as i8;
+        if taken {
+            self.idle()?;
+            let target = self.r.pc.wrapping_add_signed(i16::from(offset));
+            if target & 0xff00 != self.r.pc & 0xff00 {
+                self.read((self.r.pc & 0xff00) | (target & 0x00ff))?;
+            }
+            self.r.pc = target;
+        }
+        Ok(())
+    }
+
+    /// Run the reset sequence.
+    fn reset(&mut self) -> Result<(), Pending> {
+        self.idle()?;
+        self.idle()?;
+        for _ in 0..3 {
+            self.read(0x0100 | u16::from(self.r.s))?;
+            self.r.s = self.r.s.wrapping_sub(1);
+        }
+        self.r.p |= INTERRUPT | UNUSED;
+        self.r.pc = self.read_address(RESET_VECTOR, RESET_VECTOR + 1)?;
+        Ok(())
+    }
+
+    /// Run an interrupt sequence, either for a break instruction whose opcode has been fetched, or for a hardware
+    /// interrupt.
+    fn interrupt(&mut self, vector: u16, brk: bool) -> Result<(), Pending> {
+        if brk {
+            self.fetch()?;
+        } else {
+            self.idle()?;
+            self.idle()?;
+        }
+        let [low, high] = self.r.pc.to_le_bytes();
+        self.push(high)?;
+        self.push(low)?;
+        self.push(self.r.p | UNUSED | if brk { BREAK } else { 0 })?;
+        self.r.p |= INTERRUPT;
+        self.r.pc = self.read_address(vector, vector + 1)?;
+        Ok(())
+    }
+
+    /// Run an instruction, starting with fetching its opcode.
+    fn instruction(&mut self) -> Result<(), Pending> {
+        let opcode = self.fetch()?;
+        let mode = Mode::of(opcode);
+        match opcode {
+            // Loads and stores.
+            0xa1 | 0xa5 | 0xa9 | 0xad | 0xb1 | 0xb5 | 0xb9 | 0xbd => {
+                let value = self.operand(mode)?;
+                self.r.a = self.r.nz(value);
+            }
+            0xa2 | 0xa6 | 0xae | 0xb6 | 0xbe => {
+                let value = self.operand(mode)?;
+                self.r.x = self.r.nz(value);
+            }
+            0xa0 | 0xa4 | 0xac | 0xb4 | 0xbc => {
+                let value = self.operand(mode)?;
+                self.r.y = self.r.nz(value);
+            }
+            0x81 | 0x85 | 0x8d | 0x91 | 0x95 | 0x99 | 0x9d => self.store(mode, self.r.a)?,
+            0x86 | 0x8e | 0x96 => self.store(mode, self.r.x)?,
+            0x84 | 0x8c | 0x94 => self.store(mode, self.r.y)?,
+
+            // Arithmetic and logic.
+            0x01 | 0x05 | 0x09 | 0x0d | 0x11 | 0x15 | 0x19 | 0x1d => {
+                let value = self.operand(mode)?;
+                self.r.a = self.r.nz(self.r.a | value);
+            }
+            0x21 | 0x25 | 0x29 | 0x2d | 0x31 | 0x35 | 0x39 | 0x3d => {
+                let value = self.operand(mode)?;
+                self.r.a = self.r.nz(self.r.a & value);
+            }
+            0x41 | 0x45 | 0x49 | 0x4d | 0x51 | 0x55 | 0x59 | 0x5d => {
+                let value = self.operand(mode)?;
+                self.r.a = self.r.nz(self.r.a ^ value);
+            }
+            0x61 | 0x65 | 0x69 | 0x6d | 0x71 | 0x75 | 0x79 | 0x7d => {
+                let value = self.operand(mode)?;
+                self.r.adc(value);
+            }
+            0xe1 | 0xe5 | 0xe9 | 0xed | 0xf1 | 0xf5 | 0xf9 | 0xfd => {
+                let value = self.operand(mode)?;
+                self.r.sbc(value);
+            }
+            0xc1 | 0xc5 | 0xc9 | 0xcd | 0xd1 | 0xd5 | 0xd9 | 0xdd => {
+                let value = self.operand(mode)?;
+                self.r.compare(self.r.a, value);
+            }
+            0xe0 | 0xe4 | 0xec => {
+                let value = self.operand(mode)?;
+                self.r.compare(self.r.x, value);
+            }
+            0xc0 | 0xc4 | 0xcc => {
+                let value = self.operand(mode)?;
+                self.r.compare(self.r.y, value);
+            }
+            0x24 | 0x2c => {
+                let value = self.operand(mode)?;
+                self.r.flag(ZERO, self.r.a & value == 0);
+                self.r.p = (self.r.p & !(NEGATIVE | OVERFLOW)) | (value & (NEGATIVE | OVERFLOW));
+            }
+
+            // Read-modify-write.
+            0x06 | 0x0e | 0x16 | 0x1e => self.modify(mode, Registers::asl)?,
+            0x26 | 0x2e | 0x36 | 0x3e => self.modify(mode, Registers::rol)?,
+            0x46 | 0x4e | 0x56 | 0x5e => self.modify(mode, Registers::lsr)?,
+            0x66 | 0x6e | 0x76 | 0x7e => self.modify(mode, Registers::ror)?,
+            0xc6 | 0xce | 0xd6 | 0xde => self.modify(mode, Registers::dec)?,
+            0xe6 | 0xee | 0xf6 | 0xfe => self.modify(mode, Registers::inc)?,
+            0x0a => self.modify_a(Registers::asl)?,
+            0x2a => self.modify_a(Registers::rol)?,
+            0x4a => self.modify_a(Registers::lsr)?,
+            0x6a => self.modify_a(Registers::ror)?,
+
+            // Register transfers, increments and flags.
+            0xaa => self.modify_a(|r, a| {
+                r.x = r.nz(a);
+                a
+            })?,
+            0xa8 => self.modify_a(|r, a| {
+                r.y = r.nz(a);
+                a
+            })?,
+            0x8a => self.modify_a(|r, _| r.nz(r.x))?,
+            0x98 => self.modify_a(|r, _| r.nz(r.y))?,
+            0xba => {
+                self.idle()?;
+                self.r.x = self.r.nz(self.r.s);
+            }
+            0x9a => {
+                self.idle()?;
+                self.r.s = self.r.x;
+            }
+            0xe8 => {
+                self.idle()?;
+                self.r.x = self.r.inc(self.r.x);
+            }
+            0xca => {
+                self.idle()?;
+                self.r.x = self.r.dec(self.r.x);
+            }
+            0xc8 => {
+                self.idle()?;
+                self.r.y = self.r.inc(self.r.y);
+            }
+            0x88 => {
+                self.idle()?;
+                self.r.y = self.r.dec(self.r.y);
+            }
+            0x18 | 0x38 | 0x58 | 0x78 | 0xb8 | 0xd8 | 0xf8 => {
+                self.idle()?;
+                let flag = [CARRY, INTERRUPT, OVERFLOW, DECIMAL][usize::from(opcode >> 6)];
+                self.r.flag(flag, opcode & 0x20 != 0 && opcode != 0xb8);
+            }
+
+            // Stack.
+            0x48 => {
+                self.idle()?;
+                self.push(self.r.a)?;
+            }
+            0x08 => {
+                self.idle()?;
+                self.push(self.r.p | UNUSED | BREAK)?;
+            }
+            0x68 => {
+                self.idle()?;
+                self.read(0x0100 | u16::from(self.r.s))?;
+                let value = self.pull()?;
+                self.r.a = self.r.nz(value);
+            }
+            0x28 => {
+                self.idle()?;
+                self.read(0x0100 | u16::from(self.r.s))?;
+                self.r.p = (self.pull()? & !BREAK) | UNUSED;
+            }
+
+            // Branches, selecting the flag by the top two bits and the value it must have by the next.
+            0x10 | 0x30 | 0x50 | 0x70 | 0x90 | 0xb0 | 0xd0 | 0xf0 => {
+                let flag = [NEGATIVE, OVERFLOW, CARRY, ZERO][usize::from(opcode >> 6)];
+                self.branch((self.r.p & flag != 0) == (opcode & 0x20 != 0))?;
+            }
+
+            // Jumps, subroutines and interrupts.
+            0x4c => self.r.pc = self.address(Mode::Absolute, Access::Read)?,
+            0x6c => {
+                let pointer = self.address(Mode::Absolute, Access::Read)?;
+                // The high byte is read from the same page as the low byte, as on the real part.
+                let next = (pointer & 0xff00) | (pointer.wrapping_add(1) & 0x00ff);
+                self.r.pc = self.read_address(pointer, next)?;
+            }
+            0x20 => {
+                let low = self.fetch()?;
+                self.read(0x0100 | u16::from(self.r.s))?;
+                let [return_low, return_high] = self.r.pc.to_le_bytes();
+                self.push(return_high)?;
+                self.push(return_low)?;
+                self.r.pc = u16::from_le_bytes([low, self.read(self.r.pc)?]);
+            }
+            0x60 => {
+                self.idle()?;
+                self.read(0x0100 | u16::from(self.r.s))?;
+                let low = self.pull()?;
+                self.r.pc = u16::from_le_bytes([low, self.pull()?]);
+                self.fetch()?;
+            }
+            0x40 => {
+                self.idle()?;
+                self.read(0x0100 | u16::from(self.r.s))?;
+                self.r.p = (self.pull()? & !BREAK) | UNUSED;
+                let low = self.pull()?;
+                self.r.pc = u16::from_le_bytes([low, self.pull()?]);
+            }
+            0x00 => self.interrupt(IRQ_VECTOR, true)?,
+
+            // No operation, which undocumented opcodes are also taken as.
+            _ => self.idle()?,
+        }
+
+        Ok(())
+    }
+}
+
+/// The operation a [Mos6502] is performing.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+enum Operation {
+    /// The reset sequence.
+    Reset,
+    /// A hardware interrupt sequence, non-maskable or not.
+    Interrupt(bool),
+    /// An instruction.
+    Instruction,
+}
+
+/// A cycle-approximate NMOS 6502 microprocessor.
+///
+/// The inputs are named `PHI0`, `/RES`, `/IRQ`, `/NMI`, `RDY` and `D0` to `D7`, and the outputs are named `A0` to
+/// `A15`, `D0` to `D7`, `R/W`, `SYNC` and `PHI2`.  The data bus InputPins and OutputPins should both be connected to
+/// the same Wires.
+///
+/// Each period of the `PHI0` clock is one bus cycle, which starts as the clock falls.  The address, `R/W` and `SYNC`
+/// change then, and `PHI2` follows the clock.  Data is read as the clock falls at the end of a read cycle, and is
+/// driven while the clock is high during a write cycle.  Every instruction performs the same bus cycles as the real
+/// part, including its dummy reads and writes, so it takes the same number of cycles.  `SYNC` is high while an opcode
+/// is fetched.
+///
+/// While `/RES` is low the processor is held in reset with its buses released, and when it goes high the reset
+/// sequence runs and the program starts at the reset vector.  The processor starts in reset.  A falling edge on
+/// `/NMI`, or `/IRQ` being low while interrupts are enabled, starts an interrupt sequence once the instruction in
+/// progress completes.  A read cycle is repeated while `RDY` is low, stalling the processor, but write cycles are not.
+/// An indeterminate control input is taken as inactive, and an indeterminate data bus reads as 0xff.
+///
+/// Decimal mode is supported.  Undocumented opcodes are taken as two cycle instructions which do nothing.
+///
+/// # Example
+///
+/// ```
+/// # use rvfs_sim_core::element::processors::Mos6502;
+/// # use rvfs_sim_core::element::Element;
+/// let cpu = Mos6502::new("U1", 0);
+///
+/// assert_eq!("/RES", cpu.input_pins()[1].name());
+/// assert_eq!("SYNC", cpu.output_pins()[25].name());
+/// ```
+#[derive(Debug, Clone)]
+pub struct Mos6502 {
+    /// Name of the processor.
+    name: String,
+    /// Propagation delay from the clock to the outputs.
+    delay: u64,
+    /// The registers, as they stand at the start of the operation in progress.
+    registers: Registers,
+    /// The operation in progress.
+    operation: Operation,
+    /// The values read or written by the bus cycles of the operation completed so far.
+    log: Vec<u8>,
+    /// The bus cycle in progress, if not held in reset.
+    cycle: Option<Cycle>,
+    /// Whether a non-maskable interrupt has been requested and not yet started.
+    nmi: bool,
+    /// Number of bus cycles completed.
+    cycles: u64,
+}
+
+impl Mos6502 {
+    /// Create a new Mos6502, held in reset.
+    ///
+    /// # Parameters
+    ///
+    /// - `name`: Name of the processor.
+    /// - `delay`: Propagation delay from the clock to the outputs.
+    pub fn new(name: &str, delay: u64) -> Self {
+        Self {
+            name: name.to_string(),
+            delay,
+            registers: Registers {
+                p: UNUSED | INTERRUPT,
+                ..Registers::default()
+            },
+            operation: Operation::Reset,
+            log: Vec::new(),
+            cycle: None,
+            nmi: false,
+            cycles: 0,
+        }
+    }
+
+    /// Obtain the registers, as they stood when the last operation completed.
+    pub fn registers(&self) -> Registers {
+        self.registers
+    }
+
+    /// Obtain the number of bus cycles completed, not counting cycles repeated while stalled.
+    pub fn cycles(&self) -> u64 {
+        self.cycles
+    }
+
+    /// Plan the next bus cycle, completing the operation in progress and starting the next if it needs no more.
+    ///
+    /// # Parameters
+    ///
+    /// - `irq`: Whether an interrupt is requested on `/IRQ`.
+    fn plan(&mut self, irq: bool) {
+        loop {
+            let mut run = Run {
+                r: self.registers,
+                log: &self.log,
+                index: 0,
+                request: None,
+            };
+            let result = match self.operation {
+                Operation::Reset => run.reset(),
+                Operation::Interrupt(true) => run.interrupt(NMI_VECTOR, false),
+                Operation::Interrupt(false) => run.interrupt(IRQ_VECTOR, false),
+                Operation::Instruction => run.instruction(),
+            };
+            let (registers, request) = (run.r, run.request);
+            if result.is_err() {
+                self.cycle = request;
+                return;
+            }
+
+            self.registers = registers;
+            self.log.clear();
+            self.operation = if self.nmi {
+                self.nmi = false;
+                Operation::Interrupt(true)
+            } else if irq && self.registers.p & INTERRUPT == 0 {
+                Operation::Interrupt(false)
+            } else {
+                Operation::Instruction
+            };
+        }
+    }
+}
+
+impl Element for Mos6502 {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn input_pins(&self) -> Vec<InputPin> {
+        ["PHI0", "/RES", "/IRQ", "/NMI", "RDY"]
+            .into_iter()
+            .map(str::to_string)
+            .chain((0..8).map(|i| format!("D{i}")))
+            .map(|name| InputPin::new(&name))
+            .collect()
+    }
+
+    fn output_pins(&self) -> Vec<OutputPin> {
+        (0..16)
+            .map(|i| format!("A{i}"))
+            .chain((0..8).map(|i| format!("D{i}")))
+            .chain(["R/W", "SYNC", "PHI2"].into_iter().map(str::to_string))
+            .map(|name| {
+                OutputPin::new(
+                    &name,
+                    SimDuration::from_ticks(self.delay),
+                    OutputPinState::HighImpedance,
+                )
+            })
+            .collect()
+    }
+
+    fn step(
+        &mut self,
+        inputs: &[InputPin],
+        outputs: &mut [OutputPin],
+        _delta_t: u64,
+    ) -> Result<SimResult, String> {
+        let [clock, reset, irq, nmi, ready, data @ ..] = inputs else {
+            return Err(format!("6502 \"{}\": unexpected pins", self.name));
+        };
+        if data.len() != 8 || outputs.len() != 27 {
+            return Err(format!("6502 \"{}\": unexpected pins", self.name));
+        }
+        let (address_out, rest) = outputs.split_at_mut(16);
+        let (data_out, control_out) = rest.split_at_mut(8);
+        let [rw_out, sync_out, phi2_out] = control_out else {
+            return Err(format!("6502 \"{}\": unexpected pins", self.name));
+        };
+
+        if nmi.falling() {
+            self.nmi = true;
+        }
+        let irq = irq.state() == InputPinState::Low;
+        if reset.state() == InputPinState::Low {
+            self.operation = Operation::Reset;
+            self.log.clear();
+            self.cycle = None;
+            self.nmi = false;
+        } else if self.cycle.is_none() {
+            self.plan(irq);
+        } else if let Some(cycle) = self.cycle.filter(|_| clock.falling()) {
+            let value = match cycle.write {
+                Some(value) => Some(value),
+                None if ready.state() == InputPinState::Low => None,
+                None => Some(word(data).map_or(0xff, |value| value as u8)),
+            };
+            if let Some(value) = value {
+                self.log.push(value);
+                self.cycles += 1;
+                self.plan(irq);
+            }
+        }
+
+        let clock_high = clock.state() == InputPinState::High;
+        for (i, output) in address_out.iter_mut().enumerate() {
+            output.drive(tristate(
+                self.cycle.map(|cycle| (cycle.address >> i) & 1 != 0),
+            ));
+        }
+        let written = self
+            .cycle
+            .and_then(|cycle| cycle.write)
+            .filter(|_| clock_high);
+        for (i, output) in data_out.iter_mut().enumerate() {
+            output.drive(tristate(written.map(|value| (value >> i) & 1 != 0)));
+        }
+        rw_out.drive(level(Some(
+            self.cycle.is_none_or(|cycle| cycle.write.is_none()),
+        )));
+        sync_out.drive(level(Some(
+            self.cycle.is_some() && self.log.is_empty() && self.operation != Operation::Reset,
+        )));
+        phi2_out.drive(level(bit(clock)));
+
+        Ok(SimResult::Continuing)
+    }
+
+    #[cfg(feature = "serde")]
+    fn save_state(&self) -> Result<serde_json::Value, String> {
+        super::save_state((
+            self.registers,
+            self.operation,
+            &self.log,
+            self.cycle,
+            self.nmi,
+            self.cycles,
+        ))
+    }
+
+    #[cfg(feature = "serde")]
+    fn restore_state(&mut self, state: serde_json::Value) -> Result<(), String> {
+        (
+            self.registers,
+            self.operation,
+            self.log,
+            self.cycle,
+            self.nmi,
+            self.cycles,
+        ) = super::restore_state(&self.name, state)?;
+        Ok(())
+    }
+
+    fn box_clone(&self) -> Box<dyn Element> {
+        Box::new(self.clone())
+    }
+}
+
+/// Major opcode of `LUI`.
+const LUI: u32 = 0x37;
+/// Major opcode of `AUIPC`.
+const AUIPC: u32 = 0x17;
+/// Major opcode of `JAL`.
+const JAL: u32 = 0x6f;
+/// Major opcode of `JALR`.
+const JALR: u32 = 0x67;
+/// Major opcode of the conditional branches.
+const BRANCH: u32 = 0x63;
+/// Major opcode of the loads.
+const LOAD: u32 = 0x03;
+/// Major opcode of the stores.
+const STORE: u32 = 0x23;
+/// Major opcode of the register-immediate operations.
+const OP_IMM: u32 = 0x13;
+/// Major opcode of the register-register operations.
+const OP: u32 = 0x33;
+/// Major opcode of `FENCE`.
+const MISC_MEM: u32 = 0x0f;
+/// The `ECALL` instruction.
+const ECALL: u32 = 0x0000_0073;
+/// The `EBREAK` instruction.
+const EBREAK: u32 = 0x0010_0073;
+
+/// An RV32I instruction word.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+struct Instruction(u32);
+
+impl Instruction {
+    /// Obtain the major opcode.
+    fn opcode(self) -> u32 {
+        self.0 & 0x7f
+    }
+
+    /// Obtain the destination register.
+    fn rd(self) -> usize {
+        ((self.0 >> 7) & 0x1f) as usize
+    }
+
+    /// Obtain the minor opcode.
+    fn funct3(self) -> u32 {
+        (self.0 >> 12) & 0x07
+    }
+
+    /// Obtain the first source register.
+    fn rs1(self) -> usize {
+        ((self.0 >> 15) & 0x1f) as usize
+    }
+
+    /// Obtain the second source register.
+    fn rs2(self) -> usize {
+        ((self.0 >> 20) & 0x1f) as usize
+    }
+
+    /// Obtain the top seven bits, which select between variants of register-register operations and shifts.
+    fn funct7(self) -> u32 {
+        self.0 >> 25
+    }
+
+    /// Obtain the sign-extended immediate of an I-type instruction.
+    fn i_immediate(self) -> u32 {
+        ((self.0 as i32) >> 20) as u32
+    }
+
+    /// Obtain the sign-extended immediate of an S-type instruction.
+    fn s_immediate(self) -> u32 {
+        (((self.0 as i32) >> 25) << 5) as u32 | ((self.0 >> 7) & 0x1f)
+    }
+
+    /// Obtain the sign-extended branch offset of a B-type instruction.
+    fn b_immediate(self) -> u32 {
+        (((self.0 as i32) >> 31) << 12) as u32
+            | ((self.0 << 4) & 0x800)
+            | ((self.0 >> 20) & 0x7e0)
+            | ((self.0 >> 7) & 0x1e)
+    }
+
+    /// Obtain the upper immediate of a U-type instruction.
+    fn u_immediate(self) -> u32 {
+        self.0 & 0xffff_f000
+    }
+
+    /// Obtain the sign-extended jump offset of a J-type instruction.
+    fn j_immediate(self) -> u32 {
+        (((self.0 as i32) >> 31) << 20) as u32
+            | (self.0 & 0xf_f000)
+            | ((self.0 >> 9) & 0x800)
+            | ((self.0 >> 20) & 0x7fe)
+    }
+}
+
+/// Perform an arithmetic or logic operation, as selected by the minor opcode of a register-immediate or
+/// register-register instruction.
+///
+/// # Parameters
+///
+/// - `funct3`: The minor opcode.
+/// - `alternate`: Whether the alternate operation is selected, subtracting rather than adding or shifting right
+///   arithmetically rather than logically.
+/// - `a`: The first operand.
+/// - `b`: The second operand, of which only the low five bits are used as a shift amount.
+fn alu(funct3: u32, alternate: bool, a: u32, b: u32) -> u32 {
+    match funct3 {
+        0 if alternate => a.wrapping_sub(b),
+        0 => a.wrapping_add(b),
+        1 => a << (b & 0x1f),
+        2 => u32::from((a as i32) < (b as i32)),
+        3 => u32::from(a < b),
+        4 => a ^ b,
+        5 if alternate => ((a as i32) >> (b & 0x1f)) as u32,
+        5 => a >> (b & 0x1f),
+        6 => a | b,
+        _ => a & b,
+    }
+}
+
+/// Expand a set of byte lanes into a mask of the bits of a word they carry.
+///
+/// # Parameters
+///
+/// - `lanes`: The byte lanes, one bit each from the least significant byte.
+fn lane_mask(lanes: u8) -> u32 {
+    (0..4)
+        .filter(|lane| lanes & (1 << lane) != 0)
+        .fold(0, |mask, lane| mask | (0xff << (8 * lane)))
+}
+
+/// What a bus cycle of an [Rv32iCore] is for.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+enum Transfer {
+    /// Fetching an instruction.
+    Fetch,
+    /// Loading a register, by a load instruction with the given minor opcode.
+    Load { rd: usize, funct3: u32 },
+    /// Reading the word which part of a store is to be merged into.
+    Merge { value: u32, lanes: u8 },
+    /// Writing a word, of which only the given byte lanes are being stored.
+    Store { value: u32, lanes: u8 },
+}
+
+/// A bus cycle of an [Rv32iCore].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+struct BusCycle {
+    /// The byte address accessed, from which the word address is driven.
+    address: u32,
+    /// What the cycle is for.
+    transfer: Transfer,
+}
+
+impl BusCycle {
+    /// Create the bus cycle fetching an instruction.
+    ///
+    /// # Parameters
+    ///
+    /// - `pc`: Address of the instruction.
+    fn fetch(pc: u32) -> Self {
+        Self {
+            address: pc,
+            transfer: Transfer::Fetch,
+        }
+    }
+}
+
+/// A behavioural RV32I processor core, running firmware from memories on a 32-bit bus.
+///
+/// The inputs are named `CLK`, `/RESET` and `D0` to `D31`, and the outputs are named `A2` to `A31`, `D0` to `D31`,
+/// `/BE0` to `/BE3`, `/RD` and `/WR`.  The data bus InputPins and OutputPins should both be connected to the same
+/// Wires.  The address bus carries word addresses, so a memory 32 bits wide has its `A0` connected to `A2`, and `/BE0`
+/// to `/BE3` select the bytes of the word accessed, least significant first.  `/RD` and `/WR` suit the `/OE` and `/WE`
+/// inputs of an [Sram](crate::element::memory::Sram), whose `/CS` is decoded from the address.
+///
+/// Each period of `CLK` is one bus cycle, which starts as the clock rises, when the address and byte lanes change.
+/// During a read cycle `/RD` is low while the clock is high, and data is read as the clock falls.  During a write cycle
+/// data is driven throughout, with `/WR` low while the clock is high, so that the address and data are held after the
+/// write ends and the bus is free of the memory being read before it is driven.  Every instruction takes one cycle to
+/// fetch, a load takes a further read cycle and a word store a further write cycle, while a byte or halfword store
+/// reads the word it is stored into and writes it back merged, so that memories without byte lanes can be used.
+///
+/// While `/RESET` is low the core is held in reset with its address and data buses released, and once it is high the
+/// program starts at the reset address with the next bus cycle.  The core starts in reset.  `ECALL` and `EBREAK` halt
+/// the core and finish the Simulation, so that firmware can end a run, until it is reset again.  `FENCE` does nothing,
+/// since there is only the one bus.  An illegal instruction, or a jump, load or store to a misaligned address, fails
+/// the step, as there is no trap handling.  An indeterminate `CLK` or `/RESET` is taken as inactive, and indeterminate
+/// data bits read as one.
+///
+/// # Example
+///
+/// ```
+/// # use rvfs_sim_core::element::processors::Rv32iCore;
+/// # use rvfs_sim_core::element::Element;
+/// let cpu = Rv32iCore::new("U1", 0, 0x0000_0000);
+///
+/// assert_eq!("/RESET", cpu.input_pins()[1].name());
+/// assert_eq!("A2", cpu.output_pins()[0].name());
+/// assert_eq!("/WR", cpu.output_pins()[67].name());
+/// ```
+#[derive(Debug, Clone)]
+pub struct Rv32iCore {
+    /// Name of the core.
+    name: String,
+    /// Propagation delay from the clock to the outputs.
+    delay: u64,
+    /// Address of the first instruction run after reset.
+    reset: u32,
+    /// The integer registers, of which `x0` is always zero.
+    x: [u32; 32],
+    /// The program counter, addressing the instruction in progress.
+    pc: u32,
+    /// The bus cycle in progress, if not held in reset or halted.
+    cycle: Option<BusCycle>,
+    /// The word read from the data bus as the clock last fell.
+    latched: u32,
+    /// Whether the core has been halted by `ECALL` or `EBREAK`.
+    halted: bool,
+    /// Number of instructions run.
+    instructions: u64,
+    /// Number of bus cycles completed.
+    cycles: u64,
+}
+
+impl Rv32iCore {
+    /// Create a new Rv32iCore, held in reset.
+    ///
+    /// # Parameters
+    ///
+    /// - `name`: Name of the core.
+    /// - `delay`: Propagation delay from the clock to the outputs.
+    /// - `reset`: Address of the first instruction run after reset.
+    pub fn new(name: &str, delay: u64, reset: u32) -> Self {
+        Self {
+            name: name.to_string(),
+            delay,
+            reset,
+            x: [0; 32],
+            pc: reset,
+            cycle: None,
+            latched: 0,
+            halted: false,
+            instructions: 0,
+            cycles: 0,
+        }
+    }
+
+    /// Obtain the integer registers, `x0` to `x31`.
+    pub fn registers(&self) -> [u32; 32] {
+        self.x
+    }
+
+    /// Obtain the program counter, which addresses the instruction in progress, or the instruction which halted the
+    /// core.
+    pub fn pc(&self) -> u32 {
+        self.pc
+    }
+
+    /// Determine whether the core has been halted by `ECALL` or `EBREAK`.
+    pub fn halted(&self) -> bool {
+        self.halted
+    }
+
+    /// Obtain the number of instructions run.
+    pub fn instructions(&self) -> u64 {
+        self.instructions
+    }
+
+    /// Obtain the number of bus cycles completed.
+    pub fn cycles(&self) -> u64 {
+        self.cycles
+    }
+
+    /// Set a register, unless it is `x0`.
+    fn set(&mut self, rd: usize, value: u32) {
+        if rd != 0 {
+            self.x[rd] = value;
+        }
+    }
+
+    /// Check that an address is aligned to the size of an access.
+    ///
+    /// # Parameters
+    ///
+    /// - `access`: Description of the access, for reporting.
+    /// - `address`: The address.
+    /// - `size`: Size of the access in bytes.
+    fn aligned(&self, access: &str, address: u32, size: u32) -> Result<u32, String> {
+        if address.is_multiple_of(size) {
+            Ok(address)
+        } else {
+            Err(format!(
+                "RV32I \"{}\": misaligned {access} {address:#010x} at {:#010x}",
+                self.name, self.pc
+            ))
+        }
+    }
+
+    /// Run a fetched instruction, returning the next bus cycle, or None if the instruction halted the core.
+    ///
+    /// # Parameters
+    ///
+    /// - `instruction`: The instruction, fetched from the program counter.
+    fn execute(&mut self, instruction: Instruction) -> Result<Option<BusCycle>, String> {
+        let pc = self.pc;
+        let illegal = || {
+            format!(
+                "RV32I \"{}\": illegal instruction {:#010x} at {pc:#010x}",
+                self.name, instruction.0
+            )
+        };
+        let (rd, funct3, funct7) = (instruction.rd(), instruction.funct3(), instruction.funct7());
+        let rs1 = self.x[instruction.rs1()];
+        let rs2 = self.x[instruction.rs2()];
+        let mut next = pc.wrapping_add(4);
+        let mut access = None;
+
+        match instruction.opcode() {
+            LUI => self.set(rd, instruction.u_immediate()),
+            AUIPC => self.set(rd, pc.wrapping_add(instruction.u_immediate())),
+            JAL => {
+                self.set(rd, next);
+                next = pc.wrapping_add(instruction.j_immediate());
+            }
+            JALR if funct3 == 0 => {
+                self.set(rd, next);
+                next = rs1.wrapping_add(instruction.i_immediate()) & !1;
+            }
+            BRANCH => {
+                let taken = match funct3 {
+                    0 => rs1 == rs2,
+                    1 => rs1 != rs2,
+                    4 => (rs1 as i32) < (rs2 as i32),
+                    5 => (rs1 as i32) >= (rs2 as i32),
+                    6 => rs1 < rs2,
+                    7 => rs1 >= rs2,
+                    _ => return Err(illegal()),
+                };
+                if taken {
+                    next = pc.wrapping_add(instruction.b_immediate());
+                }
+            }
+            LOAD if matches!(funct3, 0 | 1 | 2 | 4 | 5) => {
+                let address = rs1.wrapping_add(instruction.i_immediate());
+                access = Some(BusCycle {
+                    address: self.aligned("load from", address, 1 << (funct3 & 0x03))?,
+                    transfer: Transfer::Load { rd, funct3 },
+                });
+            }
+            STORE if funct3 <= 2 => {
+                let size = 1 << funct3;
+                let address = rs1.wrapping_add(instruction.s_immediate());
+                let address = self.aligned("store to", address, size)?;
+                let lanes = (((1 << size) - 1) << (address & 0x03)) as u8;
+                let value = rs2 << (8 * (address & 0x03));
+                access = Some(BusCycle {
+                    address,
+                    transfer: if lanes == 0x0f {
+                        Transfer::Store { value, lanes }
+                    } else {
+                        Transfer::Merge { value, lanes }
+                    },
+                });
+            }
+            OP_IMM => {
+                let alternate = match (funct3, funct7) {
+                    (1, 0x00) | (5, 0x00) => false,
+                    (5, 0x20) => true,
+                    (1 | 5, _) => return Err(illegal()),
+                    _ => false,
+                };
+                self.set(rd, alu(funct3, alternate, rs1, instruction.i_immediate()));
+            }
+            OP => {
+                let alternate = match (funct3, funct7) {
+                    (_, 0x00) => false,
+                    (0 | 5, 0x20) => true,
+                    _ => return Err(illegal()),
+                };
+                self.set(rd, alu(funct3, alternate, rs1, rs2));
+            }
+            MISC_MEM if funct3 == 0 => (),
+            _ if matches!(instruction.0, ECALL | EBREAK) => {
+                self.halted = true;
+                self.instructions += 1;
+                return Ok(None);
+            }
+            _ => return Err(illegal()),
+        }
+
+        self.pc = self.aligned("jump to", next, 4)?;
+        self.instructions += 1;
+        Ok(Some(access.unwrap_or(BusCycle::fetch(self.pc))))
+    }
+
+    /// Complete a bus cycle, returning the next, or None if the core has halted.
+    ///
+    /// # Parameters
+    ///
+    /// - `cycle`: The bus cycle.
+    /// - `data`: The word on the data bus at the end of the cycle.
+    fn complete(&mut self, cycle: BusCycle, data: u32) -> Result<Option<BusCycle>, String> {
+        self.cycles += 1;
+        match cycle.transfer {
+            Transfer::Fetch => return self.execute(Instruction(data)),
+            Transfer::Load { rd, funct3 } => {
+                let shifted = data >> (8 * (cycle.address & 0x03));
+                let value = match funct3 {
+                    0 => shifted as i8 as u32,
+                    1 => shifted as i16 as u32,
+                    4 => shifted & 0xff,
+                    5 => shifted & 0xffff,
+                    _ => data,
+                };
+                self.set(rd, value);
+            }
+            Transfer::Merge { value, lanes } => {
+                let mask = lane_mask(lanes);
+                return Ok(Some(BusCycle {
+                    address: cycle.address,
+                    transfer: Transfer::Store {
+                        value: (data & !mask) | (value & mask),
+                        lanes,
+                    },
+                }));
+            }
+            Transfer::Store { .. } => (),
+        }
+
+        Ok(Some(BusCycle::fetch(self.pc)))
+    }
+}
+
+impl Element for Rv32iCore {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn input_pins(&self) -> Vec<InputPin> {
+        ["CLK", "/RESET"]
+            .into_iter()
+            .map(str::to_string)
+            .chain((0..32).map(|i| format!("D{i}")))
+            .map(|name| InputPin::new(&name))
+            .collect()
+    }
+
+    fn output_pins(&self) -> Vec<OutputPin> {
+        (2..32)
+            .map(|i| format!("A{i}"))
+            .chain((0..32).map(|i| format!("D{i}")))
+            .chain((0..4).map(|i| format!("/BE{i}")))
+            .chain(["/RD", "/WR"].into_iter().map(str::to_string))
+            .map(|name| {
+                OutputPin::new(
+                    &name,
+                    SimDuration::from_ticks(self.delay),
+                    OutputPinState::HighImpedance,
+                )
+            })
+            .collect()
+    }
+
+    fn step(
+        &mut self,
+        inputs: &[InputPin],
+        outputs: &mut [OutputPin],
+        _delta_t: u64,
+    ) -> Result<SimResult, String> {
+        let [clock, reset, data @ ..] = inputs else {
+            return Err(format!("RV32I \"{}\": unexpected pins", self.name));
+        };
+        if data.len() != 32 || outputs.len() != 68 {
+            return Err(format!("RV32I \"{}\": unexpected pins", self.name));
+        }
+        let (address_out, rest) = outputs.split_at_mut(30);
+        let (data_out, rest) = rest.split_at_mut(32);
+        let (lanes_out, control_out) = rest.split_at_mut(4);
+        let [rd_out, wr_out] = control_out else {
+            return Err(format!("RV32I \"{}\": unexpected pins", self.name));
+        };
+
+        if reset.state() == InputPinState::Low {
+            self.pc = self.reset;
+            self.cycle = None;
+            self.halted = false;
+        } else if !self.halted {
+            if clock.falling() {
+                self.latched = word(data).map_or(u32::MAX, |value| value as u32);
+            }
+            if clock.rising() {
+                self.cycle = match self.cycle {
+                    Some(cycle) => self.complete(cycle, self.latched)?,
+                    None => Some(BusCycle::fetch(self.pc)),
+                };
+            }
+        }
+
+        let (written, lanes) = match self.cycle.map(|cycle| cycle.transfer) {
+            Some(Transfer::Store { value, lanes }) => (Some(value), lanes),
+            Some(_) => (None, 0x0f),
+            None => (None, 0x00),
+        };
+        let address = self.cycle.map(|cycle| cycle.address);
+        for (i, output) in address_out.iter_mut().enumerate() {
+            output.drive(tristate(
+                address.map(|address| (address >> (i + 2)) & 1 != 0),
+            ));
+        }
+        for (i, output) in data_out.iter_mut().enumerate() {
+            output.drive(tristate(written.map(|value| (value >> i) & 1 != 0)));
+        }
+        for (i, output) in lanes_out.iter_mut().enumerate() {
+            output.drive(level(Some(lanes & (1 << i) == 0)));
+        }
+        let clock_high = clock.state() == InputPinState::High;
+        rd_out.drive(level(Some(
+            written.is_some() || address.is_none() || !clock_high,
+        )));
+        wr_out.drive(level(Some(written.is_none() || !clock_high)));
+
+        Ok(if self.halted {
+            SimResult::Finished
+        } else {
+            SimResult::Continuing
+        })
+    }
+
+    #[cfg(feature = "serde")]
+    fn save_state(&self) -> Result<serde_json::Value, String> {
+        super::save_state((
+            self.x,
+            self.pc,
+            self.cycle,
+            self.latched,
+            self.halted,
+            self.instructions,
+            self.cycles,
+        ))
+    }
+
+    #[cfg(feature = "serde")]
+    fn restore_state(&mut self, state: serde_json::Value) -> Result<(), String> {
+        (
+            self.x,
+            self.pc,
+            self.cycle,
+            self.latched,
+            self.halted,
+            self.instructions,
+            self.cycles,
+        ) = super::restore_state(&self.name, state)?;
+        Ok(())
+    }
+
+    fn box_clone(&self) -> Box<dyn Element> {
+        Box::new(self.clone())
+    }
+}
+
+/// Register every kind of processor.
+///
+/// The `mos6502` kind takes the `delay` parameter, and the `rv32i` kind takes the `delay` parameter and the `reset`
+/// parameter (default 0) giving the address of the first instruction run after reset.
+///
+/// # Parameters
+///
+/// - `registry`: The Registry to add the processors to.
+pub fn register(registry: &mut Registry) {
+    registry.register("mos6502", |name, parameters: &Parameters| {
+        Ok(Box::new(Mos6502::new(name, parameters.get_or("delay", 0)?)))
+    });
+    registry.register("rv32i", |name, parameters: &Parameters| {
+        Ok(Box::new(Rv32iCore::new(
+            name,
+            parameters.get_or("delay", 0)?,
+            parameters.get_or("reset", 0)?,
+        )))
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::element::testing::inputs;
+
+    /// A 6502 stepped against 64 KiB of memory, with its control inputs set directly.
+    struct Board {
+        /// The processor.
+        cpu: Mos6502,
+        /// The processor's outputs.
+        outputs: Vec<OutputPin>,
+        /// The memory.
+        memory: Vec<u8>,
+        /// The levels of the processor's inputs at the previous step.
+        previous: [f32; 13],
+        /// The levels of `/RES`, `/IRQ`, `/NMI` and `RDY`.
+        control: [bool; 4],
+        /// Number of clock cycles run.
+        clocks: u64,
+        /// The clock cycles and addresses at which `SYNC` was high.
+        syncs: Vec<(u64, u16)>,
+    }
+
+    impl Board {
+        /// Build a board with a program loaded at 0x8000, and the reset vector pointing to it.
+        fn new(program: &[u8]) -> Self {
+            let mut memory = vec![0; 0x10000];
+            memory[0x8000..0x8000 + program.len()].copy_from_slice(program);
+            memory[0xfffc] = 0x00;
+            memory[0xfffd] = 0x80;
+            let cpu = Mos6502::new("U1", 0);
+            Self {
+                outputs: cpu.output_pins(),
+                cpu,
+                memory,
+                previous: [0.0; 13],
+                control: [true; 4],
+                clocks: 0,
+                syncs: Vec::new(),
+            }
+        }
+
+        /// Obtain the address on the address bus.
+        fn address(&self) -> u16 {
+            (0..16).fold(0, |address, i| {
+                address | (u16::from(self.outputs[i].state() == OutputPinState::High) << i)
+            })
+        }
+
+        /// Step the processor through half a clock cycle.
+        fn half(&mut self, clock: bool) {
+            let reading = self.outputs[24].state() == OutputPinState::High;
+            let data = self.memory[usize::from(self.address())];
+            let mut levels = [0.0; 13];
+            levels[0] = f32::from(u8::from(clock));
+            for (i, active) in self.control.iter().enumerate() {
+                levels[i + 1] = f32::from(u8::from(*active));
+            }
+            for i in 0..8 {
+                levels[i + 5] = if reading {
+                    f32::from((data >> i) & 1)
+                } else {
+                    0.5
+                };
+            }
+            self.cpu
+                .step(&inputs(self.previous, levels), &mut self.outputs, 1)
+                .unwrap();
+            self.outputs
+                .iter_mut()
+                .for_each(|output| output.step(SimDuration::from_ticks(1)));
+            self.previous = levels;
+            if clock && self.outputs[24].state() == OutputPinState::Low {
+                let value = (0..8).fold(0, |value, i| {
+                    value | (u8::from(self.outputs[16 + i].state() == OutputPinState::High) << i)
+                });
+                let address = self.address();
+                self.memory[usize::from(address)] = value;
+            }
+        }
+
+        /// Run a number of clock cycles.
+        fn run(&mut self, cycles: u64) {
+            for _ in 0..cycles {
+                if self.outputs[25].state() == OutputPinState::High {
+                    self.syncs.push((self.clocks, self.address()));
+                }
+                self.half(true);
+                self.half(false);
+                self.clocks += 1;
+            }
+        }
+    }
+
+    #[test]
+    fn mos6502_program() {
+        // GIVEN a processor running a program which sums the numbers from 10 down to 1, through a subroutine
+        let mut board = Board::new(&[
+            0xa2, 0x0a, // LDX #10
+            0xa9, 0x00, // LDA #0
+            0x20, 0x21, 0x80, // loop: JSR add
+            0xca, // DEX
+            0xd0, 0xfa, // BNE loop
+            0x8d, 0x00, 0x02, // STA $0200
+            0x48, // PHA
+            0x68, // PLA
+            0xa9, 0x19, // LDA #$19
+            0xf8, // SED
+            0x69, 0x28, // ADC #$28
+            0x8d, 0x01, 0x02, // STA $0201
+            0x38, // SEC
+            0xe9, 0x49, // SBC #$49
+            0x8d, 0x02, 0x02, // STA $0202
+            0x4c, 0x1d, 0x80, // JMP *
+            0x00, // BRK (not reached)
+            0x86, 0x10, // add: STX $10
+            0x18, // CLC
+            0x65, 0x10, // ADC $10
+            0x60, // RTS
+        ]);
+        // WHEN it runs
+        board.control[0] = false;
+        board.run(3);
+        board.control[0] = true;
+        board.run(1000);
+        // THEN the binary and decimal results are stored
+        assert_eq!([0x37, 0x47, 0x98], board.memory[0x0200..0x0203]);
+        assert_eq!(0x801d, board.cpu.registers().pc);
+        assert_eq!(0xfd, board.cpu.registers().s);
+        assert_eq!(Some(&(3 + 7, 0x8000)), board.syncs.first());
+    }
+    #[test]
+    fn mos6502_cycle_counts() {
+        // GIVEN a processor running instructions with known cycle counts
+        let mut board = Board::new(&[
+            0xa2, 0x01, // LDX #1: 2 cycles
+            0xbd, 0xff, 0x80, // LDA $80FF,X: 5 cycles, crossing a page
+            0xbd, 0x00, 0x80, // LDA $8000,X: 4 cycles
+            0x9d, 0x00, 0x02, // STA $0200,X: 5 cycles
+            0xe6, 0x10, // INC $10: 5 cycles
+            0x1e, 0x00, 0x02, // ASL $0200,X: 7 cycles
+            0xd0, 0x00, // BNE *+2: 3 cycles, taken
+            0xf0, 0x00, // BEQ *+2: 2 cycles, not taken
+            0x08, // PHP: 3 cycles
+            0x28, // PLP: 4 cycles
+            0x6c, 0x00, 0x03, // JMP ($0300): 5 cycles
+        ]);
+        board.memory[0x0300] = 0x00;
+        board.memory[0x0301] = 0x90;
+        // WHEN it runs
+        board.run(100);
+        // THEN each instruction takes the same number of cycles as on the real part
+        let lengths: Vec<u64> = board
+            .syncs
+            .windows(2)
+            .map(|pair| pair[1].0 - pair[0].0)
+            .take(11)
+            .collect();
+        assert_eq!(vec![2, 5, 4, 5, 5, 7, 3, 2, 3, 4, 5], lengths);
+        assert_eq!(0x9000, board.syncs[11].1);
+        assert_eq!(0x02, board.memory[0x0201]);
+    }
+    #[test]
+    fn mos6502_interrupts() {
+        // GIVEN a processor with interrupts enabled, running an idle loop, with interrupt handlers counting calls
+        let mut board = Board::new(&[
+            0x58, // CLI
+            0x4c, 0x01, 0x80, // JMP *
+            0xee, 0x00, 0x03, // irq: INC $0300
+            0x40, // RTI
+            0xee, 0x01, 0x03, // nmi: INC $0301
+            0x40, // RTI
+        ]);
+        board.memory[0xfffe] = 0x04;
+        board.memory[0xffff] = 0x80;
+        board.memory[0xfffa] = 0x08;
+        board.memory[0xfffb] = 0x80;
+        board.run(20);
+        // WHEN an interrupt is requested until it is taken, and a non-maskable interrupt is requested twice
+        board.control[1] = false;
+        board.run(10);
+        board.control[1] = true;
+        board.run(20);
+        for _ in 0..2 {
+            board.control[2] = false;
+            board.run(1);
+            board.control[2] = true;
+            board.run(30);
+        }
+        // THEN each handler runs as many times as it was requested, and the loop resumes
+        assert_eq!([1, 2], board.memory[0x0300..0x0302]);
+        assert_eq!(0x8001, board.syncs.last().unwrap().1);
+        assert_eq!(0, board.cpu.registers().p & INTERRUPT);
+    }
+    #[test]
+    fn mos6502_ready() {
+        // GIVEN two processors running the same program
+        let program = [0xa9, 0x5a, 0x8d, 0x00, 0x02, 0x4c, 0x05, 0x80];
+        let mut free = Board::new(&program);
+        let mut stalled = Board::new(&program);
+        // WHEN one is stalled by RDY for a while during its first instruction
+        free.run(20);
+        stalled.run(8);
+        stalled.control[3] = false;
+        let address = stalled.address();
+        stalled.run(10);
+        // THEN it holds its address until it is ready again, and finishes the same program later
+        assert_eq!(address, stalled.address());
+        stalled.control[3] = true;
+        stalled.run(12);
+        assert_eq!(0x5a, stalled.memory[0x0200]);
+        assert_eq!(free.cpu.cycles(), stalled.cpu.cycles());
+    }
+    #[test]
+    fn mos6502_registered() {
+        // GIVEN the standard registry
+        let registry = Registry::standard();
+        // WHEN a processor is created
+        let cpu = registry.create("mos6502", "U1", &Parameters::new().with("delay", "5"));
+        // THEN it has the pins of the real part
+        let cpu = cpu.unwrap();
+        assert_eq!(13, cpu.input_pins().len());
+        assert_eq!("PHI2", cpu.output_pins()[26].name());
+        assert!(registry
+            .create("mos6502", "U2", &Parameters::new().with("delay", "-1"))
+            .is_err());
+    }
+
+    /// An RV32I core stepped against 64 KiB of memory, 32 bits wide, with its reset input set directly.
+    struct Rv32iBoard {
+        /// The core.
+        cpu: Rv32iCore,
+        /// The core's outputs.
+        outputs: Vec<OutputPin>,
+        /// The memory, indexed by word address.
+        memory: Vec<u32>,
+        /// The levels of the core's inputs at the previous step.
+        previous: [f32; 34],
+        /// The level of `/RESET`.
+        reset: bool,
+    }
+
+    impl Rv32iBoard {
+        /// Build a board with a program loaded at address zero.
+        fn new(program: &[u32]) -> Self {
+            let mut memory = vec![0; 0x4000];
+            memory[..program.len()].copy_from_slice(program);
+            let cpu = Rv32iCore::new("U1", 0, 0);
+            Self {
+                outputs: cpu.output_pins(),
+                cpu,
+                memory,
+                previous: [0.0; 34],
+                reset: true,
+            }
+        }
+
+        /// Obtain the word driven onto a range of the core's outputs.
+        fn driven(&self, range: core::ops::Range<usize>) -> u32 {
+            self.outputs[range]
+                .iter()
+                .enumerate()
+                .fold(0, |value, (i, output)| {
+                    value | (u32::from(output.state() == OutputPinState::High) << i)
+                })
+        }
+
+        /// Step the core through half a clock cycle.
+        fn half(&mut self, clock: bool) -> Result<SimResult, String> {
+            let reading = self.outputs[66].state() == OutputPinState::Low;
+            let address = self.driven(0..30) as usize % self.memory.len();
+            let mut levels = [0.0; 34];
+            levels[0] = f32::from(u8::from(clock));
+            levels[1] = f32::from(u8::from(self.reset));
+            for i in 0..32 {
+                levels[i + 2] = if reading {
+                    ((self.memory[address] >> i) & 1) as f32
+                } else {
+                    0.5
+                };
+            }
+            let result = self
+                .cpu
+                .step(&inputs(self.previous, levels), &mut self.outputs, 1);
+            self.outputs
+                .iter_mut()
+                .for_each(|output| output.step(SimDuration::from_ticks(1)));
+            self.previous = levels;
+            if self.outputs[67].state() == OutputPinState::Low {
+                let mask = lane_mask(!self.driven(62..66) as u8 & 0x0f);
+                let address = self.driven(0..30) as usize % self.memory.len();
+                self.memory[address] =
+                    (self.memory[address] & !mask) | (self.driven(30..62) & mask);
+            }
+            result
+        }
+
+        /// Run a number of clock cycles, stopping early if the core finishes the simulation.
+        fn run(&mut self, cycles: u64) -> Result<SimResult, String> {
+            for _ in 0..cycles {
+                for clock in [true, false] {
+                    if self.half(clock)? == SimResult::Finished {
+                        return Ok(SimResult::Finished);
+                    }
+                }
+            }
+            Ok(SimResult::Continuing)
+        }
+    }
+
+    #[test]
+    fn rv32i_decode() {
+        // GIVEN instructions with immediates of each type, and operands for the ALU
+        // WHEN they are decoded and operated on
+        // THEN the immediates are sign-extended and the operations follow the instruction set
+        assert_eq!(0xffff_ffff, Instruction(0xfff0_8093).i_immediate());
+        assert_eq!(0xffff_fffc, Instruction(0xfe11_2e23).s_immediate());
+        assert_eq!(5, Instruction(0x0041_82a3).s_immediate());
+        assert_eq!(0xffff_fff8, Instruction(0xfe00_9ce3).b_immediate());
+        assert_eq!(4094, Instruction(0x7e00_0fe3).b_immediate());
+        assert_eq!(0x1000, Instruction(0x0000_11b7).u_immediate());
+        assert_eq!(8, Instruction(0x0080_03ef).j_immediate());
+        assert_eq!(0xffff_f800, Instruction(0x801f_f06f).j_immediate());
+        assert_eq!(0xffff_fffe, alu(0, true, 1, 3));
+        assert_eq!(0xf800_0000, alu(5, true, 0x8000_0000, 0x404));
+        assert_eq!(0x0800_0000, alu(5, false, 0x8000_0000, 4));
+        assert_eq!(1, alu(2, false, 0xffff_ffff, 0));
+        assert_eq!(0, alu(3, false, 0xffff_ffff, 0));
+        assert_eq!(0x0000_ff00, lane_mask(0b0010));
+    }
+    #[test]
+    fn rv32i_program() {
+        // GIVEN a core running a program which sums the numbers from 10 down to 1, then stores and loads bytes and
+        // halfwords through a subroutine
+        let mut board = Rv32iBoard::new(&[
+            0x00a0_0093, // addi x1, x0, 10
+            0x0000_0113, // addi x2, x0, 0
+            0x0011_0133, // loop: add x2, x2, x1
+            0xfff0_8093, // addi x1, x1, -1
+            0xfe00_9ce3, // bne x1, x0, loop
+            0x0000_11b7, // lui x3, 0x1
+            0x0021_a023, // sw x2, 0(x3)
+            0xffe0_0213, // addi x4, x0, -2
+            0x0041_82a3, // sb x4, 5(x3)
+            0x0051_8283, // lb x5, 5(x3)
+            0x0051_c303, // lbu x6, 5(x3)
+            0x0080_03ef, // jal x7, store
+            0x0010_0073, // ebreak
+            0x0041_1413, // store: slli x8, x2, 4
+            0x0081_9323, // sh x8, 6(x3)
+            0x0003_8067, // jalr x0, 0(x7)
+        ]);
+        // WHEN it runs until it halts
+        let result = board.run(1000);
+        // THEN the results are stored and loaded, and it halts at the break, finishing the simulation
+        assert_eq!(Ok(SimResult::Finished), result);
+        assert_eq!([55, 0x0370_fe00], board.memory[0x400..0x402]);
+        let registers = board.cpu.registers();
+        assert_eq!([0xffff_fffe, 0xfe, 0x30], registers[5..8]);
+        assert_eq!(0, registers[0]);
+        assert_eq!(0x30, board.cpu.pc());
+        assert!(board.cpu.halted());
+    }
+    #[test]
+    fn rv32i_bus_cycles() {
+        // GIVEN a core running a load, a word store and a byte store
+        let mut board = Rv32iBoard::new(&[
+            0x1000_0093, // addi x1, x0, 0x100
+            0x0000_a103, // lw x2, 0(x1)
+            0x0020_a223, // sw x2, 4(x1)
+            0x0020_84a3, // sb x2, 9(x1)
+            0x0010_0073, // ebreak
+        ]);
+        board.memory[0x40] = 0x1234_5678;
+        board.memory[0x42] = 0xaaaa_aaaa;
+        // WHEN it runs until it halts, then is reset and runs again
+        board.run(100).unwrap();
+        let cycles = board.cpu.cycles();
+        board.reset = false;
+        board.run(2).unwrap();
+        let released = board.outputs[0].state();
+        board.reset = true;
+        board.run(100).unwrap();
+        // THEN each access takes a further cycle, a byte store two as it merges into the word, and reset releases the
+        // address bus and starts the program again
+        assert_eq!(1 + 2 + 2 + 3 + 1, cycles);
+        assert_eq!([0x1234_5678, 0xaaaa_78aa], board.memory[0x41..0x43]);
+        assert_eq!(OutputPinState::HighImpedance, released);
+        assert_eq!(10, board.cpu.instructions());
+    }
+    #[test]
+    fn rv32i_faults() {
+        // GIVEN cores running an illegal instruction and a misaligned load
+        let mut illegal = Rv32iBoard::new(&[0xffff_ffff]);
+        let mut misaligned = Rv32iBoard::new(&[
+            0x0020_0093, // addi x1, x0, 2
+            0x0000_a103, // lw x2, 0(x1)
+        ]);
+        // WHEN they run
+        // THEN each fails with the reason and the address of the instruction
+        assert_eq!(
+            Err("RV32I \"U1\": illegal instruction 0xffffffff at 0x00000000".to_string()),
+            illegal.run(10)
+        );
+        assert_eq!(
+            Err("RV32I \"U1\": misaligned load from 0x00000002 at 0x00000004".to_string()),
+            misaligned.run(10)
+        );
+    }
+    #[cfg(feature = "std")]
+    #[test]
+    fn rv32i_memories() {
+        use crate::element::clocks::ClockGenerator;
+        use crate::element::gates::{Gate, GateKind};
+        use crate::element::memory::{Rom, Sram};
+        use crate::sim::Simulation;
+        use crate::wire::{Wire, WirePull};
+
+        // GIVEN a core running firmware from a ROM at 0x0000, storing into an SRAM at 0x1000 selected through an
+        // inverter, and halting
+        let path = std::env::temp_dir().join(format!("rvfs-sim-rv32i-{}.hex", std::process::id()));
+        let mut sram = Sram::new("U3", 32, 16, 0, 0, 0).unwrap();
+        sram.set_dump(&path);
+        let firmware = vec![
+            0x0000_11b7, // lui x3, 0x1
+            0x0370_0093, // addi x1, x0, 55
+            0x0011_a023, // sw x1, 0(x3)
+            0x0011_82a3, // sb x1, 5(x3)
+            0x0001_a103, // lw x2, 0(x3)
+            0x0011_0113, // addi x2, x2, 1
+            0x0021_a423, // sw x2, 8(x3)
+            0x0010_0073, // ebreak
+        ];
+        let mut sim = Simulation::new(SimDuration::from_ticks(10));
+        let mut add = |element: Box<dyn Element>| sim.add_element(element).unwrap();
+        let cpu = add(Box::new(Rv32iCore::new("U1", 0, 0)));
+        let rom = add(Box::new(Rom::new("U2", 32, 64, 0, firmware).unwrap()));
+        let ram = add(Box::new(sram));
+        let inverter = add(Box::new(Gate::new("U4", GateKind::Not, 1, 0).unwrap()));
+        let clock = add(Box::new(
+            ClockGenerator::new("X1", 100.0, 0.5, 0.0, 0, 0.0).unwrap(),
+        ));
+        let mut net = |name: &str, pull, outputs: &[(_, &str)], inputs: &[(_, &str)]| {
+            let wire = sim.add_wire(Wire::new(name, pull)).unwrap();
+            for (element, pin) in outputs {
+                sim.connect_output(sim.output_pin(*element, pin).unwrap(), wire)
+                    .unwrap();
+            }
+            for (element, pin) in inputs {
+                sim.connect_input(wire, sim.input_pin(*element, pin).unwrap())
+                    .unwrap();
+            }
+        };
+        net("CLK", WirePull::Down, &[(clock, "CLK")], &[(cpu, "CLK")]);
+        net("/RESET", WirePull::Up, &[], &[(cpu, "/RESET")]);
+        for i in 0..32 {
+            let d = format!("D{i}");
+            let pins = [(cpu, d.as_str()), (ram, d.as_str())];
+            net(&d, WirePull::Down, &[pins[0], pins[1], (rom, &d)], &pins);
+        }
+        for i in 2..8 {
+            let (a, memory) = (format!("A{i}"), format!("A{}", i - 2));
+            let inputs = [(rom, memory.as_str()), (ram, memory.as_str())];
+            net(
+                &a,
+                WirePull::Down,
+                &[(cpu, &a)],
+                &inputs[..if i < 6 { 2 } else { 1 }],
+            );
+        }
+        net(
+            "A12",
+            WirePull::Down,
+            &[(cpu, "A12")],
+            &[(rom, "/CS"), (inverter, "I0")],
+        );
+        net("/RAMCS", WirePull::Up, &[(inverter, "Y")], &[(ram, "/CS")]);
+        net(
+            "/RD",
+            WirePull::Up,
+            &[(cpu, "/RD")],
+            &[(rom, "/OE"), (ram, "/OE")],
+        );
+        net("/WR", WirePull::Up, &[(cpu, "/WR")], &[(ram, "/WE")]);
+        // WHEN it runs until it halts
+        let result = sim.run_for(1000);
+        sim.finish_elements().unwrap();
+        // THEN the words are stored, the byte merged into a word never written, whose other bytes the SRAM drives
+        // unknown and the core reads as ones, and the simulation finishes
+        let image = std::fs::read_to_string(&path);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(Ok(SimResult::Finished), result);
+        assert_eq!("00000037\nffff37ff\n00000038\n", image.unwrap());
+    }
+    #[test]
+    fn rv32i_registered() {
+        // GIVEN the standard registry
+        let registry = Registry::standard();
+        // WHEN a core is created with a reset address
+        let cpu = registry.create("rv32i", "U1", &Parameters::new().with("reset", "4096"));
+        // THEN it has the clock, reset and bus pins
+        let cpu = cpu.unwrap();
+        assert_eq!(34, cpu.input_pins().len());
+        assert_eq!(68, cpu.output_pins().len());
+        assert_eq!("/BE0", cpu.output_pins()[62].name());
+        assert!(registry
+            .create("rv32i", "U2", &Parameters::new().with("reset", "-1"))
+            .is_err());
+    }
+}
@@ -0,0 +1,346 @@
+//! Pulse-width modulation generators, as stimulus sources for motor drivers, LED dimmers and similar circuits.
+//!
+//! As with [clock generators](crate::element::clocks::ClockGenerator), generators are specified in simulation time
+//! units, and a frequency may be given instead of a period when registering one, taking a time unit to be one
+//! nanosecond.  The duty cycle of the output can be checked with a [duty monitor](crate::monitor::Rule::Duty).
+
+use crate::element::{Element, Parameters, Registry, UNITS_PER_SECOND};
+use crate::ipin::InputPin;
+use crate::opin::{OutputPin, OutputPinState};
+use crate::prelude::*;
+use crate::sim::SimResult;
+use crate::time::SimDuration;
+
+/// A free-running PWM generator with a configurable period, duty cycle and phase offset, and complementary outputs
+/// separated by a dead time.
+///
+/// The generator has no inputs, and its outputs are named `OUT` and `/OUT`, as for the high and low side switches of a
+/// half bridge.  `OUT` is high for the duty fraction of each period, and `/OUT` for the rest, except that each output
+/// is held low for the dead time after the other falls, so the two are never high together.  A duty cycle of 0 or 1
+/// holds the outputs steady, with no dead time.
+///
+/// As with a [ClockGenerator](crate::element::clocks::ClockGenerator), the outputs are computed from the total
+/// simulation time, and their edges are resolved to the step interval of the Simulation.
+///
+/// # Example
+///
+/// ```
+/// # use rvfs_sim_core::element::pwm::PwmGenerator;
+/// let pwm = PwmGenerator::new("U1", 100.0, 0.25, 0.0, 5.0).unwrap();
+///
+/// assert_eq!((false, false), pwm.levels_at(0));
+/// assert_eq!((true, false), pwm.levels_at(10));
+/// assert_eq!((false, false), pwm.levels_at(27));
+/// assert_eq!((false, true), pwm.levels_at(50));
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct PwmGenerator {
+    /// Name of the generator.
+    name: String,
+    /// Period of the outputs.
+    period: f64,
+    /// Fraction of each period for which `OUT` would be high without dead time.
+    duty: f64,
+    /// Fraction of a period by which the outputs lead.
+    phase: f64,
+    /// Time for which each output is held low after the other falls.
+    dead_time: f64,
+    /// Simulation time of the present step.
+    time: u64,
+}
+
+impl PwmGenerator {
+    /// Create a new PwmGenerator.
+    ///
+    /// # Parameters
+    ///
+    /// - `name`: Name of the generator.
+    /// - `period`: Period of the outputs, which must be positive.
+    /// - `duty`: Fraction of each period for which `OUT` is high without dead time, from 0 to 1.
+    /// - `phase`: Phase offset in degrees, from 0 to 360 exclusive, by which the outputs lead.
+    /// - `dead_time`: Time for which each output is held low after the other falls, which must be non-negative and
+    ///   less than half the period.
+    pub fn new(
+        name: &str,
+        period: f64,
+        duty: f64,
+        phase: f64,
+        dead_time: f64,
+    ) -> Result<Self, String> {
+        if !(period.is_finite() && period > 0.0) {
+            return Err(format!("PWM generator \"{name}\": period must be positive"));
+        }
+        if !(0.0..=1.0).contains(&duty) {
+            return Err(format!(
+                "PWM generator \"{name}\": duty cycle must be from 0 to 1"
+            ));
+        }
+        if !(0.0..360.0).contains(&phase) {
+            return Err(format!(
+                "PWM generator \"{name}\": phase must be from 0 to 360 degrees"
+            ));
+        }
+        if !(dead_time >= 0.0 && dead_time * 2.0 < period) {
+            return Err(format!(
+                "PWM generator \"{name}\": dead time must be less than half the period"
+            ));
+        }
+
+        Ok(Self {
+            name: name.to_string(),
+            period,
+            duty,
+            phase: phase / 360.0,
+            dead_time,
+            time: 0,
+        })
+    }
+
+    /// Obtain the fraction of each period for which `OUT` is high without dead time.
+    pub fn duty(&self) -> f64 {
+        self.duty
+    }
+
+    /// Calculate the levels of `OUT` and `/OUT` at a simulation time.
+    ///
+    /// # Parameters
+    ///
+    /// - `time`: The simulation time.
+    pub fn levels_at(&self, time: u64) -> (bool, bool) {
+        if self.duty == 0.0 || self.duty == 1.0 {
+            return (self.duty == 1.0, self.duty == 0.0);
+        }
+        let offset = (time as f64 + self.phase * self.period) % self.period;
+        let fall = self.duty * self.period;
+        (
+            offset >= self.dead_time && offset < fall,
+            offset >= fall + self.dead_time,
+        )
+    }
+}
+
+impl Element for PwmGenerator {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn input_pins(&self) -> Vec<InputPin> {
+        Vec::new()
+    }
+
+    fn output_pins(&self) -> Vec<OutputPin> {
+        ["OUT", "/OUT"]
+            .into_iter()
+            .map(|name| OutputPin::new(name, SimDuration::ZERO, OutputPinState::Low))
+            .collect()
+    }
+
+    fn step(
+        &mut self,
+        _inputs: &[InputPin],
+        outputs: &mut [OutputPin],
+        delta_t: u64,
+    ) -> Result<SimResult, String> {
+        let [out, inverted] = outputs else {
+            return Err(format!(
+                "PWM generator \"{}\": expected 2 outputs",
+                self.name
+            ));
+        };
+
+        let (high, low) = self.levels_at(self.time);
+        for (output, level) in [(out, high), (inverted, low)] {
+            output.drive(if level {
+                OutputPinState::High
+            } else {
+                OutputPinState::Low
+            });
+        }
+        self.time += delta_t;
+
+        Ok(SimResult::Continuing)
+    }
+
+    #[cfg(feature = "serde")]
+    fn save_state(&self) -> Result<serde_json::Value, String> {
+        super::save_state(self.time)
+    }
+
+    #[cfg(feature = "serde")]
+    fn restore_state(&mut self, state: serde_json::Value) -> Result<(), String> {
+        self.time = super::restore_state(&self.name, state)?;
+        Ok(())
+    }
+
+    fn box_clone(&self) -> Box<dyn Element> {
+        Box::new(self.clone())
+    }
+}
+
+/// Register every kind of PWM element.
+///
+/// The `pwm` kind takes either the `period` or the `frequency` parameter, with a frequency in hertz taking a time unit
+/// to be one nanosecond.  It also takes the `duty` (default 0.5), `phase` (degrees, default 0) and `dead_time`
+/// (default 0) parameters.
+///
+/// # Parameters
+///
+/// - `registry`: The Registry to add the PWM elements to.
+pub fn register(registry: &mut Registry) {
+    registry.register("pwm", |name, parameters: &Parameters| {
+        let period = match (
+            parameters.get_or("period", 0.0)?,
+            parameters.get_or("frequency", 0.0)?,
+        ) {
+            (period, 0.0) => period,
+            (0.0, frequency) => UNITS_PER_SECOND / frequency,
+            _ => {
+                return Err(format!(
+                    "PWM generator \"{name}\": give either a period or a frequency"
+                ))
+            }
+        };
+        Ok(Box::new(PwmGenerator::new(
+            name,
+            period,
+            parameters.get_or("duty", 0.5)?,
+            parameters.get_or("phase", 0.0)?,
+            parameters.get_or("dead_time", 0.0)?,
+        )?))
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(feature = "std")]
+    use crate::monitor::{Monitors, Rule};
+    #[cfg(feature = "std")]
+    use crate::sim::Simulation;
+    #[cfg(feature = "std")]
+    use crate::trace::Tracer;
+    #[cfg(feature = "std")]
+    use crate::wire::{Wire, WirePull};
+
+    /// Sample the outputs of a generator over a period, as `0` and `1` for `OUT` then `/OUT` at each time.
+    fn sample(pwm: &PwmGenerator, times: impl Iterator<Item = u64>) -> String {
+        times
+            .map(|time| match pwm.levels_at(time) {
+                (false, false) => "00 ",
+                (true, false) => "10 ",
+                (false, true) => "01 ",
+                (true, true) => "11 ",
+            })
+            .collect::<String>()
+            .trim_end()
+            .to_string()
+    }
+
+    #[test]
+    fn pwm_dead_time() {
+        // GIVEN a generator with a 30% duty cycle and dead time
+        let pwm = PwmGenerator::new("U1", 100.0, 0.3, 0.0, 10.0).unwrap();
+        // WHEN its outputs are sampled over a period
+        // THEN each output rises only after the dead time following the other's fall
+        assert_eq!(
+            "00 10 10 00 01 01 01 01 01 01 00",
+            sample(&pwm, (0..=100).step_by(10))
+        );
+    }
+    #[test]
+    fn pwm_phase_and_extremes() {
+        // GIVEN generators with a phase offset, and with duty cycles of 0 and 1
+        let shifted = PwmGenerator::new("U1", 100.0, 0.5, 90.0, 0.0).unwrap();
+        let off = PwmGenerator::new("U2", 100.0, 0.0, 0.0, 10.0).unwrap();
+        let on = PwmGenerator::new("U3", 100.0, 1.0, 0.0, 10.0).unwrap();
+        // WHEN their outputs are sampled
+        // THEN the phase offset leads the outputs, and the extremes hold steady
+        assert_eq!("10 01 01 10", sample(&shifted, (0..100).step_by(25)));
+        assert_eq!("01 01 01 01", sample(&off, (0..100).step_by(25)));
+        assert_eq!("10 10 10 10", sample(&on, (0..100).step_by(25)));
+    }
+    #[test]
+    fn pwm_invalid() {
+        // GIVEN invalid settings
+        // WHEN generators are created with them
+        // THEN they are rejected
+        assert!(PwmGenerator::new("U1", 0.0, 0.5, 0.0, 0.0).is_err());
+        assert!(PwmGenerator::new("U1", 100.0, 1.5, 0.0, 0.0).is_err());
+        assert!(PwmGenerator::new("U1", 100.0, 0.5, 360.0, 0.0).is_err());
+        assert_eq!(
+            Err("PWM generator \"U1\": dead time must be less than half the period".to_string()),
+            PwmGenerator::new("U1", 100.0, 0.5, 0.0, 50.0)
+        );
+    }
+    #[cfg(feature = "std")]
+    #[test]
+    fn pwm_duty_monitored() {
+        // GIVEN a generator in a Simulation, with monitors of the duty cycles of its outputs
+        let mut sim = Simulation::new(SimDuration::from_ticks(10));
+        let pwm = sim
+            .add_element(Box::new(
+                PwmGenerator::new("U1", 400.0, 0.25, 0.0, 20.0).unwrap(),
+            ))
+            .unwrap();
+        let high = sim.add_wire(Wire::new("HI", WirePull::Down)).unwrap();
+        let low = sim.add_wire(Wire::new("LO", WirePull::Down)).unwrap();
+        for (name, wire) in [("OUT", high), ("/OUT", low)] {
+            let pin = sim.output_pin(pwm, name).unwrap();
+            sim.connect_output(pin, wire).unwrap();
+        }
+        let monitors = Monitors::new();
+        monitors.add(
+            "high side",
+            Rule::Duty {
+                wire: high,
+                duty: 0.2,
+                tolerance: 0.01,
+            },
+        );
+        monitors.add(
+            "low side",
+            Rule::Duty {
+                wire: low,
+                duty: 0.25,
+                tolerance: 0.01,
+            },
+        );
+        // WHEN it runs for several periods
+        let mut tracer = monitors.clone();
+        tracer.start(&sim).unwrap();
+        for _ in 0..200 {
+            sim.step().unwrap();
+            tracer.record(&sim, sim.changes()).unwrap();
+        }
+        // THEN the duty cycle of each complete period is measured, with the dead time shortening both outputs
+        let statuses = monitors.statuses();
+        assert_eq!(4, statuses[0].passes);
+        assert_eq!(Some(0.2), statuses[0].measurement);
+        assert_eq!(Some(0.7), statuses[1].measurement);
+        assert!(!statuses[1].passed());
+    }
+    #[test]
+    fn pwm_registered() {
+        // GIVEN the standard registry
+        let registry = Registry::standard();
+        // WHEN generators are created with good and bad parameters
+        let good = registry.create(
+            "pwm",
+            "U1",
+            &Parameters::new()
+                .with("frequency", "20000")
+                .with("duty", "0.75")
+                .with("dead_time", "500"),
+        );
+        let bad = registry.create(
+            "pwm",
+            "U2",
+            &Parameters::new()
+                .with("period", "1000")
+                .with("frequency", "20000"),
+        );
+        // THEN only the good parameters are accepted
+        assert_eq!("/OUT", good.unwrap().output_pins()[1].name());
+        assert!(bad.is_err());
+    }
+}
@@ -0,0 +1,314 @@
+//! Multi-bit registers.
+
+use crate::element::{bit, level, Element, Parameters, Registry};
+use crate::ipin::{InputPin, InputPinState};
+use crate::opin::{OutputPin, OutputPinState};
+use crate::prelude::*;
+use crate::sim::SimResult;
+use crate::time::SimDuration;
+use core::str::FromStr;
+
+/// Default width of a shift register instantiated from a configuration file.
+const DEFAULT_WIDTH: usize = 8;
+
+/// The direction in which a ShiftRegister moves its bits.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum ShiftDirection {
+    /// Serial data enters at `Q0` and moves towards the highest numbered output.
+    Right,
+    /// Serial data enters at the highest numbered output and moves towards `Q0`.
+    Left,
+}
+
+impl FromStr for ShiftDirection {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "right" => Ok(ShiftDirection::Right),
+            "left" => Ok(ShiftDirection::Left),
+            _ => Err(format!("Invalid shift direction \"{s}\"")),
+        }
+    }
+}
+
+/// A positive edge-triggered shift register with serial and synchronous parallel load, and asynchronous clear.
+///
+/// The inputs are named `CLK`, `SER`, `/LOAD`, `/CLR` and `P0` to `Pn`, and the outputs are named `Q0` to `Qn`.  On
+/// each rising edge of `CLK` the register loads `P0` to `Pn` if `/LOAD` is low, and otherwise shifts by one bit in its
+/// direction, taking the new bit from `SER`.  The last output in the shift direction serves as the serial output.
+/// Holding `/CLR` low clears every bit regardless of the clock, and an unconnected `/CLR` is treated as inactive.
+///
+/// Every bit is unknown until the register is first cleared or loaded, and unknown bits are shifted along like any
+/// other.  An indeterminate `SER` or parallel input at a clock edge makes the bit it feeds unknown, and an
+/// indeterminate `/LOAD` makes every bit unknown.  While a bit is unknown its output is driven unknown (X).
+///
+/// # Example
+///
+/// ```
+/// # use rvfs_sim_core::element::registers::{ShiftDirection, ShiftRegister};
+/// # use rvfs_sim_core::element::Element;
+/// let register = ShiftRegister::new("U1", 4, ShiftDirection::Right, 0).unwrap();
+///
+/// assert_eq!(8, register.input_pins().len());
+/// assert_eq!("Q3", register.output_pins()[3].name());
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct ShiftRegister {
+    /// Name of the register.
+    name: String,
+    /// Direction in which bits are shifted.
+    direction: ShiftDirection,
+    /// Propagation delay from a clock edge or the clear input to the outputs.
+    delay: u64,
+    /// The stored bits, if known, from `Q0` upwards.
+    bits: Vec<Option<bool>>,
+}
+
+impl ShiftRegister {
+    /// Create a new ShiftRegister.
+    ///
+    /// # Parameters
+    ///
+    /// - `name`: Name of the register.
+    /// - `width`: Number of bits, which must be at least one.
+    /// - `direction`: Direction in which bits are shifted.
+    /// - `delay`: Propagation delay from a clock edge or the clear input to the outputs.
+    pub fn new(
+        name: &str,
+        width: usize,
+        direction: ShiftDirection,
+        delay: u64,
+    ) -> Result<Self, String> {
+        if width == 0 {
+            return Err(format!(
+                "Shift register \"{name}\": width must be at least one bit"
+            ));
+        }
+
+        Ok(Self {
+            name: name.to_string(),
+            direction,
+            delay,
+            bits: vec![None; width],
+        })
+    }
+
+    /// Obtain the stored bits, if known, from `Q0` upwards.
+    pub fn bits(&self) -> &[Option<bool>] {
+        &self.bits
+    }
+}
+
+impl Element for ShiftRegister {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn input_pins(&self) -> Vec<InputPin> {
+        let control = ["CLK", "SER", "/LOAD", "/CLR"]
+            .into_iter()
+            .map(InputPin::new);
+        let parallel = (0..self.bits.len()).map(|i| InputPin::new(&format!("P{i}")));
+        control.chain(parallel).collect()
+    }
+
+    fn output_pins(&self) -> Vec<OutputPin> {
+        (0..self.bits.len())
+            .map(|i| {
+                OutputPin::new(
+                    &format!("Q{i}"),
+                    SimDuration::from_ticks(self.delay),
+                    OutputPinState::HighImpedance,
+                )
+            })
+            .collect()
+    }
+
+    fn step(
+        &mut self,
+        inputs: &[InputPin],
+        outputs: &mut [OutputPin],
+        _delta_t: u64,
+    ) -> Result<SimResult, String> {
+        let width = self.bits.len();
+        let [clk, ser, load, clear, parallel @ ..] = inputs else {
+            return Err(format!(
+                "Shift register \"{}\": expected {} inputs",
+                self.name,
+                width + 4
+            ));
+        };
+        if parallel.len() != width || outputs.len() != width {
+            return Err(format!(
+                "Shift register \"{}\": expected {width} parallel inputs and outputs",
+                self.name
+            ));
+        }
+
+        if clear.state() == InputPinState::Low {
+            self.bits.fill(Some(false));
+        } else if clk.rising() {
+            match load.state() {
+                InputPinState::Low => {
+                    for (stored, input) in self.bits.iter_mut().zip(parallel) {
+                        *stored = bit(input);
+                    }
+                }
+                InputPinState::High => match self.direction {
+                    ShiftDirection::Right => {
+                        self.bits.rotate_right(1);
+                        self.bits[0] = bit(ser);
+                    }
+                    ShiftDirection::Left => {
+                        self.bits.rotate_left(1);
+                        self.bits[width - 1] = bit(ser);
+                    }
+                },
+                InputPinState::Indeterminate => self.bits.fill(None),
+            }
+        }
+        for (stored, output) in self.bits.iter().zip(outputs) {
+            output.drive(level(*stored));
+        }
+
+        Ok(SimResult::Continuing)
+    }
+
+    #[cfg(feature = "serde")]
+    fn save_state(&self) -> Result<serde_json::Value, String> {
+        super::save_state(&self.bits)
+    }
+
+    #[cfg(feature = "serde")]
+    fn restore_state(&mut self, state: serde_json::Value) -> Result<(), String> {
+        let bits: Vec<_> = super::restore_state(&self.name, state)?;
+        if bits.len() != self.bits.len() {
+            return Err(format!(
+                "Element \"{}\": saved state has {} bits, expected {}",
+                self.name,
+                bits.len(),
+                self.bits.len()
+            ));
+        }
+        self.bits = bits;
+        Ok(())
+    }
+
+    fn box_clone(&self) -> Box<dyn Element> {
+        Box::new(self.clone())
+    }
+}
+
+/// Register every kind of register.
+///
+/// The shift register takes the `width` (default 8), `direction` (`right` or `left`, default `right`) and `delay`
+/// (default 0) parameters.
+///
+/// # Parameters
+///
+/// - `registry`: The Registry to add the registers to.
+pub fn register(registry: &mut Registry) {
+    registry.register("shift_register", |name, parameters: &Parameters| {
+        Ok(Box::new(ShiftRegister::new(
+            name,
+            parameters.get_or("width", DEFAULT_WIDTH)?,
+            parameters.get_or("direction", ShiftDirection::Right)?,
+            parameters.get_or("delay", 0)?,
+        )?))
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::element::testing::inputs;
+
+    /// Clock a four bit register once with the given serial input, load input and parallel inputs.
+    fn clock(register: &mut ShiftRegister, ser: f32, load: f32, parallel: [f32; 4]) {
+        let [p0, p1, p2, p3] = parallel;
+        let mut outputs = register.output_pins();
+        register
+            .step(
+                &inputs(
+                    [0.0, ser, load, 1.0, p0, p1, p2, p3],
+                    [1.0, ser, load, 1.0, p0, p1, p2, p3],
+                ),
+                &mut outputs,
+                10,
+            )
+            .unwrap();
+    }
+
+    const T: Option<bool> = Some(true);
+    const F: Option<bool> = Some(false);
+
+    #[test]
+    fn shift_register_load_and_shift_right() {
+        // GIVEN a four bit register shifting right
+        let mut register = ShiftRegister::new("U1", 4, ShiftDirection::Right, 0).unwrap();
+        // WHEN it is loaded and then shifted twice
+        clock(&mut register, 0.0, 0.0, [1.0, 0.0, 1.0, 1.0]);
+        assert_eq!(&[T, F, T, T], register.bits());
+        clock(&mut register, 1.0, 1.0, [0.0; 4]);
+        clock(&mut register, 0.0, 1.0, [0.0; 4]);
+        // THEN the serial input enters at Q0
+        assert_eq!(&[F, T, T, F], register.bits());
+    }
+    #[test]
+    fn shift_register_shift_left() {
+        // GIVEN a four bit register shifting left
+        let mut register = ShiftRegister::new("U1", 4, ShiftDirection::Left, 0).unwrap();
+        clock(&mut register, 0.0, 0.0, [1.0, 0.0, 0.0, 0.0]);
+        // WHEN it is shifted with an indeterminate serial input
+        clock(&mut register, 0.5, 1.0, [0.0; 4]);
+        // THEN the bits move towards Q0 and the new bit is unknown
+        assert_eq!(&[F, F, F, None], register.bits());
+    }
+    #[test]
+    fn shift_register_clear() {
+        // GIVEN a new register, whose bits are unknown
+        let mut register = ShiftRegister::new("U1", 2, ShiftDirection::Right, 0).unwrap();
+        let mut outputs = register.output_pins();
+        // WHEN it is cleared without a clock edge
+        register
+            .step(
+                &inputs([0.0; 6], [0.0, 1.0, 1.0, 0.0, 1.0, 1.0]),
+                &mut outputs,
+                10,
+            )
+            .unwrap();
+        outputs[0].step(SimDuration::from_ticks(10));
+        // THEN every bit is low
+        assert_eq!(&[F, F], register.bits());
+        assert_eq!(OutputPinState::Low, outputs[0].state());
+    }
+    #[test]
+    fn shift_register_registered_parameters() {
+        // GIVEN the standard registry
+        let registry = Registry::standard();
+        // WHEN registers are created with valid and invalid parameters
+        let parameters = Parameters::new()
+            .with("width", "16")
+            .with("direction", "left");
+        let register = registry
+            .create("shift_register", "U1", &parameters)
+            .unwrap();
+        // THEN the valid parameters are used and the invalid ones are rejected
+        assert_eq!(16, register.output_pins().len());
+        assert!(registry
+            .create(
+                "shift_register",
+                "U2",
+                &Parameters::new().with("width", "0")
+            )
+            .is_err());
+        assert!(registry
+            .create(
+                "shift_register",
+                "U3",
+                &Parameters::new().with("direction", "up")
+            )
+            .is_err());
+    }
+}
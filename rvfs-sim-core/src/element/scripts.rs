@@ -0,0 +1,84 @@
+//! Elements whose behaviour is implemented by a script, so it can be changed without recompiling the simulator.
+//!
+//! Scripts are written in [Rhai](https://rhai.rs), embedded when the `rhai` feature is enabled, or in
+//! [Lua](https://www.lua.org) 5.4, embedded when the `lua` feature is enabled.  Lua scripts can also serve as
+//! testbench hooks, observing a Simulation as it is stepped.
+
+#[cfg(feature = "lua")]
+mod lua;
+#[cfg(feature = "rhai")]
+mod rhai;
+
+#[cfg(feature = "lua")]
+pub use lua::{LuaElement, LuaHook};
+#[cfg(feature = "rhai")]
+pub use rhai::RhaiElement;
+
+use crate::element::{Parameters, Registry};
+use std::fs;
+use std::path::Path;
+
+/// Read the source of a script file.
+///
+/// # Parameters
+///
+/// - `name`: Name of the Element or hook running the script, for error messages.
+/// - `path`: Path of the script file.
+fn read_script(name: &str, path: &Path) -> Result<String, String> {
+    fs::read_to_string(path).map_err(|error| {
+        format!(
+            "Script \"{name}\": cannot read \"{}\": {error}",
+            path.display()
+        )
+    })
+}
+
+/// Split a list of pin names separated by `,`.
+///
+/// # Parameters
+///
+/// - `names`: The list of names.
+fn pin_names(names: &str) -> Vec<&str> {
+    names
+        .split(',')
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .collect()
+}
+
+/// Register every kind of script element.
+///
+/// The `rhai` and `lua` kinds each take the `script` parameter, giving the path of the script file, the `inputs` and
+/// `outputs` parameters, giving the names of the pins separated by `,`, and the `delay` parameter (default 0).
+///
+/// # Parameters
+///
+/// - `registry`: The Registry to add the script elements to.
+pub fn register(registry: &mut Registry) {
+    #[cfg(feature = "lua")]
+    registry.register("lua", |name, parameters: &Parameters| {
+        let script: String = parameters.get_or("script", String::new())?;
+        let inputs: String = parameters.get_or("inputs", String::new())?;
+        let outputs: String = parameters.get_or("outputs", String::new())?;
+        Ok(Box::new(LuaElement::load(
+            name,
+            &pin_names(&inputs),
+            &pin_names(&outputs),
+            parameters.get_or("delay", 0)?,
+            Path::new(&script),
+        )?))
+    });
+    #[cfg(feature = "rhai")]
+    registry.register("rhai", |name, parameters: &Parameters| {
+        let script: String = parameters.get_or("script", String::new())?;
+        let inputs: String = parameters.get_or("inputs", String::new())?;
+        let outputs: String = parameters.get_or("outputs", String::new())?;
+        Ok(Box::new(RhaiElement::load(
+            name,
+            &pin_names(&inputs),
+            &pin_names(&outputs),
+            parameters.get_or("delay", 0)?,
+            Path::new(&script),
+        )?))
+    });
+}
@@ -0,0 +1,508 @@
+//! Elements and testbench hooks running Lua scripts.
+
+use crate::element::scripts::read_script;
+use crate::element::Element;
+use crate::ipin::{InputPin, InputPinState};
+use crate::opin::{OutputPin, OutputPinState};
+use crate::sim::{SimResult, Simulation};
+use crate::time::SimDuration;
+use crate::trace::{Change, Logic, Tracer, DEFAULT_HIGH_THRESHOLD, DEFAULT_LOW_THRESHOLD};
+use mlua::{Function, Lua, Table, Value};
+use std::fmt;
+use std::path::Path;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+
+/// Name of the script function called on every step of an Element.
+const STEP_FUNCTION: &str = "step";
+/// Names of the optional script functions called as a hook begins, on every step, and as it completes.
+const HOOK_FUNCTIONS: [&str; 3] = ["start", "record", "finish"];
+/// Output state which releases an output to high impedance.
+const HIGH_IMPEDANCE: &str = "z";
+
+/// A Lua interpreter which has run a script, shared between copies of the Element or hook running it.
+#[derive(Clone)]
+struct Interpreter {
+    /// The interpreter.
+    lua: Arc<Mutex<Lua>>,
+}
+
+impl Interpreter {
+    /// Create a new Interpreter and run a script in it.
+    ///
+    /// # Parameters
+    ///
+    /// - `name`: Name of the Element or hook running the script, for error messages.
+    /// - `source`: The source of the script.
+    fn new(name: &str, source: &str) -> Result<Self, String> {
+        let lua = Lua::new();
+        lua.load(source)
+            .set_name(name)
+            .exec()
+            .map_err(|error| format!("Script \"{name}\": {error}"))?;
+
+        Ok(Self {
+            lua: Arc::new(Mutex::new(lua)),
+        })
+    }
+
+    /// Lock the interpreter.
+    fn lock(&self) -> MutexGuard<'_, Lua> {
+        self.lua.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// Look up a global function defined by a script, if it defines one.
+///
+/// # Parameters
+///
+/// - `lua`: The interpreter which ran the script.
+/// - `function`: Name of the function.
+fn function<'lua>(lua: &'lua Lua, function: &str) -> mlua::Result<Option<Function<'lua>>> {
+    lua.globals().get(function)
+}
+
+/// An Element whose behaviour is implemented by a Lua script.
+///
+/// The script must define a global `step(inputs, time)` function, which is called on every step with a table from
+/// the name of each input to its state and with the simulation time.  An input state is `true` when high and `false`
+/// when low, and an indeterminate input is absent.  The function returns a table from output names to their next
+/// states, in the same form, or `"z"` to release an output to high impedance.  Outputs missing from the table keep
+/// their previous state, and start low.
+///
+/// State kept between steps can be held in global or local variables of the script.  The interpreter is shared by every
+/// copy of the Element, so a Simulation holding the Element refuses to [step back](crate::sim::Simulation::step_back).
+///
+/// # Example
+///
+/// ```
+/// # use rvfs_sim_core::element::scripts::LuaElement;
+/// # use rvfs_sim_core::element::Element;
+/// let script = r#"
+///     local count, last = 0, false
+///     function step(inputs, time)
+///         if inputs.CLK and not last then count = count + 1 end
+///         last = inputs.CLK
+///         return { ODD = count % 2 == 1 }
+///     end
+/// "#;
+/// let element = LuaElement::new("U1", &["CLK"], &["ODD"], 0, script).unwrap();
+///
+/// assert_eq!("CLK", element.input_pins()[0].name());
+/// assert_eq!("ODD", element.output_pins()[0].name());
+/// ```
+#[derive(Clone)]
+pub struct LuaElement {
+    /// Name of the Element.
+    name: String,
+    /// Names of the inputs.
+    inputs: Vec<String>,
+    /// Names of the outputs.
+    outputs: Vec<String>,
+    /// Propagation delay from an input to the outputs.
+    delay: u64,
+    /// The interpreter running the script.
+    interpreter: Interpreter,
+    /// Simulation time elapsed since the start of the Simulation.
+    time: u64,
+}
+
+impl LuaElement {
+    /// Create a new LuaElement from the source of its script.
+    ///
+    /// # Parameters
+    ///
+    /// - `name`: Name of the Element.
+    /// - `inputs`: Names of the inputs.
+    /// - `outputs`: Names of the outputs.
+    /// - `delay`: Propagation delay from an input to the outputs.
+    /// - `source`: The source of the script, which must define a `step` function.
+    pub fn new(
+        name: &str,
+        inputs: &[&str],
+        outputs: &[&str],
+        delay: u64,
+        source: &str,
+    ) -> Result<Self, String> {
+        let interpreter = Interpreter::new(name, source)?;
+        if !matches!(function(&interpreter.lock(), STEP_FUNCTION), Ok(Some(_))) {
+            return Err(format!(
+                "Script \"{name}\": script does not define a \"{STEP_FUNCTION}\" function"
+            ));
+        }
+
+        Ok(Self {
+            name: name.to_string(),
+            inputs: inputs.iter().map(ToString::to_string).collect(),
+            outputs: outputs.iter().map(ToString::to_string).collect(),
+            delay,
+            interpreter,
+            time: 0,
+        })
+    }
+
+    /// Create a new LuaElement from a script file.
+    ///
+    /// # Parameters
+    ///
+    /// - `name`: Name of the Element.
+    /// - `inputs`: Names of the inputs.
+    /// - `outputs`: Names of the outputs.
+    /// - `delay`: Propagation delay from an input to the outputs.
+    /// - `path`: Path of the script file, which must define a `step` function.
+    pub fn load(
+        name: &str,
+        inputs: &[&str],
+        outputs: &[&str],
+        delay: u64,
+        path: &Path,
+    ) -> Result<Self, String> {
+        Self::new(name, inputs, outputs, delay, &read_script(name, path)?)
+    }
+}
+
+impl fmt::Debug for LuaElement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LuaElement")
+            .field("name", &self.name)
+            .field("inputs", &self.inputs)
+            .field("outputs", &self.outputs)
+            .field("delay", &self.delay)
+            .field("time", &self.time)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Element for LuaElement {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn input_pins(&self) -> Vec<InputPin> {
+        self.inputs.iter().map(|name| InputPin::new(name)).collect()
+    }
+
+    fn output_pins(&self) -> Vec<OutputPin> {
+        self.outputs
+            .iter()
+            .map(|name| {
+                OutputPin::new(
+                    name,
+                    SimDuration::from_ticks(self.delay),
+                    OutputPinState::Low,
+                )
+            })
+            .collect()
+    }
+
+    fn step(
+        &mut self,
+        inputs: &[InputPin],
+        outputs: &mut [OutputPin],
+        delta_t: u64,
+    ) -> Result<SimResult, String> {
+        self.time += delta_t;
+        let lua = self.interpreter.lock();
+        let error = |error: mlua::Error| format!("Script \"{}\": {error}", self.name);
+
+        let states = lua.create_table().map_err(error)?;
+        for (name, input) in self.inputs.iter().zip(inputs) {
+            let state = match input.state() {
+                InputPinState::Low => Value::Boolean(false),
+                InputPinState::High => Value::Boolean(true),
+                InputPinState::Indeterminate => Value::Nil,
+            };
+            states.set(name.as_str(), state).map_err(error)?;
+        }
+        let next: Table = function(&lua, STEP_FUNCTION)
+            .and_then(|step| match step {
+                Some(step) => step.call((states, self.time)),
+                None => Err(mlua::Error::runtime("step function removed")),
+            })
+            .map_err(error)?;
+
+        for (name, output) in self.outputs.iter().zip(outputs) {
+            match next.get(name.as_str()).map_err(error)? {
+                Value::Nil => (),
+                Value::Boolean(true) => output.drive(OutputPinState::High),
+                Value::Boolean(false) => output.drive(OutputPinState::Low),
+                Value::String(state) if state == HIGH_IMPEDANCE => {
+                    output.drive(OutputPinState::HighImpedance)
+                }
+                state => {
+                    return Err(format!(
+                        "Script \"{}\": output \"{name}\" set to {}, expected a boolean or \"{HIGH_IMPEDANCE}\"",
+                        self.name,
+                        state.type_name()
+                    ))
+                }
+            }
+        }
+
+        Ok(SimResult::Continuing)
+    }
+
+    fn is_replayable(&self) -> bool {
+        false
+    }
+
+    fn box_clone(&self) -> Box<dyn Element> {
+        Box::new(self.clone())
+    }
+}
+
+/// A testbench hook implemented by a Lua script, observing a Simulation as it is stepped.
+///
+/// The script may define global `start(time, signals)`, `record(time, signals)` and `finish(time, signals)`
+/// functions, which are called as the hook is [attached](crate::sim::Simulation::add_tracer), after every step, and
+/// as the Simulation [completes](crate::sim::Simulation::run).  Each is given the simulation time and a table from the
+/// name of every Wire to its logic level: `true` when high and `false` when low, with a Wire between the thresholds
+/// absent.  An error raised by the script, such as by a failed `assert`, stops the Simulation with its message.
+///
+/// # Example
+///
+/// ```
+/// # use rvfs_sim_core::element::scripts::LuaHook;
+/// # use rvfs_sim_core::sim::Simulation;
+/// # use rvfs_sim_core::time::SimDuration;
+/// # use rvfs_sim_core::wire::{Wire, WirePull};
+/// let mut sim = Simulation::new(SimDuration::from_ticks(10));
+/// sim.add_wire(Wire::new("/RESET", WirePull::Up)).unwrap();
+/// let script = r#"
+///     function record(time, signals)
+///         assert(signals["/RESET"], "reset asserted at " .. time)
+///     end
+/// "#;
+/// sim.add_tracer(Box::new(LuaHook::new("reset check", script).unwrap()));
+///
+/// sim.step().unwrap();
+/// ```
+#[derive(Clone)]
+pub struct LuaHook {
+    /// Name of the hook.
+    name: String,
+    /// The interpreter running the script.
+    interpreter: Interpreter,
+}
+
+impl LuaHook {
+    /// Create a new LuaHook from the source of its script.
+    ///
+    /// # Parameters
+    ///
+    /// - `name`: Name of the hook, for error messages.
+    /// - `source`: The source of the script.
+    pub fn new(name: &str, source: &str) -> Result<Self, String> {
+        Ok(Self {
+            name: name.to_string(),
+            interpreter: Interpreter::new(name, source)?,
+        })
+    }
+
+    /// Create a new LuaHook from a script file.
+    ///
+    /// # Parameters
+    ///
+    /// - `name`: Name of the hook, for error messages.
+    /// - `path`: Path of the script file.
+    pub fn load(name: &str, path: &Path) -> Result<Self, String> {
+        Self::new(name, &read_script(name, path)?)
+    }
+
+    /// Call one of the hook functions, if the script defines it.
+    ///
+    /// # Parameters
+    ///
+    /// - `hook`: Name of the function.
+    /// - `sim`: The Simulation being observed.
+    fn call(&self, hook: &str, sim: &Simulation) -> Result<(), String> {
+        let lua = self.interpreter.lock();
+        let error = |error: mlua::Error| format!("Script \"{}\": {error}", self.name);
+        let Some(hook) = function(&lua, hook).map_err(error)? else {
+            return Ok(());
+        };
+
+        let signals = lua.create_table().map_err(error)?;
+        for id in sim.wires() {
+            let wire = sim.wire(id)?;
+            let level = match Logic::from_level(
+                wire.measure(),
+                DEFAULT_LOW_THRESHOLD,
+                DEFAULT_HIGH_THRESHOLD,
+            ) {
+                Logic::Low => Value::Boolean(false),
+                Logic::High => Value::Boolean(true),
+                Logic::Unknown => Value::Nil,
+            };
+            signals.set(wire.name().as_str(), level).map_err(error)?;
+        }
+        hook.call((sim.time(), signals)).map_err(error)
+    }
+}
+
+impl fmt::Debug for LuaHook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LuaHook")
+            .field("name", &self.name)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Tracer for LuaHook {
+    fn start(&mut self, sim: &Simulation) -> Result<(), String> {
+        self.call(HOOK_FUNCTIONS[0], sim)
+    }
+
+    fn record(&mut self, sim: &Simulation, _changes: &[Change]) -> Result<(), String> {
+        self.call(HOOK_FUNCTIONS[1], sim)
+    }
+
+    fn finish(&mut self, sim: &Simulation) -> Result<(), String> {
+        self.call(HOOK_FUNCTIONS[2], sim)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::element::clocks::ClockGenerator;
+    use crate::element::testing::inputs;
+    use crate::element::{Parameters, Registry};
+    use crate::wire::{Wire, WirePull};
+    use std::env;
+    use std::fs;
+
+    /// A script gating an enable with a clock, noting once it has counted two rising edges of the clock while enabled.
+    const GATE: &str = r#"
+        local edges, last = 0, false
+        function step(inputs, time)
+            if inputs.EN and inputs.CLK and not last then edges = edges + 1 end
+            last = inputs.CLK
+            local y = "z"
+            if inputs.EN ~= nil then y = inputs.EN and inputs.CLK == true end
+            return { Y = y, COUNT = edges > 1 }
+        end
+    "#;
+
+    /// Step an element, returning the states of its outputs after their delay.
+    fn step(
+        element: &mut LuaElement,
+        previous: [f32; 2],
+        levels: [f32; 2],
+    ) -> Result<Vec<OutputPinState>, String> {
+        let mut outputs = element.output_pins();
+        element.step(&inputs(previous, levels), &mut outputs, 10)?;
+        Ok(outputs
+            .iter_mut()
+            .map(|output| {
+                output.step(SimDuration::from_ticks(10));
+                output.state()
+            })
+            .collect())
+    }
+
+    const L: OutputPinState = OutputPinState::Low;
+    const H: OutputPinState = OutputPinState::High;
+    const Z: OutputPinState = OutputPinState::HighImpedance;
+
+    #[test]
+    fn lua_element_step() {
+        // GIVEN an element running a script
+        let mut element = LuaElement::new("U1", &["EN", "CLK"], &["Y", "COUNT"], 0, GATE).unwrap();
+        // WHEN it is stepped through clock edges, enabled and not
+        let states = [
+            step(&mut element, [1.0, 0.0], [1.0, 1.0]),
+            step(&mut element, [1.0, 1.0], [1.0, 0.0]),
+            step(&mut element, [1.0, 0.0], [1.0, 1.0]),
+            step(&mut element, [1.0, 1.0], [0.5, 1.0]),
+            step(&mut element, [0.5, 1.0], [0.0, 0.0]),
+        ];
+        // THEN the script calculates the outputs, keeping its state between steps
+        assert_eq!(
+            [
+                Ok(vec![H, L]),
+                Ok(vec![L, L]),
+                Ok(vec![H, H]),
+                Ok(vec![Z, H]),
+                Ok(vec![L, H])
+            ],
+            states
+        );
+    }
+    #[test]
+    fn lua_element_errors() {
+        // GIVEN scripts which do not parse, lack a step function, and set an output to a number
+        let unparsed = LuaElement::new("U1", &[], &["Y"], 0, "function step(inputs, time)");
+        let missing = LuaElement::new("U2", &[], &["Y"], 0, "x = 1");
+        let mut number = LuaElement::new(
+            "U3",
+            &["A", "B"],
+            &["Y"],
+            0,
+            "function step(inputs, time) return { Y = time } end",
+        )
+        .unwrap();
+        // WHEN they are created and stepped
+        // THEN the errors are reported
+        assert!(unparsed.is_err());
+        assert_eq!(
+            "Script \"U2\": script does not define a \"step\" function",
+            missing.unwrap_err()
+        );
+        assert_eq!(
+            Err(
+                "Script \"U3\": output \"Y\" set to integer, expected a boolean or \"z\""
+                    .to_string()
+            ),
+            step(&mut number, [0.0, 0.0], [0.0, 0.0])
+        );
+    }
+    #[test]
+    fn lua_hook() {
+        // GIVEN a Simulation with a clock, and a hook allowing it only two rising edges
+        let mut sim = Simulation::new(SimDuration::from_ticks(10));
+        let clock = ClockGenerator::new("X1", 40.0, 0.5, 0.0, 0, 0.0).unwrap();
+        let clock = sim.add_element(Box::new(clock)).unwrap();
+        let clk = sim.add_wire(Wire::new("CLK", WirePull::Down)).unwrap();
+        sim.connect_output(sim.output_pin(clock, "CLK").unwrap(), clk)
+            .unwrap();
+        let script = r#"
+            local edges, last = 0, nil
+            function start(time, signals) last = signals.CLK end
+            function record(time, signals)
+                if signals.CLK and not last then edges = edges + 1 end
+                last = signals.CLK
+                assert(edges < 3, "third edge at " .. time)
+            end
+        "#;
+        sim.add_tracer(Box::new(LuaHook::new("edges", script).unwrap()));
+        // WHEN the Simulation runs
+        let error = (0..20).find_map(|_| sim.step().err());
+        // THEN the hook sees the edges, and its failed assertion stops the Simulation
+        assert!(error
+            .unwrap()
+            .error
+            .to_string()
+            .contains("third edge at 90"));
+    }
+    #[test]
+    fn lua_element_registered() {
+        // GIVEN the standard registry and a script file
+        let registry = Registry::standard();
+        let path = env::temp_dir().join("rvfs_sim_lua_element_registered.lua");
+        fs::write(&path, GATE).unwrap();
+        // WHEN elements are created from the file and from a missing file
+        let parameters = Parameters::new()
+            .with("inputs", "EN, CLK")
+            .with("outputs", "Y, COUNT");
+        let good = registry.create(
+            "lua",
+            "U1",
+            &parameters.clone().with("script", path.to_str().unwrap()),
+        );
+        let bad = registry.create("lua", "U2", &parameters.with("script", "missing.lua"));
+        fs::remove_file(&path).unwrap();
+        // THEN only the file which exists is accepted
+        assert_eq!("COUNT", good.unwrap().output_pins()[1].name());
+        assert!(bad.is_err());
+    }
+}
@@ -0,0 +1,362 @@
+//! Elements running Rhai scripts.
+
+use crate::element::scripts::read_script;
+use crate::element::Element;
+use crate::ipin::{InputPin, InputPinState};
+use crate::opin::{OutputPin, OutputPinState};
+use crate::sim::SimResult;
+use crate::time::SimDuration;
+use rhai::{CallFnOptions, Dynamic, Engine, Map, Scope, AST};
+use std::fmt;
+use std::path::Path;
+use std::sync::Arc;
+
+/// Name of the script function called on every step.
+const STEP_FUNCTION: &str = "step";
+/// Name of the optional script function giving the initial state.
+const INIT_FUNCTION: &str = "init";
+
+/// An Element whose behaviour is implemented by a Rhai script.
+///
+/// The script must define a `step(inputs, time)` function, which is called on every step with a map from the name of
+/// each input to its state and with the simulation time.  An input state is `true` when high, `false` when low, and
+/// `()` when indeterminate.  The function returns a map from output names to their next states, in the same form,
+/// with `()` releasing the output to high impedance.  Outputs missing from the map keep their previous state, and
+/// start low.
+///
+/// State kept between steps is held in `this`, which starts as the value returned by the script's `init()` function
+/// if it defines one, or as an empty map otherwise.  The state is cloned along with the Element, so it is rewound
+/// when a Simulation [steps back](crate::sim::Simulation::step_back).
+///
+/// # Example
+///
+/// ```
+/// # use rvfs_sim_core::element::scripts::RhaiElement;
+/// # use rvfs_sim_core::element::Element;
+/// let script = r#"
+///     fn init() { #{ count: 0 } }
+///     fn step(inputs, time) {
+///         if inputs.CLK == true && this.last == false { this.count += 1; }
+///         this.last = inputs.CLK;
+///         #{ ODD: this.count % 2 == 1 }
+///     }
+/// "#;
+/// let element = RhaiElement::new("U1", &["CLK"], &["ODD"], 0, script).unwrap();
+///
+/// assert_eq!("CLK", element.input_pins()[0].name());
+/// assert_eq!("ODD", element.output_pins()[0].name());
+/// ```
+#[derive(Clone)]
+pub struct RhaiElement {
+    /// Name of the Element.
+    name: String,
+    /// Names of the inputs.
+    inputs: Vec<String>,
+    /// Names of the outputs.
+    outputs: Vec<String>,
+    /// Propagation delay from an input to the outputs.
+    delay: u64,
+    /// The script engine, shared between copies of the Element.
+    engine: Arc<Engine>,
+    /// The compiled script.
+    script: AST,
+    /// The state held in `this` between steps.
+    state: Dynamic,
+    /// Simulation time elapsed since the start of the Simulation.
+    time: u64,
+}
+
+impl RhaiElement {
+    /// Create a new RhaiElement from the source of its script.
+    ///
+    /// # Parameters
+    ///
+    /// - `name`: Name of the Element.
+    /// - `inputs`: Names of the inputs.
+    /// - `outputs`: Names of the outputs.
+    /// - `delay`: Propagation delay from an input to the outputs.
+    /// - `source`: The source of the script, which must define a `step` function.
+    pub fn new(
+        name: &str,
+        inputs: &[&str],
+        outputs: &[&str],
+        delay: u64,
+        source: &str,
+    ) -> Result<Self, String> {
+        let engine = Engine::new();
+        let script = engine
+            .compile(source)
+            .map_err(|error| format!("Script element \"{name}\": {error}"))?;
+        let defines = |function| script.iter_functions().any(|f| f.name == function);
+        if !defines(STEP_FUNCTION) {
+            return Err(format!(
+                "Script element \"{name}\": script does not define a \"{STEP_FUNCTION}\" function"
+            ));
+        }
+        let state = if defines(INIT_FUNCTION) {
+            engine
+                .call_fn(&mut Scope::new(), &script, INIT_FUNCTION, ())
+                .map_err(|error| format!("Script element \"{name}\": {error}"))?
+        } else {
+            Dynamic::from_map(Map::new())
+        };
+
+        Ok(Self {
+            name: name.to_string(),
+            inputs: inputs.iter().map(ToString::to_string).collect(),
+            outputs: outputs.iter().map(ToString::to_string).collect(),
+            delay,
+            engine: Arc::new(engine),
+            script,
+            state,
+            time: 0,
+        })
+    }
+
+    /// Create a new RhaiElement from a script file.
+    ///
+    /// # Parameters
+    ///
+    /// - `name`: Name of the Element.
+    /// - `inputs`: Names of the inputs.
+    /// - `outputs`: Names of the outputs.
+    /// - `delay`: Propagation delay from an input to the outputs.
+    /// - `path`: Path of the script file, which must define a `step` function.
+    pub fn load(
+        name: &str,
+        inputs: &[&str],
+        outputs: &[&str],
+        delay: u64,
+        path: &Path,
+    ) -> Result<Self, String> {
+        Self::new(name, inputs, outputs, delay, &read_script(name, path)?)
+    }
+}
+
+impl fmt::Debug for RhaiElement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RhaiElement")
+            .field("name", &self.name)
+            .field("inputs", &self.inputs)
+            .field("outputs", &self.outputs)
+            .field("delay", &self.delay)
+            .field("state", &self.state)
+            .field("time", &self.time)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Element for RhaiElement {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn input_pins(&self) -> Vec<InputPin> {
+        self.inputs.iter().map(|name| InputPin::new(name)).collect()
+    }
+
+    fn output_pins(&self) -> Vec<OutputPin> {
+        self.outputs
+            .iter()
+            .map(|name| {
+                OutputPin::new(
+                    name,
+                    SimDuration::from_ticks(self.delay),
+                    OutputPinState::Low,
+                )
+            })
+            .collect()
+    }
+
+    fn step(
+        &mut self,
+        inputs: &[InputPin],
+        outputs: &mut [OutputPin],
+        delta_t: u64,
+    ) -> Result<SimResult, String> {
+        self.time += delta_t;
+        let states: Map = self
+            .inputs
+            .iter()
+            .zip(inputs)
+            .map(|(name, input)| {
+                let state = match input.state() {
+                    InputPinState::Low => Dynamic::FALSE,
+                    InputPinState::High => Dynamic::TRUE,
+                    InputPinState::Indeterminate => Dynamic::UNIT,
+                };
+                (name.into(), state)
+            })
+            .collect();
+
+        let time = i64::try_from(self.time).unwrap_or(i64::MAX);
+        let options = CallFnOptions::new().bind_this_ptr(&mut self.state);
+        let mut next: Map = self
+            .engine
+            .call_fn_with_options(
+                options,
+                &mut Scope::new(),
+                &self.script,
+                STEP_FUNCTION,
+                (states, time),
+            )
+            .map_err(|error| format!("Script element \"{}\": {error}", self.name))?;
+
+        for (name, output) in self.outputs.iter().zip(outputs) {
+            let Some(state) = next.remove(name.as_str()) else {
+                continue;
+            };
+            output.drive(if state.is_unit() {
+                OutputPinState::HighImpedance
+            } else {
+                match state.as_bool() {
+                    Ok(true) => OutputPinState::High,
+                    Ok(false) => OutputPinState::Low,
+                    Err(kind) => {
+                        return Err(format!(
+                            "Script element \"{}\": output \"{name}\" set to {kind}, expected bool or ()",
+                            self.name
+                        ))
+                    }
+                }
+            });
+        }
+
+        Ok(SimResult::Continuing)
+    }
+
+    fn box_clone(&self) -> Box<dyn Element> {
+        Box::new(self.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::element::testing::inputs;
+    use crate::element::{Parameters, Registry};
+    use std::env;
+    use std::fs;
+
+    /// A script gating an enable with a clock, counting the clock's rising edges while enabled.
+    const GATE: &str = r#"
+        fn init() { #{ edges: 0, last: false } }
+        fn step(inputs, time) {
+            let enabled = inputs.EN;
+            if enabled == true && inputs.CLK == true && this.last != true { this.edges += 1; }
+            this.last = inputs.CLK;
+            if enabled == () {
+                #{ Y: (), COUNT: this.edges > 1 }
+            } else {
+                #{ Y: enabled && inputs.CLK == true, COUNT: this.edges > 1 }
+            }
+        }
+    "#;
+
+    /// Step an element, returning the states of its outputs after their delay.
+    fn step(
+        element: &mut RhaiElement,
+        previous: [f32; 2],
+        levels: [f32; 2],
+    ) -> Result<Vec<OutputPinState>, String> {
+        let mut outputs = element.output_pins();
+        element.step(&inputs(previous, levels), &mut outputs, 10)?;
+        Ok(outputs
+            .iter_mut()
+            .map(|output| {
+                output.step(SimDuration::from_ticks(10));
+                output.state()
+            })
+            .collect())
+    }
+
+    const L: OutputPinState = OutputPinState::Low;
+    const H: OutputPinState = OutputPinState::High;
+    const Z: OutputPinState = OutputPinState::HighImpedance;
+
+    #[test]
+    fn rhai_element_step() {
+        // GIVEN an element running a script
+        let mut element = RhaiElement::new("U1", &["EN", "CLK"], &["Y", "COUNT"], 0, GATE).unwrap();
+        // WHEN it is stepped through clock edges, enabled and not
+        let states = [
+            step(&mut element, [1.0, 0.0], [1.0, 1.0]),
+            step(&mut element, [1.0, 1.0], [1.0, 0.0]),
+            step(&mut element, [1.0, 0.0], [1.0, 1.0]),
+            step(&mut element, [1.0, 1.0], [0.5, 1.0]),
+            step(&mut element, [0.5, 1.0], [0.0, 0.0]),
+        ];
+        // THEN the script calculates the outputs, keeping its state between steps
+        assert_eq!(
+            [
+                Ok(vec![H, L]),
+                Ok(vec![L, L]),
+                Ok(vec![H, H]),
+                Ok(vec![Z, H]),
+                Ok(vec![L, H])
+            ],
+            states
+        );
+    }
+    #[test]
+    fn rhai_element_checkpoint() {
+        // GIVEN an element which has counted an edge
+        let mut element = RhaiElement::new("U1", &["EN", "CLK"], &["Y", "COUNT"], 0, GATE).unwrap();
+        step(&mut element, [1.0, 0.0], [1.0, 1.0]).unwrap();
+        step(&mut element, [1.0, 1.0], [1.0, 0.0]).unwrap();
+        // WHEN a copy counts another edge
+        let mut copy = element.box_clone();
+        let mut outputs = copy.output_pins();
+        copy.step(&inputs([1.0, 0.0], [1.0, 1.0]), &mut outputs, 10)
+            .unwrap();
+        // THEN the original's state is unaffected
+        assert_eq!(Ok(vec![L, L]), step(&mut element, [1.0, 1.0], [1.0, 0.0]));
+    }
+    #[test]
+    fn rhai_element_errors() {
+        // GIVEN scripts which do not parse, lack a step function, and set an output to a number
+        let unparsed = RhaiElement::new("U1", &[], &["Y"], 0, "fn step(inputs, time) {");
+        let missing = RhaiElement::new("U2", &[], &["Y"], 0, "fn init() { 1 }");
+        let mut number = RhaiElement::new(
+            "U3",
+            &["A", "B"],
+            &["Y"],
+            0,
+            "fn step(inputs, time) { #{ Y: time } }",
+        )
+        .unwrap();
+        // WHEN they are created and stepped
+        // THEN the errors are reported
+        assert!(unparsed.is_err());
+        assert_eq!(
+            "Script element \"U2\": script does not define a \"step\" function",
+            missing.unwrap_err()
+        );
+        assert_eq!(
+            Err("Script element \"U3\": output \"Y\" set to i64, expected bool or ()".to_string()),
+            step(&mut number, [0.0, 0.0], [0.0, 0.0])
+        );
+    }
+    #[test]
+    fn rhai_element_registered() {
+        // GIVEN the standard registry and a script file
+        let registry = Registry::standard();
+        let path = env::temp_dir().join("rvfs_sim_rhai_element_registered.rhai");
+        fs::write(&path, GATE).unwrap();
+        // WHEN elements are created from the file and from a missing file
+        let parameters = Parameters::new()
+            .with("inputs", "EN, CLK")
+            .with("outputs", "Y, COUNT")
+            .with("delay", "5");
+        let good = registry.create(
+            "rhai",
+            "U1",
+            &parameters.clone().with("script", path.to_str().unwrap()),
+        );
+        let bad = registry.create("rhai", "U2", &parameters.with("script", "missing.rhai"));
+        fs::remove_file(&path).unwrap();
+        // THEN only the file which exists is accepted
+        assert_eq!("COUNT", good.unwrap().output_pins()[1].name());
+        assert!(bad.is_err());
+    }
+}
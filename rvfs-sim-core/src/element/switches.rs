@@ -0,0 +1,711 @@
+//! Switches and push-buttons, operated from a testbench and optionally bouncing like real contacts.
+//!
+//! The contacts of a switch connect its output to a fixed level, which is low unless configured otherwise, and release
+//! it while they are open, so the output should be [connected](crate::sim::Simulation::connect_output) to a Wire
+//! pulled to the opposite level.  Each time the switch is operated its contacts can [bounce](Bounce) before settling,
+//! which is useful for verifying debounce logic.
+//!
+//! A switch is operated through its host side, either at once or at given times from the start of the Simulation.
+
+use crate::element::{level, Element, Parameters, Registry};
+use crate::ipin::InputPin;
+use crate::opin::{OutputPin, OutputPinState};
+use crate::rng::SimRng;
+use crate::sim::SimResult;
+use crate::time::SimDuration;
+use std::collections::VecDeque;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+
+/// How the contacts of a switch bounce each time it is operated.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum Bounce {
+    /// The contacts change cleanly.
+    #[default]
+    None,
+    /// The contacts bounce in a fixed pattern, given as the durations for which they alternately hold the new and old
+    /// positions before settling in the new position.  There must be an even number of durations.
+    Pattern(Vec<u64>),
+    /// The contacts bounce pseudo-randomly for a total duration before settling in the new position.  The bounces are
+    /// drawn from a stream of the Simulation's [random numbers](crate::rng), chosen by the seed, so the same seed
+    /// always gives the same bounces in a Simulation with the same seed.
+    Random {
+        /// Total duration of the bounces.
+        duration: u64,
+        /// Seed of the pseudo-random bounces.
+        seed: u64,
+    },
+}
+
+impl Bounce {
+    /// Check that the bounce is valid.
+    ///
+    /// # Parameters
+    ///
+    /// - `name`: Name of the switch, for error messages.
+    fn validate(&self, name: &str) -> Result<(), String> {
+        match self {
+            Self::Pattern(durations) if durations.len() % 2 != 0 => Err(format!(
+                "Switch \"{name}\": bounce pattern must have an even number of durations"
+            )),
+            Self::Pattern(durations) if durations.contains(&0) => Err(format!(
+                "Switch \"{name}\": bounce durations must be non-zero"
+            )),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// An operation on a switch.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Command {
+    /// Move the switch to the closed or open position.
+    Set(bool),
+    /// Move the switch to the other position.
+    Toggle,
+    /// Close the switch, and open it again after a duration.
+    Press(u64),
+}
+
+/// Operations queued by the host side of a switch, with the times at which to perform them, if not at once.
+type Commands = Arc<Mutex<VecDeque<(Option<u64>, Command)>>>;
+
+/// Lock the operations queued by the host side of a switch.
+///
+/// # Parameters
+///
+/// - `commands`: The queued operations.
+fn lock(commands: &Commands) -> MutexGuard<'_, VecDeque<(Option<u64>, Command)>> {
+    commands.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// The contacts of a switch.
+#[derive(Debug, Clone)]
+struct Contacts {
+    /// How the contacts bounce.
+    bounce: Bounce,
+    /// The pseudo-random bounce generator.
+    random: SimRng,
+    /// The position the switch was last moved to.
+    position: bool,
+    /// Whether the contacts are presently closed.
+    closed: bool,
+    /// Pending changes of the contacts, with the times at which they happen.
+    changes: VecDeque<(u64, bool)>,
+}
+
+impl Contacts {
+    /// Create new Contacts, settled in a position.
+    ///
+    /// # Parameters
+    ///
+    /// - `closed`: Whether the contacts are closed.
+    /// - `bounce`: How the contacts bounce.
+    fn new(closed: bool, bounce: Bounce) -> Self {
+        let mut contacts = Self {
+            bounce,
+            random: SimRng::default(),
+            position: closed,
+            closed,
+            changes: VecDeque::new(),
+        };
+        contacts.set_rng(&SimRng::default());
+        contacts
+    }
+
+    /// Fork the pseudo-random bounce generator from the random number generator of a Simulation.
+    ///
+    /// # Parameters
+    ///
+    /// - `rng`: The generator of the Simulation.
+    fn set_rng(&mut self, rng: &SimRng) {
+        if let Bounce::Random { seed, .. } = self.bounce {
+            self.random = rng.fork(seed);
+        }
+    }
+
+    /// Move the switch to a position, abandoning any bounces still pending.
+    ///
+    /// # Parameters
+    ///
+    /// - `closed`: Whether to move the switch to the closed position.
+    /// - `time`: The time at which the switch is moved.
+    fn operate(&mut self, closed: bool, time: u64) {
+        if closed == self.position {
+            return;
+        }
+
+        self.position = closed;
+        self.changes.clear();
+        self.changes.push_back((time, closed));
+        let mut at = time;
+        let mut state = closed;
+        match self.bounce.clone() {
+            Bounce::None => (),
+            Bounce::Pattern(durations) => {
+                for duration in durations {
+                    at += duration;
+                    state = !state;
+                    self.changes.push_back((at, state));
+                }
+            }
+            Bounce::Random { duration, .. } => {
+                let end = time + duration;
+                loop {
+                    at += 1 + self.random.next_u64() % (duration / 8 + 1);
+                    if at >= end {
+                        break;
+                    }
+                    state = !state;
+                    self.changes.push_back((at, state));
+                }
+                if state != closed {
+                    self.changes.push_back((end, closed));
+                }
+            }
+        }
+    }
+
+    /// Apply the changes of the contacts which have happened by a time.
+    ///
+    /// # Parameters
+    ///
+    /// - `time`: The present time.
+    fn advance(&mut self, time: u64) {
+        while let Some((_, closed)) = self.changes.front().filter(|(at, _)| *at <= time) {
+            self.closed = *closed;
+            self.changes.pop_front();
+        }
+    }
+}
+
+/// The behaviour shared by switches and push-buttons.
+#[derive(Debug, Clone)]
+struct Operated {
+    /// Name of the switch.
+    name: String,
+    /// The level driven while the contacts are closed.
+    drive: bool,
+    /// The contacts.
+    contacts: Contacts,
+    /// Time elapsed since the start of the Simulation.
+    time: u64,
+    /// Operations to perform, in order of time.
+    schedule: Vec<(u64, Command)>,
+    /// Operations queued by the host side.
+    commands: Commands,
+}
+
+impl Operated {
+    /// Create the behaviour of a new switch.
+    ///
+    /// # Parameters
+    ///
+    /// - `name`: Name of the switch.
+    /// - `closed`: Whether the switch starts closed.
+    /// - `drive`: The level driven while the contacts are closed.
+    /// - `bounce`: How the contacts bounce.
+    fn new(name: &str, closed: bool, drive: bool, bounce: Bounce) -> Result<Self, String> {
+        bounce.validate(name)?;
+
+        Ok(Self {
+            name: name.to_string(),
+            drive,
+            contacts: Contacts::new(closed, bounce),
+            time: 0,
+            schedule: Vec::new(),
+            commands: Commands::default(),
+        })
+    }
+
+    /// Add an operation to the schedule, after any others at the same time.
+    ///
+    /// # Parameters
+    ///
+    /// - `time`: The time at which to perform the operation.
+    /// - `command`: The operation.
+    fn schedule(&mut self, time: u64, command: Command) {
+        let index = self.schedule.partition_point(|(at, _)| *at <= time);
+        self.schedule.insert(index, (time, command));
+    }
+
+    /// The output pins of the switch.
+    fn output_pins(&self) -> Vec<OutputPin> {
+        vec![OutputPin::new(
+            "OUT",
+            SimDuration::ZERO,
+            OutputPinState::HighImpedance,
+        )]
+    }
+
+    /// Step the switch, performing any operations due and driving its output.
+    ///
+    /// # Parameters
+    ///
+    /// - `outputs`: The output pins of the switch.
+    /// - `delta_t`: The simulation time elapsed since the last step.
+    fn step(&mut self, outputs: &mut [OutputPin], delta_t: u64) -> Result<SimResult, String> {
+        let [output] = outputs else {
+            return Err(format!("Switch \"{}\": expected 1 output", self.name));
+        };
+
+        self.time += delta_t;
+        let queued: Vec<_> = lock(&self.commands).drain(..).collect();
+        for (time, command) in queued {
+            self.schedule(time.unwrap_or(self.time), command);
+        }
+        while let Some(&(time, command)) = self.schedule.first().filter(|(at, _)| *at <= self.time)
+        {
+            self.schedule.remove(0);
+            match command {
+                Command::Set(closed) => self.contacts.operate(closed, time),
+                Command::Toggle => self.contacts.operate(!self.contacts.position, time),
+                Command::Press(duration) => {
+                    self.contacts.operate(true, time);
+                    self.schedule(time + duration, Command::Set(false));
+                }
+            }
+        }
+        self.contacts.advance(self.time);
+        output.drive(if self.contacts.closed {
+            level(Some(self.drive))
+        } else {
+            OutputPinState::HighImpedance
+        });
+
+        Ok(SimResult::Continuing)
+    }
+}
+
+/// The host side of a [Switch], through which a testbench operates it.
+///
+/// Operations are performed at the next step of the Simulation, or at a given time from its start if that is later.
+/// The queue of operations is shared by every copy of the switch, so operations already performed are not repeated
+/// when a Simulation [steps back](crate::sim::Simulation::step_back).
+#[derive(Debug, Clone)]
+pub struct SwitchHost {
+    /// The operations queued.
+    commands: Commands,
+}
+
+impl SwitchHost {
+    /// Move the switch to the closed or open position.
+    ///
+    /// # Parameters
+    ///
+    /// - `closed`: Whether to close the switch.
+    pub fn set(&self, closed: bool) {
+        lock(&self.commands).push_back((None, Command::Set(closed)));
+    }
+
+    /// Move the switch to the other position.
+    pub fn toggle(&self) {
+        lock(&self.commands).push_back((None, Command::Toggle));
+    }
+
+    /// Move the switch to the other position at a given time.
+    ///
+    /// # Parameters
+    ///
+    /// - `time`: The time from the start of the Simulation.
+    pub fn toggle_at(&self, time: u64) {
+        lock(&self.commands).push_back((Some(time), Command::Toggle));
+    }
+}
+
+/// A toggle switch, which stays in the position it is moved to.
+///
+/// The output is named `OUT`.  It drives the configured level while the contacts are closed, and is released while
+/// they are open.
+///
+/// # Example
+///
+/// ```
+/// # use rvfs_sim_core::element::switches::{Bounce, Switch};
+/// # use rvfs_sim_core::element::Element;
+/// let switch = Switch::new("S1", false, false, Bounce::Pattern(vec![10, 20])).unwrap();
+/// switch.host().toggle_at(1_000);
+///
+/// assert_eq!("OUT", switch.output_pins()[0].name());
+/// assert!(Switch::new("S2", false, false, Bounce::Pattern(vec![10])).is_err());
+/// ```
+#[derive(Debug, Clone)]
+pub struct Switch {
+    /// The behaviour of the switch.
+    operated: Operated,
+}
+
+impl Switch {
+    /// Create a new Switch.
+    ///
+    /// # Parameters
+    ///
+    /// - `name`: Name of the switch.
+    /// - `closed`: Whether the switch starts closed.
+    /// - `drive`: The level driven while the contacts are closed.
+    /// - `bounce`: How the contacts bounce each time the switch is moved.
+    pub fn new(name: &str, closed: bool, drive: bool, bounce: Bounce) -> Result<Self, String> {
+        Ok(Self {
+            operated: Operated::new(name, closed, drive, bounce)?,
+        })
+    }
+
+    /// Obtain the host side of the switch.
+    pub fn host(&self) -> SwitchHost {
+        SwitchHost {
+            commands: self.operated.commands.clone(),
+        }
+    }
+}
+
+impl Element for Switch {
+    fn name(&self) -> &str {
+        &self.operated.name
+    }
+
+    fn input_pins(&self) -> Vec<InputPin> {
+        Vec::new()
+    }
+
+    fn output_pins(&self) -> Vec<OutputPin> {
+        self.operated.output_pins()
+    }
+
+    fn step(
+        &mut self,
+        _inputs: &[InputPin],
+        outputs: &mut [OutputPin],
+        delta_t: u64,
+    ) -> Result<SimResult, String> {
+        self.operated.step(outputs, delta_t)
+    }
+
+    fn set_rng(&mut self, rng: &SimRng) {
+        self.operated.contacts.set_rng(rng);
+    }
+
+    fn box_clone(&self) -> Box<dyn Element> {
+        Box::new(self.clone())
+    }
+}
+
+/// The host side of a [Button], through which a testbench presses it.
+///
+/// Operations are performed as for a [SwitchHost].
+#[derive(Debug, Clone)]
+pub struct ButtonHost {
+    /// The operations queued.
+    commands: Commands,
+}
+
+impl ButtonHost {
+    /// Press the button, holding it until it is released.
+    pub fn press(&self) {
+        lock(&self.commands).push_back((None, Command::Set(true)));
+    }
+
+    /// Release the button.
+    pub fn release(&self) {
+        lock(&self.commands).push_back((None, Command::Set(false)));
+    }
+
+    /// Press the button and release it after a duration.
+    ///
+    /// # Parameters
+    ///
+    /// - `duration`: How long to hold the button.
+    pub fn press_for(&self, duration: u64) {
+        lock(&self.commands).push_back((None, Command::Press(duration)));
+    }
+
+    /// Press the button at a given time and release it after a duration.
+    ///
+    /// # Parameters
+    ///
+    /// - `time`: The time from the start of the Simulation.
+    /// - `duration`: How long to hold the button.
+    pub fn press_at(&self, time: u64, duration: u64) {
+        lock(&self.commands).push_back((Some(time), Command::Press(duration)));
+    }
+}
+
+/// A momentary push-button, whose contacts are closed only while it is pressed.
+///
+/// The output is named `OUT`, and is driven as for a [Switch].
+#[derive(Debug, Clone)]
+pub struct Button {
+    /// The behaviour of the button.
+    operated: Operated,
+}
+
+impl Button {
+    /// Create a new Button, initially released.
+    ///
+    /// # Parameters
+    ///
+    /// - `name`: Name of the button.
+    /// - `drive`: The level driven while the contacts are closed.
+    /// - `bounce`: How the contacts bounce each time the button is pressed or released.
+    pub fn new(name: &str, drive: bool, bounce: Bounce) -> Result<Self, String> {
+        Ok(Self {
+            operated: Operated::new(name, false, drive, bounce)?,
+        })
+    }
+
+    /// Obtain the host side of the button.
+    pub fn host(&self) -> ButtonHost {
+        ButtonHost {
+            commands: self.operated.commands.clone(),
+        }
+    }
+}
+
+impl Element for Button {
+    fn name(&self) -> &str {
+        &self.operated.name
+    }
+
+    fn input_pins(&self) -> Vec<InputPin> {
+        Vec::new()
+    }
+
+    fn output_pins(&self) -> Vec<OutputPin> {
+        self.operated.output_pins()
+    }
+
+    fn step(
+        &mut self,
+        _inputs: &[InputPin],
+        outputs: &mut [OutputPin],
+        delta_t: u64,
+    ) -> Result<SimResult, String> {
+        self.operated.step(outputs, delta_t)
+    }
+
+    fn set_rng(&mut self, rng: &SimRng) {
+        self.operated.contacts.set_rng(rng);
+    }
+
+    fn box_clone(&self) -> Box<dyn Element> {
+        Box::new(self.clone())
+    }
+}
+
+/// Parse a comma separated list of values.
+///
+/// # Parameters
+///
+/// - `text`: The text of the list, which may be empty.
+fn parse_list<T: FromStr>(text: &str) -> Result<Vec<T>, String> {
+    text.split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .map(|item| {
+            item.parse()
+                .map_err(|_| format!("Invalid list item \"{item}\""))
+        })
+        .collect()
+}
+
+/// Read the parameters shared by every kind of switch.
+///
+/// # Parameters
+///
+/// - `parameters`: The parameters of the switch.
+fn shared_parameters(parameters: &Parameters) -> Result<(bool, Bounce), String> {
+    let drive: String = parameters.get_or("drive", "low".to_string())?;
+    let drive = match drive.as_str() {
+        "low" => false,
+        "high" => true,
+        _ => return Err(format!("Invalid drive level \"{drive}\"")),
+    };
+    let pattern: String = parameters.get_or("bounce", String::new())?;
+    let duration: u64 = parameters.get_or("bounce_duration", 0)?;
+    let bounce = if !pattern.is_empty() {
+        Bounce::Pattern(parse_list(&pattern)?)
+    } else if duration > 0 {
+        Bounce::Random {
+            duration,
+            seed: parameters.get_or("seed", 0)?,
+        }
+    } else {
+        Bounce::None
+    };
+
+    Ok((drive, bounce))
+}
+
+/// Register every kind of switch.
+///
+/// Both kinds take the `drive` parameter (`low`, the default, or `high`), and either the `bounce` parameter holding
+/// a comma separated [bounce pattern](Bounce::Pattern) or the `bounce_duration` and `seed` parameters for random
+/// bounces.  The `switch` kind also takes the `closed` parameter (default false), and the `toggles` parameter holding
+/// comma separated times at which to toggle it.  The `button` kind takes the `presses` parameter, holding comma
+/// separated presses written as the time and duration separated by `:`.
+///
+/// # Parameters
+///
+/// - `registry`: The Registry to add the switches to.
+pub fn register(registry: &mut Registry) {
+    registry.register("switch", |name, parameters: &Parameters| {
+        let (drive, bounce) = shared_parameters(parameters)
+            .map_err(|message| format!("Switch \"{name}\": {message}"))?;
+        let switch = Switch::new(name, parameters.get_or("closed", false)?, drive, bounce)?;
+        let toggles: String = parameters.get_or("toggles", String::new())?;
+        for time in
+            parse_list(&toggles).map_err(|message| format!("Switch \"{name}\": {message}"))?
+        {
+            switch.host().toggle_at(time);
+        }
+        Ok(Box::new(switch))
+    });
+    registry.register("button", |name, parameters: &Parameters| {
+        let (drive, bounce) = shared_parameters(parameters)
+            .map_err(|message| format!("Switch \"{name}\": {message}"))?;
+        let button = Button::new(name, drive, bounce)?;
+        let presses: String = parameters.get_or("presses", String::new())?;
+        for press in parse_list::<String>(&presses)? {
+            let (time, duration) = press
+                .split_once(':')
+                .and_then(|(time, duration)| Some((time.parse().ok()?, duration.parse().ok()?)))
+                .ok_or_else(|| format!("Switch \"{name}\": invalid press \"{press}\""))?;
+            button.host().press_at(time, duration);
+        }
+        Ok(Box::new(button))
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Step an element with no inputs a number of times, returning the state of its output after each step.
+    fn trace(element: &mut dyn Element, steps: usize, delta_t: u64) -> Vec<OutputPinState> {
+        let mut outputs = element.output_pins();
+        (0..steps)
+            .map(|_| {
+                element.step(&[], &mut outputs, delta_t).unwrap();
+                outputs[0].step(SimDuration::from_ticks(delta_t));
+                outputs[0].state()
+            })
+            .collect()
+    }
+
+    /// Abbreviate output states as `0`, `1`, `-` and `x`.
+    fn levels(states: &[OutputPinState]) -> String {
+        states
+            .iter()
+            .map(|state| match state {
+                OutputPinState::Low => '0',
+                OutputPinState::High => '1',
+                OutputPinState::HighImpedance => '-',
+                OutputPinState::Unknown => 'x',
+            })
+            .collect()
+    }
+
+    #[test]
+    fn switch_toggle() {
+        // GIVEN a clean switch driving high, toggled at given times and at once
+        let mut switch = Switch::new("S1", false, true, Bounce::None).unwrap();
+        let host = switch.host();
+        host.toggle_at(30);
+        host.toggle_at(50);
+        // WHEN it is stepped
+        let first = trace(&mut switch, 8, 10);
+        host.toggle();
+        host.set(true);
+        let second = trace(&mut switch, 2, 10);
+        // THEN its output is driven only while it is closed
+        assert_eq!("--11----", levels(&first));
+        assert_eq!("11", levels(&second));
+    }
+    #[test]
+    fn switch_bounce_pattern() {
+        // GIVEN a switch with a bounce pattern, toggled twice
+        let mut switch =
+            Switch::new("S1", false, false, Bounce::Pattern(vec![20, 10, 30, 10])).unwrap();
+        switch.host().toggle_at(10);
+        switch.host().toggle_at(200);
+        // WHEN it is stepped
+        let states = trace(&mut switch, 30, 10);
+        // THEN the contacts bounce in the pattern each time before settling
+        assert_eq!("00-000-000000000000--0---0----", levels(&states));
+    }
+    #[test]
+    fn button_random_bounce() {
+        // GIVEN two buttons with the same random bounce
+        let bounce = Bounce::Random {
+            duration: 100,
+            seed: 7,
+        };
+        let mut first = Button::new("S1", false, bounce.clone()).unwrap();
+        let mut second = Button::new("S2", false, bounce).unwrap();
+        // WHEN each is pressed for a while
+        first.host().press_for(300);
+        second.host().press_at(1, 300);
+        let first = levels(&trace(&mut first, 500, 1));
+        let second = levels(&trace(&mut second, 500, 1));
+        // THEN they bounce identically, settling while held and after release
+        assert_eq!(first, second);
+        assert!(first[..100].contains('-'));
+        assert!(first[100..300].chars().all(|c| c == '0'));
+        assert!(first[300..400].contains('0'));
+        assert!(first[400..].chars().all(|c| c == '-'));
+    }
+    #[test]
+    fn button_bounce_follows_simulation_seed() {
+        // GIVEN three buttons with the same random bounce, two handed generators with the same seed
+        let bounce = Bounce::Random {
+            duration: 100,
+            seed: 7,
+        };
+        let mut buttons: Vec<Button> = (0..3)
+            .map(|_| Button::new("S1", false, bounce.clone()).unwrap())
+            .collect();
+        buttons[0].set_rng(&SimRng::new(1));
+        buttons[1].set_rng(&SimRng::new(1));
+        buttons[2].set_rng(&SimRng::new(2));
+        // WHEN each is pressed
+        let traces: Vec<String> = buttons
+            .iter_mut()
+            .map(|button| {
+                button.host().press_for(300);
+                levels(&trace(button, 200, 1))
+            })
+            .collect();
+        // THEN the bounces are reproduced by the same seed, and differ with another
+        assert_eq!(traces[0], traces[1]);
+        assert_ne!(traces[0], traces[2]);
+    }
+    #[test]
+    fn switches_registered() {
+        // GIVEN the standard registry
+        let registry = Registry::standard();
+        // WHEN switches are created with good and bad parameters
+        let mut button = registry
+            .create(
+                "button",
+                "S1",
+                &Parameters::new()
+                    .with("presses", "20:20, 60:10")
+                    .with("drive", "high"),
+            )
+            .unwrap();
+        let states = trace(button.as_mut(), 8, 10);
+        // THEN the good parameters are accepted and the bad rejected
+        assert_eq!("-11--1--", levels(&states));
+        for (name, value) in [
+            ("bounce", "10,20,30"),
+            ("drive", "middle"),
+            ("toggles", "ten"),
+        ] {
+            assert!(registry
+                .create("switch", "S2", &Parameters::new().with(name, value))
+                .is_err());
+        }
+        assert!(registry
+            .create("button", "S3", &Parameters::new().with("presses", "10"))
+            .is_err());
+    }
+}
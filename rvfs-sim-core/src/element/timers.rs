@@ -0,0 +1,523 @@
+//! Programmable timer peripherals, configured through a register bus.
+//!
+//! The register bus follows the timing of the 6502 bus, so a timer can be attached directly to a
+//! [Mos6502](crate::element::processors::Mos6502) with an address decoder driving its chip select.  A timer can also
+//! be used standalone, with its chip select held high and its registers set when it is created.
+
+use crate::element::{bit, level, word, Element, Parameters, Registry};
+use crate::ipin::{InputPin, InputPinState};
+use crate::opin::{OutputPin, OutputPinState};
+use crate::prelude::*;
+use crate::sim::SimResult;
+use crate::time::SimDuration;
+
+/// The control register.
+const CONTROL: u8 = 0;
+/// The status register.
+const STATUS: u8 = 1;
+/// The count register.
+const COUNT: u8 = 2;
+/// The compare register.
+const COMPARE: u8 = 3;
+
+/// Control bit enabling counting.
+const ENABLE: u8 = 0x01;
+/// Control bit clearing the count after it matches the compare register.
+const CLEAR_ON_COMPARE: u8 = 0x02;
+/// Control bit enabling the overflow interrupt.
+const OVERFLOW_INTERRUPT: u8 = 0x04;
+/// Control bit enabling the compare interrupt.
+const COMPARE_INTERRUPT: u8 = 0x08;
+/// Control bits selecting the prescaler.
+const PRESCALER: u8 = 0x70;
+
+/// Status bit set when the count overflows.
+const OVERFLOWED: u8 = 0x01;
+/// Status bit set when the count matches the compare register.
+const MATCHED: u8 = 0x02;
+
+/// A programmable 8 bit timer with a prescaler, overflow and compare outputs, and an interrupt output.
+///
+/// The inputs are named `CLK`, `/RES`, `/CS`, `R/W`, `PHI2`, `RS0`, `RS1` and `D0` to `D7`, and the outputs are named
+/// `D0` to `D7`, `OVF`, `CMP` and `/IRQ`.  The data bus InputPins and OutputPins should both be connected to the same
+/// Wires.
+///
+/// `RS0` and `RS1` select one of four registers:
+///
+/// - 0, control: bit 0 enables counting, bit 1 clears the count after it matches the compare register, bits 2 and 3
+///   enable the overflow and compare interrupts, and bits 4 to 6 select a prescaler dividing `CLK` by 2 to that power.
+/// - 1, status: bit 0 is set when the count overflows, and bit 1 when it matches the compare register.  Writing a one
+///   to a bit clears it.
+/// - 2, count: the present count.  Writing it also restarts the prescaler.
+/// - 3, compare: the value the count is compared with.
+///
+/// While `/CS` is low and `PHI2` is high, the selected register is driven onto the data bus if `R/W` is high, and is
+/// written from the data bus as `PHI2` falls if `R/W` is low.  An access with an indeterminate control or register
+/// select input is ignored, and an indeterminate data bus writes nothing.
+///
+/// While counting is enabled the count advances on rising edges of `CLK`, after the prescaler.  When the count matches
+/// the compare register, `CMP` toggles, and the count is cleared instead of advancing if so configured.  When the count
+/// wraps to zero, `OVF` is high until it next advances.  `/IRQ` is pulled low, open-drain, while a status bit whose
+/// interrupt is enabled is set.  Holding `/RES` low clears every register, and an unconnected `/RES` is treated as
+/// inactive.
+///
+/// # Example
+///
+/// ```
+/// # use rvfs_sim_core::element::timers::Timer;
+/// # use rvfs_sim_core::element::Element;
+/// let mut timer = Timer::new("U1", 0);
+/// timer.write_register(0, 0x01);
+///
+/// assert_eq!(0x01, timer.read_register(0));
+/// assert_eq!("/IRQ", timer.output_pins()[10].name());
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Timer {
+    /// Name of the timer.
+    name: String,
+    /// Propagation delay from an input to the outputs.
+    delay: u64,
+    /// The control register.
+    control: u8,
+    /// The status register.
+    status: u8,
+    /// The count register.
+    count: u8,
+    /// The compare register.
+    compare: u8,
+    /// Number of `CLK` edges counted by the prescaler.
+    prescaled: u32,
+    /// Whether `OVF` is high.
+    overflow: bool,
+    /// Whether `CMP` is high.
+    matched: bool,
+}
+
+impl Timer {
+    /// Create a new Timer, with every register clear.
+    ///
+    /// # Parameters
+    ///
+    /// - `name`: Name of the timer.
+    /// - `delay`: Propagation delay from an input to the outputs.
+    pub fn new(name: &str, delay: u64) -> Self {
+        Self {
+            name: name.to_string(),
+            delay,
+            control: 0,
+            status: 0,
+            count: 0,
+            compare: 0,
+            prescaled: 0,
+            overflow: false,
+            matched: false,
+        }
+    }
+
+    /// Read a register, as a read from the register bus would.
+    ///
+    /// # Parameters
+    ///
+    /// - `register`: The register, from 0 to 3.
+    pub fn read_register(&self, register: u8) -> u8 {
+        match register & 0x03 {
+            CONTROL => self.control,
+            STATUS => self.status,
+            COUNT => self.count,
+            _ => self.compare,
+        }
+    }
+
+    /// Write a register, as a write from the register bus would.
+    ///
+    /// # Parameters
+    ///
+    /// - `register`: The register, from 0 to 3.
+    /// - `value`: The value to write.
+    pub fn write_register(&mut self, register: u8, value: u8) {
+        match register & 0x03 {
+            CONTROL => self.control = value,
+            STATUS => self.status &= !value,
+            COUNT => {
+                self.count = value;
+                self.prescaled = 0;
+            }
+            _ => self.compare = value,
+        }
+    }
+
+    /// Advance the count by one prescaled tick.
+    fn tick(&mut self) {
+        self.overflow = false;
+        if self.count == self.compare {
+            self.status |= MATCHED;
+            self.matched = !self.matched;
+            if self.control & CLEAR_ON_COMPARE != 0 {
+                self.count = 0;
+                return;
+            }
+        }
+        let (count, wrapped) = self.count.overflowing_add(1);
+        self.count = count;
+        if wrapped {
+            self.status |= OVERFLOWED;
+            self.overflow = true;
+        }
+    }
+
+    /// Query whether an enabled interrupt is pending.
+    fn interrupt(&self) -> bool {
+        // The interrupt enable bits of the control register line up with the status bits, two places higher.
+        let enabled = (self.control & (OVERFLOW_INTERRUPT | COMPARE_INTERRUPT)) >> 2;
+        self.status & enabled != 0
+    }
+}
+
+impl Element for Timer {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn input_pins(&self) -> Vec<InputPin> {
+        ["CLK", "/RES", "/CS", "R/W", "PHI2", "RS0", "RS1"]
+            .into_iter()
+            .map(str::to_string)
+            .chain((0..8).map(|i| format!("D{i}")))
+            .map(|name| InputPin::new(&name))
+            .collect()
+    }
+
+    fn output_pins(&self) -> Vec<OutputPin> {
+        (0..8)
+            .map(|i| format!("D{i}"))
+            .map(|name| {
+                OutputPin::new(
+                    &name,
+                    SimDuration::from_ticks(self.delay),
+                    OutputPinState::HighImpedance,
+                )
+            })
+            .chain(["OVF", "CMP"].into_iter().map(|name| {
+                OutputPin::new(
+                    name,
+                    SimDuration::from_ticks(self.delay),
+                    OutputPinState::Low,
+                )
+            }))
+            .chain([OutputPin::new(
+                "/IRQ",
+                SimDuration::from_ticks(self.delay),
+                OutputPinState::HighImpedance,
+            )])
+            .collect()
+    }
+
+    fn step(
+        &mut self,
+        inputs: &[InputPin],
+        outputs: &mut [OutputPin],
+        _delta_t: u64,
+    ) -> Result<SimResult, String> {
+        let [clock, reset, select, rw, strobe, rs0, rs1, data @ ..] = inputs else {
+            return Err(format!("Timer \"{}\": unexpected pins", self.name));
+        };
+        if data.len() != 8 || outputs.len() != 11 {
+            return Err(format!("Timer \"{}\": unexpected pins", self.name));
+        }
+        let (data_out, signals) = outputs.split_at_mut(8);
+        let [overflow_out, compare_out, irq_out] = signals else {
+            return Err(format!("Timer \"{}\": unexpected pins", self.name));
+        };
+
+        let register = match (bit(rs0), bit(rs1)) {
+            (Some(low), Some(high)) => Some(u8::from(low) | (u8::from(high) << 1)),
+            _ => None,
+        };
+        let selected = select.state() == InputPinState::Low;
+        if reset.state() == InputPinState::Low {
+            *self = Self::new(&self.name, self.delay);
+        } else {
+            if let (true, Some(false), Some(register)) =
+                (selected && strobe.falling(), bit(rw), register)
+            {
+                if let Some(value) = word(data) {
+                    self.write_register(register, value as u8);
+                }
+            }
+            if self.control & ENABLE != 0 && clock.rising() {
+                self.prescaled += 1;
+                if self.prescaled >= 1 << ((self.control & PRESCALER) >> 4) {
+                    self.prescaled = 0;
+                    self.tick();
+                }
+            }
+        }
+
+        let read = register.filter(|_| {
+            selected && strobe.state() == InputPinState::High && rw.state() == InputPinState::High
+        });
+        let value = read.map(|register| self.read_register(register));
+        for (i, output) in data_out.iter_mut().enumerate() {
+            output.drive(level(value.map(|value| (value >> i) & 1 != 0)));
+        }
+        overflow_out.drive(level(Some(self.overflow)));
+        compare_out.drive(level(Some(self.matched)));
+        irq_out.drive(if self.interrupt() {
+            OutputPinState::Low
+        } else {
+            OutputPinState::HighImpedance
+        });
+
+        Ok(SimResult::Continuing)
+    }
+
+    #[cfg(feature = "serde")]
+    fn save_state(&self) -> Result<serde_json::Value, String> {
+        super::save_state((
+            self.control,
+            self.status,
+            self.count,
+            self.compare,
+            self.prescaled,
+            self.overflow,
+            self.matched,
+        ))
+    }
+
+    #[cfg(feature = "serde")]
+    fn restore_state(&mut self, state: serde_json::Value) -> Result<(), String> {
+        (
+            self.control,
+            self.status,
+            self.count,
+            self.compare,
+            self.prescaled,
+            self.overflow,
+            self.matched,
+        ) = super::restore_state(&self.name, state)?;
+        Ok(())
+    }
+
+    fn box_clone(&self) -> Box<dyn Element> {
+        Box::new(self.clone())
+    }
+}
+
+/// Register every kind of timer.
+///
+/// The `timer` kind takes the `delay` parameter, and the `control`, `count` and `compare` parameters holding the
+/// starting values of those registers in decimal, so that a standalone timer can run without a register bus.
+///
+/// # Parameters
+///
+/// - `registry`: The Registry to add the timers to.
+pub fn register(registry: &mut Registry) {
+    registry.register("timer", |name, parameters: &Parameters| {
+        let mut timer = Timer::new(name, parameters.get_or("delay", 0)?);
+        timer.write_register(CONTROL, parameters.get_or("control", 0)?);
+        timer.write_register(COUNT, parameters.get_or("count", 0)?);
+        timer.write_register(COMPARE, parameters.get_or("compare", 0)?);
+        Ok(Box::new(timer))
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::element::testing::inputs;
+
+    /// A timer stepped with its inputs set directly.
+    struct Bench {
+        /// The timer.
+        timer: Timer,
+        /// The timer's outputs.
+        outputs: Vec<OutputPin>,
+        /// The levels of the timer's inputs at the previous step.
+        previous: [f32; 15],
+    }
+
+    impl Bench {
+        /// Build a bench with a new timer, with `/RES` and `/CS` high.
+        fn new() -> Self {
+            let timer = Timer::new("U1", 0);
+            let mut previous = [0.0; 15];
+            previous[1] = 1.0;
+            previous[2] = 1.0;
+            Self {
+                outputs: timer.output_pins(),
+                timer,
+                previous,
+            }
+        }
+
+        /// Step the timer with some inputs changed from the previous step.
+        fn step(&mut self, changes: &[(usize, f32)]) {
+            let mut levels = self.previous;
+            for &(i, level) in changes {
+                levels[i] = level;
+            }
+            self.timer
+                .step(&inputs(self.previous, levels), &mut self.outputs, 1)
+                .unwrap();
+            self.outputs
+                .iter_mut()
+                .for_each(|output| output.step(SimDuration::from_ticks(1)));
+            self.previous = levels;
+        }
+
+        /// Obtain the level of an output, as a bit.
+        fn output(&self, i: usize) -> Option<bool> {
+            match self.outputs[i].state() {
+                OutputPinState::High => Some(true),
+                OutputPinState::Low => Some(false),
+                OutputPinState::HighImpedance | OutputPinState::Unknown => None,
+            }
+        }
+
+        /// Perform a bus cycle, writing a value or reading when none is given.
+        fn access(&mut self, register: u8, value: Option<u8>) -> Option<u8> {
+            let mut changes = vec![
+                (2, 0.0),
+                (3, f32::from(u8::from(value.is_none()))),
+                (5, f32::from(register & 1)),
+                (6, f32::from(register >> 1)),
+            ];
+            for i in 0..8 {
+                changes.push((
+                    7 + i,
+                    value.map_or(0.5, |value| f32::from((value >> i) & 1)),
+                ));
+            }
+            self.step(&changes);
+            self.step(&[(4, 1.0)]);
+            let read = (0..8).try_fold(0, |read, i| Some(read | (u8::from(self.output(i)?) << i)));
+            self.step(&[(4, 0.0)]);
+            self.step(&[(2, 1.0), (3, 1.0)]);
+            read
+        }
+
+        /// Run a number of `CLK` cycles, returning the levels of `OVF` and `CMP` after each.
+        fn clock(&mut self, cycles: usize) -> Vec<(bool, bool)> {
+            (0..cycles)
+                .map(|_| {
+                    self.step(&[(0, 1.0)]);
+                    self.step(&[(0, 0.0)]);
+                    (self.output(8).unwrap(), self.output(9).unwrap())
+                })
+                .collect()
+        }
+    }
+
+    #[test]
+    fn timer_bus_access() {
+        // GIVEN a timer on a register bus
+        let mut bench = Bench::new();
+        // WHEN its registers are written and read back over the bus
+        bench.access(COMPARE, Some(0xa5));
+        bench.access(COUNT, Some(0x10));
+        // THEN the values are stored, and the data bus is only driven during reads
+        assert_eq!(Some(0xa5), bench.access(COMPARE, None));
+        assert_eq!(Some(0x10), bench.access(COUNT, None));
+        assert_eq!(None, bench.output(0));
+        assert_eq!(0xa5, bench.timer.read_register(COMPARE));
+    }
+    #[test]
+    fn timer_prescaler_and_overflow() {
+        // GIVEN a timer near the end of its count, prescaled by 4, with the overflow interrupt enabled
+        let mut bench = Bench::new();
+        bench.access(COUNT, Some(0xfe));
+        bench.access(COMPARE, Some(0x80));
+        bench.access(CONTROL, Some(ENABLE | OVERFLOW_INTERRUPT | 0x20));
+        // WHEN it is clocked
+        let levels = bench.clock(12);
+        // THEN it advances every fourth cycle, overflowing on the second advance and raising an interrupt
+        let overflow: Vec<bool> = levels.iter().map(|(overflow, _)| *overflow).collect();
+        assert_eq!(
+            vec![false, false, false, false, false, false, false, true, true, true, true, false],
+            overflow
+        );
+        assert_eq!(Some(false), bench.output(10));
+        assert_eq!(Some(OVERFLOWED), bench.access(STATUS, None));
+        // AND THEN clearing the status releases the interrupt
+        bench.access(STATUS, Some(OVERFLOWED));
+        assert_eq!(None, bench.output(10));
+    }
+    #[test]
+    fn timer_clear_on_compare() {
+        // GIVEN a timer which clears on matching 2, with the compare interrupt disabled
+        let mut bench = Bench::new();
+        bench.access(COMPARE, Some(2));
+        bench.access(
+            CONTROL,
+            Some(ENABLE | CLEAR_ON_COMPARE | OVERFLOW_INTERRUPT),
+        );
+        // WHEN it is clocked
+        let levels = bench.clock(9);
+        // THEN the compare output toggles every third cycle, with no overflow or interrupt
+        let compare: Vec<bool> = levels.iter().map(|(_, compare)| *compare).collect();
+        assert_eq!(
+            vec![false, false, true, true, true, false, false, false, true],
+            compare
+        );
+        assert!(levels.iter().all(|(overflow, _)| !overflow));
+        assert_eq!(None, bench.output(10));
+        assert_eq!(Some(MATCHED), bench.access(STATUS, None));
+    }
+    #[test]
+    fn timer_reset() {
+        // GIVEN a running timer
+        let mut bench = Bench::new();
+        bench.access(CONTROL, Some(ENABLE));
+        bench.clock(5);
+        // WHEN it is reset
+        bench.step(&[(1, 0.0)]);
+        bench.step(&[(1, 1.0)]);
+        bench.clock(5);
+        // THEN its registers are clear and it no longer counts
+        assert_eq!(Some(0), bench.access(COUNT, None));
+        assert_eq!(Some(0), bench.access(CONTROL, None));
+    }
+    #[test]
+    fn timer_registered() {
+        // GIVEN the standard registry
+        let registry = Registry::standard();
+        // WHEN a standalone timer is created, running from the start
+        let mut timer = registry
+            .create(
+                "timer",
+                "U1",
+                &Parameters::new().with("control", "3").with("compare", "0"),
+            )
+            .unwrap();
+        let mut previous = [0.0; 15];
+        previous[1] = 1.0;
+        previous[2] = 1.0;
+        let mut outputs = timer.output_pins();
+        let mut compare = Vec::new();
+        for _ in 0..3 {
+            for clock in [1.0, 0.0] {
+                let mut levels = previous;
+                levels[0] = clock;
+                timer
+                    .step(&inputs(previous, levels), &mut outputs, 1)
+                    .unwrap();
+                outputs[9].step(SimDuration::from_ticks(1));
+                previous = levels;
+            }
+            compare.push(outputs[9].state());
+        }
+        // THEN it toggles its compare output on every cycle
+        assert_eq!(
+            vec![
+                OutputPinState::High,
+                OutputPinState::Low,
+                OutputPinState::High
+            ],
+            compare
+        );
+        assert!(registry
+            .create("timer", "U2", &Parameters::new().with("compare", "256"))
+            .is_err());
+    }
+}
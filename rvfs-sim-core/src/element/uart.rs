@@ -0,0 +1,566 @@
+//! Asynchronous serial ports, bridging firmware running in the circuit and a console on the host.
+//!
+//! A [Uart] is an Element which transmits bytes on its `TX` pin and receives them on its `RX` pin, framed as one start
+//! bit, eight data bits sent least significant first and one stop bit (8N1), with the line idling high.  Its other side
+//! is a [UartHost] through which a testbench queues bytes to transmit and takes the bytes received, or which is bridged
+//! to the standard input and output of the host, or to a device such as a pseudo-terminal.
+
+use crate::element::{bit, level, Element, Parameters, Registry};
+use crate::ipin::InputPin;
+use crate::opin::{OutputPin, OutputPinState};
+use crate::sim::SimResult;
+use crate::time::SimDuration;
+use std::collections::VecDeque;
+use std::fmt;
+use std::fs::OpenOptions;
+use std::io::{self, ErrorKind, Read, Write};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::thread;
+
+/// Default bit period of a port instantiated from a configuration file, which is 115200 baud if a time unit is one
+/// nanosecond.
+const DEFAULT_PERIOD: u64 = 8_680;
+
+/// Number of bits in a frame, including the start and stop bits.
+const FRAME_BITS: u64 = 10;
+
+/// The state of a port shared between a [Uart] and its [UartHost].
+struct LineState {
+    /// Bytes queued and not yet transmitted.
+    transmit: VecDeque<u8>,
+    /// Bytes received and not yet taken.
+    received: Vec<u8>,
+    /// Number of frames received without a valid stop bit or with an undefined level.
+    framing_errors: u64,
+    /// Destination of the bytes received while the port is bridged.
+    output: Option<Box<dyn Write + Send>>,
+}
+
+impl fmt::Debug for LineState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LineState")
+            .field("transmit", &self.transmit)
+            .field("received", &self.received)
+            .field("framing_errors", &self.framing_errors)
+            .field("bridged", &self.output.is_some())
+            .finish()
+    }
+}
+
+/// The host side of a [Uart].
+///
+/// Bytes queued through the host are transmitted from the next step of the Simulation.  The state is shared by every
+/// copy of the port, so a Simulation holding the port refuses to [step back](crate::sim::Simulation::step_back).
+#[derive(Debug, Clone)]
+pub struct UartHost {
+    /// The shared state of the port.
+    state: Arc<Mutex<LineState>>,
+}
+
+impl UartHost {
+    /// Queue bytes to be transmitted, after any already queued.
+    ///
+    /// # Parameters
+    ///
+    /// - `bytes`: The bytes to transmit.
+    pub fn send(&self, bytes: &[u8]) {
+        self.lock().transmit.extend(bytes);
+    }
+
+    /// Obtain the number of bytes queued and not yet transmitted, excluding any frame being transmitted.
+    pub fn pending(&self) -> usize {
+        self.lock().transmit.len()
+    }
+
+    /// Take the bytes received since they were last taken, in the order they were received.
+    ///
+    /// Bytes received while the port is bridged are written to the bridge instead.
+    pub fn take_received(&self) -> Vec<u8> {
+        std::mem::take(&mut self.lock().received)
+    }
+
+    /// Obtain the number of frames received without a valid stop bit or with an undefined level, which are discarded.
+    pub fn framing_errors(&self) -> u64 {
+        self.lock().framing_errors
+    }
+
+    /// Bridge the port to a pair of streams, so that bytes read from one are transmitted and bytes received are written
+    /// to the other.
+    ///
+    /// The input is read by a background thread until it ends or fails, so bytes arrive at whatever simulation time the
+    /// host delivers them.  The output is flushed after every byte.
+    ///
+    /// # Parameters
+    ///
+    /// - `input`: Source of the bytes to transmit.
+    /// - `output`: Destination of the bytes received.
+    pub fn bridge(
+        &self,
+        mut input: impl Read + Send + 'static,
+        output: impl Write + Send + 'static,
+    ) {
+        self.lock().output = Some(Box::new(output));
+        let host = self.clone();
+        thread::spawn(move || {
+            let mut buffer = [0; 256];
+            loop {
+                match input.read(&mut buffer) {
+                    Ok(0) => break,
+                    Ok(count) => host.send(&buffer[..count]),
+                    Err(err) if err.kind() == ErrorKind::Interrupted => {}
+                    Err(_) => break,
+                }
+            }
+        });
+    }
+
+    /// Bridge the port to the standard input and output of the host, so that firmware can print to and read from the
+    /// console.
+    ///
+    /// Standard input is consumed by the bridge, so this should not be combined with an interactive session reading
+    /// commands from it.
+    pub fn bridge_console(&self) {
+        self.bridge(io::stdin(), io::stdout());
+    }
+
+    /// Bridge the port to a device opened for reading and writing, such as a pseudo-terminal or a serial port.
+    ///
+    /// A terminal program can be attached to a port by creating a linked pair of pseudo-terminals, for example with
+    /// `socat -d -d pty,raw,echo=0 pty,raw,echo=0`, and bridging the port to one of them.
+    ///
+    /// # Parameters
+    ///
+    /// - `path`: Path of the device.
+    pub fn bridge_device(&self, path: &str) -> Result<(), String> {
+        let output = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(path)
+            .map_err(|err| format!("Failed to open UART device {path}: {err}"))?;
+        let input = output
+            .try_clone()
+            .map_err(|err| format!("Failed to open UART device {path}: {err}"))?;
+        self.bridge(input, output);
+        Ok(())
+    }
+
+    /// Lock the shared state.
+    fn lock(&self) -> MutexGuard<'_, LineState> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// A frame being transmitted or received.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+struct Frame {
+    /// The bits of the frame, with the start bit in the least significant bit.
+    bits: u16,
+    /// Time elapsed since the start of the start bit.
+    elapsed: u64,
+    /// Number of bits sampled so far, when receiving.
+    sampled: u64,
+    /// Whether an undefined level has been sampled, when receiving.
+    undefined: bool,
+}
+
+impl Frame {
+    /// Create a new Frame.
+    ///
+    /// # Parameters
+    ///
+    /// - `bits`: The bits of the frame, with the start bit in the least significant bit.
+    /// - `elapsed`: Time elapsed since the start of the start bit.
+    fn new(bits: u16, elapsed: u64) -> Self {
+        Self {
+            bits,
+            elapsed,
+            sampled: 0,
+            undefined: false,
+        }
+    }
+}
+
+/// An asynchronous serial port, whose bytes are exchanged through its [UartHost].
+///
+/// The input is named `RX` and the output `TX`.  Each bit lasts for the bit period, counted in simulation time units,
+/// so the baud rate is the number of time units in a second divided by the period.  The receiver starts a frame on a
+/// falling edge of `RX` and samples each bit in its middle, so the period should span several simulation steps.  A
+/// frame whose start bit is no longer low when sampled is ignored as a glitch.
+///
+/// # Example
+///
+/// ```
+/// # use rvfs_sim_core::element::uart::Uart;
+/// let uart = Uart::new("U1", 8_680, 0).unwrap();
+/// let host = uart.host();
+///
+/// host.send(b"hello\n");
+/// assert_eq!(6, host.pending());
+/// assert!(host.take_received().is_empty());
+/// assert!(Uart::new("U2", 0, 0).is_err());
+/// ```
+#[derive(Debug, Clone)]
+pub struct Uart {
+    /// Name of the port.
+    name: String,
+    /// Duration of each bit.
+    period: u64,
+    /// Propagation delay of the TX pin.
+    delay: u64,
+    /// The frame being transmitted, if any.
+    transmitting: Option<Frame>,
+    /// The frame being received, if any.
+    receiving: Option<Frame>,
+    /// The host side of the port.
+    host: UartHost,
+}
+
+impl Uart {
+    /// Create a new Uart.
+    ///
+    /// # Parameters
+    ///
+    /// - `name`: Name of the port.
+    /// - `period`: Duration of each bit, which must be at least one time unit.
+    /// - `delay`: Propagation delay of the TX pin.
+    pub fn new(name: &str, period: u64, delay: u64) -> Result<Self, String> {
+        if period == 0 {
+            return Err(format!(
+                "UART \"{name}\": bit period must be at least one time unit"
+            ));
+        }
+
+        Ok(Self {
+            name: name.to_string(),
+            period,
+            delay,
+            transmitting: None,
+            receiving: None,
+            host: UartHost {
+                state: Arc::new(Mutex::new(LineState {
+                    transmit: VecDeque::new(),
+                    received: Vec::new(),
+                    framing_errors: 0,
+                    output: None,
+                })),
+            },
+        })
+    }
+
+    /// Obtain the host side of the port.
+    pub fn host(&self) -> UartHost {
+        self.host.clone()
+    }
+
+    /// Advance the transmitter, starting the next queued frame once the last has finished, and obtain the level to
+    /// drive on `TX`.
+    ///
+    /// # Parameters
+    ///
+    /// - `state`: The shared state of the port.
+    /// - `delta_t`: Time elapsed since the last step.
+    fn transmit(&mut self, state: &mut LineState, delta_t: u64) -> bool {
+        let length = FRAME_BITS * self.period;
+        let mut overrun = 0;
+        if let Some(frame) = &mut self.transmitting {
+            frame.elapsed += delta_t;
+            if frame.elapsed >= length {
+                overrun = (frame.elapsed - length).min(self.period - 1);
+                self.transmitting = None;
+            }
+        }
+        if self.transmitting.is_none() {
+            self.transmitting = state
+                .transmit
+                .pop_front()
+                .map(|byte| Frame::new((u16::from(byte) << 1) | 1 << 9, overrun));
+        }
+
+        self.transmitting
+            .is_none_or(|frame| (frame.bits >> (frame.elapsed / self.period)) & 1 != 0)
+    }
+
+    /// Advance the receiver, sampling the bits of a frame as their middles are reached and delivering the byte once
+    /// the stop bit has been sampled.
+    ///
+    /// # Parameters
+    ///
+    /// - `state`: The shared state of the port.
+    /// - `rx`: The RX pin.
+    /// - `delta_t`: Time elapsed since the last step.
+    fn receive(
+        &mut self,
+        state: &mut LineState,
+        rx: &InputPin,
+        delta_t: u64,
+    ) -> Result<(), String> {
+        let Some(frame) = &mut self.receiving else {
+            if rx.falling() {
+                self.receiving = Some(Frame::new(0, 0));
+            }
+            return Ok(());
+        };
+
+        frame.elapsed += delta_t;
+        while frame.sampled < FRAME_BITS
+            && frame.elapsed >= self.period / 2 + frame.sampled * self.period
+        {
+            let sample = bit(rx);
+            if frame.sampled == 0 && sample != Some(false) {
+                self.receiving = None;
+                return Ok(());
+            }
+            frame.undefined |= sample.is_none();
+            frame.bits |= u16::from(sample == Some(true)) << frame.sampled;
+            frame.sampled += 1;
+        }
+        if frame.sampled < FRAME_BITS {
+            return Ok(());
+        }
+
+        let frame = *frame;
+        self.receiving = None;
+        if frame.undefined || frame.bits >> 9 == 0 {
+            state.framing_errors += 1;
+            return Ok(());
+        }
+        let byte = (frame.bits >> 1) as u8;
+        match &mut state.output {
+            Some(output) => output
+                .write_all(&[byte])
+                .and_then(|_| output.flush())
+                .map_err(|err| format!("UART \"{}\": failed to write to bridge: {err}", self.name)),
+            None => {
+                state.received.push(byte);
+                Ok(())
+            }
+        }
+    }
+}
+
+impl Element for Uart {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn input_pins(&self) -> Vec<InputPin> {
+        vec![InputPin::new("RX")]
+    }
+
+    fn output_pins(&self) -> Vec<OutputPin> {
+        vec![OutputPin::new(
+            "TX",
+            SimDuration::from_ticks(self.delay),
+            OutputPinState::High,
+        )]
+    }
+
+    fn step(
+        &mut self,
+        inputs: &[InputPin],
+        outputs: &mut [OutputPin],
+        delta_t: u64,
+    ) -> Result<SimResult, String> {
+        let ([rx], [tx]) = (inputs, outputs) else {
+            return Err(format!("UART \"{}\": unexpected pins", self.name));
+        };
+
+        let host = self.host.clone();
+        let mut state = host.lock();
+        tx.drive(level(Some(self.transmit(&mut state, delta_t))));
+        self.receive(&mut state, rx, delta_t)?;
+
+        Ok(SimResult::Continuing)
+    }
+
+    fn is_replayable(&self) -> bool {
+        false
+    }
+
+    fn box_clone(&self) -> Box<dyn Element> {
+        Box::new(self.clone())
+    }
+}
+
+/// Register every kind of UART element.
+///
+/// The `uart` kind takes the `period` (default 8680) and `delay` parameters.  Setting the `console` parameter to
+/// `true` bridges the port to the standard input and output of the host, while the `device` parameter bridges it to the
+/// device at the given path instead.
+///
+/// # Parameters
+///
+/// - `registry`: The Registry to add the UART elements to.
+pub fn register(registry: &mut Registry) {
+    registry.register("uart", |name, parameters: &Parameters| {
+        let uart = Uart::new(
+            name,
+            parameters.get_or("period", DEFAULT_PERIOD)?,
+            parameters.get_or("delay", 0)?,
+        )?;
+        let device: String = parameters.get_or("device", String::new())?;
+        match (parameters.get_or("console", false)?, device.is_empty()) {
+            (true, false) => {
+                return Err(format!(
+                    "UART \"{name}\": cannot bridge to both the console and a device"
+                ))
+            }
+            (true, true) => uart.host().bridge_console(),
+            (false, false) => uart.host().bridge_device(&device)?,
+            (false, true) => {}
+        }
+        Ok(Box::new(uart))
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sim::Simulation;
+    use crate::wire::{Wire, WirePull};
+    use std::io::Cursor;
+
+    /// A destination of bytes which can be read back while it is owned by a bridge.
+    #[derive(Clone, Default)]
+    struct Sink(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Sink {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    /// Build a simulation of two ports with a bit period of 100 whose TX pins each drive the RX pin of the other
+    /// through pulled up Wires, returning the simulation and the hosts of the ports.
+    fn ports() -> (Simulation, UartHost, UartHost) {
+        let mut sim = Simulation::new(SimDuration::from_ticks(10));
+        let a = Uart::new("A", 100, 0).unwrap();
+        let b = Uart::new("B", 100, 0).unwrap();
+        let (host_a, host_b) = (a.host(), b.host());
+        let a = sim.add_element(Box::new(a)).unwrap();
+        let b = sim.add_element(Box::new(b)).unwrap();
+        for (name, from, to) in [("A_TX", a, b), ("B_TX", b, a)] {
+            let wire = sim.add_wire(Wire::new(name, WirePull::Up)).unwrap();
+            sim.connect_output(sim.output_pin(from, "TX").unwrap(), wire)
+                .unwrap();
+            sim.connect_input(wire, sim.input_pin(to, "RX").unwrap())
+                .unwrap();
+        }
+        (sim, host_a, host_b)
+    }
+
+    /// Step a simulation a number of times.
+    fn run(sim: &mut Simulation, steps: usize) {
+        for _ in 0..steps {
+            sim.step().unwrap();
+        }
+    }
+
+    #[test]
+    fn uart_exchange() {
+        // GIVEN two connected ports
+        let (mut sim, a, b) = ports();
+        run(&mut sim, 5);
+        // WHEN each queues bytes for the other
+        a.send(b"Hi\x00\xff");
+        b.send(b"ok");
+        run(&mut sim, 3);
+        assert_eq!(3, a.pending());
+        // THEN the bytes arrive one frame time apart, in order
+        run(&mut sim, 100);
+        assert_eq!(b"H".to_vec(), b.take_received());
+        assert_eq!(b"o".to_vec(), a.take_received());
+        run(&mut sim, 300);
+        assert_eq!(b"i\x00\xff".to_vec(), b.take_received());
+        assert_eq!(b"k".to_vec(), a.take_received());
+        assert_eq!(0, a.pending());
+        assert_eq!((0, 0), (a.framing_errors(), b.framing_errors()));
+    }
+    #[test]
+    fn uart_bit_timing() {
+        // GIVEN a port transmitting a byte onto a watched Wire
+        let (mut sim, a, _) = ports();
+        let wire = sim.wire_by_target("A.TX").unwrap();
+        a.send(&[0b0101_0011]);
+        // WHEN the level of the Wire is sampled every bit period
+        let mut levels = Vec::new();
+        for _ in 0..11 {
+            run(&mut sim, 10);
+            levels.push(sim.logic(wire).unwrap().symbol());
+        }
+        // THEN it carries the start bit, the data least significant bit first and the stop bit, then idles high
+        assert_eq!("01100101011", levels.into_iter().collect::<String>());
+    }
+    #[test]
+    fn uart_framing_error() {
+        // GIVEN a port receiving from a Wire
+        let (mut sim, _, b) = ports();
+        let wire = sim.wire_by_target("B.RX").unwrap();
+        run(&mut sim, 5);
+        // WHEN the Wire is held low for longer than a frame, and then pulsed low too briefly to be a start bit
+        sim.force_wire(wire, Some(WirePull::Down)).unwrap();
+        run(&mut sim, 150);
+        sim.force_wire(wire, Some(WirePull::Up)).unwrap();
+        run(&mut sim, 50);
+        sim.force_wire(wire, Some(WirePull::Down)).unwrap();
+        run(&mut sim, 2);
+        sim.force_wire(wire, Some(WirePull::Up)).unwrap();
+        run(&mut sim, 100);
+        // THEN the break is counted as a framing error, and neither delivers a byte
+        assert_eq!(1, b.framing_errors());
+        assert!(b.take_received().is_empty());
+    }
+    #[test]
+    fn uart_bridge() {
+        // GIVEN two connected ports, the first bridged from a stream and the second bridged to a sink
+        let (mut sim, a, b) = ports();
+        let sink = Sink::default();
+        a.bridge(Cursor::new(b"boot\n".to_vec()), io::sink());
+        b.bridge(io::empty(), sink.clone());
+        // WHEN the simulation runs for long enough to transmit the stream
+        let mut steps = 0;
+        while sink.0.lock().unwrap().len() < 5 && steps < 10_000 {
+            run(&mut sim, 1);
+            steps += 1;
+        }
+        // THEN every byte of the stream is written to the sink rather than kept by the host
+        assert_eq!(b"boot\n".to_vec(), *sink.0.lock().unwrap());
+        assert!(b.take_received().is_empty());
+    }
+    #[test]
+    fn uart_registered() {
+        // GIVEN the standard Registry
+        let registry = Registry::standard();
+        // WHEN UARTs are created from parameters
+        let uart = registry
+            .create("uart", "U1", &Parameters::new().with("period", "100"))
+            .unwrap();
+        // THEN they have the expected pins, and invalid parameters are refused
+        assert_eq!(vec![InputPin::new("RX")], uart.input_pins());
+        assert_eq!(1, uart.output_pins().len());
+        assert!(registry
+            .create("uart", "U2", &Parameters::new().with("period", "0"))
+            .is_err());
+        assert!(registry
+            .create(
+                "uart",
+                "U3",
+                &Parameters::new()
+                    .with("console", "true")
+                    .with("device", "/dev/null")
+            )
+            .is_err());
+        assert!(registry
+            .create(
+                "uart",
+                "U4",
+                &Parameters::new().with("device", "/nonexistent/tty")
+            )
+            .is_err());
+    }
+}
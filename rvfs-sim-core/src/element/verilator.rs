@@ -0,0 +1,593 @@
+//! A bridge to RTL models compiled with [Verilator](https://www.veripool.org/verilator/), so that synthesizable blocks
+//! can be mixed with behavioural Elements.
+//!
+//! A Verilated model is a C++ class, so it is exported through a small C interface, generated by the
+//! `include/rvfs_sim_verilator.h` header in this crate, and compiled into a shared library which the bridge loads.  Any
+//! other implementation of [Model] can also be bridged, such as a hand-written model in tests.
+
+use crate::element::{bit, level, Element, Parameters, Registry};
+use crate::ipin::InputPin;
+use crate::opin::{OutputPin, OutputPinState};
+use crate::sim::SimResult;
+use crate::time::SimDuration;
+use libloading::Library;
+use std::ffi::{c_char, c_int, c_uint, c_void, CStr};
+use std::fmt;
+use std::path::Path;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+
+/// Version of the C interface exported by `rvfs_sim_verilator.h`.
+const MODEL_ABI_VERSION: u32 = 1;
+
+/// The direction of a [Port] of a Model.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum PortDirection {
+    /// The port is driven by the simulation.
+    Input,
+    /// The port is driven by the Model.
+    Output,
+}
+
+/// A top-level port of a Model.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Port {
+    /// Name of the port.
+    pub name: String,
+    /// Direction of the port.
+    pub direction: PortDirection,
+    /// Width of the port in bits, from 1 to 64.
+    pub width: u32,
+}
+
+impl Port {
+    /// Obtain the names of the pins of the port: its own name for a single bit, or its name followed by each bit
+    /// number, least significant first.
+    fn pin_names(&self) -> Vec<String> {
+        if self.width == 1 {
+            vec![self.name.clone()]
+        } else {
+            (0..self.width)
+                .map(|bit| format!("{}{bit}", self.name))
+                .collect()
+        }
+    }
+}
+
+/// A cycle-based model advanced in lockstep with a Simulation, such as a Verilated RTL block.
+///
+/// Ports are referred to by their index in the list given by [ports](Model::ports).
+pub trait Model: Send {
+    /// Obtain the top-level ports of the Model.
+    fn ports(&self) -> Vec<Port>;
+
+    /// Set the value of an input port, taking effect at the next evaluation.
+    ///
+    /// # Parameters
+    ///
+    /// - `port`: Index of the port.
+    /// - `value`: The value, least significant bit first.
+    fn set_input(&mut self, port: usize, value: u64);
+
+    /// Obtain the value of an output port from the last evaluation.
+    ///
+    /// # Parameters
+    ///
+    /// - `port`: Index of the port.
+    fn output(&self, port: usize) -> u64;
+
+    /// Evaluate the Model with its present inputs.
+    ///
+    /// # Parameters
+    ///
+    /// - `time`: The simulation time, counted from the start of the Simulation.
+    fn eval(&mut self, time: u64);
+}
+
+/// The functions of the C interface exported by a model library.
+struct ModelFunctions {
+    /// Creates an instance of the model.
+    new: unsafe extern "C" fn() -> *mut c_void,
+    /// Finishes and deletes an instance.
+    delete: unsafe extern "C" fn(*mut c_void),
+    /// Obtains the number of ports.
+    port_count: unsafe extern "C" fn() -> usize,
+    /// Obtains the name of a port.
+    port_name: unsafe extern "C" fn(usize) -> *const c_char,
+    /// Obtains whether a port is an output.
+    port_output: unsafe extern "C" fn(usize) -> c_int,
+    /// Obtains the width of a port.
+    port_width: unsafe extern "C" fn(usize) -> c_uint,
+    /// Sets an input port of an instance.
+    set_input: unsafe extern "C" fn(*mut c_void, usize, u64),
+    /// Gets an output port of an instance.
+    get_output: unsafe extern "C" fn(*mut c_void, usize) -> u64,
+    /// Evaluates an instance at a simulation time.
+    eval: unsafe extern "C" fn(*mut c_void, u64),
+}
+
+/// A Model loaded from a shared library exporting the C interface generated by `rvfs_sim_verilator.h`.
+pub struct LibraryModel {
+    /// The functions of the C interface.
+    functions: ModelFunctions,
+    /// The instance of the model created by the library.
+    model: *mut c_void,
+    /// The library, kept loaded until the instance is deleted.
+    _library: Library,
+}
+
+// The instance is only used through the functions of its library, and a LibraryModel is used from one thread at a
+// time, since Model methods take it by reference.
+unsafe impl Send for LibraryModel {}
+
+impl LibraryModel {
+    /// Load a model library and create an instance of its model.
+    ///
+    /// # Parameters
+    ///
+    /// - `path`: Path of the shared library.
+    ///
+    /// # Safety
+    ///
+    /// Loading a library runs its initialisation code, and its functions are trusted once its interface version has
+    /// been checked, so the library must export a model through `rvfs_sim_verilator.h`.
+    pub unsafe fn load(path: &Path) -> Result<Self, String> {
+        let error = |error: libloading::Error| format!("Model \"{}\": {error}", path.display());
+        let library = Library::new(path).map_err(error)?;
+
+        let version = *library
+            .get::<unsafe extern "C" fn() -> u32>(b"rvfs_sim_model_abi_version\0")
+            .map_err(error)?;
+        if version() != MODEL_ABI_VERSION {
+            return Err(format!(
+                "Model \"{}\": built for model interface version {}, expected {MODEL_ABI_VERSION}",
+                path.display(),
+                version()
+            ));
+        }
+        let functions = ModelFunctions {
+            new: *library.get(b"rvfs_sim_model_new\0").map_err(error)?,
+            delete: *library.get(b"rvfs_sim_model_delete\0").map_err(error)?,
+            port_count: *library.get(b"rvfs_sim_model_port_count\0").map_err(error)?,
+            port_name: *library.get(b"rvfs_sim_model_port_name\0").map_err(error)?,
+            port_output: *library
+                .get(b"rvfs_sim_model_port_output\0")
+                .map_err(error)?,
+            port_width: *library.get(b"rvfs_sim_model_port_width\0").map_err(error)?,
+            set_input: *library.get(b"rvfs_sim_model_set_input\0").map_err(error)?,
+            get_output: *library.get(b"rvfs_sim_model_get_output\0").map_err(error)?,
+            eval: *library.get(b"rvfs_sim_model_eval\0").map_err(error)?,
+        };
+        let model = (functions.new)();
+        if model.is_null() {
+            return Err(format!(
+                "Model \"{}\": cannot create the model",
+                path.display()
+            ));
+        }
+
+        Ok(Self {
+            functions,
+            model,
+            _library: library,
+        })
+    }
+}
+
+impl Drop for LibraryModel {
+    fn drop(&mut self) {
+        unsafe { (self.functions.delete)(self.model) }
+    }
+}
+
+impl Model for LibraryModel {
+    fn ports(&self) -> Vec<Port> {
+        let functions = &self.functions;
+        unsafe {
+            (0..(functions.port_count)())
+                .map(|port| Port {
+                    name: CStr::from_ptr((functions.port_name)(port))
+                        .to_string_lossy()
+                        .into_owned(),
+                    direction: if (functions.port_output)(port) != 0 {
+                        PortDirection::Output
+                    } else {
+                        PortDirection::Input
+                    },
+                    width: (functions.port_width)(port),
+                })
+                .collect()
+        }
+    }
+
+    fn set_input(&mut self, port: usize, value: u64) {
+        unsafe { (self.functions.set_input)(self.model, port, value) }
+    }
+
+    fn output(&self, port: usize) -> u64 {
+        unsafe { (self.functions.get_output)(self.model, port) }
+    }
+
+    fn eval(&mut self, time: u64) {
+        unsafe { (self.functions.eval)(self.model, time) }
+    }
+}
+
+/// An Element bridging a [Model], mapping each bit of its ports to a pin and evaluating it on every step.
+///
+/// Input ports are set from their pins, with an indeterminate pin read as low since RTL models have two-state logic,
+/// and the Model is evaluated at the simulation time.  Its outputs then drive their pins after the propagation delay.
+/// A port of a single bit has a pin of the same name, and a wider port has a pin for each bit, named by appending the
+/// bit number to the port name.
+///
+/// The Model is shared by every copy of the Element, so a Simulation holding the Element refuses to
+/// [step back](crate::sim::Simulation::step_back).
+///
+/// # Example
+///
+/// ```
+/// # use rvfs_sim_core::element::verilator::{Model, Port, PortDirection, VerilatorBridge};
+/// # use rvfs_sim_core::element::Element;
+/// struct Inverter(u64);
+///
+/// impl Model for Inverter {
+///     fn ports(&self) -> Vec<Port> {
+///         vec![
+///             Port { name: "a".to_string(), direction: PortDirection::Input, width: 4 },
+///             Port { name: "y".to_string(), direction: PortDirection::Output, width: 4 },
+///         ]
+///     }
+///     fn set_input(&mut self, _port: usize, value: u64) {
+///         self.0 = value;
+///     }
+///     fn output(&self, _port: usize) -> u64 {
+///         !self.0 & 0xf
+///     }
+///     fn eval(&mut self, _time: u64) {}
+/// }
+///
+/// let bridge = VerilatorBridge::new("U1", Box::new(Inverter(0)), 5).unwrap();
+///
+/// assert_eq!("a0", bridge.input_pins()[0].name());
+/// assert_eq!("y3", bridge.output_pins()[3].name());
+/// ```
+#[derive(Clone)]
+pub struct VerilatorBridge {
+    /// Name of the Element.
+    name: String,
+    /// The ports of the Model.
+    ports: Vec<Port>,
+    /// Propagation delay from an input to the outputs.
+    delay: u64,
+    /// The Model.
+    model: Arc<Mutex<Box<dyn Model>>>,
+    /// Simulation time elapsed since the start of the Simulation.
+    time: u64,
+}
+
+impl VerilatorBridge {
+    /// Create a new VerilatorBridge.
+    ///
+    /// # Parameters
+    ///
+    /// - `name`: Name of the Element.
+    /// - `model`: The Model, whose ports must each be from 1 to 64 bits wide.
+    /// - `delay`: Propagation delay from an input to the outputs.
+    pub fn new(name: &str, model: Box<dyn Model>, delay: u64) -> Result<Self, String> {
+        let ports = model.ports();
+        if let Some(port) = ports.iter().find(|port| !(1..=64).contains(&port.width)) {
+            return Err(format!(
+                "Verilator bridge \"{name}\": port \"{}\" is {} bits wide, expected 1 to 64",
+                port.name, port.width
+            ));
+        }
+
+        Ok(Self {
+            name: name.to_string(),
+            ports,
+            delay,
+            model: Arc::new(Mutex::new(model)),
+            time: 0,
+        })
+    }
+
+    /// Create a new VerilatorBridge to a model loaded from a shared library.
+    ///
+    /// # Parameters
+    ///
+    /// - `name`: Name of the Element.
+    /// - `path`: Path of the shared library.
+    /// - `delay`: Propagation delay from an input to the outputs.
+    ///
+    /// # Safety
+    ///
+    /// The library must export a model through `rvfs_sim_verilator.h`, as for [LibraryModel::load].
+    pub unsafe fn load(name: &str, path: &Path, delay: u64) -> Result<Self, String> {
+        Self::new(name, Box::new(LibraryModel::load(path)?), delay)
+    }
+
+    /// Lock the Model.
+    fn lock(&self) -> MutexGuard<'_, Box<dyn Model>> {
+        self.model.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Obtain the ports in a direction, with their indices.
+    ///
+    /// # Parameters
+    ///
+    /// - `direction`: The direction.
+    fn ports(&self, direction: PortDirection) -> impl Iterator<Item = (usize, &Port)> {
+        self.ports
+            .iter()
+            .enumerate()
+            .filter(move |(_, port)| port.direction == direction)
+    }
+}
+
+impl fmt::Debug for VerilatorBridge {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("VerilatorBridge")
+            .field("name", &self.name)
+            .field("ports", &self.ports)
+            .field("delay", &self.delay)
+            .field("time", &self.time)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Element for VerilatorBridge {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn input_pins(&self) -> Vec<InputPin> {
+        self.ports(PortDirection::Input)
+            .flat_map(|(_, port)| port.pin_names())
+            .map(|name| InputPin::new(&name))
+            .collect()
+    }
+
+    fn output_pins(&self) -> Vec<OutputPin> {
+        self.ports(PortDirection::Output)
+            .flat_map(|(_, port)| port.pin_names())
+            .map(|name| {
+                OutputPin::new(
+                    &name,
+                    SimDuration::from_ticks(self.delay),
+                    OutputPinState::Low,
+                )
+            })
+            .collect()
+    }
+
+    fn step(
+        &mut self,
+        inputs: &[InputPin],
+        outputs: &mut [OutputPin],
+        delta_t: u64,
+    ) -> Result<SimResult, String> {
+        self.time += delta_t;
+        let mut model = self.lock();
+
+        let mut inputs = inputs.iter();
+        for (index, port) in self.ports(PortDirection::Input) {
+            let value = inputs
+                .by_ref()
+                .take(port.width as usize)
+                .enumerate()
+                .fold(0, |value, (i, pin)| {
+                    value | (u64::from(bit(pin).unwrap_or(false)) << i)
+                });
+            model.set_input(index, value);
+        }
+        model.eval(self.time);
+
+        let mut outputs = outputs.iter_mut();
+        for (index, port) in self.ports(PortDirection::Output) {
+            let value = model.output(index);
+            for (i, output) in outputs.by_ref().take(port.width as usize).enumerate() {
+                output.drive(level(Some(value >> i & 1 == 1)));
+            }
+        }
+
+        Ok(SimResult::Continuing)
+    }
+
+    fn is_replayable(&self) -> bool {
+        false
+    }
+
+    fn box_clone(&self) -> Box<dyn Element> {
+        Box::new(self.clone())
+    }
+}
+
+/// Register every kind of Verilator element.
+///
+/// The `verilator` kind takes the `library` parameter, giving the path of the shared library exporting the model, and
+/// the `delay` parameter (default 0).
+///
+/// # Parameters
+///
+/// - `registry`: The Registry to add the Verilator elements to.
+pub fn register(registry: &mut Registry) {
+    registry.register("verilator", |name, parameters: &Parameters| {
+        let library: String = parameters.get_or("library", String::new())?;
+        // Configuration files name model libraries built for the simulator, as described for LibraryModel::load.
+        Ok(Box::new(unsafe {
+            VerilatorBridge::load(name, Path::new(&library), parameters.get_or("delay", 0)?)?
+        }))
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::element::clocks::ClockGenerator;
+    use crate::sim::Simulation;
+    use crate::wire::{Wire, WirePull};
+    use crate::WireId;
+
+    /// A counter with a clock and reset, counting rising clock edges, as a Verilated model would.
+    struct Counter {
+        /// The clock input.
+        clk: bool,
+        /// The reset input.
+        rst: bool,
+        /// The clock at the last evaluation.
+        last_clk: bool,
+        /// The count output.
+        count: u64,
+        /// The times of the evaluations.
+        times: Arc<Mutex<Vec<u64>>>,
+    }
+
+    impl Model for Counter {
+        fn ports(&self) -> Vec<Port> {
+            [
+                ("clk", PortDirection::Input, 1),
+                ("rst", PortDirection::Input, 1),
+                ("count", PortDirection::Output, 3),
+            ]
+            .into_iter()
+            .map(|(name, direction, width)| Port {
+                name: name.to_string(),
+                direction,
+                width,
+            })
+            .collect()
+        }
+
+        fn set_input(&mut self, port: usize, value: u64) {
+            match port {
+                0 => self.clk = value == 1,
+                _ => self.rst = value == 1,
+            }
+        }
+
+        fn output(&self, _port: usize) -> u64 {
+            self.count
+        }
+
+        fn eval(&mut self, time: u64) {
+            if self.rst {
+                self.count = 0;
+            } else if self.clk && !self.last_clk {
+                self.count = (self.count + 1) % 8;
+            }
+            self.last_clk = self.clk;
+            self.times.lock().unwrap().push(time);
+        }
+    }
+
+    #[test]
+    fn verilator_bridge_pins() {
+        // GIVEN a counter model, and a model with an over-wide port
+        let counter = Counter {
+            clk: false,
+            rst: false,
+            last_clk: false,
+            count: 0,
+            times: Arc::default(),
+        };
+        let mut wide = counter.ports();
+        wide[2].width = 65;
+        struct Wide(Vec<Port>);
+        impl Model for Wide {
+            fn ports(&self) -> Vec<Port> {
+                self.0.clone()
+            }
+            fn set_input(&mut self, _port: usize, _value: u64) {}
+            fn output(&self, _port: usize) -> u64 {
+                0
+            }
+            fn eval(&mut self, _time: u64) {}
+        }
+        // WHEN they are bridged
+        let bridge = VerilatorBridge::new("U1", Box::new(counter), 0).unwrap();
+        let error = VerilatorBridge::new("U2", Box::new(Wide(wide)), 0).unwrap_err();
+        // THEN the pins follow the ports, and the over-wide port is rejected
+        assert_eq!(
+            vec!["clk", "rst"],
+            bridge
+                .input_pins()
+                .iter()
+                .map(|pin| pin.name().to_string())
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(
+            vec!["count0", "count1", "count2"],
+            bridge
+                .output_pins()
+                .iter()
+                .map(|pin| pin.name().to_string())
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(
+            "Verilator bridge \"U2\": port \"count\" is 65 bits wide, expected 1 to 64",
+            error
+        );
+    }
+    #[test]
+    fn verilator_bridge_in_simulation() {
+        // GIVEN a counter model bridged into a Simulation, clocked by a pulled down wire with reset held low
+        let times = Arc::new(Mutex::new(Vec::new()));
+        let counter = Counter {
+            clk: false,
+            rst: false,
+            last_clk: false,
+            count: 5,
+            times: times.clone(),
+        };
+        let mut sim = Simulation::new(SimDuration::from_ticks(10));
+        let bridge = sim
+            .add_element(Box::new(
+                VerilatorBridge::new("U1", Box::new(counter), 0).unwrap(),
+            ))
+            .unwrap();
+        let clock = sim
+            .add_element(Box::new(
+                ClockGenerator::new("X1", 40.0, 0.5, 0.0, 0, 0.0).unwrap(),
+            ))
+            .unwrap();
+        let clk = sim.add_wire(Wire::new("CLK", WirePull::Down)).unwrap();
+        let rst = sim.add_wire(Wire::new("RST", WirePull::Down)).unwrap();
+        sim.connect_output(sim.output_pin(clock, "CLK").unwrap(), clk)
+            .unwrap();
+        sim.connect_input(clk, sim.input_pin(bridge, "clk").unwrap())
+            .unwrap();
+        sim.connect_input(rst, sim.input_pin(bridge, "rst").unwrap())
+            .unwrap();
+        let count: Vec<WireId> = (0..3)
+            .map(|i| {
+                let wire = sim
+                    .add_wire(Wire::new(&format!("COUNT{i}"), WirePull::None))
+                    .unwrap();
+                sim.connect_output(sim.output_pin(bridge, &format!("count{i}")).unwrap(), wire)
+                    .unwrap();
+                wire
+            })
+            .collect();
+        // WHEN it is stepped over three clock periods
+        for _ in 0..12 {
+            sim.step().unwrap();
+        }
+        // THEN the model is evaluated at each step's time, and its count wraps on the outputs
+        assert_eq!(
+            (10..=120).step_by(10).collect::<Vec<_>>(),
+            *times.lock().unwrap()
+        );
+        let value = count.iter().enumerate().fold(0, |value, (i, wire)| {
+            value | (usize::from(f32::from(sim.wire(*wire).unwrap().measure()) > 0.5) << i)
+        });
+        assert_eq!(0, value);
+    }
+    #[test]
+    fn library_model_errors() {
+        // GIVEN a missing model library
+        let path = Path::new("/nonexistent/libVcounter.so");
+        // WHEN it is loaded
+        let error = unsafe { VerilatorBridge::load("U1", path, 0) }.unwrap_err();
+        // THEN the error names the library
+        assert!(error.starts_with("Model \"/nonexistent/libVcounter.so\": "));
+    }
+}
@@ -0,0 +1,429 @@
+//! Stimulus generators driven by [WaveDrom](https://wavedrom.com) waveform descriptions, so that timing diagrams from
+//! datasheets can be pasted nearly verbatim as simulation inputs.
+//!
+//! A description is WaveJSON, in either strict JSON or the relaxed JavaScript object syntax in which diagrams are
+//! usually written.  Each lane of its `signal` list becomes an output named after the lane, and its `wave` string is
+//! played out with each character spanning one tick of simulation time:
+//!
+//! - `0`, `l` and `L` drive the output low, and `1`, `h` and `H` drive it high;
+//! - `p` and `P` drive a clock cycle which is high for the first half of the tick, and `n` and `N` one which is low;
+//! - `x` and `z` release the output;
+//! - `.` and `|` continue the previous character, repeating its clock cycle.
+//!
+//! A lane may also give a `period`, multiplying the ticks spanned by each of its characters, and a `phase`, in ticks,
+//! by which it is advanced.  Lanes without a `wave`, such as spacers, are skipped, as are the labels of lane groups.
+//! Each output holds the last character of its wave once the wave is complete.
+
+use crate::element::{Element, Parameters, Registry};
+use crate::ipin::InputPin;
+use crate::json::{self, Value};
+use crate::opin::{OutputPin, OutputPinState};
+use crate::sim::SimResult;
+use crate::time::SimDuration;
+use std::fs;
+use std::path::Path;
+
+/// The level driven by a character of a wave.
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum Symbol {
+    /// Drive the output to a level for the whole character.
+    Level(OutputPinState),
+    /// Drive a clock cycle which starts high if the flag is set, or low otherwise.
+    Clock(bool),
+}
+
+/// A single lane of a waveform description.
+#[derive(Debug, Clone, PartialEq)]
+struct Lane {
+    /// Name of the lane, and of its output.
+    name: String,
+    /// The level driven by each character of the wave.
+    symbols: Vec<Symbol>,
+    /// Number of ticks spanned by each character.
+    period: f64,
+    /// Number of ticks by which the lane is advanced.
+    phase: f64,
+}
+
+impl Lane {
+    /// Read a lane from its WaveJSON description.
+    ///
+    /// # Parameters
+    ///
+    /// - `name`: Name of the generator, for error messages.
+    /// - `description`: The description of the lane, which has a `wave`.
+    /// - `wave`: The wave string of the lane.
+    fn read(name: &str, description: &Value, wave: &str) -> Result<Self, String> {
+        let lane = description
+            .get("name")
+            .and_then(Value::as_str)
+            .ok_or_else(|| format!("Waveform generator \"{name}\": lane \"{wave}\" has no name"))?;
+        let mut symbols: Vec<Symbol> = Vec::with_capacity(wave.len());
+        for c in wave.chars() {
+            symbols.push(match c {
+                '0' | 'l' | 'L' => Symbol::Level(OutputPinState::Low),
+                '1' | 'h' | 'H' => Symbol::Level(OutputPinState::High),
+                'p' | 'P' => Symbol::Clock(true),
+                'n' | 'N' => Symbol::Clock(false),
+                'x' | 'z' => Symbol::Level(OutputPinState::HighImpedance),
+                '.' | '|' => symbols
+                    .last()
+                    .copied()
+                    .unwrap_or(Symbol::Level(OutputPinState::HighImpedance)),
+                c => {
+                    return Err(format!(
+                        "Waveform generator \"{name}\": lane \"{lane}\" has unsupported wave character '{c}'"
+                    ))
+                }
+            });
+        }
+        let period = description
+            .get("period")
+            .and_then(Value::as_f64)
+            .unwrap_or(1.0);
+        if !(period.is_finite() && period > 0.0) {
+            return Err(format!(
+                "Waveform generator \"{name}\": lane \"{lane}\" period must be positive"
+            ));
+        }
+
+        Ok(Self {
+            name: lane.to_string(),
+            symbols,
+            period,
+            phase: description
+                .get("phase")
+                .and_then(Value::as_f64)
+                .unwrap_or(0.0),
+        })
+    }
+
+    /// Obtain the level of the lane at a position along its wave.
+    ///
+    /// # Parameters
+    ///
+    /// - `ticks`: The position along the wave, in ticks.
+    fn level_at(&self, ticks: f64) -> OutputPinState {
+        let position = (ticks + self.phase).max(0.0) / self.period;
+        let index = (position as usize).min(self.symbols.len().saturating_sub(1));
+        match self.symbols.get(index) {
+            None => OutputPinState::HighImpedance,
+            Some(Symbol::Level(state)) => *state,
+            Some(Symbol::Clock(high_first)) => {
+                if (position.fract() < 0.5) == *high_first {
+                    OutputPinState::High
+                } else {
+                    OutputPinState::Low
+                }
+            }
+        }
+    }
+}
+
+/// Collect the lanes with waves from a `signal` list, including those within groups.
+///
+/// # Parameters
+///
+/// - `name`: Name of the generator, for error messages.
+/// - `items`: The items of the list.
+/// - `lanes`: Collects the lanes.
+fn collect_lanes(name: &str, items: &[Value], lanes: &mut Vec<Lane>) -> Result<(), String> {
+    for item in items {
+        match item {
+            Value::Array(group) => collect_lanes(name, group, lanes)?,
+            Value::Object(_) => {
+                if let Some(wave) = item.get("wave").and_then(Value::as_str) {
+                    lanes.push(Lane::read(name, item, wave)?);
+                }
+            }
+            _ => (),
+        }
+    }
+    Ok(())
+}
+
+/// An Element which drives its outputs with the waves of a WaveDrom description.
+///
+/// # Example
+///
+/// ```
+/// # use rvfs_sim_core::element::waveforms::WaveformGenerator;
+/// # use rvfs_sim_core::element::Element;
+/// let generator = WaveformGenerator::new(
+///     "STIM",
+///     "{ signal: [ { name: 'clk', wave: 'p...' }, { name: 'cs', wave: '1.0.' } ] }",
+///     20,
+/// )
+/// .unwrap();
+///
+/// let outputs: Vec<String> = generator.output_pins().iter().map(|pin| pin.name().to_string()).collect();
+/// assert_eq!(vec!["clk", "cs"], outputs);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct WaveformGenerator {
+    /// Name of the generator.
+    name: String,
+    /// The lanes driving the outputs.
+    lanes: Vec<Lane>,
+    /// Simulation time spanned by each tick.
+    tick: u64,
+    /// Simulation time of the present step.
+    time: u64,
+}
+
+impl WaveformGenerator {
+    /// Create a new WaveformGenerator from a WaveJSON description.
+    ///
+    /// # Parameters
+    ///
+    /// - `name`: Name of the generator.
+    /// - `description`: The WaveJSON description, whose `signal` lists the lanes.
+    /// - `tick`: Simulation time spanned by each character of a wave, which must be positive.
+    pub fn new(name: &str, description: &str, tick: u64) -> Result<Self, String> {
+        if tick == 0 {
+            return Err(format!(
+                "Waveform generator \"{name}\": tick must be positive"
+            ));
+        }
+        let description = json::parse(description)
+            .map_err(|error| format!("Waveform generator \"{name}\": {error}"))?;
+        let Some(Value::Array(signal)) = description.get("signal") else {
+            return Err(format!(
+                "Waveform generator \"{name}\": description has no signal list"
+            ));
+        };
+        let mut lanes = Vec::new();
+        collect_lanes(name, signal, &mut lanes)?;
+
+        Ok(Self {
+            name: name.to_string(),
+            lanes,
+            tick,
+            time: 0,
+        })
+    }
+
+    /// Create a new WaveformGenerator from a WaveJSON file.
+    ///
+    /// # Parameters
+    ///
+    /// - `name`: Name of the generator.
+    /// - `path`: Path of the WaveJSON file.
+    /// - `tick`: Simulation time spanned by each character of a wave, which must be positive.
+    pub fn load(name: &str, path: &Path, tick: u64) -> Result<Self, String> {
+        let description = fs::read_to_string(path).map_err(|error| {
+            format!(
+                "Waveform generator \"{name}\": cannot read \"{}\": {error}",
+                path.display()
+            )
+        })?;
+        Self::new(name, &description, tick)
+    }
+}
+
+impl Element for WaveformGenerator {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn input_pins(&self) -> Vec<InputPin> {
+        Vec::new()
+    }
+
+    fn output_pins(&self) -> Vec<OutputPin> {
+        self.lanes
+            .iter()
+            .map(|lane| OutputPin::new(&lane.name, SimDuration::ZERO, lane.level_at(0.0)))
+            .collect()
+    }
+
+    fn step(
+        &mut self,
+        _inputs: &[InputPin],
+        outputs: &mut [OutputPin],
+        delta_t: u64,
+    ) -> Result<SimResult, String> {
+        let ticks = self.time as f64 / self.tick as f64;
+        for (lane, output) in self.lanes.iter().zip(outputs) {
+            output.drive(lane.level_at(ticks));
+        }
+        self.time += delta_t;
+
+        Ok(SimResult::Continuing)
+    }
+
+    #[cfg(feature = "serde")]
+    fn save_state(&self) -> Result<serde_json::Value, String> {
+        super::save_state(self.time)
+    }
+
+    #[cfg(feature = "serde")]
+    fn restore_state(&mut self, state: serde_json::Value) -> Result<(), String> {
+        self.time = super::restore_state(&self.name, state)?;
+        Ok(())
+    }
+
+    fn box_clone(&self) -> Box<dyn Element> {
+        Box::new(self.clone())
+    }
+}
+
+/// Register every kind of waveform element.
+///
+/// The `wavedrom` kind takes either the `file` parameter, giving the path of a WaveJSON file, or the `wave` parameter,
+/// giving the description itself, and the `tick` parameter (default 1).
+///
+/// # Parameters
+///
+/// - `registry`: The Registry to add the waveform elements to.
+pub fn register(registry: &mut Registry) {
+    registry.register("wavedrom", |name, parameters: &Parameters| {
+        let file: String = parameters.get_or("file", String::new())?;
+        let wave: String = parameters.get_or("wave", String::new())?;
+        let tick = parameters.get_or("tick", 1)?;
+        Ok(Box::new(match (file.is_empty(), wave.is_empty()) {
+            (false, true) => WaveformGenerator::load(name, Path::new(&file), tick)?,
+            (true, false) => WaveformGenerator::new(name, &wave, tick)?,
+            _ => {
+                return Err(format!(
+                    "Waveform generator \"{name}\": give either a file or a wave"
+                ))
+            }
+        }))
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sim::Simulation;
+    use crate::wire::{Wire, WirePull};
+
+    /// Step a generator, returning the level of each output at each step as `0`, `1` or `z`.
+    fn play(generator: &mut WaveformGenerator, steps: usize, delta_t: u64) -> Vec<String> {
+        let mut outputs = generator.output_pins();
+        let mut levels = vec![String::new(); outputs.len()];
+        for _ in 0..steps {
+            generator.step(&[], &mut outputs, delta_t).unwrap();
+            for (level, output) in levels.iter_mut().zip(&mut outputs) {
+                output.step(SimDuration::from_ticks(delta_t));
+                level.push(match output.state() {
+                    OutputPinState::Low => '0',
+                    OutputPinState::High => '1',
+                    OutputPinState::HighImpedance => 'z',
+                    OutputPinState::Unknown => 'x',
+                });
+            }
+        }
+        levels
+    }
+
+    #[test]
+    fn waveform_generator_waves() {
+        // GIVEN a pasted diagram with clocks, levels, a gap, a group, a spacer, and lanes with a period and phase
+        let mut generator = WaveformGenerator::new(
+            "STIM",
+            "{signal: [
+                {name: 'clk', wave: 'p..|.'},
+                {name: 'nclk', wave: 'N....'},
+                {},
+                ['bus', {name: 'cs', wave: 'hL.Hx'}],
+                {name: 'slow', wave: '01', period: 2},
+                {name: 'early', wave: '01', phase: 0.5},
+            ]}",
+            10,
+        )
+        .unwrap();
+        // WHEN it is played at half ticks
+        let levels = play(&mut generator, 12, 5);
+        // THEN each lane follows its wave, and holds its last character
+        assert_eq!(
+            vec![
+                "101010101010",
+                "010101010101",
+                "11000011zzzz",
+                "000011111111",
+                "011111111111",
+            ],
+            levels
+        );
+    }
+
+    #[test]
+    fn waveform_generator_in_simulation() {
+        // GIVEN a waveform generator from the registry driving a Wire
+        let mut sim = Simulation::new(SimDuration::from_ticks(10));
+        let parameters = Parameters::new()
+            .with(
+                "wave",
+                "{\"signal\": [{\"name\": \"RESET\", \"wave\": \"0.1\"}]}",
+            )
+            .with("tick", "20");
+        let generator = sim
+            .add_element(
+                Registry::standard()
+                    .create("wavedrom", "STIM", &parameters)
+                    .unwrap(),
+            )
+            .unwrap();
+        let reset = sim.add_wire(Wire::new("RESET", WirePull::None)).unwrap();
+        sim.connect_output(sim.output_pin(generator, "RESET").unwrap(), reset)
+            .unwrap();
+        // WHEN it is stepped
+        let levels: Vec<f32> = (0..6)
+            .map(|_| {
+                sim.step().unwrap();
+                sim.wire(reset).unwrap().measure().into()
+            })
+            .collect();
+        // THEN the Wire follows the wave
+        assert_eq!(vec![0.0, 0.0, 0.0, 0.0, 1.0, 1.0], levels);
+    }
+
+    #[test]
+    fn waveform_generator_errors() {
+        // GIVEN invalid descriptions and parameters
+        let registry = Registry::standard();
+        let errors: Vec<String> = [
+            ("{signal: [{name: 'a', wave: '01=.'}]}", 1),
+            ("{signal: [{wave: '01'}]}", 1),
+            ("{signal: [{name: 'a', wave: '01', period: 0}]}", 1),
+            ("{lanes: []}", 1),
+            ("{signal: [", 1),
+            ("{signal: []}", 0),
+        ]
+        .iter()
+        .map(|(description, tick)| WaveformGenerator::new("STIM", description, *tick).unwrap_err())
+        .chain(
+            registry
+                .create("wavedrom", "STIM", &Parameters::new())
+                .err(),
+        )
+        .chain(
+            registry
+                .create(
+                    "wavedrom",
+                    "STIM",
+                    &Parameters::new().with("file", "/nonexistent.json"),
+                )
+                .err(),
+        )
+        .collect();
+        // WHEN they are used
+        // THEN each is reported
+        assert_eq!(
+            vec![
+                "Waveform generator \"STIM\": lane \"a\" has unsupported wave character '='",
+                "Waveform generator \"STIM\": lane \"01\" has no name",
+                "Waveform generator \"STIM\": lane \"a\" period must be positive",
+                "Waveform generator \"STIM\": description has no signal list",
+                "Waveform generator \"STIM\": Invalid JSON at offset 10: expected a value",
+                "Waveform generator \"STIM\": tick must be positive",
+                "Waveform generator \"STIM\": give either a file or a wave",
+                "Waveform generator \"STIM\": cannot read \"/nonexistent.json\": \
+                 No such file or directory (os error 2)",
+            ],
+            errors
+        );
+    }
+}
@@ -0,0 +1,223 @@
+//! The errors raised by a Simulation, so that callers can tell failure modes apart.
+//!
+//! Elements and Tracers are written outside the simulator, so they still describe their failures with messages, which
+//! are carried as [SimError::Failed].  A SimError converts into its message, so functions which return messages can
+//! propagate it with `?`.
+
+use crate::prelude::*;
+use crate::sim::Phase;
+use core::fmt;
+
+/// A kind of component of a Simulation.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ComponentKind {
+    /// A Wire.
+    Wire,
+    /// An Element.
+    Element,
+    /// An InputPin.
+    InputPin,
+    /// An OutputPin.
+    OutputPin,
+}
+
+impl fmt::Display for ComponentKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Wire => "wire",
+            Self::Element => "element",
+            Self::InputPin => "input pin",
+            Self::OutputPin => "output pin",
+        })
+    }
+}
+
+/// An error raised by a Simulation.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SimError {
+    /// No component of the kind has the given Id, or it has been removed.
+    UnknownId(ComponentKind),
+    /// An Element has no pin of the kind with the given name.
+    UnknownPin {
+        /// Name of the Element.
+        element: String,
+        /// Kind of pin.
+        kind: ComponentKind,
+        /// Name of the pin.
+        pin: String,
+    },
+    /// No component of the kind has the given name, or it has been removed.
+    UnknownName(ComponentKind, String),
+    /// A component of the kind with the given name is already present.
+    DuplicateName(ComponentKind, String),
+    /// No bus has the given name.
+    UnknownBus(String),
+    /// A bus with the given name has already been defined.
+    DuplicateBus(String),
+    /// A bus cannot be defined, read or driven as requested.
+    Bus {
+        /// Name of the bus.
+        name: String,
+        /// Description of the problem.
+        reason: String,
+    },
+    /// A pin cannot be connected because it is already connected to a Wire.
+    AlreadyConnected(ComponentKind),
+    /// A pin cannot be disconnected because it is not connected to a Wire.
+    NotConnected(ComponentKind),
+    /// A Wire cannot be removed because pins are still connected to it.
+    StillConnected {
+        /// Name of the Wire.
+        wire: String,
+        /// Number of pins connected to the Wire.
+        pins: usize,
+    },
+    /// A Wire cannot be removed because it is part of a bus.
+    InBus {
+        /// Name of the Wire.
+        wire: String,
+        /// Name of the bus.
+        bus: String,
+    },
+    /// An item is checked out of its Library, or does not exist.
+    CheckoutConflict,
+    /// An item cannot be checked back into its Library with the given Id.
+    CheckinConflict,
+    /// Items are still checked out of a Library which should hold them all.
+    MissingItems,
+    /// A step phase did not complete within the phase timeout.
+    PhaseTimeout(Phase),
+    /// The executor disconnected while a step phase was running.
+    Disconnected(Phase),
+    /// A Wire is driven both high and low.
+    DriverConflict(String),
+    /// The Simulation cannot step back before its start.
+    BeforeStart,
+    /// No checkpoint is available to step back to.
+    NoCheckpoint,
+    /// The Simulation cannot step back, as the named Element has state outside it which replaying steps would change.
+    NotReplayable(String),
+    /// A snapshot cannot be restored, as it was taken from a Simulation built from a different netlist.
+    SnapshotMismatch(String),
+    /// An argument is out of range.
+    InvalidArgument(String),
+    /// An Element, Tracer or other component failed, as described by its message.
+    Failed(String),
+}
+
+impl fmt::Display for SimError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnknownId(kind) => write!(f, "No {kind} found for the given ID"),
+            Self::UnknownPin { element, kind, pin } => {
+                write!(f, "Element \"{element}\" has no {kind} \"{pin}\"")
+            }
+            Self::UnknownName(kind, name) => write!(f, "No {kind} named \"{name}\""),
+            Self::DuplicateName(kind, name) => write!(f, "Duplicate {kind} \"{name}\""),
+            Self::UnknownBus(name) => write!(f, "No bus named \"{name}\""),
+            Self::DuplicateBus(name) => write!(f, "Duplicate bus \"{name}\""),
+            Self::Bus { name, reason } => write!(f, "Bus \"{name}\": {reason}"),
+            Self::AlreadyConnected(kind) => {
+                write!(f, "{} is already connected to a wire", capitalised(*kind))
+            }
+            Self::NotConnected(kind) => {
+                write!(f, "{} is not connected to a wire", capitalised(*kind))
+            }
+            Self::StillConnected { wire, pins } => {
+                write!(f, "Wire \"{wire}\" is still connected to {pins} pins")
+            }
+            Self::InBus { wire, bus } => write!(f, "Wire \"{wire}\" is part of bus \"{bus}\""),
+            Self::CheckoutConflict => write!(f, "Item not available!"),
+            Self::CheckinConflict => write!(f, "Item cannot be checked in with that ID!"),
+            Self::MissingItems => write!(f, "Items missing from library!"),
+            Self::PhaseTimeout(_) => write!(f, "Timed out waiting for step phase to complete!"),
+            Self::Disconnected(_) => {
+                write!(f, "Disconnected while waiting for step phase to complete!")
+            }
+            Self::DriverConflict(wire) => write!(f, "Wire \"{wire}\" is driven both high and low"),
+            Self::BeforeStart => write!(f, "Cannot step back before the start of the simulation!"),
+            Self::NoCheckpoint => write!(f, "No checkpoint available to step back to!"),
+            Self::NotReplayable(element) => {
+                write!(f, "Element \"{element}\" cannot be replayed to step back")
+            }
+            Self::SnapshotMismatch(reason) => {
+                write!(f, "Snapshot does not match the simulation: {reason}")
+            }
+            Self::InvalidArgument(message) | Self::Failed(message) => write!(f, "{message}"),
+        }
+    }
+}
+
+impl core::error::Error for SimError {}
+
+impl From<String> for SimError {
+    fn from(message: String) -> Self {
+        Self::Failed(message)
+    }
+}
+
+impl From<SimError> for String {
+    fn from(error: SimError) -> Self {
+        error.to_string()
+    }
+}
+
+/// Describe a kind of component at the start of a sentence.
+///
+/// # Parameters
+///
+/// - `kind`: The kind of component.
+fn capitalised(kind: ComponentKind) -> String {
+    let text = kind.to_string();
+    let mut chars = text.chars();
+    chars
+        .next()
+        .map(|first| first.to_ascii_uppercase().to_string() + chars.as_str())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sim_error_display() {
+        // GIVEN errors of several kinds
+        // WHEN they are described
+        // THEN their messages read as sentences naming the components involved
+        assert_eq!(
+            "No input pin found for the given ID",
+            SimError::UnknownId(ComponentKind::InputPin).to_string()
+        );
+        assert_eq!(
+            "Output pin is already connected to a wire",
+            SimError::AlreadyConnected(ComponentKind::OutputPin).to_string()
+        );
+        assert_eq!(
+            "Bus \"A\": must have from 1 to 64 wires",
+            SimError::Bus {
+                name: "A".to_string(),
+                reason: "must have from 1 to 64 wires".to_string()
+            }
+            .to_string()
+        );
+        assert_eq!(
+            "Duplicate output pin \"U1.Y\"",
+            SimError::DuplicateName(ComponentKind::OutputPin, "U1.Y".to_string()).to_string()
+        );
+        assert_eq!(
+            "Wire \"CLK\" is driven both high and low",
+            SimError::DriverConflict("CLK".to_string()).to_string()
+        );
+    }
+    #[test]
+    fn sim_error_conversions() {
+        // GIVEN a message and an error
+        // WHEN each is converted into the other
+        let error = SimError::from("Boom!".to_string());
+        let message = String::from(SimError::NoCheckpoint);
+        // THEN the message is carried as a failure, and the error becomes its description
+        assert_eq!(SimError::Failed("Boom!".to_string()), error);
+        assert_eq!("No checkpoint available to step back to!", message);
+    }
+}
@@ -0,0 +1,314 @@
+//! Executors run the jobs making up a Simulation step phase and hand back their results.
+//!
+//! Natively, jobs run on a thread pool and return their results over a channel.  The pool is provided by the
+//! `threadpool` crate, or by `rayon` with the `rayon` feature, as chosen by [ThreadBackend].  WebAssembly has no
+//! threads, and nor does a build without the `std` feature, so there jobs run one at a time as they are submitted, and
+//! their results are queued until they are received.
+
+use crate::prelude::*;
+#[cfg(feature = "std")]
+use crate::sim::ThreadBackend;
+use alloc::collections::VecDeque;
+use core::time::Duration;
+#[cfg(all(feature = "std", not(target_arch = "wasm32")))]
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+#[cfg(all(feature = "std", not(target_arch = "wasm32")))]
+use threadpool::ThreadPool;
+
+/// Failure to obtain the result of a job.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub(crate) enum ReceiveError {
+    /// No result arrived before the timeout.
+    Timeout,
+    /// No job remains to produce a result.
+    Disconnected,
+}
+
+/// A job run by an Executor, returning its result.
+pub(crate) type Job<T> = Box<dyn FnOnce() -> T + Send>;
+
+/// Runs jobs and hands back their results, in order of completion.
+pub(crate) trait Executor<T>: Send {
+    /// Start a job running.
+    ///
+    /// # Parameters
+    ///
+    /// - `job`: The job, returning its result.
+    fn execute(&mut self, job: Job<T>);
+
+    /// Obtain the result of the next job to complete, waiting for it if necessary.
+    ///
+    /// # Parameters
+    ///
+    /// - `timeout`: Maximum time to wait for a result.
+    fn receive(&mut self, timeout: Duration) -> Result<T, ReceiveError>;
+
+    /// Obtain the number of jobs waiting to start, or 0 if the executor cannot tell.
+    fn queued_count(&self) -> usize;
+
+    /// Obtain the number of threads on which jobs run.
+    fn threads(&self) -> usize;
+}
+
+/// Create the executor used on the present target.
+///
+/// # Parameters
+///
+/// - `backend`: Thread pool implementation to use.
+/// - `threads`: Number of threads to run jobs on, or 0 for one per CPU.
+#[cfg(all(feature = "std", not(target_arch = "wasm32")))]
+pub(crate) fn create<T: Send + 'static>(
+    backend: ThreadBackend,
+    threads: usize,
+) -> Box<dyn Executor<T>> {
+    match backend {
+        ThreadBackend::ThreadPool => Box::new(ThreadedExecutor::new(threads)),
+        #[cfg(feature = "rayon")]
+        ThreadBackend::Rayon => Box::new(RayonExecutor::new(threads)),
+    }
+}
+
+/// Create the executor used on the present target, which has no threads, so that the backend and thread count are
+/// ignored.
+///
+/// # Parameters
+///
+/// - `_backend`: Thread pool implementation to use, which is unused.
+/// - `_threads`: Number of threads to run jobs on, which is unused.
+#[cfg(all(feature = "std", target_arch = "wasm32"))]
+pub(crate) fn create<T: Send + 'static>(
+    _backend: ThreadBackend,
+    _threads: usize,
+) -> Box<dyn Executor<T>> {
+    Box::new(SerialExecutor::new())
+}
+
+/// Create the executor used on the present target, which has no threads.
+#[cfg(not(feature = "std"))]
+pub(crate) fn create<T: Send + 'static>() -> Box<dyn Executor<T>> {
+    Box::new(SerialExecutor::new())
+}
+
+/// An executor running jobs in parallel on a `threadpool` thread pool.
+#[cfg(all(feature = "std", not(target_arch = "wasm32")))]
+pub(crate) struct ThreadedExecutor<T> {
+    /// Thread pool running the jobs.
+    pool: ThreadPool,
+    /// Message passing FIFO sender to clone for passing results back from the jobs.
+    sender: Sender<T>,
+    /// Message passing FIFO receiver from which the results are obtained.
+    receiver: Receiver<T>,
+}
+
+#[cfg(all(feature = "std", not(target_arch = "wasm32")))]
+impl<T: Send + 'static> ThreadedExecutor<T> {
+    /// Create a new ThreadedExecutor.
+    ///
+    /// # Parameters
+    ///
+    /// - `threads`: Number of threads to run jobs on, or 0 for one per CPU.
+    pub(crate) fn new(threads: usize) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        Self {
+            pool: match threads {
+                0 => ThreadPool::default(),
+                threads => ThreadPool::new(threads),
+            },
+            sender,
+            receiver,
+        }
+    }
+}
+
+#[cfg(all(feature = "std", not(target_arch = "wasm32")))]
+impl<T: Send + 'static> Executor<T> for ThreadedExecutor<T> {
+    fn execute(&mut self, job: Job<T>) {
+        let sender = self.sender.clone();
+        self.pool.execute(move || {
+            let _ = sender.send(job());
+        });
+    }
+
+    fn receive(&mut self, timeout: Duration) -> Result<T, ReceiveError> {
+        receive(&self.receiver, timeout)
+    }
+
+    fn queued_count(&self) -> usize {
+        self.pool.queued_count()
+    }
+
+    fn threads(&self) -> usize {
+        self.pool.max_count()
+    }
+}
+
+/// An executor running jobs in parallel on a `rayon` thread pool.
+#[cfg(all(feature = "rayon", not(target_arch = "wasm32")))]
+pub(crate) struct RayonExecutor<T> {
+    /// Thread pool running the jobs.
+    pool: rayon::ThreadPool,
+    /// Message passing FIFO sender to clone for passing results back from the jobs.
+    sender: Sender<T>,
+    /// Message passing FIFO receiver from which the results are obtained.
+    receiver: Receiver<T>,
+}
+
+#[cfg(all(feature = "rayon", not(target_arch = "wasm32")))]
+impl<T: Send + 'static> RayonExecutor<T> {
+    /// Create a new RayonExecutor.
+    ///
+    /// # Parameters
+    ///
+    /// - `threads`: Number of threads to run jobs on, or 0 for one per CPU.
+    pub(crate) fn new(threads: usize) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        Self {
+            pool: rayon::ThreadPoolBuilder::new()
+                .num_threads(threads)
+                .build()
+                .expect("Failed to start rayon thread pool"),
+            sender,
+            receiver,
+        }
+    }
+}
+
+#[cfg(all(feature = "rayon", not(target_arch = "wasm32")))]
+impl<T: Send + 'static> Executor<T> for RayonExecutor<T> {
+    fn execute(&mut self, job: Job<T>) {
+        let sender = self.sender.clone();
+        self.pool.spawn(move || {
+            let _ = sender.send(job());
+        });
+    }
+
+    fn receive(&mut self, timeout: Duration) -> Result<T, ReceiveError> {
+        receive(&self.receiver, timeout)
+    }
+
+    /// Rayon does not report its queue, so this is always zero.
+    fn queued_count(&self) -> usize {
+        0
+    }
+
+    fn threads(&self) -> usize {
+        self.pool.current_num_threads()
+    }
+}
+
+/// Obtain the next result sent back by a job running on a thread pool, waiting for it if necessary.
+///
+/// # Parameters
+///
+/// - `receiver`: Receiver of the results.
+/// - `timeout`: Maximum time to wait for a result.
+#[cfg(all(feature = "std", not(target_arch = "wasm32")))]
+fn receive<T>(receiver: &Receiver<T>, timeout: Duration) -> Result<T, ReceiveError> {
+    receiver.recv_timeout(timeout).map_err(|error| match error {
+        RecvTimeoutError::Timeout => ReceiveError::Timeout,
+        RecvTimeoutError::Disconnected => ReceiveError::Disconnected,
+    })
+}
+
+/// An executor running each job to completion as it is submitted, for targets without threads.
+#[cfg_attr(all(feature = "std", not(target_arch = "wasm32")), allow(dead_code))]
+pub(crate) struct SerialExecutor<T> {
+    /// Results of the jobs which have run, in order of completion.
+    results: VecDeque<T>,
+}
+
+#[cfg_attr(all(feature = "std", not(target_arch = "wasm32")), allow(dead_code))]
+impl<T: Send + 'static> SerialExecutor<T> {
+    /// Create a new SerialExecutor.
+    pub(crate) fn new() -> Self {
+        Self {
+            results: VecDeque::new(),
+        }
+    }
+}
+
+impl<T: Send + 'static> Executor<T> for SerialExecutor<T> {
+    fn execute(&mut self, job: Job<T>) {
+        self.results.push_back(job());
+    }
+
+    /// Every job has already completed, so there is never any waiting, and asking for more results than there were
+    /// jobs is reported as a disconnection.
+    fn receive(&mut self, _timeout: Duration) -> Result<T, ReceiveError> {
+        self.results.pop_front().ok_or(ReceiveError::Disconnected)
+    }
+
+    /// Every job runs as it is submitted, so this is always zero.
+    fn queued_count(&self) -> usize {
+        0
+    }
+
+    fn threads(&self) -> usize {
+        1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Run four jobs on an executor, returning every result received, in order of completion.
+    ///
+    /// # Parameters
+    ///
+    /// - `executor`: The executor.
+    /// - `timeout`: Maximum time to wait for each result.
+    fn run_jobs(executor: &mut dyn Executor<i32>, timeout: Duration) -> Vec<i32> {
+        for i in 0..4 {
+            executor.execute(Box::new(move || i * 10));
+        }
+        (0..4).map(|_| executor.receive(timeout).unwrap()).collect()
+    }
+
+    #[cfg(all(feature = "std", not(target_arch = "wasm32")))]
+    #[test]
+    fn threaded_executor() {
+        // GIVEN a threaded executor with two threads
+        let mut executor = ThreadedExecutor::new(2);
+        // WHEN jobs are run on it
+        let mut results = run_jobs(&mut executor, Duration::from_secs(1));
+        // THEN every result is received, in some order, and then it times out
+        results.sort_unstable();
+        assert_eq!(vec![0, 10, 20, 30], results);
+        assert_eq!(2, executor.threads());
+        assert_eq!(
+            Err(ReceiveError::Timeout),
+            executor.receive(Duration::from_millis(1))
+        );
+    }
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn rayon_executor() {
+        // GIVEN a rayon executor with three threads
+        let mut executor = RayonExecutor::new(3);
+        // WHEN jobs are run on it
+        let mut results = run_jobs(&mut executor, Duration::from_secs(1));
+        // THEN every result is received, in some order, and then it times out
+        results.sort_unstable();
+        assert_eq!(vec![0, 10, 20, 30], results);
+        assert_eq!(3, executor.threads());
+        assert_eq!(
+            Err(ReceiveError::Timeout),
+            executor.receive(Duration::from_millis(1))
+        );
+    }
+    #[test]
+    fn serial_executor() {
+        // GIVEN a serial executor
+        let mut executor = SerialExecutor::new();
+        // WHEN jobs are run on it
+        let results = run_jobs(&mut executor, Duration::ZERO);
+        // THEN the results are received in order, with nothing queued, and then it reports a disconnection
+        assert_eq!(vec![0, 10, 20, 30], results);
+        assert_eq!(0, executor.queued_count());
+        assert_eq!(
+            Err(ReceiveError::Disconnected),
+            executor.receive(Duration::ZERO)
+        );
+    }
+}
@@ -0,0 +1,410 @@
+//! Fault injection, and campaigns which measure how many faults the checks of a test detect.
+//!
+//! A [Fault] holds a Wire stuck at a logic level, regardless of what drives it, for a whole run.  A [Campaign]
+//! builds a fresh Simulation with its test stimulus and [Monitors] for each fault in turn, injects the fault, runs the
+//! test, and records which monitors fail.  A fault which makes no monitor fail is undetected, showing a part of the
+//! design which the test does not exercise or check: the [CoverageReport] gives the fraction detected, in the manner
+//! of the fault coverage of automatic test pattern generation.
+
+use crate::error::SimError;
+use crate::monitor::{MonitorStatus, Monitors};
+use crate::sim::{SimResult, Simulation};
+use crate::wire::WirePull;
+use crate::WireId;
+use std::fmt;
+
+/// A fault which can be injected into a Simulation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fault {
+    /// The Wire is stuck low (stuck-at-0).
+    StuckLow(WireId),
+    /// The Wire is stuck high (stuck-at-1).
+    StuckHigh(WireId),
+}
+
+impl Fault {
+    /// Obtain every stuck-at fault of a Simulation: each Wire stuck low, and each Wire stuck high.
+    ///
+    /// # Parameters
+    ///
+    /// - `sim`: The Simulation.
+    pub fn stuck_at(sim: &Simulation) -> Vec<Self> {
+        sim.wires()
+            .flat_map(|id| [Fault::StuckLow(id), Fault::StuckHigh(id)])
+            .collect()
+    }
+
+    /// Obtain the Id of the faulty Wire.
+    pub fn wire(&self) -> WireId {
+        match self {
+            Fault::StuckLow(id) | Fault::StuckHigh(id) => *id,
+        }
+    }
+
+    /// Inject the fault into a Simulation, taking effect from the next step.
+    ///
+    /// # Parameters
+    ///
+    /// - `sim`: The Simulation.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use rvfs_sim_core::fault::Fault;
+    /// # use rvfs_sim_core::sim::Simulation;
+    /// # use rvfs_sim_core::time::SimDuration;
+    /// # use rvfs_sim_core::wire::{Wire, WirePull};
+    /// let mut sim = Simulation::new(SimDuration::from_ticks(10));
+    /// let id = sim.add_wire(Wire::new("/RESET", WirePull::Up)).unwrap();
+    /// Fault::StuckLow(id).inject(&mut sim).unwrap();
+    /// sim.step().unwrap();
+    ///
+    /// assert_eq!(0.0, f32::from(sim.wire(id).unwrap().measure()));
+    /// ```
+    pub fn inject(&self, sim: &mut Simulation) -> Result<(), SimError> {
+        match self {
+            Fault::StuckLow(id) => sim.force_wire(*id, Some(WirePull::Down)),
+            Fault::StuckHigh(id) => sim.force_wire(*id, Some(WirePull::Up)),
+        }
+    }
+
+    /// Describe the fault, naming its Wire.
+    ///
+    /// # Parameters
+    ///
+    /// - `wire`: Name of the faulty Wire.
+    fn describe(&self, wire: &str) -> String {
+        match self {
+            Fault::StuckLow(_) => format!("{wire} stuck-at-0"),
+            Fault::StuckHigh(_) => format!("{wire} stuck-at-1"),
+        }
+    }
+}
+
+/// The outcome of running a test with a fault injected.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FaultResult {
+    /// The fault.
+    pub fault: Fault,
+    /// Name of the faulty Wire.
+    pub wire: String,
+    /// Names of the monitors which failed, and so detected the fault, in the order they were added.
+    pub detected_by: Vec<String>,
+    /// Simulation time of the earliest failure of any monitor, if the fault was detected.
+    pub time: Option<u64>,
+}
+
+impl FaultResult {
+    /// Query whether any monitor detected the fault.
+    pub fn detected(&self) -> bool {
+        !self.detected_by.is_empty()
+    }
+}
+
+impl fmt::Display for FaultResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: ", self.fault.describe(&self.wire))?;
+        match self.time {
+            Some(time) if self.detected() => {
+                write!(f, "detected at {time} by {}", self.detected_by.join(", "))
+            }
+            _ if self.detected() => write!(f, "detected by {}", self.detected_by.join(", ")),
+            _ => write!(f, "undetected"),
+        }
+    }
+}
+
+/// The outcome of a fault campaign.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CoverageReport {
+    /// The outcome for each fault, in the order they were injected.
+    pub results: Vec<FaultResult>,
+}
+
+impl CoverageReport {
+    /// Obtain the number of faults detected.
+    pub fn detected(&self) -> usize {
+        self.results.iter().filter(|r| r.detected()).count()
+    }
+
+    /// Obtain the fraction of the faults which were detected, or 1 if there were no faults.
+    pub fn coverage(&self) -> f64 {
+        if self.results.is_empty() {
+            1.0
+        } else {
+            self.detected() as f64 / self.results.len() as f64
+        }
+    }
+
+    /// Obtain the outcomes of the faults which were not detected.
+    pub fn undetected(&self) -> impl Iterator<Item = &FaultResult> {
+        self.results.iter().filter(|r| !r.detected())
+    }
+}
+
+impl fmt::Display for CoverageReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "Fault coverage: {} of {} faults detected ({:.1}%)",
+            self.detected(),
+            self.results.len(),
+            100.0 * self.coverage()
+        )?;
+        for result in &self.results {
+            writeln!(f, "  {result}")?;
+        }
+        Ok(())
+    }
+}
+
+/// A campaign which runs a test once for each of a set of faults, reporting which faults its monitors detect.
+///
+/// The test is described by a function building a fresh Simulation, with its stimulus, and the Monitors which check
+/// it.  The test is first run without any fault, when every monitor must pass, and then once with each fault injected
+/// from the start.  Each run lasts for a span of simulation time, or until the Simulation finishes.
+///
+/// # Example
+///
+/// ```
+/// # use rvfs_sim_core::fault::Campaign;
+/// # use rvfs_sim_core::monitor::{Condition, Monitors, Rule};
+/// # use rvfs_sim_core::sim::Simulation;
+/// # use rvfs_sim_core::time::SimDuration;
+/// # use rvfs_sim_core::wire::{Wire, WirePull};
+/// let mut campaign = Campaign::new(100, || {
+///     let mut sim = Simulation::new(SimDuration::from_ticks(10));
+///     let reset = sim.add_wire(Wire::new("/RESET", WirePull::Up))?;
+///     let monitors = Monitors::new();
+///     monitors.add("out of reset", Rule::Always(Condition::High(reset)));
+///     Ok((sim, monitors))
+/// });
+/// let report = campaign.run().unwrap();
+///
+/// assert_eq!(2, report.results.len());
+/// assert_eq!(1, report.detected());
+/// ```
+pub struct Campaign<F: FnMut() -> Result<(Simulation, Monitors), String>> {
+    /// Builds the Simulation and Monitors of the test.
+    build: F,
+    /// Simulation time for which each run lasts.
+    duration: u64,
+    /// The faults to inject, or None to inject every stuck-at fault.
+    faults: Option<Vec<Fault>>,
+}
+
+impl<F: FnMut() -> Result<(Simulation, Monitors), String>> Campaign<F> {
+    /// Create a new Campaign which injects every [stuck-at](Fault::stuck_at) fault.
+    ///
+    /// # Parameters
+    ///
+    /// - `duration`: Simulation time for which each run lasts.
+    /// - `build`: Builds a fresh Simulation, with its stimulus, and the Monitors which check it, for each run.
+    pub fn new(duration: u64, build: F) -> Self {
+        Self {
+            build,
+            duration,
+            faults: None,
+        }
+    }
+
+    /// Inject only the given faults.
+    ///
+    /// # Parameters
+    ///
+    /// - `faults`: The faults to inject, one per run.
+    pub fn with_faults(mut self, faults: Vec<Fault>) -> Self {
+        self.faults = Some(faults);
+        self
+    }
+
+    /// Run the test without any fault, and then with each fault, reporting which faults were detected.
+    ///
+    /// Fails if any monitor fails without a fault injected, since its failures could not then be attributed to a
+    /// fault.
+    pub fn run(&mut self) -> Result<CoverageReport, String> {
+        let (sim, statuses) = self.run_once(None)?;
+        if let Some(status) = statuses.iter().find(|s| !s.passed()) {
+            return Err(format!(
+                "Monitor \"{}\" fails without any fault injected",
+                status.name
+            ));
+        }
+        let faults = match &self.faults {
+            Some(faults) => faults.clone(),
+            None => Fault::stuck_at(&sim),
+        };
+
+        let mut report = CoverageReport::default();
+        for fault in faults {
+            let (sim, statuses) = self.run_once(Some(fault))?;
+            let failed: Vec<_> = statuses.iter().filter(|s| !s.passed()).collect();
+            report.results.push(FaultResult {
+                fault,
+                wire: sim.wire(fault.wire())?.name().clone(),
+                detected_by: failed.iter().map(|s| s.name.clone()).collect(),
+                time: failed
+                    .iter()
+                    .filter_map(|s| s.failure_times.first())
+                    .min()
+                    .copied(),
+            });
+        }
+        Ok(report)
+    }
+
+    /// Build and run the test once, returning the Simulation and the outcome of its monitors.
+    ///
+    /// # Parameters
+    ///
+    /// - `fault`: The fault to inject, if any.
+    fn run_once(
+        &mut self,
+        fault: Option<Fault>,
+    ) -> Result<(Simulation, Vec<MonitorStatus>), String> {
+        let (mut sim, monitors) = (self.build)()?;
+        sim.add_tracer(Box::new(monitors.clone()));
+        if let Some(fault) = fault {
+            fault.inject(&mut sim)?;
+        }
+        while sim.time() < self.duration {
+            if sim.step().map_err(|error| error.to_string())? == SimResult::Finished {
+                break;
+            }
+        }
+        sim.finish_tracers()?;
+        Ok((sim, monitors.statuses()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::element::clocks::ClockGenerator;
+    use crate::element::gates::{Gate, GateKind};
+    use crate::monitor::{Condition, Rule};
+    use crate::time::SimDuration;
+    use crate::wire::Wire;
+
+    /// Build a test of an inverter driven by a clock, whose monitors check that its output follows the clock, along
+    /// with a pulled up Wire which nothing observes.
+    fn inverter() -> Result<(Simulation, Monitors), String> {
+        let mut sim = Simulation::new(SimDuration::from_ticks(10));
+        let clock =
+            sim.add_element(Box::new(ClockGenerator::new("X1", 40.0, 0.5, 0.0, 0, 0.0)?))?;
+        let gate = sim.add_element(Box::new(Gate::new("U1", GateKind::Not, 1, 0)?))?;
+        let clk = sim.add_wire(Wire::new("CLK", WirePull::Down))?;
+        let nclk = sim.add_wire(Wire::new("NCLK", WirePull::None))?;
+        sim.add_wire(Wire::new("SPARE", WirePull::Up))?;
+        sim.connect_output(sim.output_pin(clock, "CLK")?, clk)?;
+        sim.connect_input(clk, sim.input_pin(gate, "I0")?)?;
+        sim.connect_output(sim.output_pin(gate, "Y")?, nclk)?;
+        let monitors = Monitors::new();
+        monitors.add(
+            "falls",
+            Rule::Response {
+                trigger: Condition::High(clk),
+                response: Condition::Low(nclk),
+                within: 20,
+            },
+        );
+        monitors.add(
+            "rises",
+            Rule::Response {
+                trigger: Condition::Low(clk),
+                response: Condition::High(nclk),
+                within: 20,
+            },
+        );
+        Ok((sim, monitors))
+    }
+
+    #[test]
+    fn fault_stuck_at() {
+        // GIVEN a Simulation with two Wires
+        let (sim, _) = inverter().unwrap();
+        // WHEN its stuck-at faults are listed
+        let faults = Fault::stuck_at(&sim);
+        // THEN each Wire is stuck at each level
+        assert_eq!(6, faults.len());
+        assert_eq!(
+            [
+                Fault::StuckLow(WireId::from(1)),
+                Fault::StuckHigh(WireId::from(1))
+            ],
+            faults[2..4]
+        );
+        assert_eq!(WireId::from(2), faults[5].wire());
+    }
+    #[test]
+    fn fault_campaign() {
+        // GIVEN a campaign over the inverter test
+        let mut campaign = Campaign::new(200, inverter);
+        // WHEN it is run
+        let report = campaign.run().unwrap();
+        // THEN only the faults on the inverter output are detected, once it fails to follow the clock in time
+        assert_eq!(6, report.results.len());
+        assert_eq!(2, report.detected());
+        assert!((report.coverage() - 1.0 / 3.0).abs() < 1e-9);
+        assert_eq!(
+            FaultResult {
+                fault: Fault::StuckLow(WireId::from(1)),
+                wire: "NCLK".to_string(),
+                detected_by: vec!["rises".to_string()],
+                time: Some(50),
+            },
+            report.results[2]
+        );
+        assert_eq!(
+            vec!["CLK", "CLK", "SPARE", "SPARE"],
+            report
+                .undetected()
+                .map(|r| r.wire.as_str())
+                .collect::<Vec<_>>()
+        );
+        assert!(report.to_string().starts_with(
+            "Fault coverage: 2 of 6 faults detected (33.3%)\n  CLK stuck-at-0: undetected\n"
+        ));
+    }
+    #[test]
+    fn fault_campaign_selected_faults() {
+        // GIVEN a campaign over the inverter test, injecting only selected faults
+        let mut campaign = Campaign::new(200, inverter).with_faults(vec![
+            Fault::StuckHigh(WireId::from(1)),
+            Fault::StuckLow(WireId::from(9)),
+        ]);
+        // WHEN it is run
+        // THEN a fault on a Wire which does not exist is rejected
+        assert_eq!(
+            Err("No wire found for the given ID".to_string()),
+            campaign.run()
+        );
+        // AND THEN the selected faults are injected
+        let report = Campaign::new(200, inverter)
+            .with_faults(vec![Fault::StuckHigh(WireId::from(1))])
+            .run()
+            .unwrap();
+        assert_eq!(
+            "NCLK stuck-at-1: detected at 30 by falls",
+            report.results[0].to_string()
+        );
+    }
+    #[test]
+    fn fault_campaign_failing_test() {
+        // GIVEN a test whose monitor fails without any fault
+        let mut campaign = Campaign::new(100, || {
+            let (sim, monitors) = inverter()?;
+            monitors.add(
+                "never",
+                Rule::Always(Condition::All(vec![Condition::Any(vec![])])),
+            );
+            Ok((sim, monitors))
+        });
+        // WHEN the campaign is run
+        // THEN it is rejected
+        assert_eq!(
+            Err("Monitor \"never\" fails without any fault injected".to_string()),
+            campaign.run()
+        );
+    }
+}
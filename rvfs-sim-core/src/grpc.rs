@@ -0,0 +1,604 @@
+//! A gRPC service for remote control of a Simulation, so that orchestration frameworks can manage fleets of simulator
+//! instances.
+//!
+//! The service is described for clients in other languages by `proto/rvfs_sim.proto` in this crate.  It builds a
+//! Simulation from Elements of the standard [Registry], steps it, reads and forces Wire levels, and streams the
+//! changes made by each step to subscribers.  Wires are identified by name.
+//!
+//! The service runs on a [tokio](https://tokio.rs) runtime provided by the caller:
+//!
+//! ```no_run
+//! # use rvfs_sim_core::grpc::SimulatorService;
+//! # async fn run() -> Result<(), String> {
+//! SimulatorService::new()
+//!     .serve("127.0.0.1:50051".parse().unwrap())
+//!     .await
+//! # }
+//! ```
+
+// tonic::Status is large, but is the error type of every request handler.
+#![allow(clippy::result_large_err)]
+
+use crate::element::{Parameters, Registry};
+use crate::sim::{SimResult, Simulation};
+use crate::time::SimDuration;
+use crate::wire::{Wire, WirePull};
+use crate::WireId;
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use tokio::sync::mpsc::{self, UnboundedSender};
+use tokio_stream::wrappers::UnboundedReceiverStream;
+use tonic::transport::Server;
+use tonic::{Request, Response, Status};
+
+include!(concat!(env!("OUT_DIR"), "/rvfs_sim.Simulator.rs"));
+
+pub use simulator_client::SimulatorClient;
+pub use simulator_server::{Simulator, SimulatorServer};
+
+/// Specification of a Wire to add to a loaded Simulation.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct WireSpec {
+    /// Name of the Wire.
+    #[prost(string, tag = "1")]
+    pub name: String,
+    /// Default pull of the Wire: `up`, `down` or `none`.
+    #[prost(string, tag = "2")]
+    pub pull: String,
+}
+
+/// Specification of an Element to add to a loaded Simulation.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct ElementSpec {
+    /// Kind of the Element, as registered in the standard Registry.
+    #[prost(string, tag = "1")]
+    pub kind: String,
+    /// Name of the Element.
+    #[prost(string, tag = "2")]
+    pub name: String,
+    /// Parameters of the Element.
+    #[prost(map = "string, string", tag = "3")]
+    pub parameters: HashMap<String, String>,
+    /// Name of the Wire connected to each pin, by pin name.
+    #[prost(map = "string, string", tag = "4")]
+    pub connections: HashMap<String, String>,
+}
+
+/// Request to build a new Simulation.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct LoadRequest {
+    /// Simulation time step size, which must be non-zero.
+    #[prost(uint64, tag = "1")]
+    pub interval: u64,
+    /// The Wires to add.
+    #[prost(message, repeated, tag = "2")]
+    pub wires: Vec<WireSpec>,
+    /// The Elements to add, after the Wires.
+    #[prost(message, repeated, tag = "3")]
+    pub elements: Vec<ElementSpec>,
+}
+
+/// Reply to a [LoadRequest].
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct LoadReply {}
+
+/// Request to step the Simulation.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct StepRequest {
+    /// Number of steps to take.
+    #[prost(uint64, tag = "1")]
+    pub steps: u64,
+}
+
+/// Reply to a [StepRequest].
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct StepReply {
+    /// Simulation time after stepping.
+    #[prost(uint64, tag = "1")]
+    pub time: u64,
+    /// Whether the Simulation finished, stopping the steps early.
+    #[prost(bool, tag = "2")]
+    pub finished: bool,
+}
+
+/// Request to read the levels of Wires.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct ReadRequest {
+    /// Names of the Wires to read, or none to read every Wire.
+    #[prost(string, repeated, tag = "1")]
+    pub wires: Vec<String>,
+}
+
+/// The level of a Wire.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct Signal {
+    /// Name of the Wire.
+    #[prost(string, tag = "1")]
+    pub wire: String,
+    /// Level of the Wire, from 0 to 1.
+    #[prost(float, tag = "2")]
+    pub level: f32,
+}
+
+/// Reply to a [ReadRequest].
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct ReadReply {
+    /// Simulation time of the levels.
+    #[prost(uint64, tag = "1")]
+    pub time: u64,
+    /// The levels, in the order requested, or in order of Wire Id if every Wire was read.
+    #[prost(message, repeated, tag = "2")]
+    pub signals: Vec<Signal>,
+}
+
+/// Request to force a Wire, or release it.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct ForceRequest {
+    /// Name of the Wire.
+    #[prost(string, tag = "1")]
+    pub wire: String,
+    /// Pull to force: `up`, `down`, or `none` to release the Wire.
+    #[prost(string, tag = "2")]
+    pub pull: String,
+}
+
+/// Reply to a [ForceRequest].
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct ForceReply {}
+
+/// Request to receive the changes made by later steps.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct SubscribeRequest {
+    /// Names of the Wires to follow, or none to follow every Wire.
+    #[prost(string, repeated, tag = "1")]
+    pub wires: Vec<String>,
+}
+
+/// A change to the level of a Wire made by a step.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct ChangeEvent {
+    /// Simulation time at the end of the step.
+    #[prost(uint64, tag = "1")]
+    pub time: u64,
+    /// Name of the Wire.
+    #[prost(string, tag = "2")]
+    pub wire: String,
+    /// New level of the Wire, from 0 to 1.
+    #[prost(float, tag = "3")]
+    pub level: f32,
+}
+
+/// A client following the changes made by each step.
+struct Subscriber {
+    /// Ids of the Wires followed, or `None` to follow every Wire.
+    wires: Option<Vec<WireId>>,
+    /// Sender of the changes.
+    sender: UnboundedSender<Result<ChangeEvent, Status>>,
+}
+
+/// The state of the service, shared between requests.
+struct State {
+    /// The loaded Simulation, if any.
+    sim: Option<Simulation>,
+    /// Ids of the Wires of the loaded Simulation, by name.
+    wires: HashMap<String, WireId>,
+    /// Clients following the changes made by each step.
+    subscribers: Vec<Subscriber>,
+}
+
+impl State {
+    /// Obtain the loaded Simulation.
+    fn sim(&mut self) -> Result<&mut Simulation, Status> {
+        self.sim
+            .as_mut()
+            .ok_or_else(|| Status::failed_precondition("No simulation loaded"))
+    }
+
+    /// Look up a Wire of the loaded Simulation by name.
+    ///
+    /// # Parameters
+    ///
+    /// - `name`: Name of the Wire.
+    fn wire(&self, name: &str) -> Result<WireId, Status> {
+        self.wires
+            .get(name)
+            .copied()
+            .ok_or_else(|| Status::not_found(format!("No wire named \"{name}\"")))
+    }
+}
+
+/// Parse the name of a pull.
+///
+/// # Parameters
+///
+/// - `pull`: The name: `up`, `down` or `none`.
+fn pull(pull: &str) -> Result<WirePull, Status> {
+    match pull {
+        "up" => Ok(WirePull::Up),
+        "down" => Ok(WirePull::Down),
+        "none" => Ok(WirePull::None),
+        _ => Err(Status::invalid_argument(format!("Invalid pull \"{pull}\""))),
+    }
+}
+
+/// Build a Simulation from its specification.
+///
+/// # Parameters
+///
+/// - `request`: The specification.
+fn build(request: &LoadRequest) -> Result<Simulation, String> {
+    if request.interval == 0 {
+        return Err("Simulation interval must be non-zero".to_string());
+    }
+    let registry = Registry::standard();
+    let mut sim = Simulation::new(SimDuration::from_ticks(request.interval));
+
+    let mut wires = HashMap::new();
+    for spec in &request.wires {
+        let pull = pull(&spec.pull).map_err(|status| status.message().to_string())?;
+        wires.insert(
+            spec.name.as_str(),
+            sim.add_wire(Wire::new(&spec.name, pull))?,
+        );
+    }
+    for spec in &request.elements {
+        let parameters = spec
+            .parameters
+            .iter()
+            .fold(Parameters::new(), |parameters, (name, value)| {
+                parameters.with(name, value)
+            });
+        let element = sim.add_element(registry.create(&spec.kind, &spec.name, &parameters)?)?;
+        for (pin, wire) in &spec.connections {
+            let wire = *wires
+                .get(wire.as_str())
+                .ok_or_else(|| format!("No wire named \"{wire}\""))?;
+            if let Ok(input) = sim.input_pin(element, pin) {
+                sim.connect_input(wire, input)?;
+            } else {
+                sim.connect_output(sim.output_pin(element, pin)?, wire)?;
+            }
+        }
+    }
+
+    Ok(sim)
+}
+
+/// A gRPC service controlling a Simulation.
+#[derive(Clone)]
+pub struct SimulatorService {
+    /// The state of the service.
+    state: Arc<Mutex<State>>,
+}
+
+impl SimulatorService {
+    /// Create a new SimulatorService, with no Simulation loaded.
+    pub fn new() -> Self {
+        Self {
+            state: Arc::new(Mutex::new(State {
+                sim: None,
+                wires: HashMap::new(),
+                subscribers: Vec::new(),
+            })),
+        }
+    }
+
+    /// Serve requests until the server fails.
+    ///
+    /// # Parameters
+    ///
+    /// - `address`: The address to listen on.
+    pub async fn serve(self, address: SocketAddr) -> Result<(), String> {
+        Server::builder()
+            .add_service(SimulatorServer::new(self))
+            .serve(address)
+            .await
+            .map_err(|error| format!("gRPC server on {address}: {error}"))
+    }
+
+    /// Lock the state of the service.
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl Default for SimulatorService {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[tonic::async_trait]
+impl Simulator for SimulatorService {
+    type SubscribeStream = UnboundedReceiverStream<Result<ChangeEvent, Status>>;
+
+    async fn load(&self, request: Request<LoadRequest>) -> Result<Response<LoadReply>, Status> {
+        let sim = build(request.get_ref()).map_err(Status::invalid_argument)?;
+        let mut state = self.lock();
+        state.wires = sim
+            .wires()
+            .map(|id| Ok((sim.wire(id)?.name().clone(), id)))
+            .collect::<Result<_, String>>()
+            .map_err(Status::internal)?;
+        state.sim = Some(sim);
+        state.subscribers.clear();
+
+        Ok(Response::new(LoadReply {}))
+    }
+
+    async fn step(&self, request: Request<StepRequest>) -> Result<Response<StepReply>, Status> {
+        let mut state = self.lock();
+        let State {
+            sim, subscribers, ..
+        } = &mut *state;
+        let sim = sim
+            .as_mut()
+            .ok_or_else(|| Status::failed_precondition("No simulation loaded"))?;
+
+        let mut finished = false;
+        for _ in 0..request.get_ref().steps {
+            let result = sim
+                .step()
+                .map_err(|error| Status::internal(error.to_string()))?;
+            for change in sim.changes() {
+                let event = ChangeEvent {
+                    time: sim.time(),
+                    wire: sim
+                        .wire(change.id)
+                        .map_err(Status::internal)?
+                        .name()
+                        .clone(),
+                    level: change.value.into(),
+                };
+                subscribers.retain(|subscriber| {
+                    let followed = subscriber
+                        .wires
+                        .as_ref()
+                        .is_none_or(|wires| wires.contains(&change.id));
+                    !followed || subscriber.sender.send(Ok(event.clone())).is_ok()
+                });
+            }
+            if result == SimResult::Finished {
+                finished = true;
+                break;
+            }
+        }
+
+        Ok(Response::new(StepReply {
+            time: sim.time(),
+            finished,
+        }))
+    }
+
+    async fn read(&self, request: Request<ReadRequest>) -> Result<Response<ReadReply>, Status> {
+        let mut state = self.lock();
+        let ids = if request.get_ref().wires.is_empty() {
+            state.sim()?.wires().collect()
+        } else {
+            request
+                .get_ref()
+                .wires
+                .iter()
+                .map(|name| state.wire(name))
+                .collect::<Result<Vec<_>, _>>()?
+        };
+        let sim = state.sim()?;
+        let signals = ids
+            .into_iter()
+            .map(|id| {
+                let wire = sim.wire(id).map_err(Status::internal)?;
+                Ok(Signal {
+                    wire: wire.name().clone(),
+                    level: wire.measure().into(),
+                })
+            })
+            .collect::<Result<_, Status>>()?;
+
+        Ok(Response::new(ReadReply {
+            time: sim.time(),
+            signals,
+        }))
+    }
+
+    async fn force(&self, request: Request<ForceRequest>) -> Result<Response<ForceReply>, Status> {
+        let mut state = self.lock();
+        let id = state.wire(&request.get_ref().wire)?;
+        let pull = match pull(&request.get_ref().pull)? {
+            WirePull::None => None,
+            pull => Some(pull),
+        };
+        state
+            .sim()?
+            .force_wire(id, pull)
+            .map_err(Status::internal)?;
+
+        Ok(Response::new(ForceReply {}))
+    }
+
+    async fn subscribe(
+        &self,
+        request: Request<SubscribeRequest>,
+    ) -> Result<Response<Self::SubscribeStream>, Status> {
+        let mut state = self.lock();
+        state.sim()?;
+        let wires = if request.get_ref().wires.is_empty() {
+            None
+        } else {
+            Some(
+                request
+                    .get_ref()
+                    .wires
+                    .iter()
+                    .map(|name| state.wire(name))
+                    .collect::<Result<_, _>>()?,
+            )
+        };
+        let (sender, receiver) = mpsc::unbounded_channel();
+        state.subscribers.push(Subscriber { wires, sender });
+
+        Ok(Response::new(UnboundedReceiverStream::new(receiver)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio_stream::StreamExt;
+
+    /// Run a future to completion on a single-threaded runtime.
+    fn block_on<F: std::future::Future>(future: F) -> F::Output {
+        tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap()
+            .block_on(future)
+    }
+
+    /// Specification of a Simulation with a clock of period 40 driving an inverter.
+    fn inverter() -> LoadRequest {
+        LoadRequest {
+            interval: 10,
+            wires: vec![
+                WireSpec {
+                    name: "CLK".to_string(),
+                    pull: "down".to_string(),
+                },
+                WireSpec {
+                    name: "/CLK".to_string(),
+                    pull: "none".to_string(),
+                },
+            ],
+            elements: vec![
+                ElementSpec {
+                    kind: "clock".to_string(),
+                    name: "X1".to_string(),
+                    parameters: HashMap::from([("period".to_string(), "40".to_string())]),
+                    connections: HashMap::from([("CLK".to_string(), "CLK".to_string())]),
+                },
+                ElementSpec {
+                    kind: "not".to_string(),
+                    name: "U1".to_string(),
+                    parameters: HashMap::new(),
+                    connections: HashMap::from([
+                        ("I0".to_string(), "CLK".to_string()),
+                        ("Y".to_string(), "/CLK".to_string()),
+                    ]),
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn grpc_load_step_read() {
+        block_on(async {
+            // GIVEN a service with an inverter loaded
+            let service = SimulatorService::new();
+            service.load(Request::new(inverter())).await.unwrap();
+            // WHEN it is stepped and read
+            let step = service
+                .step(Request::new(StepRequest { steps: 2 }))
+                .await
+                .unwrap()
+                .into_inner();
+            let read = service
+                .read(Request::new(ReadRequest { wires: Vec::new() }))
+                .await
+                .unwrap()
+                .into_inner();
+            // THEN every wire is read at the new time
+            assert_eq!(
+                StepReply {
+                    time: 20,
+                    finished: false
+                },
+                step
+            );
+            assert_eq!(20, read.time);
+            assert_eq!(
+                vec![("CLK", 1.0), ("/CLK", 0.0)],
+                read.signals
+                    .iter()
+                    .map(|signal| (signal.wire.as_str(), signal.level))
+                    .collect::<Vec<_>>()
+            );
+        });
+    }
+    #[test]
+    fn grpc_force_and_subscribe() {
+        block_on(async {
+            // GIVEN a service with an inverter loaded and stepped, and a subscriber following the inverted clock
+            let service = SimulatorService::new();
+            service.load(Request::new(inverter())).await.unwrap();
+            service
+                .step(Request::new(StepRequest { steps: 2 }))
+                .await
+                .unwrap();
+            let mut changes = service
+                .subscribe(Request::new(SubscribeRequest {
+                    wires: vec!["/CLK".to_string()],
+                }))
+                .await
+                .unwrap()
+                .into_inner();
+            // WHEN the clock is forced low and the simulation stepped
+            service
+                .force(Request::new(ForceRequest {
+                    wire: "CLK".to_string(),
+                    pull: "down".to_string(),
+                }))
+                .await
+                .unwrap();
+            service
+                .step(Request::new(StepRequest { steps: 2 }))
+                .await
+                .unwrap();
+            // THEN the subscriber sees the inverter respond, and nothing else
+            assert_eq!(
+                ChangeEvent {
+                    time: 40,
+                    wire: "/CLK".to_string(),
+                    level: 1.0
+                },
+                changes.next().await.unwrap().unwrap()
+            );
+            service.load(Request::new(inverter())).await.unwrap();
+            assert!(changes.next().await.is_none());
+        });
+    }
+    #[test]
+    fn grpc_errors() {
+        block_on(async {
+            // GIVEN a service with nothing loaded
+            let service = SimulatorService::new();
+            // WHEN invalid requests are made
+            let unloaded = service
+                .step(Request::new(StepRequest { steps: 1 }))
+                .await
+                .unwrap_err();
+            let mut bad_wire = inverter();
+            bad_wire.elements[1]
+                .connections
+                .insert("I0".to_string(), "NOWHERE".to_string());
+            let bad_load = service.load(Request::new(bad_wire)).await.unwrap_err();
+            service.load(Request::new(inverter())).await.unwrap();
+            let bad_force = service
+                .force(Request::new(ForceRequest {
+                    wire: "CLK".to_string(),
+                    pull: "sideways".to_string(),
+                }))
+                .await
+                .unwrap_err();
+            let bad_read = service
+                .read(Request::new(ReadRequest {
+                    wires: vec!["NOWHERE".to_string()],
+                }))
+                .await
+                .unwrap_err();
+            // THEN they are rejected with statuses
+            assert_eq!(tonic::Code::FailedPrecondition, unloaded.code());
+            assert_eq!("No wire named \"NOWHERE\"", bad_load.message());
+            assert_eq!(tonic::Code::InvalidArgument, bad_force.code());
+            assert_eq!(tonic::Code::NotFound, bad_read.code());
+        });
+    }
+}
@@ -0,0 +1,894 @@
+//! Adapters exposing simulated peripherals through the [embedded-hal](https://docs.rs/embedded-hal) 1.0 and
+//! [embedded-io](https://docs.rs/embedded-io) traits, so that firmware crates can be unit-tested against a simulated
+//! circuit by swapping their HAL implementation.
+//!
+//! The firmware drives simulated time: a [HalSimulation] owns the Simulation and steps it only while the firmware
+//! waits, in a [delay](HalDelay) or a blocking bus operation, so the circuit runs as if in real time alongside the
+//! firmware.  One time unit is taken to be one nanosecond.
+//!
+//! - [HalPin] is a bit of a [GpioPort](crate::element::gpio::GpioPort), as a digital input and output pin.
+//! - [HalDelay] waits by stepping the Simulation.
+//! - [HalI2c] performs transactions through an [I2cMaster](crate::element::i2c::I2cMaster).
+//! - [HalSpi] is an SPI bus bit-banged on bits of a GpioPort.
+//! - [HalUart] is a serial port bit-banged on bits of a GpioPort, with 8 data bits, no parity and one stop bit.
+//!
+//! The adapters share the Simulation, so they are neither `Send` nor `Sync`, and a test runs its firmware on the thread
+//! which created the HalSimulation.
+
+use crate::element::gpio::{Direction, GpioHost};
+use crate::element::i2c::{I2cHost, I2cOutcome, I2cTransaction};
+use crate::sim::{SimResult, Simulation};
+use embedded_hal::digital;
+use embedded_hal::i2c::{self, NoAcknowledgeSource, Operation};
+use embedded_hal::spi::{self, Phase, Polarity};
+use std::cell::{Cell, RefCell, RefMut};
+use std::collections::VecDeque;
+use std::fmt;
+use std::rc::{Rc, Weak};
+
+/// Default time to wait for a bus operation to complete, which is 10 ms.
+const DEFAULT_TIMEOUT: u64 = 10_000_000;
+
+/// An error raised by an adapter.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HalError {
+    /// The Simulation failed or finished while it was stepped.
+    Simulation(String),
+    /// A pin was read while its level was indeterminate.
+    Indeterminate,
+    /// An operation did not complete within the timeout.
+    Timeout,
+    /// An I2C slave did not acknowledge a byte.
+    NoAcknowledge,
+    /// Another I2C master won arbitration for the bus.
+    ArbitrationLoss,
+    /// A serial frame did not end with a stop bit.
+    Framing,
+    /// The operation cannot be performed by the adapter.
+    Unsupported(String),
+}
+
+impl fmt::Display for HalError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Simulation(message) | Self::Unsupported(message) => write!(f, "{message}"),
+            Self::Indeterminate => write!(f, "Pin level is indeterminate"),
+            Self::Timeout => write!(f, "Operation timed out"),
+            Self::NoAcknowledge => write!(f, "I2C slave did not acknowledge"),
+            Self::ArbitrationLoss => write!(f, "I2C arbitration lost"),
+            Self::Framing => write!(f, "Serial frame had no stop bit"),
+        }
+    }
+}
+
+impl std::error::Error for HalError {}
+
+impl digital::Error for HalError {
+    fn kind(&self) -> digital::ErrorKind {
+        digital::ErrorKind::Other
+    }
+}
+
+impl i2c::Error for HalError {
+    fn kind(&self) -> i2c::ErrorKind {
+        match self {
+            Self::NoAcknowledge => i2c::ErrorKind::NoAcknowledge(NoAcknowledgeSource::Unknown),
+            Self::ArbitrationLoss => i2c::ErrorKind::ArbitrationLoss,
+            _ => i2c::ErrorKind::Other,
+        }
+    }
+}
+
+impl spi::Error for HalError {
+    fn kind(&self) -> spi::ErrorKind {
+        spi::ErrorKind::Other
+    }
+}
+
+impl embedded_io::Error for HalError {
+    fn kind(&self) -> embedded_io::ErrorKind {
+        match self {
+            Self::Timeout => embedded_io::ErrorKind::TimedOut,
+            Self::Framing => embedded_io::ErrorKind::InvalidData,
+            Self::Unsupported(_) => embedded_io::ErrorKind::Unsupported,
+            _ => embedded_io::ErrorKind::Other,
+        }
+    }
+}
+
+/// The state of a HalSimulation, shared by its adapters.
+struct Inner {
+    /// The Simulation.
+    sim: RefCell<Simulation>,
+    /// Time to wait for a bus operation to complete.
+    timeout: Cell<u64>,
+    /// Serial receivers sampling their pins after every step, dropped along with their ports.
+    receivers: RefCell<Vec<Weak<RefCell<UartReceiver>>>>,
+}
+
+/// A Simulation stepped by the firmware through its adapters.
+///
+/// # Example
+///
+/// ```
+/// # use rvfs_sim_core::element::gpio::GpioPort;
+/// # use rvfs_sim_core::hal::{HalDelay, HalPin, HalSimulation};
+/// # use rvfs_sim_core::sim::Simulation;
+/// # use rvfs_sim_core::time::SimDuration;
+/// # use rvfs_sim_core::wire::{Wire, WirePull};
+/// # use embedded_hal::delay::DelayNs;
+/// # use embedded_hal::digital::{InputPin, OutputPin};
+/// let mut sim = Simulation::new(SimDuration::from_ticks(10));
+/// let port = GpioPort::new("U1", 1, 0).unwrap();
+/// let host = port.host();
+/// let port = sim.add_element(Box::new(port)).unwrap();
+/// let wire = sim.add_wire(Wire::new("LED", WirePull::Down)).unwrap();
+/// sim.connect_output(sim.output_pin(port, "P0").unwrap(), wire).unwrap();
+/// sim.connect_input(wire, sim.input_pin(port, "P0").unwrap()).unwrap();
+///
+/// let hal = HalSimulation::new(sim);
+/// let mut led = HalPin::new(host, 0).unwrap();
+/// let mut delay = HalDelay::new(&hal);
+/// led.set_high().unwrap();
+/// delay.delay_us(1);
+///
+/// assert!(led.is_high().unwrap());
+/// assert_eq!(1000, hal.time());
+/// ```
+#[derive(Clone)]
+pub struct HalSimulation {
+    /// The shared state.
+    inner: Rc<Inner>,
+}
+
+impl HalSimulation {
+    /// Create a new HalSimulation.
+    ///
+    /// # Parameters
+    ///
+    /// - `sim`: The Simulation, with all its Wires and Elements already added.
+    pub fn new(sim: Simulation) -> Self {
+        Self {
+            inner: Rc::new(Inner {
+                sim: RefCell::new(sim),
+                timeout: Cell::new(DEFAULT_TIMEOUT),
+                receivers: RefCell::new(Vec::new()),
+            }),
+        }
+    }
+
+    /// Obtain the Simulation, such as to inspect its Wires.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the Simulation is already borrowed.
+    pub fn simulation(&self) -> RefMut<'_, Simulation> {
+        self.inner.sim.borrow_mut()
+    }
+
+    /// Obtain the present simulation time.
+    pub fn time(&self) -> u64 {
+        self.inner.sim.borrow().time()
+    }
+
+    /// Change the time to wait for a bus operation to complete before it fails with [HalError::Timeout].
+    ///
+    /// # Parameters
+    ///
+    /// - `timeout`: New timeout, in time units.
+    pub fn set_timeout(&self, timeout: u64) {
+        self.inner.timeout.set(timeout);
+    }
+
+    /// Step the Simulation once, then let the serial receivers sample their pins.
+    fn step(&self) -> Result<(), HalError> {
+        let mut sim = self.inner.sim.borrow_mut();
+        match sim.step() {
+            Ok(SimResult::Continuing | SimResult::Breakpoint(_) | SimResult::Limit(_)) => (),
+            Ok(SimResult::Finished) => {
+                return Err(HalError::Simulation(format!(
+                    "Simulation finished at time {}",
+                    sim.time()
+                )))
+            }
+            Err(error) => return Err(HalError::Simulation(error.to_string())),
+        }
+        let time = sim.time();
+        drop(sim);
+
+        self.inner.receivers.borrow_mut().retain(|receiver| {
+            receiver.upgrade().is_some_and(|receiver| {
+                receiver.borrow_mut().sample(time);
+                true
+            })
+        });
+        Ok(())
+    }
+
+    /// Step the Simulation until a duration has elapsed.
+    ///
+    /// # Parameters
+    ///
+    /// - `duration`: The duration, in time units.
+    pub fn advance(&self, duration: u64) -> Result<(), HalError> {
+        let end = self.time() + duration;
+        while self.time() < end {
+            self.step()?;
+        }
+        Ok(())
+    }
+
+    /// Step the Simulation until a condition holds, or the timeout elapses.
+    ///
+    /// # Parameters
+    ///
+    /// - `done`: The condition, checked before every step.
+    fn wait_until(&self, mut done: impl FnMut() -> bool) -> Result<(), HalError> {
+        let deadline = self.time() + self.inner.timeout.get();
+        while !done() {
+            if self.time() >= deadline {
+                return Err(HalError::Timeout);
+            }
+            self.step()?;
+        }
+        Ok(())
+    }
+}
+
+/// Check that a bit exists on a GPIO port.
+///
+/// # Parameters
+///
+/// - `host`: The host side of the port.
+/// - `bit`: The bit.
+fn check_bit(host: &GpioHost, bit: usize) -> Result<(), String> {
+    if bit < host.width() {
+        Ok(())
+    } else {
+        Err(format!("GPIO port has no bit {bit}"))
+    }
+}
+
+/// A bit of a GpioPort, as an embedded-hal input and output pin.
+///
+/// Setting the pin makes its bit an output, and [releasing](HalPin::release) it makes its bit an input again.  The pin
+/// reads the level of its Wire as sampled at the last step, whatever its direction, and a change made to it takes
+/// effect at the next step.
+pub struct HalPin {
+    /// The host side of the port.
+    host: GpioHost,
+    /// The bit of the port.
+    bit: usize,
+}
+
+impl HalPin {
+    /// Create a new HalPin, leaving the direction of its bit unchanged.
+    ///
+    /// # Parameters
+    ///
+    /// - `host`: The host side of the port.
+    /// - `bit`: The bit of the port.
+    pub fn new(host: GpioHost, bit: usize) -> Result<Self, String> {
+        check_bit(&host, bit)?;
+        Ok(Self { host, bit })
+    }
+
+    /// Release the pin, making its bit an input.
+    pub fn release(&mut self) {
+        // The bit was checked on creation.
+        self.host.set_direction(self.bit, Direction::Input).unwrap();
+    }
+
+    /// Drive a value onto the pin, making its bit an output.
+    ///
+    /// # Parameters
+    ///
+    /// - `value`: The value.
+    fn drive(&mut self, value: bool) {
+        self.host.write(self.bit, value).unwrap();
+        self.host
+            .set_direction(self.bit, Direction::Output)
+            .unwrap();
+    }
+}
+
+impl digital::ErrorType for HalPin {
+    type Error = HalError;
+}
+
+impl digital::OutputPin for HalPin {
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        self.drive(false);
+        Ok(())
+    }
+
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        self.drive(true);
+        Ok(())
+    }
+}
+
+impl digital::InputPin for HalPin {
+    fn is_high(&mut self) -> Result<bool, Self::Error> {
+        self.host.read(self.bit).ok_or(HalError::Indeterminate)
+    }
+
+    fn is_low(&mut self) -> Result<bool, Self::Error> {
+        self.is_high().map(|high| !high)
+    }
+}
+
+/// An embedded-hal delay, which waits by stepping the Simulation.
+///
+/// A delay ends at the first step at or after its duration, so it is rounded up to the step interval.
+///
+/// # Panics
+///
+/// The delay traits cannot fail, so a delay panics if the Simulation fails or finishes while it waits.
+pub struct HalDelay {
+    /// The HalSimulation.
+    hal: HalSimulation,
+}
+
+impl HalDelay {
+    /// Create a new HalDelay.
+    ///
+    /// # Parameters
+    ///
+    /// - `hal`: The HalSimulation to step.
+    pub fn new(hal: &HalSimulation) -> Self {
+        Self { hal: hal.clone() }
+    }
+}
+
+impl embedded_hal::delay::DelayNs for HalDelay {
+    fn delay_ns(&mut self, ns: u32) {
+        if let Err(error) = self.hal.advance(u64::from(ns)) {
+            panic!("Delay failed: {error}");
+        }
+    }
+}
+
+/// An embedded-hal I2C bus, performing transactions through the host side of an
+/// [I2cMaster](crate::element::i2c::I2cMaster).
+///
+/// The master performs a write followed by a read, so a transaction must not have a write operation after a read
+/// operation.  The outcomes of the master are taken by the adapter, so it should be the master's only user.
+pub struct HalI2c {
+    /// The HalSimulation.
+    hal: HalSimulation,
+    /// The host side of the master.
+    host: I2cHost,
+}
+
+impl HalI2c {
+    /// Create a new HalI2c.
+    ///
+    /// # Parameters
+    ///
+    /// - `hal`: The HalSimulation holding the master.
+    /// - `host`: The host side of the master.
+    pub fn new(hal: &HalSimulation, host: I2cHost) -> Self {
+        Self {
+            hal: hal.clone(),
+            host,
+        }
+    }
+}
+
+impl i2c::ErrorType for HalI2c {
+    type Error = HalError;
+}
+
+impl i2c::I2c for HalI2c {
+    fn transaction(
+        &mut self,
+        address: u8,
+        operations: &mut [Operation<'_>],
+    ) -> Result<(), Self::Error> {
+        let mut write = Vec::new();
+        let mut read = 0;
+        for operation in operations.iter() {
+            match operation {
+                Operation::Write(_) if read > 0 => {
+                    return Err(HalError::Unsupported(
+                        "I2C master cannot write after reading in a transaction".to_string(),
+                    ))
+                }
+                Operation::Write(bytes) => write.extend_from_slice(bytes),
+                Operation::Read(buffer) => read += buffer.len(),
+            }
+        }
+
+        self.host.queue(I2cTransaction {
+            address,
+            write,
+            read,
+        });
+        let mut outcome = None;
+        self.hal.wait_until(|| {
+            outcome = self.host.take_outcomes().pop();
+            outcome.is_some()
+        })?;
+        let mut bytes = match outcome {
+            Some(I2cOutcome::Complete(bytes)) => bytes.into_iter(),
+            Some(I2cOutcome::Nack) => return Err(HalError::NoAcknowledge),
+            _ => return Err(HalError::ArbitrationLoss),
+        };
+        for operation in operations {
+            if let Operation::Read(buffer) = operation {
+                for (byte, value) in buffer.iter_mut().zip(bytes.by_ref()) {
+                    *byte = value;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The bits of a GpioPort used by an SPI bus.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct SpiPins {
+    /// The clock output.
+    pub sck: usize,
+    /// The data output.
+    pub mosi: usize,
+    /// The data input.
+    pub miso: usize,
+}
+
+/// An embedded-hal SPI bus, bit-banged on bits of a GpioPort.
+///
+/// Bytes are shifted most significant bit first, in any of the four SPI modes, with each half of the clock period
+/// stepping the Simulation.  The half period should span at least two steps, so that the data input has been sampled
+/// before it is read.  Chip selects are left to the firmware, such as through a [HalPin].
+pub struct HalSpi {
+    /// The HalSimulation.
+    hal: HalSimulation,
+    /// The host side of the port.
+    host: GpioHost,
+    /// The bits of the port used.
+    pins: SpiPins,
+    /// The clock polarity and phase.
+    mode: spi::Mode,
+    /// Half of the clock period.
+    half_period: u64,
+}
+
+impl HalSpi {
+    /// Create a new HalSpi, making its clock and data outputs drive their idle levels.
+    ///
+    /// # Parameters
+    ///
+    /// - `hal`: The HalSimulation holding the port.
+    /// - `host`: The host side of the port.
+    /// - `pins`: The bits of the port to use.
+    /// - `mode`: The clock polarity and phase.
+    /// - `period`: The clock period, which must be at least two time units.
+    pub fn new(
+        hal: &HalSimulation,
+        host: GpioHost,
+        pins: SpiPins,
+        mode: spi::Mode,
+        period: u64,
+    ) -> Result<Self, String> {
+        for bit in [pins.sck, pins.mosi, pins.miso] {
+            check_bit(&host, bit)?;
+        }
+        if period < 2 {
+            return Err("SPI clock period must be at least two time units".to_string());
+        }
+        let spi = Self {
+            hal: hal.clone(),
+            host,
+            pins,
+            mode,
+            half_period: period / 2,
+        };
+        spi.set_clock(false);
+        spi.host.write(pins.mosi, false)?;
+        spi.host.set_direction(pins.sck, Direction::Output)?;
+        spi.host.set_direction(pins.mosi, Direction::Output)?;
+        spi.host.set_direction(pins.miso, Direction::Input)?;
+        Ok(spi)
+    }
+
+    /// Set the clock output to its active or idle level.
+    ///
+    /// # Parameters
+    ///
+    /// - `active`: Whether the clock is active.
+    fn set_clock(&self, active: bool) {
+        let idle_high = self.mode.polarity == Polarity::IdleHigh;
+        self.host.write(self.pins.sck, active != idle_high).unwrap();
+    }
+
+    /// Shift a byte out and in.
+    ///
+    /// # Parameters
+    ///
+    /// - `out`: The byte to shift out.
+    fn shift(&mut self, out: u8) -> Result<u8, HalError> {
+        let mut value = 0;
+        for i in (0..8).rev() {
+            // The data output changes a half period before the edge on which it is sampled: on the idle level for the
+            // first phase, or on the leading edge for the second.
+            let second_phase = self.mode.phase == Phase::CaptureOnSecondTransition;
+            self.set_clock(second_phase);
+            self.host
+                .write(self.pins.mosi, (out >> i) & 1 != 0)
+                .unwrap();
+            self.hal.advance(self.half_period)?;
+            let bit = self
+                .host
+                .read(self.pins.miso)
+                .ok_or(HalError::Indeterminate)?;
+            value |= u8::from(bit) << i;
+            self.set_clock(!second_phase);
+            self.hal.advance(self.half_period)?;
+        }
+        Ok(value)
+    }
+}
+
+impl spi::ErrorType for HalSpi {
+    type Error = HalError;
+}
+
+impl spi::SpiBus for HalSpi {
+    fn read(&mut self, words: &mut [u8]) -> Result<(), Self::Error> {
+        for word in words {
+            *word = self.shift(0)?;
+        }
+        Ok(())
+    }
+
+    fn write(&mut self, words: &[u8]) -> Result<(), Self::Error> {
+        for word in words {
+            self.shift(*word)?;
+        }
+        Ok(())
+    }
+
+    fn transfer(&mut self, read: &mut [u8], write: &[u8]) -> Result<(), Self::Error> {
+        for i in 0..read.len().max(write.len()) {
+            let value = self.shift(write.get(i).copied().unwrap_or(0))?;
+            if let Some(word) = read.get_mut(i) {
+                *word = value;
+            }
+        }
+        Ok(())
+    }
+
+    fn transfer_in_place(&mut self, words: &mut [u8]) -> Result<(), Self::Error> {
+        for word in words {
+            *word = self.shift(*word)?;
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        // Every word is shifted before its operation returns, so the clock is left idle.
+        self.set_clock(false);
+        self.hal.advance(self.half_period)
+    }
+}
+
+/// The receiving side of a HalUart, sampling its pin after every step of the Simulation.
+struct UartReceiver {
+    /// The host side of the port.
+    host: GpioHost,
+    /// The bit of the port receiving.
+    bit: usize,
+    /// The bit period.
+    period: u64,
+    /// Whether the line has been seen idle, so that a start bit can be recognised.
+    idle: bool,
+    /// The time at which the start bit of the frame being received began, with the data bits received so far.
+    frame: Option<(u64, u32, u8)>,
+    /// Bytes received and not yet read.
+    bytes: VecDeque<u8>,
+    /// Whether a frame has been received without a stop bit since the last read.
+    framing_error: bool,
+}
+
+impl UartReceiver {
+    /// Sample the pin.
+    ///
+    /// # Parameters
+    ///
+    /// - `time`: The simulation time.
+    fn sample(&mut self, time: u64) {
+        let level = self.host.read(self.bit);
+        let Some((start, bits, value)) = self.frame else {
+            if level == Some(false) && self.idle {
+                self.frame = Some((time, 0, 0));
+            }
+            self.idle = level == Some(true);
+            return;
+        };
+
+        // Bits are sampled at the first step after the middle of their period.
+        if time < start + self.period * u64::from(bits + 1) + self.period / 2 {
+            return;
+        }
+        if bits < 8 {
+            let bit = u8::from(level == Some(true));
+            self.frame = Some((start, bits + 1, value | (bit << bits)));
+        } else {
+            if level == Some(true) {
+                self.bytes.push_back(value);
+            } else {
+                self.framing_error = true;
+            }
+            self.frame = None;
+            self.idle = level == Some(true);
+        }
+    }
+}
+
+/// An embedded-io serial port, bit-banged on bits of a GpioPort with 8 data bits, no parity and one stop bit.
+///
+/// The receiving bit is sampled after every step of the Simulation, whichever adapter steps it, so bytes arriving while
+/// the firmware writes or waits are kept until they are read.  The bit period should span several steps.
+pub struct HalUart {
+    /// The HalSimulation.
+    hal: HalSimulation,
+    /// The host side of the port.
+    host: GpioHost,
+    /// The bit of the port transmitting.
+    tx: usize,
+    /// The bit period.
+    period: u64,
+    /// The receiving side.
+    receiver: Rc<RefCell<UartReceiver>>,
+}
+
+impl HalUart {
+    /// Create a new HalUart, making its transmitting bit an output driving the idle level.
+    ///
+    /// # Parameters
+    ///
+    /// - `hal`: The HalSimulation holding the port.
+    /// - `host`: The host side of the port.
+    /// - `tx`: The bit of the port transmitting.
+    /// - `rx`: The bit of the port receiving.
+    /// - `period`: The bit period, such as 104167 for 9600 baud.
+    pub fn new(
+        hal: &HalSimulation,
+        host: GpioHost,
+        tx: usize,
+        rx: usize,
+        period: u64,
+    ) -> Result<Self, String> {
+        check_bit(&host, tx)?;
+        check_bit(&host, rx)?;
+        if period == 0 {
+            return Err("Serial bit period must be non-zero".to_string());
+        }
+        host.write(tx, true)?;
+        host.set_direction(tx, Direction::Output)?;
+        host.set_direction(rx, Direction::Input)?;
+
+        let receiver = Rc::new(RefCell::new(UartReceiver {
+            host: host.clone(),
+            bit: rx,
+            period,
+            idle: false,
+            frame: None,
+            bytes: VecDeque::new(),
+            framing_error: false,
+        }));
+        hal.inner
+            .receivers
+            .borrow_mut()
+            .push(Rc::downgrade(&receiver));
+        Ok(Self {
+            hal: hal.clone(),
+            host,
+            tx,
+            period,
+            receiver,
+        })
+    }
+}
+
+impl embedded_io::ErrorType for HalUart {
+    type Error = HalError;
+}
+
+impl embedded_io::Read for HalUart {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        self.hal
+            .wait_until(|| !self.receiver.borrow().bytes.is_empty())?;
+
+        let mut receiver = self.receiver.borrow_mut();
+        if std::mem::take(&mut receiver.framing_error) {
+            return Err(HalError::Framing);
+        }
+        let count = buf.len().min(receiver.bytes.len());
+        for (byte, value) in buf.iter_mut().zip(receiver.bytes.drain(..count)) {
+            *byte = value;
+        }
+        Ok(count)
+    }
+}
+
+impl embedded_io::Write for HalUart {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        for byte in buf {
+            let bits = std::iter::once(false)
+                .chain((0..8).map(|i| (byte >> i) & 1 != 0))
+                .chain(std::iter::once(true));
+            for bit in bits {
+                self.host.write(self.tx, bit).unwrap();
+                self.hal.advance(self.period)?;
+            }
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        // Every byte is transmitted before its write returns.
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::element::gpio::GpioPort;
+    use crate::element::i2c::{I2cMaster, I2cSlave, RegisterFile};
+    use crate::element::Element;
+    use crate::time::SimDuration;
+    use crate::wire::{Wire, WirePull};
+    use crate::WireId;
+    use embedded_hal::delay::DelayNs;
+    use embedded_hal::digital::{InputPin, OutputPin};
+    use embedded_hal::i2c::I2c;
+    use embedded_hal::spi::{SpiBus, MODE_0, MODE_3};
+    use embedded_io::{Read, Write};
+
+    /// Add a GpioPort to a Simulation, connecting each bit to its own Wire, or to the Wire of an earlier bit.
+    ///
+    /// # Parameters
+    ///
+    /// - `sim`: The Simulation.
+    /// - `wires`: For each bit, the index of the bit whose Wire it connects to.
+    fn port(sim: &mut Simulation, wires: &[usize]) -> (GpioHost, Vec<WireId>) {
+        let port = GpioPort::new("U1", wires.len(), 0).unwrap();
+        let host = port.host();
+        let port = sim.add_element(Box::new(port)).unwrap();
+        let mut ids = Vec::new();
+        for (i, wire) in wires.iter().enumerate() {
+            if *wire == i {
+                ids.push(
+                    sim.add_wire(Wire::new(&format!("W{i}"), WirePull::Down))
+                        .unwrap(),
+                );
+            }
+            sim.connect_output(sim.output_pin(port, &format!("P{i}")).unwrap(), ids[*wire])
+                .unwrap();
+            sim.connect_input(ids[*wire], sim.input_pin(port, &format!("P{i}")).unwrap())
+                .unwrap();
+        }
+        (host, ids)
+    }
+
+    #[test]
+    fn hal_pins_and_delays() {
+        // GIVEN two pins of a port sharing a Wire
+        let mut sim = Simulation::new(SimDuration::from_ticks(10));
+        let (host, _) = port(&mut sim, &[0, 0]);
+        let hal = HalSimulation::new(sim);
+        let mut output = HalPin::new(host.clone(), 0).unwrap();
+        let mut input = HalPin::new(host.clone(), 1).unwrap();
+        let mut delay = HalDelay::new(&hal);
+        // WHEN one pin is driven high and then low, with delays between
+        let initial = input.is_high();
+        output.set_high().unwrap();
+        delay.delay_ns(25);
+        let high = input.is_high().unwrap();
+        output.set_low().unwrap();
+        delay.delay_us(1);
+        let low = input.is_low().unwrap();
+        output.release();
+        // THEN the other pin follows it, and time advances by whole steps
+        assert_eq!(Err(HalError::Indeterminate), initial);
+        assert!(high);
+        assert!(low);
+        assert_eq!(1030, hal.time());
+        assert_eq!(Some(Direction::Input), host.direction(0));
+        assert!(HalPin::new(host, 2).is_err());
+    }
+    #[test]
+    fn hal_i2c() {
+        // GIVEN an I2C master and a register file slave on a bus
+        let mut sim = Simulation::new(SimDuration::from_ticks(10));
+        let scl = sim.add_wire(Wire::new("SCL", WirePull::Up)).unwrap();
+        let sda = sim.add_wire(Wire::new("SDA", WirePull::Up)).unwrap();
+        let master = I2cMaster::new("M1", 80).unwrap();
+        let host = master.host();
+        let slave = I2cSlave::new("U1", 0x50, 0, Box::new(RegisterFile::new(4))).unwrap();
+        for element in [Box::new(master) as Box<dyn Element>, Box::new(slave)] {
+            let id = sim.add_element(element).unwrap();
+            for (pin, wire) in [("SCL", scl), ("SDA", sda)] {
+                sim.connect_output(sim.output_pin(id, pin).unwrap(), wire)
+                    .unwrap();
+                sim.connect_input(wire, sim.input_pin(id, pin).unwrap())
+                    .unwrap();
+            }
+        }
+        let hal = HalSimulation::new(sim);
+        let mut i2c = HalI2c::new(&hal, host);
+        // WHEN registers are written and read back, an absent slave is addressed, and an unsupported transaction made
+        i2c.write(0x50, &[0x01, 0xab, 0xcd]).unwrap();
+        let mut read = [0; 2];
+        i2c.write_read(0x50, &[0x01], &mut read).unwrap();
+        let absent = i2c.write(0x51, &[0x00]).unwrap_err();
+        let mut byte = [0];
+        let unsupported = i2c
+            .transaction(
+                0x50,
+                &mut [Operation::Read(&mut byte), Operation::Write(&[0])],
+            )
+            .unwrap_err();
+        // THEN the registers hold the bytes written, and the failures are reported
+        assert_eq!([0xab, 0xcd], read);
+        assert_eq!(
+            i2c::ErrorKind::NoAcknowledge(NoAcknowledgeSource::Unknown),
+            i2c::Error::kind(&absent)
+        );
+        assert!(matches!(unsupported, HalError::Unsupported(_)));
+    }
+    #[test]
+    fn hal_spi_loopback() {
+        // GIVEN an SPI bus whose data output is looped back to its data input
+        let mut sim = Simulation::new(SimDuration::from_ticks(10));
+        let (host, wires) = port(&mut sim, &[0, 1, 1]);
+        let hal = HalSimulation::new(sim);
+        let pins = SpiPins {
+            sck: 0,
+            mosi: 1,
+            miso: 2,
+        };
+        let mut spi = HalSpi::new(&hal, host.clone(), pins, MODE_0, 100).unwrap();
+        // WHEN bytes are transferred in two modes
+        let mut read = [0; 3];
+        spi.transfer(&mut read, &[0xa5, 0x3c]).unwrap();
+        spi.flush().unwrap();
+        let idle_low = hal.simulation().wire(wires[0]).unwrap().measure();
+        let mut spi = HalSpi::new(&hal, host, pins, MODE_3, 100).unwrap();
+        let mut words = [0x81, 0x7e];
+        spi.transfer_in_place(&mut words).unwrap();
+        spi.flush().unwrap();
+        let idle_high = hal.simulation().wire(wires[0]).unwrap().measure();
+        // THEN the bytes written are read back, with each bit taking a clock period, and the clock is left idle
+        assert_eq!([0xa5, 0x3c, 0x00], read);
+        assert_eq!([0x81, 0x7e], words);
+        assert_eq!(0.0, f32::from(idle_low));
+        assert_eq!(1.0, f32::from(idle_high));
+        assert_eq!(5 * 8 * 100 + 2 * 50, hal.time());
+    }
+    #[test]
+    fn hal_uart_loopback() {
+        // GIVEN a serial port whose output is looped back to its input
+        let mut sim = Simulation::new(SimDuration::from_ticks(10));
+        let (host, _) = port(&mut sim, &[0, 0]);
+        let hal = HalSimulation::new(sim);
+        hal.set_timeout(10_000);
+        let mut uart = HalUart::new(&hal, host, 0, 1, 100).unwrap();
+        HalDelay::new(&hal).delay_ns(100);
+        // WHEN bytes are written, then read back with a larger buffer, and read again with nothing sent
+        uart.write_all(b"OK\n").unwrap();
+        let mut buffer = [0; 8];
+        let count = uart.read(&mut buffer).unwrap();
+        let timeout = uart.read(&mut buffer).unwrap_err();
+        // THEN every byte is received, and the second read times out
+        assert_eq!(b"OK\n", &buffer[..count]);
+        assert_eq!(HalError::Timeout, timeout);
+    }
+}
@@ -0,0 +1,288 @@
+//! InputPins sample the levels of Wires so that Elements can read them as logic values.
+
+use crate::prelude::*;
+use crate::wirevalue::WireValue;
+
+/// Default Wire level at or below which an InputPin is considered logic low.
+pub const DEFAULT_LOW_THRESHOLD: f32 = 0.3;
+/// Default Wire level at or above which an InputPin is considered logic high.
+pub const DEFAULT_HIGH_THRESHOLD: f32 = 0.7;
+
+/// Logic state of an InputPin.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum InputPinState {
+    /// The sampled Wire level is at or below the low threshold.
+    Low,
+    /// The sampled Wire level is at or above the high threshold.
+    High,
+    /// The sampled Wire level is between the thresholds, or no Wire has been sampled.
+    Indeterminate,
+}
+
+/// An interface between Wire and Element instances.
+///
+/// An InputPin samples the level of its attached Wire once per step and classifies it against its thresholds, so
+/// that Elements see logic levels rather than analog values.  It also notes the edges made between definite logic
+/// levels, so that a Wire which dwells in the indeterminate band on its way from low to high still produces exactly
+/// one rising edge.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct InputPin {
+    /// A readable name for the pin.
+    name: String,
+
+    /// Wire level at or below which the pin is considered logic low.
+    low_threshold: f32,
+    /// Wire level at or above which the pin is considered logic high.
+    high_threshold: f32,
+
+    /// Logic state from the most recent sample.
+    state: InputPinState,
+    /// Whether the state changed at the most recent sample.
+    changed: bool,
+    /// Whether the most recent sample was of a Wire which is undriven or unknown.
+    unknown: bool,
+    /// Last definite logic state, if the pin has had one.
+    definite: Option<InputPinState>,
+    /// Whether the most recent sample completed a low to high transition.
+    rising: bool,
+    /// Whether the most recent sample completed a high to low transition.
+    falling: bool,
+}
+
+impl InputPin {
+    /// Create a new InputPin with the default thresholds.
+    ///
+    /// The pin is indeterminate until it first samples a Wire.
+    ///
+    /// # Parameters
+    ///
+    /// - `name`: A human-readable name to assign to the pin.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use rvfs_sim_core::ipin::{InputPin, InputPinState};
+    /// let pin = InputPin::new("/RESET");
+    ///
+    /// assert_eq!("/RESET", pin.name());
+    /// assert_eq!(InputPinState::Indeterminate, pin.state());
+    /// ```
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+
+            low_threshold: DEFAULT_LOW_THRESHOLD,
+            high_threshold: DEFAULT_HIGH_THRESHOLD,
+
+            state: InputPinState::Indeterminate,
+            changed: false,
+            unknown: false,
+            definite: None,
+            rising: false,
+            falling: false,
+        }
+    }
+
+    /// Obtain the pin name.
+    pub fn name(&self) -> &String {
+        &self.name
+    }
+
+    /// Set the thresholds used to classify the sampled Wire level.
+    ///
+    /// # Parameters
+    ///
+    /// - `low`: Level at or below which the pin is considered logic low.
+    /// - `high`: Level at or above which the pin is considered logic high.
+    pub fn set_thresholds(&mut self, low: f32, high: f32) {
+        self.low_threshold = low;
+        self.high_threshold = high;
+    }
+
+    /// Obtain the logic state from the most recent sample.
+    pub fn state(&self) -> InputPinState {
+        self.state
+    }
+
+    /// Query whether the logic state changed at the most recent sample.
+    pub fn changed(&self) -> bool {
+        self.changed
+    }
+
+    /// Query whether the most recent sample was of a Wire which is undriven or unknown, rather than one passing between
+    /// the thresholds.
+    pub fn unknown(&self) -> bool {
+        self.unknown
+    }
+
+    /// Query whether the most recent sample completed a transition from logic low to logic high.
+    pub fn rising(&self) -> bool {
+        self.rising
+    }
+
+    /// Query whether the most recent sample completed a transition from logic high to logic low.
+    pub fn falling(&self) -> bool {
+        self.falling
+    }
+
+    /// Sample the level of the attached Wire.
+    ///
+    /// # Parameters
+    ///
+    /// - `value`: Present level of the attached Wire.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use rvfs_sim_core::ipin::{InputPin, InputPinState};
+    /// # use rvfs_sim_core::wirevalue::WireValue;
+    /// let mut pin = InputPin::new("CLK");
+    ///
+    /// pin.step(WireValue::new(0.0));
+    /// pin.step(WireValue::new(0.5));
+    /// assert_eq!(InputPinState::Indeterminate, pin.state());
+    /// assert!(!pin.rising());
+    ///
+    /// pin.step(WireValue::new(0.9));
+    /// assert_eq!(InputPinState::High, pin.state());
+    /// assert!(pin.rising());
+    /// ```
+    pub fn step(&mut self, value: WireValue) {
+        let level = f32::from(value);
+        let state = if level >= self.high_threshold {
+            InputPinState::High
+        } else if level <= self.low_threshold {
+            InputPinState::Low
+        } else {
+            InputPinState::Indeterminate
+        };
+        self.unknown = false;
+        self.update(state);
+    }
+
+    /// Update the state of the pin from a Wire which is undriven or driven both high and low, and so reads as
+    /// indeterminate whatever its level.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use rvfs_sim_core::ipin::{InputPin, InputPinState};
+    /// # use rvfs_sim_core::wirevalue::WireValue;
+    /// let mut pin = InputPin::new("D0");
+    ///
+    /// pin.step(WireValue::new(1.0));
+    /// pin.step_unknown();
+    /// assert_eq!(InputPinState::Indeterminate, pin.state());
+    /// assert!(pin.changed());
+    /// assert!(pin.unknown());
+    /// ```
+    pub fn step_unknown(&mut self) {
+        self.unknown = true;
+        self.update(InputPinState::Indeterminate);
+    }
+
+    /// Update the state of the pin, noting any change and any edge between definite logic levels.
+    ///
+    /// # Parameters
+    ///
+    /// - `state`: The newly sampled state.
+    fn update(&mut self, state: InputPinState) {
+        self.changed = state != self.state;
+        self.state = state;
+        self.rising = false;
+        self.falling = false;
+        if state != InputPinState::Indeterminate {
+            match (self.definite, state) {
+                (Some(InputPinState::Low), InputPinState::High) => self.rising = true,
+                (Some(InputPinState::High), InputPinState::Low) => self.falling = true,
+                _ => (),
+            }
+            self.definite = Some(state);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn input_pin_create() {
+        // GIVEN a name
+        let name = "foo";
+        // WHEN a new InputPin is created
+        let pin = InputPin::new(name);
+        // THEN it has the specified name, default thresholds and no state
+        assert_eq!(name, pin.name());
+        assert_eq!(DEFAULT_LOW_THRESHOLD, pin.low_threshold);
+        assert_eq!(DEFAULT_HIGH_THRESHOLD, pin.high_threshold);
+        assert_eq!(InputPinState::Indeterminate, pin.state());
+        assert!(!pin.changed());
+    }
+    #[test]
+    fn input_pin_thresholds() {
+        // GIVEN a pin with narrowed thresholds
+        let mut pin = InputPin::new("foo");
+        pin.set_thresholds(0.45, 0.55);
+        // WHEN levels either side of the thresholds are sampled
+        // THEN they are classified against the new thresholds
+        pin.step(WireValue::new(0.4));
+        assert_eq!(InputPinState::Low, pin.state());
+        pin.step(WireValue::new(0.5));
+        assert_eq!(InputPinState::Indeterminate, pin.state());
+        pin.step(WireValue::new(0.6));
+        assert_eq!(InputPinState::High, pin.state());
+    }
+    #[test]
+    fn input_pin_changed() {
+        // GIVEN a pin which has sampled a low level
+        let mut pin = InputPin::new("foo");
+        pin.step(WireValue::new(0.0));
+        // WHEN the same level is sampled again
+        pin.step(WireValue::new(0.1));
+        // THEN no change is reported until the state changes
+        assert!(!pin.changed());
+        pin.step(WireValue::new(1.0));
+        assert!(pin.changed());
+    }
+    #[test]
+    fn input_pin_first_sample_is_not_an_edge() {
+        // GIVEN a new pin
+        let mut pin = InputPin::new("foo");
+        // WHEN its first sample is high
+        pin.step(WireValue::new(1.0));
+        // THEN no edge is reported, since there was no earlier definite level
+        assert!(pin.changed());
+        assert!(!pin.rising());
+        assert!(!pin.falling());
+    }
+    #[test]
+    fn input_pin_falling_edge() {
+        // GIVEN a high pin
+        let mut pin = InputPin::new("foo");
+        pin.step(WireValue::new(1.0));
+        // WHEN the level falls through the indeterminate band over several samples
+        pin.step(WireValue::new(0.5));
+        assert!(!pin.falling());
+        pin.step(WireValue::new(0.1));
+        // THEN one falling edge is reported on reaching low, and it lasts a single sample
+        assert!(pin.falling());
+        assert!(!pin.rising());
+        pin.step(WireValue::new(0.0));
+        assert!(!pin.falling());
+    }
+    #[test]
+    fn input_pin_glitch_is_not_an_edge() {
+        // GIVEN a low pin
+        let mut pin = InputPin::new("foo");
+        pin.step(WireValue::new(0.0));
+        // WHEN the level wanders into the indeterminate band and back
+        pin.step(WireValue::new(0.5));
+        pin.step(WireValue::new(0.0));
+        // THEN no edge is reported
+        assert!(!pin.rising());
+        assert!(!pin.falling());
+    }
+}
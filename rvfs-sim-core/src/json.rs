@@ -0,0 +1,365 @@
+//! Minimal helpers for writing and reading JSON text.
+
+use std::fmt::Write;
+
+/// Encode a string as a quoted JSON string literal.
+///
+/// # Parameters
+///
+/// - `text`: The string to encode.
+pub(crate) fn string(text: &str) -> String {
+    let mut out = String::with_capacity(text.len() + 2);
+    out.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(out, "\\u{:04x}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// A JSON value, as read by [parse].
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Value {
+    /// `null`.
+    Null,
+    /// `true` or `false`.
+    Bool(bool),
+    /// A number.
+    Number(f64),
+    /// A string.
+    String(String),
+    /// An array of values.
+    Array(Vec<Value>),
+    /// An object, as its members in order.
+    Object(Vec<(String, Value)>),
+}
+
+impl Value {
+    /// Obtain a member of an object, if it is an object with that member.
+    ///
+    /// # Parameters
+    ///
+    /// - `key`: Name of the member.
+    pub(crate) fn get(&self, key: &str) -> Option<&Value> {
+        match self {
+            Value::Object(members) => members
+                .iter()
+                .find(|(name, _)| name == key)
+                .map(|(_, value)| value),
+            _ => None,
+        }
+    }
+
+    /// Obtain the string, if the value is a string.
+    pub(crate) fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::String(text) => Some(text),
+            _ => None,
+        }
+    }
+
+    /// Obtain the number, if the value is a number.
+    pub(crate) fn as_f64(&self) -> Option<f64> {
+        match self {
+            Value::Number(number) => Some(*number),
+            _ => None,
+        }
+    }
+}
+
+/// Parse JSON text.
+///
+/// The relaxed JavaScript object syntax in which WaveDrom diagrams are commonly written is also accepted: unquoted
+/// keys, single-quoted strings, trailing commas, and `//` and `/* */` comments.
+///
+/// # Parameters
+///
+/// - `text`: The text to parse.
+pub(crate) fn parse(text: &str) -> Result<Value, String> {
+    let mut parser = Parser {
+        chars: text.chars().collect(),
+        position: 0,
+    };
+    let value = parser.value()?;
+    parser.skip_space()?;
+    if parser.position < parser.chars.len() {
+        return Err(parser.error("end of text"));
+    }
+    Ok(value)
+}
+
+/// A recursive descent parser of JSON text.
+struct Parser {
+    /// Characters of the text.
+    chars: Vec<char>,
+    /// Position of the next character to parse.
+    position: usize,
+}
+
+impl Parser {
+    /// Build an error describing what was expected at the present position.
+    fn error(&self, expected: &str) -> String {
+        format!(
+            "Invalid JSON at offset {}: expected {expected}",
+            self.position
+        )
+    }
+
+    /// Obtain the next character without consuming it.
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.position).copied()
+    }
+
+    /// Skip whitespace and comments.
+    fn skip_space(&mut self) -> Result<(), String> {
+        loop {
+            match (self.peek(), self.chars.get(self.position + 1)) {
+                (Some(c), _) if c.is_whitespace() => self.position += 1,
+                (Some('/'), Some('/')) => {
+                    while self.peek().is_some_and(|c| c != '\n') {
+                        self.position += 1;
+                    }
+                }
+                (Some('/'), Some('*')) => {
+                    self.position += 2;
+                    while !(self.peek() == Some('*')
+                        && self.chars.get(self.position + 1) == Some(&'/'))
+                    {
+                        if self.peek().is_none() {
+                            return Err(self.error("end of comment"));
+                        }
+                        self.position += 1;
+                    }
+                    self.position += 2;
+                }
+                _ => return Ok(()),
+            }
+        }
+    }
+
+    /// Parse a value.
+    fn value(&mut self) -> Result<Value, String> {
+        self.skip_space()?;
+        match self.peek() {
+            Some('{') => self.object(),
+            Some('[') => self.array(),
+            Some(quote @ ('"' | '\'')) => self.string(quote).map(Value::String),
+            Some(c) if c == '-' || c == '+' || c == '.' || c.is_ascii_digit() => self.number(),
+            Some(c) if c.is_alphabetic() || c == '_' || c == '$' => {
+                let start = self.position;
+                match self.identifier().as_str() {
+                    "null" => Ok(Value::Null),
+                    "true" => Ok(Value::Bool(true)),
+                    "false" => Ok(Value::Bool(false)),
+                    _ => {
+                        self.position = start;
+                        Err(self.error("a value"))
+                    }
+                }
+            }
+            _ => Err(self.error("a value")),
+        }
+    }
+
+    /// Parse the elements of an array, or the members of an object, up to a closing delimiter.
+    ///
+    /// # Parameters
+    ///
+    /// - `close`: The closing delimiter.
+    /// - `item`: Parses a single element or member.
+    fn items<T>(
+        &mut self,
+        close: char,
+        mut item: impl FnMut(&mut Self) -> Result<T, String>,
+    ) -> Result<Vec<T>, String> {
+        self.position += 1;
+        let mut items = Vec::new();
+        loop {
+            self.skip_space()?;
+            if self.peek() == Some(close) {
+                self.position += 1;
+                return Ok(items);
+            }
+            items.push(item(self)?);
+            self.skip_space()?;
+            match self.peek() {
+                Some(',') => self.position += 1,
+                Some(c) if c == close => (),
+                _ => return Err(self.error(&format!("',' or '{close}'"))),
+            }
+        }
+    }
+
+    /// Parse an array.
+    fn array(&mut self) -> Result<Value, String> {
+        self.items(']', Self::value).map(Value::Array)
+    }
+
+    /// Parse an object.
+    fn object(&mut self) -> Result<Value, String> {
+        self.items('}', |parser| {
+            let key = match parser.peek() {
+                Some(quote @ ('"' | '\'')) => parser.string(quote)?,
+                Some(c) if c.is_alphanumeric() || c == '_' || c == '$' => parser.identifier(),
+                _ => return Err(parser.error("a key")),
+            };
+            parser.skip_space()?;
+            if parser.peek() != Some(':') {
+                return Err(parser.error("':'"));
+            }
+            parser.position += 1;
+            Ok((key, parser.value()?))
+        })
+        .map(Value::Object)
+    }
+
+    /// Parse an unquoted identifier.
+    fn identifier(&mut self) -> String {
+        let start = self.position;
+        while self
+            .peek()
+            .is_some_and(|c| c.is_alphanumeric() || c == '_' || c == '$')
+        {
+            self.position += 1;
+        }
+        self.chars[start..self.position].iter().collect()
+    }
+
+    /// Parse a number.
+    fn number(&mut self) -> Result<Value, String> {
+        let start = self.position;
+        while self
+            .peek()
+            .is_some_and(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '+' | '.'))
+        {
+            self.position += 1;
+        }
+        let text: String = self.chars[start..self.position].iter().collect();
+        text.parse().map(Value::Number).map_err(|_| {
+            self.position = start;
+            self.error("a number")
+        })
+    }
+
+    /// Parse a quoted string.
+    ///
+    /// # Parameters
+    ///
+    /// - `quote`: The quotation mark delimiting the string.
+    fn string(&mut self, quote: char) -> Result<String, String> {
+        self.position += 1;
+        let mut text = String::new();
+        loop {
+            let c = self.peek().ok_or_else(|| self.error("end of string"))?;
+            self.position += 1;
+            match c {
+                c if c == quote => return Ok(text),
+                '\\' => {
+                    let escape = self.peek().ok_or_else(|| self.error("an escape"))?;
+                    self.position += 1;
+                    text.push(match escape {
+                        'n' => '\n',
+                        'r' => '\r',
+                        't' => '\t',
+                        'b' => '\u{8}',
+                        'f' => '\u{c}',
+                        'u' => {
+                            let digits: String =
+                                self.chars.iter().skip(self.position).take(4).collect();
+                            self.position += 4;
+                            u32::from_str_radix(&digits, 16)
+                                .ok()
+                                .and_then(char::from_u32)
+                                .ok_or_else(|| self.error("a unicode escape"))?
+                        }
+                        c => c,
+                    });
+                }
+                c => text.push(c),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn json_string() {
+        // GIVEN strings with and without characters requiring escapes
+        // WHEN they are encoded
+        // THEN they are quoted and escaped as required
+        assert_eq!("\"/RESET\"", string("/RESET"));
+        assert_eq!("\"a\\\"b\\\\c\\n\"", string("a\"b\\c\n"));
+        assert_eq!("\"\\u0001\"", string("\u{1}"));
+    }
+    #[test]
+    fn json_parse() {
+        // GIVEN JSON text, and the relaxed syntax of a pasted WaveDrom diagram
+        let strict = r#"{"a": [1, -2.5e1, true, null], "b\u0041": "x\"y"}"#;
+        let relaxed = "{ signal: [ // clock\n { name: 'clk', wave: 'p..' }, /* gap */ {}, ] }";
+        // WHEN they are parsed
+        // THEN their values are obtained
+        assert_eq!(
+            Ok(Value::Object(vec![
+                (
+                    "a".to_string(),
+                    Value::Array(vec![
+                        Value::Number(1.0),
+                        Value::Number(-25.0),
+                        Value::Bool(true),
+                        Value::Null,
+                    ])
+                ),
+                ("bA".to_string(), Value::String("x\"y".to_string())),
+            ])),
+            parse(strict)
+        );
+        assert_eq!(
+            Ok(Value::Object(vec![(
+                "signal".to_string(),
+                Value::Array(vec![
+                    Value::Object(vec![
+                        ("name".to_string(), Value::String("clk".to_string())),
+                        ("wave".to_string(), Value::String("p..".to_string())),
+                    ]),
+                    Value::Object(Vec::new()),
+                ])
+            )])),
+            parse(relaxed)
+        );
+    }
+    #[test]
+    fn json_parse_errors() {
+        // GIVEN malformed JSON texts
+        // WHEN they are parsed
+        // THEN the position of each error is reported
+        assert_eq!(
+            Err("Invalid JSON at offset 6: expected ',' or '}'".to_string()),
+            parse("{a: 1 b: 2}")
+        );
+        assert_eq!(
+            Err("Invalid JSON at offset 4: expected end of string".to_string()),
+            parse("'abc")
+        );
+        assert_eq!(
+            Err("Invalid JSON at offset 3: expected end of text".to_string()),
+            parse("[] []")
+        );
+        assert_eq!(
+            Err("Invalid JSON at offset 1: expected a value".to_string()),
+            parse("[undefined]")
+        );
+    }
+}
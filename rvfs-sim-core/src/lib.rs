@@ -1,70 +1,295 @@
-// pub mod ipin;
+// Without the `std` feature only the single-threaded core of the simulator is built, on `core` and `alloc`, so that
+// small circuit models can run on embedded targets.  Everything needing threads, files, sockets or a wall clock
+// requires `std`.
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+#[cfg(feature = "std")]
+pub mod activity;
+pub mod callback;
+#[cfg(feature = "std")]
+pub mod cdc;
+#[cfg(feature = "std")]
+pub mod circuit;
+#[cfg(feature = "std")]
+pub mod control;
+pub mod diff;
+pub mod element;
+pub mod error;
+mod executor;
+#[cfg(feature = "std")]
+pub mod fault;
+#[cfg(feature = "grpc")]
+pub mod grpc;
+#[cfg(feature = "hal")]
+pub mod hal;
+pub mod ipin;
+#[cfg(feature = "std")]
+mod json;
 mod library;
+mod math;
+pub mod metrics;
+#[cfg(feature = "std")]
+pub mod monitor;
+#[cfg(feature = "mqtt")]
+pub mod mqtt;
+#[cfg(feature = "std")]
+pub mod netlist;
 pub mod opin;
+#[cfg(feature = "std")]
+pub mod power;
+pub mod profile;
+pub mod rng;
+mod schedule;
+#[cfg(feature = "std")]
+pub mod select;
 pub mod sim;
+#[cfg(feature = "serde")]
+pub mod snapshot;
+pub mod stimulus;
+#[cfg(feature = "std")]
+pub mod subcircuit;
+#[cfg(feature = "std")]
+pub mod summary;
+#[cfg(feature = "std")]
+pub mod testbench;
+pub mod time;
+#[cfg(feature = "std")]
+pub mod timing;
+pub mod trace;
+pub mod watch;
 pub mod wire;
 pub mod wirevalue;
 
-/// Identifier used to look up simulation components.
+// Wall-clock instants, which WebAssembly in a browser obtains from the page's performance timer.
+#[cfg(all(feature = "std", not(target_arch = "wasm32")))]
+use std::time::Instant;
+#[cfg(all(feature = "std", target_arch = "wasm32"))]
+use web_time::Instant;
+
+use core::fmt;
+use core::hash::Hash;
+use core::marker::PhantomData;
+
+/// Allocating types which the `std` prelude would otherwise provide.
+mod prelude {
+    pub(crate) use alloc::boxed::Box;
+    pub(crate) use alloc::string::{String, ToString};
+    pub(crate) use alloc::vec::Vec;
+    #[cfg(not(feature = "std"))]
+    pub(crate) use alloc::{format, vec};
+}
+
+/// A stand-in for wall-clock instants where there is no clock, so that every span of time measures as zero.
+#[cfg(not(feature = "std"))]
+#[derive(Debug, Copy, Clone)]
+struct Instant;
+
+#[cfg(not(feature = "std"))]
+impl Instant {
+    /// Obtain the present instant.
+    fn now() -> Self {
+        Self
+    }
+
+    /// Obtain the wall-clock time elapsed since the instant.
+    fn elapsed(&self) -> core::time::Duration {
+        core::time::Duration::ZERO
+    }
+}
+
+/// Identifier used to look up simulation components, as the raw index underlying each kind of [ComponentId].
 pub type Id = usize;
 
-/// Iterator over a sequence of Ids.
-pub struct IdIter {
+/// Number of low bits of an [Id] which hold the slot of a component, the high bits holding the generation of the slot.
+const SLOT_BITS: u32 = Id::BITS * 3 / 4;
+
+/// Mask selecting the slot bits of an [Id].
+const SLOT_MASK: Id = (1 << SLOT_BITS) - 1;
+
+/// Build an Id from a slot and a generation, wrapping the generation into the bits available to it.
+///
+/// # Parameters
+///
+/// - `slot`: Slot occupied by the component.
+/// - `generation`: Number of components which have occupied the slot before it.
+fn compose<I: ComponentId>(slot: Id, generation: Id) -> I {
+    I::from((slot & SLOT_MASK) | (generation << SLOT_BITS))
+}
+
+/// An identifier of one kind of simulation component, so that passing the Id of one kind where another is expected
+/// fails to compile.
+///
+/// A component occupies a slot, which may be reused by a later component once it is removed.  Each reuse of a slot
+/// starts a new generation, which is part of the Id, so that the Id of a removed component never refers to the
+/// component which takes its place.
+pub trait ComponentId: Copy + Eq + Ord + Hash + fmt::Debug + From<Id> + Into<Id> {
+    /// Obtain the raw index of the component, combining its slot and generation.
+    fn index(self) -> Id {
+        self.into()
+    }
+
+    /// Obtain the slot occupied by the component.
+    fn slot(self) -> Id {
+        self.index() & SLOT_MASK
+    }
+
+    /// Obtain the generation of the component, which counts the components that occupied its slot before it.
+    fn generation(self) -> Id {
+        self.index() >> SLOT_BITS
+    }
+}
+
+impl ComponentId for Id {}
+
+/// Define a newtype [ComponentId] for one kind of simulation component.
+macro_rules! component_id {
+    ($(#[$meta:meta])* $name:ident) => {
+        $(#[$meta])*
+        #[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+        pub struct $name(Id);
+
+        impl From<Id> for $name {
+            fn from(index: Id) -> Self {
+                Self(index)
+            }
+        }
+
+        impl From<$name> for Id {
+            fn from(id: $name) -> Self {
+                id.0
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                match self.generation() {
+                    0 => write!(f, "{}", self.slot()),
+                    generation => write!(f, "{}.{generation}", self.slot()),
+                }
+            }
+        }
+
+        impl ComponentId for $name {}
+    };
+}
+
+component_id!(
+    /// Identifier of a [Wire](wire::Wire) in a Simulation.
+    WireId
+);
+component_id!(
+    /// Identifier of an [Element](element::Element) in a Simulation.
+    ElementId
+);
+component_id!(
+    /// Identifier of an [InputPin](ipin::InputPin) in a Simulation.
+    InputPinId
+);
+component_id!(
+    /// Identifier of an [OutputPin](opin::OutputPin) in a Simulation.
+    OutputPinId
+);
+
+/// Iterator over a sequence of Ids of one kind.
+pub struct IdIter<'a, I = Id> {
     /// Present Id.
     id: Id,
     /// Iteration terminator.
     end: Id,
+    /// Ids of removed components, in ascending order, which are skipped.
+    removed: &'a [Id],
+    /// Generation of the component in each slot, or empty if every component is of the first generation.
+    generations: &'a [Id],
+    /// Kind of Id yielded.
+    kind: PhantomData<I>,
 }
 
-impl IdIter {
+impl<'a, I> IdIter<'a, I> {
     /// Create a new iterator.
     ///
     /// # Parameters
     ///
     /// - `end`: Terminating value of the iteration (non-inclusive).
     fn new(end: Id) -> Self {
-        Self { id: 0, end }
+        Self {
+            id: 0,
+            end,
+            removed: &[],
+            generations: &[],
+            kind: PhantomData,
+        }
+    }
+
+    /// Skip the Ids of removed components.
+    ///
+    /// # Parameters
+    ///
+    /// - `removed`: Ids to skip, in ascending order.
+    fn skipping(self, removed: &'a [Id]) -> Self {
+        Self { removed, ..self }
+    }
+
+    /// Yield the Id of each slot with the generation of the component occupying it.
+    ///
+    /// # Parameters
+    ///
+    /// - `generations`: Generation of the component in each slot.
+    fn with_generations(self, generations: &'a [Id]) -> Self {
+        Self {
+            generations,
+            ..self
+        }
     }
 }
 
-impl Iterator for IdIter {
-    type Item = Id;
+impl<I: ComponentId> Iterator for IdIter<'_, I> {
+    type Item = I;
 
     fn next(&mut self) -> Option<Self::Item> {
-        let id = self.id;
-        if id < self.end {
+        while self.id < self.end {
+            let id = self.id;
             self.id += 1;
-            Some(id)
-        } else {
-            None
+            if self.removed.binary_search(&id).is_err() {
+                let generation = self.generations.get(id).copied().unwrap_or(0);
+                return Some(compose(id, generation));
+            }
         }
+        None
     }
 
     fn count(self) -> usize
     where
         Self: Sized,
     {
-        self.end
+        let skipped = self
+            .removed
+            .iter()
+            .filter(|id| (self.id..self.end).contains(*id))
+            .count();
+        self.end - self.id - skipped
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::prelude::*;
 
     #[test]
     fn id_iter_create() {
         // GIVEN an Id endpoint
         let end: Id = 7;
         // WHEN an iterator is created
-        let it = IdIter::new(end);
+        let it = IdIter::<Id>::new(end);
         // THEN creation succeeds and the iterator has "end" number of entries
         assert_eq!(end, it.count());
     }
     #[test]
     fn id_iter_iterate() {
         // GIVEN an initialized iterator
-        let mut it = IdIter::new(4);
+        let mut it = IdIter::<Id>::new(4);
         // THEN the iterator has the expected entries
         assert_eq!(Some(0), it.next());
         assert_eq!(Some(1), it.next());
@@ -72,4 +297,50 @@ mod tests {
         assert_eq!(Some(3), it.next());
         assert_eq!(None, it.next());
     }
+    #[test]
+    fn id_iter_skipping() {
+        // GIVEN an iterator which skips removed Ids
+        let mut it = IdIter::<Id>::new(5).skipping(&[1, 3]);
+        // THEN the removed Ids are neither yielded nor counted
+        assert_eq!(Some(0), it.next());
+        assert_eq!(3, IdIter::<Id>::new(5).skipping(&[1, 3]).count());
+        assert_eq!(Some(2), it.next());
+        assert_eq!(Some(4), it.next());
+        assert_eq!(None, it.next());
+    }
+    #[test]
+    fn component_id_generations() {
+        // GIVEN typed Ids of the first and a later generation of a slot
+        let first = compose::<ElementId>(3, 0);
+        let later = compose::<ElementId>(3, 2);
+        // WHEN they are taken apart and described
+        // THEN they share a slot but are distinct, and only the later one shows its generation
+        assert_eq!((3, 0), (first.slot(), first.generation()));
+        assert_eq!((3, 2), (later.slot(), later.generation()));
+        assert_ne!(first, later);
+        assert_eq!(ElementId::from(3), first);
+        assert_eq!("3", first.to_string());
+        assert_eq!("3.2", later.to_string());
+        assert_eq!(
+            vec![first, compose(4, 1)],
+            IdIter::<ElementId>::new(5)
+                .skipping(&[0, 1, 2])
+                .with_generations(&[0, 0, 0, 0, 1])
+                .collect::<Vec<_>>()
+        );
+    }
+    #[test]
+    fn component_id_conversions() {
+        // GIVEN a typed Id built from a raw Id
+        let id = WireId::from(3);
+        // WHEN it is converted back and described
+        // THEN the raw Id is recovered, and it is shown as a number
+        assert_eq!(3, id.index());
+        assert_eq!(3, Id::from(id));
+        assert_eq!("3", id.to_string());
+        assert_eq!(
+            vec![ElementId::from(0), ElementId::from(1)],
+            IdIter::<ElementId>::new(2).collect::<Vec<_>>()
+        );
+    }
 }
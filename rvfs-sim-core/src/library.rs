@@ -1,34 +1,99 @@
 //! A Library holds items and allows them to be checked out temporarily.
 
-use crate::{Id, IdIter};
+use crate::error::SimError;
+use crate::prelude::*;
+use crate::{compose, ComponentId, Id, IdIter};
+use core::marker::PhantomData;
 
 /// A container which allows items to be temporarily checked in and out by Id.
-#[derive(Debug)]
-pub struct Library<T> {
+///
+/// Items can also be removed for good, without changing the Ids of the other items.  The slot of a removed item is kept
+/// on a free list and reused by the next item added, under a new generation, so that the Id of the removed item is
+/// refused rather than resolving to the new one.  The Library hands out and accepts Ids of a single kind, so that the
+/// Id of a different kind of item cannot be used with it.
+#[derive(Debug, Clone)]
+pub struct Library<T, I = Id> {
     /// The "stacks" or "shelves" of the Library.
     items: Vec<Option<T>>,
+    /// Generation of the item in each slot.
+    generations: Vec<Id>,
+    /// Slots of removed items, free for reuse, in ascending order.
+    free: Vec<Id>,
+    /// Kind of Id used to look up items.
+    kind: PhantomData<I>,
 }
 
-impl<T> Library<T> {
+impl<T, I: ComponentId> Library<T, I> {
     /// Create a new Library instance.
     pub fn new() -> Self {
-        Self { items: Vec::new() }
+        Self {
+            items: Vec::new(),
+            generations: Vec::new(),
+            free: Vec::new(),
+            kind: PhantomData,
+        }
     }
 
     /// Add a new item to the Library's collection and provide the Id which can be used to look it up later.
     ///
+    /// The item takes the highest free slot, if any item has been removed, or otherwise a new slot.
+    ///
     /// # Parameters
     ///
     /// - `item`: The new item to be owned by the Library.
-    pub fn add(&mut self, item: T) -> Id {
-        let result = self.items.len();
-        self.items.push(Some(item));
-        result
+    pub fn add(&mut self, item: T) -> I {
+        match self.free.pop() {
+            Some(slot) => {
+                self.items[slot] = Some(item);
+                compose(slot, self.generations[slot])
+            }
+            None => {
+                self.items.push(Some(item));
+                self.generations.push(0);
+                I::from(self.items.len() - 1)
+            }
+        }
     }
 
-    /// Obtain an iterator over the Library's Ids.
-    pub fn iter(&self) -> IdIter {
+    /// Obtain an iterator over the Ids of the Library's items, skipping any which have been removed.
+    pub fn iter(&self) -> IdIter<'_, I> {
         IdIter::new(self.items.len())
+            .skipping(&self.free)
+            .with_generations(&self.generations)
+    }
+
+    /// Obtain an iterator over the Ids of the Library's items, with mutable access to each item which is checked in.
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (I, &mut T)> + '_ {
+        self.items
+            .iter_mut()
+            .zip(&self.generations)
+            .enumerate()
+            .filter_map(|(slot, (item, generation))| {
+                Some((compose(slot, *generation), item.as_mut()?))
+            })
+    }
+
+    /// Obtain the Id of the item occupying a slot, if the slot is not free.
+    ///
+    /// # Parameters
+    ///
+    /// - `slot`: The slot.
+    pub fn id(&self, slot: Id) -> Option<I> {
+        if slot < self.items.len() && self.free.binary_search(&slot).is_err() {
+            Some(compose(slot, self.generations[slot]))
+        } else {
+            None
+        }
+    }
+
+    /// Obtain the slot of an item, if its Id is of the present generation of the slot.
+    ///
+    /// # Parameters
+    ///
+    /// - `id`: Id of the item.
+    fn current(&self, id: I) -> Option<Id> {
+        let slot = id.slot();
+        (self.generations.get(slot) == Some(&id.generation())).then_some(slot)
     }
 
     /// Inspect a Library item without checking it out.
@@ -36,30 +101,36 @@ impl<T> Library<T> {
     /// # Parameters
     ///
     /// - `id`: Id of the item to inspect.
-    pub fn inspect(&self, id: Id) -> &Option<T> {
-        if id < self.items.len() {
-            // The item is on the shelf.
-            &self.items[id]
-        } else {
-            // The item is currently checked out.
-            &None
+    pub fn inspect(&self, id: I) -> &Option<T> {
+        match self.current(id) {
+            // The item is on the shelf, or checked out.
+            Some(slot) => &self.items[slot],
+            // The item does not exist, or has been removed.
+            None => &None,
         }
     }
 
+    /// Inspect a Library item mutably without checking it out.
+    ///
+    /// # Parameters
+    ///
+    /// - `id`: Id of the item to inspect.
+    pub fn inspect_mut(&mut self, id: I) -> Option<&mut T> {
+        let slot = self.current(id)?;
+        self.items[slot].as_mut()
+    }
+
     /// Check an item out of the Library, leaving its space empty.
     ///
     /// # Parameters
     ///
     /// - `id`: Id of the item to check out.
-    pub fn checkout(&mut self, id: Id) -> Result<T, String> {
-        if id < self.items.len() {
-            // The item is on the shelf.
-            self.items[id]
-                .take()
-                .ok_or("Item not available!".to_string())
-        } else {
-            // The item is currently checked out.
-            Err("Item not available!".to_string())
+    pub fn checkout(&mut self, id: I) -> Result<T, SimError> {
+        match self.current(id) {
+            // The item is on the shelf, unless it is currently checked out.
+            Some(slot) => self.items[slot].take().ok_or(SimError::CheckoutConflict),
+            // The item does not exist, or has been removed.
+            None => Err(SimError::CheckoutConflict),
         }
     }
 
@@ -69,19 +140,48 @@ impl<T> Library<T> {
     ///
     /// - `id`: Id of the item to check in.
     /// - `item`: The item being returned to the Library.
-    pub fn checkin(&mut self, id: Id, item: T) -> Result<Id, String> {
-        if id < self.items.len() && self.items[id].is_none() {
-            self.items[id] = Some(item);
-            Ok(id)
-        } else {
-            Err("Item cannot be checked in with that ID!".to_string())
+    pub fn checkin(&mut self, id: I, item: T) -> Result<I, SimError> {
+        match self.current(id) {
+            Some(slot) if self.items[slot].is_none() && !self.is_removed(id) => {
+                self.items[slot] = Some(item);
+                Ok(id)
+            }
+            _ => Err(SimError::CheckinConflict),
         }
     }
 
-    /// Verify that all items are checked in and accounted for.
-    pub fn audit(&self) -> Result<(), String> {
-        if self.items.iter().any(|i| i.is_none()) {
-            Err("Items missing from library!".to_string())
+    /// Remove an item from the Library for good, so that its Id can be neither checked out nor in, and free its slot
+    /// for the next item added.
+    ///
+    /// # Parameters
+    ///
+    /// - `id`: Id of the item to remove, which must not be checked out.
+    pub fn remove(&mut self, id: I) -> Result<T, SimError> {
+        let item = self.checkout(id)?;
+        let slot = id.slot();
+        self.generations[slot] = compose::<I>(slot, id.generation() + 1).generation();
+        if let Err(position) = self.free.binary_search(&slot) {
+            self.free.insert(position, slot);
+        }
+        Ok(item)
+    }
+
+    /// Query whether an item has been removed, including when its slot has since been reused.
+    ///
+    /// # Parameters
+    ///
+    /// - `id`: Id of the item.
+    pub fn is_removed(&self, id: I) -> bool {
+        match self.current(id) {
+            Some(slot) => self.free.binary_search(&slot).is_ok(),
+            None => id.slot() < self.items.len(),
+        }
+    }
+
+    /// Verify that all items which have not been removed are checked in and accounted for.
+    pub fn audit(&self) -> Result<(), SimError> {
+        if self.iter().any(|id| self.items[id.slot()].is_none()) {
+            Err(SimError::MissingItems)
         } else {
             Ok(())
         }
@@ -138,6 +238,19 @@ mod tests {
         assert_eq!(None, *lib.inspect(17));
     }
     #[test]
+    fn library_inspect_mut() {
+        // GIVEN a library containing an item
+        let mut lib = Library::<i32>::new();
+        let id = lib.add(102834);
+        // WHEN the item is modified in place
+        if let Some(item) = lib.inspect_mut(id) {
+            *item = -766;
+        }
+        // THEN the modified item remains in the library, and non-existent items cannot be modified
+        assert_eq!(Some(-766), *lib.inspect(id));
+        assert_eq!(None, lib.inspect_mut(17));
+    }
+    #[test]
     fn library_checkout() {
         // GIVEN a library containing some items
         let mut lib = Library::<i32>::new();
@@ -205,4 +318,87 @@ mod tests {
         // THEN the audit succeeds
         assert!(result.is_ok());
     }
+    #[test]
+    fn library_remove() {
+        // GIVEN a library containing some items
+        let mut lib = Library::<i32>::new();
+        lib.add(102834);
+        let id = lib.add(-766);
+        lib.add(0);
+        // WHEN an item is removed
+        let item = lib.remove(id);
+        // THEN it is gone for good, without changing the Ids of the other items or failing an audit
+        assert_eq!(Ok(-766), item);
+        assert!(lib.is_removed(id));
+        assert_eq!(vec![0, 2], lib.iter().collect::<Vec<_>>());
+        assert!(lib.checkin(id, 5).is_err());
+        assert!(lib.remove(id).is_err());
+        assert_eq!(Some(0), *lib.inspect(2));
+        assert!(lib.audit().is_ok());
+    }
+    #[test]
+    fn library_remove_reuse() {
+        // GIVEN a library from which an item has been removed
+        let mut lib = Library::<i32>::new();
+        lib.add(102834);
+        let stale = lib.add(-766);
+        lib.remove(stale).unwrap();
+        // WHEN new items are added
+        let reused = lib.add(5);
+        let appended = lib.add(6);
+        // THEN the first takes the freed slot under a new Id, and the removed item's Id resolves to nothing
+        assert_eq!((stale.slot(), 1), (reused.slot(), reused.generation()));
+        assert_eq!(2, appended);
+        assert_eq!(Some(5), *lib.inspect(reused));
+        assert_eq!(None, *lib.inspect(stale));
+        assert_eq!(None, lib.inspect_mut(stale));
+        assert!(lib.is_removed(stale));
+        assert!(!lib.is_removed(reused));
+        assert!(lib.checkout(stale).is_err());
+        assert!(lib.remove(stale).is_err());
+        assert_eq!(vec![0, reused, 2], lib.iter().collect::<Vec<_>>());
+        assert_eq!(Some(reused), lib.id(1));
+        let item = lib.checkout(reused).unwrap();
+        assert!(lib.checkin(stale, item).is_err());
+        assert!(lib.checkin(reused, item).is_ok());
+        assert!(lib.audit().is_ok());
+    }
+    #[test]
+    fn library_remove_reuse_order() {
+        // GIVEN a library from which two items have been removed
+        let mut lib = Library::<i32>::new();
+        let first = lib.add(1);
+        lib.add(2);
+        let third = lib.add(3);
+        lib.remove(first).unwrap();
+        lib.remove(third).unwrap();
+        // WHEN new items are added
+        let ids = [lib.add(4), lib.add(5), lib.add(6)];
+        // THEN they take the freed slots from the highest down, and then new slots
+        assert_eq!([2, 0, 3], ids.map(|id| id.slot()));
+    }
+    #[test]
+    fn library_iter_mut() {
+        // GIVEN a library with a removed item and a checked out item
+        let mut lib = Library::<i32>::new();
+        let removed = lib.add(1);
+        let checked_out = lib.add(2);
+        lib.add(3);
+        lib.remove(removed).unwrap();
+        let reused = lib.add(4);
+        let item = lib.checkout(checked_out).unwrap();
+        // WHEN the items are changed through a mutable iterator
+        for (_, value) in lib.iter_mut() {
+            *value *= 10;
+        }
+        // THEN only the items on the shelf are visited, under their present Ids
+        assert_eq!(
+            vec![(reused, 40), (2, 30)],
+            lib.iter_mut()
+                .map(|(id, value)| (id, *value))
+                .collect::<Vec<_>>()
+        );
+        assert!(lib.checkin(checked_out, item).is_ok());
+        assert_eq!(Some(2), *lib.inspect(checked_out));
+    }
 }
@@ -0,0 +1,27 @@
+//! Floating point functions which `core` lacks, taken from `libm` when built without the `std` feature.
+
+/// Calculate the exponential function.
+///
+/// # Parameters
+///
+/// - `x`: The exponent.
+pub(crate) fn exp(x: f32) -> f32 {
+    #[cfg(feature = "std")]
+    let result = x.exp();
+    #[cfg(not(feature = "std"))]
+    let result = libm::expf(x);
+    result
+}
+
+/// Obtain the fractional part of a number.
+///
+/// # Parameters
+///
+/// - `x`: The number.
+pub(crate) fn fract(x: f64) -> f64 {
+    #[cfg(feature = "std")]
+    let result = x.fract();
+    #[cfg(not(feature = "std"))]
+    let result = x - libm::trunc(x);
+    result
+}
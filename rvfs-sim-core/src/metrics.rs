@@ -0,0 +1,402 @@
+//! Runtime metrics describing the progress and performance of a Simulation.
+
+use crate::prelude::*;
+use crate::sim::Phase;
+#[cfg(feature = "std")]
+use crate::Instant;
+#[cfg(all(feature = "std", not(target_arch = "wasm32")))]
+use alloc::sync::Arc;
+#[cfg(target_has_atomic = "64")]
+use core::sync::atomic::{AtomicU64, Ordering};
+use core::time::Duration;
+#[cfg(not(target_has_atomic = "64"))]
+use portable_atomic::{AtomicU64, Ordering};
+#[cfg(all(feature = "std", not(target_arch = "wasm32")))]
+use std::io::{BufRead, BufReader, Write};
+#[cfg(all(feature = "std", not(target_arch = "wasm32")))]
+use std::net::{SocketAddr, TcpListener, ToSocketAddrs};
+#[cfg(feature = "std")]
+use std::sync::OnceLock;
+#[cfg(all(feature = "std", not(target_arch = "wasm32")))]
+use std::thread::{self, JoinHandle};
+
+/// Runtime metrics for a Simulation.
+///
+/// The Simulation updates its metrics as it steps, and a shared handle to them can be obtained from
+/// [`Simulation::metrics`](crate::sim::Simulation::metrics) so that they can be read from other threads, for example
+/// by the [Prometheus exporter](serve).
+#[derive(Debug, Default)]
+pub struct Metrics {
+    /// Wall-clock instant at which the first step began.
+    #[cfg(feature = "std")]
+    started: OnceLock<Instant>,
+    /// Seed of the Simulation's random number generator.
+    seed: AtomicU64,
+    /// Number of steps completed.
+    steps: AtomicU64,
+    /// Present simulation time.
+    time: AtomicU64,
+    /// Number of individual Wire steps executed.
+    wires_stepped: AtomicU64,
+    /// Number of Wire value changes made.
+    wire_changes: AtomicU64,
+    /// Number of step phases which timed out.
+    timeouts: AtomicU64,
+    /// Number of jobs waiting in the thread pool queue after the most recent dispatch.
+    queue_depth: AtomicU64,
+    /// Total wall-clock time spent in each step phase, in nanoseconds.
+    phase_total: [AtomicU64; Phase::COUNT],
+    /// Wall-clock time spent in each step phase during the most recent step, in nanoseconds.
+    phase_last: [AtomicU64; Phase::COUNT],
+}
+
+impl Metrics {
+    /// Create a new set of metrics, with all values zeroed.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Obtain the seed of the Simulation's random number generator, from which the run can be reproduced.
+    pub fn seed(&self) -> u64 {
+        self.seed.load(Ordering::Relaxed)
+    }
+
+    /// Obtain the number of steps completed.
+    pub fn steps(&self) -> u64 {
+        self.steps.load(Ordering::Relaxed)
+    }
+
+    /// Obtain the average number of steps completed per second of wall-clock time.
+    pub fn steps_per_second(&self) -> f64 {
+        let elapsed = self.elapsed().as_secs_f64();
+        if elapsed > 0.0 {
+            self.steps() as f64 / elapsed
+        } else {
+            0.0
+        }
+    }
+
+    /// Obtain the number of individual Wire steps executed.
+    pub fn wires_stepped(&self) -> u64 {
+        self.wires_stepped.load(Ordering::Relaxed)
+    }
+
+    /// Obtain the number of Wire value changes made.
+    pub fn wire_changes(&self) -> u64 {
+        self.wire_changes.load(Ordering::Relaxed)
+    }
+
+    /// Obtain the present simulation time.
+    pub fn time(&self) -> u64 {
+        self.time.load(Ordering::Relaxed)
+    }
+
+    /// Obtain the wall-clock time elapsed since the first step began, which is always zero without the `std` feature.
+    pub fn elapsed(&self) -> Duration {
+        #[cfg(feature = "std")]
+        let elapsed = self
+            .started
+            .get()
+            .map_or(Duration::ZERO, |started| started.elapsed());
+        #[cfg(not(feature = "std"))]
+        let elapsed = Duration::ZERO;
+        elapsed
+    }
+
+    /// Obtain the number of step phases which timed out.
+    pub fn timeouts(&self) -> u64 {
+        self.timeouts.load(Ordering::Relaxed)
+    }
+
+    /// Obtain the number of jobs waiting in the thread pool queue after the most recent dispatch.
+    pub fn queue_depth(&self) -> u64 {
+        self.queue_depth.load(Ordering::Relaxed)
+    }
+
+    /// Obtain the total wall-clock time spent in a step phase.
+    ///
+    /// # Parameters
+    ///
+    /// - `phase`: The step phase of interest.
+    pub fn phase_total(&self, phase: Phase) -> Duration {
+        Duration::from_nanos(self.phase_total[phase as usize].load(Ordering::Relaxed))
+    }
+
+    /// Obtain the wall-clock time spent in a step phase during the most recent step.
+    ///
+    /// # Parameters
+    ///
+    /// - `phase`: The step phase of interest.
+    pub fn phase_last(&self, phase: Phase) -> Duration {
+        Duration::from_nanos(self.phase_last[phase as usize].load(Ordering::Relaxed))
+    }
+
+    /// Note the seed of the Simulation's random number generator.
+    ///
+    /// # Parameters
+    ///
+    /// - `seed`: The seed.
+    pub(crate) fn set_seed(&self, seed: u64) {
+        self.seed.store(seed, Ordering::Relaxed);
+    }
+
+    /// Note the start of a step.
+    pub(crate) fn begin_step(&self) {
+        #[cfg(feature = "std")]
+        self.started.get_or_init(Instant::now);
+    }
+
+    /// Note the completion of a step.
+    ///
+    /// # Parameters
+    ///
+    /// - `time`: Simulation time after the step.
+    /// - `changes`: Number of Wire value changes made during the step.
+    pub(crate) fn end_step(&self, time: u64, changes: u64) {
+        self.steps.fetch_add(1, Ordering::Relaxed);
+        self.time.store(time, Ordering::Relaxed);
+        self.wire_changes.fetch_add(changes, Ordering::Relaxed);
+    }
+
+    /// Note that the Simulation has been moved to another time without the steps between being counted.
+    ///
+    /// # Parameters
+    ///
+    /// - `time`: Simulation time after the move.
+    pub(crate) fn rewind(&self, time: u64) {
+        self.time.store(time, Ordering::Relaxed);
+    }
+
+    /// Note the wall-clock time spent in a step phase.
+    ///
+    /// # Parameters
+    ///
+    /// - `phase`: The step phase which completed.
+    /// - `elapsed`: Wall-clock time spent in the phase.
+    pub(crate) fn record_phase(&self, phase: Phase, elapsed: Duration) {
+        let nanos = elapsed.as_nanos().min(u64::MAX as u128) as u64;
+        self.phase_total[phase as usize].fetch_add(nanos, Ordering::Relaxed);
+        self.phase_last[phase as usize].store(nanos, Ordering::Relaxed);
+    }
+
+    /// Note the stepping of Wires.
+    ///
+    /// # Parameters
+    ///
+    /// - `count`: Number of Wires stepped.
+    pub(crate) fn record_wires(&self, count: u64) {
+        self.wires_stepped.fetch_add(count, Ordering::Relaxed);
+    }
+
+    /// Note the dispatch of Element steps to the thread pool.
+    ///
+    /// # Parameters
+    ///
+    /// - `queue_depth`: Number of jobs left waiting in the thread pool queue.
+    pub(crate) fn record_queue_depth(&self, queue_depth: u64) {
+        self.queue_depth.store(queue_depth, Ordering::Relaxed);
+    }
+
+    /// Note a step phase timeout.
+    pub(crate) fn record_timeout(&self) {
+        self.timeouts.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Render the metrics in the Prometheus text exposition format.
+    pub fn to_prometheus(&self) -> String {
+        let mut text = String::new();
+        let mut metric = |name: &str, kind: &str, help: &str, samples: &[(&str, String)]| {
+            text += &format!("# HELP rvfs_sim_{name} {help}\n# TYPE rvfs_sim_{name} {kind}\n");
+            for (labels, value) in samples {
+                text += &format!("rvfs_sim_{name}{labels} {value}\n");
+            }
+        };
+
+        metric(
+            "steps_total",
+            "counter",
+            "Simulation steps completed.",
+            &[("", self.steps().to_string())],
+        );
+        metric(
+            "steps_per_second",
+            "gauge",
+            "Average simulation steps completed per second of wall-clock time.",
+            &[("", self.steps_per_second().to_string())],
+        );
+        metric(
+            "time",
+            "gauge",
+            "Present simulation time.",
+            &[("", self.time().to_string())],
+        );
+        metric(
+            "wires_stepped_total",
+            "counter",
+            "Individual wire steps executed.",
+            &[("", self.wires_stepped().to_string())],
+        );
+        metric(
+            "wire_changes_total",
+            "counter",
+            "Wire value changes made.",
+            &[("", self.wire_changes().to_string())],
+        );
+        metric(
+            "timeouts_total",
+            "counter",
+            "Step phases which timed out.",
+            &[("", self.timeouts().to_string())],
+        );
+        metric(
+            "queue_depth",
+            "gauge",
+            "Jobs waiting in the thread pool queue after the most recent dispatch.",
+            &[("", self.queue_depth().to_string())],
+        );
+
+        let labels: Vec<String> = Phase::ALL
+            .iter()
+            .map(|phase| format!("{{phase=\"{phase}\"}}"))
+            .collect();
+        let samples = |f: &dyn Fn(Phase) -> Duration| -> Vec<(&str, String)> {
+            Phase::ALL
+                .iter()
+                .zip(&labels)
+                .map(|(phase, label)| (label.as_str(), f(*phase).as_secs_f64().to_string()))
+                .collect()
+        };
+        metric(
+            "phase_seconds_total",
+            "counter",
+            "Wall-clock time spent in each step phase.",
+            &samples(&|phase| self.phase_total(phase)),
+        );
+        metric(
+            "phase_last_seconds",
+            "gauge",
+            "Wall-clock time spent in each step phase during the most recent step.",
+            &samples(&|phase| self.phase_last(phase)),
+        );
+
+        text
+    }
+}
+
+/// Serve metrics to Prometheus scrapers over HTTP.
+///
+/// A background thread is spawned which answers every HTTP request with the metrics in the Prometheus text
+/// exposition format.  The thread runs for the lifetime of the process.  This is not available on WebAssembly, which
+/// has neither threads nor sockets, nor without the `std` feature.
+///
+/// # Parameters
+///
+/// - `metrics`: Shared handle to the metrics to serve.
+/// - `address`: Address to listen on.  Use port 0 to have the operating system choose a free port.
+///
+/// # Example
+///
+/// ```no_run
+/// # use rvfs_sim_core::metrics;
+/// # use rvfs_sim_core::sim::Simulation;
+/// # use rvfs_sim_core::time::SimDuration;
+/// let sim = Simulation::new(SimDuration::from_ticks(10));
+/// let (address, _) = metrics::serve(sim.metrics(), "127.0.0.1:9184").unwrap();
+/// println!("Serving metrics at http://{address}/metrics");
+/// ```
+#[cfg(all(feature = "std", not(target_arch = "wasm32")))]
+pub fn serve(
+    metrics: Arc<Metrics>,
+    address: impl ToSocketAddrs,
+) -> std::io::Result<(SocketAddr, JoinHandle<()>)> {
+    let listener = TcpListener::bind(address)?;
+    let address = listener.local_addr()?;
+
+    let handle = thread::spawn(move || {
+        for stream in listener.incoming() {
+            let Ok(mut stream) = stream else {
+                continue;
+            };
+
+            // Consume the request headers; every path is answered with the metrics.
+            let mut reader = BufReader::new(&stream);
+            let mut line = String::new();
+            while reader.read_line(&mut line).is_ok_and(|n| n > 0) && line.trim() != "" {
+                line.clear();
+            }
+
+            let body = metrics.to_prometheus();
+            let _ = write!(
+                stream,
+                "HTTP/1.1 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\n\
+                 Connection: close\r\n\r\n{body}",
+                body.len()
+            );
+        }
+    });
+
+    Ok((address, handle))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(all(feature = "std", not(target_arch = "wasm32")))]
+    use std::io::Read;
+    #[cfg(all(feature = "std", not(target_arch = "wasm32")))]
+    use std::net::TcpStream;
+
+    #[test]
+    fn metrics_record() {
+        // GIVEN a new set of metrics
+        let metrics = Metrics::new();
+        // WHEN a step's worth of activity is recorded
+        metrics.begin_step();
+        metrics.record_phase(Phase::Wires, Duration::from_micros(3));
+        metrics.record_phase(Phase::Wires, Duration::from_micros(4));
+        metrics.record_wires(5);
+        metrics.record_queue_depth(2);
+        metrics.record_timeout();
+        metrics.end_step(10, 3);
+        // THEN the metrics reflect the activity
+        assert_eq!(1, metrics.steps());
+        assert_eq!(5, metrics.wires_stepped());
+        assert_eq!(3, metrics.wire_changes());
+        assert_eq!(10, metrics.time());
+        assert_eq!(2, metrics.queue_depth());
+        assert_eq!(1, metrics.timeouts());
+        assert_eq!(Duration::from_micros(7), metrics.phase_total(Phase::Wires));
+        assert_eq!(Duration::from_micros(4), metrics.phase_last(Phase::Wires));
+        assert_eq!(Duration::ZERO, metrics.phase_total(Phase::Elements));
+    }
+    #[test]
+    fn metrics_to_prometheus() {
+        // GIVEN metrics with some recorded activity
+        let metrics = Metrics::new();
+        metrics.record_wires(5);
+        metrics.end_step(10, 3);
+        // WHEN they are rendered for Prometheus
+        let text = metrics.to_prometheus();
+        // THEN each metric is described and has its value
+        assert!(text.contains("# TYPE rvfs_sim_steps_total counter\nrvfs_sim_steps_total 1\n"));
+        assert!(text.contains("rvfs_sim_time 10\n"));
+        assert!(text.contains("rvfs_sim_wires_stepped_total 5\n"));
+        assert!(text.contains("rvfs_sim_phase_seconds_total{phase=\"wires\"} 0\n"));
+    }
+    #[cfg(all(feature = "std", not(target_arch = "wasm32")))]
+    #[test]
+    fn metrics_serve() {
+        // GIVEN metrics being served over HTTP
+        let metrics = Arc::new(Metrics::new());
+        metrics.end_step(10, 3);
+        let (address, _) = serve(metrics, "127.0.0.1:0").unwrap();
+        // WHEN they are scraped
+        let mut stream = TcpStream::connect(address).unwrap();
+        write!(stream, "GET /metrics HTTP/1.1\r\nHost: localhost\r\n\r\n").unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        // THEN the response is successful and contains the metrics
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(response.contains("rvfs_sim_steps_total 1\n"));
+    }
+}
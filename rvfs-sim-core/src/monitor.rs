@@ -0,0 +1,491 @@
+//! Monitors check that a Simulation obeys declared rules as it is stepped.
+//!
+//! Monitors are declared through this API, or in the `monitors` section of a [netlist](crate::netlist).
+
+use crate::sim::Simulation;
+use crate::trace::{Change, Logic, Tracer, DEFAULT_HIGH_THRESHOLD, DEFAULT_LOW_THRESHOLD};
+use crate::WireId;
+use std::collections::VecDeque;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+/// Maximum number of failure times retained for each monitor.
+const FAILURE_TIME_LIMIT: usize = 100;
+
+/// A logical condition on the levels of Wires.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Condition {
+    /// The Wire is logic high.
+    High(WireId),
+    /// The Wire is logic low.
+    Low(WireId),
+    /// The inner condition does not hold.
+    Not(Box<Condition>),
+    /// Every inner condition holds.
+    All(Vec<Condition>),
+    /// At least one inner condition holds.
+    Any(Vec<Condition>),
+}
+
+impl Condition {
+    /// Evaluate the condition against the present state of a Simulation.
+    ///
+    /// A Wire between the logic thresholds is neither high nor low.
+    ///
+    /// # Parameters
+    ///
+    /// - `sim`: The Simulation to evaluate against.
+    pub fn evaluate(&self, sim: &Simulation) -> Result<bool, String> {
+        let logic = |id: &WireId| -> Result<Logic, String> {
+            Ok(Logic::from_level(
+                sim.wire(*id)?.measure(),
+                DEFAULT_LOW_THRESHOLD,
+                DEFAULT_HIGH_THRESHOLD,
+            ))
+        };
+
+        Ok(match self {
+            Condition::High(id) => logic(id)? == Logic::High,
+            Condition::Low(id) => logic(id)? == Logic::Low,
+            Condition::Not(inner) => !inner.evaluate(sim)?,
+            Condition::All(inner) => {
+                for condition in inner {
+                    if !condition.evaluate(sim)? {
+                        return Ok(false);
+                    }
+                }
+                true
+            }
+            Condition::Any(inner) => {
+                for condition in inner {
+                    if condition.evaluate(sim)? {
+                        return Ok(true);
+                    }
+                }
+                false
+            }
+        })
+    }
+}
+
+/// A rule which a Simulation is expected to obey.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Rule {
+    /// The condition must hold after every step.
+    Always(Condition),
+    /// The condition must never hold after any step.
+    Never(Condition),
+    /// Whenever the trigger condition becomes true, the response condition must hold within the given simulation time.
+    /// A check still waiting for its response when tracing finishes fails at the end of the run.
+    Response {
+        /// Condition which starts the check when it becomes true.
+        trigger: Condition,
+        /// Condition which satisfies the check.
+        response: Condition,
+        /// Simulation time allowed between the trigger and the response.
+        within: u64,
+    },
+    /// The fraction of each complete period of the Wire, from one rising edge to the next, for which it is high must
+    /// be within a tolerance of the given duty cycle, as for the output of a
+    /// [PWM generator](crate::element::pwm::PwmGenerator).  A Wire between the logic thresholds is not high.
+    Duty {
+        /// The Wire whose duty cycle is measured.
+        wire: WireId,
+        /// The expected fraction of each period for which the Wire is high.
+        duty: f64,
+        /// The allowed difference between the measured and expected fractions.
+        tolerance: f64,
+    },
+}
+
+/// The outcome of checking a monitor so far.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MonitorStatus {
+    /// Name of the monitor.
+    pub name: String,
+    /// Number of checks which passed.
+    pub passes: u64,
+    /// Number of checks which failed.
+    pub failures: u64,
+    /// Simulation times of the earliest failures.
+    pub failure_times: Vec<u64>,
+    /// The most recent value measured by the monitor, such as the duty cycle of the last complete period, if the rule
+    /// measures one.
+    pub measurement: Option<f64>,
+}
+
+impl MonitorStatus {
+    /// Query whether the monitor has had no failures.
+    pub fn passed(&self) -> bool {
+        self.failures == 0
+    }
+}
+
+impl fmt::Display for MonitorStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: {} ({} passed, {} failed)",
+            self.name,
+            if self.passed() { "PASS" } else { "FAIL" },
+            self.passes,
+            self.failures
+        )?;
+        if let Some(first) = self.failure_times.first() {
+            write!(f, ", first failure at {first}")?;
+        }
+        Ok(())
+    }
+}
+
+/// A named rule along with its checking state.
+#[derive(Debug, Clone)]
+struct Monitor {
+    /// The rule being checked.
+    rule: Rule,
+    /// Outcome of the checks so far.
+    status: MonitorStatus,
+    /// Whether the trigger condition held, or the measured Wire was high, after the previous step.
+    triggered: bool,
+    /// Deadlines of response checks which are waiting to be satisfied, earliest first.
+    pending: VecDeque<u64>,
+    /// Simulation time of the previous evaluation.
+    time: u64,
+    /// Time of the most recent rising edge of a measured Wire, and time it has since spent high.
+    period: Option<(u64, u64)>,
+}
+
+impl Monitor {
+    /// Record the outcome of a single check.
+    fn check(&mut self, passed: bool, time: u64) {
+        if passed {
+            self.status.passes += 1;
+        } else {
+            self.status.failures += 1;
+            if self.status.failure_times.len() < FAILURE_TIME_LIMIT {
+                self.status.failure_times.push(time);
+            }
+        }
+    }
+
+    /// Evaluate the rule against the present state of a Simulation.
+    fn evaluate(&mut self, sim: &Simulation) -> Result<(), String> {
+        let time = sim.time();
+        match &self.rule {
+            Rule::Always(condition) => {
+                let holds = condition.evaluate(sim)?;
+                self.check(holds, time);
+            }
+            Rule::Never(condition) => {
+                let holds = condition.evaluate(sim)?;
+                self.check(!holds, time);
+            }
+            Rule::Response {
+                trigger,
+                response,
+                within,
+            } => {
+                let within = *within;
+                let triggered = trigger.evaluate(sim)?;
+                let responded = response.evaluate(sim)?;
+
+                // Any outstanding check which has passed its deadline has failed.
+                while let Some(deadline) = self.pending.front().copied() {
+                    if deadline >= time {
+                        break;
+                    }
+                    self.pending.pop_front();
+                    self.check(false, deadline);
+                }
+                if triggered && !self.triggered {
+                    self.pending.push_back(time.saturating_add(within));
+                }
+                if responded {
+                    for _ in self.pending.drain(..) {
+                        self.status.passes += 1;
+                    }
+                }
+                self.triggered = triggered;
+            }
+            Rule::Duty {
+                wire,
+                duty,
+                tolerance,
+            } => {
+                let (duty, tolerance) = (*duty, *tolerance);
+                let high = Condition::High(*wire).evaluate(sim)?;
+
+                // The time since the previous step is attributed to the level the Wire held through it.
+                if let Some((_, high_time)) = &mut self.period {
+                    if self.triggered {
+                        *high_time += time - self.time;
+                    }
+                }
+                if high && !self.triggered {
+                    if let Some((start, high_time)) = self.period {
+                        let measured = high_time as f64 / (time - start) as f64;
+                        self.status.measurement = Some(measured);
+                        self.check((measured - duty).abs() <= tolerance, time);
+                    }
+                    self.period = Some((time, 0));
+                }
+                self.triggered = high;
+            }
+        }
+        self.time = time;
+
+        Ok(())
+    }
+}
+
+/// A collection of monitors which are checked after every step of a Simulation.
+///
+/// Clones of a Monitors instance share their state, so a clone can be kept to examine the results after the original
+/// has been attached to a Simulation.
+///
+/// # Example
+///
+/// ```
+/// # use rvfs_sim_core::monitor::{Condition, Monitors, Rule};
+/// # use rvfs_sim_core::sim::Simulation;
+/// # use rvfs_sim_core::time::SimDuration;
+/// # use rvfs_sim_core::wire::{Wire, WirePull};
+/// let mut sim = Simulation::new(SimDuration::from_ticks(10));
+/// let cs1 = sim.add_wire(Wire::new("/CS1", WirePull::Up)).unwrap();
+/// let cs2 = sim.add_wire(Wire::new("/CS2", WirePull::Up)).unwrap();
+/// let monitors = Monitors::new();
+/// monitors.add(
+///     "exclusive chip selects",
+///     Rule::Never(Condition::All(vec![Condition::Low(cs1), Condition::Low(cs2)])),
+/// );
+/// sim.add_tracer(Box::new(monitors.clone()));
+///
+/// sim.step().unwrap();
+/// assert!(monitors.passed());
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Monitors {
+    /// The monitors being checked.
+    monitors: Arc<Mutex<Vec<Monitor>>>,
+}
+
+impl Monitors {
+    /// Create a new, empty collection of monitors.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a monitor to the collection.
+    ///
+    /// # Parameters
+    ///
+    /// - `name`: A human-readable name for the monitor, used when reporting.
+    /// - `rule`: The rule to check.
+    pub fn add(&self, name: &str, rule: Rule) {
+        self.lock().push(Monitor {
+            rule,
+            status: MonitorStatus {
+                name: name.to_string(),
+                passes: 0,
+                failures: 0,
+                failure_times: Vec::new(),
+                measurement: None,
+            },
+            triggered: false,
+            pending: VecDeque::new(),
+            time: 0,
+            period: None,
+        });
+    }
+
+    /// Obtain the outcome of every monitor so far, in the order they were added.
+    pub fn statuses(&self) -> Vec<MonitorStatus> {
+        self.lock().iter().map(|m| m.status.clone()).collect()
+    }
+
+    /// Query whether every monitor has had no failures.
+    pub fn passed(&self) -> bool {
+        self.lock().iter().all(|m| m.status.passed())
+    }
+
+    /// Lock the shared monitors.
+    fn lock(&self) -> std::sync::MutexGuard<'_, Vec<Monitor>> {
+        self.monitors
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl Tracer for Monitors {
+    fn start(&mut self, sim: &Simulation) -> Result<(), String> {
+        for monitor in self.lock().iter_mut() {
+            monitor.triggered = match &monitor.rule {
+                Rule::Response { trigger, .. } => trigger.evaluate(sim)?,
+                Rule::Duty { wire, .. } => Condition::High(*wire).evaluate(sim)?,
+                _ => false,
+            };
+            monitor.time = sim.time();
+        }
+        Ok(())
+    }
+
+    fn record(&mut self, sim: &Simulation, _changes: &[Change]) -> Result<(), String> {
+        for monitor in self.lock().iter_mut() {
+            monitor.evaluate(sim)?;
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self, sim: &Simulation) -> Result<(), String> {
+        for monitor in self.lock().iter_mut() {
+            // A check which is still waiting was never answered, even if its deadline is beyond the end of the run.
+            for _ in 0..monitor.pending.len() {
+                monitor.check(false, sim.time());
+            }
+            monitor.pending.clear();
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::time::SimDuration;
+    use crate::trace::testing::{falling_wire_sim, trace};
+    use crate::wire::{Wire, WirePull};
+
+    /// Build a Simulation with a wire falling from high to low over a few steps, and a wire which stays high.
+    fn setup() -> (Simulation, WireId, WireId) {
+        let (mut sim, falling) = falling_wire_sim(10.0);
+        let high = sim.add_wire(Wire::new("ACK", WirePull::Up)).unwrap();
+        (sim, falling, high)
+    }
+
+    /// Step a Simulation, checking a set of monitors after each step.
+    fn run(sim: &mut Simulation, monitors: &Monitors, steps: usize) {
+        trace(sim, monitors.clone(), steps);
+    }
+
+    #[test]
+    fn condition_evaluate() {
+        // GIVEN a Simulation with a high wire and a low wire
+        let mut sim = Simulation::new(SimDuration::from_ticks(10));
+        let high = sim.add_wire(Wire::new("H", WirePull::Up)).unwrap();
+        let low = sim.add_wire(Wire::new("L", WirePull::Down)).unwrap();
+        // WHEN conditions on them are evaluated
+        // THEN the results follow the logic levels
+        assert_eq!(Ok(true), Condition::High(high).evaluate(&sim));
+        assert_eq!(Ok(false), Condition::Low(high).evaluate(&sim));
+        assert_eq!(
+            Ok(true),
+            Condition::Not(Box::new(Condition::High(low))).evaluate(&sim)
+        );
+        assert_eq!(
+            Ok(false),
+            Condition::All(vec![Condition::High(high), Condition::High(low)]).evaluate(&sim)
+        );
+        assert_eq!(
+            Ok(true),
+            Condition::Any(vec![Condition::High(high), Condition::High(low)]).evaluate(&sim)
+        );
+        assert!(Condition::High(WireId::from(7)).evaluate(&sim).is_err());
+    }
+    #[test]
+    fn monitor_invariant() {
+        // GIVEN monitors that a falling wire is always high, and that it is never low
+        let (mut sim, falling, _) = setup();
+        let monitors = Monitors::new();
+        monitors.add("always high", Rule::Always(Condition::High(falling)));
+        monitors.add("never low", Rule::Never(Condition::Low(falling)));
+        // WHEN the simulation is run
+        run(&mut sim, &monitors, 4);
+        // THEN the failures are counted and timestamped
+        let statuses = monitors.statuses();
+        assert_eq!(0, statuses[0].passes);
+        assert_eq!(vec![10, 20, 30, 40], statuses[0].failure_times);
+        assert_eq!(1, statuses[1].passes);
+        assert_eq!(vec![20, 30, 40], statuses[1].failure_times);
+        assert!(!monitors.passed());
+        assert_eq!(
+            "never low: FAIL (1 passed, 3 failed), first failure at 20",
+            statuses[1].to_string()
+        );
+    }
+    #[test]
+    fn monitor_response_satisfied() {
+        // GIVEN a monitor that the acknowledge is high within 10 time units of the request falling
+        let (mut sim, falling, high) = setup();
+        let monitors = Monitors::new();
+        monitors.add(
+            "ack",
+            Rule::Response {
+                trigger: Condition::Low(falling),
+                response: Condition::High(high),
+                within: 10,
+            },
+        );
+        // WHEN the simulation is run
+        run(&mut sim, &monitors, 4);
+        // THEN the check passes
+        assert_eq!(1, monitors.statuses()[0].passes);
+        assert!(monitors.passed());
+    }
+    #[test]
+    fn monitor_response_late() {
+        // GIVEN a monitor that the still-high request is low within 10 time units of the acknowledge being high
+        let (mut sim, falling, high) = setup();
+        let monitors = Monitors::new();
+        monitors.add(
+            "late",
+            Rule::Response {
+                trigger: Condition::High(high),
+                response: Condition::Low(falling),
+                within: 10,
+            },
+        );
+        // WHEN the acknowledge goes high immediately but the request takes two steps to fall
+        // (the trigger is already true at the start, so no check is started)
+        run(&mut sim, &monitors, 4);
+        // THEN no check is made for a trigger which was already true when monitoring began
+        assert_eq!(0, monitors.statuses()[0].passes);
+        assert_eq!(0, monitors.statuses()[0].failures);
+
+        // GIVEN a monitor that the acknowledge goes low within 10 time units of the request going unknown
+        let (mut sim, falling, high) = setup();
+        let monitors = Monitors::new();
+        monitors.add(
+            "never answered",
+            Rule::Response {
+                trigger: Condition::Not(Box::new(Condition::High(falling))),
+                response: Condition::Low(high),
+                within: 10,
+            },
+        );
+        // WHEN the simulation is run
+        run(&mut sim, &monitors, 4);
+        // THEN the check fails at its deadline
+        assert_eq!(vec![20], monitors.statuses()[0].failure_times);
+    }
+    #[test]
+    fn monitor_response_unresolved() {
+        // GIVEN a monitor that the acknowledge goes low within 100 time units of the request falling
+        let (mut sim, falling, high) = setup();
+        let monitors = Monitors::new();
+        monitors.add(
+            "unresolved",
+            Rule::Response {
+                trigger: Condition::Low(falling),
+                response: Condition::Low(high),
+                within: 100,
+            },
+        );
+        // WHEN the run ends before the deadline, without the acknowledge going low
+        run(&mut sim, &monitors, 4);
+        // THEN the pending check fails at the end of the run
+        let status = &monitors.statuses()[0];
+        assert_eq!(0, status.passes);
+        assert_eq!(vec![40], status.failure_times);
+        assert!(!monitors.passed());
+    }
+}
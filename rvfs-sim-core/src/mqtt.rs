@@ -0,0 +1,362 @@
+//! An MQTT client which publishes the levels of selected Wires and accepts commands to force Wires, so that a
+//! simulated device can be plugged into existing IoT dashboards as a digital twin.
+//!
+//! The client connects over plain TCP to a broker, and uses topics beneath a prefix chosen for the twin:
+//!
+//! - `<prefix>/time` carries the simulation time after each step;
+//! - `<prefix>/signals/<wire>` carries the logic level of each selected Wire as `0`, `1` or `x`, published when it
+//!   changes;
+//! - `<prefix>/force/<wire>` accepts a pull to force on any Wire: `up`, `down`, or `none` to release it.
+//!
+//! Levels are published retained, so a dashboard which subscribes later receives the present level at once.  A twin is
+//! [synchronised](MqttTwin::sync) after each step, typically from a
+//! [read-write synchronisation callback](crate::callback::Reason::ReadWriteSync):
+//!
+//! ```no_run
+//! # use rvfs_sim_core::callback::{Callbacks, Reason};
+//! # use rvfs_sim_core::mqtt::MqttTwin;
+//! # use rvfs_sim_core::sim::Simulation;
+//! # use rvfs_sim_core::time::SimDuration;
+//! # use rvfs_sim_core::wire::{Wire, WirePull};
+//! let mut sim = Simulation::new(SimDuration::from_ticks(10));
+//! let led = sim.add_wire(Wire::new("LED", WirePull::Down)).unwrap();
+//!
+//! let mut twin = MqttTwin::connect(&sim, "localhost:1883", "rvfs-sim", "plant/controller", &[led]).unwrap();
+//! let mut callbacks = Callbacks::new(sim);
+//! callbacks
+//!     .register(Reason::ReadWriteSync, move |sim, _| twin.sync(sim))
+//!     .unwrap();
+//! loop {
+//!     callbacks.step().unwrap();
+//! }
+//! ```
+
+use crate::sim::Simulation;
+use crate::trace::{Logic, DEFAULT_HIGH_THRESHOLD, DEFAULT_LOW_THRESHOLD};
+use crate::wire::WirePull;
+use crate::WireId;
+use rumqttc::{Client, Event, MqttOptions, Outgoing, Packet, QoS};
+use std::collections::HashMap;
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::thread::{self, JoinHandle};
+
+/// Number of requests which may be queued for the connection before publishing blocks.
+const REQUEST_CAPACITY: usize = 1024;
+
+/// A digital twin of a Simulation on an MQTT broker.
+pub struct MqttTwin {
+    /// The client, through which messages are published.
+    client: Client,
+    /// Force commands and connection failures received by the connection thread, as pairs of topic and payload.
+    commands: Receiver<Result<(String, Vec<u8>), String>>,
+    /// The thread driving the connection.
+    thread: Option<JoinHandle<()>>,
+    /// Prefix of every topic.
+    prefix: String,
+    /// Ids of the Wires whose levels are published, with their names.
+    signals: Vec<(WireId, String)>,
+    /// Ids of every Wire, by name.
+    wires: HashMap<String, WireId>,
+    /// Whether the initial levels have been published.
+    started: bool,
+}
+
+impl MqttTwin {
+    /// Create a new MqttTwin by connecting to a broker, and subscribe to force commands.
+    ///
+    /// # Parameters
+    ///
+    /// - `sim`: The Simulation to twin, with all its Wires already added.
+    /// - `broker`: Address of the broker as `host:port`.
+    /// - `client_id`: Identifier of the client, unique among the clients of the broker.
+    /// - `prefix`: Prefix of every topic, without a trailing `/`.
+    /// - `signals`: Ids of the Wires whose levels are published.
+    pub fn connect(
+        sim: &Simulation,
+        broker: &str,
+        client_id: &str,
+        prefix: &str,
+        signals: &[WireId],
+    ) -> Result<Self, String> {
+        let (host, port) = broker
+            .rsplit_once(':')
+            .and_then(|(host, port)| Some((host, port.parse::<u16>().ok()?)))
+            .ok_or_else(|| format!("Invalid broker address \"{broker}\""))?;
+        let signals = signals
+            .iter()
+            .map(|id| Ok((*id, sim.wire(*id)?.name().to_string())))
+            .collect::<Result<Vec<_>, String>>()?;
+        let wires = sim
+            .wires()
+            .map(|id| Ok((sim.wire(id)?.name().to_string(), id)))
+            .collect::<Result<_, String>>()?;
+
+        let (client, mut connection) =
+            Client::new(MqttOptions::new(client_id, host, port), REQUEST_CAPACITY);
+        client
+            .subscribe(format!("{prefix}/force/#"), QoS::AtLeastOnce)
+            .map_err(|error| format!("Cannot subscribe to force commands: {error}"))?;
+        let (sender, commands) = mpsc::channel();
+        let thread = thread::spawn(move || {
+            for event in connection.iter() {
+                match event {
+                    Ok(Event::Incoming(Packet::Publish(publish))) => {
+                        let command = (publish.topic, publish.payload.to_vec());
+                        if sender.send(Ok(command)).is_err() {
+                            break;
+                        }
+                    }
+                    Ok(Event::Outgoing(Outgoing::Disconnect)) => break,
+                    Ok(_) => (),
+                    Err(error) => {
+                        let _ = sender.send(Err(format!("MQTT connection failed: {error}")));
+                        break;
+                    }
+                }
+            }
+        });
+
+        Ok(Self {
+            client,
+            commands,
+            thread: Some(thread),
+            prefix: prefix.to_string(),
+            signals,
+            wires,
+            started: false,
+        })
+    }
+
+    /// Publish the levels of the selected Wires changed by the most recent step, and apply the force commands received
+    /// since the last synchronisation, which take effect in the next step.
+    ///
+    /// The first synchronisation publishes the levels of every selected Wire.
+    ///
+    /// # Parameters
+    ///
+    /// - `sim`: The Simulation being twinned.
+    pub fn sync(&mut self, sim: &mut Simulation) -> Result<(), String> {
+        let changed: Vec<WireId> = sim.changes().iter().map(|change| change.id).collect();
+        for (id, name) in &self.signals {
+            if self.started && !changed.contains(id) {
+                continue;
+            }
+            let logic = Logic::from_level(
+                sim.wire(*id)?.measure(),
+                DEFAULT_LOW_THRESHOLD,
+                DEFAULT_HIGH_THRESHOLD,
+            );
+            self.publish(&format!("signals/{name}"), logic.symbol().to_string())?;
+        }
+        self.publish("time", sim.time().to_string())?;
+        self.started = true;
+
+        loop {
+            let (topic, payload) = match self.commands.try_recv() {
+                Ok(command) => command?,
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    return Err("MQTT connection was closed".to_string())
+                }
+            };
+            let name = topic
+                .strip_prefix(&format!("{}/force/", self.prefix))
+                .ok_or_else(|| format!("Unexpected topic \"{topic}\""))?;
+            let id = *self
+                .wires
+                .get(name)
+                .ok_or_else(|| format!("No wire named \"{name}\" to force"))?;
+            let pull = match String::from_utf8_lossy(&payload).trim() {
+                "up" => Some(WirePull::Up),
+                "down" => Some(WirePull::Down),
+                "none" => None,
+                pull => return Err(format!("Invalid pull \"{pull}\" to force on \"{name}\"")),
+            };
+            sim.force_wire(id, pull)?;
+        }
+
+        Ok(())
+    }
+
+    /// Publish a retained message beneath the prefix.
+    ///
+    /// # Parameters
+    ///
+    /// - `topic`: The topic, relative to the prefix.
+    /// - `payload`: The message.
+    fn publish(&self, topic: &str, payload: String) -> Result<(), String> {
+        self.client
+            .publish(
+                format!("{}/{topic}", self.prefix),
+                QoS::AtMostOnce,
+                true,
+                payload,
+            )
+            .map_err(|error| format!("Cannot publish to MQTT broker: {error}"))
+    }
+}
+
+impl Drop for MqttTwin {
+    fn drop(&mut self) {
+        // The connection thread ends once the disconnection is sent, or has already ended if the connection failed.
+        let _ = self.client.disconnect();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::time::SimDuration;
+    use crate::wire::Wire;
+    use std::io::{Read, Write};
+    use std::net::{TcpListener, TcpStream};
+    use std::time::Duration;
+
+    /// Read an MQTT packet, returning its type and body.
+    fn read_packet(stream: &mut TcpStream) -> Option<(u8, Vec<u8>)> {
+        let mut byte = [0u8];
+        stream.read_exact(&mut byte).ok()?;
+        let kind = byte[0] >> 4;
+        let mut length = 0usize;
+        for shift in (0..4).map(|i| 7 * i) {
+            stream.read_exact(&mut byte).ok()?;
+            length |= usize::from(byte[0] & 0x7f) << shift;
+            if byte[0] & 0x80 == 0 {
+                break;
+            }
+        }
+        let mut body = vec![0u8; length];
+        stream.read_exact(&mut body).ok()?;
+        Some((kind, body))
+    }
+
+    /// Encode a short string with its length.
+    fn string(text: &str) -> Vec<u8> {
+        let mut encoded = (text.len() as u16).to_be_bytes().to_vec();
+        encoded.extend_from_slice(text.as_bytes());
+        encoded
+    }
+
+    /// Serve a broker for a single client on a local TCP port, returning its address and a handle yielding the
+    /// retained messages published by the client, in order.
+    ///
+    /// Once the client subscribes, the broker sends it each of the given commands as pairs of topic and payload.
+    fn serve(commands: Vec<(&'static str, &'static str)>) -> (String, JoinHandle<Vec<String>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        let handle = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut published = Vec::new();
+            while let Some((kind, body)) = read_packet(&mut stream) {
+                match kind {
+                    // CONNECT
+                    1 => stream.write_all(&[0x20, 2, 0, 0]).unwrap(),
+                    // PUBLISH, of which only the topic and payload of a QoS 0 message are kept
+                    3 => {
+                        let length = usize::from(u16::from_be_bytes([body[0], body[1]]));
+                        let topic = String::from_utf8_lossy(&body[2..2 + length]);
+                        let payload = String::from_utf8_lossy(&body[2 + length..]);
+                        published.push(format!("{topic} {payload}"));
+                    }
+                    // SUBSCRIBE, acknowledged and followed by the commands
+                    8 => {
+                        stream.write_all(&[0x90, 3, body[0], body[1], 1]).unwrap();
+                        for (topic, payload) in &commands {
+                            let mut message = string(topic);
+                            message.extend_from_slice(payload.as_bytes());
+                            stream.write_all(&[0x30, message.len() as u8]).unwrap();
+                            stream.write_all(&message).unwrap();
+                        }
+                    }
+                    // PINGREQ
+                    12 => stream.write_all(&[0xd0, 0]).unwrap(),
+                    // DISCONNECT
+                    14 => break,
+                    _ => (),
+                }
+            }
+            published
+        });
+        (address, handle)
+    }
+
+    /// Create a Simulation with Wires `A` and `B`, both pulled up, returning it and their Ids.
+    fn pulled_up() -> (Simulation, WireId, WireId) {
+        let mut sim = Simulation::new(SimDuration::from_ticks(10));
+        let a = sim.add_wire(Wire::new("A", WirePull::Up)).unwrap();
+        let b = sim.add_wire(Wire::new("B", WirePull::Up)).unwrap();
+        (sim, a, b)
+    }
+
+    #[test]
+    fn mqtt_twin_publish_and_force() {
+        // GIVEN a twin publishing Wire A, with the broker forcing A low and B low then released
+        let (address, broker) = serve(vec![
+            ("twin/force/A", "down"),
+            ("twin/force/B", "down"),
+            ("twin/force/B", "none"),
+        ]);
+        let (mut sim, a, b) = pulled_up();
+        let mut twin = MqttTwin::connect(&sim, &address, "test", "twin", &[a]).unwrap();
+        // WHEN the Simulation is stepped and synchronised until the force on A shows, and then once more
+        twin.sync(&mut sim).unwrap();
+        for _ in 0..100 {
+            sim.step().unwrap();
+            twin.sync(&mut sim).unwrap();
+            if f32::from(sim.wire(a).unwrap().measure()) == 0.0 {
+                break;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        let time = sim.time();
+        sim.step().unwrap();
+        twin.sync(&mut sim).unwrap();
+        drop(twin);
+        // THEN the initial level, the change and the times are published, and the force on B was released
+        let mut expected = vec!["twin/signals/A 1".to_string(), "twin/time 0".to_string()];
+        expected.extend((10..time).step_by(10).map(|t| format!("twin/time {t}")));
+        expected.push("twin/signals/A 0".to_string());
+        expected.push(format!("twin/time {time}"));
+        expected.push(format!("twin/time {}", time + 10));
+        assert_eq!(expected, broker.join().unwrap());
+        assert_eq!(1.0, f32::from(sim.wire(b).unwrap().measure()));
+    }
+
+    #[test]
+    fn mqtt_twin_errors() {
+        // GIVEN twins receiving bad commands, and bad connection parameters
+        let (unknown, _unknown) = serve(vec![("twin/force/C", "down")]);
+        let (invalid, _invalid) = serve(vec![("twin/force/A", "sideways")]);
+        let (mut sim, a, _) = pulled_up();
+        // WHEN they are connected and synchronised
+        let mut errors: Vec<String> = [unknown, invalid]
+            .iter()
+            .map(|address| {
+                let mut twin = MqttTwin::connect(&sim, address, "test", "twin", &[]).unwrap();
+                (0..100)
+                    .find_map(|_| {
+                        thread::sleep(Duration::from_millis(10));
+                        twin.sync(&mut sim).err()
+                    })
+                    .unwrap()
+            })
+            .collect();
+        errors.extend(MqttTwin::connect(&sim, "localhost", "test", "twin", &[a]).err());
+        errors.extend(
+            MqttTwin::connect(&sim, "localhost:1883", "test", "twin", &[WireId::from(7)]).err(),
+        );
+        // THEN each is reported
+        assert_eq!(
+            vec![
+                "No wire named \"C\" to force",
+                "Invalid pull \"sideways\" to force on \"A\"",
+                "Invalid broker address \"localhost\"",
+                "No wire found for the given ID",
+            ],
+            errors
+        );
+    }
+}
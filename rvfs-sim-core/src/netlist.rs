@@ -0,0 +1,573 @@
+//! Loading of circuits from netlist files, so that a Simulation can be described as data rather than built in Rust.
+//!
+//! A netlist is a JSON object giving the simulation interval in ticks, the Wires, and the Elements with the Wires their
+//! pins are connected to.  Elements are instantiated by kind from a [Registry], with parameters given as strings,
+//! numbers or booleans.  The relaxed syntax accepted for WaveDrom descriptions is accepted here too, so comments and
+//! trailing commas are allowed.
+//!
+//! ```json
+//! {
+//!     "interval": 10,
+//!     "wires": [
+//!         { "name": "CLK", "pull": "down", "tau": 2.0 },
+//!         { "name": "/CLK", "pull": "none" }
+//!     ],
+//!     "elements": [
+//!         { "name": "X1", "kind": "clock", "parameters": { "period": 40 }, "pins": { "CLK": "CLK" } },
+//!         { "name": "U1", "kind": "not", "pins": { "I0": "CLK", "Y": "/CLK" } }
+//!     ]
+//! }
+//! ```
+//!
+//! A Wire's `pull` is `up`, `down` or `none`, and its `tau` is optional.  An Element's `parameters` are optional.
+//!
+//! A netlist may also define [Subcircuits](Subcircuit), keyed by name, each with its ports and its own Wires, Elements
+//! and instances of Subcircuits defined before it.  Instances, in a Subcircuit or in the netlist itself, map each port
+//! to a Wire or port of the enclosing circuit, and name their contents beneath the instance:
+//!
+//! ```json
+//! {
+//!     "interval": 10,
+//!     "subcircuits": {
+//!         "buffer": {
+//!             "ports": ["A", "Y"],
+//!             "wires": [{ "name": "MID", "pull": "none" }],
+//!             "elements": [
+//!                 { "name": "U1", "kind": "not", "pins": { "I0": "A", "Y": "MID" } },
+//!                 { "name": "U2", "kind": "not", "pins": { "I0": "MID", "Y": "Y" } }
+//!             ]
+//!         }
+//!     },
+//!     "wires": [{ "name": "IN", "pull": "up" }, { "name": "OUT", "pull": "none" }],
+//!     "instances": [{ "name": "B1", "subcircuit": "buffer", "ports": { "A": "IN", "Y": "OUT" } }]
+//! }
+//! ```
+//!
+//! A netlist may also declare [monitors](crate::monitor), each named and giving one [Rule]: an `always` or `never`
+//! condition, a `trigger` condition with the `response` condition required `within` a number of ticks, or a `wire`
+//! whose `duty` cycle must be within a `tolerance`.  A condition is `high` or `low` naming a Wire, `not` another
+//! condition, or `all` or `any` of an array of conditions:
+//!
+//! ```json
+//! {
+//!     "interval": 10,
+//!     "wires": [{ "name": "/CS1", "pull": "up" }, { "name": "/CS2", "pull": "up" }, { "name": "/RD", "pull": "up" }],
+//!     "monitors": [
+//!         { "name": "exclusive selects", "never": { "all": [{ "low": "/CS1" }, { "low": "/CS2" }] } },
+//!         { "name": "read selects", "trigger": { "low": "/RD" }, "response": { "low": "/CS1" }, "within": 20 }
+//!     ]
+//! }
+//! ```
+//!
+//! The monitors are attached to the Simulation, and can be examined through [Circuit::monitors].
+
+use crate::circuit::Circuit;
+use crate::element::{Parameters, Registry};
+use crate::json::{self, Value};
+use crate::monitor::{Condition, Rule};
+use crate::subcircuit::Subcircuit;
+use crate::time::SimDuration;
+use crate::wire::{Wire, WirePull};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+/// Pairs of the name of a pin or port and the name of the Wire or port it is connected to.
+type Connections<'a> = Vec<(&'a str, &'a str)>;
+
+/// Build a Circuit from a netlist, instantiating its Elements from a Registry.
+///
+/// # Parameters
+///
+/// - `text`: The netlist.
+/// - `registry`: The Registry of Element kinds.
+///
+/// # Example
+///
+/// ```
+/// # use rvfs_sim_core::element::Registry;
+/// # use rvfs_sim_core::netlist;
+/// let netlist = r#"{
+///     "interval": 10,
+///     "wires": [{ "name": "A", "pull": "up" }, { "name": "Y", "pull": "none" }],
+///     "elements": [{ "name": "U1", "kind": "not", "pins": { "I0": "A", "Y": "Y" } }],
+/// }"#;
+/// let mut circuit = netlist::parse(netlist, &Registry::standard()).unwrap();
+/// circuit.simulation_mut().step().unwrap();
+///
+/// let y = circuit.wire("Y").unwrap();
+/// assert_eq!(0.0, f32::from(circuit.simulation().wire(y).unwrap().measure()));
+/// ```
+pub fn parse(text: &str, registry: &Registry) -> Result<Circuit, String> {
+    let netlist = json::parse(text).map_err(|message| format!("Invalid netlist: {message}"))?;
+    let interval = netlist
+        .get("interval")
+        .and_then(Value::as_f64)
+        .filter(|interval| *interval >= 1.0 && interval.fract() == 0.0)
+        .ok_or("Netlist must give a positive whole \"interval\"".to_string())?;
+    let mut circuit = Circuit::new(SimDuration::from_ticks(interval as u64));
+
+    let mut subcircuits: BTreeMap<&str, Subcircuit> = BTreeMap::new();
+    for (name, definition) in members(&netlist, "subcircuits", "Netlist")? {
+        let mut subcircuit = Subcircuit::new(name);
+        for port in items(definition, "ports")? {
+            let port = port
+                .as_str()
+                .ok_or(format!("Subcircuit \"{name}\": ports must be names"))?;
+            subcircuit = subcircuit.with_port(port);
+        }
+        for wire in items(definition, "wires")? {
+            subcircuit = subcircuit.with_wire(parse_wire(wire)?);
+        }
+        for element in items(definition, "elements")? {
+            let (name, kind, parameters, pins) = parse_element(element)?;
+            subcircuit = subcircuit.with_element(name, kind, parameters, &pins);
+        }
+        for instance in items(definition, "instances")? {
+            let (name, instantiated, ports) = parse_instance(instance, &subcircuits)?;
+            subcircuit = subcircuit.with_instance(name, instantiated, &ports);
+        }
+        subcircuits.insert(name, subcircuit);
+    }
+
+    for wire in items(&netlist, "wires")? {
+        circuit.add_wire(parse_wire(wire)?)?;
+    }
+
+    for element in items(&netlist, "elements")? {
+        let (name, kind, parameters, pins) = parse_element(element)?;
+        circuit.add_element(name, registry.create(kind, name, &parameters)?, &pins)?;
+    }
+
+    for instance in items(&netlist, "instances")? {
+        let (name, subcircuit, ports) = parse_instance(instance, &subcircuits)?;
+        circuit.instantiate(registry, subcircuit, name, &ports)?;
+    }
+
+    for monitor in items(&netlist, "monitors")? {
+        let (name, rule) = parse_monitor(monitor, &circuit)?;
+        circuit.add_monitor(name, rule);
+    }
+
+    Ok(circuit)
+}
+
+/// Build a Wire from its description.
+///
+/// # Parameters
+///
+/// - `wire`: The Wire's description.
+fn parse_wire(wire: &Value) -> Result<Wire, String> {
+    let name = string(wire, "name", "wire")?;
+    let pull = match string(wire, "pull", name)? {
+        "up" => WirePull::Up,
+        "down" => WirePull::Down,
+        "none" => WirePull::None,
+        pull => return Err(format!("Invalid pull \"{pull}\" for wire \"{name}\"")),
+    };
+    let mut built = Wire::new(name, pull);
+    match wire.get("tau") {
+        None => (),
+        Some(Value::Number(tau)) => built.set_time_constant(*tau as f32),
+        Some(_) => return Err(format!("Wire \"{name}\": \"tau\" must be a number")),
+    }
+    Ok(built)
+}
+
+/// Obtain the name, kind, parameters and pin connections of an Element from its description.
+///
+/// # Parameters
+///
+/// - `element`: The Element's description.
+fn parse_element(element: &Value) -> Result<(&str, &str, Parameters, Connections<'_>), String> {
+    let name = string(element, "name", "element")?;
+    let kind = string(element, "kind", name)?;
+    let mut parameters = Parameters::new();
+    for (key, value) in members(element, "parameters", &format!("Element \"{name}\""))? {
+        let value = match value {
+            Value::String(text) => text.clone(),
+            Value::Number(number) => number.to_string(),
+            Value::Bool(flag) => flag.to_string(),
+            _ => return Err(format!("Element \"{name}\": invalid parameter \"{key}\"")),
+        };
+        parameters = parameters.with(key, &value);
+    }
+    let mut connections = Vec::new();
+    for (pin, wire) in members(element, "pins", &format!("Element \"{name}\""))? {
+        let wire = wire.as_str().ok_or(format!(
+            "Element \"{name}\": pin \"{pin}\" must name a wire"
+        ))?;
+        connections.push((pin.as_str(), wire));
+    }
+    Ok((name, kind, parameters, connections))
+}
+
+/// Obtain the name, Subcircuit and port mapping of an instance from its description.
+///
+/// # Parameters
+///
+/// - `instance`: The instance's description.
+/// - `subcircuits`: The Subcircuits defined so far, keyed by name.
+fn parse_instance<'a>(
+    instance: &'a Value,
+    subcircuits: &'a BTreeMap<&str, Subcircuit>,
+) -> Result<(&'a str, &'a Subcircuit, Connections<'a>), String> {
+    let name = string(instance, "name", "instance")?;
+    let kind = string(instance, "subcircuit", name)?;
+    let subcircuit = subcircuits.get(kind).ok_or(format!(
+        "Instance \"{name}\": no subcircuit named \"{kind}\""
+    ))?;
+    let mut ports = Vec::new();
+    for (port, wire) in members(instance, "ports", &format!("Instance \"{name}\""))? {
+        let wire = wire.as_str().ok_or(format!(
+            "Instance \"{name}\": port \"{port}\" must name a wire"
+        ))?;
+        ports.push((port.as_str(), wire));
+    }
+    Ok((name, subcircuit, ports))
+}
+
+/// Obtain the name and Rule of a monitor from its description, with the Wires it names looked up in a Circuit.
+///
+/// # Parameters
+///
+/// - `monitor`: The monitor's description.
+/// - `circuit`: The Circuit holding the Wires.
+fn parse_monitor<'a>(monitor: &'a Value, circuit: &Circuit) -> Result<(&'a str, Rule), String> {
+    let name = string(monitor, "name", "monitor")?;
+    let condition = |key: &str| {
+        monitor
+            .get(key)
+            .map(|condition| parse_condition(condition, circuit, name))
+            .transpose()
+    };
+    let number = |key: &str| {
+        monitor
+            .get(key)
+            .and_then(Value::as_f64)
+            .ok_or(format!("Monitor \"{name}\": \"{key}\" must be a number"))
+    };
+
+    let rule = if let Some(condition) = condition("always")? {
+        Rule::Always(condition)
+    } else if let Some(condition) = condition("never")? {
+        Rule::Never(condition)
+    } else if let Some(trigger) = condition("trigger")? {
+        let response = condition("response")?.ok_or(format!(
+            "Monitor \"{name}\": a \"trigger\" needs a \"response\""
+        ))?;
+        let within = number("within")?;
+        if within < 0.0 || within.fract() != 0.0 {
+            return Err(format!(
+                "Monitor \"{name}\": \"within\" must be a whole number of ticks"
+            ));
+        }
+        Rule::Response {
+            trigger,
+            response,
+            within: within as u64,
+        }
+    } else if monitor.get("duty").is_some() {
+        Rule::Duty {
+            wire: circuit.wire(string(monitor, "wire", name)?)?,
+            duty: number("duty")?,
+            tolerance: number("tolerance")?,
+        }
+    } else {
+        return Err(format!(
+            "Monitor \"{name}\" must give \"always\", \"never\", \"trigger\" or \"duty\""
+        ));
+    };
+    Ok((name, rule))
+}
+
+/// Build a Condition from its description, with the Wires it names looked up in a Circuit.
+///
+/// # Parameters
+///
+/// - `condition`: The condition's description, an object with a single member.
+/// - `circuit`: The Circuit holding the Wires.
+/// - `name`: Name of the monitor, for errors.
+fn parse_condition(condition: &Value, circuit: &Circuit, name: &str) -> Result<Condition, String> {
+    let invalid = || format!("Monitor \"{name}\": invalid condition");
+    let [(key, value)] = (match condition {
+        Value::Object(members) => members.as_slice(),
+        _ => &[],
+    }) else {
+        return Err(invalid());
+    };
+    let wire = || circuit.wire(value.as_str().ok_or_else(invalid)?);
+    let conditions = || match value {
+        Value::Array(conditions) => conditions
+            .iter()
+            .map(|condition| parse_condition(condition, circuit, name))
+            .collect(),
+        _ => Err(invalid()),
+    };
+
+    match key.as_str() {
+        "high" => Ok(Condition::High(wire()?)),
+        "low" => Ok(Condition::Low(wire()?)),
+        "not" => Ok(Condition::Not(Box::new(parse_condition(
+            value, circuit, name,
+        )?))),
+        "all" => Ok(Condition::All(conditions()?)),
+        "any" => Ok(Condition::Any(conditions()?)),
+        _ => Err(invalid()),
+    }
+}
+
+/// Load a Circuit from a netlist file, instantiating its Elements from the [standard](Registry::standard) Registry.
+///
+/// # Parameters
+///
+/// - `path`: Path to the netlist file.
+pub fn load(path: &Path) -> Result<Circuit, String> {
+    let text = fs::read_to_string(path)
+        .map_err(|err| format!("Failed to read netlist {}: {err}", path.display()))?;
+    parse(&text, &Registry::standard()).map_err(|message| format!("{}: {message}", path.display()))
+}
+
+/// Obtain the items of an optional array member of the netlist or of a Subcircuit.
+///
+/// # Parameters
+///
+/// - `netlist`: The netlist, or the Subcircuit's description.
+/// - `key`: Name of the member.
+fn items<'a>(netlist: &'a Value, key: &str) -> Result<&'a [Value], String> {
+    match netlist.get(key) {
+        None => Ok(&[]),
+        Some(Value::Array(items)) => Ok(items),
+        Some(_) => Err(format!("Netlist \"{key}\" must be an array")),
+    }
+}
+
+/// Obtain the members of an optional object member of the netlist, or of an Element or instance.
+///
+/// # Parameters
+///
+/// - `item`: The description of the netlist, Element or instance.
+/// - `key`: Name of the member.
+/// - `owner`: Description of the item, for errors.
+fn members<'a>(item: &'a Value, key: &str, owner: &str) -> Result<&'a [(String, Value)], String> {
+    match item.get(key) {
+        None => Ok(&[]),
+        Some(Value::Object(members)) => Ok(members),
+        Some(_) => Err(format!("{owner}: \"{key}\" must be an object")),
+    }
+}
+
+/// Obtain a required string member of a Wire or Element.
+///
+/// # Parameters
+///
+/// - `item`: The Wire or Element's description.
+/// - `key`: Name of the member.
+/// - `name`: Name of the Wire or Element, or of its kind if it is the name being obtained, for errors.
+fn string<'a>(item: &'a Value, key: &str, name: &str) -> Result<&'a str, String> {
+    item.get(key)
+        .and_then(Value::as_str)
+        .ok_or(format!("\"{name}\" must have a string \"{key}\""))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::monitor::MonitorStatus;
+    use crate::trace::{Logic, DEFAULT_HIGH_THRESHOLD, DEFAULT_LOW_THRESHOLD};
+
+    #[test]
+    fn netlist_parse() {
+        // GIVEN a netlist of a clock and an inverter, with comments and a slow Wire
+        let text = r#"{
+            // Ticks per step.
+            interval: 10,
+            wires: [
+                { name: "CLK", pull: "down", tau: 0.0 },
+                { name: "/CLK", pull: "none" },
+                { name: "SLOW", pull: "down", tau: 25.5 },
+            ],
+            elements: [
+                { name: "X1", kind: "clock", parameters: { period: 40, duty: 0.5 }, pins: { CLK: "CLK" } },
+                { name: "U1", kind: "not", parameters: { delay: "0" }, pins: { I0: "CLK", Y: "/CLK" } },
+            ],
+        }"#;
+        // WHEN it is parsed and simulated
+        let mut circuit = parse(text, &Registry::standard()).unwrap();
+        let mut levels = Vec::new();
+        for _ in 0..8 {
+            circuit.simulation_mut().step().unwrap();
+            let wire = circuit.wire("/CLK").unwrap();
+            levels.push(Logic::from_level(
+                circuit.simulation().wire(wire).unwrap().measure(),
+                DEFAULT_LOW_THRESHOLD,
+                DEFAULT_HIGH_THRESHOLD,
+            ));
+        }
+        // THEN the inverter follows the clock, one step behind
+        let (high, low) = (Logic::High, Logic::Low);
+        assert_eq!(vec![high, low, low, high, high, low, low, high], levels);
+        assert_eq!(80, circuit.simulation().time());
+        assert!(circuit.element("U1").is_ok());
+    }
+    #[test]
+    fn netlist_subcircuits() {
+        // GIVEN a netlist of two instances of a buffer made of two inverters, one of them within another Subcircuit
+        let text = r#"{
+            interval: 10,
+            subcircuits: {
+                buffer: {
+                    ports: ["A", "Y"],
+                    wires: [{ name: "MID", pull: "none" }],
+                    elements: [
+                        { name: "U1", kind: "not", pins: { I0: "A", Y: "MID" } },
+                        { name: "U2", kind: "not", pins: { I0: "MID", Y: "Y" } },
+                    ],
+                },
+                wrapper: {
+                    ports: ["IN", "OUT"],
+                    instances: [{ name: "B", subcircuit: "buffer", ports: { A: "IN", Y: "OUT" } }],
+                },
+            },
+            wires: [
+                { name: "IN", pull: "up" },
+                { name: "MID", pull: "none" },
+                { name: "OUT", pull: "none" },
+            ],
+            instances: [
+                { name: "B1", subcircuit: "buffer", ports: { A: "IN", Y: "MID" } },
+                { name: "W1", subcircuit: "wrapper", ports: { IN: "MID", OUT: "OUT" } },
+            ],
+        }"#;
+        // WHEN it is parsed and simulated
+        let mut circuit = parse(text, &Registry::standard()).unwrap();
+        for _ in 0..5 {
+            circuit.simulation_mut().step().unwrap();
+        }
+        // THEN the instances are named hierarchically, and the input passes through both buffers
+        let level = |name| {
+            let wire = circuit.wire(name).unwrap();
+            f32::from(circuit.simulation().wire(wire).unwrap().measure())
+        };
+        assert_eq!(
+            (1.0, 0.0, 0.0),
+            (level("OUT"), level("B1.MID"), level("W1.B.MID"))
+        );
+        assert!(circuit.element("W1.B.U2").is_ok());
+    }
+    #[test]
+    fn netlist_load_monitors() {
+        // GIVEN a netlist file of a clock and an inverter, with monitors of the clock and the inverter's response
+        let path =
+            std::env::temp_dir().join(format!("rvfs-sim-monitors-{}.json", std::process::id()));
+        fs::write(
+            &path,
+            r#"{
+                interval: 10,
+                wires: [{ name: "CLK", pull: "down" }, { name: "/CLK", pull: "none" }],
+                elements: [
+                    { name: "X1", kind: "clock", parameters: { period: 40 }, pins: { CLK: "CLK" } },
+                    { name: "U1", kind: "not", pins: { I0: "CLK", Y: "/CLK" } },
+                ],
+                monitors: [
+                    { name: "complementary", never: { all: [{ high: "CLK" }, { not: { low: "/CLK" } }] } },
+                    { name: "inverts", trigger: { high: "CLK" }, response: { low: "/CLK" }, within: 10 },
+                    { name: "square", wire: "CLK", duty: 0.5, tolerance: 0.01 },
+                    { name: "defined", always: { any: [{ low: "CLK" }, { high: "CLK" }] } },
+                ],
+            }"#,
+        )
+        .unwrap();
+        // WHEN it is loaded and simulated
+        let circuit = load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        let monitors = circuit.monitors().unwrap().clone();
+        let mut sim = circuit.into_simulation();
+        sim.run_for(12).unwrap();
+        // THEN the monitors are checked, the clock and its inverse overlapping while the inverter lags a step behind
+        let statuses = monitors.statuses();
+        let names: Vec<&str> = statuses.iter().map(|status| status.name.as_str()).collect();
+        assert_eq!(vec!["complementary", "inverts", "square", "defined"], names);
+        let passed: Vec<bool> = statuses.iter().map(MonitorStatus::passed).collect();
+        assert_eq!(vec![false, true, true, true], passed);
+        assert_eq!(Some(0.5), statuses[2].measurement);
+    }
+    #[test]
+    fn netlist_errors() {
+        // GIVEN invalid netlists
+        let registry = Registry::standard();
+        let error = |text: &str| parse(text, &registry).unwrap_err();
+        // WHEN they are parsed
+        // THEN each fails with a description of the problem
+        assert!(error("{").starts_with("Invalid netlist: "));
+        assert_eq!(
+            "Netlist must give a positive whole \"interval\"",
+            error("{ interval: 0.5 }")
+        );
+        assert_eq!(
+            "Netlist \"wires\" must be an array",
+            error("{ interval: 10, wires: {} }")
+        );
+        assert_eq!(
+            "\"wire\" must have a string \"name\"",
+            error("{ interval: 10, wires: [{ pull: 'up' }] }")
+        );
+        assert_eq!(
+            "Invalid pull \"sideways\" for wire \"A\"",
+            error("{ interval: 10, wires: [{ name: 'A', pull: 'sideways' }] }")
+        );
+        assert_eq!(
+            "Wire \"A\": \"tau\" must be a number",
+            error("{ interval: 10, wires: [{ name: 'A', pull: 'up', tau: 'fast' }] }")
+        );
+        assert_eq!(
+            "Element \"U1\": pin \"I0\" must name a wire",
+            error("{ interval: 10, elements: [{ name: 'U1', kind: 'not', pins: { I0: 1 } }] }")
+        );
+        assert_eq!(
+            "No wire named \"A\"",
+            error("{ interval: 10, elements: [{ name: 'U1', kind: 'not', pins: { I0: 'A' } }] }")
+        );
+        assert_eq!(
+            "Unknown element kind \"flux_capacitor\"",
+            error("{ interval: 10, elements: [{ name: 'U1', kind: 'flux_capacitor' }] }")
+        );
+        assert_eq!(
+            "Instance \"X1\": no subcircuit named \"adder\"",
+            error("{ interval: 10, instances: [{ name: 'X1', subcircuit: 'adder' }] }")
+        );
+        assert_eq!(
+            "Subcircuit \"adder\": ports must be names",
+            error("{ interval: 10, subcircuits: { adder: { ports: [1] } } }")
+        );
+        assert_eq!(
+            "Monitor \"M1\" must give \"always\", \"never\", \"trigger\" or \"duty\"",
+            error("{ interval: 10, monitors: [{ name: 'M1' }] }")
+        );
+        assert_eq!(
+            "Monitor \"M1\": invalid condition",
+            error("{ interval: 10, monitors: [{ name: 'M1', always: { high: 'A', low: 'A' } }] }")
+        );
+        assert_eq!(
+            "No wire named \"A\"",
+            error("{ interval: 10, monitors: [{ name: 'M1', never: { high: 'A' } }] }")
+        );
+        assert_eq!(
+            "Monitor \"M1\": a \"trigger\" needs a \"response\"",
+            error(
+                "{ interval: 10, wires: [{ name: 'A', pull: 'up' }], \
+                 monitors: [{ name: 'M1', trigger: { high: 'A' } }] }"
+            )
+        );
+        assert_eq!(
+            "Monitor \"M1\": \"within\" must be a whole number of ticks",
+            error(
+                "{ interval: 10, wires: [{ name: 'A', pull: 'up' }], \
+                 monitors: [{ name: 'M1', trigger: { high: 'A' }, response: { low: 'A' }, within: 2.5 }] }"
+            )
+        );
+        assert!(load(Path::new("/nonexistent/netlist.json"))
+            .unwrap_err()
+            .starts_with("Failed to read netlist /nonexistent/netlist.json: "));
+    }
+}
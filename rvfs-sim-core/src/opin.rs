@@ -1,16 +1,28 @@
 //! OutputPins drive the values calculated by Elements onto Wires.
 
+use crate::prelude::*;
+use crate::time::SimDuration;
+
+/// Drive state of an OutputPin.
 #[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum OutputPinState {
+    /// The pin pulls its Wire low.
     Low,
+    /// The pin pulls its Wire high.
     High,
+    /// The pin does not drive its Wire, leaving it to its default pull.
     HighImpedance,
+    /// The pin drives its Wire to a level its Element cannot determine, so that the Wire reads as unknown (X).
+    Unknown,
 }
 
 /// An interface between Element and Wire instances.
 ///
 /// An OutputPin has a delay time representing the time it takes for a new value to be calculated and propagated to the
 /// attached Wire.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct OutputPin {
     /// A readable name for the pin.
     name: String,
@@ -21,9 +33,9 @@ pub struct OutputPin {
     state: OutputPinState,
 
     /// Propagation delay for this pin.
-    delay: u64,
-    /// Remaining time until the propagating state becomes active.
-    remaining_propagation: u64,
+    delay: SimDuration,
+    /// Remaining time until the propagating state becomes active, or the longest span if nothing is propagating.
+    remaining_propagation: SimDuration,
 }
 
 impl OutputPin {
@@ -39,13 +51,14 @@ impl OutputPin {
     ///
     /// ```
     /// # use rvfs_sim_core::opin::{OutputPin, OutputPinState};
-    /// let pin = OutputPin::new("/INT", 2, OutputPinState::High);
+    /// # use rvfs_sim_core::time::SimDuration;
+    /// let pin = OutputPin::new("/INT", SimDuration::from_ticks(2), OutputPinState::High);
     ///
     /// assert_eq!("/INT", pin.name());
-    /// assert_eq!(2, pin.delay());
+    /// assert_eq!(SimDuration::from_ticks(2), pin.delay());
     /// assert_eq!(OutputPinState::High, pin.state());
     /// ```
-    pub fn new(name: &str, delay: u64, state: OutputPinState) -> Self {
+    pub fn new(name: &str, delay: SimDuration, state: OutputPinState) -> Self {
         Self {
             name: name.to_string(),
 
@@ -53,7 +66,7 @@ impl OutputPin {
             state,
 
             delay,
-            remaining_propagation: u64::MAX,
+            remaining_propagation: SimDuration::MAX,
         }
     }
 
@@ -63,7 +76,7 @@ impl OutputPin {
     }
 
     /// Retrieve the propagation delay of the pin.
-    pub fn delay(&self) -> u64 {
+    pub fn delay(&self) -> SimDuration {
         self.delay
     }
 
@@ -86,6 +99,46 @@ impl OutputPin {
         self.remaining_propagation = self.delay;
     }
 
+    /// Drive a state through the pin, unless the pin is already at or propagating towards that state.
+    ///
+    /// Unlike [set](Self::set), driving the same state on every step does not keep restarting the propagation delay,
+    /// so Elements can drive their outputs unconditionally.
+    ///
+    /// # Parameters
+    ///
+    /// - `state`: State to propagate through the pin.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use rvfs_sim_core::opin::{OutputPin, OutputPinState};
+    /// # use rvfs_sim_core::time::SimDuration;
+    /// let mut pin = OutputPin::new("Y", SimDuration::from_ticks(5), OutputPinState::Low);
+    ///
+    /// pin.drive(OutputPinState::High);
+    /// pin.step(SimDuration::from_ticks(4));
+    /// pin.drive(OutputPinState::High);
+    /// pin.step(SimDuration::from_ticks(4));
+    ///
+    /// assert_eq!(OutputPinState::High, pin.state());
+    /// ```
+    pub fn drive(&mut self, state: OutputPinState) {
+        let target = if self.remaining_propagation == SimDuration::MAX {
+            self.state
+        } else {
+            self.propagating_state
+        };
+        if state != target {
+            self.set(state);
+        }
+    }
+
+    /// Obtain the time remaining until the propagating state becomes active, if it differs from the active state.
+    pub(crate) fn pending(&self) -> Option<SimDuration> {
+        (self.remaining_propagation != SimDuration::MAX && self.propagating_state != self.state)
+            .then_some(self.remaining_propagation)
+    }
+
     /// Update the output state based on the inexorable advance of time.
     ///
     /// # Parameters
@@ -96,26 +149,28 @@ impl OutputPin {
     ///
     /// ```
     /// # use rvfs_sim_core::opin::{OutputPin, OutputPinState};
-    /// let mut pin = OutputPin::new("/INT", 5, OutputPinState::High);
+    /// # use rvfs_sim_core::time::SimDuration;
+    /// let mut pin = OutputPin::new("/INT", SimDuration::from_ticks(5), OutputPinState::High);
+    /// let delta_t = SimDuration::from_ticks(4);
     ///
     /// assert_eq!(OutputPinState::High, pin.state());
     ///
-    /// pin.step(4);
+    /// pin.step(delta_t);
     /// pin.set(OutputPinState::Low);
     ///
     /// assert_eq!(OutputPinState::High, pin.state());
     ///
-    /// pin.step(4);
+    /// pin.step(delta_t);
     ///
     /// assert_eq!(OutputPinState::High, pin.state());
     ///
-    /// pin.step(4);
+    /// pin.step(delta_t);
     ///
     /// assert_eq!(OutputPinState::Low, pin.state());
     /// ```
-    pub fn step(&mut self, delta_t: u64) {
+    pub fn step(&mut self, delta_t: SimDuration) {
         if delta_t >= self.remaining_propagation {
-            self.remaining_propagation = 0;
+            self.remaining_propagation = SimDuration::ZERO;
             self.state = self.propagating_state;
         } else {
             self.remaining_propagation -= delta_t;
@@ -131,7 +186,7 @@ mod tests {
     fn output_pin_create() {
         // GIVEN a name, output delay and initial state
         let name = "foo";
-        let delay = 5u64;
+        let delay = SimDuration::from_ticks(5);
         let state = OutputPinState::HighImpedance;
         // WHEN a new OutputPin is created
         let pin = OutputPin::new(name, delay, state);
@@ -144,7 +199,7 @@ mod tests {
     fn output_pin_set_next_state_with_zero_delay_and_no_step() {
         // GIVEN a pin with initial state and no delay
         let state = OutputPinState::HighImpedance;
-        let mut pin = OutputPin::new("foo", 0, state);
+        let mut pin = OutputPin::new("foo", SimDuration::from_ticks(0), state);
         // WHEN a new state is set
         pin.set(OutputPinState::Low);
         // THEN the state remains at the initial value
@@ -153,11 +208,15 @@ mod tests {
     #[test]
     fn output_pin_set_next_state_with_zero_delay_and_step() {
         // GIVEN a pin with initial state and no delay
-        let mut pin = OutputPin::new("foo", 0, OutputPinState::HighImpedance);
+        let mut pin = OutputPin::new(
+            "foo",
+            SimDuration::from_ticks(0),
+            OutputPinState::HighImpedance,
+        );
         // WHEN a new state is set and the pin is stepped
         let state = OutputPinState::Low;
         pin.set(state);
-        pin.step(10);
+        pin.step(SimDuration::from_ticks(10));
         // THEN the state becomes the new value
         assert_eq!(state, pin.state());
     }
@@ -165,37 +224,67 @@ mod tests {
     fn output_pin_set_next_state_with_delay_and_small_step() {
         // GIVEN a pin with initial state and delay
         let state = OutputPinState::HighImpedance;
-        let mut pin = OutputPin::new("foo", 10, state);
+        let mut pin = OutputPin::new("foo", SimDuration::from_ticks(10), state);
         // WHEN a new state is set and the pin is stepped an amount smaller than the delay
         pin.set(OutputPinState::Low);
-        pin.step(2);
+        pin.step(SimDuration::from_ticks(2));
         // THEN the state remains the initial value
         assert_eq!(state, pin.state());
     }
     #[test]
     fn output_pin_set_next_state_with_delay_and_large_step() {
         // GIVEN a pin with initial state and delay
-        let mut pin = OutputPin::new("foo", 10, OutputPinState::HighImpedance);
+        let mut pin = OutputPin::new(
+            "foo",
+            SimDuration::from_ticks(10),
+            OutputPinState::HighImpedance,
+        );
         // WHEN a new state is set and the pin is stepped by more than the delay
         let state = OutputPinState::Low;
         pin.set(state);
-        pin.step(20);
+        pin.step(SimDuration::from_ticks(20));
         // THEN the state becomes the new value
         assert_eq!(state, pin.state());
     }
     #[test]
     fn output_pin_set_next_state_with_delay_and_multiple_small_steps() {
         // GIVEN a pin with initial state and delay
-        let mut pin = OutputPin::new("foo", 10, OutputPinState::HighImpedance);
+        let mut pin = OutputPin::new(
+            "foo",
+            SimDuration::from_ticks(10),
+            OutputPinState::HighImpedance,
+        );
         // WHEN a new state is set and the pin is stepped multiple times to pass the delay threshold
         let state = OutputPinState::Low;
         pin.set(state);
-        pin.step(4);
-        pin.step(4);
+        pin.step(SimDuration::from_ticks(4));
+        pin.step(SimDuration::from_ticks(4));
         // THEN the state remains the original value until the threshold is passed
         assert_eq!(OutputPinState::HighImpedance, pin.state());
-        pin.step(4);
+        pin.step(SimDuration::from_ticks(4));
         // AND THEN the state becomes the new value
         assert_eq!(state, pin.state());
     }
+    #[test]
+    fn output_pin_drive_present_state() {
+        // GIVEN a pin with delay
+        let mut pin = OutputPin::new("foo", SimDuration::from_ticks(10), OutputPinState::Low);
+        // WHEN its present state is driven
+        pin.drive(OutputPinState::Low);
+        // THEN nothing is propagated
+        assert_eq!(SimDuration::MAX, pin.remaining_propagation);
+    }
+    #[test]
+    fn output_pin_drive_swallows_short_pulse() {
+        // GIVEN a pin with delay which is propagating a new state
+        let mut pin = OutputPin::new("foo", SimDuration::from_ticks(10), OutputPinState::Low);
+        pin.drive(OutputPinState::High);
+        pin.step(SimDuration::from_ticks(5));
+        // WHEN the original state is driven again before the delay has elapsed
+        pin.drive(OutputPinState::Low);
+        pin.step(SimDuration::from_ticks(5));
+        pin.step(SimDuration::from_ticks(10));
+        // THEN the pulse never appears on the output
+        assert_eq!(OutputPinState::Low, pin.state());
+    }
 }
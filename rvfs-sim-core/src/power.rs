@@ -0,0 +1,371 @@
+//! Relative power estimation from the switching activity of Wires.
+
+use crate::sim::Simulation;
+use crate::trace::{Change, Logic, Tracer, DEFAULT_HIGH_THRESHOLD, DEFAULT_LOW_THRESHOLD};
+use crate::wirevalue::WireValue;
+use crate::WireId;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+/// Estimated power of a single net.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NetPower {
+    /// Name of the Wire.
+    pub name: String,
+    /// Number of logic transitions made by the Wire.
+    pub toggles: u64,
+    /// Relative capacitance of the Wire.
+    pub weight: f64,
+    /// Relative power: weighted toggles per unit of simulation time.
+    pub power: f64,
+}
+
+/// Estimated power of a level of the design hierarchy.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GroupPower {
+    /// Hierarchical path of the group, with levels separated by `.`.
+    pub path: String,
+    /// Relative power of all nets within the group.
+    pub power: f64,
+}
+
+/// Relative power estimates for a Simulation run.
+///
+/// The figures have no physical unit; they are only meaningful when compared with one another, or with those of a
+/// different design variant driven by the same stimulus.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PowerReport {
+    /// Simulation time covered by the estimate.
+    pub duration: u64,
+    /// Estimates for each net, highest power first.
+    pub nets: Vec<NetPower>,
+    /// Estimates for each level of the hierarchy, in path order.
+    pub groups: Vec<GroupPower>,
+}
+
+impl PowerReport {
+    /// Obtain the total relative power of all nets.
+    pub fn total(&self) -> f64 {
+        self.nets.iter().map(|net| net.power).sum()
+    }
+}
+
+impl fmt::Display for PowerReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Relative power over {} time units", self.duration)?;
+        writeln!(f, "  total: {:.6}", self.total())?;
+        writeln!(f, "Nets:")?;
+        for net in &self.nets {
+            writeln!(
+                f,
+                "  {:<32} {:>10} toggles  x{:<8} {:.6}",
+                net.name, net.toggles, net.weight, net.power
+            )?;
+        }
+        if !self.groups.is_empty() {
+            writeln!(f, "Hierarchy:")?;
+            for group in &self.groups {
+                let depth = group.path.matches('.').count();
+                writeln!(
+                    f,
+                    "  {:indent$}{:<32} {:.6}",
+                    "",
+                    group.path,
+                    group.power,
+                    indent = 2 * depth
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Switching activity accumulated for a single Wire.
+#[derive(Debug, Clone)]
+struct Activity {
+    /// Name of the Wire.
+    name: String,
+    /// Last definite logic level of the Wire, if it has had one.
+    logic: Option<bool>,
+    /// Number of logic transitions made by the Wire.
+    toggles: u64,
+}
+
+/// State shared between all clones of a PowerEstimator.
+#[derive(Debug, Default)]
+struct State {
+    /// Relative capacitance of each Wire, keyed by Wire Id.  Wires without an entry have a weight of 1.
+    weights: HashMap<WireId, f64>,
+    /// Activity of each Wire, keyed by Wire Id.
+    activity: BTreeMap<WireId, Activity>,
+    /// Simulation time at which estimation started.
+    start_time: u64,
+    /// Most recent simulation time seen.
+    time: u64,
+}
+
+/// A Tracer which counts the logic transitions of every Wire to estimate relative power consumption.
+///
+/// Dynamic power is proportional to capacitance multiplied by switching frequency, so each Wire's toggle count is
+/// multiplied by a configurable weight representing its relative capacitance.  Wires are grouped into hierarchy levels
+/// by splitting their names on `.`.
+///
+/// Clones of a PowerEstimator share their state, so a clone can be kept to obtain the report after the original has
+/// been attached to a Simulation.
+///
+/// # Example
+///
+/// ```
+/// # use rvfs_sim_core::power::PowerEstimator;
+/// # use rvfs_sim_core::sim::Simulation;
+/// # use rvfs_sim_core::time::SimDuration;
+/// # use rvfs_sim_core::wire::{Wire, WirePull};
+/// let mut sim = Simulation::new(SimDuration::from_ticks(10));
+/// let bus = sim.add_wire(Wire::new("cpu.bus.d0", WirePull::Up)).unwrap();
+/// let power = PowerEstimator::new();
+/// power.set_weight(bus, 4.0);
+/// sim.add_tracer(Box::new(power.clone()));
+///
+/// sim.step().unwrap();
+/// println!("{}", power.report());
+/// ```
+#[derive(Debug, Clone)]
+pub struct PowerEstimator {
+    /// Shared estimation state.
+    state: Arc<Mutex<State>>,
+    /// Wire level at or below which a Wire is considered logic low.
+    low_threshold: f32,
+    /// Wire level at or above which a Wire is considered logic high.
+    high_threshold: f32,
+}
+
+impl PowerEstimator {
+    /// Create a new PowerEstimator where every Wire has a weight of 1.
+    pub fn new() -> Self {
+        Self {
+            state: Arc::new(Mutex::new(State::default())),
+            low_threshold: DEFAULT_LOW_THRESHOLD,
+            high_threshold: DEFAULT_HIGH_THRESHOLD,
+        }
+    }
+
+    /// Change the thresholds used to derive logic levels from Wire levels.
+    ///
+    /// # Parameters
+    ///
+    /// - `low`: Level at or below which a Wire is considered logic low.
+    /// - `high`: Level at or above which a Wire is considered logic high.
+    pub fn with_thresholds(mut self, low: f32, high: f32) -> Self {
+        self.low_threshold = low;
+        self.high_threshold = high;
+        self
+    }
+
+    /// Set the relative capacitance of a Wire.
+    ///
+    /// # Parameters
+    ///
+    /// - `id`: Id of the Wire.
+    /// - `weight`: Relative capacitance of the Wire.
+    pub fn set_weight(&self, id: WireId, weight: f64) {
+        self.lock().weights.insert(id, weight);
+    }
+
+    /// Produce a report of the power estimated so far.
+    pub fn report(&self) -> PowerReport {
+        let state = self.lock();
+        let duration = state.time - state.start_time;
+
+        let mut nets: Vec<NetPower> = state
+            .activity
+            .iter()
+            .map(|(id, activity)| {
+                let weight = state.weights.get(id).copied().unwrap_or(1.0);
+                let power = if duration > 0 {
+                    activity.toggles as f64 * weight / duration as f64
+                } else {
+                    0.0
+                };
+                NetPower {
+                    name: activity.name.clone(),
+                    toggles: activity.toggles,
+                    weight,
+                    power,
+                }
+            })
+            .collect();
+
+        let mut groups = BTreeMap::<String, f64>::new();
+        for net in &nets {
+            let levels: Vec<&str> = net.name.split('.').collect();
+            for depth in 1..levels.len() {
+                *groups.entry(levels[..depth].join(".")).or_default() += net.power;
+            }
+        }
+
+        nets.sort_by(|a, b| {
+            b.power
+                .total_cmp(&a.power)
+                .then_with(|| a.name.cmp(&b.name))
+        });
+        PowerReport {
+            duration,
+            nets,
+            groups: groups
+                .into_iter()
+                .map(|(path, power)| GroupPower { path, power })
+                .collect(),
+        }
+    }
+
+    /// Lock the shared state.
+    fn lock(&self) -> std::sync::MutexGuard<'_, State> {
+        self.state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Convert a Wire level into a definite logic level, if it has one.
+    fn logic(&self, value: WireValue) -> Option<bool> {
+        match Logic::from_level(value, self.low_threshold, self.high_threshold) {
+            Logic::Low => Some(false),
+            Logic::High => Some(true),
+            Logic::Unknown => None,
+        }
+    }
+}
+
+impl Default for PowerEstimator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Tracer for PowerEstimator {
+    fn start(&mut self, sim: &Simulation) -> Result<(), String> {
+        let mut activity = BTreeMap::new();
+        for id in sim.wires() {
+            let wire = sim.wire(id)?;
+            activity.insert(
+                id,
+                Activity {
+                    name: wire.name().clone(),
+                    logic: self.logic(wire.measure()),
+                    toggles: 0,
+                },
+            );
+        }
+
+        let mut state = self.lock();
+        state.activity = activity;
+        state.start_time = sim.time();
+        state.time = sim.time();
+        Ok(())
+    }
+
+    fn record(&mut self, sim: &Simulation, changes: &[Change]) -> Result<(), String> {
+        let mut state = self.lock();
+        state.time = sim.time();
+        for change in changes {
+            let logic = self.logic(change.value);
+            if let (Some(activity), Some(logic)) = (state.activity.get_mut(&change.id), logic) {
+                if activity.logic.is_some_and(|previous| previous != logic) {
+                    activity.toggles += 1;
+                }
+                activity.logic = Some(logic);
+            }
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self, _sim: &Simulation) -> Result<(), String> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::time::SimDuration;
+    use crate::wire::{Wire, WirePull};
+    use crate::Id;
+
+    /// Build a change to a Wire's level.
+    fn change(id: Id, previous: f32, value: f32) -> Change {
+        Change {
+            id: WireId::from(id),
+            previous: WireValue::new(previous),
+            value: WireValue::new(value),
+        }
+    }
+
+    /// Build a Simulation with wires in a small hierarchy, and a started PowerEstimator for it.
+    fn setup() -> (Simulation, PowerEstimator) {
+        let mut sim = Simulation::new(SimDuration::from_ticks(10));
+        sim.add_wire(Wire::new("cpu.alu.carry", WirePull::Down))
+            .unwrap();
+        sim.add_wire(Wire::new("cpu.clk", WirePull::Down)).unwrap();
+        sim.add_wire(Wire::new("led", WirePull::Down)).unwrap();
+        let mut power = PowerEstimator::new();
+        power.start(&sim).unwrap();
+        (sim, power)
+    }
+
+    #[test]
+    fn power_counts_full_transitions() {
+        // GIVEN a started PowerEstimator
+        let (sim, mut power) = setup();
+        // WHEN a wire rises through the indeterminate band, and another only wanders into it
+        power
+            .record(&sim, &[change(0, 0.0, 0.5), change(2, 0.0, 0.5)])
+            .unwrap();
+        power
+            .record(&sim, &[change(0, 0.5, 1.0), change(2, 0.5, 0.1)])
+            .unwrap();
+        // THEN only the full transition is counted as a toggle
+        let report = power.report();
+        let toggles: Vec<(&str, u64)> = report
+            .nets
+            .iter()
+            .map(|n| (n.name.as_str(), n.toggles))
+            .collect();
+        assert!(toggles.contains(&("cpu.alu.carry", 1)));
+        assert!(toggles.contains(&("led", 0)));
+    }
+    #[test]
+    fn power_weights_and_hierarchy() {
+        // GIVEN a started PowerEstimator where the clock has a larger weight
+        let (mut sim, mut power) = setup();
+        power.set_weight(WireId::from(1), 3.0);
+        // WHEN the carry and clock each toggle twice over a 10 unit step
+        sim.step().unwrap();
+        power
+            .record(&sim, &[change(0, 0.0, 1.0), change(1, 0.0, 1.0)])
+            .unwrap();
+        power
+            .record(&sim, &[change(0, 1.0, 0.0), change(1, 1.0, 0.0)])
+            .unwrap();
+        // THEN the nets are ranked by weighted toggle rate, and the hierarchy levels sum their nets
+        let report = power.report();
+        assert_eq!(10, report.duration);
+        assert_eq!("cpu.clk", report.nets[0].name);
+        assert_eq!(0.6, report.nets[0].power);
+        assert_eq!("cpu.alu.carry", report.nets[1].name);
+        assert_eq!(0.2, report.nets[1].power);
+        assert_eq!(
+            vec![
+                GroupPower {
+                    path: "cpu".to_string(),
+                    power: 0.8
+                },
+                GroupPower {
+                    path: "cpu.alu".to_string(),
+                    power: 0.2
+                },
+            ],
+            report.groups
+        );
+        assert_eq!(0.8, report.total());
+    }
+}
@@ -0,0 +1,171 @@
+//! Profiling of the wall-clock time spent stepping individual Simulation components.
+
+use crate::prelude::*;
+use crate::{ComponentId, ElementId, Id, WireId};
+use alloc::collections::BTreeMap;
+use core::fmt;
+use core::time::Duration;
+
+/// Default number of components listed when a Profile is displayed.
+const DISPLAY_LIMIT: usize = 10;
+
+/// Wall-clock time spent stepping a single component.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ComponentProfile {
+    /// Name of the component.
+    pub name: String,
+    /// Number of times the component was stepped.
+    pub steps: u64,
+    /// Total wall-clock time spent stepping the component.
+    pub total: Duration,
+    /// Longest wall-clock time spent in a single step of the component.
+    pub max: Duration,
+}
+
+impl ComponentProfile {
+    /// Obtain the average wall-clock time spent in a single step of the component.
+    pub fn mean(&self) -> Duration {
+        if self.steps == 0 {
+            Duration::ZERO
+        } else {
+            self.total / self.steps as u32
+        }
+    }
+}
+
+/// Wall-clock time spent stepping each component of a Simulation, aggregated across the run.
+///
+/// Profiling is enabled with [`Simulation::set_profiling`](crate::sim::Simulation::set_profiling).
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Profile {
+    /// Time spent stepping each Wire, keyed by Wire Id.
+    wires: BTreeMap<Id, ComponentProfile>,
+    /// Time spent stepping each Element, keyed by Element Id.
+    elements: BTreeMap<Id, ComponentProfile>,
+}
+
+impl Profile {
+    /// Create a new, empty Profile.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Obtain the profile of a Wire.
+    ///
+    /// # Parameters
+    ///
+    /// - `id`: Id of the Wire.
+    pub fn wire(&self, id: WireId) -> Option<&ComponentProfile> {
+        self.wires.get(&id.index())
+    }
+
+    /// Obtain the profile of an Element.
+    ///
+    /// # Parameters
+    ///
+    /// - `id`: Id of the Element.
+    pub fn element(&self, id: ElementId) -> Option<&ComponentProfile> {
+        self.elements.get(&id.index())
+    }
+
+    /// Obtain the components which took the most wall-clock time in total, most expensive first.
+    ///
+    /// # Parameters
+    ///
+    /// - `count`: Maximum number of components to obtain.
+    pub fn top(&self, count: usize) -> Vec<&ComponentProfile> {
+        let mut components: Vec<&ComponentProfile> =
+            self.wires.values().chain(self.elements.values()).collect();
+        components.sort_by(|a, b| b.total.cmp(&a.total).then_with(|| a.name.cmp(&b.name)));
+        components.truncate(count);
+        components
+    }
+
+    /// Note the wall-clock time spent in a single step of a Wire.
+    ///
+    /// # Parameters
+    ///
+    /// - `id`: Id of the Wire.
+    /// - `name`: Name of the Wire.
+    /// - `elapsed`: Wall-clock time spent stepping the Wire.
+    pub(crate) fn record_wire(&mut self, id: WireId, name: &str, elapsed: Duration) {
+        Self::record(&mut self.wires, id.index(), name, elapsed);
+    }
+
+    /// Note the wall-clock time spent in a single step of an Element.
+    ///
+    /// # Parameters
+    ///
+    /// - `id`: Id of the Element.
+    /// - `name`: Name of the Element.
+    /// - `elapsed`: Wall-clock time spent stepping the Element.
+    pub(crate) fn record_element(&mut self, id: ElementId, name: &str, elapsed: Duration) {
+        Self::record(&mut self.elements, id.index(), name, elapsed);
+    }
+
+    /// Note the wall-clock time spent in a single step of a component.
+    fn record(
+        components: &mut BTreeMap<Id, ComponentProfile>,
+        id: Id,
+        name: &str,
+        elapsed: Duration,
+    ) {
+        let profile = components.entry(id).or_insert_with(|| ComponentProfile {
+            name: name.to_string(),
+            steps: 0,
+            total: Duration::ZERO,
+            max: Duration::ZERO,
+        });
+        profile.steps += 1;
+        profile.total += elapsed;
+        profile.max = profile.max.max(elapsed);
+    }
+}
+
+impl fmt::Display for Profile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "  {:<32} {:>10} {:>12} {:>12} {:>12}",
+            "component", "steps", "total", "mean", "max"
+        )?;
+        for component in self.top(DISPLAY_LIMIT) {
+            writeln!(
+                f,
+                "  {:<32} {:>10} {:>12} {:>12} {:>12}",
+                component.name,
+                component.steps,
+                format!("{:?}", component.total),
+                format!("{:?}", component.mean()),
+                format!("{:?}", component.max)
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn profile_record() {
+        // GIVEN an empty profile
+        let mut profile = Profile::new();
+        // WHEN several steps of two wires are recorded
+        profile.record_wire(WireId::from(0), "CLK", Duration::from_micros(2));
+        profile.record_wire(WireId::from(0), "CLK", Duration::from_micros(4));
+        profile.record_wire(WireId::from(1), "DATA", Duration::from_micros(10));
+        profile.record_element(ElementId::from(0), "U1", Duration::from_micros(1));
+        // THEN each component's times are aggregated and the most expensive is ranked first
+        let clk = profile.wire(WireId::from(0)).unwrap();
+        assert_eq!(2, clk.steps);
+        assert_eq!(Duration::from_micros(6), clk.total);
+        assert_eq!(Duration::from_micros(3), clk.mean());
+        assert_eq!(Duration::from_micros(4), clk.max);
+        let top: Vec<&str> = profile.top(5).iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(vec!["DATA", "CLK", "U1"], top);
+        assert_eq!(1, profile.element(ElementId::from(0)).unwrap().steps);
+        assert_eq!(1, profile.top(1).len());
+    }
+}
@@ -0,0 +1,126 @@
+//! Pseudo-random numbers for the stochastic features of a Simulation, such as contact bounce.
+//!
+//! Every Simulation has a seed, set once from its configuration or command line, from which all of its pseudo-random
+//! behaviour is derived: the Simulation [hands](crate::element::Element::set_rng) a generator seeded with it to each
+//! of its Elements, and [offers](crate::sim::Simulation::rng) one to any other stochastic feature.  Given the same
+//! seed, a run can therefore be reproduced exactly, and the seed is recorded in the
+//! [metrics](crate::metrics::Metrics::seed) from which run reports are made.
+//!
+//! A feature rarely draws from the generator it is handed directly, since its numbers would then depend on how many
+//! other features drew before it.  Instead it [forks](SimRng::fork) an independent stream, identified by a number of
+//! its own such as a seed parameter.
+
+/// A small, fast pseudo-random number generator, using the SplitMix64 algorithm.
+///
+/// The sequence depends only on the seed, on every platform, so it is suitable for reproducible simulations but not
+/// for cryptography.
+///
+/// # Example
+///
+/// ```
+/// # use rvfs_sim_core::rng::SimRng;
+/// let root = SimRng::new(42);
+/// let mut first = root.fork(7);
+/// let mut second = root.fork(7);
+///
+/// assert_eq!(first.next_u64(), second.next_u64());
+/// assert!(first.below(6) < 6);
+/// ```
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct SimRng {
+    /// State of the generator, advanced by a constant on each draw.
+    state: u64,
+}
+
+impl SimRng {
+    /// Create a new SimRng.
+    ///
+    /// # Parameters
+    ///
+    /// - `seed`: The seed, which determines every number drawn.
+    pub const fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    /// Create an independent stream of numbers, determined by the state of this generator and a stream number.
+    ///
+    /// Forking the generator of a Simulation with seed 0 gives the same stream as a new generator seeded with the
+    /// stream number, so seed parameters chosen before a Simulation had a seed keep their meaning.
+    ///
+    /// # Parameters
+    ///
+    /// - `stream`: Number identifying the stream.
+    pub const fn fork(&self, stream: u64) -> Self {
+        Self::new(mix(self.state) ^ stream)
+    }
+
+    /// Draw the next number, uniformly distributed over every `u64`.
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        mix(self.state)
+    }
+
+    /// Draw a number below a bound.
+    ///
+    /// # Parameters
+    ///
+    /// - `bound`: The bound, which must be non-zero.
+    pub fn below(&mut self, bound: u64) -> u64 {
+        self.next_u64() % bound
+    }
+
+    /// Draw a number uniformly distributed from 0 up to but excluding 1.
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+/// Scramble the bits of a number, as the output function of SplitMix64, which maps 0 to itself.
+///
+/// # Parameters
+///
+/// - `z`: The number to scramble.
+const fn mix(mut z: u64) -> u64 {
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::*;
+
+    #[test]
+    fn rng_reproducible() {
+        // GIVEN two generators with the same seed, and one with another
+        let mut a = SimRng::new(1);
+        let mut b = SimRng::new(1);
+        let mut c = SimRng::new(2);
+        // WHEN numbers are drawn from each
+        let a: Vec<u64> = (0..4).map(|_| a.next_u64()).collect();
+        let b: Vec<u64> = (0..4).map(|_| b.next_u64()).collect();
+        let c: Vec<u64> = (0..4).map(|_| c.next_u64()).collect();
+        // THEN the same seed gives the same numbers, matching the reference SplitMix64 sequence
+        assert_eq!(a, b);
+        assert_ne!(a, c);
+        assert_eq!(0xe220_a839_7b1d_cdaf, SimRng::new(0).next_u64());
+    }
+    #[test]
+    fn rng_streams() {
+        // GIVEN a root generator with seed 0, and one with another seed
+        let zero = SimRng::new(0);
+        let other = SimRng::new(99);
+        // WHEN streams are forked from them
+        // THEN a stream of the zero seed matches a generator seeded with the stream number, and other streams differ
+        assert_eq!(SimRng::new(7), zero.fork(7));
+        assert_ne!(zero.fork(7), zero.fork(8));
+        assert_ne!(zero.fork(7), other.fork(7));
+        // AND THEN bounded draws stay in range
+        let mut rng = other.fork(1);
+        for _ in 0..100 {
+            assert!(rng.below(10) < 10);
+            assert!((0.0..1.0).contains(&rng.next_f64()));
+        }
+    }
+}
@@ -0,0 +1,290 @@
+//! The event queue of the [event-driven](crate::sim::Scheduler::EventDriven) scheduler, which tracks which components
+//! of a Simulation have to be evaluated on each step.
+//!
+//! A Wire is due for evaluation on the step after its value changes, and an OutputPin on the step in which its
+//! propagating state becomes active.  OutputPins are stepped lazily, by all the time elapsed since they were last
+//! stepped, so a pin waiting out a long propagation delay costs nothing until its state changes.  InputPins are due on
+//! the step after their Wire changes, and again on the step after they change, so that their
+//! [changed](crate::ipin::InputPin::changed) flags are cleared as they would be if every pin were stepped.
+
+use crate::opin::OutputPin;
+use crate::prelude::*;
+use crate::time::SimDuration;
+use crate::{ComponentId, ElementId, InputPinId, OutputPinId, WireId};
+use alloc::collections::{BTreeMap, BTreeSet};
+
+/// A component due for evaluation.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum Event {
+    /// An OutputPin whose propagating state becomes active.
+    Pin(OutputPinId),
+    /// A Wire whose value or drivers may change.
+    Wire(WireId),
+}
+
+/// The components of a Simulation due for evaluation, and the connections needed to find them.
+///
+/// A Schedule describes the netlist as it was when the Schedule was made, so it is discarded whenever the netlist
+/// changes and made afresh after a step in which everything is evaluated.
+#[derive(Debug, Clone)]
+pub(crate) struct Schedule {
+    /// Time step size of the Simulation, in ticks.
+    interval: u64,
+    /// Events still to be processed, keyed by the time of the step in which they fall due.
+    events: BTreeMap<u64, BTreeSet<Event>>,
+    /// InputPins to be stepped in the next step.
+    inputs: BTreeSet<InputPinId>,
+    /// Combinational Elements to be stepped in the present step, as their inputs have changed.
+    elements: BTreeSet<ElementId>,
+    /// Elements which are not combinational, and so are stepped on every step.
+    sequential: Vec<ElementId>,
+    /// Whether each Element is combinational, indexed by slot.
+    combinational: Vec<bool>,
+    /// InputPins connected to each Wire, indexed by slot.
+    wire_inputs: Vec<Vec<InputPinId>>,
+    /// Element to which each InputPin belongs, if any, indexed by slot.
+    input_elements: Vec<Option<ElementId>>,
+    /// Simulation time up to which each OutputPin has been stepped, indexed by slot.
+    pin_times: Vec<u64>,
+}
+
+impl Schedule {
+    /// Create a new Schedule with nothing due, for a Simulation whose OutputPins have all been stepped up to the
+    /// present time.
+    ///
+    /// # Parameters
+    ///
+    /// - `interval`: Time step size of the Simulation.
+    /// - `time`: Present simulation time.
+    /// - `elements`: The Id of each Element and whether it is combinational, indexed by slot, or `None` for a slot
+    ///   which is free.
+    /// - `element_inputs`: Ids of the InputPins of each Element, indexed by slot.
+    /// - `input_wires`: Id of the Wire connected to each InputPin, if any, indexed by slot.
+    /// - `wire_count`: Number of Wires ever added to the Simulation.
+    /// - `pin_count`: Number of OutputPin slots in the Simulation.
+    pub(crate) fn new(
+        interval: SimDuration,
+        time: u64,
+        elements: &[Option<(ElementId, bool)>],
+        element_inputs: &[Vec<InputPinId>],
+        input_wires: &[Option<WireId>],
+        wire_count: usize,
+        pin_count: usize,
+    ) -> Self {
+        let mut wire_inputs = vec![Vec::new(); wire_count];
+        let mut input_elements = vec![None; input_wires.len()];
+        for (element, pins) in elements.iter().zip(element_inputs) {
+            let Some((element, _)) = element else {
+                continue;
+            };
+            for pin in pins {
+                input_elements[pin.slot()] = Some(*element);
+                if let Some(wire) = input_wires[pin.slot()] {
+                    wire_inputs[wire.slot()].push(*pin);
+                }
+            }
+        }
+
+        Self {
+            interval: interval.ticks(),
+            events: BTreeMap::new(),
+            inputs: BTreeSet::new(),
+            elements: BTreeSet::new(),
+            sequential: elements
+                .iter()
+                .flatten()
+                .filter(|(_, combinational)| !combinational)
+                .map(|(id, _)| *id)
+                .collect(),
+            combinational: elements
+                .iter()
+                .map(|element| element.is_some_and(|(_, combinational)| combinational))
+                .collect(),
+            wire_inputs,
+            input_elements,
+            pin_times: vec![time; pin_count],
+        }
+    }
+
+    /// Add an event to the queue.
+    ///
+    /// # Parameters
+    ///
+    /// - `time`: Time of the step in which the event falls due.
+    /// - `event`: The event.
+    pub(crate) fn add(&mut self, time: u64, event: Event) {
+        self.events.entry(time).or_default().insert(event);
+    }
+
+    /// Remove and obtain every event falling due at or before a time.
+    ///
+    /// # Parameters
+    ///
+    /// - `time`: Time of the present step.
+    pub(crate) fn take_due(&mut self, time: u64) -> BTreeSet<Event> {
+        let mut due = BTreeSet::new();
+        while let Some(entry) = self.events.first_entry() {
+            if *entry.key() > time {
+                break;
+            }
+            due.append(&mut entry.remove());
+        }
+        due
+    }
+
+    /// Note that a Wire has changed value, so that it is evaluated again on the next step and its InputPins are
+    /// stepped.
+    ///
+    /// # Parameters
+    ///
+    /// - `id`: The Id of the Wire.
+    /// - `time`: Time of the next step.
+    pub(crate) fn wire_changed(&mut self, id: WireId, time: u64) {
+        self.add(time, Event::Wire(id));
+        self.inputs
+            .extend(self.wire_inputs[id.slot()].iter().copied());
+    }
+
+    /// Note that an InputPin is to be stepped on the next step.
+    ///
+    /// # Parameters
+    ///
+    /// - `id`: The Id of the InputPin.
+    pub(crate) fn step_input(&mut self, id: InputPinId) {
+        self.inputs.insert(id);
+    }
+
+    /// Remove and obtain the InputPins to be stepped in the present step.
+    pub(crate) fn take_inputs(&mut self) -> BTreeSet<InputPinId> {
+        core::mem::take(&mut self.inputs)
+    }
+
+    /// Note that an InputPin has changed state, so that it is stepped again on the next step and its Element is
+    /// stepped in the present one.
+    ///
+    /// # Parameters
+    ///
+    /// - `id`: The Id of the InputPin.
+    pub(crate) fn input_changed(&mut self, id: InputPinId) {
+        self.step_input(id);
+        if let Some(element) = self.input_elements[id.slot()] {
+            if self.combinational[element.slot()] {
+                self.elements.insert(element);
+            }
+        }
+    }
+
+    /// Remove and obtain the Elements to be stepped in the present step, in ascending order of Id.
+    pub(crate) fn take_elements(&mut self) -> Vec<ElementId> {
+        let mut elements = core::mem::take(&mut self.elements);
+        elements.extend(self.sequential.iter().copied());
+        elements.into_iter().collect()
+    }
+
+    /// Obtain the time elapsed since an OutputPin was last stepped, and note that it is stepped up to a time.
+    ///
+    /// # Parameters
+    ///
+    /// - `id`: The Id of the OutputPin.
+    /// - `time`: Time up to which the pin is being stepped.
+    pub(crate) fn advance(&mut self, id: OutputPinId, time: u64) -> SimDuration {
+        let elapsed = time.saturating_sub(self.pin_times[id.slot()]);
+        self.pin_times[id.slot()] = time;
+        SimDuration::from_ticks(elapsed)
+    }
+
+    /// Add an event for an OutputPin in the step in which its propagating state becomes active, if it has one.
+    ///
+    /// # Parameters
+    ///
+    /// - `id`: The Id of the OutputPin.
+    /// - `pin`: The OutputPin, stepped up to the time noted by [advance](Self::advance).
+    pub(crate) fn pin_driven(&mut self, id: OutputPinId, pin: &OutputPin) {
+        let Some(remaining) = pin.pending() else {
+            return;
+        };
+        // The pin is stepped by a whole number of intervals by the end of the step in which it becomes active.
+        let steps = remaining.ticks().div_ceil(self.interval).max(1) - 1;
+        let time = self.pin_times[id.slot()].saturating_add(steps.saturating_mul(self.interval));
+        self.add(time, Event::Pin(id));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::opin::OutputPinState;
+
+    #[test]
+    fn schedule_events() {
+        // GIVEN a Schedule with events due at several times
+        let mut schedule = Schedule::new(SimDuration::from_ticks(10), 0, &[], &[], &[], 2, 1);
+        schedule.add(20, Event::Wire(WireId::from(1)));
+        schedule.add(10, Event::Wire(WireId::from(0)));
+        schedule.add(10, Event::Pin(OutputPinId::from(0)));
+        // WHEN the events due by each time are taken
+        let early = schedule.take_due(10);
+        let late = schedule.take_due(30);
+        // THEN each event is taken once, in order
+        assert_eq!(
+            vec![
+                Event::Pin(OutputPinId::from(0)),
+                Event::Wire(WireId::from(0))
+            ],
+            early.into_iter().collect::<Vec<_>>()
+        );
+        assert_eq!(
+            vec![Event::Wire(WireId::from(1))],
+            late.into_iter().collect::<Vec<_>>()
+        );
+        assert!(schedule.take_due(u64::MAX).is_empty());
+    }
+    #[test]
+    fn schedule_fanout() {
+        // GIVEN a combinational and a sequential Element, each with an InputPin on the same Wire
+        let wire = WireId::from(0);
+        let (a, b) = (InputPinId::from(0), InputPinId::from(1));
+        let mut schedule = Schedule::new(
+            SimDuration::from_ticks(10),
+            0,
+            &[
+                Some((ElementId::from(0), true)),
+                Some((ElementId::from(1), false)),
+            ],
+            &[vec![a], vec![b]],
+            &[Some(wire), Some(wire)],
+            1,
+            0,
+        );
+        // WHEN the Wire changes and both pins change with it
+        schedule.wire_changed(wire, 10);
+        let inputs = schedule.take_inputs();
+        for pin in &inputs {
+            schedule.input_changed(*pin);
+        }
+        // THEN both pins are stepped, and both Elements are stepped, the sequential one as it always is
+        assert_eq!(vec![a, b], inputs.into_iter().collect::<Vec<_>>());
+        assert_eq!(
+            vec![ElementId::from(0), ElementId::from(1)],
+            schedule.take_elements()
+        );
+        assert_eq!(vec![ElementId::from(1)], schedule.take_elements());
+        assert_eq!(2, schedule.take_inputs().len());
+    }
+    #[test]
+    fn schedule_pin_propagation() {
+        // GIVEN an OutputPin with a delay of two and a half intervals, stepped up to time 30
+        let mut schedule = Schedule::new(SimDuration::from_ticks(10), 30, &[], &[], &[], 0, 1);
+        let id = OutputPinId::from(0);
+        let mut pin = OutputPin::new("Y", SimDuration::from_ticks(25), OutputPinState::Low);
+        pin.set(OutputPinState::High);
+        // WHEN it is driven, and stepped lazily when it falls due
+        schedule.pin_driven(id, &pin);
+        let due = schedule.take_due(50);
+        pin.step(schedule.advance(id, 60));
+        // THEN its state becomes active in the third step, after 30 ticks
+        assert_eq!(vec![Event::Pin(id)], due.into_iter().collect::<Vec<_>>());
+        assert_eq!(OutputPinState::High, pin.state());
+        assert_eq!(None, pin.pending());
+    }
+}
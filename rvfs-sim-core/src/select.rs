@@ -0,0 +1,235 @@
+//! Selection of signals by name, shared by every subsystem which observes a subset of the Wires.
+
+mod regex;
+
+pub use regex::Regex;
+
+use crate::sim::Simulation;
+use crate::WireId;
+
+/// A pattern matching signal names.
+///
+/// Signal names are hierarchical, with levels separated by `.`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Pattern {
+    /// A glob, which must match the whole name.  `*` matches any run of characters within one hierarchy level, `**`
+    /// matches any run of characters across levels, and `?` matches any single character other than `.`.
+    Glob(String),
+    /// A regular expression, which may match anywhere within the name unless anchored.
+    Regex(Regex),
+    /// A hierarchy scope, matching the signal with exactly that name and every signal beneath it.
+    Scope(String),
+}
+
+impl Pattern {
+    /// Query whether the pattern matches a signal name.
+    ///
+    /// # Parameters
+    ///
+    /// - `name`: The signal name.
+    pub fn matches(&self, name: &str) -> bool {
+        match self {
+            Pattern::Glob(glob) => {
+                let glob: Vec<char> = glob.chars().collect();
+                let name: Vec<char> = name.chars().collect();
+                glob_matches(&glob, &name)
+            }
+            Pattern::Regex(regex) => regex.is_match(name),
+            Pattern::Scope(scope) => name
+                .strip_prefix(scope.as_str())
+                .is_some_and(|rest| rest.is_empty() || rest.starts_with('.')),
+        }
+    }
+}
+
+/// Match a glob against the whole of a name.
+fn glob_matches(glob: &[char], name: &[char]) -> bool {
+    match glob {
+        [] => name.is_empty(),
+        ['*', '*', rest @ ..] => (0..=name.len()).any(|skip| glob_matches(rest, &name[skip..])),
+        ['*', rest @ ..] => {
+            let level = name.iter().position(|c| *c == '.').unwrap_or(name.len());
+            (0..=level).any(|skip| glob_matches(rest, &name[skip..]))
+        }
+        ['?', rest @ ..] => {
+            name.first().is_some_and(|c| *c != '.') && glob_matches(rest, &name[1..])
+        }
+        [c, rest @ ..] => name.first() == Some(c) && glob_matches(rest, &name[1..]),
+    }
+}
+
+/// A selection of signals, made by include and exclude lists of patterns.
+///
+/// A signal is selected if it matches any include pattern, or if there are no include patterns, and it matches no
+/// exclude pattern.  The selected Wire Ids can be passed to the `select` method of any Tracer, or used to build a
+/// [TriggeredCapture](crate::trace::trigger::TriggeredCapture), so that every subsystem selects signals in the same
+/// way.
+///
+/// # Example
+///
+/// ```
+/// # use rvfs_sim_core::select::SignalSelector;
+/// # use rvfs_sim_core::sim::Simulation;
+/// # use rvfs_sim_core::time::SimDuration;
+/// # use rvfs_sim_core::trace::vcd::VcdWriter;
+/// # use rvfs_sim_core::wire::{Wire, WirePull};
+/// let mut sim = Simulation::new(SimDuration::from_ticks(10));
+/// sim.add_wire(Wire::new("cpu.clk", WirePull::Down)).unwrap();
+/// let d0 = sim.add_wire(Wire::new("cpu.bus.d0", WirePull::Up)).unwrap();
+/// let selector = SignalSelector::parse("scope:cpu\n!cpu.clk").unwrap();
+/// let ids = selector.select(&sim);
+/// sim.add_tracer(Box::new(VcdWriter::new(std::io::sink()).select(&ids)));
+///
+/// assert_eq!(vec![d0], ids);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SignalSelector {
+    /// Patterns of signals to include.
+    include: Vec<Pattern>,
+    /// Patterns of signals to exclude.
+    exclude: Vec<Pattern>,
+}
+
+impl SignalSelector {
+    /// Create a new SignalSelector which selects every signal.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parse a SignalSelector from its textual configuration.
+    ///
+    /// Each line holds one pattern.  A pattern is a glob unless prefixed with `regex:` or `scope:`, and is an exclude
+    /// pattern if prefixed with `!`.  Blank lines and lines starting with `#` are ignored.
+    ///
+    /// # Parameters
+    ///
+    /// - `text`: The configuration text.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut selector = Self::new();
+        for line in text.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (exclude, spec) = match line.strip_prefix('!') {
+                Some(spec) => (true, spec.trim_start()),
+                None => (false, line),
+            };
+            let pattern = if let Some(regex) = spec.strip_prefix("regex:") {
+                Pattern::Regex(Regex::new(regex)?)
+            } else if let Some(scope) = spec.strip_prefix("scope:") {
+                Pattern::Scope(scope.to_string())
+            } else {
+                Pattern::Glob(spec.strip_prefix("glob:").unwrap_or(spec).to_string())
+            };
+            selector = if exclude {
+                selector.exclude(pattern)
+            } else {
+                selector.include(pattern)
+            };
+        }
+        Ok(selector)
+    }
+
+    /// Add a pattern of signals to include.
+    ///
+    /// # Parameters
+    ///
+    /// - `pattern`: The pattern to add.
+    pub fn include(mut self, pattern: Pattern) -> Self {
+        self.include.push(pattern);
+        self
+    }
+
+    /// Add a pattern of signals to exclude.
+    ///
+    /// # Parameters
+    ///
+    /// - `pattern`: The pattern to add.
+    pub fn exclude(mut self, pattern: Pattern) -> Self {
+        self.exclude.push(pattern);
+        self
+    }
+
+    /// Query whether a signal is selected.
+    ///
+    /// # Parameters
+    ///
+    /// - `name`: The signal name.
+    pub fn matches(&self, name: &str) -> bool {
+        (self.include.is_empty() || self.include.iter().any(|p| p.matches(name)))
+            && !self.exclude.iter().any(|p| p.matches(name))
+    }
+
+    /// Obtain the Ids of the selected Wires of a Simulation, in Id order.
+    ///
+    /// # Parameters
+    ///
+    /// - `sim`: The Simulation whose Wires are selected from.
+    pub fn select(&self, sim: &Simulation) -> Vec<WireId> {
+        sim.wires()
+            .filter(|id| sim.wire(*id).is_ok_and(|wire| self.matches(wire.name())))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::time::SimDuration;
+    use crate::wire::{Wire, WirePull};
+
+    #[test]
+    fn pattern_glob() {
+        // GIVEN glob patterns
+        let single = Pattern::Glob("cpu.*.d?".to_string());
+        let deep = Pattern::Glob("cpu.**".to_string());
+        // WHEN they are matched against names
+        // THEN single stars stay within a hierarchy level and double stars cross levels
+        assert!(single.matches("cpu.bus.d0"));
+        assert!(!single.matches("cpu.bus.d10"));
+        assert!(!single.matches("cpu.a.b.d0"));
+        assert!(deep.matches("cpu.a.b.d0"));
+        assert!(!deep.matches("gpu.a"));
+    }
+    #[test]
+    fn pattern_scope() {
+        // GIVEN a scope pattern
+        let scope = Pattern::Scope("cpu.bus".to_string());
+        // WHEN it is matched against names
+        // THEN only the scope itself and signals beneath it match
+        assert!(scope.matches("cpu.bus"));
+        assert!(scope.matches("cpu.bus.d0"));
+        assert!(!scope.matches("cpu.busy"));
+        assert!(!scope.matches("cpu"));
+    }
+    #[test]
+    fn selector_parse() {
+        // GIVEN a selector configuration with each kind of pattern
+        let text = "# data lines\nregex:\\.d[0-3]$\nscope:cpu.ctl\n!cpu.ctl.debug*\n\n";
+        // WHEN it is parsed
+        let selector = SignalSelector::parse(text).unwrap();
+        // THEN signals are selected by the include patterns, less the exclude patterns
+        assert!(selector.matches("cpu.bus.d2"));
+        assert!(!selector.matches("cpu.bus.d4"));
+        assert!(selector.matches("cpu.ctl.rw"));
+        assert!(!selector.matches("cpu.ctl.debug_en"));
+        assert!(SignalSelector::parse("regex:(").is_err());
+    }
+    #[test]
+    fn selector_select() {
+        // GIVEN a Simulation with several wires
+        let mut sim = Simulation::new(SimDuration::from_ticks(10));
+        for name in ["cpu.clk", "cpu.bus.d0", "cpu.bus.d1", "led"] {
+            sim.add_wire(Wire::new(name, WirePull::Down)).unwrap();
+        }
+        // WHEN wires are selected with and without include patterns
+        let everything = SignalSelector::new().exclude(Pattern::Glob("led".to_string()));
+        let bus = SignalSelector::new().include(Pattern::Scope("cpu.bus".to_string()));
+        // THEN the matching wire Ids are obtained
+        assert_eq!(
+            [0, 1, 2].map(WireId::from).to_vec(),
+            everything.select(&sim)
+        );
+        assert_eq!([1, 2].map(WireId::from).to_vec(), bus.select(&sim));
+    }
+}
@@ -0,0 +1,414 @@
+//! A small backtracking regular expression matcher for signal names.
+
+/// A character class: a set of character ranges, possibly negated.
+#[derive(Debug, Clone, PartialEq)]
+struct Class {
+    /// Inclusive ranges of characters in the class.
+    ranges: Vec<(char, char)>,
+    /// Whether the class matches characters outside the ranges instead.
+    negated: bool,
+}
+
+impl Class {
+    /// Query whether a character is matched by the class.
+    fn matches(&self, c: char) -> bool {
+        self.ranges.iter().any(|(lo, hi)| (*lo..=*hi).contains(&c)) != self.negated
+    }
+}
+
+/// A node of a parsed regular expression.
+#[derive(Debug, Clone, PartialEq)]
+enum Node {
+    /// A literal character.
+    Char(char),
+    /// Any character.
+    Any,
+    /// A character class.
+    Class(Class),
+    /// The start of the text.
+    Start,
+    /// The end of the text.
+    End,
+    /// A choice between sequences of nodes.
+    Alt(Vec<Vec<Node>>),
+    /// A node repeated between a minimum and an optional maximum number of times.
+    Repeat(Box<Node>, usize, Option<usize>),
+}
+
+/// A compiled regular expression.
+///
+/// The supported syntax is a common subset of POSIX extended and Perl syntax: literals, `.`, bracketed classes with
+/// ranges and negation, the escapes `\d`, `\w` and `\s` and their negations, anchors `^` and `$`, groups, alternation
+/// with `|`, and the quantifiers `*`, `+`, `?`, `{n}`, `{n,}` and `{n,m}`.  Quantifiers are greedy.
+///
+/// # Example
+///
+/// ```
+/// # use rvfs_sim_core::select::Regex;
+/// let regex = Regex::new(r"^cpu\.d[0-7]$").unwrap();
+///
+/// assert!(regex.is_match("cpu.d3"));
+/// assert!(!regex.is_match("cpu.d8"));
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Regex {
+    /// The pattern text.
+    pattern: String,
+    /// The parsed pattern.
+    root: Node,
+}
+
+impl Regex {
+    /// Compile a regular expression.
+    ///
+    /// # Parameters
+    ///
+    /// - `pattern`: The regular expression text.
+    pub fn new(pattern: &str) -> Result<Self, String> {
+        let mut parser = Parser {
+            chars: pattern.chars().collect(),
+            pos: 0,
+        };
+        let root = parser.alternation()?;
+        if parser.pos < parser.chars.len() {
+            return Err(format!("Unbalanced ')' in regular expression: {pattern}"));
+        }
+        Ok(Self {
+            pattern: pattern.to_string(),
+            root,
+        })
+    }
+
+    /// Obtain the pattern text.
+    pub fn as_str(&self) -> &str {
+        &self.pattern
+    }
+
+    /// Query whether the regular expression matches anywhere within some text.
+    ///
+    /// # Parameters
+    ///
+    /// - `text`: The text to search.
+    pub fn is_match(&self, text: &str) -> bool {
+        let chars: Vec<char> = text.chars().collect();
+        (0..=chars.len()).any(|start| matches(&self.root, &chars, start, &mut |_| true))
+    }
+}
+
+/// Match a node at a position, passing the end position of each way it matches to a continuation until one succeeds.
+fn matches(node: &Node, chars: &[char], pos: usize, k: &mut dyn FnMut(usize) -> bool) -> bool {
+    match node {
+        Node::Char(c) => chars.get(pos) == Some(c) && k(pos + 1),
+        Node::Any => pos < chars.len() && k(pos + 1),
+        Node::Class(class) => chars.get(pos).is_some_and(|c| class.matches(*c)) && k(pos + 1),
+        Node::Start => pos == 0 && k(pos),
+        Node::End => pos == chars.len() && k(pos),
+        Node::Alt(branches) => branches
+            .iter()
+            .any(|branch| matches_sequence(branch, chars, pos, k)),
+        Node::Repeat(inner, min, max) => matches_repeat(inner, *min, *max, 0, chars, pos, k),
+    }
+}
+
+/// Match a sequence of nodes at a position.
+fn matches_sequence(
+    nodes: &[Node],
+    chars: &[char],
+    pos: usize,
+    k: &mut dyn FnMut(usize) -> bool,
+) -> bool {
+    match nodes.split_first() {
+        None => k(pos),
+        Some((first, rest)) => matches(first, chars, pos, &mut |next| {
+            matches_sequence(rest, chars, next, k)
+        }),
+    }
+}
+
+/// Match further repetitions of a node at a position, having already matched `count` repetitions.
+fn matches_repeat(
+    inner: &Node,
+    min: usize,
+    max: Option<usize>,
+    count: usize,
+    chars: &[char],
+    pos: usize,
+    k: &mut dyn FnMut(usize) -> bool,
+) -> bool {
+    if max.is_none_or(|max| count < max) {
+        // Repetitions which consume nothing are only worthwhile to reach the minimum, and would otherwise never end.
+        let repeated = matches(inner, chars, pos, &mut |next| {
+            (next != pos || count < min)
+                && matches_repeat(inner, min, max, count + 1, chars, next, k)
+        });
+        if repeated {
+            return true;
+        }
+    }
+    count >= min && k(pos)
+}
+
+/// Recursive descent parser for regular expressions.
+struct Parser {
+    /// Characters of the pattern.
+    chars: Vec<char>,
+    /// Position of the next character to parse.
+    pos: usize,
+}
+
+impl Parser {
+    /// Obtain the next character without consuming it.
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    /// Consume the next character.
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek();
+        self.pos += 1;
+        c
+    }
+
+    /// Build an error describing a problem at the present position.
+    fn error(&self, problem: &str) -> String {
+        let pattern: String = self.chars.iter().collect();
+        format!(
+            "{problem} at position {} in regular expression: {pattern}",
+            self.pos
+        )
+    }
+
+    /// Parse alternatives separated by `|`.
+    fn alternation(&mut self) -> Result<Node, String> {
+        let mut branches = vec![self.sequence()?];
+        while self.peek() == Some('|') {
+            self.pos += 1;
+            branches.push(self.sequence()?);
+        }
+        Ok(Node::Alt(branches))
+    }
+
+    /// Parse a sequence of quantified atoms, up to the end of the alternative.
+    fn sequence(&mut self) -> Result<Vec<Node>, String> {
+        let mut nodes = Vec::new();
+        while let Some(c) = self.peek() {
+            if c == '|' || c == ')' {
+                break;
+            }
+            let atom = self.atom()?;
+            nodes.push(self.quantified(atom)?);
+        }
+        Ok(nodes)
+    }
+
+    /// Parse any quantifiers following an atom.
+    fn quantified(&mut self, mut node: Node) -> Result<Node, String> {
+        loop {
+            let (min, max) = match self.peek() {
+                Some('*') => (0, None),
+                Some('+') => (1, None),
+                Some('?') => (0, Some(1)),
+                Some('{') => {
+                    self.pos += 1;
+                    let bounds = self.bounds()?;
+                    node = Node::Repeat(Box::new(node), bounds.0, bounds.1);
+                    continue;
+                }
+                _ => return Ok(node),
+            };
+            if matches!(node, Node::Start | Node::End) {
+                return Err(self.error("Nothing to repeat"));
+            }
+            self.pos += 1;
+            node = Node::Repeat(Box::new(node), min, max);
+        }
+    }
+
+    /// Parse the bounds of a `{n}`, `{n,}` or `{n,m}` quantifier, following the opening brace.
+    fn bounds(&mut self) -> Result<(usize, Option<usize>), String> {
+        let start = self.pos;
+        while self.peek().is_some_and(|c| c != '}') {
+            self.pos += 1;
+        }
+        if self.next() != Some('}') {
+            return Err(self.error("Unterminated repetition"));
+        }
+        let text: String = self.chars[start..self.pos - 1].iter().collect();
+        let number = |text: &str| {
+            text.trim()
+                .parse::<usize>()
+                .map_err(|_| self.error("Malformed repetition"))
+        };
+        let (min, max) = match text.split_once(',') {
+            None => (number(&text)?, Some(number(&text)?)),
+            Some((min, max)) if max.trim().is_empty() => (number(min)?, None),
+            Some((min, max)) => (number(min)?, Some(number(max)?)),
+        };
+        if max.is_some_and(|max| max < min) {
+            return Err(self.error("Repetition bounds out of order"));
+        }
+        Ok((min, max))
+    }
+
+    /// Parse a single atom.
+    fn atom(&mut self) -> Result<Node, String> {
+        match self.next() {
+            Some('(') => {
+                // Non-capturing group syntax is accepted; no groups capture.
+                if self.chars[self.pos..].starts_with(&['?', ':']) {
+                    self.pos += 2;
+                }
+                let node = self.alternation()?;
+                if self.next() != Some(')') {
+                    return Err(self.error("Unbalanced '('"));
+                }
+                Ok(node)
+            }
+            Some('.') => Ok(Node::Any),
+            Some('^') => Ok(Node::Start),
+            Some('$') => Ok(Node::End),
+            Some('[') => self.class().map(Node::Class),
+            Some('\\') => match self.next() {
+                Some(c) => Ok(match perl_class(c) {
+                    Some(class) => Node::Class(class),
+                    None => Node::Char(c),
+                }),
+                None => Err(self.error("Trailing backslash")),
+            },
+            Some('*' | '+' | '?' | '{') => {
+                self.pos -= 1;
+                Err(self.error("Nothing to repeat"))
+            }
+            Some(c) => Ok(Node::Char(c)),
+            None => Err(self.error("Unexpected end")),
+        }
+    }
+
+    /// Parse a bracketed character class, following the opening bracket.
+    fn class(&mut self) -> Result<Class, String> {
+        let negated = self.peek() == Some('^');
+        if negated {
+            self.pos += 1;
+        }
+
+        let mut ranges = Vec::new();
+        let mut first = true;
+        loop {
+            let c = match self.next() {
+                Some(']') if !first => break,
+                Some('\\') => match self.next() {
+                    Some(e) => match perl_class(e) {
+                        Some(class) if !class.negated => {
+                            ranges.extend(class.ranges);
+                            first = false;
+                            continue;
+                        }
+                        Some(_) => return Err(self.error("Negated class escape within a class")),
+                        None => e,
+                    },
+                    None => return Err(self.error("Trailing backslash")),
+                },
+                Some(c) => c,
+                None => return Err(self.error("Unterminated character class")),
+            };
+            first = false;
+
+            if self.peek() == Some('-') && self.chars.get(self.pos + 1).is_some_and(|c| *c != ']') {
+                self.pos += 1;
+                let hi = match self.next() {
+                    Some('\\') => self
+                        .next()
+                        .ok_or_else(|| self.error("Trailing backslash"))?,
+                    Some(hi) => hi,
+                    None => return Err(self.error("Unterminated character class")),
+                };
+                if hi < c {
+                    return Err(self.error("Character range out of order"));
+                }
+                ranges.push((c, hi));
+            } else {
+                ranges.push((c, c));
+            }
+        }
+
+        Ok(Class { ranges, negated })
+    }
+}
+
+/// Obtain the class for a Perl-style class escape such as `\d`, if the character names one.
+fn perl_class(c: char) -> Option<Class> {
+    let ranges = match c.to_ascii_lowercase() {
+        'd' => vec![('0', '9')],
+        'w' => vec![('0', '9'), ('A', 'Z'), ('_', '_'), ('a', 'z')],
+        's' => vec![(' ', ' '), ('\t', '\r')],
+        _ => return None,
+    };
+    Some(Class {
+        ranges,
+        negated: c.is_ascii_uppercase(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Compile a pattern and match it against some text.
+    fn is_match(pattern: &str, text: &str) -> bool {
+        Regex::new(pattern).unwrap().is_match(text)
+    }
+
+    #[test]
+    fn regex_literals_and_anchors() {
+        // GIVEN literal patterns, with and without anchors
+        // WHEN they are matched
+        // THEN unanchored patterns match anywhere and anchored patterns only at the ends
+        assert!(is_match("bus", "cpu.bus.d0"));
+        assert!(!is_match("^bus", "cpu.bus.d0"));
+        assert!(is_match("^cpu", "cpu.bus.d0"));
+        assert!(is_match("d0$", "cpu.bus.d0"));
+        assert!(!is_match("^d0$", "cpu.bus.d0"));
+        assert!(is_match("", "anything"));
+    }
+    #[test]
+    fn regex_classes() {
+        // GIVEN patterns with character classes
+        // WHEN they are matched
+        // THEN only characters within the classes match
+        assert!(is_match("^d[0-7]$", "d7"));
+        assert!(!is_match("^d[0-7]$", "d8"));
+        assert!(is_match("^d[^0-7]$", "d8"));
+        assert!(is_match(r"^\w+\.\d$", "bus_a.3"));
+        assert!(!is_match(r"^\S+$", "a b"));
+        assert!(is_match("^[]a]$", "]"));
+        assert!(is_match("^[a-]$", "-"));
+    }
+    #[test]
+    fn regex_quantifiers_and_groups() {
+        // GIVEN patterns with quantifiers, groups and alternation
+        // WHEN they are matched
+        // THEN repetition and choice behave as expected, including backtracking
+        assert!(is_match("^a*ab$", "aaab"));
+        assert!(is_match("^(ab)+$", "ababab"));
+        assert!(!is_match("^(ab)+$", "abba"));
+        assert!(is_match("^colou?r$", "color"));
+        assert!(is_match("^(?:clk|rst)_n$", "rst_n"));
+        assert!(is_match("^a{2,3}$", "aaa"));
+        assert!(!is_match("^a{2,3}$", "aaaa"));
+        assert!(is_match("^a{2,}$", "aaaaa"));
+        assert!(is_match("^(a*)*b$", "aaab"));
+    }
+    #[test]
+    fn regex_errors() {
+        // GIVEN malformed patterns
+        // WHEN they are compiled
+        // THEN an error is produced
+        assert!(Regex::new("(ab").is_err());
+        assert!(Regex::new("ab)").is_err());
+        assert!(Regex::new("*a").is_err());
+        assert!(Regex::new("[ab").is_err());
+        assert!(Regex::new("a{3,1}").is_err());
+        assert!(Regex::new("[z-a]").is_err());
+        assert!(Regex::new("ab\\").is_err());
+    }
+}
@@ -1,14 +1,45 @@
 //! The Simulation orchestrates the passage of simulated time and the transitions of states within the system.
 
+#[cfg(feature = "std")]
+use crate::control::{Control, SimController};
+use crate::diff::{State, StateDiff};
+use crate::element::Element;
+use crate::error::{ComponentKind, SimError};
+use crate::executor::{self, Executor, ReceiveError};
+use crate::ipin::InputPin;
 use crate::library::Library;
-use crate::wire::Wire;
-use crate::Id;
-use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
-use std::time::Duration;
-use threadpool::ThreadPool;
+use crate::metrics::Metrics;
+use crate::opin::{OutputPin, OutputPinState};
+use crate::prelude::*;
+use crate::profile::Profile;
+use crate::rng::SimRng;
+use crate::schedule::{Event, Schedule};
+#[cfg(feature = "serde")]
+use crate::snapshot::{ElementState, Snapshot, WireState};
+use crate::stimulus::Stimulus;
+use crate::time::SimDuration;
+use crate::trace::{Change, Logic, Tracer, DEFAULT_HIGH_THRESHOLD, DEFAULT_LOW_THRESHOLD};
+use crate::watch::{Breakpoint, Watch, WatchId};
+use crate::wire::{Wire, WireArena, WirePull, WireRef};
+use crate::wirevalue::{LogicValue, WireValue};
+use crate::{ComponentId, ElementId, Id, IdIter, InputPinId, Instant, OutputPinId, WireId};
+use alloc::collections::{BTreeMap, BTreeSet, VecDeque};
+use alloc::sync::Arc;
+use core::fmt;
+use core::time::Duration;
+#[cfg(feature = "serde")]
+use std::path::Path;
 
 /// Default timeout for all items in a simulation step phase to complete and send their results back to the Simulation.
-const DEFAULT_STEP_PHASE_TIMEOUT: Duration = Duration::from_millis(1000);
+pub const DEFAULT_STEP_PHASE_TIMEOUT: Duration = Duration::from_millis(1000);
+/// Suggested number of steps between automatic checkpoints, for [Simulation::set_checkpoints].
+pub const DEFAULT_CHECKPOINT_INTERVAL: u64 = 100;
+/// Suggested maximum number of automatic checkpoints to retain, for [Simulation::set_checkpoints].
+pub const DEFAULT_CHECKPOINT_LIMIT: usize = 16;
+/// Number of recent Wire value changes retained across all Wires to give context to step errors.
+const RECENT_CHANGE_LIMIT: usize = 256;
+/// Maximum number of recent value changes of the failing component included in a step error.
+const STEP_ERROR_CHANGES: usize = 5;
 
 /// A simulation result.
 #[derive(Debug, Copy, Clone, PartialEq)]
@@ -17,36 +48,299 @@ pub enum SimResult {
     Continuing,
     /// Simulation has completed.
     Finished,
+    /// Simulation has halted at a [breakpoint](Simulation::break_when), and can be resumed.
+    Breakpoint(WatchId),
+    /// Run has been stopped by one of the limits set on the Simulation.
+    Limit(Limit),
 }
 
-/// A result for a single simulation step.
+/// A limit on how far a Simulation runs, as a safeguard against runs which never finish.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Limit {
+    /// The simulation time has reached the [maximum](Simulation::set_max_time).
+    Time,
+    /// The run has taken the [maximum](Simulation::set_max_steps) number of steps.
+    Steps,
+    /// The run has taken the [maximum](Simulation::set_max_wall_clock) wall-clock time.
+    WallClock,
+}
+
+impl fmt::Display for Limit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Time => "time limit",
+            Self::Steps => "step limit",
+            Self::WallClock => "wall-clock limit",
+        })
+    }
+}
+
+/// The strategy by which a Simulation chooses the components to evaluate on each step.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub enum Scheduler {
+    /// Every InputPin, Element, OutputPin and Wire is evaluated on every step.
+    #[default]
+    TimeStepped,
+    /// Only the components whose inputs have changed, or whose propagating states fall due, are evaluated, from a
+    /// time-ordered queue of events.  Elements which are not [combinational](Element::is_combinational) are still
+    /// stepped on every step.  The results are the same as when time stepped, but a large circuit which is mostly idle
+    /// is simulated far faster.
+    EventDriven,
+}
+
+/// The thread pool implementation on which a Simulation runs the Elements of each step in parallel.
+#[cfg(feature = "std")]
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub enum ThreadBackend {
+    /// The pool of the `threadpool` crate.
+    #[default]
+    ThreadPool,
+    /// A pool of the `rayon` crate, which steals work between threads.
+    #[cfg(feature = "rayon")]
+    Rayon,
+}
+
+/// The phases of a Simulation step, in order of execution.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Phase {
+    /// Phase 1: InputPins sample their Wires.
+    InputPins,
+    /// Phase 2: Elements recalculate their outputs.
+    Elements,
+    /// Phase 3: Wires respond to their drivers.
+    Wires,
+}
+
+impl Phase {
+    /// Number of phases in a step.
+    pub const COUNT: usize = 3;
+    /// All phases, in order of execution.
+    pub const ALL: [Phase; Phase::COUNT] = [Phase::InputPins, Phase::Elements, Phase::Wires];
+}
+
+impl fmt::Display for Phase {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Phase::InputPins => "input_pins",
+            Phase::Elements => "elements",
+            Phase::Wires => "wires",
+        })
+    }
+}
+
+/// Failure of a Simulation step, with context to help diagnose it.
 #[derive(Debug, Clone, PartialEq)]
+pub struct StepError {
+    /// The failure, boxed to keep step results small.
+    pub error: Box<SimError>,
+    /// Simulation time at the start of the step which failed.
+    pub time: u64,
+    /// Phase of the step which failed, if the failure occurred within one.
+    pub phase: Option<Phase>,
+    /// Name of the component which failed, if known.
+    pub component: Option<String>,
+    /// Hierarchical path of the component which failed, formed from the dotted prefix of its name.
+    pub path: Option<String>,
+    /// Most recent value changes of the component which failed as pairs of time and change, oldest first.
+    pub recent_changes: Vec<(u64, Change)>,
+}
+
+impl fmt::Display for StepError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Step at time {} failed", self.time)?;
+        if let Some(phase) = self.phase {
+            write!(f, " in {phase} phase")?;
+        }
+        write!(f, ": {}", self.error)?;
+        if let Some(component) = &self.component {
+            write!(f, "\n  component: {component}")?;
+            if let Some(path) = &self.path {
+                write!(f, " (in {path})")?;
+            }
+        }
+        if !self.recent_changes.is_empty() {
+            write!(f, "\n  recent changes:")?;
+            for (time, change) in &self.recent_changes {
+                write!(
+                    f,
+                    "\n    {time}: {} -> {}",
+                    f32::from(change.previous),
+                    f32::from(change.value)
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl core::error::Error for StepError {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        Some(self.error.as_ref())
+    }
+}
+
+/// A result for a single simulation step.
+#[derive(Debug)]
 enum StepResult {
-    /// The result of a simulation step for a single Wire.
-    Wire(Result<SimResult, String>, Wire),
-    /// The result of a simulation step for a single Element.
-    Element(Result<SimResult, String> /* TODO: , Element */),
+    /// The result of a simulation step for a single Element, with its OutputPins and the wall-clock time taken if
+    /// profiling.
+    Element(
+        Result<SimResult, String>,
+        ElementId,
+        Box<dyn Element>,
+        Vec<OutputPin>,
+        Option<Duration>,
+    ),
+}
+
+/// A component of a Simulation to which a step error can be attributed.
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum Component {
+    /// A Wire, by Id.
+    Wire(WireId),
+    /// An Element, by Id.
+    Element(ElementId),
+}
+
+/// A saved copy of the Simulation state from which stepping can be resumed.
+#[derive(Debug, Clone)]
+struct Checkpoint {
+    /// Simulation time at which the checkpoint was taken.
+    time: u64,
+    /// Copy of the Wires at the checkpoint time.
+    wires: WireArena,
+    /// Copy of the Elements at the checkpoint time.
+    elements: Library<Box<dyn Element>, ElementId>,
+    /// Copy of the InputPins at the checkpoint time.
+    input_pins: Library<InputPin, InputPinId>,
+    /// Copy of the OutputPins at the checkpoint time.
+    output_pins: Library<OutputPin, OutputPinId>,
+    /// Copy of the pull forced on each Wire at the checkpoint time.
+    wire_forces: Vec<Option<WirePull>>,
+    /// Copy of the unknown flag of each Wire at the checkpoint time.
+    wire_unknown: Vec<bool>,
 }
 
 /// Top level representation of a simulation and executor of the simulation steps.
-#[derive(Debug)]
 pub struct Simulation {
     /// Time step size.
-    interval: u64,
+    interval: SimDuration,
     /// Present simulation time.
     time: u64,
+    /// Seed of the random number generator handed to Elements and other stochastic features.
+    seed: u64,
 
-    /// Thread pool for executing individual simulation step phases.
-    pool: ThreadPool,
-    /// Message passing FIFO sender to clone for passing results back to the Simulation.
-    sender: Sender<StepResult>,
-    /// Message passing FIFO receiver for the Simulation to obtain step phase results.
-    receiver: Receiver<StepResult>,
+    /// Executor for the individual items of simulation step phases, which passes their results back to the Simulation.
+    executor: Box<dyn Executor<StepResult>>,
+    /// Thread pool implementation used by the executor.
+    #[cfg(feature = "std")]
+    backend: ThreadBackend,
+    /// Number of threads requested for the executor, or 0 for one per CPU.
+    #[cfg(feature = "std")]
+    threads: usize,
     /// Maximum time to wait for all results of a step phase before raising an error.
     phase_timeout: Duration,
+    /// Simulation time at which runs stop, if any.
+    max_time: Option<u64>,
+    /// Number of steps after which each run stops, if any.
+    max_steps: Option<u64>,
+    /// Wall-clock time after which each run stops, if any.
+    max_wall_clock: Option<Duration>,
+    /// Strategy by which components are chosen for evaluation.
+    scheduler: Scheduler,
+    /// Components due for evaluation under the event-driven scheduler, or `None` if every component is to be evaluated
+    /// on the next step.
+    schedule: Option<Schedule>,
+
+    /// Values, pulls and time constants of all Wires that have been added to the Simulation, which are stepped every
+    /// step.
+    wires: WireArena,
+    /// Names of all Wires, indexed by Id, which are only needed for reporting and so are kept apart from the Wires.
+    wire_names: Vec<String>,
+    /// Id of each Wire present in the Simulation, keyed by name.
+    wire_ids: BTreeMap<String, WireId>,
+    /// Ids of the OutputPins driving each Wire, indexed by Wire Id.
+    wire_drivers: Vec<Vec<OutputPinId>>,
+    /// Pull forced on each Wire in place of its drivers, if any, indexed by Wire Id.
+    wire_forces: Vec<Option<WirePull>>,
+    /// Forces made through [force_wire](Self::force_wire) since the oldest checkpoint, with the times at which they
+    /// were made, so that they can be made again when the Simulation [steps back](Self::step_back).
+    forced: Vec<(u64, WireId, Option<WirePull>)>,
+    /// Forces to make on Wires at the steps of [stimulus](Self::add_stimulus) events, keyed by event time.
+    stimuli: BTreeMap<u64, Vec<(WireId, Option<WirePull>)>>,
+    /// Whether the drivers of each Wire drove it both high and low, or to an unknown level, at the most recent step,
+    /// indexed by Wire Id.
+    wire_unknown: Vec<bool>,
+    /// Ids of the Wires of each named bus, least significant bit first.
+    buses: BTreeMap<String, Vec<WireId>>,
+    /// Clock domain each Wire is tagged with, if any, indexed by Wire Id.
+    wire_domains: Vec<Option<String>>,
 
-    /// Collection of all Wires that have been added to the Simulation.
-    wires: Library<Wire>,
+    /// Collection of all Elements that have been added to the Simulation.
+    elements: Library<Box<dyn Element>, ElementId>,
+    /// Names of all Elements, indexed by Id, so Elements can be identified while checked out.
+    element_names: Vec<String>,
+    /// Id of each Element present in the Simulation, keyed by name.
+    element_ids: BTreeMap<String, ElementId>,
+    /// Ids of the InputPins and OutputPins of each Element, in the Element's order, indexed by Element Id.
+    element_pins: Vec<(Vec<InputPinId>, Vec<OutputPinId>)>,
+    /// Clock domain each Element is tagged with, if any, indexed by Element Id.
+    element_domains: Vec<Option<String>>,
+
+    /// Collection of all InputPins, created for each Element as it is added.
+    input_pins: Library<InputPin, InputPinId>,
+    /// Id of the Wire connected to each InputPin, if any, indexed by InputPin Id.
+    input_wires: Vec<Option<WireId>>,
+    /// Collection of all OutputPins, created for each Element as it is added.
+    output_pins: Library<OutputPin, OutputPinId>,
+    /// Id of the Wire connected to each OutputPin, if any, indexed by OutputPin Id.
+    output_wires: Vec<Option<WireId>>,
+
+    /// Number of steps between automatic checkpoints, or 0 if checkpointing is disabled.
+    checkpoint_interval: u64,
+    /// Maximum number of checkpoints to retain.
+    checkpoint_limit: usize,
+    /// Retained checkpoints, ordered from oldest to newest.
+    checkpoints: VecDeque<Checkpoint>,
+
+    /// Wire value changes made during the most recent step.
+    changes: Vec<Change>,
+    /// Most recent Wire value changes as pairs of time and change, oldest first.
+    recent_changes: VecDeque<(u64, Change)>,
+    /// Attached tracers, each paired with a flag indicating whether it has been started.
+    tracers: Vec<(Box<dyn Tracer>, bool)>,
+    /// Latest simulation time passed to the Tracers, which are not passed the steps repeated after stepping back until
+    /// the Simulation passes it again.
+    traced: u64,
+    /// Value of each Wire, indexed by Id, as last passed to the Tracers, if the Simulation has stepped back since.
+    traced_values: Option<Vec<Option<WireValue>>>,
+    /// Whether steps are being repeated to [step back](Self::step_back), so they are not observed again.
+    replaying: bool,
+    /// Breakpoints set on Wires, in the order they were set.
+    breakpoints: Vec<Breakpoint>,
+    /// Number of breakpoints ever set, from which the Id of the next one is made.
+    breakpoints_set: u64,
+    /// The run loop's end of the channel from its controllers, once one has been created.
+    #[cfg(feature = "std")]
+    control: Option<Control>,
+
+    /// Runtime metrics, shared with any exporters.
+    metrics: Arc<Metrics>,
+    /// Per-component wall-clock time, if profiling is enabled.
+    profile: Option<Profile>,
+}
+
+impl fmt::Debug for Simulation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Simulation")
+            .field("interval", &self.interval)
+            .field("time", &self.time)
+            .field("phase_timeout", &self.phase_timeout)
+            .field("wires", &self.wires)
+            .field("elements", &self.elements)
+            .field("tracers", &self.tracers.len())
+            .finish_non_exhaustive()
+    }
 }
 
 impl Simulation {
@@ -54,38 +348,133 @@ impl Simulation {
     ///
     /// # Parameters
     ///
-    /// - `interval`: Time to elapse for each step of the simulation in arbitrary time units.
+    /// - `interval`: Simulation time to elapse for each step.
     ///
     /// # Example
     ///
     /// ```
     /// # use rvfs_sim_core::sim::Simulation;
-    /// let sim = Simulation::new(10);
+    /// # use rvfs_sim_core::time::SimDuration;
+    /// let sim = Simulation::new(SimDuration::from_ticks(10));
     ///
     /// assert!(sim.is_empty());
     /// ```
-    pub fn new(interval: u64) -> Self {
-        assert_ne!(0, interval);
+    pub fn new(interval: SimDuration) -> Self {
+        assert!(!interval.is_zero());
 
-        let (sender, receiver) = mpsc::channel();
         Self {
             interval,
             time: 0,
+            seed: 0,
 
-            pool: ThreadPool::default(),
-            sender,
-            receiver,
+            #[cfg(feature = "std")]
+            executor: executor::create(ThreadBackend::default(), 0),
+            #[cfg(not(feature = "std"))]
+            executor: executor::create(),
+            #[cfg(feature = "std")]
+            backend: ThreadBackend::default(),
+            #[cfg(feature = "std")]
+            threads: 0,
             phase_timeout: DEFAULT_STEP_PHASE_TIMEOUT,
+            max_time: None,
+            max_steps: None,
+            max_wall_clock: None,
+            scheduler: Scheduler::TimeStepped,
+            schedule: None,
+
+            wires: WireArena::new(),
+            wire_names: Vec::new(),
+            wire_ids: BTreeMap::new(),
+            wire_drivers: Vec::new(),
+            wire_forces: Vec::new(),
+            forced: Vec::new(),
+            stimuli: BTreeMap::new(),
+            wire_unknown: Vec::new(),
+            buses: BTreeMap::new(),
+            wire_domains: Vec::new(),
 
-            wires: Library::new(),
+            elements: Library::new(),
+            element_names: Vec::new(),
+            element_ids: BTreeMap::new(),
+            element_pins: Vec::new(),
+            element_domains: Vec::new(),
+
+            input_pins: Library::new(),
+            input_wires: Vec::new(),
+            output_pins: Library::new(),
+            output_wires: Vec::new(),
+
+            checkpoint_interval: 0,
+            checkpoint_limit: 0,
+            checkpoints: VecDeque::new(),
+
+            changes: Vec::new(),
+            recent_changes: VecDeque::new(),
+            tracers: Vec::new(),
+            traced: 0,
+            traced_values: None,
+            replaying: false,
+            breakpoints: Vec::new(),
+            breakpoints_set: 0,
+            #[cfg(feature = "std")]
+            control: None,
+
+            metrics: Arc::new(Metrics::new()),
+            profile: None,
+        }
+    }
+
+    /// Obtain the present simulation time.
+    pub fn time(&self) -> u64 {
+        self.time
+    }
+
+    /// Obtain the seed of the Simulation's random number generator.
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Seed the Simulation's random number generator, from which all pseudo-random behaviour is derived, so that a run
+    /// can be reproduced exactly by giving it the same seed.
+    ///
+    /// A generator seeded afresh is handed to every Element already added, and to every Element added later.  The seed
+    /// is also recorded in the [metrics](Self::metrics), for run reports.  The default seed is 0.
+    ///
+    /// # Parameters
+    ///
+    /// - `seed`: The seed.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use rvfs_sim_core::sim::Simulation;
+    /// # use rvfs_sim_core::time::SimDuration;
+    /// let mut sim = Simulation::new(SimDuration::from_ticks(10));
+    /// sim.set_seed(1234);
+    ///
+    /// assert_eq!(1234, sim.metrics().seed());
+    /// assert_eq!(sim.rng().next_u64(), sim.rng().next_u64());
+    /// ```
+    pub fn set_seed(&mut self, seed: u64) {
+        self.seed = seed;
+        self.metrics.set_seed(seed);
+        let rng = self.rng();
+        for (_, element) in self.elements.iter_mut() {
+            element.set_rng(&rng);
         }
     }
 
+    /// Obtain a random number generator seeded with the Simulation's seed, for a stochastic feature to
+    /// [fork](SimRng::fork) a stream of its own from.
+    pub fn rng(&self) -> SimRng {
+        SimRng::new(self.seed)
+    }
+
     /// Query whether a Simulation has had any components added to it.
     ///
     /// A Simulation is empty if it has no Wires, Input/OutputPins, or Elements.
     pub fn is_empty(&self) -> bool {
-        self.wires.iter().count() == 0
+        self.wires().count() == 0 && self.elements.iter().count() == 0
     }
 
     /// Change the maximum time to wait for all results of a step phase before raising an error.
@@ -97,129 +486,2308 @@ impl Simulation {
         self.phase_timeout = timeout;
     }
 
-    /// Add a Wire to the Simulation.
-    ///
-    /// The Id in the successful result allows the Wire to be looked up later.
-    ///
-    /// # Parameters
-    ///
-    /// - `wire`: The Wire instance, which will be owned by the Simulation.
-    pub fn add_wire(&mut self, wire: Wire) -> Result<Id, String> {
-        Ok(self.wires.add(wire))
+    /// Limit runs to a simulation time, stopping them with a result of [SimResult::Limit] once it is reached.
+    ///
+    /// Like the other limits, this applies to [run](Self::run) and to [run_while](Self::run_while) and its bounded
+    /// variants, and is checked before each step, so a run which has reached it takes no more steps.
+    ///
+    /// # Parameters
+    ///
+    /// - `time`: Simulation time at which to stop, or `None` for no limit.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use rvfs_sim_core::sim::{Limit, SimResult, Simulation};
+    /// # use rvfs_sim_core::time::SimDuration;
+    /// # use rvfs_sim_core::wire::{Wire, WirePull};
+    /// let mut sim = Simulation::new(SimDuration::from_ticks(10));
+    /// sim.add_wire(Wire::new("/RESET", WirePull::Up)).unwrap();
+    /// sim.set_max_time(Some(1000));
+    ///
+    /// assert_eq!(Ok(SimResult::Limit(Limit::Time)), sim.run());
+    /// ```
+    pub fn set_max_time(&mut self, time: Option<u64>) {
+        self.max_time = time;
+    }
+
+    /// Limit each run to a number of steps, stopping it with a result of [SimResult::Limit] once they are taken.
+    ///
+    /// The steps are counted afresh by each call which runs the simulation.
+    ///
+    /// # Parameters
+    ///
+    /// - `steps`: Number of steps after which to stop, or `None` for no limit.
+    pub fn set_max_steps(&mut self, steps: Option<u64>) {
+        self.max_steps = steps;
+    }
+
+    /// Limit each run to a span of wall-clock time, stopping it with a result of [SimResult::Limit] once it has passed.
+    ///
+    /// The time is measured afresh by each call which runs the simulation, and includes any time spent paused by a
+    /// [controller](Self::controller).  Without the `std` feature there is no clock, and this limit is never reached.
+    ///
+    /// # Parameters
+    ///
+    /// - `duration`: Wall-clock time after which to stop, or `None` for no limit.
+    pub fn set_max_wall_clock(&mut self, duration: Option<Duration>) {
+        self.max_wall_clock = duration;
+    }
+
+    /// Set the number of threads on which the Elements of each step run in parallel, in place of one per CPU.
+    ///
+    /// Without threads, such as in WebAssembly, Elements always run one at a time, and this has no effect.
+    ///
+    /// # Parameters
+    ///
+    /// - `threads`: Number of threads, or 0 for one per CPU.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use rvfs_sim_core::sim::Simulation;
+    /// # use rvfs_sim_core::time::SimDuration;
+    /// let sim = Simulation::new(SimDuration::from_ticks(10)).with_threads(4);
+    ///
+    /// assert_eq!(4, sim.threads());
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn with_threads(mut self, threads: usize) -> Self {
+        self.threads = threads;
+        self.executor = executor::create(self.backend, threads);
+        self
+    }
+
+    /// Set the thread pool implementation on which the Elements of each step run in parallel.
+    ///
+    /// Without threads, such as in WebAssembly, Elements always run one at a time, and this has no effect.
+    ///
+    /// # Parameters
+    ///
+    /// - `backend`: The thread pool implementation.
+    #[cfg(feature = "std")]
+    pub fn with_backend(mut self, backend: ThreadBackend) -> Self {
+        self.backend = backend;
+        self.executor = executor::create(backend, self.threads);
+        self
+    }
+
+    /// Obtain the number of threads on which the Elements of each step run.
+    pub fn threads(&self) -> usize {
+        self.executor.threads()
+    }
+
+    /// Obtain the strategy by which components are chosen for evaluation on each step.
+    pub fn scheduler(&self) -> Scheduler {
+        self.scheduler
+    }
+
+    /// Change the strategy by which components are chosen for evaluation on each step.
+    ///
+    /// # Parameters
+    ///
+    /// - `scheduler`: The new strategy.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use rvfs_sim_core::sim::{Scheduler, Simulation};
+    /// # use rvfs_sim_core::time::SimDuration;
+    /// # use rvfs_sim_core::wire::{Wire, WirePull};
+    /// let mut sim = Simulation::new(SimDuration::from_ticks(10));
+    /// sim.add_wire(Wire::new("/RESET", WirePull::Up)).unwrap();
+    /// sim.set_scheduler(Scheduler::EventDriven);
+    ///
+    /// for _ in 0..10 {
+    ///     sim.step().unwrap();
+    /// }
+    ///
+    /// // The idle Wire is only stepped on the first step.
+    /// assert_eq!(1, sim.metrics().wires_stepped());
+    /// ```
+    pub fn set_scheduler(&mut self, scheduler: Scheduler) {
+        self.unschedule();
+        self.scheduler = scheduler;
+    }
+
+    /// Obtain a shared handle to the runtime metrics of the Simulation.
+    pub fn metrics(&self) -> Arc<Metrics> {
+        Arc::clone(&self.metrics)
+    }
+
+    /// Enable or disable profiling of the wall-clock time spent stepping each component.
+    ///
+    /// Enabling profiling when it is already enabled keeps the times accumulated so far.
+    ///
+    /// # Parameters
+    ///
+    /// - `enabled`: Whether to profile.
+    pub fn set_profiling(&mut self, enabled: bool) {
+        if !enabled {
+            self.profile = None;
+        } else if self.profile.is_none() {
+            self.profile = Some(Profile::new());
+        }
+    }
+
+    /// Obtain the per-component profile accumulated so far, if profiling is enabled.
+    pub fn profile(&self) -> Option<&Profile> {
+        self.profile.as_ref()
+    }
+
+    /// Obtain the Wire value changes made during the most recent step.
+    pub fn changes(&self) -> &[Change] {
+        &self.changes
+    }
+
+    /// Attach a Tracer which will be notified of the Wire value changes made during each step.
+    ///
+    /// # Parameters
+    ///
+    /// - `tracer`: The Tracer instance, which will be owned by the Simulation.
+    pub fn add_tracer(&mut self, tracer: Box<dyn Tracer>) {
+        self.tracers.push((tracer, false));
+    }
+
+    /// Notify all attached Tracers that tracing is complete.
+    pub fn finish_tracers(&mut self) -> Result<(), String> {
+        let mut tracers = core::mem::take(&mut self.tracers);
+        let result = tracers
+            .iter_mut()
+            .filter(|(_, started)| *started)
+            .try_for_each(|(tracer, _)| tracer.finish(self));
+        self.tracers = tracers;
+
+        result
+    }
+
+    /// Notify all Elements that the simulation is complete, so they can write out any state.
+    pub fn finish_elements(&mut self) -> Result<(), StepError> {
+        let failure = self
+            .elements
+            .iter_mut()
+            .find_map(|(id, element)| element.finish().err().map(|message| (id, message)));
+        if let Some((id, message)) = failure {
+            return Err(self.step_error(message.into(), None, Some(Component::Element(id))));
+        }
+
+        Ok(())
+    }
+
+    /// Configure the automatic checkpoints used to [step backwards](`Self::step_back`).
+    ///
+    /// Checkpointing is disabled until it is configured.  Each checkpoint holds a copy of every Wire, Element and pin,
+    /// and the forces made since the oldest checkpoint are retained, so frequent checkpoints of a large circuit are
+    /// costly in both time and memory.  [DEFAULT_CHECKPOINT_INTERVAL] and [DEFAULT_CHECKPOINT_LIMIT] suit interactive
+    /// use.
+    ///
+    /// # Parameters
+    ///
+    /// - `interval`: Number of steps between checkpoints.  A value of 0 disables checkpointing.
+    /// - `limit`: Maximum number of checkpoints to retain.  Older checkpoints are discarded first.
+    pub fn set_checkpoints(&mut self, interval: u64, limit: usize) {
+        self.checkpoint_interval = interval;
+        self.checkpoint_limit = limit;
+        while self.checkpoints.len() > limit {
+            self.checkpoints.pop_front();
+        }
+    }
+
+    /// Add a Wire to the Simulation.
+    ///
+    /// The Id in the successful result allows the Wire to be looked up later.  Each Wire in the Simulation must have a
+    /// different name.
+    ///
+    /// # Parameters
+    ///
+    /// - `wire`: The Wire instance, which will be owned by the Simulation.
+    pub fn add_wire(&mut self, wire: Wire) -> Result<WireId, SimError> {
+        if self.wire_ids.contains_key(wire.name()) {
+            return Err(SimError::DuplicateName(
+                ComponentKind::Wire,
+                wire.name().clone(),
+            ));
+        }
+
+        self.netlist_changed();
+        self.wire_names.push(wire.name().clone());
+        self.wire_drivers.push(Vec::new());
+        self.wire_forces.push(None);
+        self.wire_unknown.push(false);
+        self.wire_domains.push(None);
+        let id = self.wires.add(wire);
+        self.wire_ids.insert(self.wire_names[id.slot()].clone(), id);
+        Ok(id)
+    }
+
+    /// Look up a Wire by ID.
+    ///
+    /// # Parameters
+    ///
+    /// - `id`: The Id of the Wire which was returned when it was [added](`Self::add_wire`).
+    pub fn wire(&self, id: WireId) -> Result<WireRef<'_>, SimError> {
+        self.wire_names
+            .get(id.slot())
+            .and_then(|name| self.wires.view(id, name))
+            .ok_or(SimError::UnknownId(ComponentKind::Wire))
+    }
+
+    /// Look up the Id of a Wire by name.
+    ///
+    /// # Parameters
+    ///
+    /// - `name`: The name of the Wire.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use rvfs_sim_core::sim::Simulation;
+    /// # use rvfs_sim_core::time::SimDuration;
+    /// # use rvfs_sim_core::wire::{Wire, WirePull};
+    /// let mut sim = Simulation::new(SimDuration::from_ticks(10));
+    /// let reset = sim.add_wire(Wire::new("/RESET", WirePull::Up)).unwrap();
+    ///
+    /// assert_eq!(Ok(reset), sim.wire_by_name("/RESET"));
+    /// assert!(sim.wire_by_name("CLK").is_err());
+    /// assert!(sim.add_wire(Wire::new("/RESET", WirePull::Down)).is_err());
+    /// ```
+    pub fn wire_by_name(&self, name: &str) -> Result<WireId, SimError> {
+        self.wire_ids
+            .get(name)
+            .copied()
+            .ok_or_else(|| SimError::UnknownName(ComponentKind::Wire, name.to_string()))
+    }
+
+    /// Look up the Id of a Wire by its name, as `<ELEMENT>.<PIN>` by a pin connected to it, or as `<BUS>[<BIT>]` by its
+    /// bit of a [bus](Self::define_bus).
+    ///
+    /// # Parameters
+    ///
+    /// - `target`: The name of the Wire, the names of an Element and one of its pins separated by a dot, or the name
+    ///   of a bus and a bit in brackets.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use rvfs_sim_core::element::gates::{Gate, GateKind};
+    /// # use rvfs_sim_core::sim::Simulation;
+    /// # use rvfs_sim_core::time::SimDuration;
+    /// # use rvfs_sim_core::wire::{Wire, WirePull};
+    /// let mut sim = Simulation::new(SimDuration::from_ticks(10));
+    /// let a = sim.add_wire(Wire::new("A", WirePull::Up)).unwrap();
+    /// let u1 = sim.add_element(Box::new(Gate::new("U1", GateKind::Not, 1, 0).unwrap())).unwrap();
+    /// sim.connect_input(a, sim.input_pin(u1, "I0").unwrap()).unwrap();
+    ///
+    /// assert_eq!(Ok(a), sim.wire_by_target("A"));
+    /// assert_eq!(Ok(a), sim.wire_by_target("U1.I0"));
+    /// assert!(sim.wire_by_target("U1.Y").is_err());
+    ///
+    /// sim.define_bus("DATA", &[a]).unwrap();
+    /// assert_eq!(Ok(a), sim.wire_by_target("DATA[0]"));
+    /// ```
+    pub fn wire_by_target(&self, target: &str) -> Result<WireId, SimError> {
+        if target.ends_with(']') && !self.wire_ids.contains_key(target) {
+            return match self.bus(target)? {
+                [id] => Ok(*id),
+                _ => Err(SimError::InvalidArgument(format!(
+                    "\"{target}\" is not a single wire"
+                ))),
+            };
+        }
+        let Some((element, pin)) = target
+            .rsplit_once('.')
+            .filter(|_| !self.wire_ids.contains_key(target))
+        else {
+            return self.wire_by_name(target);
+        };
+
+        let element = self.element_by_name(element)?;
+        if let Ok(id) = self.input_pin(element, pin) {
+            self.input_wires[id.slot()].ok_or(SimError::NotConnected(ComponentKind::InputPin))
+        } else if let Ok(id) = self.output_pin(element, pin) {
+            self.output_wires[id.slot()].ok_or(SimError::NotConnected(ComponentKind::OutputPin))
+        } else {
+            Err(SimError::InvalidArgument(format!(
+                "No wire or pin named \"{target}\""
+            )))
+        }
+    }
+
+    /// Obtain an iterator over the Ids of all Wires in the Simulation.
+    pub fn wires(&self) -> IdIter<'_, WireId> {
+        self.wires.ids()
+    }
+
+    /// Obtain the logic value of a Wire, as read by the InputPins connected to it with the default thresholds.
+    ///
+    /// A Wire which nothing drives or pulls is high impedance, and one driven both high and low, or driven unknown by
+    /// an Element which cannot determine its output, at the most recent step is unknown, whatever their levels.
+    /// InputPins read either as unknown, so Elements which depend on them drive their own outputs unknown, and the
+    /// fault propagates rather than being hidden by a stale level.
+    ///
+    /// # Parameters
+    ///
+    /// - `id`: The Id of the Wire.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use rvfs_sim_core::sim::Simulation;
+    /// # use rvfs_sim_core::time::SimDuration;
+    /// # use rvfs_sim_core::wire::{Wire, WirePull};
+    /// # use rvfs_sim_core::wirevalue::LogicValue;
+    /// let mut sim = Simulation::new(SimDuration::from_ticks(10));
+    /// let reset = sim.add_wire(Wire::new("/RESET", WirePull::Up)).unwrap();
+    /// let data = sim.add_wire(Wire::new("D0", WirePull::None)).unwrap();
+    /// sim.step().unwrap();
+    ///
+    /// assert_eq!(Ok(LogicValue::High), sim.logic(reset));
+    /// assert_eq!(Ok(LogicValue::HighImpedance), sim.logic(data));
+    /// ```
+    pub fn logic(&self, id: WireId) -> Result<LogicValue, SimError> {
+        let wire = self.wire(id)?;
+        Ok(if self.wire_unknown[id.slot()] {
+            LogicValue::Unknown
+        } else if wire.pull() == WirePull::None {
+            LogicValue::HighImpedance
+        } else {
+            LogicValue::from_level(
+                wire.measure(),
+                DEFAULT_LOW_THRESHOLD,
+                DEFAULT_HIGH_THRESHOLD,
+            )
+        })
+    }
+
+    /// Query whether a Wire is driven or pulled without conflict or unknown drivers, so that its level can be read.
+    ///
+    /// # Parameters
+    ///
+    /// - `id`: The Id of the Wire.
+    fn is_driven(&self, id: WireId) -> bool {
+        !self.wire_unknown[id.slot()] && self.wires.pull(id) != WirePull::None
+    }
+
+    /// Force a Wire to feel a pull regardless of the OutputPins driving it, or release it back to its drivers.
+    ///
+    /// The force takes effect from the next step.  It is undone when the Simulation [steps back](Self::step_back) to
+    /// before it was made, and made again when stepping back replays the steps after it.
+    ///
+    /// # Parameters
+    ///
+    /// - `id`: The Id of the Wire.
+    /// - `pull`: The pull to force, or `None` to release the Wire.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use rvfs_sim_core::sim::Simulation;
+    /// # use rvfs_sim_core::time::SimDuration;
+    /// # use rvfs_sim_core::wire::{Wire, WirePull};
+    /// let mut sim = Simulation::new(SimDuration::from_ticks(10));
+    /// let id = sim.add_wire(Wire::new("/RESET", WirePull::Up)).unwrap();
+    /// sim.force_wire(id, Some(WirePull::Down)).unwrap();
+    /// sim.step().unwrap();
+    ///
+    /// assert_eq!(0.0, f32::from(sim.wire(id).unwrap().measure()));
+    /// ```
+    pub fn force_wire(&mut self, id: WireId, pull: Option<WirePull>) -> Result<(), SimError> {
+        self.apply_force(id, pull)?;
+        if self.checkpoint_interval != 0 && self.checkpoint_limit != 0 {
+            self.forced.push((self.time, id, pull));
+        }
+
+        Ok(())
+    }
+
+    /// Force a Wire, as for [force_wire](Self::force_wire), without noting the force to be made again when stepping
+    /// back.
+    ///
+    /// # Parameters
+    ///
+    /// - `id`: The Id of the Wire.
+    /// - `pull`: The pull to force, or `None` to release the Wire.
+    fn apply_force(&mut self, id: WireId, pull: Option<WirePull>) -> Result<(), SimError> {
+        self.wire(id)?;
+        let force = self
+            .wire_forces
+            .get_mut(id.slot())
+            .ok_or(SimError::UnknownId(ComponentKind::Wire))?;
+        *force = pull;
+        self.wires.set_pull(id, pull.unwrap_or(WirePull::None));
+        if let Some(schedule) = &mut self.schedule {
+            schedule.add(self.time, Event::Wire(id));
+        }
+
+        Ok(())
+    }
+
+    /// Add a [Stimulus], whose events force their Wires at the start of the steps in which their times fall.
+    ///
+    /// Every target is looked up as by [wire_by_target](Self::wire_by_target) before any event is added, so a Stimulus
+    /// naming an unknown Wire or an unconnected pin is refused whole.  Events whose times have already passed never
+    /// take effect.
+    ///
+    /// # Parameters
+    ///
+    /// - `stimulus`: The Stimulus to add.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use rvfs_sim_core::sim::Simulation;
+    /// # use rvfs_sim_core::stimulus::Stimulus;
+    /// # use rvfs_sim_core::time::SimDuration;
+    /// # use rvfs_sim_core::wire::{Wire, WirePull};
+    /// let mut sim = Simulation::new(SimDuration::from_ticks(10));
+    /// let reset = sim.add_wire(Wire::new("/RESET", WirePull::Up)).unwrap();
+    /// sim.add_stimulus(&Stimulus::parse("0, /RESET, low\n30, /RESET, release").unwrap()).unwrap();
+    ///
+    /// sim.run_for(3).unwrap();
+    /// assert_eq!(0.0, f32::from(sim.wire(reset).unwrap().measure()));
+    /// sim.step().unwrap();
+    /// assert_eq!(1.0, f32::from(sim.wire(reset).unwrap().measure()));
+    /// ```
+    pub fn add_stimulus(&mut self, stimulus: &Stimulus) -> Result<(), SimError> {
+        let events = stimulus
+            .events()
+            .iter()
+            .map(|event| Ok((event.time, self.wire_by_target(&event.target)?, event.force)))
+            .collect::<Result<Vec<_>, SimError>>()?;
+        for (time, id, force) in events {
+            self.stimuli.entry(time).or_default().push((id, force));
+        }
+
+        Ok(())
+    }
+
+    /// Force the Wires of the [stimulus](Self::add_stimulus) events falling within the present step.
+    fn apply_stimuli(&mut self) -> Result<(), SimError> {
+        let end = self.time.saturating_add(self.interval.ticks());
+        let forces: Vec<_> = self
+            .stimuli
+            .range(self.time..end)
+            .flat_map(|(_, forces)| forces.iter().copied())
+            .collect();
+        forces
+            .into_iter()
+            .try_for_each(|(id, force)| self.apply_force(id, force))
+    }
+
+    /// Define a named bus of Wires, so that they can be read and driven together as an integer.
+    ///
+    /// Wherever a bus is named, a range of its bits can be given instead, as `<BUS>[<MSB>:<LSB>]` or `<BUS>[<BIT>]`.
+    ///
+    /// # Parameters
+    ///
+    /// - `name`: Name of the bus, which must be unique and must not contain brackets.
+    /// - `wires`: Ids of the Wires, least significant bit first, of which there must be from 1 to 64.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use rvfs_sim_core::sim::Simulation;
+    /// # use rvfs_sim_core::time::SimDuration;
+    /// # use rvfs_sim_core::wire::{Wire, WirePull};
+    /// let mut sim = Simulation::new(SimDuration::from_ticks(10));
+    /// let wires: Vec<_> = (0..8)
+    ///     .map(|bit| sim.add_wire(Wire::new(&format!("D{bit}"), WirePull::None)).unwrap())
+    ///     .collect();
+    /// sim.define_bus("DATA", &wires).unwrap();
+    ///
+    /// sim.drive_bus("DATA", 0xa5).unwrap();
+    /// sim.step().unwrap();
+    /// assert_eq!(Ok(0xa5), sim.read_bus("DATA"));
+    /// assert_eq!(Ok(0xa), sim.read_bus("DATA[7:4]"));
+    /// ```
+    pub fn define_bus(&mut self, name: &str, wires: &[WireId]) -> Result<(), SimError> {
+        if self.buses.contains_key(name) {
+            return Err(SimError::DuplicateBus(name.to_string()));
+        }
+        if !(1..=64).contains(&wires.len()) {
+            return Err(SimError::Bus {
+                name: name.to_string(),
+                reason: "must have from 1 to 64 wires".to_string(),
+            });
+        }
+        if name.contains(['[', ']']) {
+            return Err(SimError::Bus {
+                name: name.to_string(),
+                reason: "name must not contain brackets".to_string(),
+            });
+        }
+        for id in wires {
+            self.wire(*id)?;
+        }
+
+        self.buses.insert(name.to_string(), wires.to_vec());
+        Ok(())
+    }
+
+    /// Look up the Ids of the Wires of a named bus, or of a range of its bits, least significant bit first.
+    ///
+    /// # Parameters
+    ///
+    /// - `name`: Name of the bus, optionally followed by a range of bits as `[<MSB>:<LSB>]` or `[<BIT>]`.
+    pub fn bus(&self, name: &str) -> Result<&[WireId], SimError> {
+        let (bus, range) = match name.strip_suffix(']').and_then(|name| name.split_once('[')) {
+            Some((bus, range)) => (bus, Some(range)),
+            None => (name, None),
+        };
+        let wires = self
+            .buses
+            .get(bus)
+            .ok_or_else(|| SimError::UnknownBus(bus.to_string()))?;
+        let Some(range) = range else {
+            return Ok(wires);
+        };
+
+        let invalid = || SimError::Bus {
+            name: bus.to_string(),
+            reason: format!("invalid bit range [{range}] for {} wires", wires.len()),
+        };
+        let (msb, lsb) = range.split_once(':').unwrap_or((range, range));
+        let bit = |bit: &str| bit.trim().parse::<usize>().map_err(|_| invalid());
+        let (msb, lsb) = (bit(msb)?, bit(lsb)?);
+        if lsb > msb || msb >= wires.len() {
+            return Err(invalid());
+        }
+        Ok(&wires[lsb..=msb])
+    }
+
+    /// Obtain the names of the buses and the Ids of their Wires, least significant bit first, in order of name.
+    pub fn buses(&self) -> impl Iterator<Item = (&str, &[WireId])> {
+        self.buses
+            .iter()
+            .map(|(name, wires)| (name.as_str(), wires.as_slice()))
+    }
+
+    /// Read the value of a named bus, from the logic levels of its Wires.
+    ///
+    /// Levels are classified with the same default thresholds as InputPins, and the read fails if any Wire is between
+    /// them.
+    ///
+    /// # Parameters
+    ///
+    /// - `name`: Name of the bus.
+    pub fn read_bus(&self, name: &str) -> Result<u64, SimError> {
+        let mut value = 0;
+        for (bit, id) in self.bus(name)?.iter().enumerate() {
+            let wire = self.wire(*id)?;
+            match Logic::from_level(
+                wire.measure(),
+                DEFAULT_LOW_THRESHOLD,
+                DEFAULT_HIGH_THRESHOLD,
+            ) {
+                Logic::High => value |= 1 << bit,
+                Logic::Low => (),
+                Logic::Unknown => {
+                    return Err(SimError::Bus {
+                        name: name.to_string(),
+                        reason: format!("wire \"{}\" is indeterminate", wire.name()),
+                    })
+                }
+            }
+        }
+        Ok(value)
+    }
+
+    /// Drive a value onto a named bus, by [forcing](Self::force_wire) each of its Wires up or down from the next step.
+    ///
+    /// # Parameters
+    ///
+    /// - `name`: Name of the bus.
+    /// - `value`: The value, which must fit in the width of the bus.
+    pub fn drive_bus(&mut self, name: &str, value: u64) -> Result<(), SimError> {
+        let wires = self.bus(name)?.to_vec();
+        if wires.len() < 64 && value >> wires.len() != 0 {
+            return Err(SimError::Bus {
+                name: name.to_string(),
+                reason: format!("value {value:#x} does not fit in {} bits", wires.len()),
+            });
+        }
+        for (bit, id) in wires.into_iter().enumerate() {
+            let pull = if value & (1 << bit) != 0 {
+                WirePull::Up
+            } else {
+                WirePull::Down
+            };
+            self.force_wire(id, Some(pull))?;
+        }
+        Ok(())
+    }
+
+    /// Release a named bus driven by [drive_bus](Self::drive_bus) back to the drivers of its Wires.
+    ///
+    /// # Parameters
+    ///
+    /// - `name`: Name of the bus.
+    pub fn release_bus(&mut self, name: &str) -> Result<(), SimError> {
+        for id in self.bus(name)?.to_vec() {
+            self.force_wire(id, None)?;
+        }
+        Ok(())
+    }
+
+    /// Tag a Wire with the clock domain its signal belongs to, or remove its tag.
+    ///
+    /// Wires need only be tagged where their domain cannot be inferred from the Elements driving them, such as for
+    /// inputs from outside the Simulation.  Tags are checked by the [clock-domain crossing checker](crate::cdc).
+    ///
+    /// # Parameters
+    ///
+    /// - `id`: The Id of the Wire.
+    /// - `domain`: Name of the clock domain, or `None` to remove the tag.
+    pub fn set_wire_domain(&mut self, id: WireId, domain: Option<&str>) -> Result<(), SimError> {
+        self.wire(id)?;
+        self.wire_domains[id.slot()] = domain.map(str::to_string);
+        Ok(())
+    }
+
+    /// Obtain the clock domain a Wire is tagged with, if any.
+    ///
+    /// # Parameters
+    ///
+    /// - `id`: The Id of the Wire.
+    pub fn wire_domain(&self, id: WireId) -> Result<Option<&str>, SimError> {
+        self.wire(id)?;
+        Ok(self.wire_domains[id.slot()].as_deref())
+    }
+
+    /// Add an Element to the Simulation, along with the InputPins and OutputPins it declares.
+    ///
+    /// The Id in the successful result allows the Element and its pins to be looked up later.  Each Element in the
+    /// Simulation must have a different name, as must each of its InputPins and each of its OutputPins.
+    ///
+    /// # Parameters
+    ///
+    /// - `element`: The Element instance, which will be owned by the Simulation.
+    pub fn add_element(&mut self, mut element: Box<dyn Element>) -> Result<ElementId, SimError> {
+        let name = element.name().to_string();
+        if self.element_ids.contains_key(&name) {
+            return Err(SimError::DuplicateName(ComponentKind::Element, name));
+        }
+        let input_pins = element.input_pins();
+        let output_pins = element.output_pins();
+        let input_names: Vec<&String> = input_pins.iter().map(InputPin::name).collect();
+        let output_names: Vec<&String> = output_pins.iter().map(OutputPin::name).collect();
+        for (kind, names) in [
+            (ComponentKind::InputPin, input_names),
+            (ComponentKind::OutputPin, output_names),
+        ] {
+            if let Some(pin) = names
+                .iter()
+                .enumerate()
+                .find_map(|(i, pin)| names[..i].contains(pin).then_some(pin))
+            {
+                return Err(SimError::DuplicateName(kind, format!("{name}.{pin}")));
+            }
+        }
+
+        self.netlist_changed();
+        element.set_rng(&self.rng());
+        let inputs = input_pins
+            .into_iter()
+            .map(|pin| {
+                let id = self.input_pins.add(pin);
+                place(&mut self.input_wires, id.slot(), None);
+                id
+            })
+            .collect();
+        let outputs = output_pins
+            .into_iter()
+            .map(|pin| {
+                let id = self.output_pins.add(pin);
+                place(&mut self.output_wires, id.slot(), None);
+                id
+            })
+            .collect();
+        let id = self.elements.add(element);
+        place(&mut self.element_pins, id.slot(), (inputs, outputs));
+        place(&mut self.element_names, id.slot(), name.clone());
+        place(&mut self.element_domains, id.slot(), None);
+        self.element_ids.insert(name, id);
+        Ok(id)
+    }
+
+    /// Look up an Element by Id.
+    ///
+    /// # Parameters
+    ///
+    /// - `id`: The Id of the Element which was returned when it was [added](`Self::add_element`).
+    pub fn element(&self, id: ElementId) -> Result<&dyn Element, SimError> {
+        self.elements
+            .inspect(id)
+            .as_deref()
+            .ok_or(SimError::UnknownId(ComponentKind::Element))
+    }
+
+    /// Obtain the slot of an Element in the tables indexed by slot, refusing the Id of a removed Element even if its
+    /// slot has been reused.
+    ///
+    /// # Parameters
+    ///
+    /// - `id`: The Id of the Element.
+    fn element_slot(&self, id: ElementId) -> Result<Id, SimError> {
+        match self.elements.id(id.slot()) {
+            Some(present) if present == id => Ok(id.slot()),
+            _ => Err(SimError::UnknownId(ComponentKind::Element)),
+        }
+    }
+
+    /// Look up the Id of an Element by name.
+    ///
+    /// # Parameters
+    ///
+    /// - `name`: The name of the Element.
+    pub fn element_by_name(&self, name: &str) -> Result<ElementId, SimError> {
+        self.element_ids
+            .get(name)
+            .copied()
+            .ok_or_else(|| SimError::UnknownName(ComponentKind::Element, name.to_string()))
+    }
+
+    /// Obtain an iterator over the Ids of all Elements in the Simulation.
+    pub fn elements(&self) -> IdIter<'_, ElementId> {
+        self.elements.iter()
+    }
+
+    /// Tag a sequential Element with the clock domain it is clocked in, or remove its tag.
+    ///
+    /// Untagged Elements are taken to be combinational, passing on the domains of their inputs.  Tags are checked by
+    /// the [clock-domain crossing checker](crate::cdc).
+    ///
+    /// # Parameters
+    ///
+    /// - `id`: The Id of the Element.
+    /// - `domain`: Name of the clock domain, or `None` to remove the tag.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use rvfs_sim_core::element::flipflops::DFlipFlop;
+    /// # use rvfs_sim_core::sim::Simulation;
+    /// # use rvfs_sim_core::time::SimDuration;
+    /// let mut sim = Simulation::new(SimDuration::from_ticks(10));
+    /// let ff = sim.add_element(Box::new(DFlipFlop::new("U1", 0))).unwrap();
+    /// sim.set_element_domain(ff, Some("sys")).unwrap();
+    ///
+    /// assert_eq!(Ok(Some("sys")), sim.element_domain(ff));
+    /// ```
+    pub fn set_element_domain(
+        &mut self,
+        id: ElementId,
+        domain: Option<&str>,
+    ) -> Result<(), SimError> {
+        let slot = self.element_slot(id)?;
+        self.element_domains[slot] = domain.map(str::to_string);
+        Ok(())
+    }
+
+    /// Obtain the clock domain an Element is tagged with, if any.
+    ///
+    /// # Parameters
+    ///
+    /// - `id`: The Id of the Element.
+    pub fn element_domain(&self, id: ElementId) -> Result<Option<&str>, SimError> {
+        Ok(self.element_domains[self.element_slot(id)?].as_deref())
+    }
+
+    /// Obtain the Ids of the Wires connected to an Element's InputPins, in the Element's order, with `None` for an
+    /// unconnected pin.
+    ///
+    /// # Parameters
+    ///
+    /// - `element`: The Id of the Element.
+    pub fn input_wires(&self, element: ElementId) -> Result<Vec<Option<WireId>>, SimError> {
+        let (inputs, _) = &self.element_pins[self.element_slot(element)?];
+        Ok(inputs
+            .iter()
+            .map(|pin| self.input_wires[pin.slot()])
+            .collect())
+    }
+
+    /// Obtain the Ids of the Wires connected to an Element's OutputPins, in the Element's order, with `None` for an
+    /// unconnected pin.
+    ///
+    /// # Parameters
+    ///
+    /// - `element`: The Id of the Element.
+    pub fn output_wires(&self, element: ElementId) -> Result<Vec<Option<WireId>>, SimError> {
+        let (_, outputs) = &self.element_pins[self.element_slot(element)?];
+        Ok(outputs
+            .iter()
+            .map(|pin| self.output_wires[pin.slot()])
+            .collect())
+    }
+
+    /// Look up the Id of an Element's InputPin by name.
+    ///
+    /// # Parameters
+    ///
+    /// - `element`: The Id of the Element.
+    /// - `name`: The name of the pin.
+    pub fn input_pin(&self, element: ElementId, name: &str) -> Result<InputPinId, SimError> {
+        let (inputs, _) = &self.element_pins[self.element_slot(element)?];
+        inputs
+            .iter()
+            .copied()
+            .find(|id| {
+                self.input_pins
+                    .inspect(*id)
+                    .as_ref()
+                    .is_some_and(|pin| pin.name() == name)
+            })
+            .ok_or(SimError::UnknownPin {
+                element: self.element_names[element.slot()].clone(),
+                kind: ComponentKind::InputPin,
+                pin: name.to_string(),
+            })
+    }
+
+    /// Look up the Id of an Element's OutputPin by name.
+    ///
+    /// # Parameters
+    ///
+    /// - `element`: The Id of the Element.
+    /// - `name`: The name of the pin.
+    pub fn output_pin(&self, element: ElementId, name: &str) -> Result<OutputPinId, SimError> {
+        let (_, outputs) = &self.element_pins[self.element_slot(element)?];
+        outputs
+            .iter()
+            .copied()
+            .find(|id| {
+                self.output_pins
+                    .inspect(*id)
+                    .as_ref()
+                    .is_some_and(|pin| pin.name() == name)
+            })
+            .ok_or(SimError::UnknownPin {
+                element: self.element_names[element.slot()].clone(),
+                kind: ComponentKind::OutputPin,
+                pin: name.to_string(),
+            })
+    }
+
+    /// Look up the Id of an InputPin by the names of its Element and of the pin.
+    ///
+    /// # Parameters
+    ///
+    /// - `element`: The name of the Element.
+    /// - `name`: The name of the pin.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use rvfs_sim_core::element::gates::{Gate, GateKind};
+    /// # use rvfs_sim_core::sim::Simulation;
+    /// # use rvfs_sim_core::time::SimDuration;
+    /// let mut sim = Simulation::new(SimDuration::from_ticks(10));
+    /// let u1 = sim.add_element(Box::new(Gate::new("U1", GateKind::Nand, 2, 0).unwrap())).unwrap();
+    ///
+    /// assert_eq!(sim.input_pin(u1, "I1"), sim.input_pin_by_name("U1", "I1"));
+    /// assert_eq!(sim.output_pin(u1, "Y"), sim.output_pin_by_name("U1", "Y"));
+    /// assert!(sim.input_pin_by_name("U2", "I1").is_err());
+    /// ```
+    pub fn input_pin_by_name(&self, element: &str, name: &str) -> Result<InputPinId, SimError> {
+        self.input_pin(self.element_by_name(element)?, name)
+    }
+
+    /// Look up the Id of an OutputPin by the names of its Element and of the pin.
+    ///
+    /// # Parameters
+    ///
+    /// - `element`: The name of the Element.
+    /// - `name`: The name of the pin.
+    pub fn output_pin_by_name(&self, element: &str, name: &str) -> Result<OutputPinId, SimError> {
+        self.output_pin(self.element_by_name(element)?, name)
+    }
+
+    /// Obtain the Ids and names of all of an Element's OutputPins, in the order the Element declares them.
+    ///
+    /// # Parameters
+    ///
+    /// - `element`: The Id of the Element.
+    pub fn output_pins(&self, element: ElementId) -> Result<Vec<(OutputPinId, &str)>, SimError> {
+        let (_, outputs) = &self.element_pins[self.element_slot(element)?];
+        Ok(outputs
+            .iter()
+            .filter_map(|id| {
+                self.output_pins
+                    .inspect(*id)
+                    .as_ref()
+                    .map(|pin| (*id, pin.name().as_str()))
+            })
+            .collect())
+    }
+
+    /// Query the state an OutputPin is presently driving.
+    ///
+    /// # Parameters
+    ///
+    /// - `pin`: The Id of the OutputPin.
+    pub fn output_pin_state(&self, pin: OutputPinId) -> Result<OutputPinState, SimError> {
+        self.output_pins
+            .inspect(pin)
+            .as_ref()
+            .map(OutputPin::state)
+            .ok_or(SimError::UnknownId(ComponentKind::OutputPin))
+    }
+
+    /// Connect a Wire to an InputPin, so that the pin samples the Wire on every step.
+    ///
+    /// An InputPin can be connected to only one Wire, but a Wire can be connected to any number of InputPins.
+    ///
+    /// # Parameters
+    ///
+    /// - `wire`: The Id of the Wire.
+    /// - `pin`: The Id of the InputPin, as [looked up](`Self::input_pin`) from its Element.
+    pub fn connect_input(&mut self, wire: WireId, pin: InputPinId) -> Result<(), SimError> {
+        self.wire(wire)?;
+        match self.input_wires.get(pin.slot()) {
+            _ if self.input_pins.is_removed(pin) => {
+                Err(SimError::UnknownId(ComponentKind::InputPin))
+            }
+            None => Err(SimError::UnknownId(ComponentKind::InputPin)),
+            Some(Some(_)) => Err(SimError::AlreadyConnected(ComponentKind::InputPin)),
+            Some(None) => {
+                self.netlist_changed();
+                self.input_wires[pin.slot()] = Some(wire);
+                Ok(())
+            }
+        }
+    }
+
+    /// Connect an OutputPin to a Wire, so that the pin drives the Wire on every step.
+    ///
+    /// A Wire can be connected to any number of OutputPins, so that tri-state drivers can share a bus, but only one of
+    /// them may drive it at a time.  The others must be in the [HighImpedance](OutputPinState::HighImpedance) state,
+    /// and a step in which pins drive the Wire both high and low fails.
+    ///
+    /// # Parameters
+    ///
+    /// - `pin`: The Id of the OutputPin, as [looked up](`Self::output_pin`) from its Element.
+    /// - `wire`: The Id of the Wire.
+    pub fn connect_output(&mut self, pin: OutputPinId, wire: WireId) -> Result<(), SimError> {
+        self.wire(wire)?;
+        match self.output_wires.get(pin.slot()) {
+            _ if self.output_pins.is_removed(pin) => {
+                return Err(SimError::UnknownId(ComponentKind::OutputPin))
+            }
+            None => return Err(SimError::UnknownId(ComponentKind::OutputPin)),
+            Some(Some(_)) => return Err(SimError::AlreadyConnected(ComponentKind::OutputPin)),
+            Some(None) => (),
+        }
+
+        self.netlist_changed();
+        self.output_wires[pin.slot()] = Some(wire);
+        self.wire_drivers[wire.slot()].push(pin);
+        Ok(())
+    }
+
+    /// Disconnect an InputPin from its Wire, leaving the pin holding the state it last sampled.
+    ///
+    /// # Parameters
+    ///
+    /// - `pin`: The Id of the InputPin.
+    pub fn disconnect_input(&mut self, pin: InputPinId) -> Result<(), SimError> {
+        match self.input_wires.get(pin.slot()) {
+            Some(Some(_)) if !self.input_pins.is_removed(pin) => {
+                self.netlist_changed();
+                self.input_wires[pin.slot()] = None;
+                Ok(())
+            }
+            Some(None) => Err(SimError::NotConnected(ComponentKind::InputPin)),
+            _ => Err(SimError::UnknownId(ComponentKind::InputPin)),
+        }
+    }
+
+    /// Disconnect an OutputPin from its Wire, so that it no longer drives the Wire.
+    ///
+    /// # Parameters
+    ///
+    /// - `pin`: The Id of the OutputPin.
+    pub fn disconnect_output(&mut self, pin: OutputPinId) -> Result<(), SimError> {
+        match self.output_wires.get(pin.slot()) {
+            Some(Some(wire)) if !self.output_pins.is_removed(pin) => {
+                let wire = *wire;
+                self.netlist_changed();
+                self.output_wires[pin.slot()] = None;
+                self.wire_drivers[wire.slot()].retain(|driver| *driver != pin);
+                Ok(())
+            }
+            Some(None) => Err(SimError::NotConnected(ComponentKind::OutputPin)),
+            _ => Err(SimError::UnknownId(ComponentKind::OutputPin)),
+        }
+    }
+
+    /// Remove a Wire from the Simulation.
+    ///
+    /// The Wire must first be disconnected from every pin and left out of every bus.  The Ids of the remaining Wires do
+    /// not change, and the Id of the removed Wire is not reused.
+    ///
+    /// # Parameters
+    ///
+    /// - `id`: The Id of the Wire.
+    pub fn remove_wire(&mut self, id: WireId) -> Result<(), SimError> {
+        let name = self.wire(id)?.name();
+        let pins = self
+            .input_wires
+            .iter()
+            .filter(|wire| **wire == Some(id))
+            .count()
+            + self.wire_drivers[id.slot()].len();
+        if pins > 0 {
+            return Err(SimError::StillConnected {
+                wire: name.clone(),
+                pins,
+            });
+        }
+        if let Some(bus) = self.buses.iter().find(|(_, wires)| wires.contains(&id)) {
+            return Err(SimError::InBus {
+                wire: name.clone(),
+                bus: bus.0.clone(),
+            });
+        }
+
+        self.netlist_changed();
+        self.wire_ids.remove(&self.wire_names[id.slot()]);
+        self.breakpoints.retain(|breakpoint| breakpoint.wire != id);
+        for forces in self.stimuli.values_mut() {
+            forces.retain(|(wire, _)| *wire != id);
+        }
+        self.forced.retain(|(_, wire, _)| *wire != id);
+        self.wires.remove(id);
+        self.wire_forces[id.slot()] = None;
+        self.wire_domains[id.slot()] = None;
+        Ok(())
+    }
+
+    /// Remove an Element from the Simulation, disconnecting and removing its pins, and [finish](Element::finish) it.
+    ///
+    /// The Ids of the remaining Elements and pins do not change.  The slots of those removed are reused by the next
+    /// Elements and pins added, under new Ids, so that the Id of a removed Element or pin is refused rather than
+    /// referring to the one which takes its place.  Along with [adding](Self::add_wire) Wires and Elements and
+    /// connecting them, this allows a circuit to be patched between steps, such as while a run is paused by a
+    /// [controller](Self::controller), without disturbing the state of the rest of the circuit.  Every change to the
+    /// circuit discards the checkpoints taken so far, as the Simulation can only [step back](Self::step_back) through
+    /// steps taken with the circuit as it now is.
+    ///
+    /// # Parameters
+    ///
+    /// - `id`: The Id of the Element.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use rvfs_sim_core::element::gates::{Gate, GateKind};
+    /// # use rvfs_sim_core::sim::Simulation;
+    /// # use rvfs_sim_core::time::SimDuration;
+    /// # use rvfs_sim_core::wire::{Wire, WirePull};
+    /// let mut sim = Simulation::new(SimDuration::from_ticks(10));
+    /// let a = sim.add_wire(Wire::new("A", WirePull::Down)).unwrap();
+    /// let y = sim.add_wire(Wire::new("Y", WirePull::None)).unwrap();
+    /// let u1 = sim.add_element(Box::new(Gate::new("U1", GateKind::Not, 1, 0).unwrap())).unwrap();
+    /// sim.connect_input(a, sim.input_pin(u1, "I0").unwrap()).unwrap();
+    /// sim.connect_output(sim.output_pin(u1, "Y").unwrap(), y).unwrap();
+    /// sim.step().unwrap();
+    ///
+    /// // Swap the inverter for an AND gate with its inputs tied together, which buffers its input.
+    /// sim.remove_element(u1).unwrap();
+    /// let u2 = sim.add_element(Box::new(Gate::new("U2", GateKind::And, 2, 0).unwrap())).unwrap();
+    /// sim.connect_input(a, sim.input_pin(u2, "I0").unwrap()).unwrap();
+    /// sim.connect_input(a, sim.input_pin(u2, "I1").unwrap()).unwrap();
+    /// sim.connect_output(sim.output_pin(u2, "Y").unwrap(), y).unwrap();
+    /// sim.step().unwrap();
+    /// sim.step().unwrap();
+    ///
+    /// assert!(sim.element(u1).is_err());
+    /// assert_eq!(0.0, f32::from(sim.wire(y).unwrap().measure()));
+    /// ```
+    pub fn remove_element(&mut self, id: ElementId) -> Result<(), SimError> {
+        self.element(id)?;
+        let (inputs, outputs) = self.element_pins[id.slot()].clone();
+        for pin in inputs {
+            self.input_wires[pin.slot()] = None;
+            self.input_pins.remove(pin)?;
+        }
+        for pin in outputs {
+            if let Some(wire) = self.output_wires[pin.slot()].take() {
+                self.wire_drivers[wire.slot()].retain(|driver| *driver != pin);
+            }
+            self.output_pins.remove(pin)?;
+        }
+
+        self.netlist_changed();
+        self.element_ids.remove(&self.element_names[id.slot()]);
+        self.element_domains[id.slot()] = None;
+        Ok(self.elements.remove(id)?.finish()?)
+    }
+
+    /// Discard every checkpoint and the event schedule, as the circuit has changed since they were made.
+    fn netlist_changed(&mut self) {
+        self.checkpoints.clear();
+        self.forced.clear();
+        self.unschedule();
+    }
+
+    /// Discard the event schedule, so that every component is evaluated on the next step, first bringing every
+    /// OutputPin up to the present time.
+    fn unschedule(&mut self) {
+        self.catch_up_all();
+        self.schedule = None;
+    }
+
+    /// Step every OutputPin left behind by the event schedule up to the present time.
+    fn catch_up_all(&mut self) {
+        let Some(schedule) = &mut self.schedule else {
+            return;
+        };
+        for (id, pin) in self.output_pins.iter_mut() {
+            let elapsed = schedule.advance(id, self.time);
+            if !elapsed.is_zero() {
+                pin.step(elapsed);
+            }
+        }
+    }
+
+    /// Step an OutputPin left behind by the event schedule up to a time, by all the time elapsed since it was last
+    /// stepped.
+    ///
+    /// # Parameters
+    ///
+    /// - `id`: The Id of the OutputPin.
+    /// - `time`: Time up to which to step the pin.
+    fn catch_up(&mut self, id: OutputPinId, time: u64) {
+        let Some(schedule) = &mut self.schedule else {
+            return;
+        };
+        let elapsed = schedule.advance(id, time);
+        if let Some(pin) = self.output_pins.inspect_mut(id) {
+            if !elapsed.is_zero() {
+                pin.step(elapsed);
+            }
+        }
+    }
+
+    /// Create a handle with which other threads can [control](crate::control) the Simulation while it runs.
+    ///
+    /// Controllers are served between the steps of a [run](Self::run), and every controller created shares the same
+    /// channel into the run loop.
+    #[cfg(feature = "std")]
+    pub fn controller(&mut self) -> SimController {
+        self.control.get_or_insert_with(Control::new).controller()
+    }
+
+    /// Run the simulation.
+    ///
+    /// Begin stepping the components of the simulation.  Running the simulation consumes the Simulation instance.  The
+    /// simulation will run forever unless some component eventually returns a result of [SimResult::Finished], a
+    /// [breakpoint](Self::break_when) halts it, a [limit](Self::set_max_time) is reached, or a
+    /// [controller](Self::controller) stops it.
+    pub fn run(mut self) -> Result<SimResult, StepError> {
+        let mut result = Ok(SimResult::Finished);
+        if !self.is_empty() {
+            let start = Instant::now();
+            let mut steps = 0;
+            loop {
+                #[cfg(feature = "std")]
+                if let Some(mut control) = self.control.take() {
+                    let running = control.serve(&mut self);
+                    self.control = Some(control);
+                    if !running {
+                        result = Ok(SimResult::Finished);
+                        break;
+                    }
+                }
+                if let Some(limit) = self.limit_reached(steps, start) {
+                    result = Ok(SimResult::Limit(limit));
+                    break;
+                }
+                result = self.step();
+                steps += 1;
+                if let Ok(SimResult::Continuing) = result {
+                    continue;
+                } else {
+                    break;
+                }
+            }
+        }
+        self.finish_elements()?;
+        self.finish_tracers()
+            .map_err(|message| self.step_error(message.into(), None, None))?;
+
+        result
+    }
+
+    /// Step the simulation for as long as a condition holds, returning control with the Simulation intact.
+    ///
+    /// The condition is checked before each step, so a run can be paused by the condition and resumed by calling
+    /// again.  Stepping stops early with a result of [SimResult::Finished] if some component finishes the simulation,
+    /// or of [SimResult::Breakpoint] if a [breakpoint](Self::break_when) halts it, or of [SimResult::Limit] if a
+    /// [limit](Self::set_max_time) is reached, and otherwise the result is
+    /// [SimResult::Continuing].  Unlike [run](Self::run), the Elements and Tracers are not
+    /// finished, and [controllers](Self::controller) are not served.
+    ///
+    /// # Parameters
+    ///
+    /// - `condition`: Function of the Simulation which returns whether to take another step.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use rvfs_sim_core::sim::{SimResult, Simulation};
+    /// # use rvfs_sim_core::time::SimDuration;
+    /// # use rvfs_sim_core::wire::{Wire, WirePull};
+    /// let mut sim = Simulation::new(SimDuration::from_ticks(10));
+    /// let reset = sim.add_wire(Wire::new("/RESET", WirePull::Up)).unwrap();
+    /// sim.force_wire(reset, Some(WirePull::Down)).unwrap();
+    ///
+    /// let result = sim.run_while(|sim| f32::from(sim.wire(reset).unwrap().measure()) > 0.5);
+    ///
+    /// assert_eq!(Ok(SimResult::Continuing), result);
+    /// assert_eq!(10, sim.time());
+    /// ```
+    pub fn run_while<F>(&mut self, mut condition: F) -> Result<SimResult, StepError>
+    where
+        F: FnMut(&Simulation) -> bool,
+    {
+        let start = Instant::now();
+        let mut steps = 0;
+        while condition(self) {
+            if let Some(limit) = self.limit_reached(steps, start) {
+                return Ok(SimResult::Limit(limit));
+            }
+            match self.step()? {
+                SimResult::Continuing => (),
+                result => return Ok(result),
+            }
+            steps += 1;
+        }
+
+        Ok(SimResult::Continuing)
+    }
+
+    /// Obtain the first of the limits set on the Simulation which a run has reached, if any.
+    ///
+    /// # Parameters
+    ///
+    /// - `steps`: Number of steps the run has taken.
+    /// - `start`: Wall-clock instant at which the run started.
+    fn limit_reached(&self, steps: u64, start: Instant) -> Option<Limit> {
+        if self.max_time.is_some_and(|time| self.time >= time) {
+            Some(Limit::Time)
+        } else if self.max_steps.is_some_and(|max| steps >= max) {
+            Some(Limit::Steps)
+        } else if self
+            .max_wall_clock
+            .is_some_and(|duration| start.elapsed() >= duration)
+        {
+            Some(Limit::WallClock)
+        } else {
+            None
+        }
+    }
+
+    /// Step the simulation a number of times, returning control with the Simulation intact, as for
+    /// [run_while](Self::run_while).
+    ///
+    /// # Parameters
+    ///
+    /// - `steps`: Number of steps to take.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use rvfs_sim_core::sim::Simulation;
+    /// # use rvfs_sim_core::time::SimDuration;
+    /// # use rvfs_sim_core::wire::{Wire, WirePull};
+    /// let mut sim = Simulation::new(SimDuration::from_ticks(10));
+    /// sim.add_wire(Wire::new("/RESET", WirePull::Up)).unwrap();
+    /// sim.run_for(3).unwrap();
+    /// sim.run_for(2).unwrap();
+    ///
+    /// assert_eq!(50, sim.time());
+    /// ```
+    pub fn run_for(&mut self, steps: u64) -> Result<SimResult, StepError> {
+        let mut remaining = steps;
+        self.run_while(|_| {
+            let more = remaining > 0;
+            remaining = remaining.saturating_sub(1);
+            more
+        })
+    }
+
+    /// Step the simulation until it reaches a time, returning control with the Simulation intact, as for
+    /// [run_while](Self::run_while).
+    ///
+    /// The simulation stops at the first step boundary at or after the time, and does not step at all if it is already
+    /// there.
+    ///
+    /// # Parameters
+    ///
+    /// - `time`: Simulation time to reach.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use rvfs_sim_core::sim::Simulation;
+    /// # use rvfs_sim_core::time::SimDuration;
+    /// # use rvfs_sim_core::wire::{Wire, WirePull};
+    /// let mut sim = Simulation::new(SimDuration::from_ticks(10));
+    /// sim.add_wire(Wire::new("/RESET", WirePull::Up)).unwrap();
+    /// sim.run_until(25).unwrap();
+    ///
+    /// assert_eq!(30, sim.time());
+    /// ```
+    pub fn run_until(&mut self, time: u64) -> Result<SimResult, StepError> {
+        self.run_while(|sim| sim.time() < time)
+    }
+
+    /// Set a breakpoint which halts the simulation when a Wire changes in a way of interest.
+    ///
+    /// After each step, the Wire is compared with how it stood after the step before, and if the change matches the
+    /// watch, the step returns a result of [SimResult::Breakpoint] with the Id of the breakpoint.  This ends a
+    /// [run](Self::run) or any of its bounded variants, from which the simulation can be resumed by running again.  If
+    /// several breakpoints match in the same step, the result names the one set first.
+    ///
+    /// # Parameters
+    ///
+    /// - `wire`: The Id of the Wire to watch.
+    /// - `watch`: The change at which to halt.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use rvfs_sim_core::element::clocks::ClockGenerator;
+    /// # use rvfs_sim_core::sim::{SimResult, Simulation};
+    /// # use rvfs_sim_core::time::SimDuration;
+    /// # use rvfs_sim_core::watch::Watch;
+    /// # use rvfs_sim_core::wire::{Wire, WirePull};
+    /// let mut sim = Simulation::new(SimDuration::from_ticks(10));
+    /// let clock = sim
+    ///     .add_element(Box::new(ClockGenerator::new("X1", 40.0, 0.5, 0.0, 0, 0.0).unwrap()))
+    ///     .unwrap();
+    /// let clk = sim.add_wire(Wire::new("CLK", WirePull::Down)).unwrap();
+    /// sim.connect_output(sim.output_pin(clock, "CLK").unwrap(), clk)
+    ///     .unwrap();
+    /// let falling = sim.break_when(clk, Watch::Falls(0.5)).unwrap();
+    ///
+    /// assert_eq!(Ok(SimResult::Breakpoint(falling)), sim.run_for(100));
+    /// assert_eq!(30, sim.time());
+    /// assert_eq!(Ok(SimResult::Breakpoint(falling)), sim.run_for(100));
+    /// assert_eq!(70, sim.time());
+    /// ```
+    pub fn break_when(&mut self, wire: WireId, watch: Watch) -> Result<WatchId, SimError> {
+        let present = (self.wire(wire)?.measure(), self.logic(wire)?);
+        let id = WatchId::from(self.breakpoints_set);
+        self.breakpoints_set += 1;
+        self.breakpoints
+            .push(Breakpoint::new(id, wire, watch, present));
+        Ok(id)
+    }
+
+    /// Remove a breakpoint set by [break_when](Self::break_when).
+    ///
+    /// # Parameters
+    ///
+    /// - `id`: The Id of the breakpoint.
+    pub fn remove_breakpoint(&mut self, id: WatchId) -> Result<(), SimError> {
+        let count = self.breakpoints.len();
+        self.breakpoints.retain(|breakpoint| breakpoint.id != id);
+        if self.breakpoints.len() == count {
+            return Err(SimError::InvalidArgument(format!("No breakpoint {id}")));
+        }
+        Ok(())
+    }
+
+    /// Advance the simulation by one time step.
+    ///
+    /// The result is [SimResult::Finished] if some component finishes the simulation, and otherwise
+    /// [SimResult::Breakpoint] if a [breakpoint](Self::break_when) matches the step.
+    ///
+    /// With the `tracing` feature, each step is a `step` span holding a `phase` span for each of its phases, in which
+    /// Elements being checked out and in, phase timeouts and failures are logged as events.
+    pub fn step(&mut self) -> Result<SimResult, StepError> {
+        #[cfg(feature = "tracing")]
+        let _span =
+            tracing::debug_span!("step", time = self.time, replaying = self.replaying).entered();
+        self.checkpoint();
+        if !self.replaying {
+            self.start_tracers()
+                .map_err(|message| self.step_error(message.into(), None, None))?;
+            self.metrics.begin_step();
+        }
+        self.changes.clear();
+
+        let mut result = self.timed(Phase::InputPins, |sim| sim.step_input_pins());
+        if let Ok(SimResult::Continuing) = result {
+            result = self.timed(Phase::Elements, |sim| sim.step_elements());
+            if let Ok(SimResult::Continuing) = result {
+                result = self.timed(Phase::Wires, |sim| sim.step_wires());
+            }
+        }
+
+        // NOTE: may make these debug-only later
+        self.elements
+            .audit()
+            .and_then(|_| self.output_pins.audit())
+            .map_err(|message| self.step_error(message, None, None))?;
+
+        self.time += self.interval.ticks();
+        if self.scheduler == Scheduler::EventDriven && self.schedule.is_none() && result.is_ok() {
+            self.make_schedule();
+        }
+        if self.replaying {
+            return result;
+        }
+        self.metrics.end_step(self.time, self.changes.len() as u64);
+        for change in &self.changes {
+            if self.recent_changes.len() >= RECENT_CHANGE_LIMIT {
+                self.recent_changes.pop_front();
+            }
+            self.recent_changes.push_back((self.time, *change));
+        }
+        self.record_tracers()
+            .map_err(|message| self.step_error(message.into(), None, None))?;
+        if let Some(id) = self.check_breakpoints() {
+            if let Ok(SimResult::Continuing) = result {
+                result = Ok(SimResult::Breakpoint(id));
+            }
+        }
+
+        result
+    }
+
+    /// Make the event schedule after a step in which every component was evaluated, noting the components due on the
+    /// next step.
+    fn make_schedule(&mut self) {
+        let combinational: Vec<Option<(ElementId, bool)>> = (0..self.element_names.len())
+            .map(|slot| {
+                let id = self.elements.id(slot)?;
+                self.elements
+                    .inspect(id)
+                    .as_ref()
+                    .map(|element| (id, element.is_combinational()))
+            })
+            .collect();
+        let element_inputs: Vec<Vec<InputPinId>> = self
+            .element_pins
+            .iter()
+            .map(|(inputs, _)| inputs.clone())
+            .collect();
+        let mut schedule = Schedule::new(
+            self.interval,
+            self.time,
+            &combinational,
+            &element_inputs,
+            &self.input_wires,
+            self.wire_names.len(),
+            self.output_wires.len(),
+        );
+
+        for change in &self.changes {
+            schedule.wire_changed(change.id, self.time);
+        }
+        // Every InputPin samples once more, as any of them may have changed, or seen its Wire start or stop being
+        // driven.
+        for id in self.input_pins.iter() {
+            schedule.step_input(id);
+        }
+        for id in self.output_pins.iter() {
+            if let Some(pin) = self.output_pins.inspect(id) {
+                schedule.pin_driven(id, pin);
+            }
+        }
+        self.schedule = Some(schedule);
+    }
+
+    /// Execute a step phase, recording the wall-clock time it takes.
+    ///
+    /// # Parameters
+    ///
+    /// - `phase`: The phase being executed.
+    /// - `f`: The function which executes the phase.
+    fn timed<F>(&mut self, phase: Phase, f: F) -> Result<SimResult, StepError>
+    where
+        F: FnOnce(&mut Self) -> Result<SimResult, StepError>,
+    {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::trace_span!("phase", %phase).entered();
+        let start = Instant::now();
+        let result = f(self);
+        if !self.replaying {
+            self.metrics.record_phase(phase, start.elapsed());
+        }
+
+        result
+    }
+
+    /// Rewind the simulation by a number of steps.
+    ///
+    /// The nearest checkpoint at or before the target time is restored and the simulation is deterministically
+    /// re-run forward until the target time is reached, making again any [forces](Self::force_wire) made along the way.
+    /// Forces made after the target time are forgotten.  The repeated steps are not passed to Tracers, counted in the
+    /// metrics or checked against breakpoints, and Tracers are next passed a step once the Simulation passes the latest
+    /// time they have seen, along with any Wires whose values then differ from those they last saw.
+    ///
+    /// Checkpoints must first be [configured](Self::set_checkpoints).  Stepping back fails if any Element cannot be
+    /// [replayed](Element::is_replayable), as repeating its steps would repeat their effects outside the Simulation.
+    ///
+    /// # Parameters
+    ///
+    /// - `steps`: Number of steps to rewind.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use rvfs_sim_core::sim::Simulation;
+    /// # use rvfs_sim_core::time::SimDuration;
+    /// # use rvfs_sim_core::wire::{Wire, WirePull};
+    /// let mut sim = Simulation::new(SimDuration::from_ticks(10));
+    /// sim.add_wire(Wire::new("/RESET", WirePull::Up)).unwrap();
+    /// sim.set_checkpoints(4, 8);
+    ///
+    /// for _ in 0..10 {
+    ///     sim.step().unwrap();
+    /// }
+    /// sim.step_back(3).unwrap();
+    ///
+    /// assert_eq!(70, sim.time());
+    /// ```
+    pub fn step_back(&mut self, steps: u64) -> Result<(), SimError> {
+        let target = steps
+            .checked_mul(self.interval.ticks())
+            .and_then(|delta| self.time.checked_sub(delta))
+            .ok_or(SimError::BeforeStart)?;
+        for id in self.elements() {
+            let element = self.element(id)?;
+            if !element.is_replayable() {
+                return Err(SimError::NotReplayable(element.name().to_string()));
+            }
+        }
+
+        // Discard any checkpoints taken after the target time and resume from the newest one that remains.
+        while self.checkpoints.back().is_some_and(|c| c.time > target) {
+            self.checkpoints.pop_back();
+        }
+        let checkpoint = self
+            .checkpoints
+            .back()
+            .cloned()
+            .ok_or(SimError::NoCheckpoint)?;
+
+        if self.traced_values.is_none() && self.tracers.iter().any(|(_, started)| *started) {
+            self.traced_values = Some(
+                (0..self.wire_names.len())
+                    .map(|slot| Some(self.wire(WireId::from(slot)).ok()?.measure()))
+                    .collect(),
+            );
+        }
+        while self
+            .recent_changes
+            .back()
+            .is_some_and(|(time, _)| *time > target)
+        {
+            self.recent_changes.pop_back();
+        }
+
+        self.schedule = None;
+        self.time = checkpoint.time;
+        self.wires = checkpoint.wires;
+        self.elements = checkpoint.elements;
+        self.input_pins = checkpoint.input_pins;
+        self.output_pins = checkpoint.output_pins;
+        self.wire_forces = checkpoint.wire_forces;
+        self.wire_unknown = checkpoint.wire_unknown;
+        self.forced.retain(|(time, _, _)| *time <= target);
+        self.replaying = true;
+        let result = self.replay(target);
+        self.replaying = false;
+        self.metrics.rewind(self.time);
+        self.rearm_breakpoints();
+
+        result
+    }
+
+    /// Re-run the Simulation forward from a restored checkpoint to a target time, making again the
+    /// [forces](Self::force_wire) noted along the way.
+    ///
+    /// # Parameters
+    ///
+    /// - `target`: The time to stop at.
+    fn replay(&mut self, target: u64) -> Result<(), SimError> {
+        let forced = self.forced.clone();
+        let start = self.time;
+        let mut forces = forced
+            .iter()
+            .skip_while(|(time, _, _)| *time < start)
+            .peekable();
+        loop {
+            while let Some((_, id, pull)) = forces.next_if(|(time, _, _)| *time <= self.time) {
+                self.apply_force(*id, *pull)?;
+            }
+            if self.time >= target {
+                return Ok(());
+            }
+            self.step().map_err(|err| *err.error)?;
+        }
+    }
+
+    /// Take a [snapshot](crate::snapshot) of the state of the Simulation, from which it, or another Simulation built
+    /// from the same netlist, can later be [restored](Self::restore_snapshot).
+    ///
+    /// Fails if any Element cannot [save](Element::save_state) its state.
+    #[cfg(feature = "serde")]
+    pub fn snapshot(&mut self) -> Result<Snapshot, SimError> {
+        self.catch_up_all();
+
+        let wires = (0..self.wire_names.len())
+            .map(WireId::from)
+            .map(|id| {
+                self.wire(id).ok().map(|wire| WireState {
+                    name: wire.name().clone(),
+                    value: wire.measure(),
+                    pull: self.wires.active_pull(id),
+                    force: self.wire_forces[id.slot()],
+                    unknown: self.wire_unknown[id.slot()],
+                })
+            })
+            .collect();
+        let mut elements = Vec::new();
+        for slot in 0..self.element_names.len() {
+            let Some((id, element)) = self
+                .elements
+                .id(slot)
+                .and_then(|id| Some((id, self.element(id).ok()?)))
+            else {
+                elements.push(None);
+                continue;
+            };
+            let (inputs, outputs) = &self.element_pins[id.slot()];
+            elements.push(Some(ElementState {
+                name: self.element_names[id.slot()].clone(),
+                state: element.save_state()?,
+                inputs: inputs
+                    .iter()
+                    .filter_map(|pin| self.input_pins.inspect(*pin).clone())
+                    .collect(),
+                outputs: outputs
+                    .iter()
+                    .filter_map(|pin| self.output_pins.inspect(*pin).clone())
+                    .collect(),
+            }));
+        }
+
+        Ok(Snapshot {
+            time: self.time,
+            seed: self.seed,
+            wires,
+            elements,
+        })
+    }
+
+    /// Restore the state of the Simulation from a [snapshot](crate::snapshot), taken from this Simulation or another
+    /// built from the same netlist, so that stepping resumes from the time of the snapshot.
+    ///
+    /// The Wires and Elements of the snapshot must match those of the Simulation, Id for Id and name for name, and the
+    /// Simulation is left unchanged if they do not, or if any Element cannot [restore](Element::restore_state) its
+    /// state.  Every checkpoint taken so far is discarded.
+    ///
+    /// # Parameters
+    ///
+    /// - `snapshot`: The snapshot.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use rvfs_sim_core::element::clocks::{ClockDivider, ClockGenerator};
+    /// # use rvfs_sim_core::sim::Simulation;
+    /// # use rvfs_sim_core::time::SimDuration;
+    /// # use rvfs_sim_core::wire::{Wire, WirePull};
+    /// let build = || {
+    ///     let mut sim = Simulation::new(SimDuration::from_ticks(10));
+    ///     let clk = sim.add_wire(Wire::new("CLK", WirePull::None)).unwrap();
+    ///     let q = sim.add_wire(Wire::new("Q", WirePull::None)).unwrap();
+    ///     let x1 = sim.add_element(Box::new(ClockGenerator::new("X1", 100.0, 0.5, 0.0, 0, 0.0).unwrap())).unwrap();
+    ///     let u1 = sim.add_element(Box::new(ClockDivider::new("U1", 3, true, 0).unwrap())).unwrap();
+    ///     sim.connect_output(sim.output_pin(x1, "CLK").unwrap(), clk).unwrap();
+    ///     sim.connect_input(clk, sim.input_pin(u1, "CLK").unwrap()).unwrap();
+    ///     sim.connect_output(sim.output_pin(u1, "Q").unwrap(), q).unwrap();
+    ///     sim
+    /// };
+    /// let mut sim = build();
+    /// sim.run_for(25).unwrap();
+    /// let snapshot = sim.snapshot().unwrap();
+    /// sim.run_for(40).unwrap();
+    ///
+    /// // Replay the last 40 steps in a fresh copy of the circuit, picking up the divider part way through its count.
+    /// let mut replay = build();
+    /// replay.restore_snapshot(&snapshot).unwrap();
+    /// replay.run_for(40).unwrap();
+    ///
+    /// assert_eq!(650, replay.time());
+    /// assert!(sim.diff(&replay).is_empty());
+    /// ```
+    #[cfg(feature = "serde")]
+    pub fn restore_snapshot(&mut self, snapshot: &Snapshot) -> Result<(), SimError> {
+        let mismatch = |reason: String| Err(SimError::SnapshotMismatch(reason));
+        if snapshot.wires.len() != self.wire_names.len() {
+            return mismatch(format!(
+                "{} wires, expected {}",
+                snapshot.wires.len(),
+                self.wire_names.len()
+            ));
+        }
+        if snapshot.elements.len() != self.element_names.len() {
+            return mismatch(format!(
+                "{} elements, expected {}",
+                snapshot.elements.len(),
+                self.element_names.len()
+            ));
+        }
+        for (index, saved) in snapshot.wires.iter().enumerate() {
+            let name = self.wire(WireId::from(index)).ok().map(|wire| wire.name());
+            if saved.as_ref().map(|wire| &wire.name) != name {
+                return mismatch(format!("wire {index} differs"));
+            }
+        }
+        let mut elements = Vec::new();
+        for (index, saved) in snapshot.elements.iter().enumerate() {
+            let id = self.elements.id(index);
+            let element = id.and_then(|id| self.element(id).ok());
+            let (Some(saved), Some(id), Some(element)) = (saved, id, element) else {
+                if saved.is_some() || element.is_some() {
+                    return mismatch(format!("element {index} differs"));
+                }
+                continue;
+            };
+            let (inputs, outputs) = &self.element_pins[index];
+            let input_names: Vec<&String> = inputs
+                .iter()
+                .filter_map(|pin| self.input_pins.inspect(*pin).as_ref())
+                .map(InputPin::name)
+                .collect();
+            let output_names: Vec<&String> = outputs
+                .iter()
+                .filter_map(|pin| self.output_pins.inspect(*pin).as_ref())
+                .map(OutputPin::name)
+                .collect();
+            if saved.name != self.element_names[index]
+                || input_names != saved.inputs.iter().map(InputPin::name).collect::<Vec<_>>()
+                || output_names
+                    != saved
+                        .outputs
+                        .iter()
+                        .map(OutputPin::name)
+                        .collect::<Vec<_>>()
+            {
+                return mismatch(format!("element {index} differs"));
+            }
+            let mut element = element.box_clone();
+            element.restore_state(saved.state.clone())?;
+            elements.push((id, element, saved));
+        }
+
+        if snapshot.seed != self.seed {
+            self.set_seed(snapshot.seed);
+        }
+        for (index, saved) in snapshot.wires.iter().enumerate() {
+            let Some(saved) = saved else {
+                continue;
+            };
+            let id = WireId::from(index);
+            self.wires.set_value(id, saved.value);
+            self.wires.set_pull(id, saved.pull);
+            self.wire_forces[index] = saved.force;
+            self.wire_unknown[index] = saved.unknown;
+        }
+        for (id, element, saved) in elements {
+            if let Some(slot) = self.elements.inspect_mut(id) {
+                *slot = element;
+            }
+            let (inputs, outputs) = &self.element_pins[id.slot()];
+            for (pin, state) in inputs.iter().zip(&saved.inputs) {
+                if let Some(pin) = self.input_pins.inspect_mut(*pin) {
+                    *pin = state.clone();
+                }
+            }
+            for (pin, state) in outputs.iter().zip(&saved.outputs) {
+                if let Some(pin) = self.output_pins.inspect_mut(*pin) {
+                    *pin = state.clone();
+                }
+            }
+        }
+        self.time = snapshot.time;
+        self.schedule = None;
+        self.checkpoints.clear();
+        self.forced.clear();
+        self.rearm_breakpoints();
+
+        Ok(())
+    }
+
+    /// Save a [snapshot](Self::snapshot) of the state of the Simulation to a file.
+    ///
+    /// # Parameters
+    ///
+    /// - `path`: Path to the file, which is replaced if it exists.
+    #[cfg(feature = "serde")]
+    pub fn save_snapshot(&mut self, path: &Path) -> Result<(), SimError> {
+        Ok(self.snapshot()?.save(path)?)
+    }
+
+    /// Restore the state of the Simulation from a snapshot file written by [save_snapshot](Self::save_snapshot), as
+    /// [restore_snapshot](Self::restore_snapshot) does.
+    ///
+    /// # Parameters
+    ///
+    /// - `path`: Path to the file.
+    #[cfg(feature = "serde")]
+    pub fn restore(&mut self, path: &Path) -> Result<(), SimError> {
+        self.restore_snapshot(&Snapshot::load(path)?)
+    }
+
+    /// Compare the state of the Simulation with that of another, such as a variant of the design or a replay of the
+    /// same run.
+    ///
+    /// Wires, pins and Elements are matched by name, as described for the [diff](crate::diff) module.
+    ///
+    /// # Parameters
+    ///
+    /// - `other`: The Simulation to compare against.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use rvfs_sim_core::sim::Simulation;
+    /// # use rvfs_sim_core::time::SimDuration;
+    /// # use rvfs_sim_core::wire::{Wire, WirePull};
+    /// let mut a = Simulation::new(SimDuration::from_ticks(10));
+    /// a.add_wire(Wire::new("/RESET", WirePull::Up)).unwrap();
+    /// let mut b = Simulation::new(SimDuration::from_ticks(10));
+    /// b.add_wire(Wire::new("/RESET", WirePull::Down)).unwrap();
+    ///
+    /// assert_eq!("wire \"/RESET\": 1 != 0", a.diff(&b).to_string());
+    /// ```
+    pub fn diff(&self, other: &Simulation) -> StateDiff {
+        StateDiff::between(self.state(), other.state())
+    }
+
+    /// Collect the state of the Simulation, keyed by the names of its components.
+    fn state(&self) -> State {
+        let mut state = State {
+            time: self.time,
+            ..State::default()
+        };
+        for id in self.wires() {
+            state
+                .wires
+                .insert(self.wire_names[id.slot()].clone(), self.wires.measure(id));
+        }
+        for id in self.elements() {
+            let name = &self.element_names[id.slot()];
+            let (inputs, outputs) = &self.element_pins[id.slot()];
+            for pin in inputs
+                .iter()
+                .filter_map(|pin| self.input_pins.inspect(*pin).as_ref())
+            {
+                state
+                    .inputs
+                    .insert(format!("{name}.{}", pin.name()), pin.state());
+            }
+            for pin in outputs
+                .iter()
+                .filter_map(|pin| self.output_pins.inspect(*pin).as_ref())
+            {
+                state
+                    .outputs
+                    .insert(format!("{name}.{}", pin.name()), pin.state());
+            }
+            if let Some(element) = self.elements.inspect(id) {
+                state.elements.insert(name.clone(), element.state());
+            }
+        }
+
+        state
+    }
+
+    /// Sample the levels of several Wires over a window of simulation time, as plain columns suited to exploratory
+    /// analysis, such as in a Jupyter notebook with evcxr.
+    ///
+    /// The Simulation is stepped forward to the window, or [stepped back](Simulation::step_back) to it if the window
+    /// starts in the past, and then through it.  A sample is taken at the first step at or after each multiple of the
+    /// sample period from the start of the window, so the times of the samples are returned alongside their levels.
+    /// Sampling stops early if the Simulation finishes.
+    ///
+    /// Returns the sample times, and a column of levels for each Wire, in the order the Wires were given.
+    ///
+    /// # Parameters
+    ///
+    /// - `signals`: Ids of the Wires to sample.
+    /// - `t0`: Simulation time at which to take the first sample.
+    /// - `t1`: Simulation time at or before which to take the last sample.
+    /// - `dt`: Simulation time between samples, which must be positive.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use rvfs_sim_core::element::clocks::ClockGenerator;
+    /// # use rvfs_sim_core::sim::Simulation;
+    /// # use rvfs_sim_core::time::SimDuration;
+    /// # use rvfs_sim_core::wire::{Wire, WirePull};
+    /// let mut sim = Simulation::new(SimDuration::from_ticks(10));
+    /// let clock = sim
+    ///     .add_element(Box::new(ClockGenerator::new("X1", 40.0, 0.5, 0.0, 0, 0.0).unwrap()))
+    ///     .unwrap();
+    /// let clk = sim.add_wire(Wire::new("CLK", WirePull::Down)).unwrap();
+    /// sim.connect_output(sim.output_pin(clock, "CLK").unwrap(), clk)
+    ///     .unwrap();
+    ///
+    /// let (times, levels) = sim.sample(&[clk], 20, 80, 20).unwrap();
+    ///
+    /// assert_eq!(vec![20, 40, 60, 80], times);
+    /// assert_eq!(vec![vec![1.0, 0.0, 1.0, 0.0]], levels);
+    /// ```
+    pub fn sample(
+        &mut self,
+        signals: &[WireId],
+        t0: u64,
+        t1: u64,
+        dt: u64,
+    ) -> Result<(Vec<u64>, Vec<Vec<f32>>), SimError> {
+        if dt == 0 {
+            return Err(SimError::InvalidArgument(
+                "Sample period must be positive!".to_string(),
+            ));
+        }
+        for id in signals {
+            self.wire(*id)?;
+        }
+        if t0 < self.time {
+            self.step_back((self.time - t0).div_ceil(self.interval.ticks()))?;
+        }
+
+        let mut times = Vec::new();
+        let mut levels = vec![Vec::new(); signals.len()];
+        let mut next = t0;
+        'sampling: while next <= t1 {
+            while self.time < next {
+                if self.step().map_err(|err| *err.error)? == SimResult::Finished {
+                    break 'sampling;
+                }
+            }
+            times.push(self.time);
+            for (column, id) in levels.iter_mut().zip(signals) {
+                column.push(f32::from(self.wire(*id)?.measure()));
+            }
+            while next <= self.time {
+                let Some(following) = next.checked_add(dt) else {
+                    break 'sampling;
+                };
+                next = following;
+            }
+        }
+
+        Ok((times, levels))
     }
 
-    /// Look up a Wire by ID.
+    /// Build a step error, attaching context about the step and the failing component.
     ///
     /// # Parameters
     ///
-    /// - `id`: The Id of the Wire which was returned when it was [added](`Self::add_wire`).
-    pub fn wire(&self, id: Id) -> Result<&Wire, String> {
-        self.wires
-            .inspect(id)
+    /// - `error`: The failure.
+    /// - `phase`: Phase of the step which failed, if the failure occurred within one.
+    /// - `component`: The component which failed, if known.
+    fn step_error(
+        &self,
+        error: SimError,
+        phase: Option<Phase>,
+        component: Option<Component>,
+    ) -> StepError {
+        let name = match component {
+            Some(Component::Wire(id)) => self.wire_names.get(id.slot()).cloned(),
+            Some(Component::Element(id)) => self.element_names.get(id.slot()).cloned(),
+            None => None,
+        };
+        let path = name
             .as_ref()
-            .ok_or("No wire found for the given ID".to_string())
+            .and_then(|name| name.rsplit_once('.'))
+            .map(|(path, _)| path.to_string());
+        #[cfg(feature = "tracing")]
+        tracing::error!(
+            time = self.time,
+            phase = phase.map(tracing::field::display),
+            component = name.as_deref(),
+            %error,
+            "step failed"
+        );
+        let mut recent_changes: Vec<(u64, Change)> = match component {
+            Some(Component::Wire(id)) => self
+                .recent_changes
+                .iter()
+                .rev()
+                .filter(|(_, change)| change.id == id)
+                .take(STEP_ERROR_CHANGES)
+                .copied()
+                .collect(),
+            _ => Vec::new(),
+        };
+        recent_changes.reverse();
+
+        StepError {
+            error: Box::new(error),
+            time: self.time,
+            phase,
+            component: name,
+            path,
+            recent_changes,
+        }
     }
 
-    /// Run the simulation.
+    /// Start any attached Tracers which have not yet been started.
+    fn start_tracers(&mut self) -> Result<(), String> {
+        let mut tracers = core::mem::take(&mut self.tracers);
+        let result = tracers
+            .iter_mut()
+            .filter(|(_, started)| !*started)
+            .try_for_each(|(tracer, started)| {
+                *started = true;
+                tracer.start(self)
+            });
+        self.tracers = tracers;
+
+        result
+    }
+
+    /// Pass the changes made during the most recent step to all attached Tracers, unless they have already seen the
+    /// present time.
     ///
-    /// Begin stepping the components of the simulation.  Running the simulation consumes the Simulation instance.  The
-    /// simulation will run forever unless some component eventually returns a result of [SimResult::Finished].
-    pub fn run(mut self) -> Result<SimResult, String> {
-        let mut result = Ok(SimResult::Finished);
-        if !self.is_empty() {
-            loop {
-                result = self.step();
-                if let Ok(SimResult::Continuing) = result {
-                    continue;
-                } else {
-                    break;
-                }
-            }
+    /// The first step passed after stepping back carries every Wire whose value differs from the one last passed.
+    fn record_tracers(&mut self) -> Result<(), String> {
+        if self.time <= self.traced {
+            return Ok(());
         }
+        self.traced = self.time;
+        let resumed: Option<Vec<Change>> = self.traced_values.take().map(|values| {
+            (0..self.wire_names.len())
+                .map(WireId::from)
+                .filter_map(|id| {
+                    let previous = (*values.get(id.slot())?)?;
+                    let value = self.wire(id).ok()?.measure();
+                    (previous != value).then_some(Change {
+                        id,
+                        previous,
+                        value,
+                    })
+                })
+                .collect()
+        });
+        let changes = resumed.as_deref().unwrap_or(&self.changes);
+        let mut tracers = core::mem::take(&mut self.tracers);
+        let result = tracers
+            .iter_mut()
+            .try_for_each(|(tracer, _)| tracer.record(self, changes));
+        self.tracers = tracers;
 
         result
     }
 
-    /// Advance the simulation by one time step.
-    fn step(&mut self) -> Result<SimResult, String> {
-        let mut result = self.step_input_pins();
-        if let Ok(SimResult::Continuing) = result {
-            result = self.step_elements();
-            if let Ok(SimResult::Continuing) = result {
-                result = self.step_wires();
+    /// Obtain the present level and logic value of a watched Wire.
+    ///
+    /// # Parameters
+    ///
+    /// - `id`: The Id of the Wire.
+    fn watched(&self, id: WireId) -> Option<(WireValue, LogicValue)> {
+        Some((self.wire(id).ok()?.measure(), self.logic(id).ok()?))
+    }
+
+    /// Compare each watched Wire with how it stood after the previous step, and obtain the Id of the first breakpoint
+    /// whose watch matches the change, if any.
+    fn check_breakpoints(&mut self) -> Option<WatchId> {
+        let mut breakpoints = core::mem::take(&mut self.breakpoints);
+        let mut hit = None;
+        for breakpoint in &mut breakpoints {
+            if let Some(present) = self.watched(breakpoint.wire) {
+                if breakpoint.update(present) && hit.is_none() {
+                    hit = Some(breakpoint.id);
+                }
             }
         }
+        self.breakpoints = breakpoints;
 
-        // NOTE: may make these debug-only later
-        self.wires.audit()?;
-
-        self.time += self.interval;
-
-        result
+        hit
     }
 
-    /// Execute the first phase of a Simulation step by updating the [InputPins](InputPin).
-    fn step_input_pins(&self) -> Result<SimResult, String> {
-        // TODO: implement this
-        Ok(SimResult::Continuing)
+    /// Note how each watched Wire stands without checking for a change, after the simulation has been moved to another
+    /// time.
+    fn rearm_breakpoints(&mut self) {
+        let _ = self.check_breakpoints();
     }
 
-    /// Execute the second phase of a Simulation step by updating the [Elements](Element).
-    fn step_elements(&self) -> Result<SimResult, String> {
-        // TODO: implement this
-        Ok(SimResult::Continuing)
+    /// Take an automatic checkpoint if one is due at the present time.
+    fn checkpoint(&mut self) {
+        if self.checkpoint_interval == 0 || self.checkpoint_limit == 0 {
+            return;
+        }
+        if !(self.time / self.interval.ticks()).is_multiple_of(self.checkpoint_interval) {
+            return;
+        }
+        if self.checkpoints.back().is_some_and(|c| c.time >= self.time) {
+            return;
+        }
+
+        if self.checkpoints.len() >= self.checkpoint_limit {
+            self.checkpoints.pop_front();
+            let oldest = self.checkpoints.front().map_or(self.time, |c| c.time);
+            self.forced.retain(|(time, _, _)| *time >= oldest);
+        }
+        self.catch_up_all();
+        self.checkpoints.push_back(Checkpoint {
+            time: self.time,
+            wires: self.wires.clone(),
+            elements: self.elements.clone(),
+            input_pins: self.input_pins.clone(),
+            output_pins: self.output_pins.clone(),
+            wire_forces: self.wire_forces.clone(),
+            wire_unknown: self.wire_unknown.clone(),
+        });
     }
 
-    /// Receive and unwrap a step result.
-    fn receive_result(&mut self) -> Result<StepResult, String> {
-        // Wait for every Wire step to complete (or time out), and obtain the results.
-        let execution_result = self
-            .receiver
-            .recv_timeout(self.phase_timeout)
-            .map_err(|err| {
-                (match err {
-                    RecvTimeoutError::Timeout => {
-                        "Timed out waiting for wire step phase to complete!"
-                    }
-                    RecvTimeoutError::Disconnected => {
-                        "Disconnected while waiting for wire step phase to complete!"
-                    }
-                })
-                .to_string()
-            })?;
+    /// Execute the first phase of a Simulation step by updating the [InputPins](InputPin).
+    ///
+    /// Under the event-driven scheduler, only the InputPins whose Wires or states have just changed are updated.
+    fn step_input_pins(&mut self) -> Result<SimResult, StepError> {
+        self.apply_stimuli()
+            .map_err(|error| self.step_error(error, None, None))?;
+        let ids: Vec<InputPinId> = match &mut self.schedule {
+            Some(schedule) => schedule.take_inputs().into_iter().collect(),
+            None => self.input_pins.iter().collect(),
+        };
+        for id in ids {
+            let Some(wire) = self.input_wires[id.slot()] else {
+                continue;
+            };
+            let value = self.wires.measure(wire);
+            let driven = self.is_driven(wire);
+            if let Some(pin) = self.input_pins.inspect_mut(id) {
+                if driven {
+                    pin.step(value);
+                } else {
+                    pin.step_unknown();
+                }
+                if let (true, Some(schedule)) = (pin.changed(), &mut self.schedule) {
+                    schedule.input_changed(id);
+                }
+            }
+        }
 
-        Ok(execution_result)
+        Ok(SimResult::Continuing)
     }
 
-    /// Execute the third phase of a Simulation step by updating the [Wires](Wire).
-    fn step_wires(&mut self) -> Result<SimResult, String> {
+    /// Execute the second phase of a Simulation step by updating the [Elements](Element).
+    ///
+    /// Under the event-driven scheduler, combinational Elements are only updated if one of their InputPins has changed.
+    fn step_elements(&mut self) -> Result<SimResult, StepError> {
         let mut finished = false;
+        let mut outstanding = vec![false; self.element_names.len()];
+        let ids: Vec<ElementId> = match &mut self.schedule {
+            Some(schedule) => schedule.take_elements(),
+            None => self.elements.iter().collect(),
+        };
 
-        for id in self.wires.iter() {
-            let mut wire = self.wires.checkout(id)?;
-            // "Check out" the Wire for the step execution.
+        for id in ids.iter().copied() {
+            outstanding[id.slot()] = true;
+            let component = Some(Component::Element(id));
+            if self.schedule.is_some() {
+                for pin in self.element_pins[id.slot()].1.clone() {
+                    self.catch_up(pin, self.time);
+                }
+            }
+            // "Check out" the Element and its OutputPins for the step execution, and copy its freshly sampled
+            // InputPins, which are not modified by the Element.
+            let mut element = self
+                .elements
+                .checkout(id)
+                .map_err(|message| self.step_error(message, Some(Phase::Elements), component))?;
+            #[cfg(feature = "tracing")]
+            tracing::trace!(element = %self.element_names[id.slot()], "checked out");
+            let (input_ids, output_ids) = &self.element_pins[id.slot()];
+            let inputs: Vec<InputPin> = input_ids
+                .iter()
+                .filter_map(|pin| self.input_pins.inspect(*pin).clone())
+                .collect();
+            let mut outputs = Vec::with_capacity(output_ids.len());
+            for pin in output_ids {
+                outputs.push(self.output_pins.checkout(*pin).map_err(|message| {
+                    self.step_error(message, Some(Phase::Elements), component)
+                })?);
+            }
 
-            let sender = self.sender.clone();
             let interval = self.interval;
-            // TODO: "Check-out" OutputPins and temporarily inject into Wire.
+            let profiling = self.profile.is_some();
 
-            // Delegate the Wire step execution to the thread pool.
-            self.pool.execute(move || {
-                wire.step(interval);
-                let _ = sender.send(StepResult::Wire(Ok(SimResult::Continuing), wire));
-            });
+            // Delegate the Element step execution to the executor.
+            self.executor.execute(Box::new(move || {
+                let start = profiling.then(Instant::now);
+                let result = element.step(&inputs, &mut outputs, interval.ticks());
+                let elapsed = start.map(|start| start.elapsed());
+                StepResult::Element(result, id, element, outputs, elapsed)
+            }));
         }
 
-        for id in self.wires.iter() {
-            if let StepResult::Wire(op_result, wire) = self.receive_result()? {
-                finished |= op_result? == SimResult::Finished;
+        self.metrics
+            .record_queue_depth(self.executor.queued_count() as u64);
 
-                // Check-in the Wire and OutputPins.
-                self.wires.checkin(id, wire)?;
+        // Every result is collected before any failure is reported, so that nothing is left checked out.
+        let mut failure = None;
+        for _ in 0..ids.len() {
+            let result = self.receive_result().map_err(|message| {
+                let id = outstanding.iter().position(|pending| *pending);
+                let component = id
+                    .and_then(|slot| self.elements.id(slot))
+                    .map(Component::Element);
+                self.step_error(message, Some(Phase::Elements), component)
+            })?;
+            let StepResult::Element(op_result, id, element, outputs, elapsed) = result;
+            let component = Some(Component::Element(id));
+            outstanding[id.slot()] = false;
+            let op_result = op_result.map_err(|message| {
+                self.step_error(message.into(), Some(Phase::Elements), component)
+            });
+            if let (Some(profile), Some(elapsed)) = (&mut self.profile, elapsed) {
+                profile.record_element(id, element.name(), elapsed);
+            }
 
-                // TODO: Check-in OutputPins.
+            // Check-in the Element and its OutputPins.
+            self.elements
+                .checkin(id, element)
+                .map_err(|message| self.step_error(message, Some(Phase::Elements), component))?;
+            #[cfg(feature = "tracing")]
+            tracing::trace!(element = %self.element_names[id.slot()], "checked in");
+            for (pin, output) in self.element_pins[id.slot()]
+                .1
+                .clone()
+                .into_iter()
+                .zip(outputs)
+            {
+                self.output_pins.checkin(pin, output).map_err(|message| {
+                    self.step_error(message, Some(Phase::Elements), component)
+                })?;
+                if let (Some(schedule), Some(output)) =
+                    (&mut self.schedule, self.output_pins.inspect(pin))
+                {
+                    schedule.pin_driven(pin, output);
+                }
+            }
+            match op_result {
+                Ok(result) => finished |= result == SimResult::Finished,
+                Err(error) => failure = failure.or(Some(error)),
             }
         }
+        if let Some(error) = failure {
+            return Err(error);
+        }
 
         if finished {
             Ok(SimResult::Finished)
@@ -227,19 +2795,255 @@ impl Simulation {
             Ok(SimResult::Continuing)
         }
     }
+
+    /// Receive and unwrap a step result.
+    fn receive_result(&mut self) -> Result<StepResult, SimError> {
+        // Wait for the next step to complete (or time out), and obtain its result.
+        let phase_timeout = self.phase_timeout;
+        let execution_result = self.executor.receive(phase_timeout).map_err(|err| {
+            if err == ReceiveError::Timeout {
+                self.metrics.record_timeout();
+                #[cfg(feature = "tracing")]
+                tracing::warn!(timeout = ?phase_timeout, "element phase timed out");
+            }
+            match err {
+                ReceiveError::Timeout => SimError::PhaseTimeout(Phase::Elements),
+                ReceiveError::Disconnected => SimError::Disconnected(Phase::Elements),
+            }
+        })?;
+
+        Ok(execution_result)
+    }
+
+    /// Propagate the states of the OutputPins driving a Wire and set its pull accordingly.
+    ///
+    /// Pins in the HighImpedance state do not drive the Wire, so it feels its default pull if every pin is released.  A
+    /// Wire without any connected pins keeps its present pull.
+    ///
+    /// # Parameters
+    ///
+    /// - `id`: The Id of the Wire being driven.
+    fn resolve_drivers(&mut self, id: WireId) -> Result<(), SimError> {
+        let mut pull = WirePull::None;
+        let mut conflict = false;
+        let mut unknown = false;
+        for pin in &self.wire_drivers[id.slot()] {
+            let output = self
+                .output_pins
+                .inspect_mut(*pin)
+                .ok_or(SimError::CheckoutConflict)?;
+            match &mut self.schedule {
+                Some(schedule) => {
+                    output.step(schedule.advance(*pin, self.time + self.interval.ticks()));
+                    schedule.pin_driven(*pin, output);
+                }
+                None => output.step(self.interval),
+            }
+            let drive = match output.state() {
+                OutputPinState::Low => WirePull::Down,
+                OutputPinState::High => WirePull::Up,
+                OutputPinState::HighImpedance => continue,
+                OutputPinState::Unknown => {
+                    unknown = true;
+                    continue;
+                }
+            };
+            conflict |= pull != WirePull::None && pull != drive;
+            pull = drive;
+        }
+        let force = self.wire_forces[id.slot()];
+        self.wire_unknown[id.slot()] = (conflict || unknown) && force.is_none();
+        if let Some(pull) = force {
+            self.wires.set_pull(id, pull);
+            return Ok(());
+        }
+        if self.wire_drivers[id.slot()].is_empty() {
+            return Ok(());
+        }
+        if conflict {
+            return Err(SimError::DriverConflict(self.wire_names[id.slot()].clone()));
+        }
+        self.wires.set_pull(id, pull);
+
+        Ok(())
+    }
+
+    /// Execute the third phase of a Simulation step by updating the [Wires](Wire).
+    ///
+    /// Wires are cheap to step and are stored contiguously, so they are stepped in turn rather than being handed to
+    /// the executor.  Under the event-driven scheduler, only the Wires which are due are updated.
+    fn step_wires(&mut self) -> Result<SimResult, StepError> {
+        let profiling = self.profile.is_some();
+        let mut failure = None;
+        let next = self.time + self.interval.ticks();
+        let ids: Vec<WireId> = if self.schedule.is_some() {
+            self.due_wires()
+        } else {
+            self.wires().collect()
+        };
+
+        for id in ids.iter().copied() {
+            let start = profiling.then(Instant::now);
+            let previous = self.wires.measure(id);
+            let driven = self.is_driven(id);
+            // A Wire driven both high and low still settles, so that every Wire has been stepped when the failure is
+            // reported.
+            if let Err(message) = self.resolve_drivers(id) {
+                let error = self.step_error(message, Some(Phase::Wires), Some(Component::Wire(id)));
+                failure = failure.or(Some(error));
+                // The conflict is reported again on every step for as long as it lasts.
+                if let Some(schedule) = &mut self.schedule {
+                    schedule.add(next, Event::Wire(id));
+                }
+            }
+            self.wires.step(id, self.interval);
+            if let (Some(profile), Some(start)) = (&mut self.profile, start) {
+                profile.record_wire(id, &self.wire_names[id.slot()], start.elapsed());
+            }
+
+            // Note any change in value, and any change in whether the Wire can be read, which its InputPins see too.
+            let value = self.wires.measure(id);
+            if previous != value {
+                self.changes.push(Change {
+                    id,
+                    previous,
+                    value,
+                });
+            }
+            if previous != value || driven != self.is_driven(id) {
+                if let Some(schedule) = &mut self.schedule {
+                    schedule.wire_changed(id, next);
+                }
+            }
+        }
+        if self.schedule.is_some() {
+            self.metrics.record_wires(ids.len() as u64);
+            return failure.map_or(Ok(SimResult::Continuing), Err);
+        }
+        self.metrics.record_wires(self.wires.len() as u64);
+        if let Some(error) = failure {
+            return Err(error);
+        }
+        // OutputPins which drive no Wire still advance, so their Elements see consistent states.
+        for (pin, output) in self.output_pins.iter_mut() {
+            if self.output_wires[pin.slot()].is_none() {
+                output.step(self.interval);
+            }
+        }
+
+        Ok(SimResult::Continuing)
+    }
+
+    /// Obtain the Ids of the Wires due for evaluation in the present step under the event-driven scheduler, in
+    /// ascending order.
+    ///
+    /// OutputPins which drive no Wire are stepped here when they fall due, so their Elements see consistent states.
+    fn due_wires(&mut self) -> Vec<WireId> {
+        let Some(schedule) = &mut self.schedule else {
+            return Vec::new();
+        };
+        let next = self.time + self.interval.ticks();
+        let mut wires = BTreeSet::new();
+        for event in schedule.take_due(self.time) {
+            match event {
+                Event::Wire(id) => {
+                    wires.insert(id);
+                }
+                Event::Pin(pin) => match self.output_wires[pin.slot()] {
+                    Some(id) => {
+                        wires.insert(id);
+                    }
+                    None => {
+                        if let Some(output) = self.output_pins.inspect_mut(pin) {
+                            output.step(schedule.advance(pin, next));
+                            schedule.pin_driven(pin, output);
+                        }
+                    }
+                },
+            }
+        }
+        wires.into_iter().collect()
+    }
+}
+
+/// Set the entry of a table indexed by slot, extending the table if the slot is a new one.
+///
+/// # Parameters
+///
+/// - `table`: The table.
+/// - `slot`: Slot of the component, either reused from a removed one or just past the end of the table.
+/// - `entry`: The entry for the component.
+fn place<T>(table: &mut Vec<T>, slot: Id, entry: T) {
+    match table.get_mut(slot) {
+        Some(existing) => *existing = entry,
+        None => table.push(entry),
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::element::buffers::Buffer;
+    use crate::element::clocks::ClockGenerator;
+    use crate::element::custom::FnElement;
+    use crate::element::gates::{Gate, GateKind};
+    #[cfg(feature = "std")]
+    use crate::element::indicators::Led;
+    #[cfg(feature = "serde")]
+    use crate::element::registers::{ShiftDirection, ShiftRegister};
     use crate::wire::WirePull;
+    use crate::wirevalue::WireValue;
     use float_cmp::assert_approx_eq;
 
+    /// A tracing subscriber noting each span created, by name, and each event recorded, by level and message.
+    #[cfg(feature = "tracing")]
+    #[derive(Clone, Default)]
+    struct Recorder(Arc<std::sync::Mutex<Vec<String>>>);
+
+    #[cfg(feature = "tracing")]
+    impl tracing::Subscriber for Recorder {
+        fn enabled(&self, _metadata: &tracing::Metadata<'_>) -> bool {
+            true
+        }
+
+        fn new_span(&self, span: &tracing::span::Attributes<'_>) -> tracing::span::Id {
+            let mut log = self.0.lock().unwrap();
+            log.push(format!("span {}", span.metadata().name()));
+            tracing::span::Id::from_u64(log.len() as u64)
+        }
+
+        fn record(&self, _span: &tracing::span::Id, _values: &tracing::span::Record<'_>) {}
+
+        fn record_follows_from(&self, _span: &tracing::span::Id, _follows: &tracing::span::Id) {}
+
+        fn event(&self, event: &tracing::Event<'_>) {
+            struct Message(String);
+            impl tracing::field::Visit for Message {
+                fn record_debug(&mut self, field: &tracing::field::Field, value: &dyn fmt::Debug) {
+                    if field.name() == "message" {
+                        self.0 = format!("{value:?}");
+                    }
+                }
+            }
+            let mut message = Message(String::new());
+            event.record(&mut message);
+            let level = event.metadata().level();
+            self.0
+                .lock()
+                .unwrap()
+                .push(format!("{level} {}", message.0));
+        }
+
+        fn enter(&self, _span: &tracing::span::Id) {}
+
+        fn exit(&self, _span: &tracing::span::Id) {}
+    }
+
     // Tests for Simulation
     #[test]
     fn simulation_create() {
         // WHEN a simulation is created
-        let sim = Simulation::new(10);
+        let sim = Simulation::new(SimDuration::from_ticks(10));
         // THEN instantiation succeeds and the new instance is empty and has the default phase timeout
         assert!(sim.is_empty());
         assert_eq!(DEFAULT_STEP_PHASE_TIMEOUT, sim.phase_timeout);
@@ -247,8 +3051,8 @@ mod tests {
     #[test]
     fn simulation_add_wire() {
         // GIVEN a simulation instance and a wire
-        let wire = Wire::new(&"foo".to_string(), WirePull::None);
-        let mut sim = Simulation::new(10);
+        let wire = Wire::new("foo", WirePull::None);
+        let mut sim = Simulation::new(SimDuration::from_ticks(10));
         // WHEN a wire is created
         let result = sim.add_wire(wire);
         // THEN adding the wire succeeds
@@ -257,16 +3061,177 @@ mod tests {
     #[test]
     fn simulation_run_empty() {
         // GIVEN an empty Simulation
-        let sim = Simulation::new(10);
+        let sim = Simulation::new(SimDuration::from_ticks(10));
         // WHEN the simulation is run
         let result = sim.run();
         // THEN the result is success and indicates the simulation is finished
         assert_eq!(Ok(SimResult::Finished), result);
     }
     #[test]
+    fn simulation_run_incrementally() {
+        // GIVEN a Simulation of a clock
+        let mut sim = Simulation::new(SimDuration::from_ticks(10));
+        let clk = sim.add_wire(Wire::new("CLK", WirePull::None)).unwrap();
+        let x1 = sim
+            .add_element(Box::new(
+                ClockGenerator::new("X1", 100.0, 0.5, 0.0, 0, 0.0).unwrap(),
+            ))
+            .unwrap();
+        sim.connect_output(sim.output_pin(x1, "CLK").unwrap(), clk)
+            .unwrap();
+        let high = |sim: &Simulation| f32::from(sim.wire(clk).unwrap().measure()) > 0.5;
+        // WHEN it is run for some steps, until a time, and while the clock is high, then resumed while it is low
+        let runs = [
+            sim.run_for(2).map(|result| (result, sim.time())),
+            sim.run_until(45).map(|result| (result, sim.time())),
+            sim.run_until(45).map(|result| (result, sim.time())),
+            sim.run_while(high).map(|result| (result, sim.time())),
+            sim.run_while(|sim| !high(sim))
+                .map(|result| (result, sim.time())),
+        ];
+        // THEN each run returns control at the expected time, with the Simulation still continuing
+        let continuing = SimResult::Continuing;
+        assert_eq!(
+            [
+                Ok((continuing, 20)),
+                Ok((continuing, 50)),
+                Ok((continuing, 50)),
+                Ok((continuing, 60)),
+                Ok((continuing, 110)),
+            ],
+            runs
+        );
+    }
+    #[test]
+    fn simulation_breakpoints() {
+        // GIVEN a Simulation of a clock and a reset line, with breakpoints on a rising clock and a change of reset
+        let mut sim = Simulation::new(SimDuration::from_ticks(10));
+        let clk = sim.add_wire(Wire::new("CLK", WirePull::None)).unwrap();
+        let reset = sim.add_wire(Wire::new("/RESET", WirePull::Up)).unwrap();
+        let x1 = sim
+            .add_element(Box::new(
+                ClockGenerator::new("X1", 100.0, 0.5, 0.0, 0, 0.0).unwrap(),
+            ))
+            .unwrap();
+        sim.connect_output(sim.output_pin(x1, "CLK").unwrap(), clk)
+            .unwrap();
+        let rising = sim.break_when(clk, Watch::Rises(0.5)).unwrap();
+        let reset_changed = sim.break_when(reset, Watch::LogicChanges).unwrap();
+        // WHEN it is run, then run again after the reset line is asserted, then run once the clock is no longer watched
+        let mut runs = vec![sim.run_for(20).map(|result| (result, sim.time()))];
+        sim.force_wire(reset, Some(WirePull::Down)).unwrap();
+        runs.push(sim.run_for(20).map(|result| (result, sim.time())));
+        sim.remove_breakpoint(rising).unwrap();
+        runs.push(sim.run_until(300).map(|result| (result, sim.time())));
+        // THEN each run halts at the next matching step, and a removed breakpoint is gone for good
+        assert_eq!(
+            vec![
+                Ok((SimResult::Breakpoint(rising), 110)),
+                Ok((SimResult::Breakpoint(reset_changed), 120)),
+                Ok((SimResult::Continuing, 300)),
+            ],
+            runs
+        );
+        assert!(sim.remove_breakpoint(rising).is_err());
+        assert!(sim
+            .break_when(WireId::from(5), Watch::LogicChanges)
+            .is_err());
+    }
+    #[test]
+    fn simulation_limits() {
+        // GIVEN a Simulation of a reset line, which never finishes
+        let mut sim = Simulation::new(SimDuration::from_ticks(10));
+        sim.add_wire(Wire::new("/RESET", WirePull::Up)).unwrap();
+        // WHEN it is run under a step limit, then under a time limit too, then without limits, then under a spent
+        // wall-clock limit
+        sim.set_max_steps(Some(5));
+        let mut runs = vec![sim.run_while(|_| true).map(|result| (result, sim.time()))];
+        runs.push(sim.run_for(3).map(|result| (result, sim.time())));
+        sim.set_max_time(Some(70));
+        runs.push(sim.run_for(10).map(|result| (result, sim.time())));
+        sim.set_max_steps(None);
+        sim.set_max_time(None);
+        runs.push(sim.run_for(10).map(|result| (result, sim.time())));
+        sim.set_max_wall_clock(Some(Duration::ZERO));
+        runs.push(sim.run_for(10).map(|result| (result, sim.time())));
+        // THEN each limit stops its run with a distinguishable result, and steps are counted afresh for each run
+        assert_eq!(
+            vec![
+                Ok((SimResult::Limit(Limit::Steps), 50)),
+                Ok((SimResult::Continuing, 80)),
+                Ok((SimResult::Limit(Limit::Time), 80)),
+                Ok((SimResult::Continuing, 180)),
+                Ok((SimResult::Limit(Limit::WallClock), 180)),
+            ],
+            runs
+        );
+        assert_eq!("wall-clock limit", Limit::WallClock.to_string());
+    }
+    #[cfg(feature = "std")]
+    #[test]
+    fn simulation_threads() {
+        // GIVEN Simulations of a clock driving an inverter, on the default executor and on smaller ones
+        let build = |sim: Simulation| {
+            let mut sim = sim;
+            let clk = sim.add_wire(Wire::new("CLK", WirePull::Down)).unwrap();
+            let y = sim.add_wire(Wire::new("Y", WirePull::Down)).unwrap();
+            let x1 = sim
+                .add_element(Box::new(
+                    ClockGenerator::new("X1", 100.0, 0.5, 0.0, 0, 0.0).unwrap(),
+                ))
+                .unwrap();
+            let u1 = sim
+                .add_element(Box::new(Gate::new("U1", GateKind::Not, 1, 0).unwrap()))
+                .unwrap();
+            sim.connect_output(sim.output_pin(x1, "CLK").unwrap(), clk)
+                .unwrap();
+            sim.connect_input(clk, sim.input_pin(u1, "I0").unwrap())
+                .unwrap();
+            sim.connect_output(sim.output_pin(u1, "Y").unwrap(), y)
+                .unwrap();
+            (sim, y)
+        };
+        let new = || Simulation::new(SimDuration::from_ticks(10));
+        let sims = [
+            build(new()),
+            build(new().with_threads(1)),
+            build(
+                new()
+                    .with_threads(2)
+                    .with_backend(ThreadBackend::ThreadPool),
+            ),
+        ];
+        #[cfg(feature = "rayon")]
+        let sims: Vec<_> = sims
+            .into_iter()
+            .chain([build(
+                new().with_backend(ThreadBackend::Rayon).with_threads(2),
+            )])
+            .collect();
+        // WHEN each is run
+        let mut sims = sims;
+        let levels: Vec<_> = sims
+            .iter_mut()
+            .map(|(sim, y)| {
+                (0..14)
+                    .map(|_| {
+                        sim.step().unwrap();
+                        sim.logic(*y).unwrap().symbol()
+                    })
+                    .collect::<String>()
+            })
+            .collect();
+        // THEN every executor gives the same results, on the threads requested
+        assert!(levels.iter().all(|level| *level == levels[0]));
+        assert_eq!("10000011111000", levels[0]);
+        assert!(sims[0].0.threads() >= 1);
+        assert_eq!(1, sims[1].0.threads());
+        assert_eq!(2, sims[2].0.threads());
+    }
+    #[test]
     fn simulation_step_input_pins_empty() {
         // GIVEN an empty Simulation
-        let sim = Simulation::new(10);
+        let mut sim = Simulation::new(SimDuration::from_ticks(10));
         // WHEN the input pins are stepped
         let result = sim.step_input_pins();
         // THEN the result is success and indicates the simulation should continue
@@ -275,7 +3240,7 @@ mod tests {
     #[test]
     fn simulation_step_elements_empty() {
         // GIVEN an empty Simulation
-        let sim = Simulation::new(10);
+        let mut sim = Simulation::new(SimDuration::from_ticks(10));
         // WHEN the components are stepped
         let result = sim.step_elements();
         // THEN the result is success and indicates the simulation should continue
@@ -284,7 +3249,7 @@ mod tests {
     #[test]
     fn simulation_step_wires_empty() {
         // GIVEN an empty Simulation
-        let mut sim = Simulation::new(10);
+        let mut sim = Simulation::new(SimDuration::from_ticks(10));
         // WHEN the wires are stepped
         let result = sim.step_wires();
         // THEN the result is success and indicates the simulation should continue
@@ -294,7 +3259,7 @@ mod tests {
     fn simulation_step_empty() {
         // GIVEN an empty Simulation and a simulation interval
         let interval = 10;
-        let mut sim = Simulation::new(interval);
+        let mut sim = Simulation::new(SimDuration::from_ticks(interval));
         // WHEN the simulation is stepped
         let result = sim.step();
         // THEN the result is success and indicates the simulation should continue
@@ -305,9 +3270,9 @@ mod tests {
     #[test]
     fn simulation_step_with_wires() {
         // GIVEN a Simulation with two wires
-        let wire1 = Wire::new(&"foo".to_string(), WirePull::Up);
-        let wire2 = Wire::new(&"bar".to_string(), WirePull::Down);
-        let mut sim = Simulation::new(10);
+        let wire1 = Wire::new("foo", WirePull::Up);
+        let wire2 = Wire::new("bar", WirePull::Down);
+        let mut sim = Simulation::new(SimDuration::from_ticks(10));
         let result1 = sim.add_wire(wire1);
         let result2 = sim.add_wire(wire2);
         // WHEN the wires are stepped
@@ -320,10 +3285,10 @@ mod tests {
     #[test]
     fn simulation_lookup_wire() {
         // GIVEN a Simulation with two wires
-        let wire1 = Wire::new(&"foo".to_string(), WirePull::Up);
+        let wire1 = Wire::new("foo", WirePull::Up);
         let name = "bar".to_string();
         let wire2 = Wire::new(&name, WirePull::Down);
-        let mut sim = Simulation::new(10);
+        let mut sim = Simulation::new(SimDuration::from_ticks(10));
         let result1 = sim.add_wire(wire1);
         let result2 = sim.add_wire(wire2);
         // WHEN a wire is looked up in the simulation
@@ -331,7 +3296,7 @@ mod tests {
         let result3 = if let Ok(id) = result2 {
             sim.wire(id)
         } else {
-            Err("No wire!".to_string())
+            Err(SimError::UnknownId(ComponentKind::Wire))
         };
         // THEN the wires were added, and the second wire was looked up correctly
         assert!(result1.is_ok());
@@ -342,11 +3307,77 @@ mod tests {
         }
     }
     #[test]
+    fn simulation_lookup_by_name() {
+        // GIVEN a Simulation with a Wire and a gate
+        let mut sim = Simulation::new(SimDuration::from_ticks(10));
+        let a = sim.add_wire(Wire::new("A", WirePull::Up)).unwrap();
+        let u1 = sim
+            .add_element(Box::new(Gate::new("U1", GateKind::Not, 1, 0).unwrap()))
+            .unwrap();
+        // WHEN components are looked up by name
+        // THEN those present are found, and those absent are reported by kind and name
+        assert_eq!(Ok(a), sim.wire_by_name("A"));
+        assert_eq!(Ok(u1), sim.element_by_name("U1"));
+        assert_eq!(sim.input_pin(u1, "I0"), sim.input_pin_by_name("U1", "I0"));
+        assert_eq!(sim.output_pin(u1, "Y"), sim.output_pin_by_name("U1", "Y"));
+        assert_eq!(
+            Err(SimError::UnknownName(ComponentKind::Wire, "B".to_string())),
+            sim.wire_by_name("B")
+        );
+        assert_eq!(
+            Err(SimError::UnknownName(
+                ComponentKind::Element,
+                "U2".to_string()
+            )),
+            sim.output_pin_by_name("U2", "Y")
+        );
+        // WHEN components are added with names already taken
+        // THEN they are refused, leaving the Simulation unchanged
+        assert_eq!(
+            Err(SimError::DuplicateName(
+                ComponentKind::Wire,
+                "A".to_string()
+            )),
+            sim.add_wire(Wire::new("A", WirePull::Down))
+        );
+        assert_eq!(
+            Err(SimError::DuplicateName(
+                ComponentKind::Element,
+                "U1".to_string()
+            )),
+            sim.add_element(Box::new(Gate::new("U1", GateKind::Or, 2, 0).unwrap()))
+                .map(|_| ())
+        );
+        assert_eq!(
+            Err(SimError::DuplicateName(
+                ComponentKind::InputPin,
+                "U2.D".to_string()
+            )),
+            sim.add_element(Box::new(FnElement::new(
+                "U2",
+                &["D", "CLK", "D"],
+                &["Q"],
+                |_, _| Vec::new()
+            )))
+            .map(|_| ())
+        );
+        assert_eq!(1, sim.wires().count());
+        assert_eq!(1, sim.elements().count());
+        // WHEN components are removed
+        sim.remove_element(u1).unwrap();
+        sim.remove_wire(a).unwrap();
+        // THEN their names can no longer be looked up, and are free to be used again
+        assert!(sim.wire_by_name("A").is_err());
+        assert!(sim.element_by_name("U1").is_err());
+        let b = sim.add_wire(Wire::new("A", WirePull::Down)).unwrap();
+        assert_eq!(Ok(b), sim.wire_by_name("A"));
+    }
+    #[test]
     fn simulation_step_with_wire_pulled_down() {
         // GIVEN a Simulation with a wire defaulting to pulled-up, but driven down
         let tau = 5f32;
-        let mut wire = Wire::new(&"foo".to_string(), WirePull::Up);
-        let mut sim = Simulation::new(10);
+        let mut wire = Wire::new("foo", WirePull::Up);
+        let mut sim = Simulation::new(SimDuration::from_ticks(10));
         wire.set_time_constant(tau);
         wire.set_pull(WirePull::Down);
         let result1 = sim.add_wire(wire);
@@ -360,4 +3391,899 @@ mod tests {
             assert_approx_eq!(f32, 0.13533528f32, sim.wire(id).unwrap().measure().into());
         }
     }
+    #[test]
+    fn simulation_force_wire() {
+        // GIVEN a Simulation with a wire driven high by a clock which stays high
+        let mut sim = Simulation::new(SimDuration::from_ticks(10));
+        let clock = sim
+            .add_element(Box::new(
+                ClockGenerator::new("X1", 1000.0, 0.5, 0.0, 0, 0.0).unwrap(),
+            ))
+            .unwrap();
+        let id = sim.add_wire(Wire::new("CLK", WirePull::Down)).unwrap();
+        sim.connect_output(sim.output_pin(clock, "CLK").unwrap(), id)
+            .unwrap();
+        let level = |sim: &Simulation| f32::from(sim.wire(id).unwrap().measure());
+        // WHEN the wire is forced low and then released
+        sim.step().unwrap();
+        sim.step().unwrap();
+        let driven = level(&sim);
+        sim.force_wire(id, Some(WirePull::Down)).unwrap();
+        sim.step().unwrap();
+        let forced = level(&sim);
+        sim.force_wire(id, None).unwrap();
+        sim.step().unwrap();
+        let released = level(&sim);
+        // THEN the force overrides the driver until released, and only known wires can be forced
+        assert_approx_eq!(f32, 1.0, driven);
+        assert_approx_eq!(f32, 0.0, forced);
+        assert_approx_eq!(f32, 1.0, released);
+        assert!(sim.force_wire(WireId::from(id.slot() + 1), None).is_err());
+    }
+    #[test]
+    fn simulation_force_removed_wire() {
+        // GIVEN a Simulation with a wire which has been removed, and one added after it
+        let mut sim = Simulation::new(SimDuration::from_ticks(10));
+        let removed = sim.add_wire(Wire::new("A", WirePull::Up)).unwrap();
+        sim.remove_wire(removed).unwrap();
+        let added = sim.add_wire(Wire::new("B", WirePull::Up)).unwrap();
+        // WHEN each is forced
+        let stale = sim.force_wire(removed, Some(WirePull::Down));
+        let live = sim.force_wire(added, Some(WirePull::Down));
+        // THEN only the live wire can be forced
+        assert_eq!(Err(SimError::UnknownId(ComponentKind::Wire)), stale);
+        assert_eq!(Ok(()), live);
+    }
+    #[test]
+    fn simulation_wire_domain_removed_wire() {
+        // GIVEN a Simulation with a wire which has been removed, and one added after it
+        let mut sim = Simulation::new(SimDuration::from_ticks(10));
+        let removed = sim.add_wire(Wire::new("A", WirePull::Up)).unwrap();
+        sim.remove_wire(removed).unwrap();
+        let added = sim.add_wire(Wire::new("B", WirePull::Up)).unwrap();
+        // WHEN each is tagged with a clock domain
+        let stale = sim.set_wire_domain(removed, Some("a"));
+        let live = sim.set_wire_domain(added, Some("a"));
+        // THEN only the live wire can be tagged, or have its tag read
+        assert_eq!(Err(SimError::UnknownId(ComponentKind::Wire)), stale);
+        assert_eq!(Ok(()), live);
+        assert_eq!(
+            Err(SimError::UnknownId(ComponentKind::Wire)),
+            sim.wire_domain(removed)
+        );
+        assert_eq!(Ok(Some("a")), sim.wire_domain(added));
+    }
+    #[cfg(feature = "tracing")]
+    #[test]
+    fn simulation_tracing() {
+        // GIVEN a Simulation of an Element driving a Wire, and a subscriber recording spans and events
+        let mut sim = Simulation::new(SimDuration::from_ticks(10));
+        let id = sim.add_wire(Wire::new("Y", WirePull::None)).unwrap();
+        for (name, state) in [("U1", OutputPinState::High), ("U2", OutputPinState::Low)] {
+            let element = sim
+                .add_element(Box::new(FnElement::new(name, &[], &["Y"], move |_, _| {
+                    vec![state]
+                })))
+                .unwrap();
+            if name == "U1" {
+                sim.connect_output(sim.output_pin(element, "Y").unwrap(), id)
+                    .unwrap();
+            }
+        }
+        let recorder = Recorder::default();
+        // WHEN it is stepped, and then stepped again once a second Element drives the Wire the other way
+        tracing::subscriber::with_default(recorder.clone(), || {
+            sim.step().unwrap();
+            let u2 = sim.element_by_name("U2").unwrap();
+            sim.connect_output(sim.output_pin(u2, "Y").unwrap(), id)
+                .unwrap();
+            assert!(sim.step().is_err());
+        });
+        // THEN each step and phase has a span, Elements are noted as they are checked out and in, and the failure is
+        // logged
+        let log = recorder.0.lock().unwrap();
+        let count = |entry: &str| log.iter().filter(|e| *e == entry).count();
+        assert_eq!(2, count("span step"));
+        assert_eq!(6, count("span phase"));
+        assert_eq!(4, count("TRACE checked out"));
+        assert_eq!(4, count("TRACE checked in"));
+        assert_eq!(1, count("ERROR step failed"));
+    }
+    #[test]
+    fn simulation_stimulus() {
+        // GIVEN an event-driven Simulation of an inverter, with a stimulus driving its input by pin and by Wire
+        let mut sim = Simulation::new(SimDuration::from_ticks(10));
+        sim.set_scheduler(Scheduler::EventDriven);
+        let a = sim.add_wire(Wire::new("A", WirePull::None)).unwrap();
+        let y = sim.add_wire(Wire::new("Y", WirePull::None)).unwrap();
+        let u1 = sim
+            .add_element(Box::new(Gate::new("U1", GateKind::Not, 1, 0).unwrap()))
+            .unwrap();
+        sim.connect_input(a, sim.input_pin(u1, "I0").unwrap())
+            .unwrap();
+        sim.connect_output(sim.output_pin(u1, "Y").unwrap(), y)
+            .unwrap();
+        let stimulus = Stimulus::parse("0, U1.I0, high\n55, A, low\n100, A, z").unwrap();
+        sim.add_stimulus(&stimulus).unwrap();
+        // WHEN it is run past each event in turn
+        let mut levels = Vec::new();
+        for time in [40, 90, 140] {
+            sim.run_until(time).unwrap();
+            levels.push((sim.logic(a).unwrap(), sim.logic(y).unwrap()));
+        }
+        // THEN the inverter follows the forced input, holding its output once the input floats, and a stimulus naming
+        // an unknown pin is refused
+        assert_eq!(
+            vec![
+                (LogicValue::High, LogicValue::Low),
+                (LogicValue::Low, LogicValue::High),
+                (LogicValue::HighImpedance, LogicValue::High),
+            ],
+            levels
+        );
+        assert!(sim
+            .add_stimulus(&Stimulus::new().with(200, "U1.I1", None))
+            .is_err());
+    }
+    #[test]
+    fn simulation_step_back() {
+        // GIVEN a Simulation with a wire being pulled down, which has been stepped several times
+        let mut wire = Wire::new("foo", WirePull::Up);
+        wire.set_time_constant(50f32);
+        wire.set_pull(WirePull::Down);
+        let mut sim = Simulation::new(SimDuration::from_ticks(10));
+        let id = sim.add_wire(wire).unwrap();
+        sim.set_checkpoints(3, 4);
+        let mut levels = Vec::new();
+        for _ in 0..8 {
+            levels.push(f32::from(sim.wire(id).unwrap().measure()));
+            assert_eq!(Ok(SimResult::Continuing), sim.step());
+        }
+        // WHEN the simulation is stepped back
+        let result = sim.step_back(3);
+        // THEN the time and wire value match those seen at that point in the original run, and the repeated steps are
+        // not counted
+        assert!(result.is_ok());
+        assert_eq!(50, sim.time());
+        assert_approx_eq!(f32, levels[5], sim.wire(id).unwrap().measure().into());
+        assert_eq!((8, 50), (sim.metrics().steps(), sim.metrics().time()));
+    }
+    #[test]
+    fn simulation_step_back_stimulus() {
+        // GIVEN a Simulation of a clock which stays high, whose Wire is forced low for a while by a stimulus, which has
+        // been run past the end of the force
+        let mut sim = Simulation::new(SimDuration::from_ticks(10));
+        let clock = sim
+            .add_element(Box::new(
+                ClockGenerator::new("X1", 1000.0, 0.5, 0.0, 0, 0.0).unwrap(),
+            ))
+            .unwrap();
+        let id = sim.add_wire(Wire::new("CLK", WirePull::Down)).unwrap();
+        sim.connect_output(sim.output_pin(clock, "CLK").unwrap(), id)
+            .unwrap();
+        sim.set_checkpoints(2, 8);
+        let stimulus = Stimulus::parse("30, CLK, low\n90, CLK, release").unwrap();
+        sim.add_stimulus(&stimulus).unwrap();
+        let mut levels = Vec::new();
+        for _ in 0..12 {
+            sim.step().unwrap();
+            levels.push(f32::from(sim.wire(id).unwrap().measure()));
+        }
+        // WHEN it is stepped back and run forward again
+        sim.step_back(8).unwrap();
+        let mut replayed = Vec::new();
+        for _ in 0..8 {
+            sim.step().unwrap();
+            replayed.push(f32::from(sim.wire(id).unwrap().measure()));
+        }
+        // THEN the replay forces the Wire just as the original run did
+        assert_eq!(levels[4..].to_vec(), replayed);
+    }
+    #[test]
+    fn simulation_step_back_force() {
+        // GIVEN a Simulation of a clock which stays high, whose Wire was forced low between two checkpoints
+        let mut sim = Simulation::new(SimDuration::from_ticks(10));
+        let clock = sim
+            .add_element(Box::new(
+                ClockGenerator::new("X1", 1000.0, 0.5, 0.0, 0, 0.0).unwrap(),
+            ))
+            .unwrap();
+        let id = sim.add_wire(Wire::new("CLK", WirePull::Down)).unwrap();
+        sim.connect_output(sim.output_pin(clock, "CLK").unwrap(), id)
+            .unwrap();
+        sim.set_checkpoints(4, 8);
+        let level = |sim: &Simulation| f32::from(sim.wire(id).unwrap().measure());
+        sim.run_for(5).unwrap();
+        sim.force_wire(id, Some(WirePull::Down)).unwrap();
+        sim.run_for(5).unwrap();
+        // WHEN it is stepped back to after the force, then to before it, and run forward again
+        sim.step_back(3).unwrap();
+        let after = level(&sim);
+        sim.step_back(4).unwrap();
+        let before = level(&sim);
+        sim.run_for(4).unwrap();
+        let rerun = level(&sim);
+        // THEN the force is made again by the replay, and is forgotten once stepped back past
+        assert_eq!(70, sim.time());
+        assert_approx_eq!(f32, 0.0, after);
+        assert_approx_eq!(f32, 1.0, before);
+        assert_approx_eq!(f32, 1.0, rerun);
+    }
+    #[test]
+    fn simulation_step_back_too_far() {
+        // GIVEN a Simulation which has been stepped twice
+        let mut sim = Simulation::new(SimDuration::from_ticks(10));
+        sim.add_wire(Wire::new("foo", WirePull::Up)).unwrap();
+        sim.step().unwrap();
+        sim.step().unwrap();
+        // WHEN the simulation is stepped back past the start
+        let result = sim.step_back(3);
+        // THEN the result is an error and the time is unchanged
+        assert!(result.is_err());
+        assert_eq!(20, sim.time());
+    }
+    #[test]
+    fn simulation_step_back_without_checkpoints() {
+        // GIVEN a Simulation whose checkpoints have not been configured, and one with them disabled, each stepped
+        let stepped = |disable| {
+            let mut sim = Simulation::new(SimDuration::from_ticks(10));
+            sim.add_wire(Wire::new("foo", WirePull::Up)).unwrap();
+            if disable {
+                sim.set_checkpoints(0, 0);
+            }
+            sim.step().unwrap();
+            sim.step().unwrap();
+            sim
+        };
+        let (mut sim, mut disabled) = (stepped(false), stepped(true));
+        // WHEN each is stepped back
+        // THEN the result is an error since there is no checkpoint to restore
+        assert_eq!(Err(SimError::NoCheckpoint), sim.step_back(1));
+        assert_eq!(Err(SimError::NoCheckpoint), disabled.step_back(1));
+    }
+    #[cfg(feature = "std")]
+    #[test]
+    fn simulation_step_back_not_replayable() {
+        // GIVEN a Simulation with checkpoints, holding an LED whose history is shared outside it
+        let mut sim = Simulation::new(SimDuration::from_ticks(10));
+        sim.set_checkpoints(1, 4);
+        sim.add_element(Box::new(Led::new("D1", false))).unwrap();
+        sim.step().unwrap();
+        sim.step().unwrap();
+        // WHEN it is stepped back
+        let result = sim.step_back(1);
+        // THEN it is refused, naming the LED, and the time is unchanged
+        assert_eq!(Err(SimError::NotReplayable("D1".to_string())), result);
+        assert_eq!(20, sim.time());
+    }
+    #[test]
+    fn simulation_step_records_changes() {
+        // GIVEN a Simulation with a settled wire and a wire being pulled away from its default
+        let mut sim = Simulation::new(SimDuration::from_ticks(10));
+        sim.add_wire(Wire::new("foo", WirePull::Down)).unwrap();
+        let mut wire = Wire::new("bar", WirePull::Up);
+        wire.set_time_constant(5f32);
+        wire.set_pull(WirePull::Down);
+        let id = sim.add_wire(wire).unwrap();
+        // WHEN the simulation is stepped
+        let result = sim.step();
+        // THEN only the changing wire is recorded in the change set
+        assert_eq!(Ok(SimResult::Continuing), result);
+        assert_eq!(1, sim.changes().len());
+        assert_eq!(id, sim.changes()[0].id);
+        assert_eq!(WireValue::new(1.0), sim.changes()[0].previous);
+        assert_eq!(sim.wire(id).unwrap().measure(), sim.changes()[0].value);
+    }
+    #[test]
+    fn simulation_step_updates_metrics() {
+        // GIVEN a Simulation with two wires
+        let mut sim = Simulation::new(SimDuration::from_ticks(10));
+        sim.add_wire(Wire::new("foo", WirePull::Up)).unwrap();
+        sim.add_wire(Wire::new("bar", WirePull::Down)).unwrap();
+        let metrics = sim.metrics();
+        // WHEN the simulation is stepped twice
+        sim.step().unwrap();
+        sim.step().unwrap();
+        // THEN the shared metrics reflect the steps taken
+        assert_eq!(2, metrics.steps());
+        assert_eq!(4, metrics.wires_stepped());
+        assert_eq!(0, metrics.timeouts());
+    }
+    #[test]
+    fn simulation_step_profiles() {
+        // GIVEN a Simulation with two wires, with profiling enabled
+        let mut sim = Simulation::new(SimDuration::from_ticks(10));
+        sim.add_wire(Wire::new("foo", WirePull::Up)).unwrap();
+        sim.add_wire(Wire::new("bar", WirePull::Down)).unwrap();
+        assert!(sim.profile().is_none());
+        sim.set_profiling(true);
+        // WHEN the simulation is stepped twice
+        sim.step().unwrap();
+        sim.step().unwrap();
+        // THEN every wire step is profiled
+        let profile = sim.profile().unwrap();
+        assert_eq!(2, profile.wire(WireId::from(0)).unwrap().steps);
+        assert_eq!("bar", profile.wire(WireId::from(1)).unwrap().name);
+        assert_eq!(2, profile.top(5).len());
+    }
+    #[test]
+    fn simulation_step_error_context() {
+        // GIVEN a Simulation with a falling wire in a hierarchy which has been stepped several times
+        let mut sim = Simulation::new(SimDuration::from_ticks(10));
+        sim.add_wire(Wire::new("cpu.clk", WirePull::Down)).unwrap();
+        let mut wire = Wire::new("cpu.bus.d0", WirePull::Up);
+        wire.set_time_constant(5f32);
+        wire.set_pull(WirePull::Down);
+        let id = sim.add_wire(wire).unwrap();
+        for _ in 0..7 {
+            sim.step().unwrap();
+        }
+        // WHEN an error is raised for the wire
+        let err = sim.step_error(
+            SimError::Failed("Boom!".to_string()),
+            Some(Phase::Wires),
+            Some(Component::Wire(id)),
+        );
+        // THEN the error carries the context of the failure
+        assert_eq!(70, err.time);
+        assert_eq!(Some(Phase::Wires), err.phase);
+        assert_eq!(Some("cpu.bus.d0".to_string()), err.component);
+        assert_eq!(Some("cpu.bus".to_string()), err.path);
+        let times: Vec<u64> = err.recent_changes.iter().map(|(time, _)| *time).collect();
+        assert_eq!(vec![30, 40, 50, 60, 70], times);
+        assert!(err
+            .to_string()
+            .starts_with(
+                "Step at time 70 failed in wires phase: Boom!\n  component: cpu.bus.d0 (in cpu.bus)\n  \
+                 recent changes:\n    30: "
+            ));
+    }
+    #[test]
+    fn simulation_sample() {
+        // GIVEN a Simulation with a clock of period 40, which has been stepped past the start of the window to sample
+        let mut sim = Simulation::new(SimDuration::from_ticks(10));
+        sim.set_checkpoints(2, 4);
+        let clock = sim
+            .add_element(Box::new(
+                ClockGenerator::new("X1", 40.0, 0.5, 0.0, 0, 0.0).unwrap(),
+            ))
+            .unwrap();
+        let clk = sim.add_wire(Wire::new("CLK", WirePull::Down)).unwrap();
+        let pulled = sim.add_wire(Wire::new("PULLED", WirePull::Up)).unwrap();
+        sim.connect_output(sim.output_pin(clock, "CLK").unwrap(), clk)
+            .unwrap();
+        for _ in 0..6 {
+            sim.step().unwrap();
+        }
+        // WHEN the Wires are sampled over a window starting in the past, with a period which is not a multiple of the
+        // step interval
+        let (times, levels) = sim.sample(&[pulled, clk], 35, 80, 15).unwrap();
+        // THEN the Simulation is stepped back, and each sample is taken at the first step at or after it is due
+        assert_eq!(vec![40, 50, 70, 80], times);
+        assert_eq!(
+            vec![vec![1.0, 1.0, 1.0, 1.0], vec![0.0, 1.0, 0.0, 0.0]],
+            levels
+        );
+        assert_eq!(80, sim.time());
+    }
+    #[test]
+    fn simulation_sample_errors() {
+        // GIVEN a Simulation which has been stepped without checkpoints
+        let mut sim = Simulation::new(SimDuration::from_ticks(10));
+        sim.set_checkpoints(0, 0);
+        let id = sim.add_wire(Wire::new("foo", WirePull::None)).unwrap();
+        sim.step().unwrap();
+        // WHEN it is sampled with invalid arguments
+        // THEN each is reported
+        assert_eq!(
+            Err(SimError::InvalidArgument(
+                "Sample period must be positive!".to_string()
+            )),
+            sim.sample(&[id], 10, 20, 0)
+        );
+        assert_eq!(
+            Err(SimError::UnknownId(ComponentKind::Wire)),
+            sim.sample(&[id, WireId::from(7)], 10, 20, 10)
+        );
+        assert_eq!(Err(SimError::NoCheckpoint), sim.sample(&[id], 0, 20, 10));
+    }
+    #[test]
+    fn simulation_buses() {
+        // GIVEN a Simulation with a four bit bus, one Wire of which is slow to fall
+        let mut sim = Simulation::new(SimDuration::from_ticks(10));
+        let mut wires: Vec<WireId> = (0..3)
+            .map(|bit| {
+                sim.add_wire(Wire::new(&format!("A{bit}"), WirePull::Down))
+                    .unwrap()
+            })
+            .collect();
+        let mut slow = Wire::new("A3", WirePull::Down);
+        slow.set_time_constant(100.0);
+        wires.push(sim.add_wire(slow).unwrap());
+        sim.define_bus("ADDR", &wires).unwrap();
+        // WHEN values are driven onto it
+        // THEN they are read back once the Wires have settled, least significant bit first, but not while one is
+        // between the thresholds
+        sim.drive_bus("ADDR", 0xf).unwrap();
+        sim.step().unwrap();
+        assert_eq!(Ok(0xf), sim.read_bus("ADDR"));
+        sim.drive_bus("ADDR", 0x6).unwrap();
+        for _ in 0..5 {
+            sim.step().unwrap();
+        }
+        assert_eq!(
+            Err(SimError::Bus {
+                name: "ADDR".to_string(),
+                reason: "wire \"A3\" is indeterminate".to_string()
+            }),
+            sim.read_bus("ADDR")
+        );
+        for _ in 0..50 {
+            sim.step().unwrap();
+        }
+        assert_eq!(Ok(0x6), sim.read_bus("ADDR"));
+        // AND THEN releasing it returns the Wires to their pulls
+        sim.release_bus("ADDR").unwrap();
+        sim.step().unwrap();
+        assert_eq!(Ok(0), sim.read_bus("ADDR"));
+        assert_eq!(Ok(wires.as_slice()), sim.bus("ADDR"));
+        // AND THEN invalid buses and values are rejected
+        assert_eq!(
+            Err(SimError::DuplicateBus("ADDR".to_string())),
+            sim.define_bus("ADDR", &wires)
+        );
+        assert_eq!(
+            Err(SimError::Bus {
+                name: "EMPTY".to_string(),
+                reason: "must have from 1 to 64 wires".to_string()
+            }),
+            sim.define_bus("EMPTY", &[])
+        );
+        assert_eq!(
+            Err(SimError::UnknownId(ComponentKind::Wire)),
+            sim.define_bus("BAD", &[WireId::from(99)])
+        );
+        assert_eq!(
+            Err(SimError::Bus {
+                name: "ADDR".to_string(),
+                reason: "value 0x10 does not fit in 4 bits".to_string()
+            }),
+            sim.drive_bus("ADDR", 0x10)
+        );
+        assert_eq!(
+            Err(SimError::UnknownBus("DATA".to_string())),
+            sim.read_bus("DATA")
+        );
+    }
+    #[test]
+    fn simulation_bus_ranges() {
+        // GIVEN a Simulation with an eight bit bus
+        let mut sim = Simulation::new(SimDuration::from_ticks(10));
+        let wires: Vec<WireId> = (0..8)
+            .map(|bit| {
+                sim.add_wire(Wire::new(&format!("D{bit}"), WirePull::Down))
+                    .unwrap()
+            })
+            .collect();
+        sim.define_bus("DATA", &wires).unwrap();
+        // WHEN ranges of its bits are driven and read
+        sim.drive_bus("DATA[7:4]", 0xa).unwrap();
+        sim.drive_bus("DATA[0]", 1).unwrap();
+        sim.step().unwrap();
+        // THEN only the bits in each range are affected, and single bits can be looked up as Wires
+        assert_eq!(Ok(0xa1), sim.read_bus("DATA"));
+        assert_eq!(Ok(0x1), sim.read_bus("DATA[6:5]"));
+        assert_eq!(Ok(&wires[2..4]), sim.bus("DATA[3:2]"));
+        assert_eq!(Ok(wires[7]), sim.wire_by_target("DATA[7]"));
+        assert_eq!(
+            vec![("DATA", wires.as_slice())],
+            sim.buses().collect::<Vec<_>>()
+        );
+        // AND THEN invalid ranges and names are rejected
+        let invalid = |range: &str| SimError::Bus {
+            name: "DATA".to_string(),
+            reason: format!("invalid bit range [{range}] for 8 wires"),
+        };
+        assert_eq!(Err(invalid("8")), sim.read_bus("DATA[8]"));
+        assert_eq!(Err(invalid("2:5")), sim.read_bus("DATA[2:5]"));
+        assert_eq!(Err(invalid("x")), sim.bus("DATA[x]"));
+        assert_eq!(
+            Err(SimError::UnknownBus("ADDR".to_string())),
+            sim.bus("ADDR[0]")
+        );
+        assert!(sim.wire_by_target("DATA[1:0]").is_err());
+        assert_eq!(
+            Err(SimError::Bus {
+                name: "B[0]".to_string(),
+                reason: "name must not contain brackets".to_string()
+            }),
+            sim.define_bus("B[0]", &wires[..1])
+        );
+    }
+    #[test]
+    fn simulation_modify_netlist() {
+        // GIVEN two identical Simulations of a clock, one of them with an inverter and a spare Wire in a bus
+        let build = || {
+            let mut sim = Simulation::new(SimDuration::from_ticks(10));
+            let clk = sim.add_wire(Wire::new("CLK", WirePull::None)).unwrap();
+            let x1 = sim
+                .add_element(Box::new(
+                    ClockGenerator::new("X1", 100.0, 0.5, 0.0, 0, 0.0).unwrap(),
+                ))
+                .unwrap();
+            sim.connect_output(sim.output_pin(x1, "CLK").unwrap(), clk)
+                .unwrap();
+            (sim, clk)
+        };
+        let (mut reference, _) = build();
+        let (mut sim, clk) = build();
+        let clk_bar = sim.add_wire(Wire::new("/CLK", WirePull::None)).unwrap();
+        let spare = sim.add_wire(Wire::new("SPARE", WirePull::Down)).unwrap();
+        sim.define_bus("B", &[spare]).unwrap();
+        let u1 = sim
+            .add_element(Box::new(Gate::new("U1", GateKind::Not, 1, 0).unwrap()))
+            .unwrap();
+        let input = sim.input_pin(u1, "I0").unwrap();
+        sim.connect_input(clk, input).unwrap();
+        sim.connect_output(sim.output_pin(u1, "Y").unwrap(), clk_bar)
+            .unwrap();
+        sim.set_checkpoints(1, 4);
+        for _ in 0..23 {
+            sim.step().unwrap();
+            reference.step().unwrap();
+        }
+        // WHEN Wires which are still in use are removed
+        // THEN they are kept
+        assert_eq!(
+            Err(SimError::StillConnected {
+                wire: "CLK".to_string(),
+                pins: 2
+            }),
+            sim.remove_wire(clk)
+        );
+        assert_eq!(
+            Err(SimError::InBus {
+                wire: "SPARE".to_string(),
+                bus: "B".to_string()
+            }),
+            sim.remove_wire(spare)
+        );
+        // WHEN the inverter's input is disconnected
+        sim.disconnect_input(input).unwrap();
+        // THEN it cannot be disconnected again
+        assert_eq!(
+            Err(SimError::NotConnected(ComponentKind::InputPin)),
+            sim.disconnect_input(input)
+        );
+        // WHEN the inverter and its output Wire are removed
+        sim.remove_element(u1).unwrap();
+        sim.remove_wire(clk_bar).unwrap();
+        // THEN they are gone, along with the inverter's pins and the checkpoints, without changing the other Ids
+        assert!(sim.element(u1).is_err());
+        assert!(sim.wire(clk_bar).is_err());
+        assert_eq!(vec![ElementId::from(0)], sim.elements().collect::<Vec<_>>());
+        assert_eq!(vec![clk, spare], sim.wires().collect::<Vec<_>>());
+        assert_eq!(
+            Err(SimError::UnknownId(ComponentKind::InputPin)),
+            sim.connect_input(clk, input)
+        );
+        assert_eq!(Err(SimError::NoCheckpoint), sim.step_back(1));
+        // AND THEN the clock carries on from where it was, as it does without the modification
+        assert_eq!(230, sim.time());
+        for _ in 0..20 {
+            sim.step().unwrap();
+            reference.step().unwrap();
+            assert_eq!(
+                reference.wire(clk).unwrap().measure(),
+                sim.wire(clk).unwrap().measure()
+            );
+        }
+    }
+    #[test]
+    fn simulation_reuse_slots() {
+        // GIVEN an event-driven Simulation of a clock driving an inverter, from which the inverter has been removed
+        let mut sim = Simulation::new(SimDuration::from_ticks(10));
+        sim.set_scheduler(Scheduler::EventDriven);
+        let clk = sim.add_wire(Wire::new("CLK", WirePull::None)).unwrap();
+        let clk_bar = sim.add_wire(Wire::new("/CLK", WirePull::None)).unwrap();
+        let x1 = sim
+            .add_element(Box::new(
+                ClockGenerator::new("X1", 100.0, 0.5, 0.0, 0, 0.0).unwrap(),
+            ))
+            .unwrap();
+        sim.connect_output(sim.output_pin(x1, "CLK").unwrap(), clk)
+            .unwrap();
+        let u1 = sim
+            .add_element(Box::new(Gate::new("U1", GateKind::Not, 1, 0).unwrap()))
+            .unwrap();
+        let stale = sim.input_pin(u1, "I0").unwrap();
+        sim.remove_element(u1).unwrap();
+        // WHEN another inverter is added and connected in its place
+        let u2 = sim
+            .add_element(Box::new(Gate::new("U2", GateKind::Not, 1, 0).unwrap()))
+            .unwrap();
+        let input = sim.input_pin(u2, "I0").unwrap();
+        sim.connect_input(clk, input).unwrap();
+        sim.connect_output(sim.output_pin(u2, "Y").unwrap(), clk_bar)
+            .unwrap();
+        // THEN it takes the removed inverter's slots under new Ids, which the removed inverter's Ids do not reach
+        assert_eq!((u1.slot(), 1), (u2.slot(), u2.generation()));
+        assert_eq!((stale.slot(), 1), (input.slot(), input.generation()));
+        assert!(sim.element(u1).is_err());
+        assert!(sim.input_pin(u1, "I0").is_err());
+        assert!(sim.set_element_domain(u1, Some("a")).is_err());
+        assert_eq!(
+            Err(SimError::UnknownId(ComponentKind::InputPin)),
+            sim.connect_input(clk, stale)
+        );
+        assert_eq!(vec![x1, u2], sim.elements().collect::<Vec<_>>());
+        // AND THEN the new inverter follows the clock
+        sim.run_for(7).unwrap();
+        assert_eq!(
+            (LogicValue::Low, LogicValue::High),
+            (sim.logic(clk).unwrap(), sim.logic(clk_bar).unwrap())
+        );
+    }
+    #[test]
+    fn simulation_released_wire_propagates() {
+        // GIVEN a buffer from a floating Wire, held high by a force, to another floating Wire
+        let mut sim = Simulation::new(SimDuration::from_ticks(10));
+        let a = sim.add_wire(Wire::new("A", WirePull::None)).unwrap();
+        let oe = sim.add_wire(Wire::new("/OE", WirePull::Down)).unwrap();
+        let y = sim.add_wire(Wire::new("Y", WirePull::None)).unwrap();
+        let u1 = sim
+            .add_element(Box::new(Buffer::new("U1", 1, 0).unwrap()))
+            .unwrap();
+        sim.connect_input(oe, sim.input_pin(u1, "/OE").unwrap())
+            .unwrap();
+        sim.connect_input(a, sim.input_pin(u1, "A0").unwrap())
+            .unwrap();
+        sim.connect_output(sim.output_pin(u1, "Y0").unwrap(), y)
+            .unwrap();
+        sim.force_wire(a, Some(WirePull::Up)).unwrap();
+        sim.run_for(3).unwrap();
+        assert_eq!(Ok(LogicValue::High), sim.logic(y));
+        // WHEN the force is released, leaving both Wires at their last levels
+        sim.force_wire(a, None).unwrap();
+        sim.run_for(3).unwrap();
+        // THEN the input reads as high impedance and the output as unknown, the buffer driving it unknown rather than
+        // pass on a stale level
+        assert_eq!(Ok(LogicValue::HighImpedance), sim.logic(a));
+        assert_eq!(Ok(LogicValue::Unknown), sim.logic(y));
+        assert_eq!(1.0, f32::from(sim.wire(y).unwrap().measure()));
+        assert_eq!(
+            Ok(OutputPinState::Unknown),
+            sim.output_pin_state(sim.output_pin(u1, "Y0").unwrap())
+        );
+    }
+    #[test]
+    fn simulation_unknown_propagates_through_gates() {
+        // GIVEN a chain of two inverters from a floating Wire, held high by a force
+        let mut sim = Simulation::new(SimDuration::from_ticks(10));
+        let a = sim.add_wire(Wire::new("A", WirePull::None)).unwrap();
+        let b = sim.add_wire(Wire::new("B", WirePull::Down)).unwrap();
+        let y = sim.add_wire(Wire::new("Y", WirePull::Down)).unwrap();
+        let mut outputs = Vec::new();
+        for (name, input, output) in [("U1", a, b), ("U2", b, y)] {
+            let gate = sim
+                .add_element(Box::new(Gate::new(name, GateKind::Not, 1, 0).unwrap()))
+                .unwrap();
+            sim.connect_input(input, sim.input_pin(gate, "I0").unwrap())
+                .unwrap();
+            let pin = sim.output_pin(gate, "Y").unwrap();
+            sim.connect_output(pin, output).unwrap();
+            outputs.push(pin);
+        }
+        sim.force_wire(a, Some(WirePull::Up)).unwrap();
+        sim.run_for(5).unwrap();
+        assert_eq!(Ok(LogicValue::Low), sim.logic(b));
+        assert_eq!(Ok(LogicValue::High), sim.logic(y));
+        // WHEN the force is released, leaving the input at its last level
+        sim.force_wire(a, None).unwrap();
+        sim.run_for(5).unwrap();
+        // THEN both inverters drive their outputs unknown, rather than hold their last levels or release them to
+        // their pulls
+        assert_eq!(Ok(LogicValue::HighImpedance), sim.logic(a));
+        assert_eq!(Ok(LogicValue::Unknown), sim.logic(b));
+        assert_eq!(Ok(LogicValue::Unknown), sim.logic(y));
+        for pin in outputs {
+            assert_eq!(Ok(OutputPinState::Unknown), sim.output_pin_state(pin));
+        }
+    }
+    #[cfg(feature = "serde")]
+    #[test]
+    fn simulation_snapshot_file() {
+        // GIVEN an event-driven Simulation of a clock and a shift register, with a Wire forced, part way through a run
+        let build = || {
+            let mut sim = Simulation::new(SimDuration::from_ticks(10));
+            sim.set_scheduler(Scheduler::EventDriven);
+            let clk = sim.add_wire(Wire::new("CLK", WirePull::None)).unwrap();
+            let d = sim.add_wire(Wire::new("D", WirePull::Down)).unwrap();
+            let q = sim.add_wire(Wire::new("Q3", WirePull::None)).unwrap();
+            let x1 = sim
+                .add_element(Box::new(
+                    ClockGenerator::new("X1", 100.0, 0.5, 0.0, 0, 0.0).unwrap(),
+                ))
+                .unwrap();
+            let u1 = sim
+                .add_element(Box::new(
+                    ShiftRegister::new("U1", 4, ShiftDirection::Right, 5).unwrap(),
+                ))
+                .unwrap();
+            sim.connect_output(sim.output_pin(x1, "CLK").unwrap(), clk)
+                .unwrap();
+            sim.connect_input(clk, sim.input_pin(u1, "CLK").unwrap())
+                .unwrap();
+            sim.connect_input(d, sim.input_pin(u1, "SER").unwrap())
+                .unwrap();
+            sim.connect_output(sim.output_pin(u1, "Q3").unwrap(), q)
+                .unwrap();
+            (sim, d)
+        };
+        let (mut sim, d) = build();
+        sim.run_for(47).unwrap();
+        sim.force_wire(d, Some(WirePull::Up)).unwrap();
+        sim.run_for(16).unwrap();
+        // WHEN a snapshot is saved to a file, the run continues, and a fresh copy of the circuit is restored from it
+        let path =
+            std::env::temp_dir().join(format!("rvfs-sim-snapshot-{}.json", std::process::id()));
+        sim.save_snapshot(&path).unwrap();
+        sim.run_for(60).unwrap();
+        let (mut replay, _) = build();
+        let restored = replay.restore(&path);
+        std::fs::remove_file(&path).unwrap();
+        replay.run_for(60).unwrap();
+        // THEN the copy carries on exactly as the original did, the force included
+        assert_eq!(Ok(()), restored);
+        assert_eq!(sim.time(), replay.time());
+        assert_eq!("no differences", sim.diff(&replay).to_string());
+    }
+    #[cfg(feature = "serde")]
+    #[test]
+    fn simulation_snapshot_mismatch() {
+        // GIVEN a snapshot of a Simulation with a Wire and an inverter
+        let build = |wire: &str| {
+            let mut sim = Simulation::new(SimDuration::from_ticks(10));
+            let a = sim.add_wire(Wire::new(wire, WirePull::Up)).unwrap();
+            let u1 = sim
+                .add_element(Box::new(Gate::new("U1", GateKind::Not, 1, 0).unwrap()))
+                .unwrap();
+            sim.connect_input(a, sim.input_pin(u1, "I0").unwrap())
+                .unwrap();
+            sim
+        };
+        let mut sim = build("A");
+        sim.run_for(3).unwrap();
+        let snapshot = sim.snapshot().unwrap();
+        // WHEN it is restored into Simulations built differently
+        let mut renamed = build("B");
+        let mut extended = build("A");
+        extended.add_wire(Wire::new("C", WirePull::None)).unwrap();
+        // THEN it is refused, leaving them unchanged
+        assert_eq!(
+            Err(SimError::SnapshotMismatch("wire 0 differs".to_string())),
+            renamed.restore_snapshot(&snapshot)
+        );
+        assert_eq!(
+            Err(SimError::SnapshotMismatch(
+                "1 wires, expected 2".to_string()
+            )),
+            extended.restore_snapshot(&snapshot)
+        );
+        assert_eq!(0, renamed.time());
+        assert!(build("B").diff(&renamed).is_empty());
+        // WHEN a Simulation holds an Element which cannot save its state
+        sim.add_element(Box::new(FnElement::new("U2", &[], &["Y"], |_, _| {
+            vec![OutputPinState::High]
+        })))
+        .unwrap();
+        // THEN no snapshot can be taken
+        assert_eq!(
+            Err(SimError::Failed(
+                "Element \"U2\" cannot be saved in a snapshot".to_string()
+            )),
+            sim.snapshot().map(|_| ())
+        );
+    }
+    #[test]
+    fn simulation_event_driven_matches_time_stepped() {
+        // GIVEN a circuit of a clock driving gates with delays shorter and longer than the interval, one driving no
+        // Wire, and an idle gate on a pulled-up Wire, built for each scheduler
+        let build = |scheduler: Scheduler| {
+            let mut sim = Simulation::new(SimDuration::from_ticks(10));
+            sim.set_scheduler(scheduler);
+            let clk = sim.add_wire(Wire::new("CLK", WirePull::None)).unwrap();
+            let a = sim.add_wire(Wire::new("A", WirePull::Up)).unwrap();
+            let x1 = sim
+                .add_element(Box::new(
+                    ClockGenerator::new("X1", 70.0, 0.5, 0.0, 0, 0.0).unwrap(),
+                ))
+                .unwrap();
+            sim.connect_output(sim.output_pin(x1, "CLK").unwrap(), clk)
+                .unwrap();
+            for (name, delay, input, output) in [
+                ("U1", 0, clk, true),
+                ("U2", 15, clk, true),
+                ("U3", 25, clk, false),
+                ("U4", 5, a, true),
+            ] {
+                let id = sim
+                    .add_element(Box::new(Gate::new(name, GateKind::Not, 1, delay).unwrap()))
+                    .unwrap();
+                sim.connect_input(input, sim.input_pin(id, "I0").unwrap())
+                    .unwrap();
+                if output {
+                    let wire = sim.add_wire(Wire::new(name, WirePull::None)).unwrap();
+                    sim.connect_output(sim.output_pin(id, "Y").unwrap(), wire)
+                        .unwrap();
+                }
+            }
+            sim.set_checkpoints(8, 4);
+            (sim, a)
+        };
+        let (mut stepped, a) = build(Scheduler::TimeStepped);
+        let (mut event_driven, _) = build(Scheduler::EventDriven);
+        // WHEN both are stepped, with the idle Wire forced low and released along the way, and stepped back
+        for step in 0..40 {
+            if step == 10 || step == 20 {
+                let pull = (step == 10).then_some(WirePull::Down);
+                stepped.force_wire(a, pull).unwrap();
+                event_driven.force_wire(a, pull).unwrap();
+            }
+            stepped.step().unwrap();
+            event_driven.step().unwrap();
+            // THEN they make the same changes and reach the same state on every step
+            assert_eq!(stepped.changes(), event_driven.changes());
+            assert!(stepped.diff(&event_driven).is_empty());
+        }
+        stepped.step_back(5).unwrap();
+        event_driven.step_back(5).unwrap();
+        for _ in 0..10 {
+            stepped.step().unwrap();
+            event_driven.step().unwrap();
+            assert_eq!(stepped.changes(), event_driven.changes());
+            assert!(stepped.diff(&event_driven).is_empty());
+        }
+    }
+    #[test]
+    fn simulation_event_driven_skips_idle() {
+        // GIVEN a bank of inverters on a pulled-up Wire, built for each scheduler
+        let build = |scheduler: Scheduler| {
+            let mut sim = Simulation::new(SimDuration::from_ticks(10));
+            sim.set_scheduler(scheduler);
+            let a = sim.add_wire(Wire::new("A", WirePull::Up)).unwrap();
+            for i in 0..20 {
+                let name = format!("U{i}");
+                let id = sim
+                    .add_element(Box::new(Gate::new(&name, GateKind::Not, 1, 0).unwrap()))
+                    .unwrap();
+                let y = sim.add_wire(Wire::new(&name, WirePull::None)).unwrap();
+                sim.connect_input(a, sim.input_pin(id, "I0").unwrap())
+                    .unwrap();
+                sim.connect_output(sim.output_pin(id, "Y").unwrap(), y)
+                    .unwrap();
+            }
+            sim
+        };
+        let mut stepped = build(Scheduler::TimeStepped);
+        let mut event_driven = build(Scheduler::EventDriven);
+        for _ in 0..5 {
+            stepped.step().unwrap();
+            event_driven.step().unwrap();
+        }
+        let before = (
+            stepped.metrics().wires_stepped(),
+            event_driven.metrics().wires_stepped(),
+        );
+        // WHEN both are stepped further, once the circuit has settled
+        for _ in 0..10 {
+            stepped.step().unwrap();
+            event_driven.step().unwrap();
+        }
+        // THEN every Wire is stepped every step when time stepped, but none are when event driven
+        assert_eq!(Scheduler::EventDriven, event_driven.scheduler());
+        assert_eq!(before.0 + 210, stepped.metrics().wires_stepped());
+        assert_eq!(before.1, event_driven.metrics().wires_stepped());
+        assert!(stepped.diff(&event_driven).is_empty());
+    }
 }
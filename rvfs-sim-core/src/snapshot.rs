@@ -0,0 +1,164 @@
+//! Snapshots of the full state of a Simulation, which can be written to a file so that a long run can be resumed, or
+//! replayed from a known state, later or in another process.
+//!
+//! A [Snapshot] is taken with [Simulation::snapshot](crate::sim::Simulation::snapshot) and holds the simulation time
+//! and seed, the level, pull and any force of each Wire, the state of each pin, and the internal state of each Element
+//! as [saved](crate::element::Element::save_state) by the Element itself.  It does not hold the circuit: it is
+//! [restored](crate::sim::Simulation::restore_snapshot) into a Simulation built from the same netlist, whose Wires and
+//! Elements are matched by Id and checked by name.  Elements driven from outside the Simulation, such as through
+//! sockets or host handles, cannot be saved, so neither can a Simulation holding them.
+//!
+//! Snapshots are stored as JSON.
+
+use crate::ipin::InputPin;
+use crate::opin::OutputPin;
+use crate::prelude::*;
+use crate::wire::WirePull;
+use crate::wirevalue::WireValue;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+/// The saved state of a Wire.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct WireState {
+    /// Name of the Wire.
+    pub(crate) name: String,
+    /// Present level of the Wire.
+    pub(crate) value: WireValue,
+    /// Active pull of the Wire.
+    pub(crate) pull: WirePull,
+    /// Pull forced on the Wire in place of its drivers, if any.
+    pub(crate) force: Option<WirePull>,
+    /// Whether the drivers of the Wire drove it both high and low, or to an unknown level, at the most recent step.
+    pub(crate) unknown: bool,
+}
+
+/// The saved state of an Element and its pins.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct ElementState {
+    /// Name of the Element.
+    pub(crate) name: String,
+    /// Internal state of the Element.
+    pub(crate) state: serde_json::Value,
+    /// The InputPins of the Element, in the Element's order.
+    pub(crate) inputs: Vec<InputPin>,
+    /// The OutputPins of the Element, in the Element's order.
+    pub(crate) outputs: Vec<OutputPin>,
+}
+
+/// A saved copy of the state of a Simulation.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Snapshot {
+    /// Simulation time at which the snapshot was taken.
+    pub(crate) time: u64,
+    /// Seed of the Simulation's random number generator.
+    pub(crate) seed: u64,
+    /// State of each Wire, or `None` for a removed Wire, indexed by Wire Id.
+    pub(crate) wires: Vec<Option<WireState>>,
+    /// State of each Element, or `None` for a removed Element, indexed by Element Id.
+    pub(crate) elements: Vec<Option<ElementState>>,
+}
+
+impl Snapshot {
+    /// Obtain the simulation time at which the snapshot was taken.
+    pub fn time(&self) -> u64 {
+        self.time
+    }
+
+    /// Write the snapshot out as JSON.
+    ///
+    /// # Parameters
+    ///
+    /// - `writer`: Destination of the snapshot.
+    pub fn write(&self, writer: impl Write) -> Result<(), String> {
+        serde_json::to_writer(writer, self).map_err(|err| format!("Cannot write snapshot: {err}"))
+    }
+
+    /// Read a snapshot written by [write](Self::write).
+    ///
+    /// # Parameters
+    ///
+    /// - `reader`: Source of the snapshot.
+    pub fn read(reader: impl Read) -> Result<Self, String> {
+        serde_json::from_reader(reader).map_err(|err| format!("Cannot read snapshot: {err}"))
+    }
+
+    /// Save the snapshot to a file, replacing any file already there.
+    ///
+    /// # Parameters
+    ///
+    /// - `path`: Path to the file.
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let file = File::create(path)
+            .map_err(|err| format!("Cannot create snapshot \"{}\": {err}", path.display()))?;
+        let mut writer = BufWriter::new(file);
+        self.write(&mut writer)?;
+        writer
+            .flush()
+            .map_err(|err| format!("Cannot write snapshot \"{}\": {err}", path.display()))
+    }
+
+    /// Load a snapshot from a file written by [save](Self::save).
+    ///
+    /// # Parameters
+    ///
+    /// - `path`: Path to the file.
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let file = File::open(path)
+            .map_err(|err| format!("Cannot open snapshot \"{}\": {err}", path.display()))?;
+        Self::read(BufReader::new(file))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::opin::OutputPinState;
+    use crate::time::SimDuration;
+
+    #[test]
+    fn snapshot_write_read() {
+        // GIVEN a snapshot of a Wire and an Element
+        let snapshot = Snapshot {
+            time: 120,
+            seed: 7,
+            wires: vec![
+                Some(WireState {
+                    name: "CLK".to_string(),
+                    value: WireValue::new(0.25),
+                    pull: WirePull::Up,
+                    force: Some(WirePull::Down),
+                    unknown: false,
+                }),
+                None,
+            ],
+            elements: vec![Some(ElementState {
+                name: "U1".to_string(),
+                state: serde_json::json!([true, 3]),
+                inputs: vec![InputPin::new("D")],
+                outputs: vec![OutputPin::new(
+                    "Q",
+                    SimDuration::from_ticks(5),
+                    OutputPinState::High,
+                )],
+            })],
+        };
+        // WHEN it is written out and read back
+        let mut text = Vec::new();
+        snapshot.write(&mut text).unwrap();
+        let copy = Snapshot::read(text.as_slice());
+        // THEN the copy is identical
+        assert_eq!(Ok(snapshot), copy);
+        assert_eq!(120, copy.unwrap().time());
+    }
+    #[test]
+    fn snapshot_read_invalid() {
+        // GIVEN text which is not a snapshot
+        // WHEN it is read
+        let result = Snapshot::read("{\"time\": 1}".as_bytes());
+        // THEN it is refused
+        assert!(result.unwrap_err().starts_with("Cannot read snapshot: "));
+    }
+}
@@ -0,0 +1,181 @@
+//! Stimulus files, which drive the Wires of a circuit through a schedule of level changes, so that a circuit can be
+//! exercised testbench-style without writing a driving Element.
+//!
+//! A [Stimulus] is a list of events, each naming a time, a target and a value.  The target is a Wire, given by its
+//! name, or a pin, given as `<ELEMENT>.<PIN>`, which stands for the Wire connected to it.  The value is `high` or `1`,
+//! `low` or `0`, `z` to leave the Wire floating, or `release` to hand the Wire back to its drivers.  Once
+//! [added](crate::sim::Simulation::add_stimulus) to a Simulation, each event
+//! [forces](crate::sim::Simulation::force_wire) its Wire at the start of the step in which its time falls, before the
+//! InputPins are stepped.
+//!
+//! Stimulus files hold one event per line as comma separated `time, target, value`, with blank lines and lines
+//! starting with `#` ignored:
+//!
+//! ```text
+//! # Hold the reset for the first 100 time units, then clock in a 1
+//! 0, /RESET, low
+//! 100, /RESET, release
+//! 150, U1.D, high
+//! ```
+
+use crate::prelude::*;
+use crate::wire::WirePull;
+#[cfg(feature = "std")]
+use std::fs;
+#[cfg(feature = "std")]
+use std::path::Path;
+
+/// A scheduled change of the level of a Wire.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StimulusEvent {
+    /// Simulation time at which the change is made.
+    pub time: u64,
+    /// Name of the Wire, or `<ELEMENT>.<PIN>` for the Wire connected to a pin.
+    pub target: String,
+    /// The pull to force on the Wire, or `None` to release it.
+    pub force: Option<WirePull>,
+}
+
+/// A schedule of changes to the levels of Wires.
+///
+/// # Example
+///
+/// ```
+/// # use rvfs_sim_core::stimulus::Stimulus;
+/// # use rvfs_sim_core::wire::WirePull;
+/// let stimulus = Stimulus::parse("0, /RESET, low\n100, /RESET, release\n").unwrap();
+///
+/// assert_eq!(Stimulus::new().with(0, "/RESET", Some(WirePull::Down)).with(100, "/RESET", None), stimulus);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Stimulus {
+    /// The events, in the order they were given.
+    events: Vec<StimulusEvent>,
+}
+
+impl Stimulus {
+    /// Create a new, empty Stimulus.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add an event.
+    ///
+    /// # Parameters
+    ///
+    /// - `time`: Simulation time at which the change is made.
+    /// - `target`: Name of the Wire, or `<ELEMENT>.<PIN>` for the Wire connected to a pin.
+    /// - `force`: The pull to force on the Wire, or `None` to release it.
+    pub fn with(mut self, time: u64, target: &str, force: Option<WirePull>) -> Self {
+        self.events.push(StimulusEvent {
+            time,
+            target: target.to_string(),
+            force,
+        });
+        self
+    }
+
+    /// Obtain the events, in the order they were given.
+    pub fn events(&self) -> &[StimulusEvent] {
+        &self.events
+    }
+
+    /// Parse the text of a stimulus file.
+    ///
+    /// # Parameters
+    ///
+    /// - `text`: The events, one per line as `time, target, value`.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut stimulus = Self::new();
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+            let [time, target, value] = fields[..] else {
+                return Err(format!(
+                    "Line {}: expected time, target and value",
+                    number + 1
+                ));
+            };
+            let time = time
+                .parse()
+                .map_err(|_| format!("Line {}: invalid time \"{time}\"", number + 1))?;
+            let force = parse_force(value).map_err(|err| format!("Line {}: {err}", number + 1))?;
+            stimulus = stimulus.with(time, target, force);
+        }
+
+        Ok(stimulus)
+    }
+
+    /// Load a stimulus file.
+    ///
+    /// # Parameters
+    ///
+    /// - `path`: Path to the file.
+    #[cfg(feature = "std")]
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text = fs::read_to_string(path)
+            .map_err(|err| format!("Failed to read stimulus {}: {err}", path.display()))?;
+        Self::parse(&text).map_err(|message| format!("{}: {message}", path.display()))
+    }
+}
+
+/// Parse the value of a stimulus event into the pull to force on its Wire, or `None` to release the Wire.
+///
+/// # Parameters
+///
+/// - `text`: `high` or `1`, `low` or `0`, `z`, or `release`.
+pub fn parse_force(text: &str) -> Result<Option<WirePull>, String> {
+    match text {
+        "high" | "1" => Ok(Some(WirePull::Up)),
+        "low" | "0" => Ok(Some(WirePull::Down)),
+        "z" => Ok(Some(WirePull::None)),
+        "release" => Ok(None),
+        _ => Err(format!(
+            "Invalid value \"{text}\", expected high, low, z or release"
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stimulus_parse() {
+        // GIVEN the text of a stimulus file with comments, blank lines and every kind of value
+        let text = "# reset\n0, /RESET, 0\n\n  100,/RESET,release\n150, U1.D, high\n200, D, z\n";
+        // WHEN it is parsed
+        let stimulus = Stimulus::parse(text).unwrap();
+        // THEN every event is read in order
+        assert_eq!(
+            Stimulus::new()
+                .with(0, "/RESET", Some(WirePull::Down))
+                .with(100, "/RESET", None)
+                .with(150, "U1.D", Some(WirePull::Up))
+                .with(200, "D", Some(WirePull::None)),
+            stimulus
+        );
+        assert_eq!(4, stimulus.events().len());
+    }
+    #[test]
+    fn stimulus_parse_errors() {
+        // GIVEN lines with a field missing, a bad time and a bad value
+        // WHEN they are parsed
+        // THEN each is refused with its line number
+        assert_eq!(
+            Err("Line 2: expected time, target and value".to_string()),
+            Stimulus::parse("0, A, high\n10, A\n")
+        );
+        assert_eq!(
+            Err("Line 1: invalid time \"soon\"".to_string()),
+            Stimulus::parse("soon, A, high")
+        );
+        assert_eq!(
+            Err("Line 1: Invalid value \"on\", expected high, low, z or release".to_string()),
+            Stimulus::parse("0, A, on")
+        );
+    }
+}
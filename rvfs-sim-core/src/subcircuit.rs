@@ -0,0 +1,392 @@
+//! Hierarchical sub-circuits, so that a group of Wires and Elements can be described once and instantiated many times.
+//!
+//! A [Subcircuit] is a template of Wires, Elements and instances of other Subcircuits, with ports through which it
+//! connects to the circuit it is [instantiated](crate::circuit::Circuit::instantiate) in.  Everything in an instance is
+//! named beneath the instance, with levels separated by `.`, so the Wire `carry` of an instance `alu` of a Subcircuit,
+//! itself within an instance `cpu`, is named `cpu.alu.carry`.  Such names can be matched by the hierarchy
+//! [scopes](crate::select::Pattern::Scope) of signal selectors.  A port has no Wire of its own, but stands for the Wire
+//! of the parent circuit it is mapped to.
+//!
+//! ```
+//! # use rvfs_sim_core::circuit::Circuit;
+//! # use rvfs_sim_core::element::{Parameters, Registry};
+//! # use rvfs_sim_core::subcircuit::Subcircuit;
+//! # use rvfs_sim_core::time::SimDuration;
+//! # use rvfs_sim_core::wire::{Wire, WirePull};
+//! let half_adder = Subcircuit::new("half_adder")
+//!     .with_port("A")
+//!     .with_port("B")
+//!     .with_port("S")
+//!     .with_port("C")
+//!     .with_element("U1", "xor", Parameters::new(), &[("I0", "A"), ("I1", "B"), ("Y", "S")])
+//!     .with_element("U2", "and", Parameters::new(), &[("I0", "A"), ("I1", "B"), ("Y", "C")]);
+//! let full_adder = Subcircuit::new("full_adder")
+//!     .with_port("A")
+//!     .with_port("B")
+//!     .with_port("CIN")
+//!     .with_port("S")
+//!     .with_port("COUT")
+//!     .with_wire(Wire::new("S1", WirePull::None))
+//!     .with_wire(Wire::new("C1", WirePull::None))
+//!     .with_wire(Wire::new("C2", WirePull::None))
+//!     .with_instance("H1", &half_adder, &[("A", "A"), ("B", "B"), ("S", "S1"), ("C", "C1")])
+//!     .with_instance("H2", &half_adder, &[("A", "S1"), ("B", "CIN"), ("S", "S"), ("C", "C2")])
+//!     .with_element("U1", "or", Parameters::new(), &[("I0", "C1"), ("I1", "C2"), ("Y", "COUT")]);
+//!
+//! let mut circuit = Circuit::new(SimDuration::from_ticks(10));
+//! for (name, pull) in [("A", WirePull::Up), ("B", WirePull::Up), ("CIN", WirePull::Down)] {
+//!     circuit.add_wire(Wire::new(name, pull)).unwrap();
+//! }
+//! for name in ["S", "COUT"] {
+//!     circuit.add_wire(Wire::new(name, WirePull::None)).unwrap();
+//! }
+//! let ports = [("A", "A"), ("B", "B"), ("CIN", "CIN"), ("S", "S"), ("COUT", "COUT")];
+//! circuit.instantiate(&Registry::standard(), &full_adder, "FA0", &ports).unwrap();
+//! for _ in 0..5 {
+//!     circuit.simulation_mut().step().unwrap();
+//! }
+//!
+//! let level = |name| f32::from(circuit.simulation().wire(circuit.wire(name).unwrap()).unwrap().measure());
+//! assert_eq!((0.0, 1.0), (level("S"), level("COUT")));
+//! assert_eq!(1.0, level("FA0.C1"));
+//! assert!(circuit.element("FA0.H2.U1").is_ok());
+//! ```
+
+use crate::circuit::Circuit;
+use crate::element::{Parameters, Registry};
+use crate::wire::Wire;
+use std::collections::BTreeMap;
+
+/// An Element of a Subcircuit, instantiated by kind from a [Registry].
+#[derive(Debug, Clone)]
+struct ElementTemplate {
+    /// Name of the Element within the Subcircuit.
+    name: String,
+    /// Name of the kind of Element.
+    kind: String,
+    /// Parameters of the Element.
+    parameters: Parameters,
+    /// Pairs of the name of a pin and the name of the Wire or port it is connected to.
+    pins: Vec<(String, String)>,
+}
+
+/// An instance of one Subcircuit within another.
+#[derive(Debug, Clone)]
+struct Instance {
+    /// Name of the instance within the enclosing Subcircuit.
+    name: String,
+    /// The Subcircuit instantiated.
+    subcircuit: Subcircuit,
+    /// Pairs of the name of a port and the name of the enclosing Subcircuit's Wire or port it is mapped to.
+    ports: Vec<(String, String)>,
+}
+
+/// A template of Wires, Elements and instances of other Subcircuits, connected to the enclosing circuit by ports.
+#[derive(Debug, Clone)]
+pub struct Subcircuit {
+    /// Name of the Subcircuit, for errors.
+    name: String,
+    /// Names of the ports.
+    ports: Vec<String>,
+    /// Wires of the Subcircuit, named within it.
+    wires: Vec<Wire>,
+    /// Elements of the Subcircuit.
+    elements: Vec<ElementTemplate>,
+    /// Instances of other Subcircuits.
+    instances: Vec<Instance>,
+}
+
+impl Subcircuit {
+    /// Create a new, empty Subcircuit.
+    ///
+    /// # Parameters
+    ///
+    /// - `name`: Name of the Subcircuit, by which errors refer to it.
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            ports: Vec::new(),
+            wires: Vec::new(),
+            elements: Vec::new(),
+            instances: Vec::new(),
+        }
+    }
+
+    /// Add a port, which stands for the Wire of the enclosing circuit it is mapped to by each instance.
+    ///
+    /// # Parameters
+    ///
+    /// - `name`: Name of the port.
+    pub fn with_port(mut self, name: &str) -> Self {
+        self.ports.push(name.to_string());
+        self
+    }
+
+    /// Add a Wire, of which each instance has its own.
+    ///
+    /// # Parameters
+    ///
+    /// - `wire`: The Wire, named within the Subcircuit.
+    pub fn with_wire(mut self, wire: Wire) -> Self {
+        self.wires.push(wire);
+        self
+    }
+
+    /// Add an Element, of which each instance has its own.
+    ///
+    /// # Parameters
+    ///
+    /// - `name`: Name of the Element within the Subcircuit.
+    /// - `kind`: Name of the kind of Element, in the Registry the Subcircuit is instantiated from.
+    /// - `parameters`: Parameters of the Element.
+    /// - `pins`: Pairs of the name of a pin, input or output, and the name of the Wire or port to connect it to.
+    pub fn with_element(
+        mut self,
+        name: &str,
+        kind: &str,
+        parameters: Parameters,
+        pins: &[(&str, &str)],
+    ) -> Self {
+        self.elements.push(ElementTemplate {
+            name: name.to_string(),
+            kind: kind.to_string(),
+            parameters,
+            pins: pairs(pins),
+        });
+        self
+    }
+
+    /// Add an instance of another Subcircuit.
+    ///
+    /// # Parameters
+    ///
+    /// - `name`: Name of the instance within the Subcircuit.
+    /// - `subcircuit`: The Subcircuit to instantiate.
+    /// - `ports`: Pairs of the name of a port of the instantiated Subcircuit and the name of the Wire or port of this
+    ///   one to map it to.
+    pub fn with_instance(
+        mut self,
+        name: &str,
+        subcircuit: &Subcircuit,
+        ports: &[(&str, &str)],
+    ) -> Self {
+        self.instances.push(Instance {
+            name: name.to_string(),
+            subcircuit: subcircuit.clone(),
+            ports: pairs(ports),
+        });
+        self
+    }
+
+    /// Obtain the name of the Subcircuit.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Obtain the names of the ports, in the order they were added.
+    pub fn ports(&self) -> &[String] {
+        &self.ports
+    }
+
+    /// Add an instance of the Subcircuit to a Circuit, along with the instances within it.
+    ///
+    /// # Parameters
+    ///
+    /// - `circuit`: The Circuit.
+    /// - `registry`: The Registry of Element kinds.
+    /// - `path`: Hierarchical name of the instance.
+    /// - `ports`: Pairs of the name of a port and the full name of the Circuit's Wire to map it to.
+    pub(crate) fn instantiate(
+        &self,
+        circuit: &mut Circuit,
+        registry: &Registry,
+        path: &str,
+        ports: &[(&str, &str)],
+    ) -> Result<(), String> {
+        // The full name of the Circuit's Wire which each name within the Subcircuit stands for.
+        let mut names: BTreeMap<&str, String> = BTreeMap::new();
+        for (port, wire) in ports {
+            if !self.ports.iter().any(|name| name == port) {
+                return Err(format!(
+                    "Subcircuit \"{}\" has no port \"{port}\"",
+                    self.name
+                ));
+            }
+            circuit.wire(wire)?;
+            if names.insert(port, wire.to_string()).is_some() {
+                return Err(format!("Port \"{port}\" of \"{path}\" is mapped twice"));
+            }
+        }
+        if let Some(port) = self
+            .ports
+            .iter()
+            .find(|port| !names.contains_key(port.as_str()))
+        {
+            return Err(format!("Port \"{port}\" of \"{path}\" is not mapped"));
+        }
+        for wire in &self.wires {
+            let name = format!("{path}.{}", wire.name());
+            if names.contains_key(wire.name().as_str()) {
+                return Err(format!("Duplicate wire \"{name}\""));
+            }
+            circuit.add_wire(wire.renamed(&name))?;
+            names.insert(wire.name(), name);
+        }
+
+        for element in &self.elements {
+            let name = format!("{path}.{}", element.name);
+            let built = registry.create(&element.kind, &name, &element.parameters)?;
+            circuit.add_element(&name, built, &resolve(&names, &element.pins, &self.name)?)?;
+        }
+        for instance in &self.instances {
+            instance.subcircuit.instantiate(
+                circuit,
+                registry,
+                &format!("{path}.{}", instance.name),
+                &resolve(&names, &instance.ports, &self.name)?,
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Resolve the Wire or port names of pairs of a pin or port and a name within a Subcircuit into full Wire names.
+///
+/// # Parameters
+///
+/// - `names`: Full name of the Wire which each name within the Subcircuit stands for.
+/// - `pairs`: The pairs.
+/// - `subcircuit`: Name of the Subcircuit, for errors.
+fn resolve<'a>(
+    names: &'a BTreeMap<&str, String>,
+    pairs: &'a [(String, String)],
+    subcircuit: &str,
+) -> Result<Vec<(&'a str, &'a str)>, String> {
+    pairs
+        .iter()
+        .map(|(pin, wire)| match names.get(wire.as_str()) {
+            Some(name) => Ok((pin.as_str(), name.as_str())),
+            None => Err(format!(
+                "Subcircuit \"{subcircuit}\" has no wire or port named \"{wire}\""
+            )),
+        })
+        .collect()
+}
+
+/// Copy pairs of names into owned strings.
+///
+/// # Parameters
+///
+/// - `pairs`: The pairs.
+fn pairs(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+    pairs
+        .iter()
+        .map(|(a, b)| (a.to_string(), b.to_string()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::time::SimDuration;
+    use crate::wire::WirePull;
+
+    /// Build a Circuit with an input and an output Wire, and an instance of a Subcircuit between them.
+    ///
+    /// # Parameters
+    ///
+    /// - `subcircuit`: The Subcircuit.
+    /// - `ports`: Port mapping of the instance.
+    fn build(subcircuit: &Subcircuit, ports: &[(&str, &str)]) -> Result<Circuit, String> {
+        let mut circuit = Circuit::new(SimDuration::from_ticks(10));
+        circuit.add_wire(Wire::new("IN", WirePull::Up))?;
+        circuit.add_wire(Wire::new("OUT", WirePull::None))?;
+        circuit.instantiate(&Registry::standard(), subcircuit, "top", ports)?;
+        Ok(circuit)
+    }
+
+    #[test]
+    fn subcircuit_nested_instances() {
+        // GIVEN a pair of inverters, and a Subcircuit of two pairs in series
+        let pair = Subcircuit::new("pair")
+            .with_port("A")
+            .with_port("Y")
+            .with_wire(Wire::new("MID", WirePull::None))
+            .with_element("U1", "not", Parameters::new(), &[("I0", "A"), ("Y", "MID")])
+            .with_element("U2", "not", Parameters::new(), &[("I0", "MID"), ("Y", "Y")]);
+        let chain = Subcircuit::new("chain")
+            .with_port("A")
+            .with_port("Y")
+            .with_wire(Wire::new("MID", WirePull::None))
+            .with_instance("P1", &pair, &[("A", "A"), ("Y", "MID")])
+            .with_instance("P2", &pair, &[("A", "MID"), ("Y", "Y")]);
+        // WHEN the chain is instantiated and simulated
+        let mut circuit = build(&chain, &[("A", "IN"), ("Y", "OUT")]).unwrap();
+        for _ in 0..6 {
+            circuit.simulation_mut().step().unwrap();
+        }
+        // THEN every Wire and Element is named beneath its instance, and the input passes through all four inverters
+        let sim = circuit.simulation();
+        let names: Vec<String> = sim
+            .wires()
+            .map(|id| sim.wire(id).unwrap().name().clone())
+            .collect();
+        assert_eq!(
+            vec!["IN", "OUT", "top.MID", "top.P1.MID", "top.P2.MID"],
+            names
+        );
+        let element = circuit.element("top.P2.U1").unwrap();
+        assert_eq!("top.P2.U1", sim.element(element).unwrap().name());
+        assert_eq!(4, sim.elements().count());
+        let level = |name| f32::from(sim.wire(circuit.wire(name).unwrap()).unwrap().measure());
+        assert_eq!(
+            (1.0, 1.0, 0.0),
+            (level("OUT"), level("top.MID"), level("top.P2.MID"))
+        );
+        assert_eq!(["A", "Y"], chain.ports());
+        assert_eq!("chain", chain.name());
+    }
+    #[test]
+    fn subcircuit_errors() {
+        // GIVEN a Subcircuit of an inverter, and variants with a missing Wire and a Wire named as a port
+        let inverter = Subcircuit::new("inverter")
+            .with_port("A")
+            .with_port("Y")
+            .with_element("U1", "not", Parameters::new(), &[("I0", "A"), ("Y", "Y")]);
+        let missing = inverter.clone().with_element(
+            "U2",
+            "not",
+            Parameters::new(),
+            &[("I0", "B"), ("Y", "Y")],
+        );
+        let clash = inverter.clone().with_wire(Wire::new("A", WirePull::None));
+        // WHEN they are instantiated with valid and invalid port mappings
+        // THEN each problem is reported
+        assert!(build(&inverter, &[("A", "IN"), ("Y", "OUT")]).is_ok());
+        assert_eq!(
+            "Subcircuit \"inverter\" has no port \"Q\"",
+            build(&inverter, &[("A", "IN"), ("Q", "OUT")]).unwrap_err()
+        );
+        assert_eq!(
+            "Port \"Y\" of \"top\" is not mapped",
+            build(&inverter, &[("A", "IN")]).unwrap_err()
+        );
+        assert_eq!(
+            "Port \"A\" of \"top\" is mapped twice",
+            build(&inverter, &[("A", "IN"), ("A", "OUT")]).unwrap_err()
+        );
+        assert_eq!(
+            "No wire named \"CLK\"",
+            build(&inverter, &[("A", "CLK"), ("Y", "OUT")]).unwrap_err()
+        );
+        assert_eq!(
+            "Subcircuit \"inverter\" has no wire or port named \"B\"",
+            build(&missing, &[("A", "IN"), ("Y", "OUT")]).unwrap_err()
+        );
+        assert_eq!(
+            "Duplicate wire \"top.A\"",
+            build(&clash, &[("A", "IN"), ("Y", "OUT")]).unwrap_err()
+        );
+    }
+}
@@ -0,0 +1,215 @@
+//! Summary of a Simulation run, for display at the end of the run or for consumption by other tools.
+
+use crate::json;
+use crate::metrics::Metrics;
+use crate::monitor::Monitors;
+use std::fmt;
+use std::time::Duration;
+
+/// Summary of a Simulation run.
+///
+/// The figures taken from the Simulation itself are obtained from its [metrics](crate::sim::Simulation::metrics),
+/// which remain available after the Simulation has been consumed by [running](crate::sim::Simulation::run) it.
+/// Assertion results, coverage and warnings come from other subsystems and are added with the builder methods.
+///
+/// # Example
+///
+/// ```
+/// # use rvfs_sim_core::monitor::Monitors;
+/// # use rvfs_sim_core::sim::Simulation;
+/// # use rvfs_sim_core::summary::RunSummary;
+/// # use rvfs_sim_core::time::SimDuration;
+/// # use rvfs_sim_core::wire::{Wire, WirePull};
+/// let mut sim = Simulation::new(SimDuration::from_ticks(10));
+/// sim.add_wire(Wire::new("/RESET", WirePull::Up)).unwrap();
+/// let monitors = Monitors::new();
+/// sim.add_tracer(Box::new(monitors.clone()));
+///
+/// sim.step().unwrap();
+/// let summary = RunSummary::new(&sim.metrics()).with_monitors(&monitors);
+/// println!("{summary}");
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct RunSummary {
+    /// Seed of the Simulation's random number generator, from which the run can be reproduced.
+    pub seed: u64,
+    /// Simulation time covered by the run.
+    pub simulated_time: u64,
+    /// Wall-clock time taken by the run.
+    pub wall_time: Duration,
+    /// Number of steps completed.
+    pub steps: u64,
+    /// Number of Wire value changes made.
+    pub transitions: u64,
+    /// Number of assertion checks which passed.
+    pub assertions_passed: u64,
+    /// Number of assertion checks which failed.
+    pub assertions_failed: u64,
+    /// Coverage achieved as a percentage, if coverage was measured.
+    pub coverage: Option<f64>,
+    /// Warnings raised during the run.
+    pub warnings: Vec<String>,
+}
+
+impl RunSummary {
+    /// Create a summary from the metrics of a Simulation.
+    ///
+    /// # Parameters
+    ///
+    /// - `metrics`: Metrics of the Simulation run.
+    pub fn new(metrics: &Metrics) -> Self {
+        Self {
+            seed: metrics.seed(),
+            simulated_time: metrics.time(),
+            wall_time: metrics.elapsed(),
+            steps: metrics.steps(),
+            transitions: metrics.wire_changes(),
+            assertions_passed: 0,
+            assertions_failed: 0,
+            coverage: None,
+            warnings: Vec::new(),
+        }
+    }
+
+    /// Include the results of a set of monitors in the assertion counts.
+    ///
+    /// # Parameters
+    ///
+    /// - `monitors`: Monitors checked during the run.
+    pub fn with_monitors(mut self, monitors: &Monitors) -> Self {
+        for status in monitors.statuses() {
+            self.assertions_passed += status.passes;
+            self.assertions_failed += status.failures;
+        }
+        self
+    }
+
+    /// Record the coverage achieved.
+    ///
+    /// # Parameters
+    ///
+    /// - `percent`: Coverage achieved as a percentage.
+    pub fn with_coverage(mut self, percent: f64) -> Self {
+        self.coverage = Some(percent);
+        self
+    }
+
+    /// Add a warning raised during the run.
+    ///
+    /// # Parameters
+    ///
+    /// - `warning`: Description of the warning.
+    pub fn with_warning(mut self, warning: &str) -> Self {
+        self.warnings.push(warning.to_string());
+        self
+    }
+
+    /// Query whether the run was free of assertion failures.
+    pub fn passed(&self) -> bool {
+        self.assertions_failed == 0
+    }
+
+    /// Render the summary as a JSON object.
+    pub fn to_json(&self) -> String {
+        let coverage = match self.coverage {
+            Some(percent) => percent.to_string(),
+            None => "null".to_string(),
+        };
+        let warnings: Vec<String> = self.warnings.iter().map(|w| json::string(w)).collect();
+        format!(
+            "{{\"seed\":{},\"simulated_time\":{},\"wall_time\":{},\"steps\":{},\"transitions\":{},\
+             \"assertions_passed\":{},\"assertions_failed\":{},\"coverage\":{coverage},\"warnings\":[{}]}}",
+            self.seed,
+            self.simulated_time,
+            self.wall_time.as_secs_f64(),
+            self.steps,
+            self.transitions,
+            self.assertions_passed,
+            self.assertions_failed,
+            warnings.join(",")
+        )
+    }
+}
+
+impl fmt::Display for RunSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "Run summary: {}",
+            if self.passed() { "PASS" } else { "FAIL" }
+        )?;
+        writeln!(f, "  seed:           {}", self.seed)?;
+        writeln!(f, "  simulated time: {}", self.simulated_time)?;
+        writeln!(f, "  wall time:      {:?}", self.wall_time)?;
+        writeln!(f, "  steps:          {}", self.steps)?;
+        writeln!(f, "  transitions:    {}", self.transitions)?;
+        writeln!(
+            f,
+            "  assertions:     {} passed, {} failed",
+            self.assertions_passed, self.assertions_failed
+        )?;
+        match self.coverage {
+            Some(percent) => writeln!(f, "  coverage:       {percent:.1}%")?,
+            None => writeln!(f, "  coverage:       not measured")?,
+        }
+        writeln!(f, "  warnings:       {}", self.warnings.len())?;
+        for warning in &self.warnings {
+            writeln!(f, "    {warning}")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::monitor::{Condition, Rule};
+    use crate::trace::testing::{falling_wire_sim, trace};
+
+    #[test]
+    fn summary_from_run() {
+        // GIVEN a Simulation with a falling wire, monitored to be always high
+        let (mut sim, id) = falling_wire_sim(10.0);
+        sim.set_seed(5);
+        let monitors = Monitors::new();
+        monitors.add("reset high", Rule::Always(Condition::High(id)));
+        // WHEN it is run for a few steps and summarised
+        trace(&mut sim, monitors.clone(), 3);
+        let summary = RunSummary::new(&sim.metrics())
+            .with_monitors(&monitors)
+            .with_warning("reset asserted");
+        // THEN the summary reflects the run
+        assert_eq!(5, summary.seed);
+        assert_eq!(30, summary.simulated_time);
+        assert_eq!(3, summary.steps);
+        assert_eq!(3, summary.transitions);
+        assert_eq!(0, summary.assertions_passed);
+        assert_eq!(3, summary.assertions_failed);
+        assert!(!summary.passed());
+        assert!(summary
+            .to_string()
+            .contains("  warnings:       1\n    reset asserted\n"));
+    }
+    #[test]
+    fn summary_to_json() {
+        // GIVEN a summary with coverage and a warning
+        let summary = RunSummary {
+            seed: 42,
+            simulated_time: 100,
+            wall_time: Duration::from_millis(1500),
+            steps: 10,
+            transitions: 4,
+            assertions_passed: 2,
+            assertions_failed: 1,
+            coverage: Some(87.5),
+            warnings: vec!["bus \"fight\"".to_string()],
+        };
+        // WHEN it is rendered as JSON
+        // THEN every field is present
+        assert_eq!(
+            "{\"seed\":42,\"simulated_time\":100,\"wall_time\":1.5,\"steps\":10,\"transitions\":4,\
+             \"assertions_passed\":2,\"assertions_failed\":1,\"coverage\":87.5,\"warnings\":[\"bus \\\"fight\\\"\"]}",
+            summary.to_json()
+        );
+    }
+}
@@ -0,0 +1,562 @@
+//! Asynchronous testbenches, in which tests, drivers and monitors are written as straight-line `async` code awaiting
+//! edges and delays, rather than as state machines advanced on every step.
+//!
+//! A [Testbench] owns a Simulation and runs a test future to completion, stepping the Simulation whenever every task
+//! is waiting.  Tasks wait through a [TestbenchHandle], and drive the circuit through the host side of Elements such as
+//! [GpioPort](crate::element::gpio::GpioPort).  Background tasks, such as protocol drivers and monitors which run for
+//! the whole test, are [spawned](TestbenchHandle::spawn) alongside it.
+//!
+//! Tasks are polled after every step of the Simulation, so no waker is needed: a task is simply polled again once
+//! the Simulation has moved on.
+
+use crate::sim::{SimResult, Simulation};
+use crate::trace::{Logic, DEFAULT_HIGH_THRESHOLD, DEFAULT_LOW_THRESHOLD};
+use crate::{ComponentId, WireId};
+use std::cell::RefCell;
+use std::future::Future;
+use std::mem;
+use std::pin::{pin, Pin};
+use std::rc::Rc;
+use std::task::{Context, Poll, Waker};
+
+/// A task run by a Testbench, which fails with an error message.
+type Task = Pin<Box<dyn Future<Output = Result<(), String>>>>;
+
+/// The state of a Testbench shared with its tasks.
+#[derive(Default)]
+struct Shared {
+    /// The simulation time.
+    time: u64,
+    /// The number of steps taken.
+    steps: u64,
+    /// The logic level of each Wire.
+    levels: Vec<Logic>,
+    /// The logic level of each Wire before the last step.
+    previous: Vec<Logic>,
+    /// Tasks spawned and not yet polled.
+    spawned: Vec<Task>,
+}
+
+impl Shared {
+    /// Obtain the logic level of a Wire, and its level before the last step.
+    ///
+    /// # Parameters
+    ///
+    /// - `wire`: Id of the Wire.
+    fn levels(&self, wire: WireId) -> Result<(Logic, Logic), String> {
+        match (
+            self.levels.get(wire.index()),
+            self.previous.get(wire.index()),
+        ) {
+            (Some(level), Some(previous)) => Ok((*level, *previous)),
+            _ => Err(format!("Invalid wire id {wire}")),
+        }
+    }
+}
+
+/// A handle through which the tasks of a [Testbench] observe the Simulation and wait on it.
+#[derive(Clone)]
+pub struct TestbenchHandle {
+    /// The shared state of the Testbench.
+    shared: Rc<RefCell<Shared>>,
+}
+
+impl TestbenchHandle {
+    /// Obtain the present simulation time.
+    pub fn time(&self) -> u64 {
+        self.shared.borrow().time
+    }
+
+    /// Obtain the logic level of a Wire, if it is definite.
+    ///
+    /// # Parameters
+    ///
+    /// - `wire`: Id of the Wire.
+    pub fn level(&self, wire: WireId) -> Result<Option<bool>, String> {
+        Ok(match self.shared.borrow().levels(wire)?.0 {
+            Logic::Low => Some(false),
+            Logic::High => Some(true),
+            Logic::Unknown => None,
+        })
+    }
+
+    /// Wait for a Wire to go high.  Only an edge at a later step completes the wait, even if the Wire went high at the
+    /// present one.
+    ///
+    /// # Parameters
+    ///
+    /// - `wire`: Id of the Wire.
+    pub fn rising_edge(&self, wire: WireId) -> EdgeFuture {
+        self.edge(wire, Logic::High)
+    }
+
+    /// Wait for a Wire to go low.  Only an edge at a later step completes the wait, even if the Wire went low at the
+    /// present one.
+    ///
+    /// # Parameters
+    ///
+    /// - `wire`: Id of the Wire.
+    pub fn falling_edge(&self, wire: WireId) -> EdgeFuture {
+        self.edge(wire, Logic::Low)
+    }
+
+    /// Wait for a Wire to reach a logic level.
+    ///
+    /// # Parameters
+    ///
+    /// - `wire`: Id of the Wire.
+    /// - `to`: The level to wait for.
+    fn edge(&self, wire: WireId, to: Logic) -> EdgeFuture {
+        EdgeFuture {
+            shared: self.shared.clone(),
+            wire,
+            to,
+            since: None,
+        }
+    }
+
+    /// Wait for an amount of simulation time to elapse, completing at the first step at or after it.
+    ///
+    /// # Parameters
+    ///
+    /// - `duration`: The simulation time to wait.
+    pub fn timer(&self, duration: u64) -> TimerFuture {
+        TimerFuture {
+            shared: self.shared.clone(),
+            duration,
+            deadline: None,
+        }
+    }
+
+    /// Wait for the next step of the Simulation.
+    pub fn next_step(&self) -> StepFuture {
+        StepFuture {
+            shared: self.shared.clone(),
+            since: None,
+        }
+    }
+
+    /// Start a background task, which runs alongside the test until it completes or the test does.  An error from the
+    /// task fails the test.
+    ///
+    /// # Parameters
+    ///
+    /// - `task`: The task.
+    pub fn spawn(&self, task: impl Future<Output = Result<(), String>> + 'static) {
+        self.shared.borrow_mut().spawned.push(Box::pin(task));
+    }
+}
+
+/// A future completing when a Wire next reaches a logic level, created by [TestbenchHandle::rising_edge] and
+/// [TestbenchHandle::falling_edge].
+pub struct EdgeFuture {
+    /// The shared state of the Testbench.
+    shared: Rc<RefCell<Shared>>,
+    /// Id of the Wire.
+    wire: WireId,
+    /// The level waited for.
+    to: Logic,
+    /// The step at which the wait started, once polled.
+    since: Option<u64>,
+}
+
+impl Future for EdgeFuture {
+    type Output = Result<(), String>;
+
+    fn poll(mut self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let shared = self.shared.clone();
+        let shared = shared.borrow();
+        let (level, previous) = shared.levels(self.wire)?;
+        match self.since {
+            Some(since) if shared.steps > since && level == self.to && previous != self.to => {
+                Poll::Ready(Ok(()))
+            }
+            Some(_) => Poll::Pending,
+            None => {
+                self.since = Some(shared.steps);
+                Poll::Pending
+            }
+        }
+    }
+}
+
+/// A future completing once an amount of simulation time has elapsed, created by [TestbenchHandle::timer].
+pub struct TimerFuture {
+    /// The shared state of the Testbench.
+    shared: Rc<RefCell<Shared>>,
+    /// The simulation time to wait.
+    duration: u64,
+    /// The simulation time at which the wait ends, once polled.
+    deadline: Option<u64>,
+}
+
+impl Future for TimerFuture {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let time = self.shared.borrow().time;
+        let duration = self.duration;
+        let deadline = *self.deadline.get_or_insert(time + duration);
+        if time >= deadline {
+            Poll::Ready(())
+        } else {
+            Poll::Pending
+        }
+    }
+}
+
+/// A future completing at the next step of the Simulation, created by [TestbenchHandle::next_step].
+pub struct StepFuture {
+    /// The shared state of the Testbench.
+    shared: Rc<RefCell<Shared>>,
+    /// The step at which the wait started, once polled.
+    since: Option<u64>,
+}
+
+impl Future for StepFuture {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let steps = self.shared.borrow().steps;
+        if *self.since.get_or_insert(steps) < steps {
+            Poll::Ready(())
+        } else {
+            Poll::Pending
+        }
+    }
+}
+
+/// A Simulation driven by asynchronous tasks.
+///
+/// # Example
+///
+/// ```
+/// # use rvfs_sim_core::element::clocks::ClockGenerator;
+/// # use rvfs_sim_core::sim::Simulation;
+/// # use rvfs_sim_core::testbench::Testbench;
+/// # use rvfs_sim_core::time::SimDuration;
+/// # use rvfs_sim_core::wire::{Wire, WirePull};
+/// let mut sim = Simulation::new(SimDuration::from_ticks(10));
+/// let clock = sim
+///     .add_element(Box::new(ClockGenerator::new("X1", 100.0, 0.5, 0.0, 0, 0.0).unwrap()))
+///     .unwrap();
+/// let clk = sim.add_wire(Wire::new("CLK", WirePull::Down)).unwrap();
+/// sim.connect_output(sim.output_pin(clock, "CLK").unwrap(), clk)
+///     .unwrap();
+///
+/// let mut testbench = Testbench::new(sim);
+/// let tb = testbench.handle();
+/// let time = testbench
+///     .run(
+///         async move {
+///             for _ in 0..3 {
+///                 tb.rising_edge(clk).await?;
+///             }
+///             Ok(tb.time())
+///         },
+///         1000,
+///     )
+///     .unwrap();
+///
+/// assert_eq!(210, time);
+/// ```
+pub struct Testbench {
+    /// The Simulation.
+    sim: Simulation,
+    /// The shared state, also held by every handle.
+    shared: Rc<RefCell<Shared>>,
+    /// Background tasks which have not completed.
+    tasks: Vec<Task>,
+}
+
+impl Testbench {
+    /// Create a new Testbench.
+    ///
+    /// # Parameters
+    ///
+    /// - `sim`: The Simulation to drive, with all its Wires and Elements already added.
+    pub fn new(sim: Simulation) -> Self {
+        Self {
+            sim,
+            shared: Rc::new(RefCell::new(Shared::default())),
+            tasks: Vec::new(),
+        }
+    }
+
+    /// Obtain the Simulation.
+    pub fn simulation(&self) -> &Simulation {
+        &self.sim
+    }
+
+    /// Take back the Simulation.
+    pub fn into_simulation(self) -> Simulation {
+        self.sim
+    }
+
+    /// Obtain a handle for tasks to observe and wait on the Simulation.
+    pub fn handle(&self) -> TestbenchHandle {
+        TestbenchHandle {
+            shared: self.shared.clone(),
+        }
+    }
+
+    /// Run a test, stepping the Simulation until it completes, and return its result.
+    ///
+    /// Background tasks still running when the test completes are dropped.  The test fails if a background task
+    /// fails, if the Simulation fails or finishes first, or if the timeout elapses first.
+    ///
+    /// # Parameters
+    ///
+    /// - `test`: The test.
+    /// - `timeout`: Simulation time after which the test is abandoned.
+    pub fn run<T>(
+        &mut self,
+        test: impl Future<Output = Result<T, String>>,
+        timeout: u64,
+    ) -> Result<T, String> {
+        let mut test = pin!(test);
+        let mut cx = Context::from_waker(Waker::noop());
+        let deadline = self.sim.time() + timeout;
+        self.sample(true)?;
+
+        let result = loop {
+            if let Poll::Ready(result) = test.as_mut().poll(&mut cx) {
+                break result;
+            }
+            if let Err(error) = self.poll_tasks(&mut cx) {
+                break Err(error);
+            }
+            if self.sim.time() >= deadline {
+                break Err(format!(
+                    "Test timed out at time {}, after {timeout}",
+                    self.sim.time()
+                ));
+            }
+            match self.sim.step() {
+                Ok(SimResult::Continuing | SimResult::Breakpoint(_) | SimResult::Limit(_)) => (),
+                Ok(SimResult::Finished) => {
+                    break Err(format!(
+                        "Simulation finished at time {} before the test completed",
+                        self.sim.time()
+                    ))
+                }
+                Err(error) => break Err(error.to_string()),
+            }
+            if let Err(error) = self.sample(false) {
+                break Err(error);
+            }
+        };
+
+        self.tasks.clear();
+        self.shared.borrow_mut().spawned.clear();
+        result
+    }
+
+    /// Poll every background task, including those spawned while polling, dropping those which complete.
+    ///
+    /// # Parameters
+    ///
+    /// - `cx`: The context to poll in.
+    fn poll_tasks(&mut self, cx: &mut Context<'_>) -> Result<(), String> {
+        let mut pending = Vec::new();
+        loop {
+            let spawned = mem::take(&mut self.shared.borrow_mut().spawned);
+            if spawned.is_empty() && self.tasks.is_empty() {
+                break;
+            }
+            for mut task in self.tasks.drain(..).chain(spawned) {
+                match task.as_mut().poll(cx) {
+                    Poll::Ready(result) => result?,
+                    Poll::Pending => pending.push(task),
+                }
+            }
+        }
+        self.tasks = pending;
+
+        Ok(())
+    }
+
+    /// Record the simulation time and Wire levels for the tasks.
+    ///
+    /// # Parameters
+    ///
+    /// - `initial`: Whether this is the first sample of a run, which has no previous step.
+    fn sample(&mut self, initial: bool) -> Result<(), String> {
+        let levels = self
+            .sim
+            .wires()
+            .map(|id| {
+                Ok(Logic::from_level(
+                    self.sim.wire(id)?.measure(),
+                    DEFAULT_LOW_THRESHOLD,
+                    DEFAULT_HIGH_THRESHOLD,
+                ))
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+
+        let mut shared = self.shared.borrow_mut();
+        shared.time = self.sim.time();
+        shared.previous = if initial {
+            levels.clone()
+        } else {
+            mem::replace(&mut shared.levels, levels.clone())
+        };
+        shared.levels = levels;
+        if !initial {
+            shared.steps += 1;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::element::clocks::ClockGenerator;
+    use crate::element::gates::{Gate, GateKind};
+    use crate::element::gpio::{Direction, GpioPort};
+    use crate::time::SimDuration;
+    use crate::wire::{Wire, WirePull};
+
+    /// Create a Simulation with a clock of period 100 on a Wire, returning the Simulation and the Wire.
+    fn clocked() -> (Simulation, WireId) {
+        let mut sim = Simulation::new(SimDuration::from_ticks(10));
+        let clock = sim
+            .add_element(Box::new(
+                ClockGenerator::new("X1", 100.0, 0.5, 0.0, 0, 0.0).unwrap(),
+            ))
+            .unwrap();
+        let clk = sim.add_wire(Wire::new("CLK", WirePull::Down)).unwrap();
+        sim.connect_output(sim.output_pin(clock, "CLK").unwrap(), clk)
+            .unwrap();
+        (sim, clk)
+    }
+
+    #[test]
+    fn testbench_edges_and_timers() {
+        // GIVEN a clocked Simulation
+        let (sim, clk) = clocked();
+        let mut testbench = Testbench::new(sim);
+        let tb = testbench.handle();
+        // WHEN a test waits on edges, timers and steps
+        let times = testbench.run(
+            async move {
+                let mut times = Vec::new();
+                tb.rising_edge(clk).await?;
+                times.push(tb.time());
+                tb.falling_edge(clk).await?;
+                times.push(tb.time());
+                tb.timer(25).await;
+                times.push(tb.time());
+                tb.next_step().await;
+                times.push(tb.time());
+                tb.timer(0).await;
+                times.push(tb.time());
+                assert_eq!(Ok(Some(false)), tb.level(clk));
+                Ok(times)
+            },
+            1000,
+        );
+        // THEN each wait completes at the step which satisfies it
+        assert_eq!(Ok(vec![10, 60, 90, 100, 100]), times);
+    }
+    #[test]
+    fn testbench_drivers_and_monitors() {
+        // GIVEN a clocked Simulation with a GPIO bit driving an inverter
+        let (mut sim, clk) = clocked();
+        let port = GpioPort::new("P1", 1, 0).unwrap();
+        let host = port.host();
+        let port = sim.add_element(Box::new(port)).unwrap();
+        let inverter = sim
+            .add_element(Box::new(Gate::new("U1", GateKind::Not, 1, 0).unwrap()))
+            .unwrap();
+        let data = sim.add_wire(Wire::new("D", WirePull::None)).unwrap();
+        let inverted = sim.add_wire(Wire::new("/D", WirePull::None)).unwrap();
+        sim.connect_output(sim.output_pin(port, "P0").unwrap(), data)
+            .unwrap();
+        sim.connect_input(data, sim.input_pin(port, "P0").unwrap())
+            .unwrap();
+        sim.connect_input(data, sim.input_pin(inverter, "I0").unwrap())
+            .unwrap();
+        sim.connect_output(sim.output_pin(inverter, "Y").unwrap(), inverted)
+            .unwrap();
+        host.set_direction(0, Direction::Output).unwrap();
+        let mut testbench = Testbench::new(sim);
+        let tb = testbench.handle();
+        // WHEN a driver writes a pattern on falling clock edges and a monitor samples on rising edges
+        let samples = Rc::new(RefCell::new(Vec::new()));
+        let result = testbench.run(
+            {
+                let samples = samples.clone();
+                async move {
+                    let driver = tb.clone();
+                    tb.spawn(async move {
+                        for value in [true, false, true, true] {
+                            driver.falling_edge(clk).await?;
+                            host.write(0, value)?;
+                        }
+                        Ok(())
+                    });
+                    let monitor = tb.clone();
+                    tb.spawn(async move {
+                        loop {
+                            monitor.rising_edge(clk).await?;
+                            samples.borrow_mut().push(monitor.level(inverted)?);
+                        }
+                    });
+                    tb.timer(450).await;
+                    Ok(())
+                }
+            },
+            1000,
+        );
+        // THEN the monitor sees the inverse of the pattern, one clock later
+        assert_eq!(Ok(()), result);
+        assert_eq!(
+            vec![None, Some(false), Some(true), Some(false), Some(false)],
+            *samples.borrow()
+        );
+    }
+    #[test]
+    fn testbench_failures() {
+        // GIVEN a clocked Simulation
+        let (sim, clk) = clocked();
+        let mut testbench = Testbench::new(sim);
+        let tb = testbench.handle();
+        // WHEN tests time out, fail in the background, or wait on a missing Wire
+        let timeout = testbench.run(
+            {
+                let tb = tb.clone();
+                async move {
+                    tb.timer(500).await;
+                    Ok(())
+                }
+            },
+            200,
+        );
+        let background = testbench.run(
+            {
+                let tb = tb.clone();
+                async move {
+                    let failing = tb.clone();
+                    tb.spawn(async move {
+                        failing.rising_edge(clk).await?;
+                        Err("protocol violation".to_string())
+                    });
+                    tb.timer(1000).await;
+                    Ok(())
+                }
+            },
+            2000,
+        );
+        let missing = testbench.run(async move { tb.rising_edge(WireId::from(7)).await }, 100);
+        // THEN each run fails with a message
+        assert_eq!(
+            Err("Test timed out at time 200, after 200".to_string()),
+            timeout
+        );
+        assert_eq!(Err("protocol violation".to_string()), background);
+        assert_eq!(Err("Invalid wire id 7".to_string()), missing);
+    }
+}
@@ -0,0 +1,255 @@
+//! Spans of simulation time, kept distinct from plain numbers so that they cannot be mixed up with counts, or with
+//! times in other units.
+//!
+//! Simulation time advances in ticks, and one tick is taken to be one nanosecond wherever a physical time or frequency
+//! is converted, as with the frequencies of clocks.  A [SimDuration] is built from ticks or from a physical unit, and
+//! converts back explicitly, so that a component configured in microseconds cannot be silently handed nanoseconds.
+
+use core::fmt;
+use core::ops::{Add, AddAssign, Div, Mul, Rem, Sub, SubAssign};
+
+/// Number of ticks in a second.
+pub const TICKS_PER_SECOND: u64 = 1_000_000_000;
+
+/// A span of simulation time, counted in ticks.
+///
+/// # Example
+///
+/// ```
+/// # use rvfs_sim_core::time::SimDuration;
+/// let interval = SimDuration::from_micros(2);
+/// let delay = SimDuration::from_ticks(500);
+///
+/// assert_eq!(2_000, interval.ticks());
+/// assert_eq!(SimDuration::from_ticks(2_500), interval + delay);
+/// assert_eq!(4, interval / delay);
+/// assert_eq!(2e-6, interval.as_secs_f64());
+/// ```
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SimDuration(u64);
+
+impl SimDuration {
+    /// A span of no time.
+    pub const ZERO: Self = Self(0);
+    /// The longest span of time which can be represented.
+    pub const MAX: Self = Self(u64::MAX);
+
+    /// Create a span of time from a number of ticks.
+    ///
+    /// # Parameters
+    ///
+    /// - `ticks`: The number of ticks.
+    pub const fn from_ticks(ticks: u64) -> Self {
+        Self(ticks)
+    }
+
+    /// Create a span of time from a number of nanoseconds.
+    ///
+    /// # Parameters
+    ///
+    /// - `nanos`: The number of nanoseconds.
+    pub const fn from_nanos(nanos: u64) -> Self {
+        Self(nanos.saturating_mul(TICKS_PER_SECOND / 1_000_000_000))
+    }
+
+    /// Create a span of time from a number of microseconds.
+    ///
+    /// # Parameters
+    ///
+    /// - `micros`: The number of microseconds.
+    pub const fn from_micros(micros: u64) -> Self {
+        Self(micros.saturating_mul(TICKS_PER_SECOND / 1_000_000))
+    }
+
+    /// Create a span of time from a number of milliseconds.
+    ///
+    /// # Parameters
+    ///
+    /// - `millis`: The number of milliseconds.
+    pub const fn from_millis(millis: u64) -> Self {
+        Self(millis.saturating_mul(TICKS_PER_SECOND / 1_000))
+    }
+
+    /// Create a span of time from a number of seconds, rounded to the nearest tick.
+    ///
+    /// # Parameters
+    ///
+    /// - `secs`: The number of seconds, which saturates at zero and at the longest span.
+    pub fn from_secs_f64(secs: f64) -> Self {
+        Self((secs * TICKS_PER_SECOND as f64 + 0.5) as u64)
+    }
+
+    /// Obtain the number of ticks in the span.
+    pub const fn ticks(self) -> u64 {
+        self.0
+    }
+
+    /// Obtain the span in seconds.
+    pub fn as_secs_f64(self) -> f64 {
+        self.0 as f64 / TICKS_PER_SECOND as f64
+    }
+
+    /// Query whether the span is of no time.
+    pub const fn is_zero(self) -> bool {
+        self.0 == 0
+    }
+
+    /// Add a span of time, or obtain None if the result would overflow.
+    ///
+    /// # Parameters
+    ///
+    /// - `other`: The span to add.
+    pub const fn checked_add(self, other: Self) -> Option<Self> {
+        match self.0.checked_add(other.0) {
+            Some(ticks) => Some(Self(ticks)),
+            None => None,
+        }
+    }
+
+    /// Multiply the span, or obtain None if the result would overflow.
+    ///
+    /// # Parameters
+    ///
+    /// - `factor`: The factor to multiply by.
+    pub const fn checked_mul(self, factor: u64) -> Option<Self> {
+        match self.0.checked_mul(factor) {
+            Some(ticks) => Some(Self(ticks)),
+            None => None,
+        }
+    }
+
+    /// Subtract a span of time, stopping at zero.
+    ///
+    /// # Parameters
+    ///
+    /// - `other`: The span to subtract.
+    pub const fn saturating_sub(self, other: Self) -> Self {
+        Self(self.0.saturating_sub(other.0))
+    }
+}
+
+impl fmt::Display for SimDuration {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ticks", self.0)
+    }
+}
+
+impl Add for SimDuration {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self(self.0 + other.0)
+    }
+}
+
+impl AddAssign for SimDuration {
+    fn add_assign(&mut self, other: Self) {
+        self.0 += other.0;
+    }
+}
+
+impl Sub for SimDuration {
+    type Output = Self;
+
+    fn sub(self, other: Self) -> Self {
+        Self(self.0 - other.0)
+    }
+}
+
+impl SubAssign for SimDuration {
+    fn sub_assign(&mut self, other: Self) {
+        self.0 -= other.0;
+    }
+}
+
+impl Mul<u64> for SimDuration {
+    type Output = Self;
+
+    fn mul(self, factor: u64) -> Self {
+        Self(self.0 * factor)
+    }
+}
+
+/// Dividing by a count gives a shorter span.
+impl Div<u64> for SimDuration {
+    type Output = Self;
+
+    fn div(self, divisor: u64) -> Self {
+        Self(self.0 / divisor)
+    }
+}
+
+/// Dividing by a span gives the number of whole spans which fit.
+impl Div for SimDuration {
+    type Output = u64;
+
+    fn div(self, divisor: Self) -> u64 {
+        self.0 / divisor.0
+    }
+}
+
+impl Rem for SimDuration {
+    type Output = Self;
+
+    fn rem(self, divisor: Self) -> Self {
+        Self(self.0 % divisor.0)
+    }
+}
+
+impl From<core::time::Duration> for SimDuration {
+    /// Convert a wall-clock duration, saturating at the longest span.
+    fn from(duration: core::time::Duration) -> Self {
+        let ticks = duration.as_nanos() * u128::from(TICKS_PER_SECOND) / 1_000_000_000;
+        Self(ticks.min(u128::from(u64::MAX)) as u64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::*;
+    use core::time::Duration;
+
+    #[test]
+    fn sim_duration_units() {
+        // GIVEN spans of time in different units
+        // WHEN they are created
+        // THEN each is counted in nanosecond ticks
+        assert_eq!(7, SimDuration::from_ticks(7).ticks());
+        assert_eq!(7, SimDuration::from_nanos(7).ticks());
+        assert_eq!(7_000, SimDuration::from_micros(7).ticks());
+        assert_eq!(7_000_000, SimDuration::from_millis(7).ticks());
+        assert_eq!(1_500_000_000, SimDuration::from_secs_f64(1.5).ticks());
+        assert_eq!(SimDuration::ZERO, SimDuration::from_secs_f64(-1.0));
+        assert_eq!(SimDuration::MAX, SimDuration::from_millis(u64::MAX));
+        assert_eq!(
+            SimDuration::from_millis(3),
+            SimDuration::from(Duration::from_millis(3))
+        );
+        assert_eq!("40 ticks", SimDuration::from_ticks(40).to_string());
+    }
+    #[test]
+    fn sim_duration_arithmetic() {
+        // GIVEN two spans of time
+        let a = SimDuration::from_ticks(25);
+        let b = SimDuration::from_ticks(10);
+        // WHEN they are combined
+        // THEN the results are spans, or counts when dividing one span by another
+        assert_eq!(SimDuration::from_ticks(35), a + b);
+        assert_eq!(SimDuration::from_ticks(15), a - b);
+        assert_eq!(SimDuration::from_ticks(50), a * 2);
+        assert_eq!(SimDuration::from_ticks(5), a / 5);
+        assert_eq!(2, a / b);
+        assert_eq!(SimDuration::from_ticks(5), a % b);
+        assert_eq!(SimDuration::ZERO, b.saturating_sub(a));
+        assert_eq!(None, SimDuration::MAX.checked_add(b));
+        assert_eq!(None, SimDuration::MAX.checked_mul(2));
+        assert_eq!(Some(SimDuration::from_ticks(75)), a.checked_mul(3));
+        assert!(SimDuration::ZERO.is_zero());
+        let mut c = a;
+        c += b;
+        c -= a;
+        assert_eq!(b, c);
+    }
+}
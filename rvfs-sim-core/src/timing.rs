@@ -0,0 +1,343 @@
+//! Static timing analysis of the paths between sequential Elements.
+//!
+//! A signal launched by an OutputPin of a sequential Element appears on the Wire it drives after the pin's propagation
+//! delay, and is seen by the pins reading the Wire once the Wire has settled past their logic thresholds.  It then
+//! passes through any [combinational](crate::element::Element::is_combinational) Elements reading the Wire, each adding
+//! the delay of its OutputPin and the settling time of the Wire driven, until it is captured by an InputPin of a
+//! sequential Element.
+//!
+//! [paths] finds the slowest path to each capturing InputPin without simulating the circuit, with the contribution of
+//! each segment along it, so that the paths limiting how fast the circuit can be clocked can be found in an imported
+//! netlist before it is run.
+
+use crate::ipin::{DEFAULT_HIGH_THRESHOLD, DEFAULT_LOW_THRESHOLD};
+use crate::sim::Simulation;
+use crate::{ElementId, WireId};
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::fmt;
+
+/// A segment of a timing path: an OutputPin and the Wire it drives.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Segment {
+    /// The OutputPin, named as `element.pin`.
+    pub pin: String,
+    /// Name of the Wire driven by the pin.
+    pub wire: String,
+    /// Propagation delay of the pin, in ticks.
+    pub delay: u64,
+    /// Time the Wire takes to settle past the logic thresholds, in ticks.
+    pub settle: u64,
+}
+
+impl Segment {
+    /// Obtain the time the segment contributes to its path, in ticks.
+    pub fn time(&self) -> u64 {
+        self.delay + self.settle
+    }
+}
+
+impl fmt::Display for Segment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} -> {}: {} + {}",
+            self.pin, self.wire, self.delay, self.settle
+        )
+    }
+}
+
+/// A path from an OutputPin of a sequential Element, through combinational Elements, to an InputPin of a sequential
+/// Element.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TimingPath {
+    /// Segments of the path, from the launching OutputPin onwards.
+    pub segments: Vec<Segment>,
+    /// The capturing InputPin, named as `element.pin`.
+    pub capture: String,
+}
+
+impl TimingPath {
+    /// Obtain the time a signal takes along the path, in ticks.
+    pub fn time(&self) -> u64 {
+        self.segments.iter().map(Segment::time).sum()
+    }
+}
+
+impl fmt::Display for TimingPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let launch = self.segments.first().map_or("", |segment| &segment.pin);
+        write!(f, "{launch} -> {}: {}", self.capture, self.time())?;
+        for segment in &self.segments {
+            write!(f, "\n  {segment}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Calculate the time a Wire takes to settle past the default logic thresholds when pulled from one level to the
+/// other, rounded up to whole ticks.
+///
+/// # Parameters
+///
+/// - `tau`: Time constant of the Wire.
+fn settle_time(tau: f32) -> u64 {
+    let rise = -tau * (1.0 - DEFAULT_HIGH_THRESHOLD).ln();
+    let fall = -tau * DEFAULT_LOW_THRESHOLD.ln();
+    rise.max(fall).ceil() as u64
+}
+
+/// Find the slowest path to each InputPin of a sequential Element in a Simulation, slowest first.
+///
+/// Paths are launched by the OutputPins of sequential Elements, and pass through combinational Elements, which are
+/// taken to respond to any of their inputs on every output.  Where a Wire is driven by several OutputPins, the latest
+/// arrival is taken.  Combinational loops have no settled timing, so paths into them are not followed.  Paths which
+/// take the same time are given in the order of their capturing Elements.
+///
+/// # Parameters
+///
+/// - `sim`: The Simulation to analyse.
+///
+/// # Example
+///
+/// ```
+/// # use rvfs_sim_core::element::flipflops::DFlipFlop;
+/// # use rvfs_sim_core::element::gates::{Gate, GateKind};
+/// # use rvfs_sim_core::sim::Simulation;
+/// # use rvfs_sim_core::time::SimDuration;
+/// # use rvfs_sim_core::timing;
+/// # use rvfs_sim_core::wire::{Wire, WirePull};
+/// let mut sim = Simulation::new(SimDuration::from_ticks(10));
+/// let q = sim.add_wire(Wire::new("Q1", WirePull::None)).unwrap();
+/// let d = sim.add_wire(Wire::new("D2", WirePull::None)).unwrap();
+/// let u1 = sim.add_element(Box::new(DFlipFlop::new("U1", 10))).unwrap();
+/// let u2 = sim.add_element(Box::new(Gate::new("U2", GateKind::Not, 1, 5).unwrap())).unwrap();
+/// let u3 = sim.add_element(Box::new(DFlipFlop::new("U3", 10))).unwrap();
+/// sim.connect_output(sim.output_pin(u1, "Q").unwrap(), q).unwrap();
+/// sim.connect_input(q, sim.input_pin(u2, "I0").unwrap()).unwrap();
+/// sim.connect_output(sim.output_pin(u2, "Y").unwrap(), d).unwrap();
+/// sim.connect_input(d, sim.input_pin(u3, "D").unwrap()).unwrap();
+///
+/// let paths = timing::paths(&sim).unwrap();
+/// assert_eq!(
+///     "U1.Q -> U3.D: 15\n  U1.Q -> Q1: 10 + 0\n  U2.Y -> D2: 5 + 0",
+///     paths[0].to_string()
+/// );
+/// ```
+pub fn paths(sim: &Simulation) -> Result<Vec<TimingPath>, String> {
+    // The Elements reading each Wire, with the index of the reading InputPin, and the combinational Elements.
+    let mut readers: BTreeMap<WireId, Vec<(ElementId, usize)>> = BTreeMap::new();
+    let mut combinational = BTreeSet::new();
+    for element in sim.elements() {
+        for (index, wire) in sim.input_wires(element)?.into_iter().enumerate() {
+            if let Some(wire) = wire {
+                readers.entry(wire).or_default().push((element, index));
+            }
+        }
+        if sim.element(element)?.is_combinational() {
+            combinational.insert(element);
+        }
+    }
+
+    // The number of combinational OutputPins yet to be resolved which drive each Wire, and the number of input Wires
+    // yet to be resolved of each combinational Element.
+    let mut unresolved_drivers: BTreeMap<WireId, usize> = BTreeMap::new();
+    for &element in &combinational {
+        for wire in sim.output_wires(element)?.into_iter().flatten() {
+            *unresolved_drivers.entry(wire).or_default() += 1;
+        }
+    }
+    let mut unresolved_inputs: BTreeMap<ElementId, usize> = BTreeMap::new();
+    for &element in &combinational {
+        let inputs: BTreeSet<WireId> = sim.input_wires(element)?.into_iter().flatten().collect();
+        let pending = inputs
+            .iter()
+            .filter(|wire| unresolved_drivers.contains_key(wire))
+            .count();
+        unresolved_inputs.insert(element, pending);
+    }
+
+    // The time and slowest path by which a signal arrives on each Wire.
+    let mut arrivals = BTreeMap::new();
+    for element in sim.elements() {
+        if !combinational.contains(&element) {
+            drive(sim, &mut arrivals, element, &(0, Vec::new()))?;
+        }
+    }
+
+    // Resolve the combinational Elements in order, once every Wire they read has been resolved.
+    let mut queue: VecDeque<ElementId> = unresolved_inputs
+        .iter()
+        .filter(|(_, pending)| **pending == 0)
+        .map(|(element, _)| *element)
+        .collect();
+    while let Some(element) = queue.pop_front() {
+        let slowest = sim
+            .input_wires(element)?
+            .into_iter()
+            .flatten()
+            .filter_map(|wire| arrivals.get(&wire))
+            .max_by_key(|(time, _)| *time)
+            .cloned();
+        if let Some(arrival) = &slowest {
+            drive(sim, &mut arrivals, element, arrival)?;
+        }
+        for wire in sim.output_wires(element)?.into_iter().flatten() {
+            let Some(drivers) = unresolved_drivers.get_mut(&wire) else {
+                continue;
+            };
+            *drivers -= 1;
+            if *drivers > 0 {
+                continue;
+            }
+            let mut resolved: Vec<ElementId> = readers
+                .get(&wire)
+                .into_iter()
+                .flatten()
+                .map(|(reader, _)| *reader)
+                .collect();
+            resolved.dedup();
+            for reader in resolved {
+                if let Some(pending) = unresolved_inputs.get_mut(&reader) {
+                    *pending -= 1;
+                    if *pending == 0 {
+                        queue.push_back(reader);
+                    }
+                }
+            }
+        }
+    }
+
+    let mut paths = Vec::new();
+    for element in sim.elements() {
+        if combinational.contains(&element) {
+            continue;
+        }
+        let capturer = sim.element(element)?;
+        let pins = capturer.input_pins();
+        for (pin, wire) in pins.iter().zip(sim.input_wires(element)?) {
+            if let Some((_, segments)) = wire.and_then(|wire| arrivals.get(&wire)) {
+                paths.push(TimingPath {
+                    segments: segments.clone(),
+                    capture: format!("{}.{}", capturer.name(), pin.name()),
+                });
+            }
+        }
+    }
+    paths.sort_by_key(|path| std::cmp::Reverse(path.time()));
+    Ok(paths)
+}
+
+/// Extend the path by which a signal arrives at an Element through each of its OutputPins, to the Wire the pin drives,
+/// keeping the slowest arrival on each Wire.
+///
+/// # Parameters
+///
+/// - `sim`: The Simulation being analysed.
+/// - `arrivals`: The time and slowest path by which a signal arrives on each Wire.
+/// - `element`: The Element.
+/// - `arrival`: The time and path by which a signal arrives at the Element.
+fn drive(
+    sim: &Simulation,
+    arrivals: &mut BTreeMap<WireId, (u64, Vec<Segment>)>,
+    element: ElementId,
+    arrival: &(u64, Vec<Segment>),
+) -> Result<(), String> {
+    let driver = sim.element(element)?;
+    for (pin, wire) in driver.output_pins().iter().zip(sim.output_wires(element)?) {
+        let Some(wire) = wire else {
+            continue;
+        };
+        let view = sim.wire(wire)?;
+        let segment = Segment {
+            pin: format!("{}.{}", driver.name(), pin.name()),
+            wire: view.name().clone(),
+            delay: pin.delay().ticks(),
+            settle: settle_time(view.time_constant()),
+        };
+        let time = arrival.0 + segment.time();
+        if arrivals
+            .get(&wire)
+            .is_none_or(|(slowest, _)| time > *slowest)
+        {
+            let mut segments = arrival.1.clone();
+            segments.push(segment);
+            arrivals.insert(wire, (time, segments));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::circuit;
+
+    #[test]
+    fn timing_paths() {
+        // GIVEN a flip-flop launching through a slow Wire and two gates, reconverging with a faster flip-flop's path
+        let circuit = circuit! {
+            interval 10;
+            wire clk: pull_down;
+            wire q1: none tau 4.0;
+            wire q2: none;
+            wire a: none;
+            wire b: none;
+            element u1: dff(delay = 10) { CLK: clk, Q: q1 };
+            element u2: dff(delay = 3) { CLK: clk, Q: q2 };
+            element u3: not(delay = 5) { I0: q1, Y: a };
+            element u4: nand(delay = 7) { I0: a, I1: q2, Y: b };
+            element u5: dff { CLK: clk, D: b };
+            element u6: dff { CLK: clk, D: q2 };
+        }
+        .unwrap();
+        // WHEN its timing paths are found
+        let paths = paths(circuit.simulation()).unwrap();
+        // THEN the slowest path to each capturing pin is reported, slowest first, with each segment's contribution
+        let reports: Vec<String> = paths.iter().map(TimingPath::to_string).collect();
+        assert_eq!(
+            vec![
+                "u1.Q -> u5.D: 27\n  u1.Q -> q1: 10 + 5\n  u3.Y -> a: 5 + 0\n  u4.Y -> b: 7 + 0",
+                "u2.Q -> u6.D: 3\n  u2.Q -> q2: 3 + 0"
+            ],
+            reports
+        );
+        assert_eq!(
+            vec![15, 5, 7],
+            paths[0]
+                .segments
+                .iter()
+                .map(Segment::time)
+                .collect::<Vec<_>>()
+        );
+    }
+    #[test]
+    fn timing_paths_loops_and_shared_wires() {
+        // GIVEN a flip-flop driving a combinational loop, and a Wire driven by two flip-flops
+        let circuit = circuit! {
+            interval 10;
+            wire x: none;
+            wire y: none;
+            wire bus: none;
+            element u1: dff(delay = 4) { Q: x };
+            element u2: not { I0: x, Y: y };
+            element u3: not { I0: y, Y: x };
+            element u4: dff { D: y };
+            element u5: dff { D: x };
+            element u6: dff(delay = 2) { Q: bus };
+            element u7: dff(delay = 9) { Q: bus };
+            element u8: dff { D: bus };
+        }
+        .unwrap();
+        // WHEN its timing paths are found
+        let paths = paths(circuit.simulation()).unwrap();
+        // THEN paths into the loop are not followed, and the later arrival on the shared Wire is taken
+        let reports: Vec<String> = paths.iter().map(TimingPath::to_string).collect();
+        assert_eq!(
+            vec![
+                "u7.Q -> u8.D: 9\n  u7.Q -> bus: 9 + 0",
+                "u1.Q -> u5.D: 4\n  u1.Q -> x: 4 + 0"
+            ],
+            reports
+        );
+    }
+}
@@ -0,0 +1,144 @@
+//! Tracers record the changes made to a Simulation as it is stepped.
+
+#[cfg(feature = "std")]
+pub mod csv;
+#[cfg(feature = "fst")]
+pub mod fst;
+#[cfg(feature = "std")]
+pub mod golden;
+#[cfg(feature = "std")]
+pub mod jsonl;
+#[cfg(feature = "parquet")]
+pub mod parquet;
+#[cfg(feature = "std")]
+pub mod plot;
+#[cfg(feature = "std")]
+pub mod policy;
+#[cfg(feature = "std")]
+pub mod trigger;
+#[cfg(feature = "std")]
+pub mod vcd;
+#[cfg(feature = "std")]
+pub mod wavedrom;
+
+use crate::prelude::*;
+use crate::sim::Simulation;
+use crate::wirevalue::WireValue;
+use crate::WireId;
+
+// Traced signals are classified with the same default thresholds as InputPins, so traces show what Elements see.
+pub use crate::ipin::{DEFAULT_HIGH_THRESHOLD, DEFAULT_LOW_THRESHOLD};
+
+/// Logic level of a traced signal, derived from the level of its Wire.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Logic {
+    /// The Wire level is at or below the low threshold.
+    Low,
+    /// The Wire level is at or above the high threshold.
+    High,
+    /// The Wire level is between the thresholds.
+    Unknown,
+}
+
+impl Logic {
+    /// Classify a Wire level.
+    ///
+    /// # Parameters
+    ///
+    /// - `value`: The Wire level to classify.
+    /// - `low`: Level at or below which the signal is considered logic low.
+    /// - `high`: Level at or above which the signal is considered logic high.
+    pub fn from_level(value: WireValue, low: f32, high: f32) -> Self {
+        let level = f32::from(value);
+        if level >= high {
+            Logic::High
+        } else if level <= low {
+            Logic::Low
+        } else {
+            Logic::Unknown
+        }
+    }
+
+    /// Obtain the conventional single character symbol for the logic level: `0`, `1` or `x`.
+    pub fn symbol(self) -> char {
+        match self {
+            Logic::Low => '0',
+            Logic::High => '1',
+            Logic::Unknown => 'x',
+        }
+    }
+}
+
+/// A change in the value of a single Wire during a Simulation step.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Change {
+    /// Id of the Wire which changed.
+    pub id: WireId,
+    /// Value of the Wire before the step.
+    pub previous: WireValue,
+    /// Value of the Wire after the step.
+    pub value: WireValue,
+}
+
+/// A consumer of the changes made to a Simulation as it is stepped.
+pub trait Tracer: Send {
+    /// Begin tracing.
+    ///
+    /// This is called before the first step after the Tracer is [attached](`Simulation::add_tracer`), so the initial
+    /// state of the Simulation can be recorded.
+    ///
+    /// # Parameters
+    ///
+    /// - `sim`: The Simulation being traced.
+    fn start(&mut self, sim: &Simulation) -> Result<(), String>;
+
+    /// Record the changes made during a step.
+    ///
+    /// # Parameters
+    ///
+    /// - `sim`: The Simulation being traced, with its time already advanced past the step.
+    /// - `changes`: The Wire value changes made during the step, ordered by Id.
+    fn record(&mut self, sim: &Simulation, changes: &[Change]) -> Result<(), String>;
+
+    /// Complete tracing, flushing any buffered output.
+    ///
+    /// # Parameters
+    ///
+    /// - `sim`: The Simulation being traced.
+    fn finish(&mut self, sim: &Simulation) -> Result<(), String>;
+}
+
+/// Helpers for testing Tracers.
+#[cfg(all(test, feature = "std"))]
+pub(crate) mod testing {
+    use crate::sim::Simulation;
+    use crate::time::SimDuration;
+    use crate::trace::Tracer;
+    use crate::wire::{Wire, WirePull};
+    use crate::WireId;
+
+    /// Build a Simulation with a Wire named `/RESET` being pulled low from its default high level.
+    ///
+    /// # Parameters
+    ///
+    /// - `tau`: Time constant of the Wire.
+    pub(crate) fn falling_wire_sim(tau: f32) -> (Simulation, WireId) {
+        let mut wire = Wire::new("/RESET", WirePull::Up);
+        wire.set_time_constant(tau);
+        wire.set_pull(WirePull::Down);
+        let mut sim = Simulation::new(SimDuration::from_ticks(10));
+        let id = sim.add_wire(wire).unwrap();
+        (sim, id)
+    }
+
+    /// Trace a number of steps of a Simulation directly, returning the Tracer once tracing has finished.
+    pub(crate) fn trace<T: Tracer>(sim: &mut Simulation, mut tracer: T, steps: usize) -> T {
+        tracer.start(sim).unwrap();
+        for _ in 0..steps {
+            sim.step().unwrap();
+            tracer.record(sim, sim.changes()).unwrap();
+        }
+        tracer.finish(sim).unwrap();
+        tracer
+    }
+}
@@ -0,0 +1,782 @@
+//! Writing of Fast Signal Trace (FST) files, the compressed waveform format read by GTKWave and Surfer.
+//!
+//! FST files hold the value changes of each signal in blocks, each compressed separately and indexed by time, so
+//! that viewers can open long traces quickly and seek within them without reading the whole file.
+
+use crate::opin::OutputPinState;
+use crate::sim::Simulation;
+use crate::trace::vcd::reference;
+use crate::trace::{Change, Logic, Tracer, DEFAULT_HIGH_THRESHOLD, DEFAULT_LOW_THRESHOLD};
+use crate::{OutputPinId, WireId};
+use std::collections::HashMap;
+use std::io::{Seek, SeekFrom, Write};
+
+/// Number of value changes buffered before a block is written, unless another is chosen.
+pub const DEFAULT_BLOCK_SIZE: usize = 1 << 16;
+
+/// Block types of the FST format.
+const BLOCK_HEADER: u8 = 0;
+const BLOCK_VALUE_CHANGES: u8 = 1;
+const BLOCK_GEOMETRY: u8 = 3;
+const BLOCK_HIERARCHY: u8 = 4;
+
+/// Hierarchy entry types of the FST format.
+const SCOPE_MODULE: u8 = 0;
+const VAR_WIRE: u8 = 16;
+const DIRECTION_IMPLICIT: u8 = 0;
+const ENTRY_SCOPE: u8 = 254;
+const ENTRY_UPSCOPE: u8 = 255;
+
+/// Marker of value changes packed with LZ4.
+const PACK_LZ4: u8 = b'4';
+
+/// Length of the header block, excluding its type.
+const HEADER_LENGTH: u64 = 329;
+
+/// Header of a gzip stream holding raw deflate data, as the hierarchy is written.
+const GZIP_HEADER: [u8; 10] = [0x1f, 0x8b, 8, 0, 0, 0, 0, 0, 0, 255];
+
+/// Compression level used for zlib and deflate data.
+const LEVEL: u8 = 6;
+
+/// State of a traced signal, whose handle is its index plus one.
+#[derive(Debug, Clone)]
+struct Signal {
+    /// Last value written for the signal, one character per bit from the most significant.
+    value: Vec<u8>,
+    /// Value changes of the signal in the present block.
+    changes: Vec<u8>,
+    /// Index into the present block's time table of the last change, or zero if there was none.
+    last: usize,
+}
+
+/// A Tracer which writes the traced Wires to an FST file.
+///
+/// Each traced Wire is written as a single-bit logic signal, derived from its level using a pair of thresholds.  The
+/// state driven by each OutputPin can be written too, including high impedance (`z`), as can the value of each bus
+/// defined in the Simulation, as a vector.  Unlike [VcdWriter](crate::trace::vcd::VcdWriter), the analog levels of
+/// Wires are not written.  One simulation time unit is a nanosecond.
+///
+/// Value changes are buffered and written in compressed blocks.  The file is only complete once tracing has
+/// finished, as its index follows the last block; a Simulation which is stepped further can be finished again, which
+/// rewrites the index after the new blocks.
+///
+/// # Example
+///
+/// ```
+/// # use rvfs_sim_core::sim::Simulation;
+/// # use rvfs_sim_core::time::SimDuration;
+/// # use rvfs_sim_core::trace::fst::FstWriter;
+/// # use rvfs_sim_core::wire::{Wire, WirePull};
+/// # use std::io::Cursor;
+/// let mut sim = Simulation::new(SimDuration::from_ticks(10));
+/// sim.add_wire(Wire::new("/RESET", WirePull::Up)).unwrap();
+/// sim.add_tracer(Box::new(FstWriter::new(Cursor::new(Vec::new()))));
+///
+/// sim.step().unwrap();
+/// sim.finish_tracers().unwrap();
+/// ```
+pub struct FstWriter<W: Write + Seek + Send> {
+    /// Destination of the FST output.
+    out: W,
+    /// Ids of the Wires to trace, or None to trace every Wire.
+    selection: Option<Vec<WireId>>,
+    /// Wire level at or below which a signal is considered logic low.
+    low_threshold: f32,
+    /// Wire level at or above which a signal is considered logic high.
+    high_threshold: f32,
+    /// Names to write in place of the names of Wires, keyed by Wire Id.
+    names: HashMap<WireId, String>,
+    /// Whether to trace the OutputPins of every Element.
+    pins: bool,
+    /// Whether to trace the buses defined in the Simulation.
+    buses: bool,
+    /// Number of value changes buffered before a block is written.
+    block_size: usize,
+    /// State of each traced signal, in handle order.
+    signals: Vec<Signal>,
+    /// Index of the signal of each traced Wire, keyed by Wire Id.
+    wire_signals: HashMap<WireId, usize>,
+    /// Id and signal index of each traced OutputPin.
+    pin_signals: Vec<(OutputPinId, usize)>,
+    /// Wires, from the least significant bit, and signal index of each traced bus.
+    bus_signals: Vec<(Vec<WireId>, usize)>,
+    /// Uncompressed hierarchy of scopes and variables.
+    hierarchy: Vec<u8>,
+    /// Number of scopes in the hierarchy.
+    scopes: u64,
+    /// Offset in the output of the header block.
+    header: u64,
+    /// Offset in the output at which the next block is written.
+    tail: u64,
+    /// Simulation time at which tracing started.
+    start_time: u64,
+    /// Simulation time of the values at the start of the present block.
+    block_start: u64,
+    /// Values of every signal at the start of the present block, in handle order.
+    frame: Vec<u8>,
+    /// Simulation times of the value changes in the present block.
+    times: Vec<u64>,
+    /// Number of value changes in the present block.
+    pending: usize,
+    /// Number of blocks written.
+    blocks: u64,
+}
+
+impl<W: Write + Seek + Send> FstWriter<W> {
+    /// Create a new FstWriter which traces every Wire.
+    ///
+    /// # Parameters
+    ///
+    /// - `out`: Destination of the FST output.
+    pub fn new(out: W) -> Self {
+        Self {
+            out,
+            selection: None,
+            low_threshold: DEFAULT_LOW_THRESHOLD,
+            high_threshold: DEFAULT_HIGH_THRESHOLD,
+            names: HashMap::new(),
+            pins: false,
+            buses: false,
+            block_size: DEFAULT_BLOCK_SIZE,
+            signals: Vec::new(),
+            wire_signals: HashMap::new(),
+            pin_signals: Vec::new(),
+            bus_signals: Vec::new(),
+            hierarchy: Vec::new(),
+            scopes: 0,
+            header: 0,
+            tail: 0,
+            start_time: 0,
+            block_start: 0,
+            frame: Vec::new(),
+            times: Vec::new(),
+            pending: 0,
+            blocks: 0,
+        }
+    }
+
+    /// Restrict tracing to the selected Wires.
+    ///
+    /// # Parameters
+    ///
+    /// - `ids`: Ids of the Wires to trace.
+    pub fn select(mut self, ids: &[WireId]) -> Self {
+        self.selection = Some(ids.to_vec());
+        self
+    }
+
+    /// Change the thresholds used to derive logic values from Wire levels.
+    ///
+    /// Levels between the two thresholds are written as unknown (`x`).
+    ///
+    /// # Parameters
+    ///
+    /// - `low`: Level at or below which a signal is considered logic low.
+    /// - `high`: Level at or above which a signal is considered logic high.
+    pub fn with_thresholds(mut self, low: f32, high: f32) -> Self {
+        self.low_threshold = low;
+        self.high_threshold = high;
+        self
+    }
+
+    /// Write a Wire under a different name, such as a net name from a schematic.
+    ///
+    /// Whitespace in the name is replaced, as it is in Wire names.
+    ///
+    /// # Parameters
+    ///
+    /// - `id`: Id of the Wire.
+    /// - `name`: Name to write for the Wire.
+    pub fn with_name(mut self, id: WireId, name: &str) -> Self {
+        self.names.insert(id, name.to_string());
+        self
+    }
+
+    /// Enable or disable writing the state driven by each OutputPin of every Element.
+    ///
+    /// Each pin is written as `element.pin`, in a scope of its own.
+    ///
+    /// # Parameters
+    ///
+    /// - `pins`: Whether to write the OutputPins.
+    pub fn with_output_pins(mut self, pins: bool) -> Self {
+        self.pins = pins;
+        self
+    }
+
+    /// Enable or disable writing the value of each bus defined in the Simulation.
+    ///
+    /// Each bus is written as a vector, most significant bit first, in a scope of its own.
+    ///
+    /// # Parameters
+    ///
+    /// - `buses`: Whether to write the buses.
+    pub fn with_buses(mut self, buses: bool) -> Self {
+        self.buses = buses;
+        self
+    }
+
+    /// Change the number of value changes buffered before a block is written.
+    ///
+    /// Larger blocks compress better but take more memory while tracing.
+    ///
+    /// # Parameters
+    ///
+    /// - `changes`: Number of value changes in each block, which must be at least one.
+    pub fn with_block_size(mut self, changes: usize) -> Self {
+        self.block_size = changes.max(1);
+        self
+    }
+
+    /// Consume the writer and obtain the underlying output.
+    pub fn into_inner(self) -> W {
+        self.out
+    }
+
+    /// Add a signal to the hierarchy, in the present scope.
+    ///
+    /// # Parameters
+    ///
+    /// - `name`: Name of the signal.
+    /// - `value`: Initial value of the signal, one character per bit.
+    fn declare(&mut self, name: &str, value: Vec<u8>) -> usize {
+        self.hierarchy.push(VAR_WIRE);
+        self.hierarchy.push(DIRECTION_IMPLICIT);
+        self.hierarchy
+            .extend(reference(name).bytes().filter(|b| *b != 0));
+        self.hierarchy.push(0);
+        varint(&mut self.hierarchy, value.len() as u64);
+        varint(&mut self.hierarchy, 0);
+
+        self.signals.push(Signal {
+            value,
+            changes: Vec::new(),
+            last: 0,
+        });
+        self.signals.len() - 1
+    }
+
+    /// Open a scope in the hierarchy.
+    fn scope(&mut self, name: &str) {
+        self.hierarchy.push(ENTRY_SCOPE);
+        self.hierarchy.push(SCOPE_MODULE);
+        self.hierarchy.extend(name.bytes());
+        self.hierarchy.extend([0, 0]);
+        self.scopes += 1;
+    }
+
+    /// Buffer a value change of a signal, if its value differs from the last one written.
+    ///
+    /// # Parameters
+    ///
+    /// - `index`: Index of the signal.
+    /// - `value`: New value of the signal, one character per bit.
+    /// - `time`: Simulation time of the change.
+    fn change(&mut self, index: usize, value: &[u8], time: u64) {
+        let signal = &mut self.signals[index];
+        if signal.value == value {
+            return;
+        }
+
+        if self.times.last() != Some(&time) {
+            self.times.push(time);
+        }
+        let at = self.times.len() - 1;
+        let delta = (at - signal.last) as u64;
+        signal.last = at;
+
+        match value {
+            [bit @ (b'0' | b'1')] => {
+                varint(&mut signal.changes, delta << 2 | u64::from(bit - b'0') << 1)
+            }
+            [other] => {
+                let code = if *other == b'z' { 1 } else { 0 };
+                varint(&mut signal.changes, delta << 4 | code << 1 | 1);
+            }
+            _ => {
+                varint(&mut signal.changes, delta << 1 | 1);
+                signal.changes.extend_from_slice(value);
+            }
+        }
+        signal.value = value.to_vec();
+        self.pending += 1;
+    }
+
+    /// Buffer a value change for each OutputPin and bus whose value has changed.
+    ///
+    /// # Parameters
+    ///
+    /// - `sim`: The Simulation.
+    fn change_pins_and_buses(&mut self, sim: &Simulation) -> Result<(), String> {
+        let time = sim.time();
+        for n in 0..self.pin_signals.len() {
+            let (id, index) = self.pin_signals[n];
+            let value = pin_value(sim.output_pin_state(id)?);
+            self.change(index, &[value], time);
+        }
+        for n in 0..self.bus_signals.len() {
+            let value = self.bus_value(sim, &self.bus_signals[n].0)?;
+            self.change(self.bus_signals[n].1, &value, time);
+        }
+
+        Ok(())
+    }
+
+    /// Obtain the value of a bus, most significant bit first.
+    fn bus_value(&self, sim: &Simulation, wires: &[WireId]) -> Result<Vec<u8>, String> {
+        wires
+            .iter()
+            .rev()
+            .map(|id| Ok(self.logic(sim.wire(*id)?.measure())))
+            .collect()
+    }
+
+    /// Convert a Wire level into a logic value.
+    fn logic(&self, value: crate::wirevalue::WireValue) -> u8 {
+        Logic::from_level(value, self.low_threshold, self.high_threshold).symbol() as u8
+    }
+
+    /// Write the buffered value changes as a block, and begin the next one with the present values.
+    fn write_block(&mut self) -> Result<(), String> {
+        let end = self.times.last().copied().unwrap_or(self.block_start);
+        let mut block = Vec::new();
+        block.extend(self.block_start.to_be_bytes());
+        block.extend(end.to_be_bytes());
+        let memory: usize = self.signals.iter().map(|s| s.changes.len()).sum();
+        block.extend((memory as u64).to_be_bytes());
+
+        // The values at the start of the block.
+        let frame = zlib(&self.frame);
+        varint(&mut block, self.frame.len() as u64);
+        varint(&mut block, frame.len() as u64);
+        varint(&mut block, self.signals.len() as u64);
+        block.extend(frame);
+
+        // The value changes of each signal, followed by the chain of their offsets.
+        varint(&mut block, self.signals.len() as u64);
+        let start = block.len();
+        block.push(PACK_LZ4);
+        let mut chain = Vec::new();
+        let mut previous = 0;
+        let mut empty = 0u64;
+        for signal in &mut self.signals {
+            if signal.changes.is_empty() {
+                empty += 1;
+                continue;
+            }
+            if empty > 0 {
+                varint(&mut chain, empty << 1);
+                empty = 0;
+            }
+            let offset = block.len() - start;
+            varint(&mut chain, ((offset - previous) as u64) << 1 | 1);
+            previous = offset;
+
+            let packed = lz4_flex::compress(&signal.changes);
+            if packed.len() < signal.changes.len() {
+                varint(&mut block, signal.changes.len() as u64);
+                block.extend(packed);
+            } else {
+                varint(&mut block, 0);
+                block.extend_from_slice(&signal.changes);
+            }
+            signal.changes.clear();
+            signal.last = 0;
+        }
+        if empty > 0 {
+            varint(&mut chain, empty << 1);
+        }
+        block.extend((chain.len() as u64).to_be_bytes());
+        block.splice(block.len() - 8..block.len() - 8, chain);
+
+        // The time table, as differences from the previous time.
+        let mut table = Vec::new();
+        let mut last = 0;
+        for time in &self.times {
+            varint(&mut table, time - last);
+            last = *time;
+        }
+        let packed = zlib(&table);
+        let packed_len = packed.len() as u64;
+        block.extend(packed);
+        block.extend((table.len() as u64).to_be_bytes());
+        block.extend(packed_len.to_be_bytes());
+        block.extend((self.times.len() as u64).to_be_bytes());
+
+        self.out
+            .seek(SeekFrom::Start(self.tail))
+            .map_err(|err| err.to_string())?;
+        self.write_section(BLOCK_VALUE_CHANGES, &block)?;
+        self.tail += 9 + block.len() as u64;
+        self.blocks += 1;
+
+        self.block_start = end;
+        self.frame = self.signals.iter().flat_map(|s| s.value.clone()).collect();
+        self.times.clear();
+        self.pending = 0;
+        Ok(())
+    }
+
+    /// Write a block, preceded by its type and length.
+    fn write_section(&mut self, kind: u8, body: &[u8]) -> Result<(), String> {
+        self.out.write_all(&[kind]).map_err(|err| err.to_string())?;
+        self.out
+            .write_all(&(body.len() as u64 + 8).to_be_bytes())
+            .map_err(|err| err.to_string())?;
+        self.out.write_all(body).map_err(|err| err.to_string())
+    }
+
+    /// Write the header block at its offset.
+    fn write_header(&mut self) -> Result<(), String> {
+        let end = self.start_time.max(self.block_start);
+        let signals = self.signals.len() as u64;
+        let mut header = Vec::new();
+        header.extend(self.start_time.to_be_bytes());
+        header.extend(end.to_be_bytes());
+        header.extend(core::f64::consts::E.to_le_bytes());
+        header.extend(0u64.to_be_bytes());
+        header.extend(self.scopes.to_be_bytes());
+        header.extend(signals.to_be_bytes());
+        header.extend(signals.to_be_bytes());
+        header.extend(self.blocks.to_be_bytes());
+        header.push(-9i8 as u8);
+        let mut version = [0u8; 128];
+        version[..8].copy_from_slice(b"rvfs-sim");
+        header.extend(version);
+        header.extend([0u8; 119]);
+        header.push(0);
+        header.extend(0u64.to_be_bytes());
+        debug_assert_eq!(HEADER_LENGTH, header.len() as u64 + 8);
+
+        self.out
+            .seek(SeekFrom::Start(self.header))
+            .map_err(|err| err.to_string())?;
+        self.write_section(BLOCK_HEADER, &header)
+    }
+}
+
+impl<W: Write + Seek + Send> Tracer for FstWriter<W> {
+    fn start(&mut self, sim: &Simulation) -> Result<(), String> {
+        let ids: Vec<WireId> = match &self.selection {
+            Some(ids) => ids.clone(),
+            None => sim.wires().collect(),
+        };
+
+        self.scope("logic");
+        for id in ids {
+            let wire = sim.wire(id)?;
+            let name = self.names.get(&id).cloned();
+            let value = self.logic(wire.measure());
+            let index = self.declare(name.as_deref().unwrap_or(wire.name()), vec![value]);
+            self.wire_signals.insert(id, index);
+        }
+        self.hierarchy.push(ENTRY_UPSCOPE);
+        if self.pins {
+            self.scope("pins");
+            for element in sim.elements() {
+                let element_name = sim.element(element)?.name().to_string();
+                for (id, pin) in sim.output_pins(element)? {
+                    let value = pin_value(sim.output_pin_state(id)?);
+                    let index = self.declare(&format!("{element_name}.{pin}"), vec![value]);
+                    self.pin_signals.push((id, index));
+                }
+            }
+            self.hierarchy.push(ENTRY_UPSCOPE);
+        }
+        if self.buses {
+            self.scope("buses");
+            for (name, wires) in sim.buses() {
+                let value = self.bus_value(sim, wires)?;
+                let index = self.declare(name, value);
+                self.bus_signals.push((wires.to_vec(), index));
+            }
+            self.hierarchy.push(ENTRY_UPSCOPE);
+        }
+
+        self.start_time = sim.time();
+        self.block_start = sim.time();
+        self.frame = self.signals.iter().flat_map(|s| s.value.clone()).collect();
+        self.header = self.out.stream_position().map_err(|err| err.to_string())?;
+        self.write_header()?;
+        self.tail = self.header + 1 + HEADER_LENGTH;
+        Ok(())
+    }
+
+    fn record(&mut self, sim: &Simulation, changes: &[Change]) -> Result<(), String> {
+        for change in changes {
+            if let Some(index) = self.wire_signals.get(&change.id).copied() {
+                let value = self.logic(change.value);
+                self.change(index, &[value], sim.time());
+            }
+        }
+        self.change_pins_and_buses(sim)?;
+
+        if self.pending >= self.block_size {
+            self.write_block()?;
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self, _sim: &Simulation) -> Result<(), String> {
+        if self.pending > 0 || self.blocks == 0 {
+            self.write_block()?;
+        }
+
+        let mut geometry = Vec::new();
+        for signal in &self.signals {
+            varint(&mut geometry, signal.value.len() as u64);
+        }
+        let mut body = Vec::new();
+        body.extend((geometry.len() as u64).to_be_bytes());
+        body.extend((self.signals.len() as u64).to_be_bytes());
+        body.extend(zlib(&geometry));
+
+        let mut hierarchy = Vec::new();
+        hierarchy.extend((self.hierarchy.len() as u64).to_be_bytes());
+        hierarchy.extend(GZIP_HEADER);
+        hierarchy.extend(miniz_oxide::deflate::compress_to_vec(
+            &self.hierarchy,
+            LEVEL,
+        ));
+
+        self.out
+            .seek(SeekFrom::Start(self.tail))
+            .map_err(|err| err.to_string())?;
+        self.write_section(BLOCK_GEOMETRY, &body)?;
+        self.write_section(BLOCK_HIERARCHY, &hierarchy)?;
+        let end = self.out.stream_position().map_err(|err| err.to_string())?;
+        self.write_header()?;
+        self.out
+            .seek(SeekFrom::Start(end))
+            .map_err(|err| err.to_string())?;
+        self.out.flush().map_err(|err| err.to_string())
+    }
+}
+
+/// Obtain the logic value of an OutputPin state.
+fn pin_value(state: OutputPinState) -> u8 {
+    match state {
+        OutputPinState::Low => b'0',
+        OutputPinState::High => b'1',
+        OutputPinState::HighImpedance => b'z',
+        OutputPinState::Unknown => b'x',
+    }
+}
+
+/// Append an unsigned LEB128 variable-length integer.
+fn varint(bytes: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        bytes.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    bytes.push(value as u8);
+}
+
+/// Compress data with zlib, unless that would not make it smaller.
+///
+/// Readers take data whose compressed and uncompressed lengths are equal to be uncompressed.
+fn zlib(bytes: &[u8]) -> Vec<u8> {
+    let packed = miniz_oxide::deflate::compress_to_vec_zlib(bytes, LEVEL);
+    if packed.len() < bytes.len() {
+        packed
+    } else {
+        bytes.to_vec()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::element::gates::{Gate, GateKind};
+    use crate::time::SimDuration;
+    use crate::trace::testing::{self, falling_wire_sim};
+    use crate::wire::{Wire, WirePull};
+    use fst_reader::{FstFilter, FstHierarchyEntry, FstReader, FstSignalValue};
+    use std::collections::BTreeMap;
+    use std::io::Cursor;
+
+    /// Trace a number of steps of a Simulation directly into a buffer.
+    fn trace(sim: &mut Simulation, fst: FstWriter<Cursor<Vec<u8>>>, steps: usize) -> Vec<u8> {
+        testing::trace(sim, fst, steps).into_inner().into_inner()
+    }
+
+    /// Read back the value changes of every signal in an FST file, keyed by scope and name.
+    fn read(bytes: Vec<u8>) -> BTreeMap<String, Vec<(u64, String)>> {
+        let mut reader = FstReader::open_and_read_time_table(Cursor::new(bytes)).unwrap();
+        let mut names = HashMap::new();
+        let mut scope = String::new();
+        reader
+            .read_hierarchy(|entry| match entry {
+                FstHierarchyEntry::Scope { name, .. } => scope = name,
+                FstHierarchyEntry::Var { name, handle, .. } => {
+                    names.insert(handle.get_index(), format!("{scope}.{name}"));
+                }
+                _ => {}
+            })
+            .unwrap();
+
+        let mut signals: BTreeMap<String, Vec<(u64, String)>> = BTreeMap::new();
+        reader
+            .read_signals(&FstFilter::all(), |time, handle, value| {
+                if let FstSignalValue::String(value) = value {
+                    signals
+                        .entry(names[&handle.get_index()].clone())
+                        .or_default()
+                        .push((time, String::from_utf8(value.to_vec()).unwrap()));
+                }
+            })
+            .unwrap();
+        signals
+    }
+
+    /// Build a Simulation with an inverter whose output drives its own input, so that it oscillates.
+    fn ring_sim() -> Simulation {
+        let mut sim = Simulation::new(SimDuration::from_ticks(10));
+        let a = sim.add_wire(Wire::new("A", WirePull::Down)).unwrap();
+        let gate = sim
+            .add_element(Box::new(Gate::new("U1", GateKind::Not, 1, 0).unwrap()))
+            .unwrap();
+        sim.connect_input(a, sim.input_pin(gate, "I0").unwrap())
+            .unwrap();
+        sim.connect_output(sim.output_pin(gate, "Y").unwrap(), a)
+            .unwrap();
+        sim
+    }
+
+    /// Flatten value changes into the form they are given in tests.
+    fn changes(values: &[(u64, &str)]) -> Vec<(u64, String)> {
+        values.iter().map(|(t, v)| (*t, v.to_string())).collect()
+    }
+
+    #[test]
+    fn fst_varint() {
+        // GIVEN some integers
+        // WHEN they are written as variable-length integers
+        // THEN each byte holds seven bits, least significant first, with the top bit marking a following byte
+        let mut bytes = Vec::new();
+        varint(&mut bytes, 0);
+        varint(&mut bytes, 0x7f);
+        varint(&mut bytes, 0x80);
+        varint(&mut bytes, 300);
+        assert_eq!(vec![0x00, 0x7f, 0x80, 0x01, 0xac, 0x02], bytes);
+    }
+    #[test]
+    fn fst_header_and_initial_values() {
+        // GIVEN a Simulation with a pulled-up wire
+        let (mut sim, _) = falling_wire_sim(10.0);
+        // WHEN it is traced without stepping
+        let bytes = trace(&mut sim, FstWriter::new(Cursor::new(Vec::new())), 0);
+        // THEN the header describes the signal, and its initial value is read back
+        let reader = FstReader::open(Cursor::new(bytes.clone())).unwrap();
+        let header = reader.get_header();
+        assert_eq!(
+            (0, 0, 1, -9),
+            (
+                header.start_time,
+                header.end_time,
+                header.var_count,
+                header.timescale_exponent
+            )
+        );
+        assert_eq!("rvfs-sim", header.version);
+        assert_eq!(
+            BTreeMap::from([("logic./RESET".to_string(), changes(&[(0, "1")]))]),
+            read(bytes)
+        );
+    }
+    #[test]
+    fn fst_logic_transitions() {
+        // GIVEN a Simulation with a wire falling from high to low, and another which stays low
+        let (mut sim, _) = falling_wire_sim(10.0);
+        sim.add_wire(Wire::new("CLK", WirePull::Down)).unwrap();
+        // WHEN it is traced over several steps
+        let bytes = trace(&mut sim, FstWriter::new(Cursor::new(Vec::new())), 4);
+        // THEN the logic transitions are read back at the time they occur
+        let signals = read(bytes);
+        assert_eq!(
+            changes(&[(0, "1"), (10, "x"), (20, "0")]),
+            signals["logic./RESET"]
+        );
+        assert_eq!(changes(&[(0, "0")]), signals["logic.CLK"]);
+    }
+    #[test]
+    fn fst_blocks() {
+        // GIVEN a Simulation with an oscillating inverter loop, traced in a single block
+        let single = trace(&mut ring_sim(), FstWriter::new(Cursor::new(Vec::new())), 50);
+        // WHEN it is traced with a block written every few changes
+        let bytes = trace(
+            &mut ring_sim(),
+            FstWriter::new(Cursor::new(Vec::new())).with_block_size(3),
+            50,
+        );
+        // THEN the value changes read back are the same as those written in a single block
+        let reader = FstReader::open(Cursor::new(bytes.clone())).unwrap();
+        assert_eq!(500, reader.get_header().end_time);
+        let signals = read(bytes);
+        assert!(signals["logic.A"].len() > 3, "{signals:?}");
+        assert_eq!(read(single), signals);
+    }
+    #[test]
+    fn fst_selection_and_names() {
+        // GIVEN a Simulation with two wires
+        let (mut sim, _) = falling_wire_sim(10.0);
+        let other = sim.add_wire(Wire::new("CLK", WirePull::Down)).unwrap();
+        // WHEN only the second wire is traced, under another name
+        let fst = FstWriter::new(Cursor::new(Vec::new()))
+            .select(&[other])
+            .with_name(other, "SYS CLK");
+        let bytes = trace(&mut sim, fst, 2);
+        // THEN only that wire appears in the output, under its new name
+        let signals = read(bytes);
+        assert_eq!(vec!["logic.SYS_CLK"], signals.keys().collect::<Vec<_>>());
+    }
+    #[test]
+    fn fst_output_pins_and_buses() {
+        // GIVEN a Simulation with a two-wire bus driven to 2, and an inverter driving one of its wires
+        let mut sim = Simulation::new(SimDuration::from_ticks(10));
+        let d0 = sim.add_wire(Wire::new("D0", WirePull::Down)).unwrap();
+        let d1 = sim.add_wire(Wire::new("D1", WirePull::Down)).unwrap();
+        sim.define_bus("DATA", &[d0, d1]).unwrap();
+        sim.drive_bus("DATA", 0b10).unwrap();
+        let gate = sim
+            .add_element(Box::new(Gate::new("U1", GateKind::Not, 1, 0).unwrap()))
+            .unwrap();
+        sim.connect_input(d1, sim.input_pin(gate, "I0").unwrap())
+            .unwrap();
+        let fst = FstWriter::new(Cursor::new(Vec::new()))
+            .with_output_pins(true)
+            .with_buses(true);
+        // WHEN it is traced over several steps
+        let bytes = trace(&mut sim, fst, 3);
+        // THEN the pin and the bus are read back, the bus as a vector with the most significant bit first
+        let signals = read(bytes);
+        assert_eq!(changes(&[(0, "00"), (10, "10")]), signals["buses.DATA"]);
+        assert_eq!(
+            changes(&[(0, "z"), (10, "1"), (20, "0")]),
+            signals["pins.U1.Y"]
+        );
+    }
+    #[test]
+    fn fst_finish_again() {
+        // GIVEN a Simulation with a falling wire, traced and finished
+        let (mut sim, _) = falling_wire_sim(10.0);
+        let mut fst = FstWriter::new(Cursor::new(Vec::new()));
+        fst.start(&sim).unwrap();
+        sim.step().unwrap();
+        fst.record(&sim, sim.changes()).unwrap();
+        fst.finish(&sim).unwrap();
+        // WHEN it is stepped further and finished again
+        for _ in 0..2 {
+            sim.step().unwrap();
+            fst.record(&sim, sim.changes()).unwrap();
+        }
+        fst.finish(&sim).unwrap();
+        // THEN the file holds every change
+        let signals = read(fst.into_inner().into_inner());
+        assert_eq!(
+            changes(&[(0, "1"), (10, "x"), (20, "0")]),
+            signals["logic./RESET"]
+        );
+    }
+}
@@ -0,0 +1,344 @@
+//! Comparison of a Simulation run against a reference ("golden") waveform.
+
+use crate::sim::Simulation;
+use crate::trace::vcd::Waveform;
+use crate::trace::{Change, Logic, Tracer, DEFAULT_HIGH_THRESHOLD, DEFAULT_LOW_THRESHOLD};
+use crate::wirevalue::WireValue;
+use crate::WireId;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+/// Default maximum number of divergences to report.
+const DEFAULT_DIVERGENCE_LIMIT: usize = 10;
+
+/// The ways in which a run can diverge from the reference waveform.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum DivergenceKind {
+    /// The run made a transition to the given value which the reference did not make.
+    Unexpected(char),
+    /// The reference made a transition to the given value which the run did not make.
+    Missing(char),
+}
+
+/// A single difference between a run and the reference waveform.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Divergence {
+    /// Time of the transition.
+    pub time: u64,
+    /// Name of the signal.
+    pub signal: String,
+    /// Nature of the difference.
+    pub kind: DivergenceKind,
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.kind {
+            DivergenceKind::Unexpected(value) => write!(
+                f,
+                "{}: {} changed to {value}, which the reference does not",
+                self.time, self.signal
+            ),
+            DivergenceKind::Missing(value) => write!(
+                f,
+                "{}: {} did not change to {value}, as the reference does",
+                self.time, self.signal
+            ),
+        }
+    }
+}
+
+/// State shared between all clones of a GoldenComparator.
+#[derive(Debug, Default)]
+struct State {
+    /// Observed transitions of each compared signal as pairs of time and value, keyed by name.
+    observed: BTreeMap<String, Vec<(u64, char)>>,
+    /// Simulation time at which the comparison started.
+    start_time: u64,
+    /// Most recent simulation time seen.
+    time: u64,
+}
+
+/// A Tracer which compares the logic transitions of a run with those of a reference waveform.
+///
+/// Each Wire is matched to the reference signal with the same name; Wires without a reference signal are not
+/// compared.  A transition in the run matches one in the reference if both change to the same value within the
+/// configured time tolerance of each other.  Only the portion of the reference up to the most recent simulation time
+/// is compared.
+///
+/// Clones of a GoldenComparator share their state, so a clone can be kept to examine the divergences after the
+/// original has been attached to a Simulation.
+///
+/// # Example
+///
+/// ```
+/// # use rvfs_sim_core::sim::Simulation;
+/// # use rvfs_sim_core::time::SimDuration;
+/// # use rvfs_sim_core::trace::golden::GoldenComparator;
+/// # use rvfs_sim_core::trace::vcd;
+/// # use rvfs_sim_core::wire::{Wire, WirePull};
+/// let reference = vcd::read("$var wire 1 ! CLK $end $enddefinitions $end #0 0!".as_bytes()).unwrap();
+/// let mut sim = Simulation::new(SimDuration::from_ticks(10));
+/// sim.add_wire(Wire::new("CLK", WirePull::Down)).unwrap();
+/// let golden = GoldenComparator::new(reference).with_tolerance(2);
+/// sim.add_tracer(Box::new(golden.clone()));
+///
+/// sim.step().unwrap();
+/// assert!(golden.divergences().is_empty());
+/// ```
+#[derive(Debug, Clone)]
+pub struct GoldenComparator {
+    /// Reference waveform.
+    reference: Arc<Waveform>,
+    /// Maximum time difference between matching transitions.
+    tolerance: u64,
+    /// Maximum number of divergences to report.
+    limit: usize,
+    /// Wire level at or below which a signal is considered logic low.
+    low_threshold: f32,
+    /// Wire level at or above which a signal is considered logic high.
+    high_threshold: f32,
+    /// Names of the compared Wires, keyed by Wire Id.
+    names: BTreeMap<WireId, String>,
+    /// Shared comparison state.
+    state: Arc<Mutex<State>>,
+}
+
+impl GoldenComparator {
+    /// Create a new GoldenComparator requiring transitions to match exactly in time.
+    ///
+    /// # Parameters
+    ///
+    /// - `reference`: The reference waveform, such as one [read](crate::trace::vcd::read) from a VCD file.
+    pub fn new(reference: Waveform) -> Self {
+        Self {
+            reference: Arc::new(reference),
+            tolerance: 0,
+            limit: DEFAULT_DIVERGENCE_LIMIT,
+            low_threshold: DEFAULT_LOW_THRESHOLD,
+            high_threshold: DEFAULT_HIGH_THRESHOLD,
+            names: BTreeMap::new(),
+            state: Arc::new(Mutex::new(State::default())),
+        }
+    }
+
+    /// Allow matching transitions to differ in time.
+    ///
+    /// # Parameters
+    ///
+    /// - `tolerance`: Maximum time difference between matching transitions.
+    pub fn with_tolerance(mut self, tolerance: u64) -> Self {
+        self.tolerance = tolerance;
+        self
+    }
+
+    /// Change the maximum number of divergences reported.
+    ///
+    /// # Parameters
+    ///
+    /// - `limit`: Maximum number of divergences to report.
+    pub fn with_limit(mut self, limit: usize) -> Self {
+        self.limit = limit;
+        self
+    }
+
+    /// Change the thresholds used to derive logic values from Wire levels.
+    ///
+    /// # Parameters
+    ///
+    /// - `low`: Level at or below which a signal is considered logic low.
+    /// - `high`: Level at or above which a signal is considered logic high.
+    pub fn with_thresholds(mut self, low: f32, high: f32) -> Self {
+        self.low_threshold = low;
+        self.high_threshold = high;
+        self
+    }
+
+    /// Obtain the earliest divergences between the run so far and the reference, in time order.
+    pub fn divergences(&self) -> Vec<Divergence> {
+        let state = self
+            .state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let mut divergences = Vec::new();
+
+        for (name, observed) in &state.observed {
+            let Some(expected) = self.reference.signal(name) else {
+                continue;
+            };
+            // High impedance reads as unknown, which may leave repeated values that are not transitions.
+            let mut expected: Vec<(u64, char)> = expected
+                .iter()
+                .copied()
+                .map(|(time, value)| (time, if value == 'z' { 'x' } else { value }))
+                .filter(|(time, _)| {
+                    *time >= state.start_time && *time <= state.time.saturating_add(self.tolerance)
+                })
+                .collect();
+            expected.dedup_by(|later, earlier| later.1 == earlier.1);
+
+            let mut matched = vec![false; expected.len()];
+            for (time, value) in observed {
+                let found = expected.iter().enumerate().position(|(index, (t, v))| {
+                    !matched[index] && v == value && t.abs_diff(*time) <= self.tolerance
+                });
+                match found {
+                    Some(index) => matched[index] = true,
+                    None => divergences.push(Divergence {
+                        time: *time,
+                        signal: name.clone(),
+                        kind: DivergenceKind::Unexpected(*value),
+                    }),
+                }
+            }
+            // Reference transitions too close to the end of the run may still be matched later.
+            for ((time, value), matched) in expected.iter().zip(matched) {
+                if !matched && time.saturating_add(self.tolerance) <= state.time {
+                    divergences.push(Divergence {
+                        time: *time,
+                        signal: name.clone(),
+                        kind: DivergenceKind::Missing(*value),
+                    });
+                }
+            }
+        }
+
+        divergences.sort_by(|a, b| a.time.cmp(&b.time).then_with(|| a.signal.cmp(&b.signal)));
+        divergences.truncate(self.limit);
+        divergences
+    }
+
+    /// Convert a Wire level into a VCD logic value.
+    fn logic(&self, value: WireValue) -> char {
+        Logic::from_level(value, self.low_threshold, self.high_threshold).symbol()
+    }
+}
+
+impl Tracer for GoldenComparator {
+    fn start(&mut self, sim: &Simulation) -> Result<(), String> {
+        let mut observed = BTreeMap::new();
+        self.names.clear();
+        for id in sim.wires() {
+            let wire = sim.wire(id)?;
+            if self.reference.signal(wire.name()).is_some() {
+                self.names.insert(id, wire.name().clone());
+                observed.insert(
+                    wire.name().clone(),
+                    vec![(sim.time(), self.logic(wire.measure()))],
+                );
+            }
+        }
+
+        let mut state = self
+            .state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        state.observed = observed;
+        state.start_time = sim.time();
+        state.time = sim.time();
+        Ok(())
+    }
+
+    fn record(&mut self, sim: &Simulation, changes: &[Change]) -> Result<(), String> {
+        let mut state = self
+            .state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        state.time = sim.time();
+        for change in changes {
+            let Some(name) = self.names.get(&change.id) else {
+                continue;
+            };
+            let value = self.logic(change.value);
+            if let Some(observed) = state.observed.get_mut(name) {
+                if observed.last().is_none_or(|(_, last)| *last != value) {
+                    observed.push((sim.time(), value));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self, _sim: &Simulation) -> Result<(), String> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::trace::testing::{falling_wire_sim, trace};
+    use crate::trace::vcd;
+    use crate::wire::{Wire, WirePull};
+
+    /// Run a falling wire for a number of steps, comparing it against a reference.
+    fn compare(golden: GoldenComparator, steps: usize) -> Vec<Divergence> {
+        let (mut sim, _) = falling_wire_sim(10.0);
+        sim.add_wire(Wire::new("UNCOMPARED", WirePull::Up)).unwrap();
+        trace(&mut sim, golden.clone(), steps);
+        golden.divergences()
+    }
+
+    /// Build a comparator from VCD text describing the /RESET signal.
+    fn comparator(changes: &str) -> GoldenComparator {
+        let text = format!("$var wire 1 ! /RESET $end $enddefinitions $end {changes}");
+        GoldenComparator::new(vcd::read(text.as_bytes()).unwrap())
+    }
+
+    #[test]
+    fn golden_match() {
+        // GIVEN a reference matching the falling wire exactly
+        let golden = comparator("#0 1! #10 x! #20 0!");
+        // WHEN the run is compared
+        let divergences = compare(golden, 4);
+        // THEN there are no divergences
+        assert_eq!(Vec::<Divergence>::new(), divergences);
+    }
+    #[test]
+    fn golden_within_tolerance() {
+        // GIVEN a reference where the transitions are slightly later than in the run
+        let golden = comparator("#0 1! #13 x! #25 0!").with_tolerance(5);
+        // WHEN the run is compared with a tolerance covering the difference
+        let divergences = compare(golden, 4);
+        // THEN there are no divergences
+        assert!(divergences.is_empty());
+    }
+    #[test]
+    fn golden_divergences() {
+        // GIVEN a reference where the wire falls much later than in the run
+        let golden = comparator("#0 1! #10 x! #30 z! #35 0!").with_tolerance(2);
+        // WHEN the run is compared
+        let divergences = compare(golden, 4);
+        // THEN the early fall is unexpected, and the later one is missing
+        assert_eq!(
+            vec![
+                Divergence {
+                    time: 20,
+                    signal: "/RESET".to_string(),
+                    kind: DivergenceKind::Unexpected('0'),
+                },
+                Divergence {
+                    time: 35,
+                    signal: "/RESET".to_string(),
+                    kind: DivergenceKind::Missing('0'),
+                },
+            ],
+            divergences
+        );
+        assert_eq!(
+            "20: /RESET changed to 0, which the reference does not",
+            divergences[0].to_string()
+        );
+    }
+    #[test]
+    fn golden_limit() {
+        // GIVEN a reference which diverges from the run at every transition
+        let golden = comparator("#0 0! #10 1!").with_limit(1);
+        // WHEN the run is compared with a limit of one divergence
+        let divergences = compare(golden, 4);
+        // THEN only the earliest divergence is reported
+        assert_eq!(1, divergences.len());
+        assert_eq!(0, divergences[0].time);
+    }
+}
@@ -0,0 +1,162 @@
+//! Tracer which writes an event log of signal transitions as JSON Lines.
+
+use crate::json;
+use crate::sim::Simulation;
+use crate::trace::{Change, Logic, Tracer, DEFAULT_HIGH_THRESHOLD, DEFAULT_LOW_THRESHOLD};
+use crate::WireId;
+use std::collections::HashMap;
+use std::io::Write;
+
+/// A Tracer which writes one JSON object per line for every logic transition of the traced Wires.
+///
+/// Each object has the fields:
+///
+/// - `time`: Simulation time at which the transition was observed.
+/// - `signal`: Name of the Wire.
+/// - `old`: Previous logic level of the Wire: `"0"`, `"1"` or `"x"`.
+/// - `new`: New logic level of the Wire.
+/// - `level`: New analog level of the Wire.
+///
+/// # Example
+///
+/// ```
+/// # use rvfs_sim_core::sim::Simulation;
+/// # use rvfs_sim_core::time::SimDuration;
+/// # use rvfs_sim_core::trace::jsonl::JsonlWriter;
+/// # use rvfs_sim_core::wire::{Wire, WirePull};
+/// let mut sim = Simulation::new(SimDuration::from_ticks(10));
+/// sim.add_wire(Wire::new("/RESET", WirePull::Up)).unwrap();
+/// sim.add_tracer(Box::new(JsonlWriter::new(std::io::stdout())));
+///
+/// sim.step().unwrap();
+/// sim.finish_tracers().unwrap();
+/// ```
+pub struct JsonlWriter<W: Write + Send> {
+    /// Destination of the event log.
+    out: W,
+    /// Ids of the Wires to trace, or None to trace every Wire.
+    selection: Option<Vec<WireId>>,
+    /// Wire level at or below which a signal is considered logic low.
+    low_threshold: f32,
+    /// Wire level at or above which a signal is considered logic high.
+    high_threshold: f32,
+    /// Name and last logic level of each traced Wire, keyed by Wire Id.
+    signals: HashMap<WireId, (String, Logic)>,
+}
+
+impl<W: Write + Send> JsonlWriter<W> {
+    /// Create a new JsonlWriter which traces every Wire.
+    ///
+    /// # Parameters
+    ///
+    /// - `out`: Destination of the event log, such as a file or pipe.
+    pub fn new(out: W) -> Self {
+        Self {
+            out,
+            selection: None,
+            low_threshold: DEFAULT_LOW_THRESHOLD,
+            high_threshold: DEFAULT_HIGH_THRESHOLD,
+            signals: HashMap::new(),
+        }
+    }
+
+    /// Restrict tracing to the selected Wires.
+    ///
+    /// # Parameters
+    ///
+    /// - `ids`: Ids of the Wires to trace.
+    pub fn select(mut self, ids: &[WireId]) -> Self {
+        self.selection = Some(ids.to_vec());
+        self
+    }
+
+    /// Change the thresholds used to derive logic levels from Wire levels.
+    ///
+    /// # Parameters
+    ///
+    /// - `low`: Level at or below which a signal is considered logic low.
+    /// - `high`: Level at or above which a signal is considered logic high.
+    pub fn with_thresholds(mut self, low: f32, high: f32) -> Self {
+        self.low_threshold = low;
+        self.high_threshold = high;
+        self
+    }
+
+    /// Consume the writer and obtain the underlying output.
+    pub fn into_inner(self) -> W {
+        self.out
+    }
+}
+
+impl<W: Write + Send> Tracer for JsonlWriter<W> {
+    fn start(&mut self, sim: &Simulation) -> Result<(), String> {
+        let ids: Vec<WireId> = match &self.selection {
+            Some(ids) => ids.clone(),
+            None => sim.wires().collect(),
+        };
+
+        for id in ids {
+            let wire = sim.wire(id)?;
+            let logic = Logic::from_level(wire.measure(), self.low_threshold, self.high_threshold);
+            self.signals.insert(id, (wire.name().clone(), logic));
+        }
+
+        Ok(())
+    }
+
+    fn record(&mut self, sim: &Simulation, changes: &[Change]) -> Result<(), String> {
+        for change in changes {
+            let logic = Logic::from_level(change.value, self.low_threshold, self.high_threshold);
+            let Some((name, previous)) = self.signals.get_mut(&change.id) else {
+                continue;
+            };
+            if *previous == logic {
+                continue;
+            }
+
+            writeln!(
+                self.out,
+                "{{\"time\":{},\"signal\":{},\"old\":\"{}\",\"new\":\"{}\",\"level\":{}}}",
+                sim.time(),
+                json::string(name),
+                previous.symbol(),
+                logic.symbol(),
+                f32::from(change.value),
+            )
+            .map_err(|err| err.to_string())?;
+            *previous = logic;
+        }
+
+        Ok(())
+    }
+
+    fn finish(&mut self, _sim: &Simulation) -> Result<(), String> {
+        self.out.flush().map_err(|err| err.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::trace::testing::{falling_wire_sim, trace};
+    use crate::wire::{Wire, WirePull};
+
+    #[test]
+    fn jsonl_transitions() {
+        // GIVEN a Simulation with a wire falling from high to low, and a wire which stays low
+        let (mut sim, _) = falling_wire_sim(10.0);
+        sim.add_wire(Wire::new("CLK", WirePull::Down)).unwrap();
+        // WHEN it is traced over several steps
+        let jsonl = trace(&mut sim, JsonlWriter::new(Vec::new()), 4);
+        // THEN one line is written for each logic transition
+        let text = String::from_utf8(jsonl.into_inner()).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(
+            vec![
+                "{\"time\":10,\"signal\":\"/RESET\",\"old\":\"1\",\"new\":\"x\",\"level\":0.36787945}",
+                "{\"time\":20,\"signal\":\"/RESET\",\"old\":\"x\",\"new\":\"0\",\"level\":0.1353353}",
+            ],
+            lines
+        );
+    }
+}
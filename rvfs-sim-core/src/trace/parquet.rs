@@ -0,0 +1,318 @@
+//! Tracer which writes sampled Wire levels as an Apache Parquet file.
+
+use crate::sim::Simulation;
+use crate::trace::{Change, Tracer};
+use crate::WireId;
+use parquet::basic::{Compression, LogicalType, Repetition, Type as PhysicalType};
+use parquet::data_type::{FloatType, Int64Type};
+use parquet::file::properties::WriterProperties;
+use parquet::file::writer::SerializedFileWriter;
+use parquet::schema::types::Type;
+use std::io::Write;
+use std::sync::Arc;
+
+/// Number of samples written in each row group, unless another is chosen.
+pub const DEFAULT_ROW_GROUP_SIZE: usize = 1 << 16;
+
+/// A Tracer which samples the analog levels of the traced Wires and writes them as Parquet rows.
+///
+/// The file has a `time` column of unsigned 64-bit integers, followed by a column of 32-bit floats holding the level
+/// of each traced Wire, named after it.  Samples are taken as [CsvWriter](crate::trace::csv::CsvWriter) takes them:
+/// at the start of tracing and then whenever at least one sample period has elapsed since the last sample.  They are
+/// buffered and written in Snappy-compressed row groups, and the file is only complete once tracing has finished.
+///
+/// # Example
+///
+/// ```
+/// # use rvfs_sim_core::sim::Simulation;
+/// # use rvfs_sim_core::time::SimDuration;
+/// # use rvfs_sim_core::trace::parquet::ParquetWriter;
+/// # use rvfs_sim_core::wire::{Wire, WirePull};
+/// let mut sim = Simulation::new(SimDuration::from_ticks(10));
+/// sim.add_wire(Wire::new("/RESET", WirePull::Up)).unwrap();
+/// sim.add_tracer(Box::new(ParquetWriter::new(std::io::sink(), 100)));
+///
+/// sim.step().unwrap();
+/// sim.finish_tracers().unwrap();
+/// ```
+pub struct ParquetWriter<W: Write + Send> {
+    /// Destination of the Parquet output, held here until tracing starts and once it has finished.
+    out: Option<W>,
+    /// Writer of the Parquet file while tracing.
+    writer: Option<SerializedFileWriter<W>>,
+    /// Ids of the Wires to trace, or None to trace every Wire.
+    selection: Option<Vec<WireId>>,
+    /// Simulation time between samples.
+    period: u64,
+    /// Number of samples in each row group.
+    row_group_size: usize,
+    /// Ids of the Wires being traced, in column order.
+    columns: Vec<WireId>,
+    /// Simulation time at which the next sample is due.
+    next_sample: u64,
+    /// Times of the samples not yet written.
+    times: Vec<i64>,
+    /// Levels of each traced Wire in the samples not yet written, in column order.
+    levels: Vec<Vec<f32>>,
+}
+
+impl<W: Write + Send> ParquetWriter<W> {
+    /// Create a new ParquetWriter which traces every Wire.
+    ///
+    /// # Parameters
+    ///
+    /// - `out`: Destination of the Parquet output.
+    /// - `period`: Simulation time between samples.  A period of 0 samples after every step.
+    pub fn new(out: W, period: u64) -> Self {
+        Self {
+            out: Some(out),
+            writer: None,
+            selection: None,
+            period,
+            row_group_size: DEFAULT_ROW_GROUP_SIZE,
+            columns: Vec::new(),
+            next_sample: 0,
+            times: Vec::new(),
+            levels: Vec::new(),
+        }
+    }
+
+    /// Restrict tracing to the selected Wires.
+    ///
+    /// # Parameters
+    ///
+    /// - `ids`: Ids of the Wires to trace, in the order their columns should appear.
+    pub fn select(mut self, ids: &[WireId]) -> Self {
+        self.selection = Some(ids.to_vec());
+        self
+    }
+
+    /// Change the number of samples written in each row group.
+    ///
+    /// Larger row groups compress better but take more memory while tracing.
+    ///
+    /// # Parameters
+    ///
+    /// - `rows`: Number of samples in each row group, which must be at least one.
+    pub fn with_row_group_size(mut self, rows: usize) -> Self {
+        self.row_group_size = rows.max(1);
+        self
+    }
+
+    /// Consume the writer and obtain the underlying output, or None if tracing has started but not finished.
+    pub fn into_inner(self) -> Option<W> {
+        self.out
+    }
+
+    /// Buffer a sample of the present level of every traced Wire, writing a row group once enough are buffered.
+    fn sample(&mut self, sim: &Simulation) -> Result<(), String> {
+        self.times.push(sim.time() as i64);
+        for (id, levels) in self.columns.iter().zip(&mut self.levels) {
+            levels.push(f32::from(sim.wire(*id)?.measure()));
+        }
+        self.next_sample = sim.time().saturating_add(self.period);
+
+        if self.times.len() >= self.row_group_size {
+            self.write_row_group()?;
+        }
+        Ok(())
+    }
+
+    /// Write the buffered samples as a row group.
+    fn write_row_group(&mut self) -> Result<(), String> {
+        let Some(writer) = &mut self.writer else {
+            return Ok(());
+        };
+        if self.times.is_empty() {
+            return Ok(());
+        }
+
+        let failed = |err: parquet::errors::ParquetError| err.to_string();
+        let mut group = writer.next_row_group().map_err(failed)?;
+        if let Some(mut column) = group.next_column().map_err(failed)? {
+            column
+                .typed::<Int64Type>()
+                .write_batch(&self.times, None, None)
+                .map_err(failed)?;
+            column.close().map_err(failed)?;
+        }
+        for levels in &mut self.levels {
+            if let Some(mut column) = group.next_column().map_err(failed)? {
+                column
+                    .typed::<FloatType>()
+                    .write_batch(levels, None, None)
+                    .map_err(failed)?;
+                column.close().map_err(failed)?;
+            }
+            levels.clear();
+        }
+        group.close().map_err(failed)?;
+        self.times.clear();
+        Ok(())
+    }
+}
+
+impl<W: Write + Send> Tracer for ParquetWriter<W> {
+    fn start(&mut self, sim: &Simulation) -> Result<(), String> {
+        self.columns = match &self.selection {
+            Some(ids) => ids.clone(),
+            None => sim.wires().collect(),
+        };
+
+        let failed = |err: parquet::errors::ParquetError| err.to_string();
+        let mut fields = vec![Arc::new(
+            Type::primitive_type_builder("time", PhysicalType::INT64)
+                .with_repetition(Repetition::REQUIRED)
+                .with_logical_type(Some(LogicalType::Integer {
+                    bit_width: 64,
+                    is_signed: false,
+                }))
+                .build()
+                .map_err(failed)?,
+        )];
+        for id in &self.columns {
+            fields.push(Arc::new(
+                Type::primitive_type_builder(sim.wire(*id)?.name(), PhysicalType::FLOAT)
+                    .with_repetition(Repetition::REQUIRED)
+                    .build()
+                    .map_err(failed)?,
+            ));
+        }
+        let schema = Type::group_type_builder("trace")
+            .with_fields(fields)
+            .build()
+            .map_err(failed)?;
+        let properties = WriterProperties::builder()
+            .set_compression(Compression::SNAPPY)
+            .build();
+
+        let out = self
+            .out
+            .take()
+            .ok_or("Parquet tracing has already started")?;
+        self.writer = Some(
+            SerializedFileWriter::new(out, Arc::new(schema), Arc::new(properties))
+                .map_err(failed)?,
+        );
+        self.levels = vec![Vec::new(); self.columns.len()];
+        self.sample(sim)
+    }
+
+    fn record(&mut self, sim: &Simulation, _changes: &[Change]) -> Result<(), String> {
+        if self.writer.is_some() && sim.time() >= self.next_sample {
+            self.sample(sim)?;
+        }
+
+        Ok(())
+    }
+
+    fn finish(&mut self, _sim: &Simulation) -> Result<(), String> {
+        self.write_row_group()?;
+        if let Some(writer) = self.writer.take() {
+            let mut out = writer.into_inner().map_err(|err| err.to_string())?;
+            out.flush().map_err(|err| err.to_string())?;
+            self.out = Some(out);
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::time::SimDuration;
+    use crate::trace::testing::{self, falling_wire_sim};
+    use crate::wire::{Wire, WirePull};
+    use parquet::file::reader::{FileReader, SerializedFileReader};
+    use parquet::record::RowAccessor;
+    use std::fs::File;
+
+    /// Column names, number of row groups, and rows of a time and levels, read back from a Parquet file.
+    type Contents = (Vec<String>, usize, Vec<(u64, Vec<f32>)>);
+
+    /// Trace a number of steps of a Simulation into a file, and read back its column names, row group count and rows.
+    fn trace(
+        sim: &mut Simulation,
+        parquet: ParquetWriter<File>,
+        path: &std::path::Path,
+        steps: usize,
+    ) -> Contents {
+        let parquet = testing::trace(sim, parquet, steps);
+        assert!(parquet.into_inner().is_some());
+
+        let reader = SerializedFileReader::new(File::open(path).unwrap()).unwrap();
+        std::fs::remove_file(path).unwrap();
+        let metadata = reader.metadata();
+        let names = metadata
+            .file_metadata()
+            .schema_descr()
+            .columns()
+            .iter()
+            .map(|column| column.name().to_string())
+            .collect();
+        let groups = metadata.num_row_groups();
+        let rows = reader
+            .get_row_iter(None)
+            .unwrap()
+            .map(|row| {
+                let row = row.unwrap();
+                let levels = (1..row.len()).map(|i| row.get_float(i).unwrap()).collect();
+                (row.get_ulong(0).unwrap(), levels)
+            })
+            .collect();
+        (names, groups, rows)
+    }
+
+    /// Create a file for a test's output, in the temporary directory.
+    fn create(test: &str) -> (File, std::path::PathBuf) {
+        let path =
+            std::env::temp_dir().join(format!("rvfs-sim-{test}-{}.parquet", std::process::id()));
+        (File::create(&path).unwrap(), path)
+    }
+
+    #[test]
+    fn parquet_sample_period() {
+        // GIVEN a Simulation with a wire falling from high to low
+        let (mut sim, _) = falling_wire_sim(10.0);
+        sim.add_wire(Wire::new("CLK", WirePull::Down)).unwrap();
+        // WHEN it is traced with a sample period longer than the step interval
+        let (file, path) = create("parquet-sample-period");
+        let (names, groups, rows) = trace(&mut sim, ParquetWriter::new(file, 20), &path, 4);
+        // THEN a column for the time and each wire, and a row for each elapsed sample period, are read back
+        assert_eq!(vec!["time", "/RESET", "CLK"], names);
+        assert_eq!(1, groups);
+        assert_eq!(
+            vec![
+                (0, vec![1.0, 0.0]),
+                (20, vec![0.1353353, 0.0]),
+                (40, vec![0.018315641, 0.0])
+            ],
+            rows
+        );
+    }
+    #[test]
+    fn parquet_selection_and_row_groups() {
+        // GIVEN a Simulation with two wires
+        let mut sim = Simulation::new(SimDuration::from_ticks(10));
+        let first = sim.add_wire(Wire::new("A", WirePull::Up)).unwrap();
+        let second = sim.add_wire(Wire::new("B", WirePull::Down)).unwrap();
+        // WHEN the wires are selected in reverse order and sampled on every step, in row groups of two samples
+        let (file, path) = create("parquet-selection");
+        let parquet = ParquetWriter::new(file, 0)
+            .select(&[second, first])
+            .with_row_group_size(2);
+        let (names, groups, rows) = trace(&mut sim, parquet, &path, 2);
+        // THEN the columns follow the selection order, and every sample is read back across the row groups
+        assert_eq!(vec!["time", "B", "A"], names);
+        assert_eq!(2, groups);
+        assert_eq!(
+            vec![
+                (0, vec![0.0, 1.0]),
+                (10, vec![0.0, 1.0]),
+                (20, vec![0.0, 1.0])
+            ],
+            rows
+        );
+    }
+}
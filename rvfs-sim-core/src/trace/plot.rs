@@ -0,0 +1,530 @@
+//! Rendering of analog Wire levels as SVG charts, or as PNG images with the `png` feature.
+
+use crate::sim::Simulation;
+use crate::trace::{Change, Tracer};
+use crate::WireId;
+#[cfg(feature = "png")]
+use plotters::prelude::*;
+#[cfg(feature = "png")]
+use plotters::style::text_anchor::{HPos, Pos, VPos};
+use std::io::Write;
+
+/// Default width of a chart, in pixels.
+const DEFAULT_WIDTH: u32 = 800;
+/// Height of the lane in which each signal is drawn, in pixels.
+const LANE_HEIGHT: u32 = 60;
+/// Width of the margin holding the signal names, in pixels.
+const NAME_MARGIN: u32 = 140;
+/// Height of the margin holding the time axis, in pixels.
+const AXIS_MARGIN: u32 = 30;
+/// Number of intervals into which the time axis is divided.
+const TIME_TICKS: u64 = 5;
+
+/// The levels of a single signal over time.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Series {
+    /// Name of the signal.
+    pub name: String,
+    /// Levels of the signal as pairs of time and level, in time order.
+    pub points: Vec<(u64, f32)>,
+}
+
+/// File format of a chart.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ChartFormat {
+    /// Scalable Vector Graphics.
+    Svg,
+    /// Portable Network Graphics, rendered with the fonts installed on the system.
+    #[cfg(feature = "png")]
+    Png,
+}
+
+/// Positions of the parts of a chart, shared by each of its formats.
+struct Layout {
+    /// Simulation time at the left edge of the chart.
+    start: u64,
+    /// Simulation time spanned by the chart, which is never zero.
+    span: f64,
+    /// Width of the area in which signals are drawn, in pixels.
+    plot_width: f64,
+    /// Height of the chart, in pixels.
+    height: u32,
+}
+
+impl Layout {
+    /// Lay out a chart.
+    ///
+    /// # Parameters
+    ///
+    /// - `lanes`: Number of signals drawn.
+    /// - `start`: Simulation time at the left edge of the chart.
+    /// - `stop`: Simulation time at the right edge of the chart.
+    /// - `width`: Width of the chart, in pixels.
+    fn new(lanes: usize, start: u64, stop: u64, width: u32) -> Self {
+        Self {
+            start,
+            span: stop.saturating_sub(start).max(1) as f64,
+            plot_width: width.saturating_sub(NAME_MARGIN + 10).max(1) as f64,
+            height: LANE_HEIGHT * lanes as u32 + AXIS_MARGIN,
+        }
+    }
+
+    /// Horizontal position of a simulation time.
+    fn x(&self, time: u64) -> f64 {
+        NAME_MARGIN as f64 + (time.saturating_sub(self.start) as f64 / self.span) * self.plot_width
+    }
+
+    /// Horizontal position of the right edge of the area in which signals are drawn.
+    fn right(&self) -> f64 {
+        NAME_MARGIN as f64 + self.plot_width
+    }
+
+    /// Vertical positions of the top and bottom of a signal's lane.
+    fn lane(&self, lane: usize) -> (f64, f64) {
+        let top = (lane as u32 * LANE_HEIGHT) as f64;
+        (top, top + LANE_HEIGHT as f64 - 10.0)
+    }
+
+    /// Vertical position of a level within a lane with the given bottom.
+    fn y(&self, bottom: f64, level: f32) -> f64 {
+        bottom - level.clamp(0.0, 1.0) as f64 * (LANE_HEIGHT as f64 - 20.0)
+    }
+
+    /// Vertical position of the time axis.
+    fn axis(&self) -> f64 {
+        (self.height - AXIS_MARGIN) as f64
+    }
+}
+
+/// Write a chart of the levels of several signals over a window of simulation time, in the chosen format.
+///
+/// # Parameters
+///
+/// - `out`: Destination of the chart.
+/// - `format`: File format of the chart.
+/// - `series`: The signals to draw, from top to bottom.
+/// - `start`: Simulation time at the left edge of the chart.
+/// - `stop`: Simulation time at the right edge of the chart.
+/// - `width`: Width of the chart, in pixels.
+pub fn write_chart(
+    out: impl Write,
+    format: ChartFormat,
+    series: &[Series],
+    start: u64,
+    stop: u64,
+    width: u32,
+) -> Result<(), String> {
+    match format {
+        ChartFormat::Svg => write_svg(out, series, start, stop, width),
+        #[cfg(feature = "png")]
+        ChartFormat::Png => write_png(out, series, start, stop, width),
+    }
+}
+
+/// Write an SVG chart of the levels of several signals over a window of simulation time.
+///
+/// Each signal is drawn in its own lane, scaled so that levels 0 and 1 span the lane, with a shared time axis along
+/// the bottom.  Points outside the window are clipped.
+///
+/// # Parameters
+///
+/// - `out`: Destination of the SVG output.
+/// - `series`: The signals to draw, from top to bottom.
+/// - `start`: Simulation time at the left edge of the chart.
+/// - `stop`: Simulation time at the right edge of the chart.
+/// - `width`: Width of the chart, in pixels.
+///
+/// # Example
+///
+/// ```
+/// # use rvfs_sim_core::trace::plot::{self, Series};
+/// let clk = Series {
+///     name: "CLK".to_string(),
+///     points: vec![(0, 0.0), (10, 1.0), (20, 0.0)],
+/// };
+/// let mut svg = Vec::new();
+/// plot::write_svg(&mut svg, &[clk], 0, 20, 400).unwrap();
+///
+/// assert!(String::from_utf8(svg).unwrap().starts_with("<svg"));
+/// ```
+pub fn write_svg(
+    mut out: impl Write,
+    series: &[Series],
+    start: u64,
+    stop: u64,
+    width: u32,
+) -> Result<(), String> {
+    let layout = Layout::new(series.len(), start, stop, width);
+    let height = layout.height;
+
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{width}\" height=\"{height}\" \
+         viewBox=\"0 0 {width} {height}\" font-family=\"monospace\" font-size=\"12\">\n"
+    );
+    svg += &format!("<rect width=\"{width}\" height=\"{height}\" fill=\"white\"/>\n");
+
+    for (lane, signal) in series.iter().enumerate() {
+        let (top, bottom) = layout.lane(lane);
+
+        svg += &format!(
+            "<text x=\"4\" y=\"{:.1}\">{}</text>\n",
+            (top + bottom) / 2.0 + 4.0,
+            escape(&signal.name)
+        );
+        svg += &format!(
+            "<line x1=\"{NAME_MARGIN}\" y1=\"{bottom:.1}\" x2=\"{:.1}\" y2=\"{bottom:.1}\" stroke=\"#ccc\"/>\n",
+            layout.right()
+        );
+
+        let points: Vec<String> = signal
+            .points
+            .iter()
+            .filter(|(time, _)| *time >= start && *time <= stop)
+            .map(|(time, level)| format!("{:.1},{:.1}", layout.x(*time), layout.y(bottom, *level)))
+            .collect();
+        if !points.is_empty() {
+            svg += &format!(
+                "<polyline fill=\"none\" stroke=\"#1f77b4\" stroke-width=\"1.5\" points=\"{}\"/>\n",
+                points.join(" ")
+            );
+        }
+    }
+
+    let axis = layout.axis();
+    svg += &format!(
+        "<line x1=\"{NAME_MARGIN}\" y1=\"{axis:.1}\" x2=\"{:.1}\" y2=\"{axis:.1}\" stroke=\"black\"/>\n",
+        layout.right()
+    );
+    for time in ticks(start, stop) {
+        svg += &format!(
+            "<text x=\"{:.1}\" y=\"{:.1}\" text-anchor=\"middle\">{time}</text>\n",
+            layout.x(time),
+            axis + 18.0
+        );
+    }
+    svg += "</svg>\n";
+
+    out.write_all(svg.as_bytes())
+        .and_then(|_| out.flush())
+        .map_err(|err| err.to_string())
+}
+
+/// Write a PNG image of a chart of the levels of several signals over a window of simulation time.
+///
+/// The chart is laid out as [write_svg] lays it out, with text drawn in a monospace font found on the system.
+///
+/// # Parameters
+///
+/// - `out`: Destination of the PNG output.
+/// - `series`: The signals to draw, from top to bottom.
+/// - `start`: Simulation time at the left edge of the chart.
+/// - `stop`: Simulation time at the right edge of the chart.
+/// - `width`: Width of the chart, in pixels.
+#[cfg(feature = "png")]
+pub fn write_png(
+    mut out: impl Write,
+    series: &[Series],
+    start: u64,
+    stop: u64,
+    width: u32,
+) -> Result<(), String> {
+    use image::ImageEncoder;
+
+    let layout = Layout::new(series.len(), start, stop, width);
+    let height = layout.height;
+    let mut pixels = vec![0u8; width as usize * height as usize * 3];
+    {
+        let root = BitMapBackend::with_buffer(&mut pixels, (width, height)).into_drawing_area();
+        let failed = |err: DrawingAreaErrorKind<_>| err.to_string();
+        let font = ("monospace", 12).into_font().color(&BLACK);
+        root.fill(&WHITE).map_err(failed)?;
+
+        let right = layout.right() as i32;
+        for (lane, signal) in series.iter().enumerate() {
+            let (top, bottom) = layout.lane(lane);
+            let name = font.pos(Pos::new(HPos::Left, VPos::Center));
+            root.draw(&Text::new(
+                signal.name.as_str(),
+                (4, ((top + bottom) / 2.0) as i32),
+                name,
+            ))
+            .map_err(failed)?;
+            let base = bottom as i32;
+            root.draw(&PathElement::new(
+                [(NAME_MARGIN as i32, base), (right, base)],
+                RGBColor(0xcc, 0xcc, 0xcc),
+            ))
+            .map_err(failed)?;
+
+            let points: Vec<(i32, i32)> = signal
+                .points
+                .iter()
+                .filter(|(time, _)| *time >= start && *time <= stop)
+                .map(|(time, level)| (layout.x(*time) as i32, layout.y(bottom, *level) as i32))
+                .collect();
+            root.draw(&PathElement::new(
+                points,
+                RGBColor(0x1f, 0x77, 0xb4).stroke_width(2),
+            ))
+            .map_err(failed)?;
+        }
+
+        let axis = layout.axis() as i32;
+        root.draw(&PathElement::new(
+            [(NAME_MARGIN as i32, axis), (right, axis)],
+            BLACK,
+        ))
+        .map_err(failed)?;
+        let label = font.pos(Pos::new(HPos::Center, VPos::Top));
+        for time in ticks(start, stop) {
+            root.draw(&Text::new(
+                time.to_string(),
+                (layout.x(time) as i32, axis + 6),
+                label.clone(),
+            ))
+            .map_err(failed)?;
+        }
+        root.present().map_err(failed)?;
+    }
+
+    image::codecs::png::PngEncoder::new(&mut out)
+        .write_image(&pixels, width, height, image::ColorType::Rgb8)
+        .map_err(|err| err.to_string())?;
+    out.flush().map_err(|err| err.to_string())
+}
+
+/// Obtain the simulation times labelled along the time axis.
+fn ticks(start: u64, stop: u64) -> impl Iterator<Item = u64> {
+    (0..=TIME_TICKS).map(move |tick| start + (stop.saturating_sub(start)) * tick / TIME_TICKS)
+}
+
+/// Escape text for inclusion in SVG.
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// A Tracer which collects the analog levels of the traced Wires and writes them as a chart when tracing finishes.
+///
+/// Charts are written as SVG unless another [format](ChartFormat) is chosen.
+///
+/// # Example
+///
+/// ```
+/// # use rvfs_sim_core::sim::Simulation;
+/// # use rvfs_sim_core::time::SimDuration;
+/// # use rvfs_sim_core::trace::plot::PlotWriter;
+/// # use rvfs_sim_core::wire::{Wire, WirePull};
+/// let mut sim = Simulation::new(SimDuration::from_ticks(10));
+/// sim.add_wire(Wire::new("/RESET", WirePull::Up)).unwrap();
+/// sim.add_tracer(Box::new(PlotWriter::new(std::io::sink()).with_window(0, 1_000)));
+///
+/// sim.step().unwrap();
+/// sim.finish_tracers().unwrap();
+/// ```
+pub struct PlotWriter<W: Write + Send> {
+    /// Destination of the chart.
+    out: W,
+    /// File format of the chart.
+    format: ChartFormat,
+    /// Ids of the Wires to trace, or None to trace every Wire.
+    selection: Option<Vec<WireId>>,
+    /// Window of simulation time to chart, or None to chart the whole run.
+    window: Option<(u64, u64)>,
+    /// Width of the chart, in pixels.
+    width: u32,
+    /// Ids of the Wires being traced, in the order of the series.
+    ids: Vec<WireId>,
+    /// Levels collected for each traced Wire.
+    series: Vec<Series>,
+    /// Simulation times at which tracing started and most recently recorded.
+    span: (u64, u64),
+}
+
+impl<W: Write + Send> PlotWriter<W> {
+    /// Create a new PlotWriter which charts every Wire over the whole run.
+    ///
+    /// # Parameters
+    ///
+    /// - `out`: Destination of the chart.
+    pub fn new(out: W) -> Self {
+        Self {
+            out,
+            format: ChartFormat::Svg,
+            selection: None,
+            window: None,
+            width: DEFAULT_WIDTH,
+            ids: Vec::new(),
+            series: Vec::new(),
+            span: (0, 0),
+        }
+    }
+
+    /// Restrict charting to the selected Wires.
+    ///
+    /// # Parameters
+    ///
+    /// - `ids`: Ids of the Wires to chart, from top to bottom.
+    pub fn select(mut self, ids: &[WireId]) -> Self {
+        self.selection = Some(ids.to_vec());
+        self
+    }
+
+    /// Limit the chart to a window of simulation time.
+    ///
+    /// # Parameters
+    ///
+    /// - `start`: Simulation time at the left edge of the chart.
+    /// - `stop`: Simulation time at the right edge of the chart.
+    pub fn with_window(mut self, start: u64, stop: u64) -> Self {
+        self.window = Some((start, stop));
+        self
+    }
+
+    /// Change the width of the chart.
+    ///
+    /// # Parameters
+    ///
+    /// - `width`: Width of the chart, in pixels.
+    pub fn with_width(mut self, width: u32) -> Self {
+        self.width = width;
+        self
+    }
+
+    /// Change the file format of the chart.
+    ///
+    /// # Parameters
+    ///
+    /// - `format`: File format of the chart.
+    pub fn with_format(mut self, format: ChartFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Consume the writer and obtain the underlying output.
+    pub fn into_inner(self) -> W {
+        self.out
+    }
+
+    /// Query whether a simulation time falls within the charted window.
+    fn in_window(&self, time: u64) -> bool {
+        self.window
+            .is_none_or(|(start, stop)| time >= start && time <= stop)
+    }
+}
+
+impl<W: Write + Send> Tracer for PlotWriter<W> {
+    fn start(&mut self, sim: &Simulation) -> Result<(), String> {
+        self.ids = match &self.selection {
+            Some(ids) => ids.clone(),
+            None => sim.wires().collect(),
+        };
+        self.series.clear();
+        for id in &self.ids {
+            let wire = sim.wire(*id)?;
+            let mut points = Vec::new();
+            if self.in_window(sim.time()) {
+                points.push((sim.time(), f32::from(wire.measure())));
+            }
+            self.series.push(Series {
+                name: wire.name().clone(),
+                points,
+            });
+        }
+        self.span = (sim.time(), sim.time());
+        Ok(())
+    }
+
+    fn record(&mut self, sim: &Simulation, _changes: &[Change]) -> Result<(), String> {
+        self.span.1 = sim.time();
+        if !self.in_window(sim.time()) {
+            return Ok(());
+        }
+        for (id, series) in self.ids.iter().zip(&mut self.series) {
+            series
+                .points
+                .push((sim.time(), f32::from(sim.wire(*id)?.measure())));
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self, _sim: &Simulation) -> Result<(), String> {
+        let (start, stop) = self.window.unwrap_or(self.span);
+        write_chart(
+            &mut self.out,
+            self.format,
+            &self.series,
+            start,
+            stop,
+            self.width,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(feature = "png")]
+    use crate::time::SimDuration;
+    use crate::trace::testing::{falling_wire_sim, trace};
+    use crate::wire::{Wire, WirePull};
+
+    #[test]
+    fn plot_write_svg() {
+        // GIVEN a signal rising across a window
+        let series = Series {
+            name: "A<B".to_string(),
+            points: vec![(0, 0.0), (50, 0.5), (100, 1.0), (200, 1.0)],
+        };
+        // WHEN it is charted over part of its span
+        let mut svg = Vec::new();
+        write_svg(&mut svg, &[series], 0, 100, 240).unwrap();
+        let svg = String::from_utf8(svg).unwrap();
+        // THEN the points within the window are drawn across the lane, and the name is escaped
+        assert!(svg.contains("points=\"140.0,50.0 185.0,30.0 230.0,10.0\""));
+        assert!(svg.contains(">A&lt;B</text>"));
+        assert!(svg.trim_end().ends_with("</svg>"));
+    }
+    #[test]
+    fn plot_writer() {
+        // GIVEN a Simulation with a wire falling from high to low, and another which is not charted
+        let (mut sim, reset) = falling_wire_sim(10.0);
+        sim.add_wire(Wire::new("CLK", WirePull::Down)).unwrap();
+        // WHEN it is charted within a window
+        let plot = PlotWriter::new(Vec::new())
+            .select(&[reset])
+            .with_window(10, 30);
+        let plot = trace(&mut sim, plot, 4);
+        let svg = String::from_utf8(plot.into_inner()).unwrap();
+        // THEN only the selected wire is charted, with a point for each step within the window
+        assert!(svg.contains(">/RESET</text>"));
+        assert!(!svg.contains("CLK"));
+        let polyline = svg.lines().find(|l| l.starts_with("<polyline")).unwrap();
+        assert_eq!(3, polyline.matches(',').count());
+    }
+    #[cfg(feature = "png")]
+    #[test]
+    fn plot_writer_png() {
+        // GIVEN a Simulation with a pulled-up wire
+        let mut sim = Simulation::new(SimDuration::from_ticks(10));
+        sim.add_wire(Wire::new("/RESET", WirePull::Up)).unwrap();
+        // WHEN it is charted as a PNG image
+        let mut plot = PlotWriter::new(Vec::new())
+            .with_format(ChartFormat::Png)
+            .with_width(240);
+        plot.start(&sim).unwrap();
+        for _ in 0..4 {
+            sim.step().unwrap();
+            plot.record(&sim, sim.changes()).unwrap();
+        }
+        plot.finish(&sim).unwrap();
+        // THEN the image is laid out as the SVG chart is, with the level drawn high across the lane
+        let image = image::load_from_memory(&plot.into_inner())
+            .unwrap()
+            .to_rgb8();
+        assert_eq!((240, LANE_HEIGHT + AXIS_MARGIN), image.dimensions());
+        assert_eq!([0x1f, 0x77, 0xb4], image.get_pixel(185, 10).0);
+        assert_eq!([0xff, 0xff, 0xff], image.get_pixel(185, 30).0);
+    }
+}
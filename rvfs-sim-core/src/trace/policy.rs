@@ -0,0 +1,288 @@
+//! Per-signal policies controlling which changes are passed on to a Tracer.
+
+use crate::sim::Simulation;
+use crate::trace::{Change, Tracer};
+use crate::wirevalue::WireValue;
+use crate::WireId;
+use std::collections::HashMap;
+
+/// Policy determining when the value of a traced signal is recorded.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum TracePolicy {
+    /// Record every change in value.
+    EveryChange,
+    /// Record the value every N steps, if it has changed since it was last recorded.
+    Sampled(u64),
+    /// Record the value only when it crosses the given level.
+    Crossing(f32),
+}
+
+/// A Tracer adapter which filters the changes passed to another Tracer according to per-signal policies.
+///
+/// Tracing only begins once the simulation time reaches the configured start time, at which point the inner Tracer is
+/// started and sees the Simulation state as its initial state.  Changes after the configured stop time are discarded.
+///
+/// # Example
+///
+/// ```
+/// # use rvfs_sim_core::sim::Simulation;
+/// # use rvfs_sim_core::time::SimDuration;
+/// # use rvfs_sim_core::trace::policy::{PolicyTracer, TracePolicy};
+/// # use rvfs_sim_core::trace::vcd::VcdWriter;
+/// # use rvfs_sim_core::wire::{Wire, WirePull};
+/// let mut sim = Simulation::new(SimDuration::from_ticks(10));
+/// let clk = sim.add_wire(Wire::new("CLK", WirePull::Down)).unwrap();
+/// let tracer = PolicyTracer::new(VcdWriter::new(std::io::sink()))
+///     .with_policy(clk, TracePolicy::Crossing(0.5))
+///     .with_window(1_000, 5_000);
+/// sim.add_tracer(Box::new(tracer));
+/// ```
+pub struct PolicyTracer<T: Tracer> {
+    /// Tracer receiving the filtered changes.
+    inner: T,
+    /// Policy applied to signals without an explicit policy.
+    default: TracePolicy,
+    /// Explicit per-signal policies, keyed by Wire Id.
+    policies: HashMap<WireId, TracePolicy>,
+    /// Simulation time at which tracing begins.
+    start_time: u64,
+    /// Simulation time after which tracing ends.
+    stop_time: u64,
+    /// Whether the inner Tracer has been started.
+    started: bool,
+    /// Number of steps recorded since the inner Tracer was started.
+    steps: u64,
+    /// Last value passed on for each signal, keyed by Wire Id.
+    recorded: HashMap<WireId, WireValue>,
+}
+
+impl<T: Tracer> PolicyTracer<T> {
+    /// Create a new PolicyTracer which passes on every change, for all time.
+    ///
+    /// # Parameters
+    ///
+    /// - `inner`: Tracer to receive the filtered changes.
+    pub fn new(inner: T) -> Self {
+        Self {
+            inner,
+            default: TracePolicy::EveryChange,
+            policies: HashMap::new(),
+            start_time: 0,
+            stop_time: u64::MAX,
+            started: false,
+            steps: 0,
+            recorded: HashMap::new(),
+        }
+    }
+
+    /// Change the policy applied to signals without an explicit policy.
+    ///
+    /// # Parameters
+    ///
+    /// - `policy`: New default policy.
+    pub fn with_default(mut self, policy: TracePolicy) -> Self {
+        self.default = policy;
+        self
+    }
+
+    /// Set the policy for a single signal.
+    ///
+    /// # Parameters
+    ///
+    /// - `id`: Id of the Wire to which the policy applies.
+    /// - `policy`: Policy for the Wire.
+    pub fn with_policy(mut self, id: WireId, policy: TracePolicy) -> Self {
+        self.policies.insert(id, policy);
+        self
+    }
+
+    /// Limit tracing to a window of simulation time.
+    ///
+    /// # Parameters
+    ///
+    /// - `start`: Simulation time at which tracing begins.
+    /// - `stop`: Simulation time after which tracing ends.
+    pub fn with_window(mut self, start: u64, stop: u64) -> Self {
+        self.start_time = start;
+        self.stop_time = stop;
+        self
+    }
+
+    /// Consume the adapter and obtain the inner Tracer.
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+
+    /// Determine the policy which applies to a signal.
+    fn policy(&self, id: WireId) -> TracePolicy {
+        self.policies.get(&id).copied().unwrap_or(self.default)
+    }
+
+    /// Start the inner Tracer if the start time has been reached.
+    fn try_start(&mut self, sim: &Simulation) -> Result<(), String> {
+        if !self.started && sim.time() >= self.start_time && sim.time() <= self.stop_time {
+            self.started = true;
+            self.recorded = sim
+                .wires()
+                .map(|id| sim.wire(id).map(|wire| (id, wire.measure())))
+                .collect::<Result<_, _>>()?;
+            self.inner.start(sim)?;
+        }
+
+        Ok(())
+    }
+
+    /// Determine whether a new value should be passed on for a signal, given the value last passed on.
+    fn accept(&self, policy: TracePolicy, previous: WireValue, value: WireValue) -> bool {
+        match policy {
+            TracePolicy::EveryChange => previous != value,
+            TracePolicy::Sampled(n) => previous != value && self.steps.is_multiple_of(n.max(1)),
+            TracePolicy::Crossing(level) => {
+                (f32::from(previous) < level) != (f32::from(value) < level)
+            }
+        }
+    }
+}
+
+impl<T: Tracer> Tracer for PolicyTracer<T> {
+    fn start(&mut self, sim: &Simulation) -> Result<(), String> {
+        self.try_start(sim)
+    }
+
+    fn record(&mut self, sim: &Simulation, changes: &[Change]) -> Result<(), String> {
+        if !self.started {
+            return self.try_start(sim);
+        }
+        if sim.time() > self.stop_time {
+            return Ok(());
+        }
+        self.steps += 1;
+
+        let mut accepted = Vec::new();
+        for change in changes {
+            let policy = self.policy(change.id);
+            let previous = self
+                .recorded
+                .get(&change.id)
+                .copied()
+                .unwrap_or(change.previous);
+            if !matches!(policy, TracePolicy::Sampled(_))
+                && self.accept(policy, previous, change.value)
+            {
+                accepted.push(Change {
+                    previous,
+                    ..*change
+                });
+            }
+        }
+
+        // Sampled signals may have changed during an earlier step, so check their present values.
+        for id in sim.wires() {
+            let policy = self.policy(id);
+            if let TracePolicy::Sampled(_) = policy {
+                let value = sim.wire(id)?.measure();
+                let previous = self.recorded.get(&id).copied().unwrap_or(value);
+                if self.accept(policy, previous, value) {
+                    accepted.push(Change {
+                        id,
+                        previous,
+                        value,
+                    });
+                }
+            }
+        }
+
+        accepted.sort_by_key(|change| change.id);
+        for change in &accepted {
+            self.recorded.insert(change.id, change.value);
+        }
+        self.inner.record(sim, &accepted)
+    }
+
+    fn finish(&mut self, sim: &Simulation) -> Result<(), String> {
+        if self.started {
+            self.inner.finish(sim)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::trace::testing::{self, falling_wire_sim};
+
+    /// Tracer which remembers everything it is given.
+    #[derive(Default)]
+    struct Recorder {
+        /// Simulation time at which the Tracer was started.
+        started: Option<u64>,
+        /// Simulation time and Wire Id of every change recorded.
+        changes: Vec<(u64, WireId)>,
+    }
+
+    impl Tracer for Recorder {
+        fn start(&mut self, sim: &Simulation) -> Result<(), String> {
+            self.started = Some(sim.time());
+            Ok(())
+        }
+        fn record(&mut self, sim: &Simulation, changes: &[Change]) -> Result<(), String> {
+            self.changes
+                .extend(changes.iter().map(|c| (sim.time(), c.id)));
+            Ok(())
+        }
+        fn finish(&mut self, _sim: &Simulation) -> Result<(), String> {
+            Ok(())
+        }
+    }
+
+    /// Trace a number of steps of a Simulation through a PolicyTracer.
+    fn trace(sim: &mut Simulation, tracer: PolicyTracer<Recorder>, steps: usize) -> Recorder {
+        testing::trace(sim, tracer, steps).into_inner()
+    }
+
+    #[test]
+    fn policy_every_change() {
+        // GIVEN a Simulation with a falling wire
+        let (mut sim, id) = falling_wire_sim(20.0);
+        // WHEN it is traced with the default policy
+        let recorder = trace(&mut sim, PolicyTracer::new(Recorder::default()), 3);
+        // THEN every change is passed on
+        assert_eq!(Some(0), recorder.started);
+        assert_eq!(vec![(10, id), (20, id), (30, id)], recorder.changes);
+    }
+    #[test]
+    fn policy_sampled() {
+        // GIVEN a Simulation with a falling wire
+        let (mut sim, id) = falling_wire_sim(20.0);
+        // WHEN it is traced with a policy of sampling every third step
+        let tracer =
+            PolicyTracer::new(Recorder::default()).with_policy(id, TracePolicy::Sampled(3));
+        let recorder = trace(&mut sim, tracer, 7);
+        // THEN only every third step is passed on
+        assert_eq!(vec![(30, id), (60, id)], recorder.changes);
+    }
+    #[test]
+    fn policy_crossing() {
+        // GIVEN a Simulation with a falling wire
+        let (mut sim, id) = falling_wire_sim(20.0);
+        // WHEN it is traced with a policy of only recording crossings of the midpoint
+        let tracer =
+            PolicyTracer::new(Recorder::default()).with_default(TracePolicy::Crossing(0.5));
+        let recorder = trace(&mut sim, tracer, 6);
+        // THEN only the step where the level falls through the midpoint is passed on
+        assert_eq!(vec![(20, id)], recorder.changes);
+    }
+    #[test]
+    fn policy_window() {
+        // GIVEN a Simulation with a falling wire
+        let (mut sim, id) = falling_wire_sim(20.0);
+        // WHEN it is traced within a time window
+        let tracer = PolicyTracer::new(Recorder::default()).with_window(20, 40);
+        let recorder = trace(&mut sim, tracer, 6);
+        // THEN the inner tracer starts at the window start and only changes within the window are passed on
+        assert_eq!(Some(20), recorder.started);
+        assert_eq!(vec![(30, id), (40, id)], recorder.changes);
+    }
+}
@@ -0,0 +1,834 @@
+//! Oscilloscope-style capture of selected signals around a trigger event.
+
+use crate::monitor::Condition;
+use crate::sim::Simulation;
+use crate::trace::{
+    csv, plot, vcd, Change, Logic, Tracer, DEFAULT_HIGH_THRESHOLD, DEFAULT_LOW_THRESHOLD,
+};
+use crate::wirevalue::WireValue;
+use crate::WireId;
+use std::collections::{BTreeMap, VecDeque};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+/// Default number of changes of each signal retained from before the trigger.
+const DEFAULT_PRE_TRIGGER_DEPTH: usize = 16;
+/// Default number of steps captured after the trigger.
+const DEFAULT_POST_TRIGGER_DEPTH: usize = 16;
+/// Width of dumped charts, in pixels.
+const CHART_WIDTH: u32 = 800;
+
+/// Direction of a logic transition.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Edge {
+    /// A transition from logic low to logic high.
+    Rising,
+    /// A transition from logic high to logic low.
+    Falling,
+    /// A transition in either direction.
+    Either,
+}
+
+/// An event which starts a capture: an edge on a signal, optionally qualified by a condition.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Trigger {
+    /// Id of the Wire whose edge fires the trigger.
+    signal: WireId,
+    /// Direction of the edge.
+    edge: Edge,
+    /// Condition which must also hold when the edge occurs.
+    qualifier: Option<Condition>,
+}
+
+impl Trigger {
+    /// Create a Trigger which fires on an edge of a signal.
+    ///
+    /// # Parameters
+    ///
+    /// - `signal`: Id of the Wire to watch.
+    /// - `edge`: Direction of the edge which fires the trigger.
+    pub fn new(signal: WireId, edge: Edge) -> Self {
+        Self {
+            signal,
+            edge,
+            qualifier: None,
+        }
+    }
+
+    /// Only fire the trigger if a condition holds when the edge occurs.
+    ///
+    /// # Parameters
+    ///
+    /// - `qualifier`: Condition which must hold, such as another signal being high.
+    pub fn when(mut self, qualifier: Condition) -> Self {
+        self.qualifier = Some(qualifier);
+        self
+    }
+
+    /// Determine whether a transition between definite logic levels matches the edge of the trigger.
+    fn matches(&self, from: Logic, to: Logic) -> bool {
+        match self.edge {
+            Edge::Rising => from == Logic::Low && to == Logic::High,
+            Edge::Falling => from == Logic::High && to == Logic::Low,
+            Edge::Either => from != to,
+        }
+    }
+}
+
+/// A fixed-capacity buffer which discards its oldest item when a new item is pushed while it is full.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RingBuffer<T> {
+    /// Items in the buffer, oldest first.
+    items: VecDeque<T>,
+    /// Maximum number of items held.
+    capacity: usize,
+}
+
+impl<T> RingBuffer<T> {
+    /// Create a new, empty RingBuffer.
+    ///
+    /// # Parameters
+    ///
+    /// - `capacity`: Maximum number of items held.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            items: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    /// Add an item, discarding the oldest item if the buffer is full.
+    ///
+    /// # Parameters
+    ///
+    /// - `item`: The item to add.
+    pub fn push(&mut self, item: T) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.items.len() == self.capacity {
+            self.items.pop_front();
+        }
+        self.items.push_back(item);
+    }
+
+    /// Obtain the number of items held.
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    /// Query whether the buffer holds no items.
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// Obtain the maximum number of items held.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Iterate over the items held, oldest first.
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.items.iter()
+    }
+}
+
+/// File format in which captures are dumped.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum DumpFormat {
+    /// Value Change Dump, with both logic and analog levels.
+    Vcd,
+    /// Comma-separated analog levels.
+    Csv,
+    /// SVG chart of the analog levels.
+    Svg,
+    /// PNG image of a chart of the analog levels.
+    #[cfg(feature = "png")]
+    Png,
+}
+
+/// The level changes of a single signal within a capture.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CapturedSignal {
+    /// Name of the Wire.
+    pub name: String,
+    /// Level changes of the Wire as pairs of time and level, in time order.
+    pub changes: Vec<(u64, WireValue)>,
+}
+
+/// The signals captured around a trigger event.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Capture {
+    /// Index of the trigger which fired, in the order the triggers were armed.
+    pub trigger: usize,
+    /// Simulation time at which the trigger fired.
+    pub trigger_time: u64,
+    /// The captured signals, starting with the retained pre-trigger history of each.
+    pub signals: Vec<CapturedSignal>,
+}
+
+impl Capture {
+    /// Obtain the level changes of every signal, grouped by time in time order.
+    ///
+    /// Each group holds pairs of signal index and level.
+    fn timeline(&self) -> BTreeMap<u64, Vec<(usize, WireValue)>> {
+        let mut timeline = BTreeMap::<u64, Vec<(usize, WireValue)>>::new();
+        for (index, signal) in self.signals.iter().enumerate() {
+            for (time, value) in &signal.changes {
+                timeline.entry(*time).or_default().push((index, *value));
+            }
+        }
+        timeline
+    }
+
+    /// Write the capture as a VCD file.
+    ///
+    /// Each signal is written as a single-bit logic signal using the default thresholds, alongside its real-valued
+    /// level.
+    ///
+    /// # Parameters
+    ///
+    /// - `out`: Destination of the VCD output.
+    pub fn write_vcd(&self, mut out: impl Write) -> Result<(), String> {
+        let mut text = String::new();
+        text += "$version rvfs-sim $end\n";
+        text += &format!("$comment trigger fired at {} $end\n", self.trigger_time);
+        text += "$timescale 1ns $end\n";
+        let mut logic_vars = String::new();
+        let mut level_vars = String::new();
+        for (index, signal) in self.signals.iter().enumerate() {
+            let name = vcd::reference(&signal.name);
+            logic_vars += &format!("$var wire 1 {} {name} $end\n", vcd::identifier(2 * index));
+            level_vars += &format!(
+                "$var real 64 {} {name} $end\n",
+                vcd::identifier(2 * index + 1)
+            );
+        }
+        text += "$scope module logic $end\n";
+        text += &logic_vars;
+        text += "$upscope $end\n";
+        text += "$scope module levels $end\n";
+        text += &level_vars;
+        text += "$upscope $end\n";
+        text += "$enddefinitions $end\n";
+
+        for (time, changes) in self.timeline() {
+            text += &format!("#{time}\n");
+            for (index, value) in changes {
+                let logic = Logic::from_level(value, DEFAULT_LOW_THRESHOLD, DEFAULT_HIGH_THRESHOLD);
+                text += &format!("{}{}\n", logic.symbol(), vcd::identifier(2 * index));
+                text += &format!("r{} {}\n", f32::from(value), vcd::identifier(2 * index + 1));
+            }
+        }
+
+        out.write_all(text.as_bytes())
+            .and_then(|_| out.flush())
+            .map_err(|err| err.to_string())
+    }
+
+    /// Write the capture as an SVG chart of the analog levels, spanning the earliest to the latest change.
+    ///
+    /// # Parameters
+    ///
+    /// - `out`: Destination of the SVG output.
+    /// - `width`: Width of the chart, in pixels.
+    pub fn write_svg(&self, out: impl Write, width: u32) -> Result<(), String> {
+        self.write_chart(out, plot::ChartFormat::Svg, width)
+    }
+
+    /// Write the capture as a PNG image of a chart of the analog levels, spanning the earliest to the latest change.
+    ///
+    /// # Parameters
+    ///
+    /// - `out`: Destination of the PNG output.
+    /// - `width`: Width of the chart, in pixels.
+    #[cfg(feature = "png")]
+    pub fn write_png(&self, out: impl Write, width: u32) -> Result<(), String> {
+        self.write_chart(out, plot::ChartFormat::Png, width)
+    }
+
+    /// Write the capture as a chart of the analog levels, spanning the earliest to the latest change.
+    ///
+    /// # Parameters
+    ///
+    /// - `out`: Destination of the chart.
+    /// - `format`: File format of the chart.
+    /// - `width`: Width of the chart, in pixels.
+    fn write_chart(
+        &self,
+        out: impl Write,
+        format: plot::ChartFormat,
+        width: u32,
+    ) -> Result<(), String> {
+        let timeline = self.timeline();
+        let start = timeline.keys().next().copied().unwrap_or(self.trigger_time);
+        let stop = timeline.keys().last().copied().unwrap_or(self.trigger_time);
+        let series: Vec<plot::Series> = self
+            .signals
+            .iter()
+            .map(|signal| plot::Series {
+                name: signal.name.clone(),
+                points: signal
+                    .changes
+                    .iter()
+                    .map(|(time, value)| (*time, f32::from(*value)))
+                    .collect(),
+            })
+            .collect();
+        plot::write_chart(out, format, &series, start, stop, width)
+    }
+
+    /// Write the capture as CSV.
+    ///
+    /// The first row names the columns: `time` followed by the name of each signal.  A row is written for each time
+    /// at which any signal changed, holding the level of every signal at that time.  Levels of signals not yet seen
+    /// are left empty.
+    ///
+    /// # Parameters
+    ///
+    /// - `out`: Destination of the CSV output.
+    pub fn write_csv(&self, mut out: impl Write) -> Result<(), String> {
+        let mut text = "time".to_string();
+        for signal in &self.signals {
+            text += &format!(",{}", csv::field(&signal.name));
+        }
+        text += "\n";
+
+        let mut levels: Vec<Option<WireValue>> = vec![None; self.signals.len()];
+        for (time, changes) in self.timeline() {
+            for (index, value) in changes {
+                levels[index] = Some(value);
+            }
+            text += &time.to_string();
+            for level in &levels {
+                text += ",";
+                if let Some(value) = level {
+                    text += &f32::from(*value).to_string();
+                }
+            }
+            text += "\n";
+        }
+
+        out.write_all(text.as_bytes())
+            .and_then(|_| out.flush())
+            .map_err(|err| err.to_string())
+    }
+}
+
+/// A capture which is still collecting post-trigger changes.
+#[derive(Debug, Clone)]
+struct Pending {
+    /// The capture collected so far.
+    capture: Capture,
+    /// Number of steps remaining to be captured.
+    remaining: usize,
+}
+
+/// Runtime state of an armed trigger.
+#[derive(Debug, Clone, Default)]
+struct Armed {
+    /// Last definite logic level of the trigger signal.
+    level: Option<Logic>,
+    /// Whether the trigger has fired.
+    fired: bool,
+    /// The capture in progress, if the trigger has fired and the capture is incomplete.
+    pending: Option<Pending>,
+}
+
+/// State shared between all clones of a TriggeredCapture.
+#[derive(Debug, Default)]
+struct State {
+    /// Names of the captured signals.
+    names: Vec<String>,
+    /// Recent level changes of each captured signal, in the order of the names.
+    history: Vec<RingBuffer<(u64, WireValue)>>,
+    /// Runtime state of each armed trigger, in the order they were armed.
+    armed: Vec<Armed>,
+    /// Completed captures, in order of completion.
+    captures: Vec<Capture>,
+}
+
+/// A Tracer which captures selected signals only once a trigger fires, retaining a short history from before it.
+///
+/// Until a trigger fires, the level changes of each captured signal are kept in a separate ring buffer holding its
+/// most recent changes, so long runs use little memory and a capture contains only the activity leading up to and
+/// following the event of interest.  Any number of triggers may be armed at once; each fires at most once and
+/// produces its own capture, which is complete once the configured number of steps after the trigger have been
+/// captured, or when tracing finishes.  Completed captures can optionally be dumped to files as they complete.
+///
+/// Clones of a TriggeredCapture share their state, so a clone can be kept to obtain the captures after the original
+/// has been attached to a Simulation.
+///
+/// # Example
+///
+/// ```
+/// # use rvfs_sim_core::monitor::Condition;
+/// # use rvfs_sim_core::sim::Simulation;
+/// # use rvfs_sim_core::time::SimDuration;
+/// # use rvfs_sim_core::trace::trigger::{Edge, Trigger, TriggeredCapture};
+/// # use rvfs_sim_core::wire::{Wire, WirePull};
+/// let mut sim = Simulation::new(SimDuration::from_ticks(10));
+/// let clk = sim.add_wire(Wire::new("CLK", WirePull::Down)).unwrap();
+/// let enable = sim.add_wire(Wire::new("EN", WirePull::Up)).unwrap();
+/// let capture = TriggeredCapture::new(&[clk, enable])
+///     .arm(Trigger::new(clk, Edge::Rising).when(Condition::High(enable)))
+///     .with_depth(100, 50);
+/// sim.add_tracer(Box::new(capture.clone()));
+///
+/// sim.step().unwrap();
+/// assert!(capture.captures().is_empty());
+/// ```
+#[derive(Debug, Clone)]
+pub struct TriggeredCapture {
+    /// Events which start a capture.
+    triggers: Vec<Trigger>,
+    /// Ids of the captured Wires.
+    signals: Vec<WireId>,
+    /// Number of changes of each signal retained from before a trigger.
+    pre_trigger: usize,
+    /// Number of steps captured after a trigger.
+    post_trigger: usize,
+    /// Wire level at or below which a signal is considered logic low.
+    low_threshold: f32,
+    /// Wire level at or above which a signal is considered logic high.
+    high_threshold: f32,
+    /// Directory and format in which to dump completed captures.
+    dump: Option<(PathBuf, DumpFormat)>,
+    /// Shared capture state.
+    state: Arc<Mutex<State>>,
+}
+
+impl TriggeredCapture {
+    /// Create a new TriggeredCapture with no triggers armed.
+    ///
+    /// # Parameters
+    ///
+    /// - `signals`: Ids of the Wires to capture.
+    pub fn new(signals: &[WireId]) -> Self {
+        Self {
+            triggers: Vec::new(),
+            signals: signals.to_vec(),
+            pre_trigger: DEFAULT_PRE_TRIGGER_DEPTH,
+            post_trigger: DEFAULT_POST_TRIGGER_DEPTH,
+            low_threshold: DEFAULT_LOW_THRESHOLD,
+            high_threshold: DEFAULT_HIGH_THRESHOLD,
+            dump: None,
+            state: Arc::new(Mutex::new(State::default())),
+        }
+    }
+
+    /// Arm a trigger.
+    ///
+    /// # Parameters
+    ///
+    /// - `trigger`: Event which starts a capture.
+    pub fn arm(mut self, trigger: Trigger) -> Self {
+        self.triggers.push(trigger);
+        self
+    }
+
+    /// Change the amount captured either side of a trigger.
+    ///
+    /// # Parameters
+    ///
+    /// - `pre`: Number of changes of each signal retained from before a trigger.
+    /// - `post`: Number of steps captured after a trigger.
+    pub fn with_depth(mut self, pre: usize, post: usize) -> Self {
+        self.pre_trigger = pre;
+        self.post_trigger = post;
+        self
+    }
+
+    /// Change the thresholds used to detect edges of the trigger signals.
+    ///
+    /// # Parameters
+    ///
+    /// - `low`: Level at or below which a signal is considered logic low.
+    /// - `high`: Level at or above which a signal is considered logic high.
+    pub fn with_thresholds(mut self, low: f32, high: f32) -> Self {
+        self.low_threshold = low;
+        self.high_threshold = high;
+        self
+    }
+
+    /// Dump each capture to a file as it completes.
+    ///
+    /// Files are named `capture-<trigger>-<time>` with an extension for the format, where `<trigger>` is the index of
+    /// the trigger in the order the triggers were armed and `<time>` is the simulation time at which it fired.
+    ///
+    /// # Parameters
+    ///
+    /// - `directory`: Directory in which to create the files.
+    /// - `format`: Format of the files.
+    pub fn with_dump(mut self, directory: impl Into<PathBuf>, format: DumpFormat) -> Self {
+        self.dump = Some((directory.into(), format));
+        self
+    }
+
+    /// Obtain the completed captures, in order of completion.
+    pub fn captures(&self) -> Vec<Capture> {
+        self.lock().captures.clone()
+    }
+
+    /// Lock the shared state.
+    fn lock(&self) -> std::sync::MutexGuard<'_, State> {
+        self.state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Determine the definite logic level of a Wire level, if it has one.
+    fn logic(&self, value: WireValue) -> Option<Logic> {
+        match Logic::from_level(value, self.low_threshold, self.high_threshold) {
+            Logic::Unknown => None,
+            logic => Some(logic),
+        }
+    }
+
+    /// Record a completed capture, dumping it if configured to.
+    fn complete(&self, state: &mut State, capture: Capture) -> Result<(), String> {
+        if let Some((directory, format)) = &self.dump {
+            let extension = match format {
+                DumpFormat::Vcd => "vcd",
+                DumpFormat::Csv => "csv",
+                DumpFormat::Svg => "svg",
+                #[cfg(feature = "png")]
+                DumpFormat::Png => "png",
+            };
+            let path = directory.join(format!(
+                "capture-{}-{}.{extension}",
+                capture.trigger, capture.trigger_time
+            ));
+            let file = File::create(&path)
+                .map(BufWriter::new)
+                .map_err(|err| format!("Cannot create {}: {err}", path.display()))?;
+            match format {
+                DumpFormat::Vcd => capture.write_vcd(file)?,
+                DumpFormat::Csv => capture.write_csv(file)?,
+                DumpFormat::Svg => capture.write_svg(file, CHART_WIDTH)?,
+                #[cfg(feature = "png")]
+                DumpFormat::Png => capture.write_png(file, CHART_WIDTH)?,
+            }
+        }
+
+        state.captures.push(capture);
+        Ok(())
+    }
+}
+
+impl Tracer for TriggeredCapture {
+    fn start(&mut self, sim: &Simulation) -> Result<(), String> {
+        let mut names = Vec::new();
+        let mut history = Vec::new();
+        for id in &self.signals {
+            let wire = sim.wire(*id)?;
+            let mut changes = RingBuffer::new(self.pre_trigger.max(1));
+            changes.push((sim.time(), wire.measure()));
+            names.push(wire.name().clone());
+            history.push(changes);
+        }
+        let armed = self
+            .triggers
+            .iter()
+            .map(|trigger| {
+                sim.wire(trigger.signal).map(|wire| Armed {
+                    level: self.logic(wire.measure()),
+                    ..Armed::default()
+                })
+            })
+            .collect::<Result<_, _>>()?;
+
+        let mut state = self.lock();
+        state.names = names;
+        state.history = history;
+        state.armed = armed;
+        state.captures.clear();
+        Ok(())
+    }
+
+    fn record(&mut self, sim: &Simulation, changes: &[Change]) -> Result<(), String> {
+        let mut state = self.lock();
+        let state = &mut *state;
+
+        // Capture the changes of the selected signals into the history and every capture in progress.
+        for change in changes {
+            for (index, _) in self
+                .signals
+                .iter()
+                .enumerate()
+                .filter(|(_, id)| **id == change.id)
+            {
+                let entry = (sim.time(), change.value);
+                state.history[index].push(entry);
+                for armed in &mut state.armed {
+                    if let Some(pending) = &mut armed.pending {
+                        pending.capture.signals[index].changes.push(entry);
+                    }
+                }
+            }
+        }
+
+        // Complete any captures which have now captured enough steps after their trigger.
+        for index in 0..state.armed.len() {
+            let completed = state.armed[index].pending.take_if(|pending| {
+                pending.remaining = pending.remaining.saturating_sub(1);
+                pending.remaining == 0
+            });
+            if let Some(pending) = completed {
+                self.complete(state, pending.capture)?;
+            }
+        }
+
+        // Fire any armed triggers whose edge occurred during this step.
+        for (index, trigger) in self.triggers.iter().enumerate() {
+            let Some(level) = changes
+                .iter()
+                .find(|c| c.id == trigger.signal)
+                .and_then(|c| self.logic(c.value))
+            else {
+                continue;
+            };
+            let previous = state.armed[index].level.replace(level);
+            if state.armed[index].fired
+                || !previous.is_some_and(|from| trigger.matches(from, level))
+            {
+                continue;
+            }
+            if let Some(condition) = &trigger.qualifier {
+                if !condition.evaluate(sim)? {
+                    continue;
+                }
+            }
+
+            let capture = Capture {
+                trigger: index,
+                trigger_time: sim.time(),
+                signals: state
+                    .names
+                    .iter()
+                    .zip(&state.history)
+                    .map(|(name, history)| CapturedSignal {
+                        name: name.clone(),
+                        changes: history.iter().copied().collect(),
+                    })
+                    .collect(),
+            };
+            state.armed[index].fired = true;
+            if self.post_trigger == 0 {
+                self.complete(state, capture)?;
+            } else {
+                state.armed[index].pending = Some(Pending {
+                    capture,
+                    remaining: self.post_trigger,
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    fn finish(&mut self, _sim: &Simulation) -> Result<(), String> {
+        let mut state = self.lock();
+        let state = &mut *state;
+        for index in 0..state.armed.len() {
+            if let Some(pending) = state.armed[index].pending.take() {
+                self.complete(state, pending.capture)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::time::SimDuration;
+    use crate::wire::{Wire, WirePull};
+
+    /// Build a change to a Wire's level.
+    fn change(id: WireId, previous: f32, value: f32) -> Change {
+        Change {
+            id,
+            previous: WireValue::new(previous),
+            value: WireValue::new(value),
+        }
+    }
+
+    /// Build a Simulation with a clock and an enable, both initially low.
+    fn setup() -> (Simulation, WireId, WireId) {
+        let mut sim = Simulation::new(SimDuration::from_ticks(10));
+        let clk = sim.add_wire(Wire::new("CLK", WirePull::Down)).unwrap();
+        let enable = sim.add_wire(Wire::new("EN", WirePull::Down)).unwrap();
+        (sim, clk, enable)
+    }
+
+    /// Step a Simulation, recording the given changes to a capture after each step.
+    fn run(sim: &mut Simulation, capture: &mut TriggeredCapture, steps: &[&[Change]]) {
+        for changes in steps {
+            sim.step().unwrap();
+            capture.record(sim, changes).unwrap();
+        }
+    }
+
+    #[test]
+    fn ring_buffer() {
+        // GIVEN a ring buffer with a capacity of two
+        let mut buffer = RingBuffer::new(2);
+        // WHEN three items are pushed
+        buffer.push(1);
+        buffer.push(2);
+        buffer.push(3);
+        // THEN only the two most recent are held
+        assert_eq!(2, buffer.len());
+        assert_eq!(vec![&2, &3], buffer.iter().collect::<Vec<_>>());
+    }
+    #[test]
+    fn trigger_edges() {
+        // GIVEN triggers on each kind of edge
+        let rising = Trigger::new(WireId::from(0), Edge::Rising);
+        let falling = Trigger::new(WireId::from(0), Edge::Falling);
+        let either = Trigger::new(WireId::from(0), Edge::Either);
+        // WHEN transitions are matched against them
+        // THEN only transitions in the right direction match
+        assert!(rising.matches(Logic::Low, Logic::High));
+        assert!(!rising.matches(Logic::High, Logic::Low));
+        assert!(falling.matches(Logic::High, Logic::Low));
+        assert!(!falling.matches(Logic::Low, Logic::High));
+        assert!(either.matches(Logic::High, Logic::Low));
+        assert!(!either.matches(Logic::Low, Logic::Low));
+    }
+    #[test]
+    fn capture_pre_and_post_trigger() {
+        // GIVEN a capture triggered by a rising clock while the enable is low, retaining two changes before the
+        // trigger and one step after it
+        let (mut sim, clk, enable) = setup();
+        let mut capture = TriggeredCapture::new(&[clk, enable])
+            .arm(Trigger::new(clk, Edge::Rising).when(Condition::Low(enable)))
+            .with_depth(2, 1);
+        capture.start(&sim).unwrap();
+        // WHEN the clock wanders several times before rising, and changes again afterwards
+        run(
+            &mut sim,
+            &mut capture,
+            &[
+                &[change(clk, 0.0, 0.1)],
+                &[change(clk, 0.1, 0.2)],
+                &[change(clk, 0.2, 0.1)],
+            ],
+        );
+        assert!(capture.captures().is_empty());
+        run(
+            &mut sim,
+            &mut capture,
+            &[
+                &[change(clk, 0.1, 1.0)],
+                &[change(clk, 1.0, 0.9)],
+                &[change(clk, 0.9, 0.8)],
+            ],
+        );
+        // THEN the capture holds the retained history of each signal, the trigger and the step which followed
+        let captures = capture.captures();
+        assert_eq!(1, captures.len());
+        assert_eq!(0, captures[0].trigger);
+        assert_eq!(40, captures[0].trigger_time);
+        let times: Vec<u64> = captures[0].signals[0].changes.iter().map(|c| c.0).collect();
+        assert_eq!(vec![30, 40, 50], times);
+        assert_eq!(
+            vec![(0, WireValue::new(0.0))],
+            captures[0].signals[1].changes
+        );
+    }
+    #[test]
+    fn capture_qualifier() {
+        // GIVEN a capture triggered by a rising clock only while enabled
+        let (mut sim, clk, enable) = setup();
+        let mut capture = TriggeredCapture::new(&[clk, enable])
+            .arm(Trigger::new(clk, Edge::Rising).when(Condition::High(enable)));
+        capture.start(&sim).unwrap();
+        // WHEN the clock rises while the enable is low
+        run(&mut sim, &mut capture, &[&[change(clk, 0.0, 1.0)]]);
+        capture.finish(&sim).unwrap();
+        // THEN the trigger does not fire
+        assert!(capture.captures().is_empty());
+    }
+    #[test]
+    fn capture_multiple_triggers() {
+        // GIVEN a capture with triggers armed on a rising clock and a rising enable
+        let (mut sim, clk, enable) = setup();
+        let mut capture = TriggeredCapture::new(&[clk])
+            .arm(Trigger::new(clk, Edge::Rising))
+            .arm(Trigger::new(enable, Edge::Rising))
+            .with_depth(4, 10);
+        capture.start(&sim).unwrap();
+        // WHEN both rise at different times, and tracing finishes before the post-trigger depth is reached
+        run(
+            &mut sim,
+            &mut capture,
+            &[&[change(clk, 0.0, 1.0)], &[change(enable, 0.0, 1.0)]],
+        );
+        assert!(capture.captures().is_empty());
+        capture.finish(&sim).unwrap();
+        // THEN each trigger produces its own capture
+        let triggers: Vec<(usize, u64)> = capture
+            .captures()
+            .iter()
+            .map(|c| (c.trigger, c.trigger_time))
+            .collect();
+        assert_eq!(vec![(0, 10), (1, 20)], triggers);
+    }
+    #[test]
+    fn capture_write() {
+        // GIVEN a capture of two signals
+        let capture = Capture {
+            trigger: 0,
+            trigger_time: 10,
+            signals: vec![
+                CapturedSignal {
+                    name: "CLK".to_string(),
+                    changes: vec![(0, WireValue::new(0.0)), (10, WireValue::new(1.0))],
+                },
+                CapturedSignal {
+                    name: "A,B".to_string(),
+                    changes: vec![(10, WireValue::new(0.5))],
+                },
+            ],
+        };
+        // WHEN it is written as CSV, VCD and SVG
+        let mut csv = Vec::new();
+        capture.write_csv(&mut csv).unwrap();
+        let mut vcd = Vec::new();
+        capture.write_vcd(&mut vcd).unwrap();
+        // THEN the CSV has a row for each change time and the VCD reads back as the logic levels
+        assert_eq!(
+            "time,CLK,\"A,B\"\n0,0,\n10,1,0.5\n",
+            String::from_utf8(csv).unwrap()
+        );
+        let waveform = vcd::read(vcd.as_slice()).unwrap();
+        assert_eq!(Some(&[(0, '0'), (10, '1')][..]), waveform.signal("CLK"));
+        assert_eq!(Some(&[(10, 'x')][..]), waveform.signal("A,B"));
+        let mut svg = Vec::new();
+        capture.write_svg(&mut svg, 400).unwrap();
+        assert!(String::from_utf8(svg).unwrap().contains(">A,B</text>"));
+    }
+    #[test]
+    fn capture_dump() {
+        // GIVEN a capture which dumps to VCD files in a temporary directory
+        let directory =
+            std::env::temp_dir().join(format!("rvfs-sim-capture-{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        let (mut sim, clk, _) = setup();
+        let mut capture = TriggeredCapture::new(&[clk])
+            .arm(Trigger::new(clk, Edge::Rising))
+            .with_depth(4, 0)
+            .with_dump(&directory, DumpFormat::Vcd);
+        capture.start(&sim).unwrap();
+        // WHEN the trigger fires
+        run(&mut sim, &mut capture, &[&[change(clk, 0.0, 1.0)]]);
+        // THEN the capture is dumped immediately
+        let text = std::fs::read_to_string(directory.join("capture-0-10.vcd")).unwrap();
+        std::fs::remove_dir_all(&directory).unwrap();
+        assert!(text.contains("$comment trigger fired at 10 $end"));
+    }
+}
@@ -0,0 +1,932 @@
+//! Reading and writing of Value Change Dump (VCD) files, as used by waveform viewers such as GTKWave.
+
+use crate::opin::OutputPinState;
+use crate::sim::Simulation;
+use crate::time::SimDuration;
+use crate::trace::{Change, Logic, Tracer, DEFAULT_HIGH_THRESHOLD, DEFAULT_LOW_THRESHOLD};
+use crate::wirevalue::WireValue;
+use crate::{Instant, OutputPinId, WireId};
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufWriter, Write};
+use std::path::Path;
+use std::time::Duration;
+
+/// Per-Wire state of a traced signal.
+#[derive(Debug, Clone)]
+struct Signal {
+    /// VCD identifier code of the logic level variable.
+    logic_code: String,
+    /// VCD identifier code of the real-valued level variable.
+    level_code: String,
+    /// Last logic value written for the signal.
+    logic: char,
+}
+
+/// State of a traced OutputPin.
+#[derive(Debug, Clone)]
+struct PinSignal {
+    /// Id of the OutputPin.
+    id: OutputPinId,
+    /// VCD identifier code of the pin's variable.
+    code: String,
+    /// Last value written for the pin.
+    value: char,
+}
+
+/// State of a traced bus.
+#[derive(Debug, Clone)]
+struct BusSignal {
+    /// Ids of the bus's Wires, from the least significant bit.
+    wires: Vec<WireId>,
+    /// VCD identifier code of the bus's vector variable.
+    code: String,
+    /// Last value written for the bus, from the most significant bit.
+    value: String,
+}
+
+/// Units of the VCD timescale, from the coarsest, with the number of simulation ticks (nanoseconds) in each.
+const TIME_UNITS: [(&str, u64); 4] = [
+    ("s", 1_000_000_000),
+    ("ms", 1_000_000),
+    ("us", 1_000),
+    ("ns", 1),
+];
+
+/// A Tracer which writes the traced Wires to a VCD file.
+///
+/// Each traced Wire is written as a single-bit logic signal, derived from its level using a pair of thresholds.  The
+/// analog level of each Wire can optionally be written alongside as a real-valued signal, and the state driven by each
+/// OutputPin can be written too, including high impedance (`z`), as can the value of each bus defined in the
+/// Simulation, as a vector.  One simulation time unit is a nanosecond, written with a timescale of `1ns` unless a
+/// coarser one is chosen.  Wires are written under their own names unless they are given others.
+///
+/// # Example
+///
+/// ```
+/// # use rvfs_sim_core::sim::Simulation;
+/// # use rvfs_sim_core::time::SimDuration;
+/// # use rvfs_sim_core::trace::vcd::VcdWriter;
+/// # use rvfs_sim_core::wire::{Wire, WirePull};
+/// let mut sim = Simulation::new(SimDuration::from_ticks(10));
+/// sim.add_wire(Wire::new("/RESET", WirePull::Up)).unwrap();
+/// sim.add_tracer(Box::new(VcdWriter::new(std::io::sink())));
+///
+/// sim.step().unwrap();
+/// sim.finish_tracers().unwrap();
+/// ```
+///
+/// A running Simulation can be followed live in GTKWave or Surfer by [opening](VcdWriter::open_live) a named pipe
+/// which the viewer reads, such as with `mkfifo trace.vcd && gtkwave -I trace.vcd`, so that the output is flushed
+/// periodically.
+pub struct VcdWriter<W: Write + Send> {
+    /// Destination of the VCD output.
+    out: W,
+    /// Ids of the Wires to trace, or None to trace every Wire.
+    selection: Option<Vec<WireId>>,
+    /// Whether to write the real-valued level of each Wire as well as its logic level.
+    levels: bool,
+    /// Wire level at or below which a signal is considered logic low.
+    low_threshold: f32,
+    /// Wire level at or above which a signal is considered logic high.
+    high_threshold: f32,
+    /// Names to write in place of the names of Wires, keyed by Wire Id.
+    names: HashMap<WireId, String>,
+    /// Number of simulation ticks in each unit of the VCD timescale.
+    timescale: u64,
+    /// Whether to trace the OutputPins of every Element.
+    pins: bool,
+    /// State of each traced signal, keyed by Wire Id.
+    signals: HashMap<WireId, Signal>,
+    /// State of each traced OutputPin.
+    pin_signals: Vec<PinSignal>,
+    /// Whether to trace the buses defined in the Simulation.
+    buses: bool,
+    /// State of each traced bus.
+    bus_signals: Vec<BusSignal>,
+    /// Minimum wall-clock time between flushes of the output after a step, or None to flush only when finished.
+    flush_interval: Option<Duration>,
+    /// Wall-clock time at which the output was last flushed.
+    last_flush: Option<Instant>,
+    /// Simulation time of the last time marker written.
+    marked: Option<u64>,
+}
+
+impl<W: Write + Send> VcdWriter<W> {
+    /// Create a new VcdWriter which traces every Wire.
+    ///
+    /// # Parameters
+    ///
+    /// - `out`: Destination of the VCD output.
+    pub fn new(out: W) -> Self {
+        Self {
+            out,
+            selection: None,
+            levels: false,
+            low_threshold: DEFAULT_LOW_THRESHOLD,
+            high_threshold: DEFAULT_HIGH_THRESHOLD,
+            names: HashMap::new(),
+            timescale: 1,
+            pins: false,
+            signals: HashMap::new(),
+            pin_signals: Vec::new(),
+            buses: false,
+            bus_signals: Vec::new(),
+            flush_interval: None,
+            last_flush: None,
+            marked: None,
+        }
+    }
+
+    /// Restrict tracing to the selected Wires.
+    ///
+    /// # Parameters
+    ///
+    /// - `ids`: Ids of the Wires to trace.
+    pub fn select(mut self, ids: &[WireId]) -> Self {
+        self.selection = Some(ids.to_vec());
+        self
+    }
+
+    /// Enable or disable writing the real-valued level of each Wire.
+    ///
+    /// # Parameters
+    ///
+    /// - `levels`: Whether to write the Wire levels.
+    pub fn with_levels(mut self, levels: bool) -> Self {
+        self.levels = levels;
+        self
+    }
+
+    /// Change the thresholds used to derive logic values from Wire levels.
+    ///
+    /// Levels between the two thresholds are written as unknown (`x`).
+    ///
+    /// # Parameters
+    ///
+    /// - `low`: Level at or below which a signal is considered logic low.
+    /// - `high`: Level at or above which a signal is considered logic high.
+    pub fn with_thresholds(mut self, low: f32, high: f32) -> Self {
+        self.low_threshold = low;
+        self.high_threshold = high;
+        self
+    }
+
+    /// Write a Wire under a different name, such as a net name from a schematic.
+    ///
+    /// Whitespace in the name is replaced, as it is in Wire names.
+    ///
+    /// # Parameters
+    ///
+    /// - `id`: Id of the Wire.
+    /// - `name`: Name to write for the Wire.
+    pub fn with_name(mut self, id: WireId, name: &str) -> Self {
+        self.names.insert(id, name.to_string());
+        self
+    }
+
+    /// Change the timescale written, so that times are written in coarser units than nanoseconds.
+    ///
+    /// Simulation times are truncated to whole units, so changes within a unit are written at its start.
+    ///
+    /// # Parameters
+    ///
+    /// - `unit`: Duration of a unit, which must be 1, 10 or 100 nanoseconds, microseconds, milliseconds or seconds.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use rvfs_sim_core::time::SimDuration;
+    /// # use rvfs_sim_core::trace::vcd::VcdWriter;
+    /// assert!(VcdWriter::new(std::io::sink())
+    ///     .with_timescale(SimDuration::from_ticks(10_000))
+    ///     .is_ok());
+    /// assert!(VcdWriter::new(std::io::sink())
+    ///     .with_timescale(SimDuration::from_ticks(20))
+    ///     .is_err());
+    /// ```
+    pub fn with_timescale(mut self, unit: SimDuration) -> Result<Self, String> {
+        timescale(unit.ticks())?;
+        self.timescale = unit.ticks();
+        Ok(self)
+    }
+
+    /// Enable or disable writing the state driven by each OutputPin of every Element.
+    ///
+    /// Each pin is written as `element.pin`, in a scope of its own.
+    ///
+    /// # Parameters
+    ///
+    /// - `pins`: Whether to write the OutputPins.
+    pub fn with_output_pins(mut self, pins: bool) -> Self {
+        self.pins = pins;
+        self
+    }
+
+    /// Enable or disable writing the value of each bus defined in the Simulation.
+    ///
+    /// Each bus is written as a vector, most significant bit first, in a scope of its own.
+    ///
+    /// # Parameters
+    ///
+    /// - `buses`: Whether to write the buses.
+    pub fn with_buses(mut self, buses: bool) -> Self {
+        self.buses = buses;
+        self
+    }
+
+    /// Flush the output periodically while tracing, so that a viewer can follow the Simulation live.
+    ///
+    /// The output is flushed after a step once the interval has elapsed since it was last flushed, along with a time
+    /// marker for the step so that the viewer's time advances even when no signal has changed.
+    ///
+    /// # Parameters
+    ///
+    /// - `interval`: Minimum wall-clock time between flushes, which may be zero to flush after every step.
+    pub fn with_flush_interval(mut self, interval: Duration) -> Self {
+        self.flush_interval = Some(interval);
+        self
+    }
+
+    /// Consume the writer and obtain the underlying output.
+    pub fn into_inner(self) -> W {
+        self.out
+    }
+
+    /// Flush the output if the flush interval has elapsed, marking the present time.
+    ///
+    /// # Parameters
+    ///
+    /// - `time`: The present simulation time.
+    fn flush_due(&mut self, time: u64) -> Result<(), String> {
+        let Some(interval) = self.flush_interval else {
+            return Ok(());
+        };
+        if self.last_flush.is_some_and(|at| at.elapsed() < interval) {
+            return Ok(());
+        }
+
+        let time = time / self.timescale;
+        if self.marked != Some(time) {
+            writeln!(self.out, "#{time}").map_err(|err| err.to_string())?;
+            self.marked = Some(time);
+        }
+        self.out.flush().map_err(|err| err.to_string())?;
+        self.last_flush = Some(Instant::now());
+        Ok(())
+    }
+
+    /// Convert a Wire level into a VCD logic value.
+    fn logic(&self, value: WireValue) -> char {
+        Logic::from_level(value, self.low_threshold, self.high_threshold).symbol()
+    }
+
+    /// Write a VCD value change for a single signal.
+    ///
+    /// The time marker is written first if it has not already been written for this time.
+    fn write_change(
+        &mut self,
+        id: WireId,
+        value: WireValue,
+        time: &mut Option<u64>,
+    ) -> Result<(), String> {
+        let logic = self.logic(value);
+        let Some(signal) = self.signals.get_mut(&id) else {
+            return Ok(());
+        };
+
+        let mut text = String::new();
+        if signal.logic != logic {
+            signal.logic = logic;
+            text += &format!("{logic}{}\n", signal.logic_code);
+        }
+        if self.levels {
+            text += &format!("r{} {}\n", f32::from(value), signal.level_code);
+        }
+
+        self.write_text(&text, time)
+    }
+
+    /// Write a VCD value change for each OutputPin whose state has changed.
+    ///
+    /// # Parameters
+    ///
+    /// - `sim`: The Simulation.
+    /// - `time`: Simulation time of the changes, or None if its marker has already been written.
+    fn write_pins(&mut self, sim: &Simulation, time: &mut Option<u64>) -> Result<(), String> {
+        let mut text = String::new();
+        for pin in &mut self.pin_signals {
+            let value = match sim.output_pin_state(pin.id)? {
+                OutputPinState::Low => '0',
+                OutputPinState::High => '1',
+                OutputPinState::HighImpedance => 'z',
+                OutputPinState::Unknown => 'x',
+            };
+            if pin.value != value {
+                pin.value = value;
+                text += &format!("{value}{}\n", pin.code);
+            }
+        }
+
+        self.write_text(&text, time)
+    }
+
+    /// Write a VCD value change for each bus whose value has changed.
+    ///
+    /// # Parameters
+    ///
+    /// - `sim`: The Simulation.
+    /// - `time`: Simulation time of the changes, or None if its marker has already been written.
+    fn write_buses(&mut self, sim: &Simulation, time: &mut Option<u64>) -> Result<(), String> {
+        let mut text = String::new();
+        for bus in &mut self.bus_signals {
+            let mut value = String::new();
+            for id in bus.wires.iter().rev() {
+                let level = sim.wire(*id)?.measure();
+                value.push(
+                    Logic::from_level(level, self.low_threshold, self.high_threshold).symbol(),
+                );
+            }
+            if bus.value != value {
+                text += &format!("b{value} {}\n", bus.code);
+                bus.value = value;
+            }
+        }
+
+        self.write_text(&text, time)
+    }
+
+    /// Write value changes, preceded by the time marker if it has not already been written for this time.
+    ///
+    /// # Parameters
+    ///
+    /// - `text`: The value changes, which may be empty.
+    /// - `time`: Simulation time of the changes, or None if its marker has already been written.
+    fn write_text(&mut self, text: &str, time: &mut Option<u64>) -> Result<(), String> {
+        if !text.is_empty() {
+            if let Some(t) = time.take() {
+                let t = t / self.timescale;
+                if self.marked != Some(t) {
+                    writeln!(self.out, "#{t}").map_err(|err| err.to_string())?;
+                    self.marked = Some(t);
+                }
+            }
+            self.out
+                .write_all(text.as_bytes())
+                .map_err(|err| err.to_string())?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<W: Write + Send> Tracer for VcdWriter<W> {
+    fn start(&mut self, sim: &Simulation) -> Result<(), String> {
+        let ids: Vec<WireId> = match &self.selection {
+            Some(ids) => ids.clone(),
+            None => sim.wires().collect(),
+        };
+
+        let mut header = String::new();
+        header += "$version rvfs-sim $end\n";
+        header += &format!("$timescale {} $end\n", timescale(self.timescale)?);
+
+        let mut logic_vars = String::new();
+        let mut level_vars = String::new();
+        for (index, id) in ids.iter().enumerate() {
+            let wire = sim.wire(*id)?;
+            let name = reference(self.names.get(id).unwrap_or(wire.name()));
+            let signal = Signal {
+                logic_code: identifier(2 * index),
+                level_code: identifier(2 * index + 1),
+                logic: ' ',
+            };
+
+            logic_vars += &format!("$var wire 1 {} {name} $end\n", signal.logic_code);
+            level_vars += &format!("$var real 64 {} {name} $end\n", signal.level_code);
+            self.signals.insert(*id, signal);
+        }
+
+        header += "$scope module logic $end\n";
+        header += &logic_vars;
+        header += "$upscope $end\n";
+        if self.levels {
+            header += "$scope module levels $end\n";
+            header += &level_vars;
+            header += "$upscope $end\n";
+        }
+        let mut index = 2 * ids.len();
+        if self.pins {
+            header += "$scope module pins $end\n";
+            for element in sim.elements() {
+                let element_name = sim.element(element)?.name().to_string();
+                for (id, pin) in sim.output_pins(element)? {
+                    let code = identifier(index);
+                    index += 1;
+                    header += &format!(
+                        "$var wire 1 {code} {} $end\n",
+                        reference(&format!("{element_name}.{pin}"))
+                    );
+                    self.pin_signals.push(PinSignal {
+                        id,
+                        code,
+                        value: ' ',
+                    });
+                }
+            }
+            header += "$upscope $end\n";
+        }
+        if self.buses {
+            header += "$scope module buses $end\n";
+            for (name, wires) in sim.buses() {
+                let code = identifier(index);
+                index += 1;
+                header += &format!(
+                    "$var wire {} {code} {} [{}:0] $end\n",
+                    wires.len(),
+                    reference(name),
+                    wires.len() - 1
+                );
+                self.bus_signals.push(BusSignal {
+                    wires: wires.to_vec(),
+                    code,
+                    value: String::new(),
+                });
+            }
+            header += "$upscope $end\n";
+        }
+        header += "$enddefinitions $end\n";
+        self.out
+            .write_all(header.as_bytes())
+            .map_err(|err| err.to_string())?;
+
+        // Dump the initial values of all signals.
+        let mut time = Some(sim.time());
+        for id in ids {
+            let value = sim.wire(id)?.measure();
+            self.write_change(id, value, &mut time)?;
+        }
+        self.write_pins(sim, &mut time)?;
+        self.write_buses(sim, &mut time)?;
+
+        self.flush_due(sim.time())
+    }
+
+    fn record(&mut self, sim: &Simulation, changes: &[Change]) -> Result<(), String> {
+        let mut time = Some(sim.time());
+        for change in changes {
+            self.write_change(change.id, change.value, &mut time)?;
+        }
+        self.write_pins(sim, &mut time)?;
+        self.write_buses(sim, &mut time)?;
+
+        self.flush_due(sim.time())
+    }
+
+    fn finish(&mut self, _sim: &Simulation) -> Result<(), String> {
+        self.out.flush().map_err(|err| err.to_string())
+    }
+}
+
+impl VcdWriter<BufWriter<File>> {
+    /// Create a new VcdWriter which traces every Wire to a file or named pipe, flushing it periodically so that a
+    /// viewer can follow the Simulation live.
+    ///
+    /// Opening a named pipe waits until the viewer opens it for reading.  An existing regular file is overwritten.
+    ///
+    /// # Parameters
+    ///
+    /// - `path`: Path of the file or named pipe.
+    /// - `interval`: Minimum wall-clock time between flushes, which may be zero to flush after every step.
+    pub fn open_live(path: &Path, interval: Duration) -> Result<Self, String> {
+        let file = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(path)
+            .map_err(|err| format!("Cannot open \"{}\": {err}", path.display()))?;
+        Ok(Self::new(BufWriter::new(file)).with_flush_interval(interval))
+    }
+}
+
+/// The single-bit signals read from a VCD file.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Waveform {
+    /// Value changes of each signal as pairs of time and value (`0`, `1`, `x` or `z`), keyed by reference name.
+    signals: BTreeMap<String, Vec<(u64, char)>>,
+}
+
+impl Waveform {
+    /// Obtain the names of all signals in the waveform.
+    pub fn names(&self) -> impl Iterator<Item = &String> {
+        self.signals.keys()
+    }
+
+    /// Obtain the value changes of a signal as pairs of time and value, in time order.
+    ///
+    /// # Parameters
+    ///
+    /// - `name`: Reference name of the signal.
+    pub fn signal(&self, name: &str) -> Option<&[(u64, char)]> {
+        self.signals.get(name).map(|changes| changes.as_slice())
+    }
+}
+
+/// Read the single-bit signals from a VCD file.
+///
+/// Signals are identified by their reference names, without any enclosing scope.  Vector and real-valued variables
+/// are ignored.  Times are read as-is, without applying the timescale.
+///
+/// # Parameters
+///
+/// - `input`: Source of the VCD text.
+///
+/// # Example
+///
+/// ```
+/// # use rvfs_sim_core::trace::vcd;
+/// let text = "$var wire 1 ! CLK $end $enddefinitions $end #0 0! #5 1!";
+/// let waveform = vcd::read(text.as_bytes()).unwrap();
+///
+/// assert_eq!(Some(&[(0, '0'), (5, '1')][..]), waveform.signal("CLK"));
+/// ```
+pub fn read(input: impl BufRead) -> Result<Waveform, String> {
+    let mut names: HashMap<String, Vec<String>> = HashMap::new();
+    let mut waveform = Waveform::default();
+    let mut time = 0u64;
+    let mut definitions = true;
+    let mut tokens = Vec::new();
+
+    for line in input.lines() {
+        let line = line.map_err(|err| err.to_string())?;
+        tokens.extend(line.split_whitespace().map(str::to_string));
+    }
+
+    let mut tokens = tokens.into_iter();
+    while let Some(token) = tokens.next() {
+        if definitions {
+            match token.as_str() {
+                "$var" => {
+                    let fields: Vec<String> = tokens.by_ref().take_while(|t| t != "$end").collect();
+                    if fields.len() < 4 {
+                        return Err(format!("Malformed VCD variable: {}", fields.join(" ")));
+                    }
+                    if fields[1] == "1" && fields[0] != "real" {
+                        names
+                            .entry(fields[2].clone())
+                            .or_default()
+                            .push(fields[3].clone());
+                        waveform.signals.entry(fields[3].clone()).or_default();
+                    }
+                }
+                "$enddefinitions" => {
+                    definitions = false;
+                    tokens.by_ref().find(|t| t == "$end");
+                }
+                _ if token.starts_with('$') => {
+                    tokens.by_ref().find(|t| t == "$end");
+                }
+                _ => return Err(format!("Unexpected VCD token in definitions: {token}")),
+            }
+            continue;
+        }
+
+        let mut chars = token.chars();
+        match chars.next() {
+            Some('#') => {
+                time = chars
+                    .as_str()
+                    .parse()
+                    .map_err(|_| format!("Malformed VCD time: {token}"))?;
+            }
+            Some(value @ ('0' | '1' | 'x' | 'X' | 'z' | 'Z')) => {
+                for name in names.get(chars.as_str()).into_iter().flatten() {
+                    if let Some(changes) = waveform.signals.get_mut(name) {
+                        changes.push((time, value.to_ascii_lowercase()));
+                    }
+                }
+            }
+            Some('b' | 'B' | 'r' | 'R') => {
+                // Vector and real values are followed by their identifier code, and are not retained.
+                tokens.next();
+            }
+            Some('$') => {
+                // Keywords such as $dumpvars simply wrap value changes.
+            }
+            _ => return Err(format!("Unexpected VCD token: {token}")),
+        }
+    }
+
+    Ok(waveform)
+}
+
+/// Generate the VCD identifier code for a variable index.
+///
+/// Codes are formed from the printable ASCII characters `!` through `~`.
+pub(crate) fn identifier(mut index: usize) -> String {
+    const FIRST: u8 = b'!';
+    const COUNT: usize = (b'~' - b'!' + 1) as usize;
+
+    let mut code = String::new();
+    loop {
+        code.push((FIRST + (index % COUNT) as u8) as char);
+        index /= COUNT;
+        if index == 0 {
+            break;
+        }
+        index -= 1;
+    }
+    code
+}
+
+/// Describe a VCD timescale, such as `100us`, from the number of simulation ticks in each of its units.
+///
+/// # Parameters
+///
+/// - `ticks`: Number of ticks in a unit, which must be 1, 10 or 100 of one of the VCD time units.
+fn timescale(ticks: u64) -> Result<String, String> {
+    TIME_UNITS
+        .iter()
+        .find_map(|(unit, size)| {
+            [1, 10, 100]
+                .into_iter()
+                .find(|multiple| multiple * size == ticks)
+                .map(|multiple| format!("{multiple}{unit}"))
+        })
+        .ok_or(format!("Invalid VCD timescale of {ticks} ticks"))
+}
+
+/// Convert a Wire name into a VCD reference, which may not contain whitespace.
+pub(crate) fn reference(name: &str) -> String {
+    name.chars()
+        .map(|c| if c.is_whitespace() { '_' } else { c })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::element::clocks::ClockGenerator;
+    use crate::element::gates::{Gate, GateKind};
+    use crate::trace::testing::{self, falling_wire_sim};
+    use crate::wire::{Wire, WirePull};
+    use std::sync::{Arc, Mutex};
+
+    /// A buffer which can be read back while a Tracer attached to a Simulation owns it.
+    #[derive(Clone, Default)]
+    struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for SharedBuffer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    /// Trace a number of steps of a Simulation directly into a buffer.
+    fn trace(sim: &mut Simulation, vcd: VcdWriter<Vec<u8>>, steps: usize) -> String {
+        String::from_utf8(testing::trace(sim, vcd, steps).into_inner()).unwrap()
+    }
+
+    #[test]
+    fn vcd_identifier() {
+        // GIVEN some variable indices
+        // WHEN they are converted into identifier codes
+        // THEN the codes are unique printable strings
+        assert_eq!("!", identifier(0));
+        assert_eq!("~", identifier(93));
+        assert_eq!("!!", identifier(94));
+        assert_eq!("\"!", identifier(95));
+        assert_eq!("~!", identifier(187));
+        assert_eq!("!\"", identifier(188));
+    }
+    #[test]
+    fn vcd_reference() {
+        // GIVEN a Wire name containing whitespace
+        // WHEN it is converted to a VCD reference
+        // THEN the whitespace is replaced
+        assert_eq!("data_bus/0", reference("data bus/0"));
+    }
+    #[test]
+    fn vcd_header_and_initial_values() {
+        // GIVEN a Simulation with a pulled-up wire
+        let (mut sim, _) = falling_wire_sim(10.0);
+        // WHEN it is traced without stepping
+        let text = trace(&mut sim, VcdWriter::new(Vec::new()), 0);
+        // THEN the header declares the wire and the initial value is dumped
+        assert!(text.contains("$timescale 1ns $end\n"));
+        assert!(text.contains("$var wire 1 ! /RESET $end\n"));
+        assert!(!text.contains("$var real"));
+        assert!(text.ends_with("$enddefinitions $end\n#0\n1!\n"));
+    }
+    #[test]
+    fn vcd_logic_transitions() {
+        // GIVEN a Simulation with a wire falling from high to low
+        let (mut sim, _) = falling_wire_sim(10.0);
+        // WHEN it is traced over several steps
+        let text = trace(&mut sim, VcdWriter::new(Vec::new()), 4);
+        // THEN only the logic transitions are written, at the time they occur
+        assert!(text.ends_with("#0\n1!\n#10\nx!\n#20\n0!\n"));
+    }
+    #[test]
+    fn vcd_live() {
+        // GIVEN a Simulation with a wire falling from high to low, traced live to a file flushed after every step
+        let (mut sim, _) = falling_wire_sim(10.0);
+        let path = std::env::temp_dir().join(format!("rvfs-sim-live-{}.vcd", std::process::id()));
+        let mut vcd = VcdWriter::open_live(&path, Duration::ZERO).unwrap();
+        // WHEN it is stepped, without finishing tracing
+        vcd.start(&sim).unwrap();
+        for _ in 0..4 {
+            sim.step().unwrap();
+            vcd.record(&sim, sim.changes()).unwrap();
+        }
+        let text = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        // THEN every step has already been written, with a time marker even when nothing changed
+        assert!(text.ends_with("#0\n1!\n#10\nx!\n#20\n0!\n#30\n#40\n"));
+        assert_eq!(
+            Err(format!(
+                "Cannot open \"{}\": No such file or directory (os error 2)",
+                path.join("trace.vcd").display()
+            )),
+            VcdWriter::open_live(&path.join("trace.vcd"), Duration::ZERO).map(|_| ())
+        );
+    }
+    #[test]
+    fn vcd_levels() {
+        // GIVEN a Simulation with a falling wire
+        let (mut sim, _) = falling_wire_sim(10.0);
+        // WHEN it is traced with levels enabled
+        let text = trace(&mut sim, VcdWriter::new(Vec::new()).with_levels(true), 1);
+        // THEN the real-valued levels are declared and written
+        assert!(text.contains("$scope module levels $end\n$var real 64 \" /RESET $end\n"));
+        assert!(text.contains("#0\n1!\nr1 \"\n"));
+        assert!(text.contains("#10\nx!\nr0.36787945 \"\n"));
+    }
+    #[test]
+    fn vcd_selection() {
+        // GIVEN a Simulation with two wires
+        let (mut sim, _) = falling_wire_sim(10.0);
+        let other = sim.add_wire(Wire::new("CLK", WirePull::Down)).unwrap();
+        // WHEN only the second wire is traced
+        let text = trace(&mut sim, VcdWriter::new(Vec::new()).select(&[other]), 2);
+        // THEN only that wire appears in the output
+        assert!(text.contains("$var wire 1 ! CLK $end\n"));
+        assert!(!text.contains("/RESET"));
+        assert!(text.ends_with("#0\n0!\n"));
+    }
+    #[test]
+    fn vcd_read() {
+        // GIVEN VCD text with scalar, vector and real variables, and a shared identifier code
+        let text = "$date today $end\n$timescale 1ns $end\n$scope module top $end\n\
+                    $var wire 1 ! CLK $end\n$var wire 8 \" DATA [7:0] $end\n$var real 64 # LEVEL $end\n\
+                    $var wire 1 ! CLK_ALIAS $end\n$upscope $end\n$enddefinitions $end\n\
+                    #0\n$dumpvars\n0!\nb00000000 \"\nr0.5 #\n$end\n#10\n1!\n#20\nZ!\n";
+        // WHEN it is read
+        let waveform = read(text.as_bytes()).unwrap();
+        // THEN only the single-bit signals are retained, with their value changes
+        let names: Vec<&String> = waveform.names().collect();
+        assert_eq!(vec!["CLK", "CLK_ALIAS"], names);
+        assert_eq!(
+            Some(&[(0, '0'), (10, '1'), (20, 'z')][..]),
+            waveform.signal("CLK")
+        );
+        assert_eq!(waveform.signal("CLK"), waveform.signal("CLK_ALIAS"));
+        assert_eq!(None, waveform.signal("DATA"));
+    }
+    #[test]
+    fn vcd_read_malformed() {
+        // GIVEN VCD text with a malformed time
+        let text = "$enddefinitions $end #abc";
+        // WHEN it is read
+        let result = read(text.as_bytes());
+        // THEN reading fails
+        assert!(result.is_err());
+    }
+    #[test]
+    fn vcd_round_trip() {
+        // GIVEN a VCD trace of a falling wire
+        let (mut sim, _) = falling_wire_sim(10.0);
+        let text = trace(&mut sim, VcdWriter::new(Vec::new()).with_levels(true), 4);
+        // WHEN it is read back
+        let waveform = read(text.as_bytes()).unwrap();
+        // THEN the logic transitions are recovered
+        assert_eq!(
+            Some(&[(0, '1'), (10, 'x'), (20, '0')][..]),
+            waveform.signal("/RESET")
+        );
+    }
+    #[test]
+    fn vcd_names_and_timescale() {
+        // GIVEN a Simulation with a falling wire, traced under another name with a timescale of 10ns
+        let (mut sim, id) = falling_wire_sim(10.0);
+        let vcd = VcdWriter::new(Vec::new())
+            .with_name(id, "nRESET in")
+            .with_timescale(SimDuration::from_ticks(10))
+            .unwrap();
+        // WHEN it is traced over several steps
+        let text = trace(&mut sim, vcd, 4);
+        // THEN the wire is declared under its new name, and times are written in the timescale's units
+        assert!(text.contains("$timescale 10ns $end\n"));
+        assert!(text.contains("$var wire 1 ! nRESET_in $end\n"));
+        assert!(text.ends_with("#0\n1!\n#1\nx!\n#2\n0!\n"));
+        assert_eq!(Ok("1ns".to_string()), timescale(1));
+        assert_eq!(Ok("100us".to_string()), timescale(100_000));
+        assert_eq!(Ok("1s".to_string()), timescale(1_000_000_000));
+        assert_eq!(
+            Err("Invalid VCD timescale of 0 ticks".to_string()),
+            timescale(0)
+        );
+        assert_eq!(
+            Err("Invalid VCD timescale of 50 ticks".to_string()),
+            timescale(50)
+        );
+    }
+    #[test]
+    fn vcd_output_pins() {
+        // GIVEN a Simulation of a clock driving an inverter, traced with its OutputPins
+        let mut sim = Simulation::new(SimDuration::from_ticks(10));
+        let clk = sim.add_wire(Wire::new("CLK", WirePull::Down)).unwrap();
+        let x1 = sim
+            .add_element(Box::new(
+                ClockGenerator::new("X1", 40.0, 0.5, 0.0, 0, 0.0).unwrap(),
+            ))
+            .unwrap();
+        let u1 = sim
+            .add_element(Box::new(Gate::new("U1", GateKind::Not, 1, 0).unwrap()))
+            .unwrap();
+        sim.connect_output(sim.output_pin(x1, "CLK").unwrap(), clk)
+            .unwrap();
+        sim.connect_input(clk, sim.input_pin(u1, "I0").unwrap())
+            .unwrap();
+        let vcd = VcdWriter::new(Vec::new()).with_output_pins(true);
+        // WHEN it is traced over several steps
+        let text = trace(&mut sim, vcd, 4);
+        // THEN every OutputPin is declared, even the unconnected one, and its states are written from high impedance
+        assert!(text.contains(
+            "$scope module pins $end\n$var wire 1 # X1.CLK $end\n$var wire 1 $ U1.Y $end\n"
+        ));
+        let waveform = read(text.as_bytes()).unwrap();
+        assert_eq!(
+            Some(&[(0, '0'), (10, '1'), (30, '0')][..]),
+            waveform.signal("X1.CLK")
+        );
+        assert_eq!(
+            Some(&[(0, 'z'), (10, '1'), (20, '0'), (40, '1')][..]),
+            waveform.signal("U1.Y")
+        );
+    }
+    #[test]
+    fn vcd_buses() {
+        // GIVEN a Simulation with a two-wire bus driven to 2, traced with its buses
+        let mut sim = Simulation::new(SimDuration::from_ticks(10));
+        let d0 = sim.add_wire(Wire::new("D0", WirePull::Down)).unwrap();
+        let d1 = sim.add_wire(Wire::new("D1", WirePull::Down)).unwrap();
+        sim.define_bus("DATA", &[d0, d1]).unwrap();
+        sim.drive_bus("DATA", 0b10).unwrap();
+        let vcd = VcdWriter::new(Vec::new()).with_buses(true);
+        // WHEN it is traced over several steps
+        let text = trace(&mut sim, vcd, 2);
+        // THEN the bus is declared as a vector and its value is written whenever it changes, most significant bit first
+        assert!(text.contains("$scope module buses $end\n$var wire 2 % DATA [1:0] $end\n"));
+        assert!(
+            text.contains("#0\n0!\n0#\nb00 %\n#10\n1#\nb10 %\n"),
+            "{text}"
+        );
+    }
+    #[test]
+    fn vcd_step_back() {
+        // GIVEN a Simulation of a clock traced by an attached writer, which has been run for several periods
+        let mut sim = Simulation::new(SimDuration::from_ticks(10));
+        let clock = sim
+            .add_element(Box::new(
+                ClockGenerator::new("X1", 40.0, 0.5, 0.0, 0, 0.0).unwrap(),
+            ))
+            .unwrap();
+        let clk = sim.add_wire(Wire::new("CLK", WirePull::Down)).unwrap();
+        sim.connect_output(sim.output_pin(clock, "CLK").unwrap(), clk)
+            .unwrap();
+        sim.set_checkpoints(2, 8);
+        let buffer = SharedBuffer::default();
+        sim.add_tracer(Box::new(VcdWriter::new(buffer.clone())));
+        sim.run_for(10).unwrap();
+        // WHEN it is stepped back, its clock is forced low, and it is run on past where it was
+        sim.step_back(4).unwrap();
+        sim.force_wire(clk, Some(WirePull::Down)).unwrap();
+        sim.run_for(6).unwrap();
+        sim.finish_tracers().unwrap();
+        // THEN the times written only ever increase, and the force is written once the run passes its old end
+        let text = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+        let times: Vec<u64> = text
+            .lines()
+            .filter_map(|line| line.strip_prefix('#')?.parse().ok())
+            .collect();
+        assert!(times.windows(2).all(|pair| pair[0] < pair[1]), "{text}");
+        assert_eq!(Some(&110), times.last());
+        assert!(text.ends_with("#110\n0!\n"), "{text}");
+    }
+}
@@ -0,0 +1,294 @@
+//! Export of digital Wire levels as [WaveDrom](https://wavedrom.com) timing diagrams.
+
+use crate::json;
+use crate::sim::Simulation;
+use crate::trace::{Change, Logic, Tracer, DEFAULT_HIGH_THRESHOLD, DEFAULT_LOW_THRESHOLD};
+use crate::WireId;
+use std::io::Write;
+
+/// The logic levels of a single signal over time.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Lane {
+    /// Name of the signal.
+    pub name: String,
+    /// Logic levels of the signal as pairs of time and level, in time order.
+    pub points: Vec<(u64, Logic)>,
+}
+
+impl Lane {
+    /// Obtain the WaveDrom `wave` string of the signal, with one character for each period of a window of simulation
+    /// time.
+    ///
+    /// Each character gives the logic level at the start of its period: `0`, `1` or `x`, or `.` where the level is
+    /// unchanged from the previous period.  Periods before the first point are `x`.
+    ///
+    /// # Parameters
+    ///
+    /// - `start`: Simulation time at the start of the first period.
+    /// - `stop`: Simulation time at or before which the last period starts.
+    /// - `period`: Simulation time spanned by each character.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use rvfs_sim_core::trace::Logic;
+    /// # use rvfs_sim_core::trace::wavedrom::Lane;
+    /// let clk = Lane {
+    ///     name: "CLK".to_string(),
+    ///     points: vec![(0, Logic::Low), (20, Logic::High), (40, Logic::Low)],
+    /// };
+    ///
+    /// assert_eq!("0.1.0", clk.wave(0, 40, 10));
+    /// ```
+    pub fn wave(&self, start: u64, stop: u64, period: u64) -> String {
+        let period = period.max(1);
+        let mut wave = String::new();
+        let mut previous = None;
+        let mut points = self.points.iter().peekable();
+        let mut logic = None;
+        for time in (start..=stop).step_by(period as usize) {
+            while let Some((_, level)) = points.next_if(|(at, _)| *at <= time) {
+                logic = Some(*level);
+            }
+            let symbol = logic.map_or('x', Logic::symbol);
+            wave.push(if previous == Some(symbol) {
+                '.'
+            } else {
+                symbol
+            });
+            previous = Some(symbol);
+        }
+        wave
+    }
+}
+
+/// Write a WaveDrom `signal` description of several signals over a window of simulation time.
+///
+/// # Parameters
+///
+/// - `out`: Destination of the JSON output.
+/// - `lanes`: The signals to describe, from top to bottom.
+/// - `start`: Simulation time at the start of the diagram.
+/// - `stop`: Simulation time at or before which the last period of the diagram starts.
+/// - `period`: Simulation time spanned by each character of the waves.
+///
+/// # Example
+///
+/// ```
+/// # use rvfs_sim_core::trace::Logic;
+/// # use rvfs_sim_core::trace::wavedrom::{self, Lane};
+/// let clk = Lane {
+///     name: "CLK".to_string(),
+///     points: vec![(0, Logic::Low), (10, Logic::High), (20, Logic::Low)],
+/// };
+/// let mut json = Vec::new();
+/// wavedrom::write_json(&mut json, &[clk], 0, 20, 10).unwrap();
+///
+/// assert_eq!(
+///     "{\"signal\": [\n  {\"name\": \"CLK\", \"wave\": \"010\"}\n]}\n",
+///     String::from_utf8(json).unwrap()
+/// );
+/// ```
+pub fn write_json(
+    mut out: impl Write,
+    lanes: &[Lane],
+    start: u64,
+    stop: u64,
+    period: u64,
+) -> Result<(), String> {
+    let signals: Vec<String> = lanes
+        .iter()
+        .map(|lane| {
+            format!(
+                "  {{\"name\": {}, \"wave\": {}}}",
+                json::string(&lane.name),
+                json::string(&lane.wave(start, stop, period))
+            )
+        })
+        .collect();
+    writeln!(out, "{{\"signal\": [\n{}\n]}}", signals.join(",\n")).map_err(|err| err.to_string())
+}
+
+/// A Tracer which collects the logic levels of the traced Wires and writes them as a WaveDrom timing diagram when
+/// tracing finishes.
+///
+/// # Example
+///
+/// ```
+/// # use rvfs_sim_core::sim::Simulation;
+/// # use rvfs_sim_core::time::SimDuration;
+/// # use rvfs_sim_core::trace::wavedrom::WaveDromWriter;
+/// # use rvfs_sim_core::wire::{Wire, WirePull};
+/// let mut sim = Simulation::new(SimDuration::from_ticks(10));
+/// sim.add_wire(Wire::new("/RESET", WirePull::Up)).unwrap();
+/// sim.add_tracer(Box::new(WaveDromWriter::new(std::io::sink(), 10).with_window(0, 100)));
+///
+/// sim.step().unwrap();
+/// sim.finish_tracers().unwrap();
+/// ```
+pub struct WaveDromWriter<W: Write + Send> {
+    /// Destination of the JSON output.
+    out: W,
+    /// Ids of the Wires to trace, or None to trace every Wire.
+    selection: Option<Vec<WireId>>,
+    /// Window of simulation time to describe, or None to describe the whole run.
+    window: Option<(u64, u64)>,
+    /// Simulation time spanned by each character of the waves.
+    period: u64,
+    /// Ids of the Wires being traced, in the order of the lanes.
+    ids: Vec<WireId>,
+    /// Logic levels collected for each traced Wire, at each change.
+    lanes: Vec<Lane>,
+    /// Simulation times at which tracing started and most recently recorded.
+    span: (u64, u64),
+}
+
+impl<W: Write + Send> WaveDromWriter<W> {
+    /// Create a new WaveDromWriter which describes every Wire over the whole run.
+    ///
+    /// # Parameters
+    ///
+    /// - `out`: Destination of the JSON output.
+    /// - `period`: Simulation time spanned by each character of the waves.
+    pub fn new(out: W, period: u64) -> Self {
+        Self {
+            out,
+            selection: None,
+            window: None,
+            period,
+            ids: Vec::new(),
+            lanes: Vec::new(),
+            span: (0, 0),
+        }
+    }
+
+    /// Restrict the diagram to the selected Wires.
+    ///
+    /// # Parameters
+    ///
+    /// - `ids`: Ids of the Wires to describe, from top to bottom.
+    pub fn select(mut self, ids: &[WireId]) -> Self {
+        self.selection = Some(ids.to_vec());
+        self
+    }
+
+    /// Limit the diagram to a window of simulation time.
+    ///
+    /// # Parameters
+    ///
+    /// - `start`: Simulation time at the start of the diagram.
+    /// - `stop`: Simulation time at or before which the last period of the diagram starts.
+    pub fn with_window(mut self, start: u64, stop: u64) -> Self {
+        self.window = Some((start, stop));
+        self
+    }
+
+    /// Consume the writer and obtain the underlying output.
+    pub fn into_inner(self) -> W {
+        self.out
+    }
+
+    /// Record the present logic level of every traced Wire whose level has changed.
+    fn sample(&mut self, sim: &Simulation) -> Result<(), String> {
+        for (id, lane) in self.ids.iter().zip(&mut self.lanes) {
+            let logic = Logic::from_level(
+                sim.wire(*id)?.measure(),
+                DEFAULT_LOW_THRESHOLD,
+                DEFAULT_HIGH_THRESHOLD,
+            );
+            if lane.points.last().is_none_or(|(_, last)| *last != logic) {
+                lane.points.push((sim.time(), logic));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<W: Write + Send> Tracer for WaveDromWriter<W> {
+    fn start(&mut self, sim: &Simulation) -> Result<(), String> {
+        self.ids = match &self.selection {
+            Some(ids) => ids.clone(),
+            None => sim.wires().collect(),
+        };
+        self.lanes = self
+            .ids
+            .iter()
+            .map(|id| {
+                Ok(Lane {
+                    name: sim.wire(*id)?.name().clone(),
+                    points: Vec::new(),
+                })
+            })
+            .collect::<Result<_, String>>()?;
+        self.span = (sim.time(), sim.time());
+        self.sample(sim)
+    }
+
+    fn record(&mut self, sim: &Simulation, _changes: &[Change]) -> Result<(), String> {
+        self.span.1 = sim.time();
+        self.sample(sim)
+    }
+
+    fn finish(&mut self, _sim: &Simulation) -> Result<(), String> {
+        let (start, stop) = self.window.unwrap_or(self.span);
+        write_json(&mut self.out, &self.lanes, start, stop, self.period)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::element::clocks::ClockGenerator;
+    use crate::time::SimDuration;
+    use crate::wire::{Wire, WirePull};
+
+    #[test]
+    fn wavedrom_lane_wave() {
+        // GIVEN a signal which starts late, glitches within a period, and becomes unknown
+        let lane = Lane {
+            name: "A".to_string(),
+            points: vec![
+                (20, Logic::High),
+                (25, Logic::Low),
+                (28, Logic::High),
+                (50, Logic::Unknown),
+            ],
+        };
+        // WHEN its wave is obtained over windows before, across and after its changes
+        // THEN each character gives the level at the start of its period
+        assert_eq!("x.1..x", lane.wave(0, 50, 10));
+        assert_eq!("1", lane.wave(30, 30, 10));
+        assert_eq!("1.x.", lane.wave(30, 65, 10));
+    }
+
+    #[test]
+    fn wavedrom_writer() {
+        // GIVEN a Simulation with a clock, and a Wire which is not described
+        let mut sim = Simulation::new(SimDuration::from_ticks(10));
+        let clock = sim
+            .add_element(Box::new(
+                ClockGenerator::new("X1", 40.0, 0.5, 0.0, 0, 0.0).unwrap(),
+            ))
+            .unwrap();
+        let clk = sim.add_wire(Wire::new("CLK", WirePull::Down)).unwrap();
+        sim.add_wire(Wire::new("\"Q\"", WirePull::Up)).unwrap();
+        sim.connect_output(sim.output_pin(clock, "CLK").unwrap(), clk)
+            .unwrap();
+        // WHEN it is described within a window
+        let mut writer = WaveDromWriter::new(Vec::new(), 10)
+            .select(&[clk])
+            .with_window(10, 90);
+        writer.start(&sim).unwrap();
+        for _ in 0..10 {
+            sim.step().unwrap();
+            writer.record(&sim, sim.changes()).unwrap();
+        }
+        writer.finish(&sim).unwrap();
+        let json = String::from_utf8(writer.into_inner()).unwrap();
+        // THEN only the selected Wire is described, with a character for each period of the window
+        assert_eq!(
+            "{\"signal\": [\n  {\"name\": \"CLK\", \"wave\": \"1.0.1.0.1\"}\n]}\n",
+            json
+        );
+    }
+}
@@ -0,0 +1,174 @@
+//! Breakpoints which halt a run when a Wire changes in a way of interest, so that a circuit can be debugged without
+//! tracing everything.
+//!
+//! A breakpoint is set with [Simulation::break_when](crate::sim::Simulation::break_when), naming a Wire and a [Watch]
+//! describing the change to look for.  After each step, the Wire is compared with how it stood after the step before,
+//! and if the change matches, the step returns [SimResult::Breakpoint](crate::sim::SimResult::Breakpoint), which ends a
+//! [run](crate::sim::Simulation::run) or any of its bounded variants.  A run halted by a breakpoint can be resumed by
+//! running again, as the breakpoint only fires again on a fresh change.
+//!
+//! Unlike the breakpoints of a [controller](crate::sim::Simulation::controller), which pause a run in another thread,
+//! these are checked by the Simulation itself, so they also work without `std`.
+
+use crate::wirevalue::{LogicValue, WireValue};
+use crate::WireId;
+use core::fmt;
+
+/// Handle identifying a breakpoint, with which it can be removed and recognised when it halts a run.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct WatchId(u64);
+
+impl From<u64> for WatchId {
+    fn from(id: u64) -> Self {
+        Self(id)
+    }
+}
+
+impl fmt::Display for WatchId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// A change of a Wire at which a breakpoint halts a run.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Watch {
+    /// The level of the Wire rises from below a threshold to or above it.
+    Rises(f32),
+    /// The level of the Wire falls from at or above a threshold to below it.
+    Falls(f32),
+    /// The level of the Wire crosses a threshold in either direction.
+    Crosses(f32),
+    /// The [logic value](crate::sim::Simulation::logic) of the Wire changes, including to or from high impedance or
+    /// unknown.
+    LogicChanges,
+}
+
+impl Watch {
+    /// Determine whether a change of a Wire between steps matches the watch.
+    ///
+    /// # Parameters
+    ///
+    /// - `from`: Level and logic value of the Wire after the previous step.
+    /// - `to`: Level and logic value of the Wire after the present step.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use rvfs_sim_core::watch::Watch;
+    /// # use rvfs_sim_core::wirevalue::{LogicValue, WireValue};
+    /// let low = (WireValue::new(0.2), LogicValue::Low);
+    /// let middle = (WireValue::new(0.5), LogicValue::Unknown);
+    ///
+    /// assert!(Watch::Rises(0.5).matches(low, middle));
+    /// assert!(!Watch::Falls(0.5).matches(low, middle));
+    /// assert!(Watch::LogicChanges.matches(low, middle));
+    /// ```
+    pub fn matches(self, from: (WireValue, LogicValue), to: (WireValue, LogicValue)) -> bool {
+        let above = |level: WireValue, threshold: f32| f32::from(level) >= threshold;
+        match self {
+            Self::Rises(threshold) => !above(from.0, threshold) && above(to.0, threshold),
+            Self::Falls(threshold) => above(from.0, threshold) && !above(to.0, threshold),
+            Self::Crosses(threshold) => above(from.0, threshold) != above(to.0, threshold),
+            Self::LogicChanges => from.1 != to.1,
+        }
+    }
+}
+
+/// A breakpoint set on a Wire, with how the Wire stood after the last step.
+#[derive(Debug, Clone)]
+pub(crate) struct Breakpoint {
+    /// Handle identifying the breakpoint.
+    pub(crate) id: WatchId,
+    /// Id of the watched Wire.
+    pub(crate) wire: WireId,
+    /// The change at which to halt.
+    watch: Watch,
+    /// Level and logic value of the Wire after the last step.
+    last: (WireValue, LogicValue),
+}
+
+impl Breakpoint {
+    /// Create a new Breakpoint.
+    ///
+    /// # Parameters
+    ///
+    /// - `id`: Handle identifying the breakpoint.
+    /// - `wire`: Id of the watched Wire.
+    /// - `watch`: The change at which to halt.
+    /// - `present`: Present level and logic value of the Wire.
+    pub(crate) fn new(
+        id: WatchId,
+        wire: WireId,
+        watch: Watch,
+        present: (WireValue, LogicValue),
+    ) -> Self {
+        Self {
+            id,
+            wire,
+            watch,
+            last: present,
+        }
+    }
+
+    /// Note how the Wire stands after a step, and determine whether its change since the last step matches the watch.
+    ///
+    /// # Parameters
+    ///
+    /// - `present`: Present level and logic value of the Wire.
+    pub(crate) fn update(&mut self, present: (WireValue, LogicValue)) -> bool {
+        let hit = self.watch.matches(self.last, present);
+        self.last = present;
+        hit
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::*;
+
+    #[test]
+    fn watch_thresholds() {
+        // GIVEN levels either side of a threshold
+        let low = (WireValue::new(0.3), LogicValue::Low);
+        let high = (WireValue::new(0.7), LogicValue::High);
+        let watches = [Watch::Rises(0.5), Watch::Falls(0.5), Watch::Crosses(0.5)];
+        // WHEN each watch is matched against a rise, a fall and no change
+        let matches: Vec<[bool; 3]> = watches
+            .iter()
+            .map(|watch| {
+                [
+                    watch.matches(low, high),
+                    watch.matches(high, low),
+                    watch.matches(high, high),
+                ]
+            })
+            .collect();
+        // THEN each matches the crossings in its direction only
+        assert_eq!(
+            vec![
+                [true, false, false],
+                [false, true, false],
+                [true, true, false]
+            ],
+            matches
+        );
+    }
+    #[test]
+    fn breakpoint_update() {
+        // GIVEN a breakpoint on a Wire's logic value, set while the Wire floats
+        let floating = (WireValue::new(0.5), LogicValue::HighImpedance);
+        let driven = (WireValue::new(0.5), LogicValue::Unknown);
+        let mut breakpoint = Breakpoint::new(
+            WatchId::from(0),
+            WireId::from(3),
+            Watch::LogicChanges,
+            floating,
+        );
+        // WHEN the Wire becomes contended, then stays so
+        // THEN the breakpoint fires once, though the level never changed
+        assert!(breakpoint.update(driven));
+        assert!(!breakpoint.update(driven));
+    }
+}
@@ -1,9 +1,14 @@
 //! Wires propagate signals from OutputPin instances to InputPin instances.
 
+use crate::math;
+use crate::prelude::*;
+use crate::time::SimDuration;
 use crate::wirevalue::WireValue;
+use crate::{ComponentId, Id, IdIter, WireId};
 
 /// Types of pull which may be exerted on a Wire.
 #[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum WirePull {
     /// Wire value is pulled towards 1.0.
     Up,
@@ -72,6 +77,19 @@ impl Wire {
         &self.name
     }
 
+    /// Obtain a copy of the Wire under another name.
+    ///
+    /// # Parameters
+    ///
+    /// - `name`: Name of the copy.
+    #[cfg(feature = "std")]
+    pub(crate) fn renamed(&self, name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            ..self.clone()
+        }
+    }
+
     /// Determine the present pull direction of the Wire.
     ///
     /// The active pull direction will take precedence over the default pull value.
@@ -91,7 +109,7 @@ impl Wire {
     /// # use rvfs_sim_core::wire::{Wire, WirePull};
     /// let wire = Wire::new("/RESET", WirePull::Down);
     ///
-    /// assert_eq!(0.0, wire.measure().into());
+    /// assert_eq!(0.0, f32::from(wire.measure()));
     /// ```
     pub fn measure(&self) -> WireValue {
         self.value
@@ -120,18 +138,210 @@ impl Wire {
     /// # Parameters
     ///
     /// - `delta_t`: Simulation time elapsed since the last step.
-    pub fn step(&mut self, delta_t: u64) {
-        let pull = self.pull();
-
-        if pull != WirePull::None {
-            let newval = f32::from(self.value) * (-(delta_t as f32) / self.tau).exp();
-            if pull == WirePull::Up {
-                self.value = (1.0f32 - newval).into();
-            } else {
-                self.value = newval.into();
-            }
+    pub fn step(&mut self, delta_t: SimDuration) {
+        self.value = settle(self.value, self.pull(), self.tau, delta_t);
+    }
+}
+
+/// Calculate the new value of a Wire, based on its present value, pull direction, and time constant.
+///
+/// # Parameters
+///
+/// - `value`: Present value of the Wire.
+/// - `pull`: Pull direction the Wire feels.
+/// - `tau`: Time constant of the Wire.
+/// - `delta_t`: Simulation time elapsed since the last step.
+fn settle(value: WireValue, pull: WirePull, tau: f32, delta_t: SimDuration) -> WireValue {
+    if pull == WirePull::None {
+        return value;
+    }
+
+    let newval = f32::from(value) * math::exp(-(delta_t.ticks() as f32) / tau);
+    if pull == WirePull::Up {
+        (1.0f32 - newval).into()
+    } else {
+        newval.into()
+    }
+}
+
+/// A read-only view of a Wire held by a [Simulation](crate::sim::Simulation).
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct WireRef<'a> {
+    /// Name of the Wire.
+    name: &'a String,
+    /// Present pull direction of the Wire, taking its default pull into account.
+    pull: WirePull,
+    /// Present value of the Wire.
+    value: WireValue,
+    /// Time constant of the Wire.
+    tau: f32,
+}
+
+impl<'a> WireRef<'a> {
+    /// Get the name assigned to the Wire.
+    pub fn name(&self) -> &'a String {
+        self.name
+    }
+
+    /// Determine the present pull direction of the Wire.
+    ///
+    /// The active pull direction will take precedence over the default pull value.
+    pub fn pull(&self) -> WirePull {
+        self.pull
+    }
+
+    /// Measure the present level of the Wire.
+    pub fn measure(&self) -> WireValue {
+        self.value
+    }
+
+    /// Obtain the time constant which controls the rate at which the Wire's value moves in the pulled direction.
+    pub fn time_constant(&self) -> f32 {
+        self.tau
+    }
+}
+
+/// The Wires of a Simulation, stored field by field in contiguous arrays indexed by Wire Id.
+///
+/// Every Wire is visited on every step, so keeping each field in an array of its own lets the step loop stream through
+/// memory rather than hopping between Wires.  Names are not stored here, as they are only needed for reporting.  A
+/// removed Wire keeps its place, so that the Ids of the other Wires do not change.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(crate) struct WireArena {
+    /// Default pull of each Wire.
+    default_pulls: Vec<WirePull>,
+    /// Active pull of each Wire.
+    pulls: Vec<WirePull>,
+    /// Time constant of each Wire.
+    taus: Vec<f32>,
+    /// Present value of each Wire.
+    values: Vec<WireValue>,
+    /// Ids of removed Wires, in ascending order.
+    removed: Vec<Id>,
+}
+
+impl WireArena {
+    /// Create a new, empty WireArena.
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a Wire, discarding its name, and obtain its Id.
+    ///
+    /// # Parameters
+    ///
+    /// - `wire`: The Wire.
+    pub(crate) fn add(&mut self, wire: Wire) -> WireId {
+        self.default_pulls.push(wire.default_pull);
+        self.pulls.push(wire.pull);
+        self.taus.push(wire.tau);
+        self.values.push(wire.value);
+        WireId::from(self.values.len() - 1)
+    }
+
+    /// Obtain the number of Wires ever added, including any since removed.
+    pub(crate) fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    /// Obtain an iterator over the Ids of the Wires which have not been removed.
+    pub(crate) fn ids(&self) -> IdIter<'_, WireId> {
+        IdIter::new(self.len()).skipping(&self.removed)
+    }
+
+    /// Remove a Wire, so that it is no longer stepped or viewed.
+    ///
+    /// # Parameters
+    ///
+    /// - `id`: The Id of the Wire.
+    pub(crate) fn remove(&mut self, id: WireId) {
+        let id = id.index();
+        if let Err(position) = self.removed.binary_search(&id) {
+            self.removed.insert(position, id);
+        }
+    }
+
+    /// Obtain a view of a Wire, if it exists.
+    ///
+    /// # Parameters
+    ///
+    /// - `id`: The Id of the Wire.
+    /// - `name`: The name of the Wire.
+    pub(crate) fn view<'a>(&self, id: WireId, name: &'a String) -> Option<WireRef<'a>> {
+        let index = id.index();
+        (index < self.len() && self.removed.binary_search(&index).is_err()).then(|| WireRef {
+            name,
+            pull: self.pull(id),
+            value: self.values[index],
+            tau: self.taus[index],
+        })
+    }
+
+    /// Determine the present pull direction of a Wire, taking its default pull into account.
+    ///
+    /// # Parameters
+    ///
+    /// - `id`: The Id of the Wire.
+    pub(crate) fn pull(&self, id: WireId) -> WirePull {
+        let id = id.index();
+        if self.pulls[id] == WirePull::None {
+            self.default_pulls[id]
+        } else {
+            self.pulls[id]
         }
     }
+
+    /// Measure the present level of a Wire.
+    ///
+    /// # Parameters
+    ///
+    /// - `id`: The Id of the Wire.
+    pub(crate) fn measure(&self, id: WireId) -> WireValue {
+        self.values[id.index()]
+    }
+
+    /// Obtain the active pull direction of a Wire, without taking its default pull into account.
+    ///
+    /// # Parameters
+    ///
+    /// - `id`: The Id of the Wire.
+    #[cfg(feature = "serde")]
+    pub(crate) fn active_pull(&self, id: WireId) -> WirePull {
+        self.pulls[id.index()]
+    }
+
+    /// Set the present level of a Wire.
+    ///
+    /// # Parameters
+    ///
+    /// - `id`: The Id of the Wire.
+    /// - `value`: New level of the Wire.
+    #[cfg(feature = "serde")]
+    pub(crate) fn set_value(&mut self, id: WireId, value: WireValue) {
+        self.values[id.index()] = value;
+    }
+
+    /// Set the active pull direction of a Wire.
+    ///
+    /// # Parameters
+    ///
+    /// - `id`: The Id of the Wire.
+    /// - `pull`: New active pull direction of the Wire.
+    pub(crate) fn set_pull(&mut self, id: WireId, pull: WirePull) {
+        self.pulls[id.index()] = pull;
+    }
+
+    /// Calculate the new value of a Wire, as [Wire::step] does.
+    ///
+    /// # Parameters
+    ///
+    /// - `id`: The Id of the Wire.
+    /// - `delta_t`: Simulation time elapsed since the last step.
+    pub(crate) fn step(&mut self, id: WireId, delta_t: SimDuration) {
+        let pull = self.pull(id);
+        let id = id.index();
+        self.values[id] = settle(self.values[id], pull, self.taus[id], delta_t);
+    }
 }
 
 #[cfg(test)]
@@ -205,7 +415,7 @@ mod tests {
         wire.set_time_constant(tau);
         wire.set_pull(WirePull::Up);
         // WHEN step is called
-        wire.step(10);
+        wire.step(SimDuration::from_ticks(10));
         // THEN the value has changed in the pull-up direction
         assert_approx_eq!(f32, 0.93233235f32, wire.measure().into());
     }
@@ -217,7 +427,7 @@ mod tests {
         wire.set_time_constant(tau);
         wire.set_pull(WirePull::Down);
         // WHEN step is called
-        wire.step(10);
+        wire.step(SimDuration::from_ticks(10));
         // THEN the value has changed in the pull-down direction
         assert_approx_eq!(f32, 0.06766764f32, wire.measure().into());
     }
@@ -229,7 +439,7 @@ mod tests {
         wire.set_time_constant(tau);
         wire.set_pull(WirePull::None);
         // WHEN step is called
-        wire.step(10);
+        wire.step(SimDuration::from_ticks(10));
         // THEN the value has not changed from the default
         assert_approx_eq!(f32, 0.5, wire.measure().into());
     }
@@ -241,7 +451,7 @@ mod tests {
         wire.set_time_constant(tau);
         wire.set_pull(WirePull::Down);
         // WHEN step is called
-        wire.step(10);
+        wire.step(SimDuration::from_ticks(10));
         // THEN the value has changed in the pull-down direction
         assert_approx_eq!(f32, 0.13533528f32, wire.measure().into());
     }
@@ -253,7 +463,7 @@ mod tests {
         wire.set_time_constant(tau);
         wire.set_pull(WirePull::Up);
         // WHEN step is called
-        wire.step(10);
+        wire.step(SimDuration::from_ticks(10));
         // THEN the value is immediately at maximum
         assert_approx_eq!(f32, 1.0f32, wire.measure().into());
     }
@@ -265,8 +475,39 @@ mod tests {
         wire.set_time_constant(tau);
         wire.set_pull(WirePull::Down);
         // WHEN step is called
-        wire.step(10);
+        wire.step(SimDuration::from_ticks(10));
         // THEN the value is immediately at minimum
         assert_approx_eq!(f32, 0.0f32, wire.measure().into());
     }
+    #[test]
+    fn wire_arena_matches_wire() {
+        // GIVEN a Wire with a time constant and default pull-up, and an arena holding a copy of it
+        let mut wire = Wire::new("foo", WirePull::Up);
+        wire.set_time_constant(5.0);
+        let mut arena = WireArena::new();
+        let id = arena.add(wire.clone());
+        let name = wire.name().clone();
+        // WHEN both are pulled down and stepped, then released and stepped
+        wire.set_pull(WirePull::Down);
+        arena.set_pull(id, WirePull::Down);
+        wire.step(SimDuration::from_ticks(10));
+        arena.step(id, SimDuration::from_ticks(10));
+        let pulled = arena.view(id, &name).unwrap();
+        wire.set_pull(WirePull::None);
+        arena.set_pull(id, WirePull::None);
+        wire.step(SimDuration::from_ticks(10));
+        arena.step(id, SimDuration::from_ticks(10));
+        // THEN the arena's view of the Wire follows the Wire itself, and there is no view of a missing or removed Wire
+        assert_eq!(WirePull::Down, pulled.pull());
+        assert_approx_eq!(f32, 0.13533528f32, pulled.measure().into());
+        let released = arena.view(id, &name).unwrap();
+        assert_eq!("foo", released.name());
+        assert_eq!(wire.pull(), released.pull());
+        assert_eq!(wire.measure(), released.measure());
+        assert_eq!(1, arena.len());
+        assert_eq!(None, arena.view(WireId::from(1), &name));
+        arena.remove(id);
+        assert_eq!(None, arena.view(id, &name));
+        assert_eq!(0, arena.ids().count());
+    }
 }
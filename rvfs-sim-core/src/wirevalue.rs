@@ -2,6 +2,7 @@
 
 /// Representation of the values which a Wire can take between low (0.0) and high (1.0).
 #[derive(Debug, Copy, Clone, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct WireValue {
     /// Wire level value, in the range [0.0, 1.0].
     level: f32,
@@ -64,6 +65,60 @@ impl From<WireValue> for f64 {
     }
 }
 
+/// Logic interpretation of a Wire, telling a Wire which is undriven or contested apart from one at a definite level.
+///
+/// A floating Wire keeps whatever level it last had, which would otherwise read as a plausible logic level and hide the
+/// fault, so it is classified by how it is driven before its level is considered.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum LogicValue {
+    /// The Wire is driven or pulled, and its level is at or below the low threshold.
+    Low,
+    /// The Wire is driven or pulled, and its level is at or above the high threshold.
+    High,
+    /// Nothing drives or pulls the Wire.
+    HighImpedance,
+    /// The Wire is driven both high and low, or its level is between the thresholds.
+    Unknown,
+}
+
+impl LogicValue {
+    /// Classify the level of a Wire which is driven or pulled without conflict.
+    ///
+    /// # Parameters
+    ///
+    /// - `value`: The Wire level to classify.
+    /// - `low`: Level at or below which the Wire is considered logic low.
+    /// - `high`: Level at or above which the Wire is considered logic high.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use rvfs_sim_core::wirevalue::{LogicValue, WireValue};
+    /// assert_eq!(LogicValue::High, LogicValue::from_level(WireValue::new(0.9), 0.3, 0.7));
+    /// assert_eq!(LogicValue::Unknown, LogicValue::from_level(WireValue::new(0.5), 0.3, 0.7));
+    /// ```
+    pub fn from_level(value: WireValue, low: f32, high: f32) -> Self {
+        let level = f32::from(value);
+        if level >= high {
+            LogicValue::High
+        } else if level <= low {
+            LogicValue::Low
+        } else {
+            LogicValue::Unknown
+        }
+    }
+
+    /// Obtain the conventional single character symbol for the logic value: `0`, `1`, `z` or `x`.
+    pub fn symbol(self) -> char {
+        match self {
+            LogicValue::Low => '0',
+            LogicValue::High => '1',
+            LogicValue::HighImpedance => 'z',
+            LogicValue::Unknown => 'x',
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -0,0 +1,365 @@
+//! Fluent assertions on the signals of a Simulation, to make Rust unit tests of circuits concise.
+//!
+//! An assertion names a Wire and states how its logic level should behave, stepping the Simulation as far as needed to
+//! check it:
+//!
+//! ```
+//! # use rvfs_sim_core::element::clocks::ClockGenerator;
+//! # use rvfs_sim_core::sim::Simulation;
+//! # use rvfs_sim_core::time::SimDuration;
+//! # use rvfs_sim_core::wire::{Wire, WirePull};
+//! use rvfs_sim_test::expect;
+//!
+//! let mut sim = Simulation::new(SimDuration::from_ticks(10));
+//! let clock = sim
+//!     .add_element(Box::new(ClockGenerator::new("X1", 100.0, 0.5, 0.0, 50, 0.0).unwrap()))
+//!     .unwrap();
+//! let clk = sim.add_wire(Wire::new("CLK", WirePull::Down)).unwrap();
+//! sim.connect_output(sim.output_pin(clock, "CLK").unwrap(), clk)
+//!     .unwrap();
+//!
+//! expect(&mut sim, "CLK").to_stay_low_between(0, 50);
+//! assert_eq!(60, expect(&mut sim, "CLK").to_rise_within(20));
+//! expect(&mut sim, "CLK").to_fall_within(50);
+//! ```
+//!
+//! A failed assertion panics with a message describing the failure, followed by the waveform of the Wire captured
+//! while checking it, drawn with `_` for low, `‾` for high and `x` for unknown levels.
+//!
+//! The [stimulus] module generates randomised stimulus for property-based tests.
+
+pub mod stimulus;
+
+use rvfs_sim_core::sim::{SimResult, Simulation};
+use rvfs_sim_core::trace::{Logic, DEFAULT_HIGH_THRESHOLD, DEFAULT_LOW_THRESHOLD};
+use rvfs_sim_core::WireId;
+
+/// Begin an assertion on a Wire of a Simulation.
+///
+/// # Parameters
+///
+/// - `sim`: The Simulation.
+/// - `wire`: Name of the Wire.
+///
+/// # Panics
+///
+/// Panics if the Simulation has no Wire with the name.
+#[track_caller]
+pub fn expect<'a>(sim: &'a mut Simulation, wire: &str) -> Expectation<'a> {
+    let id = sim
+        .wire_by_name(wire)
+        .unwrap_or_else(|_| panic!("expected a wire named \"{wire}\", but there is none"));
+    Expectation {
+        sim,
+        id,
+        name: wire.to_string(),
+    }
+}
+
+/// An assertion on a Wire of a Simulation, begun by [expect].
+pub struct Expectation<'a> {
+    /// The Simulation.
+    sim: &'a mut Simulation,
+    /// Id of the Wire.
+    id: WireId,
+    /// Name of the Wire.
+    name: String,
+}
+
+impl Expectation<'_> {
+    /// Assert that the Wire is high now.
+    #[track_caller]
+    pub fn to_be_high(self) {
+        self.check_level(Logic::High);
+    }
+
+    /// Assert that the Wire is low now.
+    #[track_caller]
+    pub fn to_be_low(self) {
+        self.check_level(Logic::Low);
+    }
+
+    /// Assert that the Wire rises to high within a span of simulation time, stepping the Simulation until it does.
+    ///
+    /// Returns the simulation time at which the Wire was first seen high.
+    ///
+    /// # Parameters
+    ///
+    /// - `duration`: Simulation time within which the Wire must rise.
+    #[track_caller]
+    pub fn to_rise_within(self, duration: u64) -> u64 {
+        self.check_change(Logic::High, "rise", duration)
+    }
+
+    /// Assert that the Wire falls to low within a span of simulation time, stepping the Simulation until it does.
+    ///
+    /// Returns the simulation time at which the Wire was first seen low.
+    ///
+    /// # Parameters
+    ///
+    /// - `duration`: Simulation time within which the Wire must fall.
+    #[track_caller]
+    pub fn to_fall_within(self, duration: u64) -> u64 {
+        self.check_change(Logic::Low, "fall", duration)
+    }
+
+    /// Assert that the Wire stays low at every step over a window of simulation time.
+    ///
+    /// The Simulation is stepped through the window, or stepped back to it if the window starts in the past, which
+    /// needs [checkpoints](Simulation::set_checkpoints) to have been configured.
+    ///
+    /// # Parameters
+    ///
+    /// - `t0`: Simulation time at the start of the window.
+    /// - `t1`: Simulation time at the end of the window.
+    #[track_caller]
+    pub fn to_stay_low_between(self, t0: u64, t1: u64) {
+        self.check_stays(Logic::Low, t0, t1);
+    }
+
+    /// Assert that the Wire stays high at every step over a window of simulation time.
+    ///
+    /// The Simulation is stepped through the window, or stepped back to it if the window starts in the past, which
+    /// needs [checkpoints](Simulation::set_checkpoints) to have been configured.
+    ///
+    /// # Parameters
+    ///
+    /// - `t0`: Simulation time at the start of the window.
+    /// - `t1`: Simulation time at the end of the window.
+    #[track_caller]
+    pub fn to_stay_high_between(self, t0: u64, t1: u64) {
+        self.check_stays(Logic::High, t0, t1);
+    }
+
+    /// Obtain the present logic level of the Wire.
+    #[track_caller]
+    fn logic(&self) -> Logic {
+        let wire = self
+            .sim
+            .wire(self.id)
+            .unwrap_or_else(|error| panic!("cannot read \"{}\": {error}", self.name));
+        level(f32::from(wire.measure()))
+    }
+
+    /// Step the Simulation, returning its new time and the logic level of the Wire, or None if it has finished.
+    #[track_caller]
+    fn step(&mut self) -> Option<(u64, Logic)> {
+        match self.sim.step() {
+            Ok(SimResult::Continuing | SimResult::Breakpoint(_) | SimResult::Limit(_)) => {
+                Some((self.sim.time(), self.logic()))
+            }
+            Ok(SimResult::Finished) => None,
+            Err(error) => panic!(
+                "simulation failed while checking \"{}\": {error}",
+                self.name
+            ),
+        }
+    }
+
+    /// Assert that the Wire has a logic level now.
+    ///
+    /// # Parameters
+    ///
+    /// - `expected`: The logic level.
+    #[track_caller]
+    fn check_level(self, expected: Logic) {
+        let logic = self.logic();
+        if logic != expected {
+            panic!(
+                "expected {} to be {} at time {}, but it was {}",
+                self.name,
+                describe(expected),
+                self.sim.time(),
+                describe(logic)
+            );
+        }
+    }
+
+    /// Assert that the Wire changes to a logic level within a span of simulation time.
+    ///
+    /// # Parameters
+    ///
+    /// - `expected`: The logic level to change to.
+    /// - `verb`: Description of the change, for the failure message.
+    /// - `duration`: Simulation time within which the Wire must change.
+    #[track_caller]
+    fn check_change(mut self, expected: Logic, verb: &str, duration: u64) -> u64 {
+        let start = self.sim.time();
+        let mut samples = vec![(start, self.logic())];
+        while self.sim.time() < start.saturating_add(duration) {
+            let Some((time, logic)) = self.step() else {
+                break;
+            };
+            let previous = samples[samples.len() - 1].1;
+            samples.push((time, logic));
+            if previous != expected && logic == expected {
+                return time;
+            }
+        }
+        panic!(
+            "expected {} to {verb} within {duration} of time {start}, but it did not by time {}\n{}",
+            self.name,
+            self.sim.time(),
+            waveform(&self.name, &samples)
+        );
+    }
+
+    /// Assert that the Wire stays at a logic level over a window of simulation time.
+    ///
+    /// # Parameters
+    ///
+    /// - `expected`: The logic level.
+    /// - `t0`: Simulation time at the start of the window.
+    /// - `t1`: Simulation time at the end of the window.
+    #[track_caller]
+    fn check_stays(self, expected: Logic, t0: u64, t1: u64) {
+        let (times, levels) = self
+            .sim
+            .sample(&[self.id], t0, t1, 1)
+            .unwrap_or_else(|error| panic!("cannot sample \"{}\": {error}", self.name));
+        let samples: Vec<(u64, Logic)> = times
+            .into_iter()
+            .zip(levels[0].iter().map(|level| self::level(*level)))
+            .collect();
+        if let Some((time, logic)) = samples.iter().find(|(_, logic)| *logic != expected) {
+            panic!(
+                "expected {} to stay {} between {t0} and {t1}, but it was {} at time {time}\n{}",
+                self.name,
+                describe(expected),
+                describe(*logic),
+                waveform(&self.name, &samples)
+            );
+        }
+    }
+}
+
+/// Classify a Wire level with the default thresholds.
+///
+/// # Parameters
+///
+/// - `level`: The Wire level.
+fn level(level: f32) -> Logic {
+    Logic::from_level(level.into(), DEFAULT_LOW_THRESHOLD, DEFAULT_HIGH_THRESHOLD)
+}
+
+/// Describe a logic level in words.
+///
+/// # Parameters
+///
+/// - `logic`: The logic level.
+fn describe(logic: Logic) -> &'static str {
+    match logic {
+        Logic::Low => "low",
+        Logic::High => "high",
+        Logic::Unknown => "unknown",
+    }
+}
+
+/// Draw the waveform of a Wire, with one character for each sample, labelled with the times of its first and last
+/// samples.
+///
+/// # Parameters
+///
+/// - `name`: Name of the Wire.
+/// - `samples`: The samples, as pairs of time and logic level.
+fn waveform(name: &str, samples: &[(u64, Logic)]) -> String {
+    let wave: String = samples
+        .iter()
+        .map(|(_, logic)| match logic {
+            Logic::Low => '_',
+            Logic::High => '‾',
+            Logic::Unknown => 'x',
+        })
+        .collect();
+    let (first, last) = match samples {
+        [] => (0, 0),
+        [first, .., last] => (first.0, last.0),
+        [only] => (only.0, only.0),
+    };
+    format!("  {name}: {wave}  (time {first} to {last})")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rvfs_sim_core::element::clocks::ClockGenerator;
+    use rvfs_sim_core::sim::{DEFAULT_CHECKPOINT_INTERVAL, DEFAULT_CHECKPOINT_LIMIT};
+    use rvfs_sim_core::time::SimDuration;
+    use rvfs_sim_core::wire::{Wire, WirePull};
+
+    /// Create a Simulation with a clock of period 40 on Wire `CLK`, and a Wire `RESET` pulled up, which can be stepped
+    /// back.
+    fn clocked() -> Simulation {
+        let mut sim = Simulation::new(SimDuration::from_ticks(10));
+        sim.set_checkpoints(DEFAULT_CHECKPOINT_INTERVAL, DEFAULT_CHECKPOINT_LIMIT);
+        let clock = sim
+            .add_element(Box::new(
+                ClockGenerator::new("X1", 40.0, 0.5, 0.0, 0, 0.0).unwrap(),
+            ))
+            .unwrap();
+        let clk = sim.add_wire(Wire::new("CLK", WirePull::Down)).unwrap();
+        sim.add_wire(Wire::new("RESET", WirePull::Up)).unwrap();
+        sim.connect_output(sim.output_pin(clock, "CLK").unwrap(), clk)
+            .unwrap();
+        sim
+    }
+
+    #[test]
+    fn expect_passing_assertions() {
+        // GIVEN a clocked Simulation
+        let mut sim = clocked();
+        // WHEN assertions which hold are made
+        expect(&mut sim, "RESET").to_be_high();
+        let rise = expect(&mut sim, "CLK").to_rise_within(10);
+        let fall = expect(&mut sim, "CLK").to_fall_within(40);
+        expect(&mut sim, "CLK").to_be_low();
+        expect(&mut sim, "CLK").to_stay_high_between(10, 20);
+        expect(&mut sim, "RESET").to_stay_high_between(0, 100);
+        // THEN they pass, and the edges are found when they occur
+        assert_eq!(10, rise);
+        assert_eq!(30, fall);
+        assert_eq!(100, sim.time());
+    }
+    #[test]
+    #[should_panic(
+        expected = "expected CLK to rise within 20 of time 10, but it did not by time 30\n  CLK: ‾‾_  (time 10 to 30)"
+    )]
+    fn expect_rise_failure() {
+        // GIVEN a clocked Simulation with the clock already high
+        let mut sim = clocked();
+        sim.step().unwrap();
+        // WHEN the clock is expected to rise too soon
+        // THEN the assertion fails with the captured waveform
+        expect(&mut sim, "CLK").to_rise_within(20);
+    }
+    #[test]
+    #[should_panic(
+        expected = "expected CLK to stay low between 30 and 60, but it was high at time 50\n  \
+                    CLK: __‾‾  (time 30 to 60)"
+    )]
+    fn expect_stay_failure() {
+        // GIVEN a clocked Simulation
+        let mut sim = clocked();
+        // WHEN the clock is expected to stay low too long
+        // THEN the assertion fails with the captured waveform
+        expect(&mut sim, "CLK").to_stay_low_between(30, 60);
+    }
+    #[test]
+    #[should_panic(expected = "expected RESET to be low at time 0, but it was high")]
+    fn expect_level_failure() {
+        // GIVEN a clocked Simulation
+        let mut sim = clocked();
+        // WHEN the pulled up Wire is expected to be low
+        // THEN the assertion fails
+        expect(&mut sim, "RESET").to_be_low();
+    }
+    #[test]
+    #[should_panic(expected = "expected a wire named \"ACK\", but there is none")]
+    fn expect_unknown_wire() {
+        // GIVEN a clocked Simulation
+        let mut sim = clocked();
+        // WHEN an assertion names a Wire it does not have
+        // THEN the assertion fails
+        expect(&mut sim, "ACK").to_be_low();
+    }
+}
@@ -0,0 +1,432 @@
+//! Randomised but constrained stimulus sequences for property-based testing of Element models with [proptest].
+//!
+//! Strategies generate [Action]s, such as valid I2C transactions and forced Wire levels, and [sequence] strings them
+//! together with random idle gaps.  A [Driver] applies a sequence to a Simulation.  When a property fails, proptest
+//! shrinks the sequence towards the shortest, simplest one which still fails, so that corner cases are reported in a
+//! form which is easy to reproduce.  Sequences can also be generated outside a property test, by a [runner] seeded
+//! like a Simulation, so that a randomised run is reproduced by giving it the same seed.
+//!
+//! # Example
+//!
+//! ```
+//! # use proptest::prelude::*;
+//! # use rvfs_sim_core::element::i2c::{I2cMaster, I2cOutcome};
+//! # use rvfs_sim_core::sim::Simulation;
+//! # use rvfs_sim_core::time::SimDuration;
+//! # use rvfs_sim_core::wire::{Wire, WirePull};
+//! use rvfs_sim_test::stimulus::{self, Driver};
+//!
+//! proptest!(|(actions in stimulus::sequence(stimulus::i2c(&[0x50], 2, 2), 100, 1..4))| {
+//!     // With no slave on the bus, every transaction is not acknowledged
+//!     let mut sim = Simulation::new(SimDuration::from_ticks(10));
+//!     let master = I2cMaster::new("M1", 80).unwrap();
+//!     let host = master.host();
+//!     let id = sim.add_element(Box::new(master)).unwrap();
+//!     for name in ["SCL", "SDA"] {
+//!         let wire = sim.add_wire(Wire::new(name, WirePull::Up)).unwrap();
+//!         sim.connect_input(wire, sim.input_pin(id, name).unwrap()).unwrap();
+//!         sim.connect_output(sim.output_pin(id, name).unwrap(), wire).unwrap();
+//!     }
+//!
+//!     let outcomes = Driver::new(&[]).with_i2c(host, 10_000).apply(&mut sim, &actions).unwrap();
+//!     prop_assert!(outcomes.iter().all(|outcome| *outcome == I2cOutcome::Nack));
+//! });
+//! ```
+
+use proptest::prelude::*;
+use proptest::test_runner::{Config, RngAlgorithm, TestRng, TestRunner};
+use rvfs_sim_core::element::i2c::{I2cHost, I2cOutcome, I2cTransaction};
+use rvfs_sim_core::rng::SimRng;
+use rvfs_sim_core::sim::{SimResult, Simulation};
+use rvfs_sim_core::wire::WirePull;
+use rvfs_sim_core::WireId;
+use std::ops::Range;
+
+/// One action of a stimulus sequence.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Action {
+    /// Force a Wire, given by its index among the Wires of the [Driver], high (true) or low (false).
+    Drive(usize, bool),
+    /// Release a Wire, given by its index among the Wires of the [Driver], from being forced.
+    Release(usize),
+    /// Perform an I2C transaction, and wait for it to finish.
+    I2c(I2cTransaction),
+    /// Let a span of simulation time pass without stimulus.
+    Idle(u64),
+}
+
+/// Generate Actions which force or release Wires.
+///
+/// # Parameters
+///
+/// - `wires`: Number of Wires of the [Driver] which may be forced.
+pub fn levels(wires: usize) -> impl Strategy<Value = Action> {
+    prop_oneof![
+        (0..wires, any::<bool>()).prop_map(|(wire, high)| Action::Drive(wire, high)),
+        (0..wires).prop_map(Action::Release),
+    ]
+}
+
+/// Generate Actions which perform valid I2C transactions.
+///
+/// Shrinking favours the first address and transactions which write and read fewer bytes.
+///
+/// # Parameters
+///
+/// - `addresses`: The 7 bit slave addresses to choose from, which must not be empty.
+/// - `write`: Largest number of bytes to write.
+/// - `read`: Largest number of bytes to read.
+pub fn i2c(addresses: &[u8], write: usize, read: usize) -> impl Strategy<Value = Action> {
+    (
+        prop::sample::select(addresses.to_vec()),
+        prop::collection::vec(any::<u8>(), 0..=write),
+        0..=read,
+    )
+        .prop_map(|(address, write, read)| {
+            Action::I2c(I2cTransaction {
+                address: address & 0x7f,
+                write,
+                read,
+            })
+        })
+}
+
+/// Generate sequences of Actions, each followed by a random idle gap.
+///
+/// Shrinking favours shorter sequences with shorter gaps.
+///
+/// # Parameters
+///
+/// - `action`: Strategy generating each Action.
+/// - `idle`: Longest idle gap, in simulation time.
+/// - `length`: Range of the number of Actions, not counting the gaps.
+pub fn sequence(
+    action: impl Strategy<Value = Action>,
+    idle: u64,
+    length: Range<usize>,
+) -> impl Strategy<Value = Vec<Action>> {
+    prop::collection::vec((action, 0..=idle), length).prop_map(|actions| {
+        actions
+            .into_iter()
+            .flat_map(|(action, gap)| {
+                let gap = (gap > 0).then_some(Action::Idle(gap));
+                std::iter::once(action).chain(gap)
+            })
+            .collect()
+    })
+}
+
+/// Create a proptest TestRunner which generates stimulus from a Simulation seed, so that the same seed always gives
+/// the same stimulus.
+///
+/// # Parameters
+///
+/// - `seed`: The [seed](rvfs_sim_core::sim::Simulation::seed) of the Simulation being stimulated.
+/// - `config`: Configuration of the runner.
+///
+/// # Example
+///
+/// ```
+/// # use proptest::strategy::{Strategy, ValueTree};
+/// # use proptest::test_runner::Config;
+/// use rvfs_sim_test::stimulus;
+///
+/// let strategy = stimulus::sequence(stimulus::levels(2), 100, 1..8);
+/// let mut first = stimulus::runner(42, Config::default());
+/// let mut second = stimulus::runner(42, Config::default());
+///
+/// assert_eq!(
+///     strategy.new_tree(&mut first).unwrap().current(),
+///     strategy.new_tree(&mut second).unwrap().current()
+/// );
+/// ```
+pub fn runner(seed: u64, config: Config) -> TestRunner {
+    let mut rng = SimRng::new(seed);
+    let bytes: Vec<u8> = (0..4).flat_map(|_| rng.next_u64().to_le_bytes()).collect();
+    TestRunner::new_with_rng(config, TestRng::from_seed(RngAlgorithm::ChaCha, &bytes))
+}
+
+/// Applies stimulus sequences to a Simulation.
+#[derive(Debug, Clone, Default)]
+pub struct Driver {
+    /// Ids of the Wires which may be forced, in the order they are indexed by Actions.
+    wires: Vec<WireId>,
+    /// The host of an I2C master performing transactions, and the simulation time within which each must finish.
+    i2c: Option<(I2cHost, u64)>,
+}
+
+impl Driver {
+    /// Create a new Driver.
+    ///
+    /// # Parameters
+    ///
+    /// - `wires`: Ids of the Wires which may be forced, in the order they are indexed by Actions.
+    pub fn new(wires: &[WireId]) -> Self {
+        Self {
+            wires: wires.to_vec(),
+            i2c: None,
+        }
+    }
+
+    /// Perform I2C transactions through an I2C master.
+    ///
+    /// # Parameters
+    ///
+    /// - `host`: The host of the master.
+    /// - `timeout`: Simulation time within which each transaction must finish.
+    pub fn with_i2c(mut self, host: I2cHost, timeout: u64) -> Self {
+        self.i2c = Some((host, timeout));
+        self
+    }
+
+    /// Apply a sequence of Actions to a Simulation, stepping it as they require.
+    ///
+    /// Returns the outcomes of the I2C transactions performed, in order.  Stepping stops early if the Simulation
+    /// finishes.
+    ///
+    /// # Parameters
+    ///
+    /// - `sim`: The Simulation.
+    /// - `actions`: The sequence of Actions.
+    pub fn apply(
+        &self,
+        sim: &mut Simulation,
+        actions: &[Action],
+    ) -> Result<Vec<I2cOutcome>, String> {
+        let mut outcomes = Vec::new();
+        for action in actions {
+            match action {
+                Action::Drive(wire, high) => {
+                    let pull = if *high { WirePull::Up } else { WirePull::Down };
+                    sim.force_wire(self.wire(*wire)?, Some(pull))?;
+                }
+                Action::Release(wire) => sim.force_wire(self.wire(*wire)?, None)?,
+                Action::I2c(transaction) => {
+                    let (host, timeout) = self
+                        .i2c
+                        .as_ref()
+                        .ok_or("No I2C master to perform the transaction")?;
+                    host.queue(transaction.clone());
+                    let deadline = sim.time().saturating_add(*timeout);
+                    loop {
+                        let finished = host.take_outcomes();
+                        if !finished.is_empty() {
+                            outcomes.extend(finished);
+                            break;
+                        }
+                        if sim.time() >= deadline {
+                            return Err(format!(
+                                "I2C transaction {transaction:?} did not finish within {timeout}"
+                            ));
+                        }
+                        if step(sim)? == SimResult::Finished {
+                            return Ok(outcomes);
+                        }
+                    }
+                }
+                Action::Idle(duration) => {
+                    let end = sim.time().saturating_add(*duration);
+                    while sim.time() < end {
+                        if step(sim)? == SimResult::Finished {
+                            return Ok(outcomes);
+                        }
+                    }
+                }
+            }
+        }
+        Ok(outcomes)
+    }
+
+    /// Obtain the Id of a Wire which may be forced.
+    ///
+    /// # Parameters
+    ///
+    /// - `index`: Index of the Wire.
+    fn wire(&self, index: usize) -> Result<WireId, String> {
+        self.wires
+            .get(index)
+            .copied()
+            .ok_or_else(|| format!("No driven wire at index {index}"))
+    }
+}
+
+/// Step a Simulation.
+///
+/// # Parameters
+///
+/// - `sim`: The Simulation.
+fn step(sim: &mut Simulation) -> Result<SimResult, String> {
+    sim.step().map_err(|error| error.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::strategy::ValueTree;
+    use proptest::test_runner::TestError;
+    use rvfs_sim_core::element::i2c::{I2cMaster, I2cSlave, RegisterFile};
+    use rvfs_sim_core::element::Element;
+    use rvfs_sim_core::time::SimDuration;
+    use rvfs_sim_core::wire::Wire;
+
+    /// Build a Simulation of an I2C bus with a register slave at address 0x50, returning it and a Driver of its master.
+    fn bus() -> (Simulation, Driver) {
+        let mut sim = Simulation::new(SimDuration::from_ticks(10));
+        let wires = [
+            sim.add_wire(Wire::new("SCL", WirePull::Up)).unwrap(),
+            sim.add_wire(Wire::new("SDA", WirePull::Up)).unwrap(),
+        ];
+        let master = I2cMaster::new("M1", 80).unwrap();
+        let host = master.host();
+        let slave = I2cSlave::new("U1", 0x50, 0, Box::new(RegisterFile::new(4))).unwrap();
+        let elements: [Box<dyn Element>; 2] = [Box::new(master), Box::new(slave)];
+        for element in elements {
+            let id = sim.add_element(element).unwrap();
+            for (name, wire) in ["SCL", "SDA"].into_iter().zip(wires) {
+                sim.connect_input(wire, sim.input_pin(id, name).unwrap())
+                    .unwrap();
+                sim.connect_output(sim.output_pin(id, name).unwrap(), wire)
+                    .unwrap();
+            }
+        }
+        (sim, Driver::new(&wires).with_i2c(host, 10_000))
+    }
+
+    #[test]
+    fn stimulus_driver() {
+        // GIVEN an I2C bus
+        let (mut sim, driver) = bus();
+        // WHEN a sequence holds the bus, then releases it and performs transactions
+        let outcomes = driver
+            .apply(
+                &mut sim,
+                &[
+                    Action::Drive(1, false),
+                    Action::Idle(50),
+                    Action::Release(1),
+                    Action::I2c("50 w 01 a5".parse().unwrap()),
+                    Action::I2c("50 w 01 r 1".parse().unwrap()),
+                ],
+            )
+            .unwrap();
+        // THEN the idle gap passes, and the transactions finish in order
+        assert_eq!(
+            vec![
+                I2cOutcome::Complete(vec![]),
+                I2cOutcome::Complete(vec![0xa5])
+            ],
+            outcomes
+        );
+        // AND THEN Actions which cannot be applied are rejected
+        assert_eq!(
+            Err("No driven wire at index 2".to_string()),
+            driver.apply(&mut sim, &[Action::Release(2)])
+        );
+        assert_eq!(
+            Err("No I2C master to perform the transaction".to_string()),
+            Driver::new(&[]).apply(&mut sim, &[Action::I2c("50".parse().unwrap())])
+        );
+    }
+    #[test]
+    fn stimulus_seeded() {
+        // GIVEN runners seeded with two seeds
+        let strategy = sequence(levels(4), 1_000, 4..8);
+        let generate = |seed| {
+            let mut runner = runner(seed, Config::default());
+            (0..8)
+                .map(|_| strategy.new_tree(&mut runner).unwrap().current())
+                .collect::<Vec<_>>()
+        };
+        // WHEN sequences are generated
+        // THEN the same seed always gives the same sequences, and another seed gives others
+        assert_eq!(generate(1), generate(1));
+        assert_ne!(generate(1), generate(2));
+    }
+    #[test]
+    fn stimulus_sequence() {
+        // GIVEN a strategy generating I2C transactions with idle gaps
+        let strategy = sequence(i2c(&[0x50, 0x51], 2, 2), 100, 1..5);
+        let mut runner = TestRunner::deterministic();
+        // WHEN sequences are generated
+        // THEN each is valid, and every Action other than a gap is a transaction
+        for _ in 0..32 {
+            let actions = strategy.new_tree(&mut runner).unwrap().current();
+            assert!(!actions.is_empty());
+            for action in actions {
+                match action {
+                    Action::I2c(transaction) => {
+                        assert!([0x50, 0x51].contains(&transaction.address));
+                        assert!(transaction.write.len() <= 2 && transaction.read <= 2);
+                    }
+                    Action::Idle(gap) => assert!((1..=100).contains(&gap)),
+                    action => panic!("unexpected action {action:?}"),
+                }
+            }
+        }
+    }
+    #[test]
+    fn stimulus_slave_acknowledges() {
+        // GIVEN an I2C bus, and random transactions to present and absent slaves while the bus is disturbed
+        let actions = sequence(prop_oneof![i2c(&[0x50, 0x51], 3, 2), levels(2)], 200, 1..6);
+        let mut runner = TestRunner::new(Config {
+            cases: 16,
+            failure_persistence: None,
+            ..Config::default()
+        });
+        // WHEN the transactions are performed
+        // THEN on an undisturbed bus every transaction finishes, and only those to the present slave are acknowledged
+        runner
+            .run(&actions, |actions| {
+                let (mut sim, driver) = bus();
+                let undisturbed = actions.iter().all(|a| !matches!(a, Action::Drive(..)));
+                let outcomes = match driver.apply(&mut sim, &actions) {
+                    Ok(outcomes) if undisturbed => outcomes,
+                    Ok(_) => return Ok(()),
+                    Err(error) => {
+                        prop_assert!(!undisturbed, "{}", error);
+                        return Ok(());
+                    }
+                };
+                let addresses = actions.iter().filter_map(|action| match action {
+                    Action::I2c(transaction) => Some(transaction.address),
+                    _ => None,
+                });
+                for (address, outcome) in addresses.zip(&outcomes) {
+                    prop_assert_eq!(address == 0x50, *outcome != I2cOutcome::Nack);
+                }
+                Ok(())
+            })
+            .unwrap();
+    }
+    #[test]
+    fn stimulus_shrinking() {
+        // GIVEN a property which fails whenever more than one byte is read
+        let actions = sequence(i2c(&[0x51, 0x50], 3, 3), 200, 1..6);
+        let mut runner = TestRunner::new(Config {
+            failure_persistence: None,
+            ..Config::default()
+        });
+        // WHEN it is checked against random transactions
+        let result = runner.run(&actions, |actions| {
+            let (mut sim, driver) = bus();
+            for outcome in driver.apply(&mut sim, &actions).unwrap() {
+                if let I2cOutcome::Complete(bytes) = outcome {
+                    prop_assert!(bytes.len() <= 1);
+                }
+            }
+            Ok(())
+        });
+        // THEN the failing sequence is shrunk to the simplest which still fails
+        match result {
+            Err(TestError::Fail(_, actions)) => assert_eq!(
+                vec![Action::I2c(I2cTransaction {
+                    address: 0x50,
+                    write: vec![],
+                    read: 2
+                })],
+                actions
+            ),
+            result => panic!("unexpected result {result:?}"),
+        }
+    }
+}
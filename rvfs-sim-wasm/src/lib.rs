@@ -0,0 +1,213 @@
+//! WebAssembly bindings for the RVFS simulator, so circuits can be simulated and visualized directly in a browser.
+//!
+//! Build with `wasm-pack build rvfs-sim-wasm --target web` and import the generated module:
+//!
+//! ```js
+//! import init, { Simulator } from "./pkg/rvfs_sim_wasm.js";
+//!
+//! await init();
+//! const sim = new Simulator(10);
+//! const clk = sim.addWire("CLK", "down");
+//! const clock = sim.addElement("clock", "X1", ["period=100"]);
+//! sim.connect(clock, "CLK", clk);
+//! sim.step(20);
+//! console.log(sim.time(), sim.wireLevels());
+//! ```
+//!
+//! Methods which can fail throw the error message as a string.
+
+use rvfs_sim_core::element::{Parameters, Registry};
+use rvfs_sim_core::sim::{SimResult, Simulation};
+use rvfs_sim_core::time::SimDuration;
+use rvfs_sim_core::wire::{Wire, WirePull};
+use rvfs_sim_core::{ComponentId, ElementId, Id, WireId};
+use wasm_bindgen::prelude::*;
+
+/// A Simulation built and driven from JavaScript, with Elements instantiated by kind from the standard registry.
+///
+/// Wires and Elements are referred to by the numeric Ids returned when they are added.
+#[wasm_bindgen]
+pub struct Simulator {
+    /// The Simulation.
+    sim: Simulation,
+    /// The registry from which Elements are instantiated.
+    registry: Registry,
+}
+
+#[wasm_bindgen]
+impl Simulator {
+    /// Create a new, empty Simulator.
+    ///
+    /// # Parameters
+    ///
+    /// - `interval`: Simulation time step size, which must be non-zero.
+    #[wasm_bindgen(constructor)]
+    pub fn new(interval: u32) -> Result<Simulator, String> {
+        if interval == 0 {
+            return Err("Simulation interval must be non-zero".to_string());
+        }
+
+        Ok(Self {
+            sim: Simulation::new(SimDuration::from_ticks(u64::from(interval))),
+            registry: Registry::standard(),
+        })
+    }
+
+    /// Obtain the present simulation time.
+    pub fn time(&self) -> f64 {
+        self.sim.time() as f64
+    }
+
+    /// Obtain the names of every kind of Element which can be added.
+    pub fn kinds(&self) -> Vec<String> {
+        self.registry.kinds().map(ToString::to_string).collect()
+    }
+
+    /// Add a Wire, returning its Id.
+    ///
+    /// # Parameters
+    ///
+    /// - `name`: Name of the Wire.
+    /// - `pull`: Default pull of the Wire: `up`, `down` or `none`.
+    #[wasm_bindgen(js_name = addWire)]
+    pub fn add_wire(&mut self, name: &str, pull: &str) -> Result<Id, String> {
+        let pull = match pull {
+            "up" => WirePull::Up,
+            "down" => WirePull::Down,
+            "none" => WirePull::None,
+            _ => return Err(format!("Invalid pull \"{pull}\" for wire \"{name}\"")),
+        };
+        self.sim
+            .add_wire(Wire::new(name, pull))
+            .map(WireId::index)
+            .map_err(String::from)
+    }
+
+    /// Instantiate an Element of a registered kind and add it, returning its Id.
+    ///
+    /// # Parameters
+    ///
+    /// - `kind`: Kind of the Element, one of [kinds](Self::kinds).
+    /// - `name`: Name of the Element.
+    /// - `parameters`: Parameters of the Element, each of the form `name=value`.
+    #[wasm_bindgen(js_name = addElement)]
+    pub fn add_element(
+        &mut self,
+        kind: &str,
+        name: &str,
+        parameters: Vec<String>,
+    ) -> Result<Id, String> {
+        let parameters =
+            parameters
+                .iter()
+                .try_fold(Parameters::new(), |parameters, parameter| {
+                    let (key, value) = parameter.split_once('=').ok_or_else(|| {
+                        format!("Invalid parameter \"{parameter}\" for \"{name}\"")
+                    })?;
+                    Ok::<_, String>(parameters.with(key.trim(), value.trim()))
+                })?;
+        let element = self.registry.create(kind, name, &parameters)?;
+        self.sim
+            .add_element(element)
+            .map(ElementId::index)
+            .map_err(String::from)
+    }
+
+    /// Connect a pin of an Element to a Wire.
+    ///
+    /// # Parameters
+    ///
+    /// - `element`: Id of the Element.
+    /// - `pin`: Name of the input or output pin.
+    /// - `wire`: Id of the Wire.
+    pub fn connect(&mut self, element: Id, pin: &str, wire: Id) -> Result<(), String> {
+        let (element, wire) = (ElementId::from(element), WireId::from(wire));
+        if let Ok(input) = self.sim.input_pin(element, pin) {
+            self.sim.connect_input(wire, input).map_err(String::from)
+        } else {
+            let output = self.sim.output_pin(element, pin)?;
+            self.sim.connect_output(output, wire).map_err(String::from)
+        }
+    }
+
+    /// Advance the simulation by a number of time steps, stopping early if it finishes.  Returns whether it finished.
+    ///
+    /// # Parameters
+    ///
+    /// - `steps`: Number of steps to take.
+    pub fn step(&mut self, steps: u32) -> Result<bool, String> {
+        let result = self
+            .sim
+            .run_for(u64::from(steps))
+            .map_err(|error| error.to_string())?;
+        Ok(result == SimResult::Finished)
+    }
+
+    /// Obtain the names of every Wire, indexed by Id.
+    #[wasm_bindgen(js_name = wireNames)]
+    pub fn wire_names(&self) -> Result<Vec<String>, String> {
+        self.sim
+            .wires()
+            .map(|id| Ok(self.sim.wire(id)?.name().clone()))
+            .collect()
+    }
+
+    /// Obtain the levels of every Wire, indexed by Id, from 0 to 1.
+    #[wasm_bindgen(js_name = wireLevels)]
+    pub fn wire_levels(&self) -> Result<Vec<f32>, String> {
+        self.sim
+            .wires()
+            .map(|id| Ok(f32::from(self.sim.wire(id)?.measure())))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn simulator_build_and_step() {
+        // GIVEN a simulator with a clock driving an inverter
+        let mut sim = Simulator::new(10).unwrap();
+        let clk = sim.add_wire("CLK", "down").unwrap();
+        let inverted = sim.add_wire("/CLK", "none").unwrap();
+        let clock = sim
+            .add_element("clock", "X1", vec!["period = 40".to_string()])
+            .unwrap();
+        let inverter = sim.add_element("not", "U1", Vec::new()).unwrap();
+        sim.connect(clock, "CLK", clk).unwrap();
+        sim.connect(inverter, "I0", clk).unwrap();
+        sim.connect(inverter, "Y", inverted).unwrap();
+        // WHEN it is stepped
+        let finished = sim.step(2);
+        // THEN time advances, and the wires are visible by name and level
+        assert_eq!(Ok(false), finished);
+        assert_eq!(20.0, sim.time());
+        assert_eq!(
+            Ok(vec!["CLK".to_string(), "/CLK".to_string()]),
+            sim.wire_names()
+        );
+        let levels = sim.wire_levels().unwrap();
+        assert_eq!(vec![1.0, 0.0], levels);
+    }
+    #[test]
+    fn simulator_errors() {
+        // GIVEN a simulator, and a zero interval
+        let mut sim = Simulator::new(10).unwrap();
+        // WHEN invalid requests are made
+        // THEN they are rejected with messages
+        assert!(Simulator::new(0).is_err());
+        assert_eq!(
+            Err("Invalid pull \"sideways\" for wire \"A\"".to_string()),
+            sim.add_wire("A", "sideways")
+        );
+        assert_eq!(
+            Err("Invalid parameter \"inputs\" for \"U1\"".to_string()),
+            sim.add_element("nand", "U1", vec!["inputs".to_string()])
+        );
+        assert!(sim.add_element("flux_capacitor", "U2", Vec::new()).is_err());
+        assert!(sim.connect(0, "A", 0).is_err());
+        assert!(sim.kinds().contains(&"nand".to_string()));
+    }
+}
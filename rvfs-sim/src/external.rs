@@ -0,0 +1,216 @@
+//! Discovery and invocation of external `rvfs-sim-<name>` subcommands found on the PATH.
+
+use std::env;
+use std::ffi::OsStr;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, ExitCode};
+
+/// Prefix of executable names which are treated as external subcommands.
+const PREFIX: &str = "rvfs-sim-";
+
+/// Environment variable through which the effective configuration path is passed to external subcommands.
+pub const CONFIG_ENV: &str = "RVFS_SIM_CONFIG";
+/// Environment variable through which the control socket address is passed to external subcommands.
+pub const CONTROL_SOCKET_ENV: &str = "RVFS_SIM_CONTROL_SOCKET";
+/// Environment variable through which the random number seed is passed to external subcommands.
+pub const SEED_ENV: &str = "RVFS_SIM_SEED";
+
+/// Context passed from the parent executable to an external subcommand.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Context {
+    /// Effective configuration file path, if any.
+    pub config: Option<PathBuf>,
+    /// Address of the simulation control socket, if any.
+    pub control_socket: Option<String>,
+    /// Seed of the simulation's random number generator, if any, so that a randomised run can be reproduced.
+    pub seed: Option<u64>,
+}
+
+/// Locate the executable implementing an external subcommand.
+///
+/// Names containing a path separator are never found, so that a subcommand cannot reach outside the search path.
+///
+/// # Parameters
+///
+/// - `name`: Subcommand name, without the `rvfs-sim-` prefix.
+/// - `path`: Search path, in the same format as the `PATH` environment variable.
+pub fn find(name: &str, path: &OsStr) -> Option<PathBuf> {
+    if name.chars().any(std::path::is_separator) {
+        return None;
+    }
+    let file_name = format!("{PREFIX}{name}{}", env::consts::EXE_SUFFIX);
+    env::split_paths(path)
+        .map(|dir| dir.join(&file_name))
+        .find(|candidate| is_executable(candidate))
+}
+
+/// List the names of all external subcommands available on a search path.
+///
+/// # Parameters
+///
+/// - `path`: Search path, in the same format as the `PATH` environment variable.
+pub fn list(path: &OsStr) -> Vec<String> {
+    let mut names: Vec<String> = env::split_paths(path)
+        .filter_map(|dir| fs::read_dir(dir).ok())
+        .flatten()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| is_executable(&entry.path()))
+        .filter_map(|entry| {
+            let file_name = entry.file_name().into_string().ok()?;
+            let name = file_name.strip_prefix(PREFIX)?;
+            let name = name.strip_suffix(env::consts::EXE_SUFFIX).unwrap_or(name);
+            (!name.is_empty()).then(|| name.to_string())
+        })
+        .collect();
+
+    names.sort();
+    names.dedup();
+    names
+}
+
+/// Run an external subcommand to completion, passing along the parent's context.
+///
+/// # Parameters
+///
+/// - `program`: Path to the subcommand executable.
+/// - `args`: Arguments to pass to the subcommand.
+/// - `context`: Context to expose to the subcommand through its environment.
+pub fn invoke(program: &Path, args: &[String], context: &Context) -> Result<ExitCode, String> {
+    let mut command = Command::new(program);
+    command.args(args);
+    if let Some(config) = &context.config {
+        command.env(CONFIG_ENV, config);
+    }
+    if let Some(address) = &context.control_socket {
+        command.env(CONTROL_SOCKET_ENV, address);
+    }
+    if let Some(seed) = context.seed {
+        command.env(SEED_ENV, seed.to_string());
+    }
+
+    let status = command
+        .status()
+        .map_err(|err| format!("Failed to run {}: {err}", program.display()))?;
+
+    Ok(exit_code(status.code()))
+}
+
+/// Convert the exit code of a subcommand to the parent's, treating a code which cannot be passed on as failure.
+///
+/// # Parameters
+///
+/// - `code`: Exit code of the subcommand, or None if it was terminated by a signal.
+fn exit_code(code: Option<i32>) -> ExitCode {
+    code.and_then(|code| u8::try_from(code).ok())
+        .map_or(ExitCode::FAILURE, ExitCode::from)
+}
+
+/// Determine whether a path refers to an executable file.
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+
+    fs::metadata(path).is_ok_and(|m| m.is_file() && m.permissions().mode() & 0o111 != 0)
+}
+
+/// Determine whether a path refers to an executable file.
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    fs::metadata(path).is_ok_and(|m| m.is_file())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Create an empty scratch directory unique to a test.
+    fn scratch_dir(test: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("rvfs-sim-external-{}-{test}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    /// Create an executable file in a directory.
+    fn touch_executable(dir: &Path, name: &str) {
+        let path = dir.join(format!("{name}{}", env::consts::EXE_SUFFIX));
+        fs::write(&path, "#!/bin/sh\n").unwrap();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+        }
+    }
+
+    #[test]
+    fn external_find() {
+        // GIVEN two directories on a search path, both containing the same subcommand
+        let first = scratch_dir("find-first");
+        let second = scratch_dir("find-second");
+        touch_executable(&first, "rvfs-sim-power");
+        touch_executable(&second, "rvfs-sim-power");
+        let path = env::join_paths([&first, &second]).unwrap();
+        // WHEN the subcommand is looked up
+        let found = find("power", &path);
+        // THEN the first match on the search path is returned
+        assert_eq!(
+            Some(first.join(format!("rvfs-sim-power{}", env::consts::EXE_SUFFIX))),
+            found
+        );
+    }
+    #[test]
+    fn external_find_missing() {
+        // GIVEN a search path without the requested subcommand
+        let dir = scratch_dir("find-missing");
+        touch_executable(&dir, "rvfs-sim-power");
+        let path = env::join_paths([&dir]).unwrap();
+        // WHEN a different subcommand is looked up
+        let found = find("timing", &path);
+        // THEN nothing is found
+        assert_eq!(None, found);
+    }
+    #[test]
+    fn external_find_path_separator() {
+        // GIVEN a search path whose directory has a subdirectory holding a subcommand
+        let dir = scratch_dir("find-separator");
+        fs::create_dir_all(dir.join("rvfs-sim-nested")).unwrap();
+        touch_executable(&dir.join("rvfs-sim-nested"), "power");
+        let path = env::join_paths([&dir]).unwrap();
+        // WHEN it is looked up through a name containing a path separator
+        let found = find(&format!("nested{}power", std::path::MAIN_SEPARATOR), &path);
+        // THEN nothing is found
+        assert_eq!(None, found);
+    }
+    #[test]
+    fn external_list() {
+        // GIVEN search path directories containing subcommands, duplicates and unrelated files
+        let first = scratch_dir("list-first");
+        let second = scratch_dir("list-second");
+        touch_executable(&first, "rvfs-sim-timing");
+        touch_executable(&first, "rvfs-sim-power");
+        touch_executable(&second, "rvfs-sim-power");
+        touch_executable(&second, "gtkwave");
+        fs::write(second.join("rvfs-sim-notes.txt"), "").unwrap();
+        let path = env::join_paths([&first, &second]).unwrap();
+        // WHEN the available subcommands are listed
+        let names = list(&path);
+        // THEN each executable subcommand is listed once, in sorted order
+        #[cfg(unix)]
+        assert_eq!(vec!["power".to_string(), "timing".to_string()], names);
+        #[cfg(not(unix))]
+        assert!(names.contains(&"power".to_string()));
+    }
+    #[test]
+    fn external_exit_code() {
+        // GIVEN exit codes inside and outside the range which can be passed on, and termination by a signal
+        // WHEN they are converted
+        // THEN codes in range are kept, and any other is a failure
+        assert_eq!(ExitCode::SUCCESS, exit_code(Some(0)));
+        assert_eq!(ExitCode::from(3), exit_code(Some(3)));
+        assert_eq!(ExitCode::from(255), exit_code(Some(255)));
+        assert_eq!(ExitCode::FAILURE, exit_code(Some(256)));
+        assert_eq!(ExitCode::FAILURE, exit_code(Some(-1)));
+        assert_eq!(ExitCode::FAILURE, exit_code(None));
+    }
+}
@@ -1,3 +1,308 @@
-fn main() {
-    println!("Hello, world!");
+//! Command line entry point for the RVFS simulator.
+
+mod external;
+mod repl;
+mod simulate;
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+/// Configuration file used when none is given explicitly.
+const DEFAULT_CONFIG: &str = "rvfs-sim.toml";
+
+/// Usage summary printed for `help`.
+const USAGE: &str = "\
+Usage: rvfs-sim [OPTIONS] <COMMAND> [ARGS]...
+
+Options:
+  --config <PATH>           TOML configuration file giving defaults, such as the seed
+  --control-socket <ADDR>   Address of the simulation control socket
+  --seed <N>                Seed of the random number generator, to reproduce a randomised run
+  -h, --help                Print this help
+
+Commands:
+  help                      Print this help
+  list                      List available commands
+  repl <NETLIST>            Load a circuit from a JSON netlist and explore it interactively
+  run [--metrics <ADDR>] <NETLIST> <STEPS> [STIMULUS]
+                            Load a circuit from a JSON netlist and simulate it for a number of steps,
+                            optionally driven by a stimulus file and serving Prometheus metrics
+
+Any other command <name> runs the `rvfs-sim-<name>` executable found on the PATH.";
+
+/// Options which precede the command name on the command line.
+#[derive(Debug, Default, PartialEq)]
+struct Options {
+    /// Context to pass along to external commands.
+    context: external::Context,
+    /// Command name, if one was given.
+    command: Option<String>,
+    /// Arguments following the command name.
+    args: Vec<String>,
+}
+
+impl Options {
+    /// Parse the command line arguments.
+    ///
+    /// # Parameters
+    ///
+    /// - `args`: Command line arguments, excluding the program name.
+    fn parse(args: impl IntoIterator<Item = String>) -> Result<Self, String> {
+        let mut options = Options::default();
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            let (flag, inline) = match arg.split_once('=') {
+                Some((flag, value)) if flag.starts_with("--") => {
+                    (flag.to_string(), Some(value.to_string()))
+                }
+                _ => (arg.clone(), None),
+            };
+            let mut value = || {
+                inline
+                    .clone()
+                    .or_else(|| args.next())
+                    .ok_or(format!("Missing value for {flag}"))
+            };
+
+            match flag.as_str() {
+                "--config" => options.context.config = Some(PathBuf::from(value()?)),
+                "--control-socket" => options.context.control_socket = Some(value()?),
+                "--seed" => options.context.seed = Some(parse_seed(&value()?)?),
+                "-h" | "--help" => options.command = Some("help".to_string()),
+                _ if flag.starts_with('-') => return Err(format!("Unknown option {arg}")),
+                _ => {
+                    options.command = Some(arg);
+                    options.args = args.collect();
+                    break;
+                }
+            }
+        }
+
+        Ok(options)
+    }
+
+    /// Fill in any context not given on the command line from the environment, then from the configuration file and
+    /// defaults.
+    fn resolve(mut self) -> Result<Self, String> {
+        if self.context.config.is_none() {
+            self.context.config = env::var_os(external::CONFIG_ENV)
+                .map(PathBuf::from)
+                .or_else(|| Some(PathBuf::from(DEFAULT_CONFIG)).filter(|p| p.is_file()));
+        }
+        if self.context.control_socket.is_none() {
+            self.context.control_socket = env::var(external::CONTROL_SOCKET_ENV).ok();
+        }
+        if self.context.seed.is_none() {
+            self.context.seed = env_seed(env::var(external::SEED_ENV).ok())?;
+        }
+        if let Some(config) = &self.context.config {
+            let seed = config_seed(config)?;
+            self.context.seed = self.context.seed.or(seed);
+        }
+        Ok(self)
+    }
+}
+
+/// Read the seed of the random number generator from a configuration file, if it gives one.
+///
+/// The file is TOML, with the seed given as a non-negative integer by a top-level `seed` key.
+///
+/// # Parameters
+///
+/// - `path`: Path to the configuration file.
+fn config_seed(path: &Path) -> Result<Option<u64>, String> {
+    let text = fs::read_to_string(path)
+        .map_err(|err| format!("Failed to read configuration {}: {err}", path.display()))?;
+    let config: toml::Table = text
+        .parse()
+        .map_err(|err| format!("Invalid configuration {}: {err}", path.display()))?;
+    match config.get("seed") {
+        None => Ok(None),
+        Some(toml::Value::Integer(seed)) if *seed >= 0 => Ok(Some(*seed as u64)),
+        Some(_) => Err(format!(
+            "{}: \"seed\" must be a non-negative integer",
+            path.display()
+        )),
+    }
+}
+
+/// Parse a random number seed.
+///
+/// # Parameters
+///
+/// - `text`: The seed, in decimal.
+fn parse_seed(text: &str) -> Result<u64, String> {
+    text.parse().map_err(|_| format!("Invalid seed {text}"))
+}
+
+/// Parse the seed given by the environment, if one is given.
+///
+/// # Parameters
+///
+/// - `value`: Value of the seed environment variable, if it is set.
+fn env_seed(value: Option<String>) -> Result<Option<u64>, String> {
+    value.map(|seed| parse_seed(&seed)).transpose()
+}
+
+fn main() -> ExitCode {
+    match Options::parse(env::args().skip(1))
+        .and_then(Options::resolve)
+        .and_then(run)
+    {
+        Ok(code) => code,
+        Err(err) => {
+            eprintln!("error: {err}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Dispatch the requested command.
+///
+/// # Parameters
+///
+/// - `options`: Parsed and resolved command line options.
+fn run(options: Options) -> Result<ExitCode, String> {
+    let path = env::var_os("PATH").unwrap_or_default();
+
+    match options.command.as_deref() {
+        None | Some("help") => {
+            println!("{USAGE}");
+            Ok(ExitCode::SUCCESS)
+        }
+        Some("list") => {
+            println!("Available commands:");
+            println!("    help");
+            println!("    list");
+            println!("    repl");
+            println!("    run");
+            for name in external::list(&path) {
+                println!("    {name}");
+            }
+            Ok(ExitCode::SUCCESS)
+        }
+        Some("repl") => repl::run(&options.args, &options.context),
+        Some("run") => simulate::run(&options.args, &options.context),
+        Some(name) => {
+            let program = external::find(name, &path).ok_or(format!("No such command: {name}"))?;
+            external::invoke(&program, &options.args, &options.context)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Convert string literals to owned command line arguments.
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|a| a.to_string()).collect()
+    }
+
+    #[test]
+    fn options_parse_external_command() {
+        // GIVEN a command line with options, a command, and command arguments
+        let line = args(&[
+            "--config",
+            "top.toml",
+            "--control-socket=127.0.0.1:7878",
+            "--seed",
+            "42",
+            "power",
+            "--top",
+            "5",
+        ]);
+        // WHEN the command line is parsed
+        let options = Options::parse(line).unwrap();
+        // THEN the options are captured and everything after the command is passed through untouched
+        assert_eq!(Some(PathBuf::from("top.toml")), options.context.config);
+        assert_eq!(
+            Some("127.0.0.1:7878".to_string()),
+            options.context.control_socket
+        );
+        assert_eq!(Some(42), options.context.seed);
+        assert_eq!(Some("power".to_string()), options.command);
+        assert_eq!(args(&["--top", "5"]), options.args);
+    }
+    #[test]
+    fn options_parse_missing_value() {
+        // GIVEN a command line with an option missing its value
+        let line = args(&["--config"]);
+        // WHEN the command line is parsed
+        let result = Options::parse(line);
+        // THEN parsing fails
+        assert!(result.is_err());
+    }
+    #[test]
+    fn options_parse_invalid_seed() {
+        // GIVEN a command line with a seed which is not a number
+        let line = args(&["--seed=lucky", "list"]);
+        // WHEN the command line is parsed
+        let result = Options::parse(line);
+        // THEN parsing fails
+        assert_eq!(Err("Invalid seed lucky".to_string()), result);
+    }
+    #[test]
+    fn options_env_seed() {
+        // GIVEN seed environment variables which are unset, a number, and not a number
+        // WHEN they are parsed
+        // THEN the seed is read, and the invalid one is reported as for the command line option
+        assert_eq!(Ok(None), env_seed(None));
+        assert_eq!(Ok(Some(42)), env_seed(Some("42".to_string())));
+        assert_eq!(
+            Err("Invalid seed lucky".to_string()),
+            env_seed(Some("lucky".to_string()))
+        );
+    }
+    #[test]
+    fn options_resolve_config_seed() {
+        // GIVEN configuration files giving a seed, giving none, and giving an invalid one
+        let dir = env::temp_dir();
+        let id = std::process::id();
+        let config = |name: &str, text: &str| {
+            let path = dir.join(format!("rvfs-sim-config-{name}-{id}.toml"));
+            fs::write(&path, text).unwrap();
+            path
+        };
+        let seeded = config("seeded", "# reproduce the failing run\nseed = 1234\n");
+        let unseeded = config("unseeded", "[trace]\nformat = \"vcd\"\n");
+        let invalid = config("invalid", "seed = \"lucky\"\n");
+        // WHEN they are read, and the seeded one is resolved with and without a seed on the command line
+        let resolve = |line: &[&str]| Options::parse(args(line)).unwrap().resolve();
+        let path = seeded.display().to_string();
+        let from_config = resolve(&["--config", &path, "list"]);
+        let from_line = resolve(&["--config", &path, "--seed", "7", "list"]);
+        // THEN the seeds are read or reported as invalid, and one on the command line overrides the configuration file
+        assert_eq!(Ok(Some(1234)), config_seed(&seeded));
+        assert_eq!(Ok(None), config_seed(&unseeded));
+        assert_eq!(
+            Err(format!(
+                "{}: \"seed\" must be a non-negative integer",
+                invalid.display()
+            )),
+            config_seed(&invalid)
+        );
+        assert!(config_seed(Path::new("/nonexistent.toml"))
+            .unwrap_err()
+            .starts_with("Failed to read configuration /nonexistent.toml"));
+        if env::var_os(external::SEED_ENV).is_none() {
+            assert_eq!(Some(1234), from_config.unwrap().context.seed);
+        }
+        assert_eq!(Some(7), from_line.unwrap().context.seed);
+        for file in [seeded, unseeded, invalid] {
+            let _ = fs::remove_file(file);
+        }
+    }
+    #[test]
+    fn options_parse_unknown_option() {
+        // GIVEN a command line with an unrecognized option
+        let line = args(&["--frobnicate", "list"]);
+        // WHEN the command line is parsed
+        let result = Options::parse(line);
+        // THEN parsing fails
+        assert!(result.is_err());
+    }
 }
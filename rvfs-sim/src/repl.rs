@@ -0,0 +1,348 @@
+//! The built-in `repl` command, which loads a circuit from a netlist file and lets it be explored interactively, one
+//! command per line.
+
+use crate::external::Context;
+use rvfs_sim_core::netlist;
+use rvfs_sim_core::sim::{
+    SimResult, Simulation, DEFAULT_CHECKPOINT_INTERVAL, DEFAULT_CHECKPOINT_LIMIT,
+};
+use rvfs_sim_core::stimulus;
+use rvfs_sim_core::watch::{Watch, WatchId};
+use rvfs_sim_core::WireId;
+use std::collections::BTreeMap;
+use std::io::{self, BufRead, Write};
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+/// Usage summary of the `repl` command, printed when its arguments are invalid.
+const USAGE: &str = "Usage: rvfs-sim repl <NETLIST>";
+
+/// Summary of the interactive commands, printed for `help`.
+const HELP: &str = "\
+Commands:
+  step                      Advance the simulation by one step
+  run <STEPS>               Advance the simulation by a number of steps, stopping early at a watched change
+  back <STEPS>              Rewind the simulation by a number of steps
+  peek <WIRE>               Print the logic value and level of a Wire
+  poke <WIRE> <VALUE>       Force a Wire high, low or z, or release it back to its drivers
+  watch <WIRE>              Stop runs when the logic value of a Wire changes
+  unwatch <WIRE>            Stop watching a Wire
+  time                      Print the simulation time
+  help                      Print this help
+  quit                      Leave the simulator
+
+A Wire is given by its name, or as <ELEMENT>.<PIN> for the Wire connected to a pin.";
+
+/// Prompt printed before each command is read.
+const PROMPT: &str = "> ";
+
+/// Load a netlist and read commands exploring it from standard input until `quit` or the end of input.
+///
+/// # Parameters
+///
+/// - `args`: Arguments following the command name: the netlist path.
+/// - `context`: Context from the command line, from which the seed is taken.
+pub fn run(args: &[String], context: &Context) -> Result<ExitCode, String> {
+    let [path] = args else {
+        return Err(USAGE.to_string());
+    };
+    let mut sim = netlist::load(&PathBuf::from(path))?.into_simulation();
+    if let Some(seed) = context.seed {
+        sim.set_seed(seed);
+    }
+
+    let mut repl = Repl::new(sim);
+    repl.interact(io::stdin().lock(), io::stdout().lock())?;
+    repl.sim.finish_elements().map_err(|err| err.to_string())?;
+
+    Ok(ExitCode::SUCCESS)
+}
+
+/// An interactive session exploring a Simulation.
+struct Repl {
+    /// The Simulation being explored.
+    sim: Simulation,
+    /// Breakpoint set for each watched Wire.
+    watches: BTreeMap<WireId, WatchId>,
+}
+
+impl Repl {
+    /// Create a new session, taking checkpoints of the Simulation so that it can be stepped back.
+    ///
+    /// # Parameters
+    ///
+    /// - `sim`: The Simulation to explore.
+    fn new(mut sim: Simulation) -> Self {
+        sim.set_checkpoints(DEFAULT_CHECKPOINT_INTERVAL, DEFAULT_CHECKPOINT_LIMIT);
+        Self {
+            sim,
+            watches: BTreeMap::new(),
+        }
+    }
+
+    /// Read and execute commands until `quit` or the end of input, printing a reply to each.
+    ///
+    /// A command which fails is reported without ending the session.
+    ///
+    /// # Parameters
+    ///
+    /// - `input`: Source of the commands, one per line.
+    /// - `output`: Destination of the prompts and replies.
+    fn interact(&mut self, input: impl BufRead, mut output: impl Write) -> Result<(), String> {
+        let mut lines = input.lines();
+        loop {
+            write!(output, "{PROMPT}")
+                .and_then(|_| output.flush())
+                .map_err(|err| err.to_string())?;
+            let Some(line) = lines.next() else {
+                return writeln!(output).map_err(|err| err.to_string());
+            };
+            let reply = match self.execute(&line.map_err(|err| err.to_string())?) {
+                Ok(Some(reply)) => reply,
+                Ok(None) => return Ok(()),
+                Err(error) => format!("error: {error}"),
+            };
+            if !reply.is_empty() {
+                writeln!(output, "{reply}").map_err(|err| err.to_string())?;
+            }
+        }
+    }
+
+    /// Execute a single command.
+    ///
+    /// Returns the reply to print, which may be empty, or `None` if the session is to end.
+    ///
+    /// # Parameters
+    ///
+    /// - `line`: The command and its arguments, separated by whitespace.
+    fn execute(&mut self, line: &str) -> Result<Option<String>, String> {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let reply = match words.as_slice() {
+            [] => String::new(),
+            ["step"] => self.advance(1)?,
+            ["run", steps] => {
+                let steps = steps
+                    .parse()
+                    .map_err(|_| format!("Invalid step count {steps}"))?;
+                self.advance(steps)?
+            }
+            ["back", steps] => {
+                let steps = steps
+                    .parse()
+                    .map_err(|_| format!("Invalid step count {steps}"))?;
+                self.sim.step_back(steps).map_err(|err| err.to_string())?;
+                format!("Time {}", self.sim.time())
+            }
+            ["peek", wire] => {
+                let id = self.sim.wire_by_target(wire)?;
+                let level = f32::from(self.sim.wire(id)?.measure());
+                format!("{wire} = {} ({level:.3})", self.sim.logic(id)?.symbol())
+            }
+            ["poke", wire, value] => {
+                let pull = stimulus::parse_force(value)?;
+                let id = self.sim.wire_by_target(wire)?;
+                self.sim.force_wire(id, pull)?;
+                String::new()
+            }
+            ["watch", wire] => {
+                let id = self.sim.wire_by_target(wire)?;
+                if !self.watches.contains_key(&id) {
+                    let watch = self.sim.break_when(id, Watch::LogicChanges)?;
+                    self.watches.insert(id, watch);
+                }
+                String::new()
+            }
+            ["unwatch", wire] => {
+                let id = self.sim.wire_by_target(wire)?;
+                let watch = self
+                    .watches
+                    .remove(&id)
+                    .ok_or(format!("{wire} is not watched"))?;
+                self.sim.remove_breakpoint(watch)?;
+                String::new()
+            }
+            ["time"] => format!("Time {}", self.sim.time()),
+            ["help"] => HELP.to_string(),
+            ["quit" | "exit"] => return Ok(None),
+            [command, ..] => {
+                return Err(format!("Unknown command or arguments: {command}, try help"))
+            }
+        };
+
+        Ok(Some(reply))
+    }
+
+    /// Advance the simulation by a number of steps, stopping early if a watched Wire changes or the simulation
+    /// finishes, and describe where it stopped.
+    ///
+    /// # Parameters
+    ///
+    /// - `steps`: Number of steps to take.
+    fn advance(&mut self, steps: u64) -> Result<String, String> {
+        let result = self.sim.run_for(steps).map_err(|err| err.to_string())?;
+        let time = self.sim.time();
+        Ok(match result {
+            SimResult::Continuing => format!("Time {time}"),
+            SimResult::Finished => format!("Simulation finished at time {time}"),
+            SimResult::Limit(limit) => format!("Time {time}: {limit} reached"),
+            SimResult::Breakpoint(watch) => {
+                let (id, _) = self
+                    .watches
+                    .iter()
+                    .find(|(_, w)| **w == watch)
+                    .ok_or(format!("Stopped at unknown breakpoint {watch}"))?;
+                let wire = self.sim.wire(*id)?;
+                format!(
+                    "Time {time}: {} changed to {}",
+                    wire.name(),
+                    self.sim.logic(*id)?.symbol()
+                )
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::fs;
+
+    /// Load a session exploring a clock driving an inverter.
+    ///
+    /// # Parameters
+    ///
+    /// - `name`: Name distinguishing the netlist file of the calling test.
+    fn session(name: &str) -> Repl {
+        let path =
+            env::temp_dir().join(format!("rvfs-sim-repl-{name}-{}.json", std::process::id()));
+        fs::write(
+            &path,
+            r#"{
+                "interval": 10,
+                "wires": [{ "name": "CLK", "pull": "down" }, { "name": "/CLK", "pull": "down" }],
+                "elements": [
+                    { "name": "X1", "kind": "clock", "parameters": { "period": 1000 }, "pins": { "CLK": "CLK" } },
+                    { "name": "U1", "kind": "not", "pins": { "I0": "CLK", "Y": "/CLK" } }
+                ]
+            }"#,
+        )
+        .unwrap();
+        let sim = netlist::load(&path).unwrap().into_simulation();
+        let _ = fs::remove_file(path);
+        Repl::new(sim)
+    }
+
+    #[test]
+    fn repl_peek_poke() {
+        // GIVEN a session exploring a clock driving an inverter
+        let mut repl = session("peek");
+        // WHEN the clock is run, then forced low through the inverter's input pin
+        let mut replies = Vec::new();
+        for line in [
+            "run 3",
+            "peek CLK",
+            "peek /CLK",
+            "poke U1.I0 low",
+            "run 3",
+            "peek /CLK",
+        ] {
+            replies.push(repl.execute(line));
+        }
+        // THEN the Wires are read as the clock and inverter drive them, and by the force
+        assert_eq!(
+            vec![
+                Ok(Some("Time 30".to_string())),
+                Ok(Some("CLK = 1 (1.000)".to_string())),
+                Ok(Some("/CLK = 0 (0.000)".to_string())),
+                Ok(Some(String::new())),
+                Ok(Some("Time 60".to_string())),
+                Ok(Some("/CLK = 1 (1.000)".to_string())),
+            ],
+            replies
+        );
+    }
+    #[test]
+    fn repl_watch() {
+        // GIVEN a session exploring a clock, with its Wire watched
+        let mut repl = session("watch");
+        repl.execute("watch CLK").unwrap();
+        // WHEN it is run for longer than half a clock period, twice, then again once no longer watched
+        let rising = repl.execute("run 100");
+        let falling = repl.execute("run 100");
+        repl.execute("unwatch X1.CLK").unwrap();
+        let finished = repl.execute("run 100");
+        // THEN the first two runs stop when the clock changes, and the last runs its full length
+        assert_eq!(Ok(Some("Time 10: CLK changed to 1".to_string())), rising);
+        assert_eq!(Ok(Some("Time 510: CLK changed to 0".to_string())), falling);
+        assert_eq!(Ok(Some("Time 1510".to_string())), finished);
+        assert!(repl.execute("unwatch CLK").is_err());
+    }
+    #[test]
+    fn repl_back() {
+        // GIVEN a session exploring a clock driving an inverter, run past a poke of the inverter's input
+        let mut repl = session("back");
+        for line in ["run 3", "poke U1.I0 low", "run 3"] {
+            repl.execute(line).unwrap();
+        }
+        // WHEN it is stepped back to after the poke, then to before it, and then too far
+        let mut replies = Vec::new();
+        for line in [
+            "back 2",
+            "peek CLK",
+            "back 2",
+            "peek CLK",
+            "back 5",
+            "back many",
+        ] {
+            replies.push(repl.execute(line));
+        }
+        // THEN the poked Wire reads as it did at each time, and the mistakes are refused with a reason
+        assert_eq!(Ok(Some("Time 40".to_string())), replies[0]);
+        assert_eq!(Ok(Some("CLK = 0 (0.000)".to_string())), replies[1]);
+        assert_eq!(Ok(Some("Time 20".to_string())), replies[2]);
+        assert_eq!(Ok(Some("CLK = 1 (1.000)".to_string())), replies[3]);
+        assert!(replies[4].is_err());
+        assert_eq!(Err("Invalid step count many".to_string()), replies[5]);
+    }
+    #[test]
+    fn repl_errors() {
+        // GIVEN a session
+        let mut repl = session("errors");
+        // WHEN invalid commands are given
+        // THEN each is refused with a reason
+        assert_eq!(
+            Err("Unknown command or arguments: jump, try help".to_string()),
+            repl.execute("jump 5")
+        );
+        assert_eq!(
+            Err("Invalid step count many".to_string()),
+            repl.execute("run many")
+        );
+        assert_eq!(
+            Err("No wire named \"D0\"".to_string()),
+            repl.execute("peek D0")
+        );
+        assert_eq!(
+            Err("No wire or pin named \"U1.I2\"".to_string()),
+            repl.execute("peek U1.I2")
+        );
+        assert!(repl.execute("poke CLK maybe").is_err());
+    }
+    #[test]
+    fn repl_interact() {
+        // GIVEN a session and a script of commands with a mistake in it
+        let mut repl = session("interact");
+        let script = "step\nfly\n\nquit\nstep\n";
+        // WHEN the script is read
+        let mut output = Vec::new();
+        let result = repl.interact(script.as_bytes(), &mut output);
+        // THEN every command up to quit is answered in turn, the mistake without ending the session
+        assert_eq!(Ok(()), result);
+        assert_eq!(
+            "> Time 10\n> error: Unknown command or arguments: fly, try help\n> > ",
+            String::from_utf8(output).unwrap()
+        );
+        assert_eq!(10, repl.sim.time());
+    }
+}
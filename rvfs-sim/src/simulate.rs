@@ -0,0 +1,273 @@
+//! The built-in `run` command, which loads a circuit from a netlist file and simulates it, optionally driven by a
+//! [stimulus](rvfs_sim_core::stimulus) file.
+
+use crate::external::Context;
+use rvfs_sim_core::metrics;
+use rvfs_sim_core::netlist;
+use rvfs_sim_core::stimulus::Stimulus;
+use rvfs_sim_core::summary::RunSummary;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+/// Usage summary of the `run` command, printed when its arguments are invalid.
+const USAGE: &str = "Usage: rvfs-sim run [--metrics <ADDR>] <NETLIST> <STEPS> [STIMULUS]";
+
+/// Arguments of the `run` command.
+#[derive(Debug, PartialEq)]
+struct Args {
+    /// Path of the netlist file.
+    netlist: PathBuf,
+    /// Number of steps to simulate.
+    steps: u64,
+    /// Path of the stimulus file driving the circuit, if any.
+    stimulus: Option<PathBuf>,
+    /// Address to serve Prometheus metrics on while the simulation runs, if any.
+    metrics: Option<String>,
+}
+
+/// Parse the arguments of the `run` command.
+///
+/// # Parameters
+///
+/// - `args`: Arguments following the command name.
+fn parse(args: &[String]) -> Result<Args, String> {
+    let (metrics, args) = match args.split_first() {
+        Some((flag, rest)) if flag == "--metrics" => {
+            let (address, rest) = rest
+                .split_first()
+                .ok_or(format!("Missing value for --metrics\n{USAGE}"))?;
+            (Some(address.clone()), rest)
+        }
+        Some((flag, rest)) if flag.starts_with("--metrics=") => {
+            (Some(flag["--metrics=".len()..].to_string()), rest)
+        }
+        _ => (None, args),
+    };
+    match args {
+        [netlist, steps, stimulus @ ..] if stimulus.len() <= 1 => {
+            let steps = steps
+                .parse()
+                .map_err(|_| format!("Invalid step count {steps}\n{USAGE}"))?;
+            Ok(Args {
+                netlist: PathBuf::from(netlist),
+                steps,
+                stimulus: stimulus.first().map(PathBuf::from),
+                metrics,
+            })
+        }
+        _ => Err(USAGE.to_string()),
+    }
+}
+
+/// Load a netlist and simulate it for a number of steps, or until it finishes, then print a summary of the run and the
+/// outcome of any monitors the netlist declares, failing if any of them failed.
+///
+/// # Parameters
+///
+/// - `args`: Arguments following the command name: optionally `--metrics` and the address to serve Prometheus metrics
+///   on during the run, then the netlist path, the number of steps, and optionally the path of a stimulus file driving
+///   the circuit.
+/// - `context`: Context from the command line, from which the seed is taken.
+pub fn run(args: &[String], context: &Context) -> Result<ExitCode, String> {
+    let Args {
+        netlist: path,
+        steps,
+        stimulus,
+        metrics,
+    } = parse(args)?;
+    let circuit = netlist::load(&path)?;
+    let monitors = circuit.monitors().cloned();
+    let mut sim = circuit.into_simulation();
+    if let Some(seed) = context.seed {
+        sim.set_seed(seed);
+    }
+    if let Some(stimulus) = stimulus {
+        sim.add_stimulus(&Stimulus::load(&stimulus)?)?;
+    }
+    if let Some(address) = metrics {
+        let (address, _) = metrics::serve(sim.metrics(), address.as_str())
+            .map_err(|err| format!("Failed to serve metrics on {address}: {err}"))?;
+        println!("Serving metrics at http://{address}/metrics");
+    }
+
+    sim.run_for(steps).map_err(|err| err.to_string())?;
+    sim.finish_elements().map_err(|err| err.to_string())?;
+    sim.finish_tracers()?;
+    println!("{}", RunSummary::new(&sim.metrics()));
+
+    let Some(monitors) = monitors else {
+        return Ok(ExitCode::SUCCESS);
+    };
+    for status in monitors.statuses() {
+        println!("{status}");
+    }
+    Ok(if monitors.passed() {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::fs;
+
+    #[test]
+    fn simulate_parse() {
+        // GIVEN valid and invalid arguments
+        let args = |args: &[&str]| args.iter().map(|a| a.to_string()).collect::<Vec<_>>();
+        // WHEN they are parsed
+        // THEN the netlist, step count, stimulus and metrics address are extracted, or usage is given
+        let parsed = |stimulus: Option<&str>, metrics: Option<&str>| Args {
+            netlist: PathBuf::from("top.json"),
+            steps: 100,
+            stimulus: stimulus.map(PathBuf::from),
+            metrics: metrics.map(str::to_string),
+        };
+        assert_eq!(Ok(parsed(None, None)), parse(&args(&["top.json", "100"])));
+        assert_eq!(
+            Ok(parsed(Some("reset.csv"), None)),
+            parse(&args(&["top.json", "100", "reset.csv"]))
+        );
+        assert_eq!(
+            Ok(parsed(None, Some("127.0.0.1:9100"))),
+            parse(&args(&["--metrics", "127.0.0.1:9100", "top.json", "100"]))
+        );
+        assert_eq!(
+            Ok(parsed(Some("reset.csv"), Some(":9100"))),
+            parse(&args(&["--metrics=:9100", "top.json", "100", "reset.csv"]))
+        );
+        assert_eq!(
+            Err(format!("Missing value for --metrics\n{USAGE}")),
+            parse(&args(&["--metrics"]))
+        );
+        assert_eq!(Err(USAGE.to_string()), parse(&args(&["top.json"])));
+        assert_eq!(
+            Err(USAGE.to_string()),
+            parse(&args(&["top.json", "100", "reset.csv", "more"]))
+        );
+        assert_eq!(
+            Err(format!("Invalid step count many\n{USAGE}")),
+            parse(&args(&["top.json", "many"]))
+        );
+    }
+    #[test]
+    fn simulate_run() {
+        // GIVEN a netlist file of a clock
+        let path = env::temp_dir().join(format!("rvfs-sim-simulate-{}.json", std::process::id()));
+        fs::write(
+            &path,
+            r#"{
+                "interval": 10,
+                "wires": [{ "name": "CLK", "pull": "down" }],
+                "elements": [
+                    { "name": "X1", "kind": "clock", "parameters": { "period": 40 }, "pins": { "CLK": "CLK" } }
+                ]
+            }"#,
+        )
+        .unwrap();
+        let context = Context {
+            seed: Some(7),
+            ..Context::default()
+        };
+        // WHEN it is run, run serving metrics on free and invalid addresses, and a missing netlist is run
+        let netlist = path.display().to_string();
+        let result = run(&[netlist.clone(), "10".to_string()], &context);
+        let served = run(
+            &["--metrics", "127.0.0.1:0", &netlist, "10"].map(str::to_string),
+            &context,
+        );
+        let unserved = run(
+            &["--metrics", "nowhere", &netlist, "10"].map(str::to_string),
+            &context,
+        );
+        let missing = run(
+            &["/nonexistent.json".to_string(), "10".to_string()],
+            &context,
+        );
+        // THEN the runs succeed, and the invalid address and missing netlist are reported
+        assert_eq!(Ok(ExitCode::SUCCESS), result);
+        assert_eq!(Ok(ExitCode::SUCCESS), served);
+        assert!(unserved
+            .unwrap_err()
+            .starts_with("Failed to serve metrics on nowhere"));
+        assert!(missing
+            .unwrap_err()
+            .starts_with("Failed to read netlist /nonexistent.json"));
+        let _ = fs::remove_file(path);
+    }
+    #[test]
+    fn simulate_run_stimulus() {
+        // GIVEN a netlist file of a reset line, and stimulus files driving it and driving a missing Wire
+        let dir = env::temp_dir();
+        let id = std::process::id();
+        let path = dir.join(format!("rvfs-sim-stimulus-{id}.json"));
+        let reset = dir.join(format!("rvfs-sim-stimulus-{id}.csv"));
+        let missing = dir.join(format!("rvfs-sim-stimulus-missing-{id}.csv"));
+        fs::write(
+            &path,
+            r#"{ "interval": 10, "wires": [{ "name": "/RESET", "pull": "up" }], "elements": [] }"#,
+        )
+        .unwrap();
+        fs::write(
+            &reset,
+            "# reset pulse\n0, /RESET, low\n50, /RESET, release\n",
+        )
+        .unwrap();
+        fs::write(&missing, "0, CLK, high\n").unwrap();
+        let run_with = |stimulus: &PathBuf| {
+            let args = [&path, stimulus].map(|p| p.display().to_string());
+            run(
+                &[args[0].clone(), "10".to_string(), args[1].clone()],
+                &Context::default(),
+            )
+        };
+        // WHEN it is run with each
+        let result = run_with(&reset);
+        let failed = run_with(&missing);
+        // THEN the run with the reset pulse succeeds, and the missing Wire is reported
+        assert_eq!(Ok(ExitCode::SUCCESS), result);
+        assert_eq!(Err("No wire named \"CLK\"".to_string()), failed);
+        for file in [path, reset, missing] {
+            let _ = fs::remove_file(file);
+        }
+    }
+    #[test]
+    fn simulate_run_monitors() {
+        // GIVEN netlist files of a pulled-up reset line, monitored as always high and as always low
+        let dir = env::temp_dir();
+        let id = std::process::id();
+        let netlist = |level: &str| {
+            let path = dir.join(format!("rvfs-sim-monitors-{level}-{id}.json"));
+            fs::write(
+                &path,
+                format!(
+                    r#"{{
+                        "interval": 10,
+                        "wires": [{{ "name": "/RESET", "pull": "up" }}],
+                        "monitors": [{{ "name": "reset", "always": {{ "{level}": "/RESET" }} }}]
+                    }}"#
+                ),
+            )
+            .unwrap();
+            path
+        };
+        let (high, low) = (netlist("high"), netlist("low"));
+        // WHEN each is run
+        let run_file = |path: &PathBuf| {
+            run(
+                &[path.display().to_string(), "5".to_string()],
+                &Context::default(),
+            )
+        };
+        let (passed, failed) = (run_file(&high), run_file(&low));
+        // THEN the run succeeds while its monitor passes, and fails when it does not
+        assert_eq!(Ok(ExitCode::SUCCESS), passed);
+        assert_eq!(Ok(ExitCode::FAILURE), failed);
+        for file in [high, low] {
+            let _ = fs::remove_file(file);
+        }
+    }
+}